@@ -0,0 +1,31 @@
+// C API of rustberg, built with the capi feature:
+//   cargo build --release --features capi
+// which produces librustberg.so (librustberg.dylib on macOS) to link against
+#ifndef RUSTBERG_H
+#define RUSTBERG_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// Version of the API, bumped on incompatible changes of the functions or of the JSON listing
+uint32_t rustberg_capi_version(void);
+
+// Lists the data files of the current snapshot of a table along with their delete files, as
+// JSON. The table is located by a JSON catalog config:
+//   {"metadata-location": "file:/warehouse/db/table/metadata/00001-x.metadata.json"}
+// Returns null on error, setting *error (when error isn't null) to a message. Both the listing
+// and the error message must be freed with rustberg_free_string
+char *rustberg_snapshot_files(const char *config, const char *namespace_, const char *name,
+                              char **error);
+
+// Frees a string returned by rustberg. Null pointers are ignored
+void rustberg_free_string(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,37 @@
+// Runs the scan planning service
+//
+// Usage: rustberg-planner [--listen ADDR]
+//
+// Tables are resolved through the Hive Metastore catalog of the configuration, see
+// rustberg::config
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use rustberg::config::RustbergConfig;
+use rustberg::planner::{PlannerServer, PlannerService};
+
+const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1:50051";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let mut address = DEFAULT_LISTEN_ADDRESS.to_string();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--listen" => address = args.next().ok_or("--listen needs an address")?,
+            _ => return Err(format!("Unknown argument {}", arg).into()),
+        }
+    }
+    let address: SocketAddr = address.parse()?;
+
+    let service = Arc::new(PlannerService::new(Arc::new(
+        RustbergConfig::load()?.hms_catalog()?,
+    )));
+    println!("rustberg planner listening on {}", address);
+    tonic::transport::Server::builder()
+        .add_service(PlannerServer::new(service))
+        .serve(address)
+        .await?;
+    Ok(())
+}
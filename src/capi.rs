@@ -0,0 +1,248 @@
+// C API listing the files of the current snapshot of a table, for engines embedding rustberg in
+// their scanners (e.g. DuckDB extensions). Like the planner service, tables are located by their
+// current metadata file, given in a JSON catalog config:
+//   {"metadata-location": "file:/warehouse/db/table/metadata/00001-x.metadata.json"}
+// The listing is returned as a JSON string, see SnapshotFiles. The functions of this module are
+// the stable ABI declared in include/rustberg.h: strings returned by rustberg are owned by the
+// caller and freed with rustberg_free_string
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::ident::TableIdent;
+use crate::iceberg::io::LocalFileIO;
+use crate::iceberg::spec::manifest::{DataContentType, DataFile};
+use crate::iceberg::table::Table;
+
+// Version of the C API, bumped on incompatible changes of the functions or of the JSON listing
+pub const CAPI_VERSION: u32 = 1;
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+struct CatalogConfig {
+    metadata_location: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotFiles {
+    pub table_uuid: String,
+    pub metadata_location: String,
+    // None for tables without snapshots
+    pub snapshot_id: Option<i64>,
+    pub data_files: Vec<SnapshotDataFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotDataFile {
+    pub file_path: String,
+    pub file_format: String,
+    pub record_count: i64,
+    pub file_size_in_bytes: i64,
+    pub spec_id: i32,
+    // Partition path of the file, e.g. "category=books/ts_day=2022-10-08"
+    pub partition: String,
+    pub sequence_number: i64,
+    // Delete files to apply when reading the data file
+    pub delete_files: Vec<SnapshotDeleteFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotDeleteFile {
+    pub file_path: String,
+    pub file_format: String,
+    // "position-deletes" or "equality-deletes"
+    pub content: String,
+    pub record_count: i64,
+    pub file_size_in_bytes: i64,
+    // Field ids of the equality delete columns, empty for position deletes
+    pub equality_ids: Vec<i32>,
+    // Location of the blob of deletion vectors, stored in Puffin files
+    pub content_offset: Option<i64>,
+    pub content_size_in_bytes: Option<i64>,
+}
+
+// Lists the data files of the current snapshot of a table along with their delete files
+pub fn snapshot_files(config: &str, namespace: &str, name: &str) -> Result<SnapshotFiles> {
+    let config: CatalogConfig = serde_json::from_str(config)?;
+    let table = Table::load(
+        TableIdent::try_new(namespace.parse()?, name)?,
+        config.metadata_location,
+        Arc::new(LocalFileIO::new()),
+    )?;
+    let metadata = table.metadata();
+    let plan = table.scan().plan_files()?;
+    let data_files = plan
+        .tasks()
+        .iter()
+        .map(|task| SnapshotDataFile {
+            file_path: task.data_file.file_path.clone(),
+            file_format: task.data_file.file_format.to_string(),
+            record_count: task.data_file.record_count,
+            file_size_in_bytes: task.data_file.file_size_in_bytes,
+            spec_id: task.spec_id,
+            partition: metadata
+                .partition_spec_by_id(task.spec_id)
+                .map(|spec| spec.partition_path(&task.data_file.partition))
+                .unwrap_or_default(),
+            sequence_number: task.sequence_number,
+            delete_files: task.deletes.iter().map(delete_file).collect(),
+        })
+        .collect();
+    let fingerprint = plan.fingerprint();
+    Ok(SnapshotFiles {
+        table_uuid: fingerprint.table_uuid.to_string(),
+        metadata_location: fingerprint.metadata_location.clone(),
+        snapshot_id: fingerprint.snapshot_id,
+        data_files,
+    })
+}
+
+fn delete_file(delete: &DataFile) -> SnapshotDeleteFile {
+    SnapshotDeleteFile {
+        file_path: delete.file_path.clone(),
+        file_format: delete.file_format.to_string(),
+        content: match delete.content {
+            DataContentType::EqualityDeletes => "equality-deletes",
+            _ => "position-deletes",
+        }
+        .to_string(),
+        record_count: delete.record_count,
+        file_size_in_bytes: delete.file_size_in_bytes,
+        equality_ids: delete.equality_ids.clone().unwrap_or_default(),
+        content_offset: delete.content_offset,
+        content_size_in_bytes: delete.content_size_in_bytes,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rustberg_capi_version() -> u32 {
+    CAPI_VERSION
+}
+
+// Returns the JSON listing of the files of the current snapshot of the table, or null on error.
+// On error, and if `error` isn't null, *error is set to a message the caller must free
+//
+// # Safety
+// `config`, `namespace` and `name` must be valid NUL-terminated strings, and `error` either null
+// or a valid pointer to write to
+#[no_mangle]
+pub unsafe extern "C" fn rustberg_snapshot_files(
+    config: *const c_char,
+    namespace: *const c_char,
+    name: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let list = || -> Result<String> {
+        let config = c_str(config, "config")?;
+        let namespace = c_str(namespace, "namespace")?;
+        let name = c_str(name, "name")?;
+        Ok(serde_json::to_string(&snapshot_files(
+            config, namespace, name,
+        )?)?)
+    };
+    let message = match catch_unwind(AssertUnwindSafe(list)) {
+        Ok(Ok(json)) => return into_c_string(json),
+        Ok(Err(e)) => e.to_string(),
+        Err(_) => "rustberg panicked while listing the files".to_string(),
+    };
+    if !error.is_null() {
+        *error = into_c_string(message);
+    }
+    std::ptr::null_mut()
+}
+
+// Frees a string returned by rustberg. Null pointers are ignored
+//
+// # Safety
+// `s` must be null or a string returned by rustberg that wasn't freed yet
+#[no_mangle]
+pub unsafe extern "C" fn rustberg_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+unsafe fn c_str<'a>(s: *const c_char, argument: &str) -> Result<&'a str> {
+    if s.is_null() {
+        return Err(IcebergError::Invalid(format!("Null {}", argument)));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| IcebergError::Invalid(format!("{} is not valid UTF-8", argument)))
+}
+
+fn into_c_string(s: String) -> *mut c_char {
+    // Interior NULs can't be represented in C strings
+    CString::new(s.replace('\0', ""))
+        .unwrap_or_default()
+        .into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::test_utils::{append, create_table, ids_batch};
+
+    #[test]
+    fn test_snapshot_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = create_table(dir.path());
+        let table = append(&table, &ids_batch(&[1, 2, 3]));
+        let config = CString::new(format!(
+            r#"{{"metadata-location": "{}"}}"#,
+            table.metadata_location()
+        ))
+        .unwrap();
+        let namespace = CString::new("db").unwrap();
+        let name = CString::new("table").unwrap();
+
+        let mut error = std::ptr::null_mut();
+        let json = unsafe {
+            rustberg_snapshot_files(
+                config.as_ptr(),
+                namespace.as_ptr(),
+                name.as_ptr(),
+                &mut error,
+            )
+        };
+        assert!(error.is_null());
+        let listing: SnapshotFiles =
+            serde_json::from_str(unsafe { CStr::from_ptr(json) }.to_str().unwrap()).unwrap();
+        unsafe { rustberg_free_string(json) };
+        assert_eq!(table.metadata().current_snapshot_id, listing.snapshot_id);
+        assert_eq!(1, listing.data_files.len());
+        assert_eq!(3, listing.data_files[0].record_count);
+        assert!(listing.data_files[0].delete_files.is_empty());
+
+        let config = CString::new(r#"{"metadata-location": "file:/missing.json"}"#).unwrap();
+        let json = unsafe {
+            rustberg_snapshot_files(
+                config.as_ptr(),
+                namespace.as_ptr(),
+                name.as_ptr(),
+                &mut error,
+            )
+        };
+        assert!(json.is_null());
+        assert!(!error.is_null());
+        unsafe { rustberg_free_string(error) };
+        let json = unsafe {
+            rustberg_snapshot_files(
+                std::ptr::null(),
+                namespace.as_ptr(),
+                name.as_ptr(),
+                std::ptr::null_mut(),
+            )
+        };
+        assert!(json.is_null());
+        assert_eq!(CAPI_VERSION, rustberg_capi_version());
+    }
+}
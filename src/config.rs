@@ -0,0 +1,495 @@
+// Configuration of the clients rustberg creates: catalog URIs and authentication, warehouse,
+// FileIO options and timeouts. The configuration is a set of properties, read from a JSON file
+// of string values (e.g. {"catalog-uris": "thrift://metastore:9083", "read-timeout": "30s"}),
+// then from RUSTBERG_* environment variables, which override the file, then set by the with_*
+// methods. RustbergConfig::load reads the file named by RUSTBERG_CONFIG and the environment
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "hms")]
+use crate::hms::catalog::{metastore_error, HmsCatalog, HmsCatalogConfig, MetastoreClient};
+#[cfg(feature = "hms")]
+use crate::hms::retry::RetryingMetastore;
+#[cfg(feature = "hms")]
+use crate::hms::sasl::PlainMechanism;
+#[cfg(feature = "hms")]
+use crate::hms::{connect_with_timeouts, HmsAuth, HmsClient};
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::freshness::parse_duration_ms;
+use crate::iceberg::io::{FileIO, FileIOConfig};
+use crate::iceberg::retry::{RetryPolicy, RetryingFileIO};
+
+// Environment variable naming the configuration file
+pub const CONFIG_FILE_ENV: &str = "RUSTBERG_CONFIG";
+
+// Comma separated metastore URIs, tried in order, e.g. thrift://metastore:9083
+pub const CATALOG_URIS: &str = "catalog-uris";
+pub const WAREHOUSE: &str = "warehouse";
+// none, plain or kerberos
+pub const HMS_AUTH: &str = "hms.auth";
+pub const HMS_USERNAME: &str = "hms.username";
+pub const HMS_PASSWORD: &str = "hms.password";
+// First component of the Kerberos principal of the metastore, hive by default
+pub const HMS_KERBEROS_SERVICE: &str = "hms.kerberos-service";
+// Durations such as 10s or 500ms
+pub const CONNECT_TIMEOUT: &str = "connect-timeout";
+pub const READ_TIMEOUT: &str = "read-timeout";
+// How long metastore lookups are cached, not cached by default, see HmsCatalog::with_cache_ttl
+pub const HMS_CACHE_TTL: &str = "hms.cache-ttl";
+// Retries of FileIO reads and metastore lookups failing with transient errors, see RetryPolicy.
+// 1 attempt disables retries
+pub const RETRY_MAX_ATTEMPTS: &str = "retry.max-attempts";
+pub const RETRY_INITIAL_BACKOFF: &str = "retry.initial-backoff";
+pub const RETRY_MAX_BACKOFF: &str = "retry.max-backoff";
+// Prefix of FileIO options, e.g. file-io.s3.region. Only set from the file or with_property,
+// environment variable names can't tell the dots of option names from dashes
+pub const FILE_IO_PREFIX: &str = "file-io.";
+
+const PROPERTIES: &[&str] = &[
+    CATALOG_URIS,
+    WAREHOUSE,
+    HMS_AUTH,
+    HMS_USERNAME,
+    HMS_PASSWORD,
+    HMS_KERBEROS_SERVICE,
+    CONNECT_TIMEOUT,
+    READ_TIMEOUT,
+    HMS_CACHE_TTL,
+    RETRY_MAX_ATTEMPTS,
+    RETRY_INITIAL_BACKOFF,
+    RETRY_MAX_BACKOFF,
+];
+
+#[cfg(feature = "hms")]
+const DEFAULT_KERBEROS_SERVICE: &str = "hive";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HmsAuthKind {
+    #[default]
+    None,
+    Plain,
+    Kerberos,
+}
+
+#[derive(Clone, Default, PartialEq)]
+pub struct RustbergConfig {
+    pub catalog_uris: Vec<String>,
+    pub warehouse: Option<String>,
+    pub hms_auth: HmsAuthKind,
+    pub hms_username: Option<String>,
+    pub hms_password: Option<String>,
+    pub hms_kerberos_service: Option<String>,
+    pub connect_timeout: Option<Duration>,
+    pub read_timeout: Option<Duration>,
+    pub hms_cache_ttl: Option<Duration>,
+    pub retry_policy: RetryPolicy,
+    pub file_io_options: BTreeMap<String, String>,
+}
+
+// Keeps the password out of logs
+impl fmt::Debug for RustbergConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RustbergConfig")
+            .field("catalog_uris", &self.catalog_uris)
+            .field("warehouse", &self.warehouse)
+            .field("hms_auth", &self.hms_auth)
+            .field("hms_username", &self.hms_username)
+            .field("hms_password", &self.hms_password.as_ref().map(|_| "***"))
+            .field("hms_kerberos_service", &self.hms_kerberos_service)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("read_timeout", &self.read_timeout)
+            .field("hms_cache_ttl", &self.hms_cache_ttl)
+            .field("retry_policy", &self.retry_policy)
+            .field("file_io_options", &self.file_io_options)
+            .finish()
+    }
+}
+
+impl RustbergConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The file named by RUSTBERG_CONFIG if set, overridden by the environment
+    pub fn load() -> Result<Self> {
+        let config = match std::env::var_os(CONFIG_FILE_ENV) {
+            Some(path) => Self::from_file(Path::new(&path))?,
+            None => Self::new(),
+        };
+        config.with_env()
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let properties: BTreeMap<String, String> = serde_json::from_slice(&std::fs::read(path)?)
+            .map_err(|e| {
+                IcebergError::Invalid(format!(
+                    "Invalid configuration file {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+        properties
+            .iter()
+            .try_fold(Self::new(), |config, (key, value)| {
+                config.with_property(key, value)
+            })
+    }
+
+    // Overrides the properties set in RUSTBERG_* environment variables, e.g. RUSTBERG_HMS_AUTH
+    // for hms.auth
+    pub fn with_env(self) -> Result<Self> {
+        PROPERTIES
+            .iter()
+            .try_fold(self, |config, key| match std::env::var(env_variable(key)) {
+                Ok(value) => config.with_property(key, &value),
+                Err(_) => Ok(config),
+            })
+    }
+
+    pub fn with_property(mut self, key: &str, value: &str) -> Result<Self> {
+        let invalid = || IcebergError::Invalid(format!("Invalid {} {:?}", key, value));
+        match key {
+            CATALOG_URIS => {
+                self.catalog_uris = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|uri| !uri.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }
+            WAREHOUSE => self.warehouse = Some(value.to_string()),
+            HMS_AUTH => {
+                self.hms_auth = match value.trim().to_lowercase().as_str() {
+                    "none" => HmsAuthKind::None,
+                    "plain" => HmsAuthKind::Plain,
+                    "kerberos" => HmsAuthKind::Kerberos,
+                    _ => return Err(invalid()),
+                }
+            }
+            HMS_USERNAME => self.hms_username = Some(value.to_string()),
+            HMS_PASSWORD => self.hms_password = Some(value.to_string()),
+            HMS_KERBEROS_SERVICE => self.hms_kerberos_service = Some(value.to_string()),
+            RETRY_MAX_ATTEMPTS => {
+                self.retry_policy.max_attempts = value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|attempts| *attempts > 0)
+                    .ok_or_else(invalid)?
+            }
+            CONNECT_TIMEOUT
+            | READ_TIMEOUT
+            | HMS_CACHE_TTL
+            | RETRY_INITIAL_BACKOFF
+            | RETRY_MAX_BACKOFF => {
+                let duration = parse_duration_ms(value)
+                    .ok()
+                    .and_then(|ms| u64::try_from(ms).ok())
+                    .filter(|ms| *ms > 0)
+                    .map(Duration::from_millis)
+                    .ok_or_else(invalid)?;
+                match key {
+                    CONNECT_TIMEOUT => self.connect_timeout = Some(duration),
+                    READ_TIMEOUT => self.read_timeout = Some(duration),
+                    HMS_CACHE_TTL => self.hms_cache_ttl = Some(duration),
+                    RETRY_INITIAL_BACKOFF => self.retry_policy.initial_backoff = duration,
+                    _ => self.retry_policy.max_backoff = duration,
+                }
+            }
+            _ => match key.strip_prefix(FILE_IO_PREFIX) {
+                Some(option) => {
+                    self.file_io_options
+                        .insert(option.to_string(), value.to_string());
+                }
+                None => {
+                    return Err(IcebergError::Invalid(format!(
+                        "Unknown configuration property {}",
+                        key
+                    )))
+                }
+            },
+        }
+        Ok(self)
+    }
+
+    pub fn with_catalog_uris(mut self, uris: &[&str]) -> Self {
+        self.catalog_uris = uris.iter().map(|uri| uri.to_string()).collect();
+        self
+    }
+
+    pub fn with_warehouse(mut self, warehouse: &str) -> Self {
+        self.warehouse = Some(warehouse.to_string());
+        self
+    }
+
+    pub fn with_plain_auth(mut self, username: &str, password: &str) -> Self {
+        self.hms_auth = HmsAuthKind::Plain;
+        self.hms_username = Some(username.to_string());
+        self.hms_password = Some(password.to_string());
+        self
+    }
+
+    // Kerberos authentication with the service of the metastore principal, hive by default
+    pub fn with_kerberos_auth(mut self, service: Option<&str>) -> Self {
+        self.hms_auth = HmsAuthKind::Kerberos;
+        self.hms_kerberos_service = service.map(str::to_string);
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_hms_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.hms_cache_ttl = Some(ttl);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    pub fn with_file_io_option(mut self, option: &str, value: &str) -> Self {
+        self.file_io_options
+            .insert(option.to_string(), value.to_string());
+        self
+    }
+
+    // FileIO for the warehouse, see FileIOConfig, retrying reads as set by the retry properties
+    pub fn file_io(&self) -> Result<Arc<dyn FileIO>> {
+        self.retry_policy.validate()?;
+        let file_io = self.file_io_config().build()?;
+        if self.retry_policy.max_attempts == 1 {
+            return Ok(file_io);
+        }
+        Ok(Arc::new(RetryingFileIO::new(
+            file_io,
+            self.retry_policy.clone(),
+        )))
+    }
+
+    pub fn file_io_config(&self) -> FileIOConfig {
+        let mut config = FileIOConfig::new();
+        if let Some(warehouse) = &self.warehouse {
+            config = config.with_warehouse(warehouse);
+        }
+        for (option, value) in &self.file_io_options {
+            config = config.with_option(option, value);
+        }
+        config
+    }
+
+    #[cfg(feature = "hms")]
+    pub fn hms_catalog_config(&self) -> HmsCatalogConfig {
+        let mut config = HmsCatalogConfig::new();
+        if let Some(warehouse) = &self.warehouse {
+            config = config.with_warehouse(warehouse);
+        }
+        if let Some(ttl) = self.hms_cache_ttl {
+            config = config.with_cache_ttl(ttl);
+        }
+        config
+    }
+}
+
+#[cfg(feature = "hms")]
+impl RustbergConfig {
+    // Connects to the first metastore of catalog-uris accepting the connection
+    pub fn hms_client(&self) -> thrift::Result<HmsClient> {
+        let mut error = None;
+        for uri in &self.catalog_uris {
+            match connect_with_timeouts(
+                uri,
+                self.hms_auth(uri)?,
+                self.connect_timeout,
+                self.read_timeout,
+            ) {
+                Ok(client) => return Ok(client),
+                Err(e) => {
+                    log::warn!("Couldn't connect to metastore {}: {}", uri, e);
+                    error = Some(e);
+                }
+            }
+        }
+        Err(error.unwrap_or_else(|| config_error(format!("No {} configured", CATALOG_URIS))))
+    }
+
+    // Catalog of the tables in the metastore, reading and writing them through file_io. Lost
+    // connections are reopened, see RetryingMetastore
+    pub fn hms_catalog(&self) -> Result<HmsCatalog> {
+        // Invalid settings fail before connecting
+        let config = self.hms_catalog_config();
+        config.validate()?;
+        let file_io = self.file_io()?;
+        let connect = {
+            let config = self.clone();
+            move || -> thrift::Result<Box<dyn MetastoreClient>> {
+                Ok(Box::new(config.hms_client()?))
+            }
+        };
+        let client = RetryingMetastore::connect(connect, self.retry_policy.clone())
+            .map_err(|e| metastore_error(e, "Metastore connection"))?;
+        HmsCatalog::new(client, file_io).with_config(config)
+    }
+
+    fn hms_auth(&self, uri: &str) -> thrift::Result<HmsAuth> {
+        match self.hms_auth {
+            HmsAuthKind::None => Ok(HmsAuth::None),
+            HmsAuthKind::Plain => {
+                let (Some(username), Some(password)) = (&self.hms_username, &self.hms_password)
+                else {
+                    return Err(config_error(format!(
+                        "Plain authentication needs {} and {}",
+                        HMS_USERNAME, HMS_PASSWORD
+                    )));
+                };
+                Ok(HmsAuth::Plain(PlainMechanism::new(username, password)))
+            }
+            HmsAuthKind::Kerberos => {
+                let service = self
+                    .hms_kerberos_service
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_KERBEROS_SERVICE.to_string());
+                kerberos_auth(service, uri)
+            }
+        }
+    }
+}
+
+// The host of the metastore principal is the host of the URI
+#[cfg(feature = "kerberos")]
+fn kerberos_auth(service: String, uri: &str) -> thrift::Result<HmsAuth> {
+    let address = uri.strip_prefix("thrift://").unwrap_or(uri);
+    let host = address.rsplit_once(':').map_or(address, |(host, _)| host);
+    Ok(HmsAuth::Kerberos {
+        service,
+        host: host.to_string(),
+    })
+}
+
+#[cfg(all(feature = "hms", not(feature = "kerberos")))]
+fn kerberos_auth(_service: String, _uri: &str) -> thrift::Result<HmsAuth> {
+    Err(config_error(
+        "Kerberos authentication needs the kerberos feature".to_string(),
+    ))
+}
+
+fn env_variable(key: &str) -> String {
+    format!("RUSTBERG_{}", key.to_uppercase().replace(['.', '-'], "_"))
+}
+
+#[cfg(feature = "hms")]
+fn config_error(message: String) -> thrift::Error {
+    thrift::Error::User(Box::new(IcebergError::Invalid(message)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_properties() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rustberg.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "catalog-uris": "thrift://a:9083, thrift://b:9083",
+                "warehouse": "file:/warehouse",
+                "hms.auth": "PLAIN",
+                "hms.username": "user",
+                "hms.password": "secret",
+                "read-timeout": "30s",
+                "hms.cache-ttl": "5s",
+                "retry.max-attempts": "6",
+                "retry.max-backoff": "1s",
+                "file-io.s3.region": "eu-west-1"
+            }"#,
+        )
+        .unwrap();
+        let config = RustbergConfig::from_file(&path).unwrap();
+        assert_eq!(
+            vec!["thrift://a:9083", "thrift://b:9083"],
+            config.catalog_uris
+        );
+        assert_eq!(HmsAuthKind::Plain, config.hms_auth);
+        assert_eq!(Some(Duration::from_secs(30)), config.read_timeout);
+        assert_eq!(Some(Duration::from_secs(5)), config.hms_cache_ttl);
+        assert_eq!(6, config.retry_policy.max_attempts);
+        assert_eq!(Duration::from_secs(1), config.retry_policy.max_backoff);
+        assert_eq!("eu-west-1", config.file_io_options["s3.region"]);
+        assert!(!format!("{:?}", config).contains("secret"));
+        // LocalFileIO takes no options
+        assert!(config.file_io().is_err());
+
+        let config = config
+            .with_connect_timeout(Duration::from_millis(500))
+            .with_property(CATALOG_URIS, "thrift://c:9083")
+            .unwrap();
+        assert_eq!(vec!["thrift://c:9083"], config.catalog_uris);
+        assert_eq!(Some(Duration::from_millis(500)), config.connect_timeout);
+
+        assert!(RustbergConfig::new()
+            .with_property(READ_TIMEOUT, "soon")
+            .is_err());
+        assert!(RustbergConfig::new().with_property("uri", "x").is_err());
+        assert!(RustbergConfig::new()
+            .with_property(RETRY_MAX_ATTEMPTS, "0")
+            .is_err());
+        // The initial backoff exceeds the max backoff
+        assert!(RustbergConfig::new()
+            .with_property(RETRY_INITIAL_BACKOFF, "1m")
+            .unwrap()
+            .file_io()
+            .is_err());
+        std::fs::write(&path, r#"{"warehouse": 1}"#).unwrap();
+        assert!(RustbergConfig::from_file(&path).is_err());
+        assert!(RustbergConfig::new()
+            .with_warehouse("s3://bucket/warehouse")
+            .file_io()
+            .is_err());
+        assert!(RustbergConfig::new().file_io().is_ok());
+    }
+
+    #[test]
+    fn test_env_variable() {
+        assert_eq!(
+            "RUSTBERG_HMS_KERBEROS_SERVICE",
+            env_variable(HMS_KERBEROS_SERVICE)
+        );
+        assert_eq!("RUSTBERG_CATALOG_URIS", env_variable(CATALOG_URIS));
+    }
+
+    #[test]
+    #[cfg(feature = "hms")]
+    fn test_hms_client_failover() {
+        // Nothing listens on the first address
+        let closed = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap()
+        };
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let open = listener.local_addr().unwrap();
+        let config = RustbergConfig::new()
+            .with_catalog_uris(&[
+                &format!("thrift://{}", closed),
+                &format!("thrift://{}", open),
+            ])
+            .with_connect_timeout(Duration::from_secs(5));
+        assert!(config.hms_client().is_ok());
+
+        assert!(RustbergConfig::new().hms_client().is_err());
+        let missing_password = config.with_property(HMS_AUTH, "plain").unwrap();
+        assert!(missing_password.hms_client().is_err());
+    }
+}
@@ -0,0 +1,1422 @@
+// Catalog of the Iceberg tables registered in a Hive Metastore, compatible with the HiveCatalog
+// of Iceberg: tables are HMS tables with a table_type parameter of ICEBERG, whose
+// metadata_location parameter points to the current metadata file, and namespaces are HMS
+// databases. Views are registered the same way with a table_type of ICEBERG-VIEW. Commits hold an exclusive HMS lock on the table while swapping the location
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use thrift::TransportErrorKind;
+
+use crate::hms::hms_api::{
+    self, AlreadyExistsException, CheckLockRequest, Database, InvalidObjectException,
+    InvalidOperationException, LockComponent, LockLevel, LockRequest, LockResponse, LockState,
+    LockType, MetaException, NoSuchObjectException, TThriftHiveMetastoreSyncClient, UnlockRequest,
+};
+use crate::hms::HmsClient;
+use crate::iceberg::catalog::{
+    delete_files, table_files, write_metadata_file, IcebergCatalog, NamespaceMetadata,
+};
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::ident::{Namespace, TableIdent};
+use crate::iceberg::io::FileIO;
+use crate::iceberg::read_only::ensure_writable;
+use crate::iceberg::retry::transient_error;
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+use crate::iceberg::table::Table;
+use crate::iceberg::view::View;
+
+pub const METADATA_LOCATION: &str = "metadata_location";
+pub const PREVIOUS_METADATA_LOCATION: &str = "previous_metadata_location";
+pub const TABLE_TYPE: &str = "table_type";
+pub const ICEBERG_TABLE_TYPE: &str = "ICEBERG";
+pub const ICEBERG_VIEW_TYPE: &str = "ICEBERG-VIEW";
+// Registered tables are external, so that the metastore leaves their files alone when they are
+// dropped or renamed
+const EXTERNAL: &str = "EXTERNAL";
+const EXTERNAL_TABLE: &str = "EXTERNAL_TABLE";
+
+// Namespace properties kept in fields of the database rather than in its parameters
+pub const NAMESPACE_COMMENT: &str = "comment";
+pub const NAMESPACE_LOCATION: &str = "location";
+
+const DEFAULT_LOCK_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(180);
+// Number of cached tables above which expired ones are dropped when caching another one
+const CACHE_PRUNE_THRESHOLD: usize = 10_000;
+
+// The metastore calls made by the catalog. Implemented by HmsClient, tests implement it in
+// memory
+pub trait MetastoreClient: Send {
+    fn get_all_databases(&mut self) -> thrift::Result<Vec<String>>;
+    fn get_database(&mut self, name: &str) -> thrift::Result<Database>;
+    fn create_database(&mut self, database: Database) -> thrift::Result<()>;
+    fn alter_database(&mut self, name: &str, database: Database) -> thrift::Result<()>;
+    // Keeps the data of the database and fails if it still has tables
+    fn drop_database(&mut self, name: &str) -> thrift::Result<()>;
+    fn get_table(&mut self, database: &str, name: &str) -> thrift::Result<hms_api::Table>;
+    fn get_all_tables(&mut self, database: &str) -> thrift::Result<Vec<String>>;
+    fn get_table_objects_by_name(
+        &mut self,
+        database: &str,
+        names: Vec<String>,
+    ) -> thrift::Result<Vec<hms_api::Table>>;
+    fn create_table(&mut self, table: hms_api::Table) -> thrift::Result<()>;
+    // Renames the table when the database or name of `table` differ
+    fn alter_table(
+        &mut self,
+        database: &str,
+        name: &str,
+        table: hms_api::Table,
+    ) -> thrift::Result<()>;
+    // Keeps the files of the table
+    fn drop_table(&mut self, database: &str, name: &str) -> thrift::Result<()>;
+    fn lock(&mut self, request: LockRequest) -> thrift::Result<LockResponse>;
+    fn check_lock(&mut self, lock_id: i64) -> thrift::Result<LockResponse>;
+    fn unlock(&mut self, lock_id: i64) -> thrift::Result<()>;
+}
+
+impl MetastoreClient for HmsClient {
+    #[tracing::instrument(name = "hms.get_all_databases", level = "debug", skip(self), err)]
+    fn get_all_databases(&mut self) -> thrift::Result<Vec<String>> {
+        TThriftHiveMetastoreSyncClient::get_all_databases(self)
+    }
+
+    #[tracing::instrument(name = "hms.get_database", level = "debug", skip(self), err)]
+    fn get_database(&mut self, name: &str) -> thrift::Result<Database> {
+        TThriftHiveMetastoreSyncClient::get_database(self, name.to_string())
+    }
+
+    #[tracing::instrument(
+        name = "hms.create_database",
+        level = "debug",
+        skip(self, database),
+        err
+    )]
+    fn create_database(&mut self, database: Database) -> thrift::Result<()> {
+        TThriftHiveMetastoreSyncClient::create_database(self, database)
+    }
+
+    #[tracing::instrument(
+        name = "hms.alter_database",
+        level = "debug",
+        skip(self, database),
+        err
+    )]
+    fn alter_database(&mut self, name: &str, database: Database) -> thrift::Result<()> {
+        TThriftHiveMetastoreSyncClient::alter_database(self, name.to_string(), database)
+    }
+
+    #[tracing::instrument(name = "hms.drop_database", level = "debug", skip(self), err)]
+    fn drop_database(&mut self, name: &str) -> thrift::Result<()> {
+        TThriftHiveMetastoreSyncClient::drop_database(self, name.to_string(), false, false)
+    }
+
+    #[tracing::instrument(name = "hms.get_table", level = "debug", skip(self), err)]
+    fn get_table(&mut self, database: &str, name: &str) -> thrift::Result<hms_api::Table> {
+        TThriftHiveMetastoreSyncClient::get_table(self, database.to_string(), name.to_string())
+    }
+
+    #[tracing::instrument(name = "hms.get_all_tables", level = "debug", skip(self), err)]
+    fn get_all_tables(&mut self, database: &str) -> thrift::Result<Vec<String>> {
+        TThriftHiveMetastoreSyncClient::get_all_tables(self, database.to_string())
+    }
+
+    #[tracing::instrument(
+        name = "hms.get_table_objects_by_name",
+        level = "debug",
+        skip(self, names),
+        err
+    )]
+    fn get_table_objects_by_name(
+        &mut self,
+        database: &str,
+        names: Vec<String>,
+    ) -> thrift::Result<Vec<hms_api::Table>> {
+        TThriftHiveMetastoreSyncClient::get_table_objects_by_name(self, database.to_string(), names)
+    }
+
+    #[tracing::instrument(name = "hms.create_table", level = "debug", skip(self, table), err)]
+    fn create_table(&mut self, table: hms_api::Table) -> thrift::Result<()> {
+        TThriftHiveMetastoreSyncClient::create_table(self, table)
+    }
+
+    #[tracing::instrument(name = "hms.alter_table", level = "debug", skip(self, table), err)]
+    fn alter_table(
+        &mut self,
+        database: &str,
+        name: &str,
+        table: hms_api::Table,
+    ) -> thrift::Result<()> {
+        TThriftHiveMetastoreSyncClient::alter_table(
+            self,
+            database.to_string(),
+            name.to_string(),
+            table,
+        )
+    }
+
+    #[tracing::instrument(name = "hms.drop_table", level = "debug", skip(self), err)]
+    fn drop_table(&mut self, database: &str, name: &str) -> thrift::Result<()> {
+        TThriftHiveMetastoreSyncClient::drop_table(
+            self,
+            database.to_string(),
+            name.to_string(),
+            false,
+        )
+    }
+
+    #[tracing::instrument(name = "hms.lock", level = "debug", skip(self, request), err)]
+    fn lock(&mut self, request: LockRequest) -> thrift::Result<LockResponse> {
+        TThriftHiveMetastoreSyncClient::lock(self, request)
+    }
+
+    #[tracing::instrument(name = "hms.check_lock", level = "debug", skip(self), err)]
+    fn check_lock(&mut self, lock_id: i64) -> thrift::Result<LockResponse> {
+        TThriftHiveMetastoreSyncClient::check_lock(self, CheckLockRequest::new(lock_id, None, None))
+    }
+
+    #[tracing::instrument(name = "hms.unlock", level = "debug", skip(self), err)]
+    fn unlock(&mut self, lock_id: i64) -> thrift::Result<()> {
+        TThriftHiveMetastoreSyncClient::unlock(self, UnlockRequest::new(lock_id))
+    }
+}
+
+// Settings of an HmsCatalog, starting from the defaults and checked by HmsCatalog::with_config
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct HmsCatalogConfig {
+    // Parent of the locations of namespaces created without one, as <warehouse>/<name>.db
+    pub warehouse: Option<String>,
+    // How long lookups are cached, see HmsCatalog::with_cache_ttl. Nothing is cached by default
+    pub cache_ttl: Option<Duration>,
+    // How long commits wait for the lock of the table, checking it at the given interval
+    pub lock_timeout: Duration,
+    pub lock_check_interval: Duration,
+}
+
+impl Default for HmsCatalogConfig {
+    fn default() -> Self {
+        HmsCatalogConfig {
+            warehouse: None,
+            cache_ttl: None,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            lock_check_interval: DEFAULT_LOCK_CHECK_INTERVAL,
+        }
+    }
+}
+
+impl HmsCatalogConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_warehouse(mut self, warehouse: &str) -> Self {
+        self.warehouse = Some(warehouse.trim_end_matches('/').to_string());
+        self
+    }
+
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    pub fn with_lock_check_interval(mut self, interval: Duration) -> Self {
+        self.lock_check_interval = interval;
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: &str| Err(IcebergError::Invalid(message.to_string()));
+        if self.warehouse.as_ref().is_some_and(String::is_empty) {
+            return invalid("The warehouse of the catalog can't be empty");
+        }
+        if self.cache_ttl.is_some_and(|ttl| ttl.is_zero()) {
+            return invalid("The cache TTL of the catalog must be positive");
+        }
+        if self.lock_check_interval.is_zero() || self.lock_check_interval > self.lock_timeout {
+            return invalid(&format!(
+                "The lock check interval {:?} must be positive and at most the lock timeout {:?}",
+                self.lock_check_interval, self.lock_timeout
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Calls to the metastore are serialized over a single connection
+pub struct HmsCatalog {
+    client: Mutex<Box<dyn MetastoreClient>>,
+    file_io: Arc<dyn FileIO>,
+    config: HmsCatalogConfig,
+    cache: Option<LookupCache>,
+}
+
+impl fmt::Debug for HmsCatalog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HmsCatalog")
+            .field("file_io", &self.file_io)
+            .field("config", &self.config)
+            .field("cache", &self.cache)
+            .finish()
+    }
+}
+
+// Tables and database names looked up in the metastore, reused for a short time. Tables that
+// don't exist are cached too, with the message of their NotFound error
+#[derive(Debug)]
+struct LookupCache {
+    ttl: Duration,
+    state: Mutex<LookupState>,
+}
+
+#[derive(Debug, Default)]
+struct LookupState {
+    tables: HashMap<TableIdent, Cached<std::result::Result<hms_api::Table, String>>>,
+    databases: Option<Cached<Vec<String>>>,
+}
+
+#[derive(Debug)]
+struct Cached<T> {
+    value: T,
+    expires_at: Instant,
+}
+
+impl LookupCache {
+    fn table(&self, key: &TableIdent) -> Option<Result<hms_api::Table>> {
+        let state = self.state.lock().unwrap();
+        let cached = state.tables.get(key)?;
+        (cached.expires_at > Instant::now())
+            .then(|| cached.value.clone().map_err(IcebergError::NotFound))
+    }
+
+    fn put_table(&self, key: TableIdent, table: &Result<hms_api::Table>) {
+        let value = match table {
+            Ok(table) => Ok(table.clone()),
+            Err(IcebergError::NotFound(message)) => Err(message.clone()),
+            // Other errors may be transient
+            Err(_) => return,
+        };
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        if state.tables.len() >= CACHE_PRUNE_THRESHOLD {
+            state.tables.retain(|_, cached| cached.expires_at > now);
+        }
+        let expires_at = now + self.ttl;
+        state.tables.insert(key, Cached { value, expires_at });
+    }
+
+    fn databases(&self) -> Option<Vec<String>> {
+        let state = self.state.lock().unwrap();
+        let cached = state.databases.as_ref()?;
+        (cached.expires_at > Instant::now()).then(|| cached.value.clone())
+    }
+
+    fn put_databases(&self, databases: &[String]) {
+        self.state.lock().unwrap().databases = Some(Cached {
+            value: databases.to_vec(),
+            expires_at: Instant::now() + self.ttl,
+        });
+    }
+
+    fn invalidate_table(&self, ident: &TableIdent) {
+        self.state.lock().unwrap().tables.remove(ident);
+    }
+
+    fn invalidate_databases(&self) {
+        self.state.lock().unwrap().databases = None;
+    }
+}
+
+impl HmsCatalog {
+    pub fn new(client: impl MetastoreClient + 'static, file_io: Arc<dyn FileIO>) -> Self {
+        HmsCatalog {
+            client: Mutex::new(Box::new(client)),
+            file_io,
+            config: HmsCatalogConfig::default(),
+            cache: None,
+        }
+    }
+
+    pub fn with_config(mut self, config: HmsCatalogConfig) -> Result<Self> {
+        config.validate()?;
+        self.cache = config.cache_ttl.map(|ttl| LookupCache {
+            ttl,
+            state: Mutex::new(LookupState::default()),
+        });
+        self.config = config;
+        Ok(self)
+    }
+
+    // Caches tables, including missing ones, and the names of the databases for the given time.
+    // Tables are loaded and refreshed from the cached metadata locations, so changes made by
+    // other writers may be seen up to `ttl` late. Commits aren't affected, they check the
+    // current metadata location of the table in the metastore
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.config.cache_ttl = Some(ttl);
+        self.cache = Some(LookupCache {
+            ttl,
+            state: Mutex::new(LookupState::default()),
+        });
+        self
+    }
+
+    // Forgets the cached table, e.g. after it was changed through another catalog
+    pub fn invalidate_table(&self, ident: &TableIdent) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate_table(ident);
+        }
+    }
+
+    pub fn with_warehouse(mut self, warehouse: &str) -> Self {
+        self.config = self.config.with_warehouse(warehouse);
+        self
+    }
+
+    fn client(&self) -> MutexGuard<'_, Box<dyn MetastoreClient>> {
+        self.client.lock().unwrap()
+    }
+
+    fn metadata_location(&self, ident: &TableIdent) -> Result<String> {
+        let object = format!("Table {}", ident);
+        iceberg_metadata_location(&self.hms_table(ident, &object)?, &object)
+    }
+
+    fn hms_table(&self, ident: &TableIdent, object: &str) -> Result<hms_api::Table> {
+        let (database, name) = hms_name(ident)?;
+        match self.cache.as_ref().and_then(|cache| cache.table(ident)) {
+            Some(table) => table,
+            None => {
+                let table = self
+                    .client()
+                    .get_table(database, name)
+                    .map_err(|e| metastore_error(e, object));
+                if let Some(cache) = &self.cache {
+                    cache.put_table(ident.clone(), &table);
+                }
+                table
+            }
+        }
+    }
+
+    // Names of the HMS tables of a namespace whose parameters mark them as the given type of
+    // Iceberg object
+    fn list_of_type(&self, namespace: &Namespace, object_type: &str) -> Result<Vec<String>> {
+        // Databases also hold Hive tables and views
+        let object = format!("Namespace {}", namespace);
+        let database = namespace.single_level()?;
+        let mut client = self.client();
+        let names = client
+            .get_all_tables(database)
+            .map_err(|e| metastore_error(e, &object))?;
+        let tables = client
+            .get_table_objects_by_name(database, names)
+            .map_err(|e| metastore_error(e, &object))?;
+        let mut names: Vec<String> = tables
+            .into_iter()
+            .filter(|table| {
+                let object = format!(
+                    "{}.{}",
+                    namespace,
+                    table.table_name.as_deref().unwrap_or_default()
+                );
+                metadata_location_of_type(table, object_type, &object).is_ok()
+            })
+            .filter_map(|table| table.table_name)
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn database_names(&self) -> Result<Vec<String>> {
+        if let Some(names) = self.cache.as_ref().and_then(LookupCache::databases) {
+            return Ok(names);
+        }
+        let names = self
+            .client()
+            .get_all_databases()
+            .map_err(|e| metastore_error(e, "Namespaces"))?;
+        if let Some(cache) = &self.cache {
+            cache.put_databases(&names);
+        }
+        Ok(names)
+    }
+
+    fn invalidate_databases(&self) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate_databases();
+        }
+    }
+
+    // Adds an existing table to the metastore, whose current metadata is at `metadata_location`
+    pub fn register_table(&self, ident: &TableIdent, metadata_location: &str) -> Result<Table> {
+        let (database, name) = hms_name(ident)?;
+        let table = Table::load(
+            ident.clone(),
+            metadata_location.to_string(),
+            self.file_io.clone(),
+        )?;
+        let parameters = BTreeMap::from([
+            (TABLE_TYPE.to_string(), ICEBERG_TABLE_TYPE.to_string()),
+            (METADATA_LOCATION.to_string(), metadata_location.to_string()),
+            (EXTERNAL.to_string(), "TRUE".to_string()),
+        ]);
+        let hms_table = hms_api::Table {
+            table_name: Some(name.to_string()),
+            db_name: Some(database.to_string()),
+            sd: Some(hms_api::StorageDescriptor {
+                location: Some(table.metadata().location.clone()),
+                ..Default::default()
+            }),
+            parameters: Some(parameters),
+            table_type: Some(EXTERNAL_TABLE.to_string()),
+            ..Default::default()
+        };
+        let created = self.client().create_table(hms_table);
+        // Lookups may have cached that the table doesn't exist
+        self.invalidate_table(ident);
+        created.map_err(|e| metastore_error(e, &format!("Table {}", ident)))?;
+        Ok(table)
+    }
+
+    // Renames the table within or across namespaces. Files stay where they are
+    pub fn rename_table(&self, from: &TableIdent, to: &TableIdent) -> Result<()> {
+        let object = format!("Table {}", from);
+        let (database, name) = hms_name(from)?;
+        let (to_database, to_name) = hms_name(to)?;
+        let mut client = self.client();
+        let mut table = client
+            .get_table(database, name)
+            .map_err(|e| metastore_error(e, &object))?;
+        iceberg_metadata_location(&table, &object)?;
+        table.db_name = Some(to_database.to_string());
+        table.table_name = Some(to_name.to_string());
+        let renamed = client.alter_table(database, name, table);
+        drop(client);
+        self.invalidate_table(from);
+        self.invalidate_table(to);
+        renamed.map_err(|e| metastore_error(e, &object))
+    }
+
+    fn database(&self, namespace: &Namespace) -> Result<Database> {
+        self.client()
+            .get_database(namespace.single_level()?)
+            .map_err(|e| metastore_error(e, &format!("Namespace {}", namespace)))
+    }
+}
+
+impl IcebergCatalog for HmsCatalog {
+    fn load_table(&self, ident: &TableIdent) -> Result<Table> {
+        Table::load(
+            ident.clone(),
+            self.metadata_location(ident)?,
+            self.file_io.clone(),
+        )
+    }
+
+    fn refresh_table(&self, table: &Table) -> Result<Table> {
+        table.refresh(&self.metadata_location(table.ident())?)
+    }
+
+    fn commit_table(&self, base: &Table, metadata: TableMetadataV2) -> Result<Table> {
+        let ident = base.ident();
+        let (database, name) = hms_name(ident)?;
+        let object = format!("Table {}", ident);
+        let mut client = self.client();
+        let lock_id = lock_table(client.as_mut(), &self.config, database, name)?;
+        let committed = (|| {
+            let mut table = client
+                .get_table(database, name)
+                .map_err(|e| metastore_error(e, &object))?;
+            if iceberg_metadata_location(&table, &object)? != base.metadata_location() {
+                return Err(IcebergError::CommitConflict(format!(
+                    "{} changed since it was loaded",
+                    object
+                )));
+            }
+            let location = write_metadata_file(base.file_io().as_ref(), Some(base), metadata)?;
+            let parameters = table.parameters.get_or_insert_with(BTreeMap::new);
+            parameters.insert(METADATA_LOCATION.to_string(), location.clone());
+            parameters.insert(
+                PREVIOUS_METADATA_LOCATION.to_string(),
+                base.metadata_location().to_string(),
+            );
+            client
+                .alter_table(database, name, table)
+                .map_err(|e| metastore_error(e, &object))?;
+            Ok(location)
+        })();
+        let unlocked = client
+            .unlock(lock_id)
+            .map_err(|e| metastore_error(e, &object));
+        drop(client);
+        // Whatever the outcome, the cached table may be outdated
+        self.invalidate_table(ident);
+        let location = committed?;
+        if let Err(e) = unlocked {
+            // The commit went through, the lock expires on its own
+            log::warn!("Couldn't release lock {} of {}: {}", lock_id, object, e);
+        }
+        Table::load(ident.clone(), location, base.file_io().clone())
+    }
+
+    fn list_tables(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        self.list_of_type(namespace, ICEBERG_TABLE_TYPE)
+    }
+
+    fn load_view(&self, ident: &TableIdent) -> Result<View> {
+        let object = format!("View {}", ident);
+        let table = self.hms_table(ident, &object)?;
+        View::load(
+            ident.clone(),
+            metadata_location_of_type(&table, ICEBERG_VIEW_TYPE, &object)?,
+            self.file_io.clone(),
+        )
+    }
+
+    fn list_views(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        self.list_of_type(namespace, ICEBERG_VIEW_TYPE)
+    }
+
+    fn drop_table(&self, ident: &TableIdent, purge: bool) -> Result<()> {
+        let (database, name) = hms_name(ident)?;
+        // The metastore only drops the table, the files are found from its metadata
+        let files = match purge {
+            true => {
+                ensure_writable(&format!("purge table {}", ident))?;
+                table_files(&self.load_table(ident)?)?
+            }
+            false => vec![],
+        };
+        let dropped = self.client().drop_table(database, name);
+        self.invalidate_table(ident);
+        dropped.map_err(|e| metastore_error(e, &format!("Table {}", ident)))?;
+        delete_files(self.file_io.as_ref(), &files);
+        Ok(())
+    }
+
+    fn list_namespaces(&self) -> Result<Vec<NamespaceMetadata>> {
+        // Databases dropped since they were listed are left out
+        self.database_names()?
+            .iter()
+            .filter_map(|name| {
+                let namespace = match Namespace::try_new(vec![name.clone()]) {
+                    Ok(namespace) => namespace,
+                    Err(e) => return Some(Err(e)),
+                };
+                match self.database(&namespace) {
+                    Ok(database) => Some(Ok(namespace_metadata(namespace, &database))),
+                    Err(IcebergError::NotFound(_)) => None,
+                    Err(e) => Some(Err(e)),
+                }
+            })
+            .collect()
+    }
+
+    fn create_namespace(
+        &self,
+        namespace: &Namespace,
+        properties: HashMap<String, String>,
+    ) -> Result<NamespaceMetadata> {
+        let mut database = Database {
+            name: Some(namespace.single_level()?.to_string()),
+            description: None,
+            location_uri: None,
+            parameters: Some(BTreeMap::new()),
+            privileges: None,
+            owner_name: None,
+            owner_type: None,
+        };
+        for (key, value) in properties {
+            set_namespace_property(&mut database, &key, Some(value));
+        }
+        if database.location_uri.is_none() {
+            database.location_uri = self
+                .config
+                .warehouse
+                .as_ref()
+                .map(|warehouse| format!("{}/{}.db", warehouse, namespace));
+        }
+        let created = self.client().create_database(database.clone());
+        self.invalidate_databases();
+        created.map_err(|e| metastore_error(e, &format!("Namespace {}", namespace)))?;
+        Ok(namespace_metadata(namespace.clone(), &database))
+    }
+
+    fn drop_namespace(&self, namespace: &Namespace) -> Result<()> {
+        let dropped = self.client().drop_database(namespace.single_level()?);
+        self.invalidate_databases();
+        dropped.map_err(|e| metastore_error(e, &format!("Namespace {}", namespace)))
+    }
+
+    fn update_namespace_properties(
+        &self,
+        namespace: &Namespace,
+        updates: HashMap<String, String>,
+        removals: &[&str],
+    ) -> Result<NamespaceMetadata> {
+        let mut database = self.database(namespace)?;
+        for key in removals {
+            set_namespace_property(&mut database, key, None);
+        }
+        for (key, value) in updates {
+            set_namespace_property(&mut database, &key, Some(value));
+        }
+        self.client()
+            .alter_database(namespace.single_level()?, database.clone())
+            .map_err(|e| metastore_error(e, &format!("Namespace {}", namespace)))?;
+        Ok(namespace_metadata(namespace.clone(), &database))
+    }
+}
+
+fn iceberg_metadata_location(table: &hms_api::Table, object: &str) -> Result<String> {
+    metadata_location_of_type(table, ICEBERG_TABLE_TYPE, object)
+}
+
+fn metadata_location_of_type(
+    table: &hms_api::Table,
+    object_type: &str,
+    object: &str,
+) -> Result<String> {
+    let parameter = |key| table.parameters.as_ref().and_then(|p| p.get(key));
+    if !parameter(TABLE_TYPE).is_some_and(|t| t.eq_ignore_ascii_case(object_type)) {
+        let kind = match object_type {
+            ICEBERG_VIEW_TYPE => "view",
+            _ => "table",
+        };
+        return Err(IcebergError::Invalid(format!(
+            "{} is not an Iceberg {}",
+            object, kind
+        )));
+    }
+    parameter(METADATA_LOCATION)
+        .cloned()
+        .ok_or_else(|| IcebergError::Invalid(format!("{} has no {}", object, METADATA_LOCATION)))
+}
+
+// Waits for an exclusive lock on the table, returns its id
+fn lock_table(
+    client: &mut dyn MetastoreClient,
+    config: &HmsCatalogConfig,
+    namespace: &str,
+    name: &str,
+) -> Result<i64> {
+    let object = format!("Table {}.{}", namespace, name);
+    let component = LockComponent {
+        type_: LockType::EXCLUSIVE,
+        level: LockLevel::TABLE,
+        dbname: namespace.to_string(),
+        tablename: Some(name.to_string()),
+        partitionname: None,
+        operation_type: None,
+        is_acid: None,
+        is_dynamic_partition_write: None,
+    };
+    let request = LockRequest::new(
+        vec![component],
+        None,
+        std::env::var("USER").unwrap_or_else(|_| "rustberg".to_string()),
+        std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string()),
+        Some("rustberg".to_string()),
+    );
+    let mut response = client
+        .lock(request)
+        .map_err(|e| metastore_error(e, &object))?;
+    let started = Instant::now();
+    while response.state == LockState::WAITING && started.elapsed() < config.lock_timeout {
+        thread::sleep(config.lock_check_interval);
+        response = client
+            .check_lock(response.lockid)
+            .map_err(|e| metastore_error(e, &object))?;
+    }
+    if response.state != LockState::ACQUIRED {
+        let _ = client.unlock(response.lockid);
+        return Err(IcebergError::Invalid(format!(
+            "Couldn't lock {}, lock {} is in state {}",
+            object, response.lockid, response.state.0
+        )));
+    }
+    Ok(response.lockid)
+}
+
+// Database and table names of a table, namespaces of the metastore have a single level
+fn hms_name(ident: &TableIdent) -> Result<(&str, &str)> {
+    Ok((ident.namespace().single_level()?, ident.name()))
+}
+
+fn namespace_metadata(name: Namespace, database: &Database) -> NamespaceMetadata {
+    let mut properties: HashMap<String, String> = database
+        .parameters
+        .iter()
+        .flatten()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if let Some(comment) = &database.description {
+        properties.insert(NAMESPACE_COMMENT.to_string(), comment.clone());
+    }
+    if let Some(location) = &database.location_uri {
+        properties.insert(NAMESPACE_LOCATION.to_string(), location.clone());
+    }
+    NamespaceMetadata { name, properties }
+}
+
+// Sets or, given no value, removes a namespace property
+fn set_namespace_property(database: &mut Database, key: &str, value: Option<String>) {
+    match key {
+        NAMESPACE_COMMENT => database.description = value,
+        NAMESPACE_LOCATION => database.location_uri = value,
+        _ => {
+            let parameters = database.parameters.get_or_insert_with(BTreeMap::new);
+            match value {
+                Some(value) => parameters.insert(key.to_string(), value),
+                None => parameters.remove(key),
+            };
+        }
+    }
+}
+
+// Maps the exceptions of the metastore to errors of the crate, keeping their messages, which
+// thrift leaves out of their Display. Other errors are IO errors
+pub(crate) fn metastore_error(error: thrift::Error, object: &str) -> IcebergError {
+    let describe = |message: &Option<String>| match message.as_deref() {
+        Some(message) if !message.is_empty() => format!("{}: {}", object, message),
+        _ => object.to_string(),
+    };
+    if let thrift::Error::User(user) = &error {
+        if let Some(e) = user.downcast_ref::<NoSuchObjectException>() {
+            return IcebergError::NotFound(describe(&e.message));
+        }
+        let message = user
+            .downcast_ref::<AlreadyExistsException>()
+            .map(|e| &e.message)
+            .or_else(|| {
+                user.downcast_ref::<InvalidOperationException>()
+                    .map(|e| &e.message)
+            })
+            .or_else(|| {
+                user.downcast_ref::<InvalidObjectException>()
+                    .map(|e| &e.message)
+            })
+            .or_else(|| user.downcast_ref::<MetaException>().map(|e| &e.message));
+        if let Some(message) = message {
+            return IcebergError::Invalid(describe(message));
+        }
+    }
+    if is_transient_thrift(&error) {
+        return transient_error(format!("{}: {}", object, error));
+    }
+    IcebergError::Io(std::io::Error::other(format!("{}: {}", object, error)))
+}
+
+// Errors of connections that were lost or timed out, after which the call may succeed on a new
+// connection
+pub(crate) fn is_transient_thrift(error: &thrift::Error) -> bool {
+    matches!(
+        error,
+        thrift::Error::Transport(e) if matches!(
+            e.kind,
+            TransportErrorKind::NotOpen | TransportErrorKind::TimedOut | TransportErrorKind::EndOfFile
+        )
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::io::LocalFileIO;
+    use crate::iceberg::test_utils::{append_ids, create_table, TestCatalog};
+
+    // Metastore keeping databases, tables and locks in memory. Clones share their state
+    #[derive(Debug, Clone, Default)]
+    struct FakeMetastore {
+        state: Arc<Mutex<FakeState>>,
+    }
+
+    #[derive(Debug, Default)]
+    struct FakeState {
+        databases: BTreeMap<String, Database>,
+        tables: BTreeMap<(String, String), hms_api::Table>,
+        locks: Vec<i64>,
+        next_lock_id: i64,
+        get_table_calls: usize,
+        get_all_databases_calls: usize,
+        // Locks stay waiting, as if another writer held them
+        contended: bool,
+    }
+
+    fn no_such_object(object: String) -> thrift::Error {
+        NoSuchObjectException::new(object).into()
+    }
+
+    impl FakeMetastore {
+        fn register(&self, namespace: &str, name: &str, metadata_location: &str) {
+            let parameters = BTreeMap::from([
+                (TABLE_TYPE.to_string(), ICEBERG_TABLE_TYPE.to_string()),
+                (METADATA_LOCATION.to_string(), metadata_location.to_string()),
+            ]);
+            let table = hms_api::Table::new(
+                name.to_string(),
+                namespace.to_string(),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                parameters,
+                None,
+                None,
+                "EXTERNAL_TABLE".to_string(),
+                None,
+                None,
+                None,
+            );
+            let key = (namespace.to_string(), name.to_string());
+            self.state.lock().unwrap().tables.insert(key, table);
+        }
+
+        fn parameter(&self, namespace: &str, name: &str, key: &str) -> Option<String> {
+            let state = self.state.lock().unwrap();
+            let table = &state.tables[&(namespace.to_string(), name.to_string())];
+            table.parameters.as_ref().unwrap().get(key).cloned()
+        }
+    }
+
+    impl MetastoreClient for FakeMetastore {
+        fn get_all_databases(&mut self) -> thrift::Result<Vec<String>> {
+            let mut state = self.state.lock().unwrap();
+            state.get_all_databases_calls += 1;
+            Ok(state.databases.keys().cloned().collect())
+        }
+
+        fn get_database(&mut self, name: &str) -> thrift::Result<Database> {
+            let state = self.state.lock().unwrap();
+            state
+                .databases
+                .get(name)
+                .cloned()
+                .ok_or_else(|| no_such_object(format!("{} not found", name)))
+        }
+
+        fn create_database(&mut self, database: Database) -> thrift::Result<()> {
+            let mut state = self.state.lock().unwrap();
+            let name = database.name.clone().unwrap();
+            if state.databases.contains_key(&name) {
+                return Err(AlreadyExistsException::new(format!("{} already exists", name)).into());
+            }
+            state.databases.insert(name, database);
+            Ok(())
+        }
+
+        fn alter_database(&mut self, name: &str, database: Database) -> thrift::Result<()> {
+            self.get_database(name)?;
+            let mut state = self.state.lock().unwrap();
+            state.databases.insert(name.to_string(), database);
+            Ok(())
+        }
+
+        fn drop_database(&mut self, name: &str) -> thrift::Result<()> {
+            let mut state = self.state.lock().unwrap();
+            if state.tables.keys().any(|(database, _)| database == name) {
+                return Err(InvalidOperationException::new(format!(
+                    "Database {} is not empty",
+                    name
+                ))
+                .into());
+            }
+            state
+                .databases
+                .remove(name)
+                .map(|_| ())
+                .ok_or_else(|| no_such_object(format!("{} not found", name)))
+        }
+
+        fn get_table(&mut self, database: &str, name: &str) -> thrift::Result<hms_api::Table> {
+            let mut state = self.state.lock().unwrap();
+            state.get_table_calls += 1;
+            state
+                .tables
+                .get(&(database.to_string(), name.to_string()))
+                .cloned()
+                .ok_or_else(|| no_such_object(format!("{}.{} not found", database, name)))
+        }
+
+        fn get_all_tables(&mut self, database: &str) -> thrift::Result<Vec<String>> {
+            let state = self.state.lock().unwrap();
+            Ok(state
+                .tables
+                .keys()
+                .filter(|(db, _)| db == database)
+                .map(|(_, name)| name.clone())
+                .collect())
+        }
+
+        fn get_table_objects_by_name(
+            &mut self,
+            database: &str,
+            names: Vec<String>,
+        ) -> thrift::Result<Vec<hms_api::Table>> {
+            let state = self.state.lock().unwrap();
+            Ok(names
+                .into_iter()
+                .filter_map(|name| state.tables.get(&(database.to_string(), name)).cloned())
+                .collect())
+        }
+
+        fn create_table(&mut self, table: hms_api::Table) -> thrift::Result<()> {
+            let mut state = self.state.lock().unwrap();
+            let key = (
+                table.db_name.clone().unwrap(),
+                table.table_name.clone().unwrap(),
+            );
+            if state.tables.contains_key(&key) {
+                return Err(AlreadyExistsException::new(format!(
+                    "{}.{} already exists",
+                    key.0, key.1
+                ))
+                .into());
+            }
+            state.tables.insert(key, table);
+            Ok(())
+        }
+
+        fn alter_table(
+            &mut self,
+            database: &str,
+            name: &str,
+            table: hms_api::Table,
+        ) -> thrift::Result<()> {
+            let mut state = self.state.lock().unwrap();
+            let key = (database.to_string(), name.to_string());
+            let new_key = (
+                table.db_name.clone().unwrap(),
+                table.table_name.clone().unwrap(),
+            );
+            if new_key != key && state.tables.contains_key(&new_key) {
+                return Err(InvalidOperationException::new(format!(
+                    "{}.{} already exists",
+                    new_key.0, new_key.1
+                ))
+                .into());
+            }
+            assert!(state.tables.remove(&key).is_some());
+            state.tables.insert(new_key, table);
+            Ok(())
+        }
+
+        fn drop_table(&mut self, database: &str, name: &str) -> thrift::Result<()> {
+            let mut state = self.state.lock().unwrap();
+            state
+                .tables
+                .remove(&(database.to_string(), name.to_string()))
+                .map(|_| ())
+                .ok_or_else(|| no_such_object(format!("{}.{} not found", database, name)))
+        }
+
+        fn lock(&mut self, request: LockRequest) -> thrift::Result<LockResponse> {
+            assert_eq!(LockType::EXCLUSIVE, request.component[0].type_);
+            let mut state = self.state.lock().unwrap();
+            state.next_lock_id += 1;
+            let lock_id = state.next_lock_id;
+            state.locks.push(lock_id);
+            Ok(LockResponse::new(lock_id, LockState::WAITING))
+        }
+
+        fn check_lock(&mut self, lock_id: i64) -> thrift::Result<LockResponse> {
+            let state = match self.state.lock().unwrap().contended {
+                true => LockState::WAITING,
+                false => LockState::ACQUIRED,
+            };
+            Ok(LockResponse::new(lock_id, state))
+        }
+
+        fn unlock(&mut self, lock_id: i64) -> thrift::Result<()> {
+            self.state.lock().unwrap().locks.retain(|id| *id != lock_id);
+            Ok(())
+        }
+    }
+
+    fn catalog(metastore: &FakeMetastore, file_io: Arc<dyn FileIO>) -> HmsCatalog {
+        HmsCatalog::new(metastore.clone(), file_io).with_warehouse("file:/warehouse/")
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let metastore = FakeMetastore::default();
+        let dir = tempfile::tempdir().unwrap();
+        let file_io = create_table(dir.path()).file_io().clone();
+        let catalog = catalog(&metastore, file_io);
+
+        let properties = HashMap::from([
+            ("comment".to_string(), "Sales data".to_string()),
+            ("owner-team".to_string(), "sales".to_string()),
+        ]);
+        let sales = catalog
+            .create_namespace(&"sales".parse().unwrap(), properties)
+            .unwrap();
+        assert_eq!(
+            Some("file:/warehouse/sales.db"),
+            sales.properties.get("location").map(String::as_str)
+        );
+        let database = metastore.clone().get_database("sales").unwrap();
+        assert_eq!(Some("Sales data"), database.description.as_deref());
+        assert_eq!(
+            Some(&BTreeMap::from([(
+                "owner-team".to_string(),
+                "sales".to_string()
+            )])),
+            database.parameters.as_ref()
+        );
+        let location = HashMap::from([("location".to_string(), "file:/hr".to_string())]);
+        catalog
+            .create_namespace(&"hr".parse().unwrap(), location.clone())
+            .unwrap();
+        let error = catalog
+            .create_namespace(&"hr".parse().unwrap(), location)
+            .unwrap_err();
+        assert!(matches!(error, IcebergError::Invalid(_)), "{}", error);
+        assert!(error.to_string().contains("hr already exists"));
+
+        let namespaces = catalog.list_namespaces().unwrap();
+        assert_eq!(
+            vec!["hr", "sales"],
+            namespaces
+                .iter()
+                .map(|n| n.name.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(sales, namespaces[1]);
+
+        let updated = catalog
+            .update_namespace_properties(
+                &"sales".parse().unwrap(),
+                HashMap::from([("retention".to_string(), "30d".to_string())]),
+                &["comment", "owner-team"],
+            )
+            .unwrap();
+        assert_eq!(
+            HashMap::from([
+                ("retention".to_string(), "30d".to_string()),
+                (
+                    "location".to_string(),
+                    "file:/warehouse/sales.db".to_string()
+                ),
+            ]),
+            updated.properties
+        );
+        assert_eq!(updated, catalog.list_namespaces().unwrap()[1]);
+
+        catalog.drop_namespace(&"hr".parse().unwrap()).unwrap();
+        assert!(matches!(
+            catalog.drop_namespace(&"hr".parse().unwrap()),
+            Err(IcebergError::NotFound(_))
+        ));
+        assert!(matches!(
+            catalog.update_namespace_properties(&"hr".parse().unwrap(), HashMap::new(), &[]),
+            Err(IcebergError::NotFound(_))
+        ));
+        metastore.register("sales", "orders", "file:/orders/v1.metadata.json");
+        assert!(matches!(
+            catalog.drop_namespace(&"sales".parse().unwrap()),
+            Err(IcebergError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_commit_swaps_metadata_location() {
+        let metastore = FakeMetastore::default();
+        let dir = tempfile::tempdir().unwrap();
+        let created = create_table(dir.path());
+        metastore.register("db", "t", created.metadata_location());
+        let catalog = catalog(&metastore, created.file_io().clone());
+
+        let table = catalog.load_table(&"db.t".parse().unwrap()).unwrap();
+        assert_eq!(created.metadata(), table.metadata());
+        let committed = catalog
+            .commit_table(&table, table.metadata().clone())
+            .unwrap();
+        assert_eq!(
+            Some(committed.metadata_location()),
+            metastore.parameter("db", "t", METADATA_LOCATION).as_deref()
+        );
+        assert_eq!(
+            Some(table.metadata_location()),
+            metastore
+                .parameter("db", "t", PREVIOUS_METADATA_LOCATION)
+                .as_deref()
+        );
+        let refreshed = catalog.refresh_table(&table).unwrap();
+        assert_eq!(committed.metadata_location(), refreshed.metadata_location());
+
+        // The stale table fails to commit and its lock is released either way
+        let error = catalog
+            .commit_table(&table, table.metadata().clone())
+            .unwrap_err();
+        assert!(
+            matches!(error, IcebergError::CommitConflict(_)),
+            "{}",
+            error
+        );
+        assert!(metastore.state.lock().unwrap().locks.is_empty());
+
+        assert!(matches!(
+            catalog.load_table(&"db.missing".parse().unwrap()),
+            Err(IcebergError::NotFound(_))
+        ));
+        catalog.drop_table(&"db.t".parse().unwrap(), false).unwrap();
+        assert!(catalog.load_table(&"db.t".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_table_lifecycle() {
+        let metastore = FakeMetastore::default();
+        let dir = tempfile::tempdir().unwrap();
+        let test_catalog = TestCatalog::new();
+        let table = test_catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&test_catalog, &table, &[1, 2, 3]);
+        let file_io = table.file_io().clone();
+        let catalog = catalog(&metastore, file_io.clone()).with_cache_ttl(Duration::from_secs(60));
+
+        // Registering replaces the cached lookup of the missing table
+        assert!(catalog.load_table(&"db.t".parse().unwrap()).is_err());
+        let registered = catalog
+            .register_table(&"db.t".parse().unwrap(), table.metadata_location())
+            .unwrap();
+        assert_eq!(table.metadata(), registered.metadata());
+        let loaded = catalog.load_table(&"db.t".parse().unwrap()).unwrap();
+        assert_eq!(table.metadata_location(), loaded.metadata_location());
+        assert_eq!(
+            Some("TRUE".to_string()),
+            metastore.parameter("db", "t", EXTERNAL)
+        );
+        let error = catalog
+            .register_table(&"db.t".parse().unwrap(), table.metadata_location())
+            .unwrap_err();
+        assert!(error.to_string().contains("already exists"));
+        assert!(catalog
+            .register_table(&"db.other".parse().unwrap(), "file:/missing.metadata.json")
+            .is_err());
+
+        catalog
+            .rename_table(&"db.t".parse().unwrap(), &"db2.u".parse().unwrap())
+            .unwrap();
+        assert!(matches!(
+            catalog.load_table(&"db.t".parse().unwrap()),
+            Err(IcebergError::NotFound(_))
+        ));
+        let renamed = catalog.load_table(&"db2.u".parse().unwrap()).unwrap();
+        assert_eq!(table.metadata_location(), renamed.metadata_location());
+        assert!(catalog
+            .rename_table(&"db.t".parse().unwrap(), &"db2.v".parse().unwrap())
+            .is_err());
+
+        // Purging deletes the data, manifests and metadata files of every snapshot
+        let files = table_files(&renamed).unwrap();
+        assert_eq!(5, files.len());
+        catalog.drop_table(&"db2.u".parse().unwrap(), true).unwrap();
+        assert!(catalog.load_table(&"db2.u".parse().unwrap()).is_err());
+        for file in &files {
+            assert!(!file_io.exists(file).unwrap(), "{} was not purged", file);
+        }
+        assert!(file_io.list(&table.metadata().location).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_list_tables() {
+        let metastore = FakeMetastore::default();
+        metastore.register("db", "b", "file:/b.metadata.json");
+        metastore.register("db", "a", "file:/a.metadata.json");
+        metastore.register("other", "c", "file:/c.metadata.json");
+        // A plain Hive table of the same database
+        let mut hive =
+            metastore.state.lock().unwrap().tables[&("db".to_string(), "b".to_string())].clone();
+        hive.table_name = Some("hive".to_string());
+        hive.parameters = Some(BTreeMap::new());
+        metastore.clone().create_table(hive).unwrap();
+
+        let catalog = catalog(&metastore, Arc::new(LocalFileIO::new()));
+        assert_eq!(
+            vec!["a", "b"],
+            catalog.list_tables(&"db".parse().unwrap()).unwrap()
+        );
+        assert_eq!(
+            vec!["c"],
+            catalog.list_tables(&"other".parse().unwrap()).unwrap()
+        );
+        assert!(catalog
+            .list_tables(&"missing".parse().unwrap())
+            .unwrap()
+            .is_empty());
+        // Databases of the metastore can't be nested
+        assert!(matches!(
+            catalog.list_tables(&"db.nested".parse().unwrap()),
+            Err(IcebergError::Unsupported(_))
+        ));
+        assert!(matches!(
+            catalog.load_table(&"db.nested.a".parse().unwrap()),
+            Err(IcebergError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_views() {
+        use crate::iceberg::spec::view_metadata::tests::VIEW_METADATA;
+
+        let dir = tempfile::tempdir().unwrap();
+        let location = format!("file:{}/v1.metadata.json", dir.path().display());
+        let file_io: Arc<dyn FileIO> = Arc::new(LocalFileIO::new());
+        file_io
+            .write(&location, bytes::Bytes::from(VIEW_METADATA))
+            .unwrap();
+        let metastore = FakeMetastore::default();
+        metastore.register("db", "events", "file:/events.metadata.json");
+        metastore.register("db", "event_agg", &location);
+        {
+            let mut state = metastore.state.lock().unwrap();
+            let view = state
+                .tables
+                .get_mut(&("db".to_string(), "event_agg".to_string()))
+                .unwrap();
+            view.parameters
+                .as_mut()
+                .unwrap()
+                .insert(TABLE_TYPE.to_string(), ICEBERG_VIEW_TYPE.to_string());
+            view.table_type = Some("VIRTUAL_VIEW".to_string());
+        }
+
+        let catalog = catalog(&metastore, file_io);
+        assert_eq!(
+            vec!["event_agg"],
+            catalog.list_views(&"db".parse().unwrap()).unwrap()
+        );
+        assert_eq!(
+            vec!["events"],
+            catalog.list_tables(&"db".parse().unwrap()).unwrap()
+        );
+        let view = catalog.load_view(&"db.event_agg".parse().unwrap()).unwrap();
+        assert_eq!(location, view.metadata_location());
+        assert_eq!(2, view.current_version().unwrap().version_id);
+        assert!(matches!(
+            catalog.load_view(&"db.events".parse().unwrap()),
+            Err(IcebergError::Invalid(message)) if message.contains("not an Iceberg view")
+        ));
+        assert!(catalog
+            .load_table(&"db.event_agg".parse().unwrap())
+            .is_err());
+        assert!(matches!(
+            catalog.load_view(&"db.missing".parse().unwrap()),
+            Err(IcebergError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_catalog_config() {
+        let metastore = FakeMetastore::default();
+        let dir = tempfile::tempdir().unwrap();
+        let created = create_table(dir.path());
+        metastore.register("db", "t", created.metadata_location());
+        let file_io = created.file_io().clone();
+
+        let invalid = [
+            HmsCatalogConfig::new().with_warehouse(""),
+            HmsCatalogConfig::new().with_cache_ttl(Duration::ZERO),
+            HmsCatalogConfig::new().with_lock_check_interval(Duration::ZERO),
+            HmsCatalogConfig::new()
+                .with_lock_timeout(Duration::from_millis(10))
+                .with_lock_check_interval(Duration::from_secs(1)),
+        ];
+        for config in invalid {
+            let catalog = HmsCatalog::new(metastore.clone(), file_io.clone());
+            assert!(matches!(
+                catalog.with_config(config),
+                Err(IcebergError::Invalid(_))
+            ));
+        }
+
+        // Commits give up on contended locks after the lock timeout, releasing their lock
+        let config = HmsCatalogConfig::new()
+            .with_warehouse("file:/warehouse/")
+            .with_lock_timeout(Duration::from_millis(20))
+            .with_lock_check_interval(Duration::from_millis(5));
+        assert_eq!(Some("file:/warehouse"), config.warehouse.as_deref());
+        let catalog = HmsCatalog::new(metastore.clone(), file_io)
+            .with_config(config)
+            .unwrap();
+        let table = catalog.load_table(&"db.t".parse().unwrap()).unwrap();
+        metastore.state.lock().unwrap().contended = true;
+        let error = catalog
+            .commit_table(&table, table.metadata().clone())
+            .unwrap_err();
+        assert!(error.to_string().contains("Couldn't lock Table db.t"));
+        assert!(metastore.state.lock().unwrap().locks.is_empty());
+    }
+
+    #[test]
+    fn test_cached_lookups() {
+        let metastore = FakeMetastore::default();
+        let dir = tempfile::tempdir().unwrap();
+        let created = create_table(dir.path());
+        metastore.register("db", "t", created.metadata_location());
+        let catalog = catalog(&metastore, created.file_io().clone())
+            .with_cache_ttl(Duration::from_secs(3600));
+        let calls = || {
+            let state = metastore.state.lock().unwrap();
+            (state.get_table_calls, state.get_all_databases_calls)
+        };
+
+        let table = catalog.load_table(&"db.t".parse().unwrap()).unwrap();
+        catalog.refresh_table(&table).unwrap();
+        assert!(matches!(
+            catalog.load_table(&"db.missing".parse().unwrap()),
+            Err(IcebergError::NotFound(_))
+        ));
+        let error = catalog
+            .load_table(&"db.missing".parse().unwrap())
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("db.missing not found"),
+            "{}",
+            error
+        );
+        assert_eq!((2, 0), calls());
+
+        // Commits invalidate the table, so that the new metadata location is seen right away
+        let committed = catalog
+            .commit_table(&table, table.metadata().clone())
+            .unwrap();
+        let refreshed = catalog.refresh_table(&table).unwrap();
+        assert_eq!(committed.metadata_location(), refreshed.metadata_location());
+        // The commit itself reads the table in the metastore
+        assert_eq!((4, 0), calls());
+
+        // Tables created elsewhere are only seen once invalidated
+        metastore.register("db", "missing", created.metadata_location());
+        assert!(catalog.load_table(&"db.missing".parse().unwrap()).is_err());
+        catalog.invalidate_table(&"db.missing".parse().unwrap());
+        assert!(catalog.load_table(&"db.missing".parse().unwrap()).is_ok());
+
+        catalog.list_namespaces().unwrap();
+        catalog.list_namespaces().unwrap();
+        assert_eq!(1, calls().1);
+        catalog
+            .create_namespace(&"db".parse().unwrap(), HashMap::new())
+            .unwrap();
+        assert_eq!(1, catalog.list_namespaces().unwrap().len());
+        assert_eq!(2, calls().1);
+
+        // Entries expire after the TTL
+        let catalog = HmsCatalog::new(metastore.clone(), created.file_io().clone())
+            .with_cache_ttl(Duration::from_millis(1));
+        catalog.load_table(&"db.t".parse().unwrap()).unwrap();
+        thread::sleep(Duration::from_millis(5));
+        catalog.load_table(&"db.t".parse().unwrap()).unwrap();
+        assert_eq!(7, calls().0);
+    }
+}
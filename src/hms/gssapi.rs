@@ -0,0 +1,361 @@
+// Kerberos security contexts on top of the GSS-API of the system Kerberos library (MIT or
+// Heimdal libgssapi_krb5), loaded when the first context is created so that rustberg doesn't
+// need Kerberos development files to build. Credentials come from the ticket cache of the
+// process, e.g. filled by kinit or from a keytab with KRB5CCNAME and KRB5_CLIENT_KTNAME
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::Arc;
+
+use libloading::Library;
+use once_cell::sync::OnceCell;
+
+use crate::hms::sasl::GssContext;
+
+const LIBRARY_NAMES: &[&str] = &[
+    "libgssapi_krb5.so.2",
+    "libgssapi_krb5.so",
+    "libgssapi_krb5.dylib",
+];
+
+// 1.2.840.113554.1.2.1.4, names such as hive@metastore.example.com
+const NT_HOSTBASED_SERVICE: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x12, 0x01, 0x02, 0x01, 0x04];
+// 1.2.840.113554.1.2.2
+const KRB5_MECHANISM: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x12, 0x01, 0x02, 0x02];
+
+const MUTUAL_FLAG: u32 = 2;
+const INTEGRITY_FLAG: u32 = 32;
+const CONFIDENTIALITY_FLAG: u32 = 16;
+const GSS_CODE: i32 = 1;
+const MECHANISM_CODE: i32 = 2;
+
+#[repr(C)]
+struct Buffer {
+    length: usize,
+    value: *mut c_void,
+}
+
+#[repr(C)]
+struct Oid {
+    length: u32,
+    elements: *mut c_void,
+}
+
+type Name = *mut c_void;
+type Context = *mut c_void;
+
+type ImportName = unsafe extern "C" fn(*mut u32, *mut Buffer, *mut Oid, *mut Name) -> u32;
+type InitSecContext = unsafe extern "C" fn(
+    *mut u32,
+    *mut c_void,
+    *mut Context,
+    Name,
+    *mut Oid,
+    u32,
+    u32,
+    *mut c_void,
+    *mut Buffer,
+    *mut *mut Oid,
+    *mut Buffer,
+    *mut u32,
+    *mut u32,
+) -> u32;
+type Wrap =
+    unsafe extern "C" fn(*mut u32, Context, i32, u32, *mut Buffer, *mut i32, *mut Buffer) -> u32;
+type Unwrap =
+    unsafe extern "C" fn(*mut u32, Context, *mut Buffer, *mut Buffer, *mut i32, *mut u32) -> u32;
+type ReleaseBuffer = unsafe extern "C" fn(*mut u32, *mut Buffer) -> u32;
+type ReleaseName = unsafe extern "C" fn(*mut u32, *mut Name) -> u32;
+type DeleteSecContext = unsafe extern "C" fn(*mut u32, *mut Context, *mut Buffer) -> u32;
+type DisplayStatus =
+    unsafe extern "C" fn(*mut u32, u32, i32, *mut Oid, *mut u32, *mut Buffer) -> u32;
+
+// The functions of the library, valid as long as the library is loaded
+#[derive(Debug)]
+struct GssApi {
+    _library: Library,
+    import_name: ImportName,
+    init_sec_context: InitSecContext,
+    wrap: Wrap,
+    unwrap: Unwrap,
+    release_buffer: ReleaseBuffer,
+    release_name: ReleaseName,
+    delete_sec_context: DeleteSecContext,
+    display_status: DisplayStatus,
+}
+
+static GSS_API: OnceCell<Arc<GssApi>> = OnceCell::new();
+
+fn gss_api() -> thrift::Result<Arc<GssApi>> {
+    GSS_API
+        .get_or_try_init(|| {
+            let library = LIBRARY_NAMES
+                .iter()
+                // Safety: the Kerberos library doesn't run code with preconditions when loaded
+                .find_map(|name| unsafe { Library::new(name) }.ok())
+                .ok_or_else(|| {
+                    kerberos_error(format!(
+                        "Couldn't load the Kerberos library, tried {}",
+                        LIBRARY_NAMES.join(", ")
+                    ))
+                })?;
+            // Safety: the types match the declarations of gssapi.h
+            unsafe {
+                Ok(Arc::new(GssApi {
+                    import_name: symbol(&library, "gss_import_name")?,
+                    init_sec_context: symbol(&library, "gss_init_sec_context")?,
+                    wrap: symbol(&library, "gss_wrap")?,
+                    unwrap: symbol(&library, "gss_unwrap")?,
+                    release_buffer: symbol(&library, "gss_release_buffer")?,
+                    release_name: symbol(&library, "gss_release_name")?,
+                    delete_sec_context: symbol(&library, "gss_delete_sec_context")?,
+                    display_status: symbol(&library, "gss_display_status")?,
+                    _library: library,
+                }))
+            }
+        })
+        .cloned()
+}
+
+// Safety: T must be the type of the function
+unsafe fn symbol<T: Copy>(library: &Library, name: &str) -> thrift::Result<T> {
+    library
+        .get::<T>(name.as_bytes())
+        .map(|symbol| *symbol)
+        .map_err(|e| kerberos_error(format!("Missing {}: {}", name, e)))
+}
+
+fn kerberos_error(message: String) -> thrift::Error {
+    thrift::Error::User(message.into())
+}
+
+fn oid(elements: &'static [u8]) -> Oid {
+    Oid {
+        length: elements.len() as u32,
+        elements: elements.as_ptr() as *mut c_void,
+    }
+}
+
+fn input_buffer(data: &[u8]) -> Buffer {
+    Buffer {
+        length: data.len(),
+        value: data.as_ptr() as *mut c_void,
+    }
+}
+
+fn empty_buffer() -> Buffer {
+    Buffer {
+        length: 0,
+        value: ptr::null_mut(),
+    }
+}
+
+impl GssApi {
+    // Fails on routine and calling errors, returns whether the call completed otherwise
+    fn check(&self, operation: &str, major: u32, minor: u32) -> thrift::Result<bool> {
+        if major & 0xffff_0000 == 0 {
+            return Ok(major & 1 == 0);
+        }
+        let messages = [(major, GSS_CODE), (minor, MECHANISM_CODE)]
+            .into_iter()
+            .filter(|(status, _)| *status != 0)
+            .flat_map(|(status, status_type)| self.status_messages(status, status_type))
+            .collect::<Vec<_>>();
+        Err(kerberos_error(format!(
+            "Kerberos {} failed: {}",
+            operation,
+            messages.join(", ")
+        )))
+    }
+
+    fn status_messages(&self, status: u32, status_type: i32) -> Vec<String> {
+        let mut messages = vec![];
+        let mut message_context = 0;
+        loop {
+            let mut minor = 0;
+            let mut message = empty_buffer();
+            let mut mechanism = oid(KRB5_MECHANISM);
+            // Safety: the output buffer is released once copied
+            let major = unsafe {
+                (self.display_status)(
+                    &mut minor,
+                    status,
+                    status_type,
+                    &mut mechanism,
+                    &mut message_context,
+                    &mut message,
+                )
+            };
+            if major != 0 {
+                break;
+            }
+            messages.push(String::from_utf8_lossy(&self.take(&mut message)).into_owned());
+            if message_context == 0 {
+                break;
+            }
+        }
+        messages
+    }
+
+    // Copies and releases a buffer allocated by the library
+    fn take(&self, buffer: &mut Buffer) -> Vec<u8> {
+        if buffer.value.is_null() {
+            return vec![];
+        }
+        // Safety: the library allocated `length` bytes at `value`
+        let data = unsafe { std::slice::from_raw_parts(buffer.value as *const u8, buffer.length) }
+            .to_vec();
+        let mut minor = 0;
+        unsafe { (self.release_buffer)(&mut minor, buffer) };
+        data
+    }
+}
+
+// Kerberos context with a service, initiated with the default credentials of the process
+#[derive(Debug)]
+pub struct KerberosContext {
+    api: Arc<GssApi>,
+    target: Name,
+    context: Context,
+}
+
+// Safety: the handles are only used through &mut self, GSS-API handles can be used from any
+// thread as long as they aren't used concurrently
+unsafe impl Send for KerberosContext {}
+
+impl KerberosContext {
+    // Context with the service on the host, e.g. hive and the host name of the metastore for
+    // the hive/metastore.example.com@REALM principal
+    pub fn new(service: &str, host: &str) -> thrift::Result<Self> {
+        let api = gss_api()?;
+        let name = format!("{}@{}", service, host);
+        let mut minor = 0;
+        let mut target = ptr::null_mut();
+        let mut name_type = oid(NT_HOSTBASED_SERVICE);
+        // Safety: the name is copied by the library
+        let major = unsafe {
+            (api.import_name)(
+                &mut minor,
+                &mut input_buffer(name.as_bytes()),
+                &mut name_type,
+                &mut target,
+            )
+        };
+        api.check("name import", major, minor)?;
+        Ok(KerberosContext {
+            api,
+            target,
+            context: ptr::null_mut(),
+        })
+    }
+}
+
+impl GssContext for KerberosContext {
+    fn step(&mut self, token: &[u8]) -> thrift::Result<(Vec<u8>, bool)> {
+        let mut minor = 0;
+        let mut mechanism = oid(KRB5_MECHANISM);
+        let mut input = input_buffer(token);
+        let mut output = empty_buffer();
+        // Safety: the context handle is updated in place, the output buffer is released once
+        // copied
+        let major = unsafe {
+            (self.api.init_sec_context)(
+                &mut minor,
+                ptr::null_mut(),
+                &mut self.context,
+                self.target,
+                &mut mechanism,
+                MUTUAL_FLAG | INTEGRITY_FLAG | CONFIDENTIALITY_FLAG,
+                0,
+                ptr::null_mut(),
+                if token.is_empty() {
+                    ptr::null_mut()
+                } else {
+                    &mut input
+                },
+                ptr::null_mut(),
+                &mut output,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        let output = self.api.take(&mut output);
+        let established = self
+            .api
+            .check("security context initialization", major, minor)?;
+        Ok((output, established))
+    }
+
+    fn wrap(&mut self, data: &[u8], encrypt: bool) -> thrift::Result<Vec<u8>> {
+        let mut minor = 0;
+        let mut output = empty_buffer();
+        // Safety: the output buffer is released once copied
+        let major = unsafe {
+            (self.api.wrap)(
+                &mut minor,
+                self.context,
+                encrypt as i32,
+                0,
+                &mut input_buffer(data),
+                ptr::null_mut(),
+                &mut output,
+            )
+        };
+        let output = self.api.take(&mut output);
+        self.api.check("wrap", major, minor)?;
+        Ok(output)
+    }
+
+    fn unwrap(&mut self, data: &[u8]) -> thrift::Result<Vec<u8>> {
+        let mut minor = 0;
+        let mut output = empty_buffer();
+        // Safety: the output buffer is released once copied
+        let major = unsafe {
+            (self.api.unwrap)(
+                &mut minor,
+                self.context,
+                &mut input_buffer(data),
+                &mut output,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        let output = self.api.take(&mut output);
+        self.api.check("unwrap", major, minor)?;
+        Ok(output)
+    }
+}
+
+impl Drop for KerberosContext {
+    fn drop(&mut self) {
+        let mut minor = 0;
+        // Safety: the handles aren't used afterwards
+        unsafe {
+            if !self.context.is_null() {
+                (self.api.delete_sec_context)(&mut minor, &mut self.context, ptr::null_mut());
+            }
+            (self.api.release_name)(&mut minor, &mut self.target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kerberos_context_without_credentials() {
+        if gss_api().is_err() {
+            // No Kerberos library on this machine
+            return;
+        }
+        // Without credentials for the realm of the made up host, the initialization fails with
+        // the error of the library
+        let mut context = KerberosContext::new("hive", "metastore.example.com").unwrap();
+        let error = context.step(&[]).unwrap_err().to_string();
+        assert!(
+            error.starts_with("Kerberos security context initialization failed: "),
+            "{}",
+            error
+        );
+    }
+}
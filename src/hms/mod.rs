@@ -1,2 +1,95 @@
+pub mod catalog;
+#[allow(clippy::all)]
 mod fb303;
+#[cfg(feature = "kerberos")]
+pub mod gssapi;
+#[allow(clippy::all)]
 pub mod hms_api;
+pub mod retry;
+pub mod sasl;
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use thrift::protocol::{TBinaryInputProtocol, TBinaryOutputProtocol};
+use thrift::transport::{TBufferedReadTransport, TBufferedWriteTransport};
+
+use crate::hms::hms_api::ThriftHiveMetastoreSyncClient;
+use crate::hms::sasl::{negotiate, sasl_transports, PlainMechanism, SaslMechanism};
+
+pub type HmsClient = ThriftHiveMetastoreSyncClient<
+    TBinaryInputProtocol<Box<dyn Read + Send>>,
+    TBinaryOutputProtocol<Box<dyn Write + Send>>,
+>;
+
+// How clients authenticate with the metastore, matching hive.metastore.sasl.enabled
+#[derive(Debug)]
+pub enum HmsAuth {
+    None,
+    // SASL PLAIN, e.g. for metastores behind an LDAP authenticating proxy
+    Plain(PlainMechanism),
+    // SASL GSSAPI with the Kerberos credentials of the process. The service is the first
+    // component of the metastore principal, usually hive, the host its second component
+    #[cfg(feature = "kerberos")]
+    Kerberos {
+        service: String,
+        host: String,
+    },
+    // Any other SASL mechanism
+    Sasl(Box<dyn SaslMechanism>),
+}
+
+// Connects to the metastore at the address, e.g. metastore.example.com:9083
+pub fn connect(address: &str, auth: HmsAuth) -> thrift::Result<HmsClient> {
+    connect_with_timeouts(address, auth, None, None)
+}
+
+// Like connect, failing when connecting or reading a response takes longer than the timeouts
+pub fn connect_with_timeouts(
+    address: &str,
+    auth: HmsAuth,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+) -> thrift::Result<HmsClient> {
+    let address = address.strip_prefix("thrift://").unwrap_or(address);
+    let mut stream = match connect_timeout {
+        Some(timeout) => {
+            let mut connected = None;
+            for address in address.to_socket_addrs()? {
+                connected = Some(TcpStream::connect_timeout(&address, timeout));
+                if let Some(Ok(_)) = connected {
+                    break;
+                }
+            }
+            connected.ok_or_else(|| {
+                thrift::Error::User(format!("Couldn't resolve {}", address).into())
+            })??
+        }
+        None => TcpStream::connect(address)?,
+    };
+    stream.set_read_timeout(read_timeout)?;
+    let mut mechanism: Box<dyn SaslMechanism> = match auth {
+        HmsAuth::None => {
+            let read = TBufferedReadTransport::new(stream.try_clone()?);
+            let write = TBufferedWriteTransport::new(stream);
+            return Ok(client(Box::new(read), Box::new(write)));
+        }
+        HmsAuth::Plain(mechanism) => Box::new(mechanism),
+        #[cfg(feature = "kerberos")]
+        HmsAuth::Kerberos { service, host } => Box::new(sasl::GssapiMechanism::new(
+            gssapi::KerberosContext::new(&service, &host)?,
+        )),
+        HmsAuth::Sasl(mechanism) => mechanism,
+    };
+    negotiate(&mut stream, mechanism.as_mut())?;
+    let (read, write) = sasl_transports(stream.try_clone()?, stream, mechanism);
+    Ok(client(Box::new(read), Box::new(write)))
+}
+
+fn client(read: Box<dyn Read + Send>, write: Box<dyn Write + Send>) -> HmsClient {
+    ThriftHiveMetastoreSyncClient::new(
+        TBinaryInputProtocol::new(read, true),
+        TBinaryOutputProtocol::new(write, true),
+    )
+}
@@ -0,0 +1,271 @@
+// Metastore client reconnecting after lost connections and retrying lookups, see RetryPolicy. A
+// thrift connection is unusable once a call failed in transport, the response of a timed out call
+// could arrive in place of the next one, so that the connection is replaced before the next call.
+// Calls changing the metastore aren't retried, their outcome is unknown when they fail without a
+// response
+use crate::hms::catalog::{is_transient_thrift, MetastoreClient};
+use crate::hms::hms_api::{self, Database, LockRequest, LockResponse};
+use crate::iceberg::retry::RetryPolicy;
+
+type Connect = Box<dyn FnMut() -> thrift::Result<Box<dyn MetastoreClient>> + Send>;
+
+pub struct RetryingMetastore {
+    connect: Connect,
+    client: Option<Box<dyn MetastoreClient>>,
+    policy: RetryPolicy,
+}
+
+impl RetryingMetastore {
+    // Connects with `connect`, retried as calls are
+    pub fn connect(
+        connect: impl FnMut() -> thrift::Result<Box<dyn MetastoreClient>> + Send + 'static,
+        policy: RetryPolicy,
+    ) -> thrift::Result<Self> {
+        let mut metastore = RetryingMetastore {
+            connect: Box::new(connect),
+            client: None,
+            policy,
+        };
+        metastore.call("connection", true, |_| Ok(()))?;
+        Ok(metastore)
+    }
+
+    fn call<T>(
+        &mut self,
+        operation: &str,
+        retry: bool,
+        mut f: impl FnMut(&mut dyn MetastoreClient) -> thrift::Result<T>,
+    ) -> thrift::Result<T> {
+        let mut attempt = || {
+            let client = match &mut self.client {
+                Some(client) => client,
+                None => self.client.insert((self.connect)()?),
+            };
+            let result = f(client.as_mut());
+            if result.as_ref().is_err_and(is_transient_thrift) {
+                self.client = None;
+            }
+            result
+        };
+        match retry {
+            true => {
+                let policy = self.policy.clone();
+                policy.run_with(operation, is_transient_thrift, attempt)
+            }
+            false => attempt(),
+        }
+    }
+}
+
+impl MetastoreClient for RetryingMetastore {
+    fn get_all_databases(&mut self) -> thrift::Result<Vec<String>> {
+        self.call("listing of databases", true, |c| c.get_all_databases())
+    }
+
+    fn get_database(&mut self, name: &str) -> thrift::Result<Database> {
+        self.call("lookup of a database", true, |c| c.get_database(name))
+    }
+
+    fn create_database(&mut self, database: Database) -> thrift::Result<()> {
+        self.call("database creation", false, |c| {
+            c.create_database(database.clone())
+        })
+    }
+
+    fn alter_database(&mut self, name: &str, database: Database) -> thrift::Result<()> {
+        self.call("database change", false, |c| {
+            c.alter_database(name, database.clone())
+        })
+    }
+
+    fn drop_database(&mut self, name: &str) -> thrift::Result<()> {
+        self.call("database drop", false, |c| c.drop_database(name))
+    }
+
+    fn get_table(&mut self, database: &str, name: &str) -> thrift::Result<hms_api::Table> {
+        self.call("lookup of a table", true, |c| c.get_table(database, name))
+    }
+
+    fn get_all_tables(&mut self, database: &str) -> thrift::Result<Vec<String>> {
+        self.call("listing of tables", true, |c| c.get_all_tables(database))
+    }
+
+    fn get_table_objects_by_name(
+        &mut self,
+        database: &str,
+        names: Vec<String>,
+    ) -> thrift::Result<Vec<hms_api::Table>> {
+        self.call("lookup of tables", true, |c| {
+            c.get_table_objects_by_name(database, names.clone())
+        })
+    }
+
+    fn create_table(&mut self, table: hms_api::Table) -> thrift::Result<()> {
+        self.call("table creation", false, |c| c.create_table(table.clone()))
+    }
+
+    fn alter_table(
+        &mut self,
+        database: &str,
+        name: &str,
+        table: hms_api::Table,
+    ) -> thrift::Result<()> {
+        self.call("table change", false, |c| {
+            c.alter_table(database, name, table.clone())
+        })
+    }
+
+    fn drop_table(&mut self, database: &str, name: &str) -> thrift::Result<()> {
+        self.call("table drop", false, |c| c.drop_table(database, name))
+    }
+
+    fn lock(&mut self, request: LockRequest) -> thrift::Result<LockResponse> {
+        self.call("lock", false, |c| c.lock(request.clone()))
+    }
+
+    fn check_lock(&mut self, lock_id: i64) -> thrift::Result<LockResponse> {
+        self.call("lock check", true, |c| c.check_lock(lock_id))
+    }
+
+    fn unlock(&mut self, lock_id: i64) -> thrift::Result<()> {
+        self.call("unlock", false, |c| c.unlock(lock_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use thrift::{TransportError, TransportErrorKind};
+
+    use super::*;
+
+    // Client counting its calls, whose connection drops after the given number of calls
+    struct Connection {
+        calls: Arc<Mutex<Vec<String>>>,
+        remaining: usize,
+    }
+
+    impl Connection {
+        fn call(&mut self, name: &str) -> thrift::Result<()> {
+            if self.remaining == 0 {
+                return Err(thrift::Error::Transport(TransportError::new(
+                    TransportErrorKind::EndOfFile,
+                    "Connection closed",
+                )));
+            }
+            self.remaining -= 1;
+            self.calls.lock().unwrap().push(name.to_string());
+            Ok(())
+        }
+
+        // Records calls the tests don't expect, which fail without being retried
+        fn unexpected<T>(&mut self, name: &str) -> thrift::Result<T> {
+            self.call(name)?;
+            Err(thrift::Error::from(format!("unexpected call to {}", name)))
+        }
+    }
+
+    impl MetastoreClient for Connection {
+        fn get_all_databases(&mut self) -> thrift::Result<Vec<String>> {
+            self.call("get_all_databases")?;
+            Ok(vec!["db".to_string()])
+        }
+
+        fn get_database(&mut self, _name: &str) -> thrift::Result<Database> {
+            self.unexpected("get_database")
+        }
+
+        fn create_database(&mut self, _database: Database) -> thrift::Result<()> {
+            self.call("create_database")
+        }
+
+        fn alter_database(&mut self, _name: &str, _database: Database) -> thrift::Result<()> {
+            self.unexpected("alter_database")
+        }
+
+        fn drop_database(&mut self, _name: &str) -> thrift::Result<()> {
+            self.unexpected("drop_database")
+        }
+
+        fn get_table(&mut self, _database: &str, _name: &str) -> thrift::Result<hms_api::Table> {
+            self.unexpected("get_table")
+        }
+
+        fn get_all_tables(&mut self, _database: &str) -> thrift::Result<Vec<String>> {
+            self.unexpected("get_all_tables")
+        }
+
+        fn get_table_objects_by_name(
+            &mut self,
+            _database: &str,
+            _names: Vec<String>,
+        ) -> thrift::Result<Vec<hms_api::Table>> {
+            self.unexpected("get_table_objects_by_name")
+        }
+
+        fn create_table(&mut self, _table: hms_api::Table) -> thrift::Result<()> {
+            self.unexpected("create_table")
+        }
+
+        fn alter_table(
+            &mut self,
+            _database: &str,
+            _name: &str,
+            _table: hms_api::Table,
+        ) -> thrift::Result<()> {
+            self.unexpected("alter_table")
+        }
+
+        fn drop_table(&mut self, _database: &str, _name: &str) -> thrift::Result<()> {
+            self.unexpected("drop_table")
+        }
+
+        fn lock(&mut self, _request: LockRequest) -> thrift::Result<LockResponse> {
+            self.unexpected("lock")
+        }
+
+        fn check_lock(&mut self, _lock_id: i64) -> thrift::Result<LockResponse> {
+            self.unexpected("check_lock")
+        }
+
+        fn unlock(&mut self, _lock_id: i64) -> thrift::Result<()> {
+            self.unexpected("unlock")
+        }
+    }
+
+    #[test]
+    fn test_reconnects_and_retries_lookups() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let connections = Arc::new(Mutex::new(0));
+        let connect = {
+            let (calls, connections) = (calls.clone(), connections.clone());
+            move || -> thrift::Result<Box<dyn MetastoreClient>> {
+                *connections.lock().unwrap() += 1;
+                Ok(Box::new(Connection {
+                    calls: calls.clone(),
+                    remaining: 1,
+                }))
+            }
+        };
+        let policy = RetryPolicy::new()
+            .with_max_attempts(2)
+            .with_initial_backoff(Duration::from_millis(1));
+        let mut metastore = RetryingMetastore::connect(connect, policy).unwrap();
+        assert_eq!(1, *connections.lock().unwrap());
+
+        // Each connection serves one call, lookups are retried on a new connection
+        assert_eq!(vec!["db"], metastore.get_all_databases().unwrap());
+        assert_eq!(vec!["db"], metastore.get_all_databases().unwrap());
+        assert_eq!(2, *connections.lock().unwrap());
+        assert!(metastore.create_database(Database::default()).is_err());
+        assert_eq!(2, *connections.lock().unwrap());
+        metastore.create_database(Database::default()).unwrap();
+        assert_eq!(3, *connections.lock().unwrap());
+        assert_eq!(
+            vec!["get_all_databases", "get_all_databases", "create_database"],
+            *calls.lock().unwrap()
+        );
+    }
+}
@@ -0,0 +1,506 @@
+// SASL authentication of Thrift connections, as done by TSaslClientTransport of the Java Thrift
+// library. Metastores with hive.metastore.sasl.enabled expect it, with the GSSAPI mechanism
+// for Kerberos or PLAIN for username/password authentication.
+//
+// The client starts with a negotiation: messages of a status byte, a 4 bytes big endian length
+// and a payload, exchanged until both sides completed the mechanism. Thrift messages are then
+// sent in frames of a 4 bytes big endian length and a payload, wrapped by the mechanism when it
+// negotiated an integrity or confidentiality layer
+use std::fmt::Debug;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+
+const START: u8 = 1;
+const OK: u8 = 2;
+const BAD: u8 = 3;
+const ERROR: u8 = 4;
+const COMPLETE: u8 = 5;
+
+// Larger negotiation messages and frames are rejected instead of being allocated
+const MAX_MESSAGE_SIZE: usize = 256 * 1024 * 1024;
+
+// Client side of a SASL mechanism
+pub trait SaslMechanism: Debug + Send {
+    // Name sent to the server, e.g. GSSAPI
+    fn name(&self) -> &str;
+
+    // Response to a challenge of the server. Called with an empty challenge for the initial
+    // response
+    fn evaluate_challenge(&mut self, challenge: &[u8]) -> thrift::Result<Vec<u8>>;
+
+    fn is_complete(&self) -> bool;
+
+    // Protects the frames sent once the negotiation completed, if the mechanism negotiated a
+    // security layer
+    fn wrap(&mut self, data: &[u8]) -> thrift::Result<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+
+    fn unwrap(&mut self, data: &[u8]) -> thrift::Result<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+}
+
+// The PLAIN mechanism of RFC 4616, sending the credentials in clear
+#[derive(Clone)]
+pub struct PlainMechanism {
+    authorization_id: String,
+    username: String,
+    password: String,
+    complete: bool,
+}
+
+impl PlainMechanism {
+    pub fn new(username: &str, password: &str) -> Self {
+        PlainMechanism {
+            authorization_id: String::new(),
+            username: username.to_string(),
+            password: password.to_string(),
+            complete: false,
+        }
+    }
+
+    // Acts as another user than the authenticated one, if the server allows it
+    pub fn with_authorization_id(mut self, authorization_id: &str) -> Self {
+        self.authorization_id = authorization_id.to_string();
+        self
+    }
+}
+
+// Keeps the password out of logs
+impl Debug for PlainMechanism {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PlainMechanism")
+            .field("authorization_id", &self.authorization_id)
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SaslMechanism for PlainMechanism {
+    fn name(&self) -> &str {
+        "PLAIN"
+    }
+
+    fn evaluate_challenge(&mut self, _challenge: &[u8]) -> thrift::Result<Vec<u8>> {
+        self.complete = true;
+        Ok([
+            self.authorization_id.as_bytes(),
+            self.username.as_bytes(),
+            self.password.as_bytes(),
+        ]
+        .join(&0))
+    }
+
+    fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
+
+// A GSS-API security context being established with a service, e.g. a Kerberos context, see
+// hms::gssapi for an implementation on top of the system Kerberos library
+pub trait GssContext: Debug + Send {
+    // Processes a token of the server, empty for the first call, and returns the token to send
+    // back along with whether the context is established
+    fn step(&mut self, token: &[u8]) -> thrift::Result<(Vec<u8>, bool)>;
+
+    fn wrap(&mut self, data: &[u8], encrypt: bool) -> thrift::Result<Vec<u8>>;
+
+    fn unwrap(&mut self, data: &[u8]) -> thrift::Result<Vec<u8>>;
+}
+
+// Security layers of the GSSAPI mechanism
+const NO_SECURITY_LAYER: u8 = 1;
+const INTEGRITY_LAYER: u8 = 2;
+const CONFIDENTIALITY_LAYER: u8 = 4;
+
+// The GSSAPI mechanism of RFC 4752, used for Kerberos. Once the context is established, the
+// server offers security layers and the client picks the weakest one offered, which is no layer
+// unless the metastore requires integrity or confidentiality (hadoop.rpc.protection)
+#[derive(Debug)]
+pub struct GssapiMechanism<C: GssContext> {
+    context: C,
+    established: bool,
+    // The security layer picked by the client once complete
+    layer: Option<u8>,
+}
+
+impl<C: GssContext> GssapiMechanism<C> {
+    pub fn new(context: C) -> Self {
+        GssapiMechanism {
+            context,
+            established: false,
+            layer: None,
+        }
+    }
+}
+
+impl<C: GssContext> SaslMechanism for GssapiMechanism<C> {
+    fn name(&self) -> &str {
+        "GSSAPI"
+    }
+
+    fn evaluate_challenge(&mut self, challenge: &[u8]) -> thrift::Result<Vec<u8>> {
+        if !self.established {
+            let (token, established) = self.context.step(challenge)?;
+            self.established = established;
+            return Ok(token);
+        }
+
+        // The server offers security layers and its maximum message size
+        let offer = self.context.unwrap(challenge)?;
+        let &[layers, ref max_size @ ..] = offer.as_slice() else {
+            return Err(sasl_error("Invalid GSSAPI security layer offer"));
+        };
+        let layer = [NO_SECURITY_LAYER, INTEGRITY_LAYER, CONFIDENTIALITY_LAYER]
+            .into_iter()
+            .find(|layer| layers & layer != 0)
+            .ok_or_else(|| sasl_error("No GSSAPI security layer offered"))?;
+        // The maximum size is 0 without security layer
+        let max_size = match layer {
+            NO_SECURITY_LAYER => [0; 3],
+            _ => max_size
+                .try_into()
+                .map_err(|_| sasl_error("Invalid GSSAPI security layer offer"))?,
+        };
+        self.layer = Some(layer);
+        self.context
+            .wrap(&[&[layer], &max_size[..]].concat(), false)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.layer.is_some()
+    }
+
+    fn wrap(&mut self, data: &[u8]) -> thrift::Result<Vec<u8>> {
+        match self.layer {
+            Some(INTEGRITY_LAYER) => self.context.wrap(data, false),
+            Some(CONFIDENTIALITY_LAYER) => self.context.wrap(data, true),
+            _ => Ok(data.to_vec()),
+        }
+    }
+
+    fn unwrap(&mut self, data: &[u8]) -> thrift::Result<Vec<u8>> {
+        match self.layer {
+            Some(INTEGRITY_LAYER | CONFIDENTIALITY_LAYER) => self.context.unwrap(data),
+            _ => Ok(data.to_vec()),
+        }
+    }
+}
+
+// Authenticates the connection. The mechanism must be used for the transports of the connection
+// afterwards, see sasl_transports
+pub fn negotiate<S: Read + Write>(
+    channel: &mut S,
+    mechanism: &mut dyn SaslMechanism,
+) -> thrift::Result<()> {
+    send_message(channel, START, mechanism.name().as_bytes())?;
+    let response = mechanism.evaluate_challenge(&[])?;
+    let status = if mechanism.is_complete() {
+        COMPLETE
+    } else {
+        OK
+    };
+    send_message(channel, status, &response)?;
+
+    let mut status = OK;
+    while !mechanism.is_complete() {
+        let (received, challenge) = receive_message(channel)?;
+        status = received;
+        let response = mechanism.evaluate_challenge(&challenge)?;
+        // The server is done, nothing left to send
+        if status == COMPLETE {
+            continue;
+        }
+        let status = if mechanism.is_complete() {
+            COMPLETE
+        } else {
+            OK
+        };
+        send_message(channel, status, &response)?;
+    }
+    // Wait for the server to acknowledge the last response
+    if status == OK {
+        let (status, _) = receive_message(channel)?;
+        if status != COMPLETE {
+            return Err(sasl_error(
+                "The server didn't complete the SASL negotiation",
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn send_message(channel: &mut impl Write, status: u8, payload: &[u8]) -> thrift::Result<()> {
+    let mut message = Vec::with_capacity(5 + payload.len());
+    message.push(status);
+    message.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    message.extend_from_slice(payload);
+    channel.write_all(&message)?;
+    channel.flush()?;
+    Ok(())
+}
+
+fn receive_message(channel: &mut impl Read) -> thrift::Result<(u8, Vec<u8>)> {
+    let mut header = [0; 5];
+    channel.read_exact(&mut header)?;
+    let [status, length @ ..] = header;
+    let payload = read_payload(channel, u32::from_be_bytes(length))?;
+    match status {
+        OK | COMPLETE => Ok((status, payload)),
+        BAD | ERROR => Err(sasl_error(&format!(
+            "SASL negotiation failed: {}",
+            String::from_utf8_lossy(&payload)
+        ))),
+        _ => Err(sasl_error(&format!(
+            "Invalid SASL negotiation status {}",
+            status
+        ))),
+    }
+}
+
+fn read_payload(channel: &mut impl Read, length: u32) -> thrift::Result<Vec<u8>> {
+    let length = length as usize;
+    if length > MAX_MESSAGE_SIZE {
+        return Err(sasl_error(&format!(
+            "SASL message of {} bytes is too large",
+            length
+        )));
+    }
+    let mut payload = vec![0; length];
+    channel.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+// Transport errors of thrift only display their kind
+fn sasl_error(message: &str) -> thrift::Error {
+    thrift::Error::User(message.into())
+}
+
+fn io_error(e: thrift::Error) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+// Reads the frames of an authenticated connection
+#[derive(Debug)]
+pub struct SaslReadTransport<R> {
+    channel: R,
+    mechanism: Arc<Mutex<Box<dyn SaslMechanism>>>,
+    frame: Vec<u8>,
+    position: usize,
+}
+
+// Buffers writes to an authenticated connection, a frame is sent on every flush
+#[derive(Debug)]
+pub struct SaslWriteTransport<W> {
+    channel: W,
+    mechanism: Arc<Mutex<Box<dyn SaslMechanism>>>,
+    buffer: Vec<u8>,
+}
+
+// Transports over the two halves of a connection authenticated with the mechanism
+pub fn sasl_transports<R: Read, W: Write>(
+    read: R,
+    write: W,
+    mechanism: Box<dyn SaslMechanism>,
+) -> (SaslReadTransport<R>, SaslWriteTransport<W>) {
+    let mechanism = Arc::new(Mutex::new(mechanism));
+    (
+        SaslReadTransport {
+            channel: read,
+            mechanism: mechanism.clone(),
+            frame: vec![],
+            position: 0,
+        },
+        SaslWriteTransport {
+            channel: write,
+            mechanism,
+            buffer: vec![],
+        },
+    )
+}
+
+impl<R: Read> Read for SaslReadTransport<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.frame.len() {
+            let mut length = [0; 4];
+            self.channel.read_exact(&mut length)?;
+            let frame =
+                read_payload(&mut self.channel, u32::from_be_bytes(length)).map_err(io_error)?;
+            self.frame = self
+                .mechanism
+                .lock()
+                .unwrap()
+                .unwrap(&frame)
+                .map_err(io_error)?;
+            self.position = 0;
+        }
+        let read = buf.len().min(self.frame.len() - self.position);
+        buf[..read].copy_from_slice(&self.frame[self.position..self.position + read]);
+        self.position += read;
+        Ok(read)
+    }
+}
+
+impl<W: Write> Write for SaslWriteTransport<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let frame = self
+                .mechanism
+                .lock()
+                .unwrap()
+                .wrap(&self.buffer)
+                .map_err(io_error)?;
+            self.buffer.clear();
+            self.channel
+                .write_all(&(frame.len() as u32).to_be_bytes())?;
+            self.channel.write_all(&frame)?;
+        }
+        self.channel.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{TcpListener, TcpStream};
+
+    use super::*;
+
+    // Context "encrypting" by reversing the data, established after 2 steps
+    #[derive(Debug, Default)]
+    struct ReversingContext {
+        steps: usize,
+    }
+
+    impl GssContext for ReversingContext {
+        fn step(&mut self, token: &[u8]) -> thrift::Result<(Vec<u8>, bool)> {
+            self.steps += 1;
+            assert_eq!(self.steps == 1, token.is_empty());
+            Ok((
+                format!("token {}", self.steps).into_bytes(),
+                self.steps == 2,
+            ))
+        }
+
+        fn wrap(&mut self, data: &[u8], encrypt: bool) -> thrift::Result<Vec<u8>> {
+            let mut data = data.to_vec();
+            if encrypt {
+                data.reverse();
+            }
+            Ok(data)
+        }
+
+        fn unwrap(&mut self, data: &[u8]) -> thrift::Result<Vec<u8>> {
+            Ok(data.to_vec())
+        }
+    }
+
+    // Server side of a GSSAPI negotiation offering the given security layers
+    fn gssapi_server(stream: &mut TcpStream, layers: u8) -> Vec<Vec<u8>> {
+        let mut received = vec![receive(stream).1, receive(stream).1];
+        send_message(stream, OK, b"challenge").unwrap();
+        received.push(receive(stream).1);
+        send_message(stream, OK, &[layers, 0, 1, 0]).unwrap();
+        received.push(receive(stream).1);
+        send_message(stream, COMPLETE, &[]).unwrap();
+        received
+    }
+
+    // Receives a message of the client, including the START message
+    fn receive(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let mut header = [0; 5];
+        stream.read_exact(&mut header).unwrap();
+        let [status, length @ ..] = header;
+        (
+            status,
+            read_payload(stream, u32::from_be_bytes(length)).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_sasl_negotiation() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let (status, name) = receive(&mut stream);
+            assert_eq!(START, status);
+            let (status, response) = receive(&mut stream);
+            assert_eq!(COMPLETE, status);
+            send_message(&mut stream, COMPLETE, &[]).unwrap();
+            // Echo a frame
+            let mut length = [0; 4];
+            stream.read_exact(&mut length).unwrap();
+            let frame = read_payload(&mut stream, u32::from_be_bytes(length)).unwrap();
+            stream.write_all(&length).unwrap();
+            stream.write_all(&frame).unwrap();
+
+            let (mut stream, _) = listener.accept().unwrap();
+            receive(&mut stream);
+            send_message(&mut stream, BAD, b"Wrong password").unwrap();
+            (name, response)
+        });
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut mechanism = PlainMechanism::new("user", "secret");
+        negotiate(&mut stream, &mut mechanism).unwrap();
+        let (mut read, mut write) = sasl_transports(
+            stream.try_clone().unwrap(),
+            stream,
+            Box::new(mechanism.clone()),
+        );
+        write.write_all(b"hello ").unwrap();
+        write.write_all(b"metastore").unwrap();
+        write.flush().unwrap();
+        let mut echo = [0; 15];
+        read.read_exact(&mut echo).unwrap();
+        assert_eq!(b"hello metastore", &echo);
+        assert!(!format!("{:?}", mechanism).contains("secret"));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        let error = negotiate(&mut stream, &mut PlainMechanism::new("user", "wrong")).unwrap_err();
+        assert!(error.to_string().contains("Wrong password"));
+        let (name, response) = server.join().unwrap();
+        assert_eq!(b"PLAIN", name.as_slice());
+        assert_eq!(b"\0user\0secret", response.as_slice());
+    }
+
+    #[test]
+    fn test_gssapi_negotiation() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let no_layer = gssapi_server(&mut stream, NO_SECURITY_LAYER | INTEGRITY_LAYER);
+            let (mut stream, _) = listener.accept().unwrap();
+            let confidentiality = gssapi_server(&mut stream, CONFIDENTIALITY_LAYER);
+            (no_layer, confidentiality)
+        });
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut mechanism = GssapiMechanism::new(ReversingContext::default());
+        negotiate(&mut stream, &mut mechanism).unwrap();
+        assert_eq!(Some(NO_SECURITY_LAYER), mechanism.layer);
+        assert_eq!(b"abc".to_vec(), mechanism.wrap(b"abc").unwrap());
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut mechanism = GssapiMechanism::new(ReversingContext::default());
+        negotiate(&mut stream, &mut mechanism).unwrap();
+        assert_eq!(Some(CONFIDENTIALITY_LAYER), mechanism.layer);
+        assert_eq!(b"cba".to_vec(), mechanism.wrap(b"abc").unwrap());
+
+        let (no_layer, confidentiality) = server.join().unwrap();
+        assert_eq!(b"GSSAPI", no_layer[0].as_slice());
+        assert_eq!(b"token 1", no_layer[1].as_slice());
+        assert_eq!(b"token 2", no_layer[2].as_slice());
+        // No layer and no maximum size
+        assert_eq!(vec![NO_SECURITY_LAYER, 0, 0, 0], no_layer[3]);
+        // The maximum size of the server, not encrypted
+        assert_eq!(vec![CONFIDENTIALITY_LAYER, 0, 1, 0], confidentiality[3]);
+    }
+}
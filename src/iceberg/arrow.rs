@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, AsArray, BinaryArray, BooleanArray, Date32Array, Decimal128Array,
+    FixedSizeBinaryArray, Float32Array, Float64Array, Int32Array, Int64Array, StringArray,
+    StructArray, Time64MicrosecondArray, TimestampMicrosecondArray,
+};
+use arrow::datatypes::{
+    DataType, Date32Type, Decimal128Type, Field, Fields, Float32Type, Float64Type, Int32Type,
+    Int64Type, Schema, Time64MicrosecondType, TimeUnit, TimestampMicrosecondType,
+};
+use parquet::arrow::PARQUET_FIELD_ID_META_KEY;
+
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::spec::schema::{IcebergType, PrimitiveType, StructField, StructType};
+use crate::iceberg::spec::values::Literal;
+
+// Timezone used for timestamptz columns. Iceberg stores timestamptz adjusted to UTC
+pub const UTC_TIMEZONE: &str = "+00:00";
+
+// Convert an Iceberg struct (usually the schema of a table) to an Arrow schema. Every arrow
+// field carries the Iceberg field id in its metadata under the same key the parquet crate uses,
+// so that the resulting schema can be handed to the parquet writer as is
+pub fn schema_to_arrow(schema: &StructType) -> Result<Schema> {
+    let fields = schema
+        .fields
+        .iter()
+        .map(struct_field_to_arrow)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Schema::new(fields))
+}
+
+pub fn struct_field_to_arrow(field: &StructField) -> Result<Field> {
+    Ok(with_field_id(
+        Field::new(
+            &field.name,
+            type_to_arrow(&field.field_type)?,
+            !field.required,
+        ),
+        field.id,
+    ))
+}
+
+pub fn type_to_arrow(iceberg_type: &IcebergType) -> Result<DataType> {
+    match iceberg_type {
+        IcebergType::Primitive(primitive) => primitive_to_arrow(primitive),
+        IcebergType::Struct(struct_type) => Ok(DataType::Struct(Fields::from(
+            struct_type
+                .fields
+                .iter()
+                .map(struct_field_to_arrow)
+                .collect::<Result<Vec<_>>>()?,
+        ))),
+        IcebergType::List(list) => {
+            let element = with_field_id(
+                Field::new(
+                    "element",
+                    type_to_arrow(&list.element)?,
+                    !list.element_required,
+                ),
+                list.element_id,
+            );
+            Ok(DataType::List(Arc::new(element)))
+        }
+        IcebergType::Map(map) => {
+            let key = with_field_id(
+                Field::new("key", type_to_arrow(&map.key)?, false),
+                map.key_id,
+            );
+            let value = with_field_id(
+                Field::new("value", type_to_arrow(&map.value)?, !map.value_required),
+                map.value_id,
+            );
+            let entries = Field::new(
+                "key_value",
+                DataType::Struct(Fields::from(vec![key, value])),
+                false,
+            );
+            Ok(DataType::Map(Arc::new(entries), false))
+        }
+    }
+}
+
+pub fn primitive_to_arrow(primitive: &PrimitiveType) -> Result<DataType> {
+    Ok(match primitive {
+        PrimitiveType::Boolean => DataType::Boolean,
+        PrimitiveType::Int => DataType::Int32,
+        PrimitiveType::Long => DataType::Int64,
+        PrimitiveType::Float => DataType::Float32,
+        PrimitiveType::Double => DataType::Float64,
+        PrimitiveType::Decimal { precision, scale } => DataType::Decimal128(
+            *precision,
+            i8::try_from(*scale).map_err(|_| {
+                IcebergError::Unsupported(format!("Decimal scale {} is too large", scale))
+            })?,
+        ),
+        PrimitiveType::Date => DataType::Date32,
+        PrimitiveType::Time => DataType::Time64(TimeUnit::Microsecond),
+        PrimitiveType::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, None),
+        PrimitiveType::Timestamptz => {
+            DataType::Timestamp(TimeUnit::Microsecond, Some(UTC_TIMEZONE.into()))
+        }
+        PrimitiveType::String => DataType::Utf8,
+        PrimitiveType::Uuid => DataType::FixedSizeBinary(16),
+        PrimitiveType::Fixed(length) => {
+            DataType::FixedSizeBinary(i32::try_from(*length).map_err(|_| {
+                IcebergError::Unsupported(format!("Fixed length {} is too large", length))
+            })?)
+        }
+        PrimitiveType::Binary => DataType::Binary,
+        PrimitiveType::TimestampNs => DataType::Timestamp(TimeUnit::Nanosecond, None),
+        PrimitiveType::TimestamptzNs => {
+            DataType::Timestamp(TimeUnit::Nanosecond, Some(UTC_TIMEZONE.into()))
+        }
+        PrimitiveType::Variant => {
+            return Err(IcebergError::Unsupported(
+                "Variant columns in Arrow".to_string(),
+            ))
+        }
+    })
+}
+
+// Value of a row of an array holding a primitive column, None for nulls. The array must have
+// the arrow type the primitive type maps to
+pub fn literal_from_array(
+    array: &dyn Array,
+    row: usize,
+    primitive: &PrimitiveType,
+) -> Result<Option<Literal>> {
+    if array.is_null(row) {
+        return Ok(None);
+    }
+    if array.data_type() != &primitive_to_arrow(primitive)? {
+        return Err(IcebergError::Invalid(format!(
+            "Arrow type {} doesn't match Iceberg type {:?}",
+            array.data_type(),
+            primitive
+        )));
+    }
+    Ok(Some(match primitive {
+        PrimitiveType::Boolean => Literal::Boolean(array.as_boolean().value(row)),
+        PrimitiveType::Int => Literal::Int(array.as_primitive::<Int32Type>().value(row)),
+        PrimitiveType::Long => Literal::Long(array.as_primitive::<Int64Type>().value(row)),
+        PrimitiveType::Float => Literal::Float(array.as_primitive::<Float32Type>().value(row)),
+        PrimitiveType::Double => Literal::Double(array.as_primitive::<Float64Type>().value(row)),
+        PrimitiveType::Decimal { .. } => {
+            Literal::Decimal(array.as_primitive::<Decimal128Type>().value(row))
+        }
+        PrimitiveType::Date => Literal::Date(array.as_primitive::<Date32Type>().value(row)),
+        PrimitiveType::Time => {
+            Literal::Time(array.as_primitive::<Time64MicrosecondType>().value(row))
+        }
+        PrimitiveType::Timestamp => {
+            Literal::Timestamp(array.as_primitive::<TimestampMicrosecondType>().value(row))
+        }
+        PrimitiveType::Timestamptz => {
+            Literal::Timestamptz(array.as_primitive::<TimestampMicrosecondType>().value(row))
+        }
+        PrimitiveType::String => Literal::String(array.as_string::<i32>().value(row).to_string()),
+        PrimitiveType::Uuid => {
+            Literal::try_from_bytes(array.as_fixed_size_binary().value(row), primitive)?
+        }
+        PrimitiveType::Fixed(_) => Literal::Fixed(array.as_fixed_size_binary().value(row).to_vec()),
+        PrimitiveType::Binary => Literal::Binary(array.as_binary::<i32>().value(row).to_vec()),
+        PrimitiveType::TimestampNs | PrimitiveType::TimestamptzNs | PrimitiveType::Variant => {
+            return Err(IcebergError::Unsupported(format!(
+                "Values of type {:?}",
+                primitive
+            )))
+        }
+    }))
+}
+
+// Array of a primitive column holding the given values, the counterpart of literal_from_array.
+// Values must match the primitive type
+pub fn literals_to_array(
+    values: &[Option<Literal>],
+    primitive: &PrimitiveType,
+) -> Result<ArrayRef> {
+    let mismatch = |value: &Literal| {
+        IcebergError::Invalid(format!(
+            "Value {:?} doesn't match Iceberg type {:?}",
+            value, primitive
+        ))
+    };
+    macro_rules! collect {
+        ($array:ty, $($variant:ident)|+) => {
+            Arc::new(
+                values
+                    .iter()
+                    .map(|value| match value {
+                        None => Ok(None),
+                        $(Some(Literal::$variant(v)) => Ok(Some(v.clone())),)+
+                        Some(value) => Err(mismatch(value)),
+                    })
+                    .collect::<Result<$array>>()?,
+            ) as ArrayRef
+        };
+    }
+    Ok(match primitive {
+        PrimitiveType::Boolean => collect!(BooleanArray, Boolean),
+        PrimitiveType::Int => collect!(Int32Array, Int),
+        PrimitiveType::Long => collect!(Int64Array, Long),
+        PrimitiveType::Float => collect!(Float32Array, Float),
+        PrimitiveType::Double => collect!(Float64Array, Double),
+        PrimitiveType::Decimal { .. } => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    None => Ok(None),
+                    Some(Literal::Decimal(v)) => Ok(Some(*v)),
+                    Some(value) => Err(mismatch(value)),
+                })
+                .collect::<Result<Decimal128Array>>()?
+                .with_data_type(primitive_to_arrow(primitive)?),
+        ),
+        PrimitiveType::Date => collect!(Date32Array, Date),
+        PrimitiveType::Time => collect!(Time64MicrosecondArray, Time),
+        PrimitiveType::Timestamp => collect!(TimestampMicrosecondArray, Timestamp),
+        PrimitiveType::Timestamptz => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    None => Ok(None),
+                    Some(Literal::Timestamptz(v)) => Ok(Some(*v)),
+                    Some(value) => Err(mismatch(value)),
+                })
+                .collect::<Result<TimestampMicrosecondArray>>()?
+                .with_timezone(UTC_TIMEZONE),
+        ),
+        PrimitiveType::String => collect!(StringArray, String),
+        PrimitiveType::Binary => collect!(BinaryArray, Binary),
+        PrimitiveType::Uuid | PrimitiveType::Fixed(_) => {
+            let size = match primitive_to_arrow(primitive)? {
+                DataType::FixedSizeBinary(size) => size,
+                _ => unreachable!(),
+            };
+            let bytes = values
+                .iter()
+                .map(|value| match value {
+                    None => Ok(None),
+                    Some(value @ (Literal::Uuid(_) | Literal::Fixed(_))) => {
+                        Ok(Some(value.to_bytes()))
+                    }
+                    Some(value) => Err(mismatch(value)),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Arc::new(FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+                bytes.into_iter(),
+                size,
+            )?)
+        }
+        PrimitiveType::TimestampNs | PrimitiveType::TimestamptzNs | PrimitiveType::Variant => {
+            return Err(IcebergError::Unsupported(format!(
+                "Values of type {:?}",
+                primitive
+            )))
+        }
+    })
+}
+
+// Struct array of partition tuples of the given partition type, the values of a tuple being in
+// the order of its fields. Missing values are null
+pub fn partitions_to_array(
+    partitions: &[&[Option<Literal>]],
+    partition_type: &StructType,
+) -> Result<StructArray> {
+    let DataType::Struct(fields) = type_to_arrow(&IcebergType::Struct(partition_type.clone()))?
+    else {
+        unreachable!()
+    };
+    if fields.is_empty() {
+        return Ok(StructArray::new_empty_fields(partitions.len(), None));
+    }
+    let columns = partition_type
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let IcebergType::Primitive(primitive) = &field.field_type else {
+                return Err(IcebergError::Unsupported(format!(
+                    "Partition field {} of type {:?}",
+                    field.name, field.field_type
+                )));
+            };
+            let values: Vec<Option<Literal>> = partitions
+                .iter()
+                .map(|partition| partition.get(index).cloned().flatten())
+                .collect();
+            literals_to_array(&values, primitive)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(StructArray::try_new(fields, columns, None)?)
+}
+
+// Iceberg field id recorded in the metadata of an arrow field, if any
+pub fn field_id(field: &Field) -> Option<i32> {
+    field
+        .metadata()
+        .get(PARQUET_FIELD_ID_META_KEY)
+        .and_then(|id| id.parse::<i32>().ok())
+}
+
+fn with_field_id(field: Field, id: i32) -> Field {
+    field.with_metadata(HashMap::from([(
+        PARQUET_FIELD_ID_META_KEY.to_string(),
+        id.to_string(),
+    )]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::spec::schema::{ListType, MapType};
+
+    fn field(id: i32, name: &str, required: bool, field_type: IcebergType) -> StructField {
+        StructField {
+            id,
+            name: name.to_string(),
+            required,
+            field_type,
+            doc: None,
+            initial_default: None,
+            write_default: None,
+        }
+    }
+
+    #[test]
+    fn test_schema_to_arrow_carries_field_ids() {
+        let schema = StructType {
+            fields: vec![
+                field(1, "id", true, IcebergType::Primitive(PrimitiveType::Long)),
+                field(
+                    2,
+                    "tags",
+                    false,
+                    IcebergType::List(ListType {
+                        element_id: 3,
+                        element_required: true,
+                        element: Box::new(IcebergType::Primitive(PrimitiveType::String)),
+                    }),
+                ),
+                field(
+                    4,
+                    "props",
+                    false,
+                    IcebergType::Map(MapType {
+                        key_id: 5,
+                        key: Box::new(IcebergType::Primitive(PrimitiveType::String)),
+                        value_id: 6,
+                        value_required: false,
+                        value: Box::new(IcebergType::Primitive(PrimitiveType::Decimal {
+                            precision: 10,
+                            scale: 2,
+                        })),
+                    }),
+                ),
+            ],
+        };
+
+        let arrow_schema = schema_to_arrow(&schema).unwrap();
+        assert_eq!(3, arrow_schema.fields().len());
+        assert_eq!(Some(1), field_id(arrow_schema.field(0)));
+        assert!(!arrow_schema.field(0).is_nullable());
+        assert_eq!(&DataType::Int64, arrow_schema.field(0).data_type());
+
+        match arrow_schema.field(1).data_type() {
+            DataType::List(element) => {
+                assert_eq!(Some(3), field_id(element));
+                assert!(!element.is_nullable());
+            }
+            other => panic!("Unexpected type {:?}", other),
+        }
+
+        match arrow_schema.field(2).data_type() {
+            DataType::Map(entries, _) => match entries.data_type() {
+                DataType::Struct(fields) => {
+                    assert_eq!(Some(5), field_id(&fields[0]));
+                    assert_eq!(Some(6), field_id(&fields[1]));
+                    assert_eq!(&DataType::Decimal128(10, 2), fields[1].data_type());
+                }
+                other => panic!("Unexpected type {:?}", other),
+            },
+            other => panic!("Unexpected type {:?}", other),
+        }
+    }
+}
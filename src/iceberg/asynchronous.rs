@@ -0,0 +1,252 @@
+// Async variants of the catalog, FileIO and scan APIs, for services running on tokio. Metadata,
+// manifests and data files are read with blocking IO, which these variants run on the blocking
+// thread pool of the runtime rather than on its async worker threads. Synchronous catalogs and
+// FileIOs are exposed as async ones through BlockingCatalog and BlockingFileIO
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use arrow::record_batch::RecordBatch;
+use bytes::Bytes;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::ident::TableIdent;
+use crate::iceberg::io::{FileIO, FileInfo};
+use crate::iceberg::scan::{ScanPlan, TableScan};
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+use crate::iceberg::table::Table;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+pub type RecordBatchStream = Pin<Box<dyn Stream<Item = Result<RecordBatch>> + Send>>;
+
+// Number of record batches read ahead of the consumer of a stream
+const STREAM_BUFFERED_BATCHES: usize = 2;
+
+// Async counterpart of FileIO
+pub trait AsyncFileIO: Send + Sync {
+    fn read<'a>(&'a self, location: &'a str) -> BoxFuture<'a, Bytes>;
+
+    fn write<'a>(&'a self, location: &'a str, data: Bytes) -> BoxFuture<'a, ()>;
+
+    fn exists<'a>(&'a self, location: &'a str) -> BoxFuture<'a, bool>;
+
+    fn delete<'a>(&'a self, location: &'a str) -> BoxFuture<'a, ()>;
+
+    fn list<'a>(&'a self, location: &'a str) -> BoxFuture<'a, Vec<FileInfo>>;
+}
+
+// Async counterpart of IcebergCatalog
+pub trait AsyncCatalog: Send + Sync {
+    fn load_table<'a>(&'a self, ident: &'a TableIdent) -> BoxFuture<'a, Table>;
+
+    fn refresh_table<'a>(&'a self, table: &'a Table) -> BoxFuture<'a, Table> {
+        self.load_table(table.ident())
+    }
+
+    fn commit_table<'a>(
+        &'a self,
+        base: &'a Table,
+        metadata: TableMetadataV2,
+    ) -> BoxFuture<'a, Table>;
+
+    fn drop_table<'a>(&'a self, ident: &'a TableIdent, purge: bool) -> BoxFuture<'a, ()>;
+
+    fn current_time_ms(&self) -> BoxFuture<'_, i64>;
+}
+
+// Runs blocking rustberg code on the blocking thread pool of the runtime
+pub async fn spawn_blocking<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| IcebergError::Invalid(format!("Blocking task failed: {}", e)))?
+}
+
+// An async FileIO running the calls of a FileIO on the blocking thread pool
+#[derive(Debug, Clone)]
+pub struct BlockingFileIO {
+    file_io: Arc<dyn FileIO>,
+}
+
+impl BlockingFileIO {
+    pub fn new(file_io: Arc<dyn FileIO>) -> Self {
+        BlockingFileIO { file_io }
+    }
+
+    fn call<'a, T, F>(&self, location: &str, f: F) -> BoxFuture<'a, T>
+    where
+        F: FnOnce(&dyn FileIO, &str) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let file_io = self.file_io.clone();
+        let location = location.to_string();
+        Box::pin(spawn_blocking(move || f(file_io.as_ref(), &location)))
+    }
+}
+
+impl AsyncFileIO for BlockingFileIO {
+    fn read<'a>(&'a self, location: &'a str) -> BoxFuture<'a, Bytes> {
+        self.call(location, |file_io, location| file_io.read(location))
+    }
+
+    fn write<'a>(&'a self, location: &'a str, data: Bytes) -> BoxFuture<'a, ()> {
+        self.call(location, move |file_io, location| {
+            file_io.write(location, data)
+        })
+    }
+
+    fn exists<'a>(&'a self, location: &'a str) -> BoxFuture<'a, bool> {
+        self.call(location, |file_io, location| file_io.exists(location))
+    }
+
+    fn delete<'a>(&'a self, location: &'a str) -> BoxFuture<'a, ()> {
+        self.call(location, |file_io, location| file_io.delete(location))
+    }
+
+    fn list<'a>(&'a self, location: &'a str) -> BoxFuture<'a, Vec<FileInfo>> {
+        self.call(location, |file_io, location| file_io.list(location))
+    }
+}
+
+// An async catalog running the calls of a catalog on the blocking thread pool
+#[derive(Debug, Clone)]
+pub struct BlockingCatalog {
+    catalog: Arc<dyn IcebergCatalog>,
+}
+
+impl BlockingCatalog {
+    pub fn new(catalog: Arc<dyn IcebergCatalog>) -> Self {
+        BlockingCatalog { catalog }
+    }
+
+    // Runs blocking code with the catalog, e.g. to commit a table operation:
+    //   catalog.run(move |catalog| table.new_append().add_files(files).commit(catalog))
+    pub async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&dyn IcebergCatalog) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let catalog = self.catalog.clone();
+        spawn_blocking(move || f(catalog.as_ref())).await
+    }
+}
+
+impl AsyncCatalog for BlockingCatalog {
+    fn load_table<'a>(&'a self, ident: &'a TableIdent) -> BoxFuture<'a, Table> {
+        let ident = ident.clone();
+        Box::pin(self.run(move |catalog| catalog.load_table(&ident)))
+    }
+
+    fn refresh_table<'a>(&'a self, table: &'a Table) -> BoxFuture<'a, Table> {
+        let table = table.clone();
+        Box::pin(self.run(move |catalog| catalog.refresh_table(&table)))
+    }
+
+    fn commit_table<'a>(
+        &'a self,
+        base: &'a Table,
+        metadata: TableMetadataV2,
+    ) -> BoxFuture<'a, Table> {
+        let base = base.clone();
+        Box::pin(self.run(move |catalog| catalog.commit_table(&base, metadata)))
+    }
+
+    fn drop_table<'a>(&'a self, ident: &'a TableIdent, purge: bool) -> BoxFuture<'a, ()> {
+        let ident = ident.clone();
+        Box::pin(self.run(move |catalog| catalog.drop_table(&ident, purge)))
+    }
+
+    fn current_time_ms(&self) -> BoxFuture<'_, i64> {
+        Box::pin(self.run(|catalog| catalog.current_time_ms()))
+    }
+}
+
+// Plans a scan of the table, configured by `scan`:
+//   plan_files(table, |scan| scan.select(&["id"]).filter(predicate)).await
+pub async fn plan_files<F>(table: Table, scan: F) -> Result<ScanPlan>
+where
+    F: FnOnce(TableScan<'_>) -> TableScan<'_> + Send + 'static,
+{
+    spawn_blocking(move || scan(table.scan()).plan_files()).await
+}
+
+impl ScanPlan {
+    // Streams the record batches of the plan, see to_arrow. Files are read on the blocking
+    // thread pool, a few batches ahead of the consumer
+    pub fn to_arrow_stream(&self) -> RecordBatchStream {
+        let plan = self.clone();
+        let (sender, receiver) = tokio::sync::mpsc::channel(STREAM_BUFFERED_BATCHES);
+        tokio::task::spawn_blocking(move || {
+            let batches = match plan.to_arrow() {
+                Ok(batches) => batches,
+                Err(e) => {
+                    let _ = sender.blocking_send(Err(e));
+                    return;
+                }
+            };
+            for batch in batches {
+                // Stops reading once the stream is dropped
+                if sender.blocking_send(batch).is_err() {
+                    return;
+                }
+            }
+        });
+        Box::pin(ReceiverStream::new(receiver))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_stream::StreamExt;
+
+    use super::*;
+    use crate::iceberg::io::LocalFileIO;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_async_catalog_and_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let test_catalog = Arc::new(TestCatalog::new());
+        let table = test_catalog.create_table("db", "t", dir.path());
+        append_ids(test_catalog.as_ref(), &table, &[1, 2, 3]);
+        let catalog = BlockingCatalog::new(test_catalog);
+
+        let table = catalog.load_table(&"db.t".parse().unwrap()).await.unwrap();
+        let mut metadata = table.metadata().clone();
+        metadata
+            .properties
+            .get_or_insert_with(Default::default)
+            .insert("owner".to_string(), "async".to_string());
+        let table = catalog.commit_table(&table, metadata).await.unwrap();
+        assert_eq!(Some("async"), table.metadata().property("owner"));
+        assert!(catalog
+            .load_table(&"db.missing".parse().unwrap())
+            .await
+            .is_err());
+
+        let plan = plan_files(table.clone(), |scan| scan.select(&["id"]))
+            .await
+            .unwrap();
+        let batches: Vec<RecordBatch> =
+            plan.to_arrow_stream().collect::<Result<_>>().await.unwrap();
+        assert_eq!(3, batches.iter().map(|b| b.num_rows()).sum::<usize>());
+        assert!(batches.iter().all(|b| b.num_columns() == 1));
+
+        let file_io = BlockingFileIO::new(Arc::new(LocalFileIO::new()));
+        let location = format!("file:{}/x/a.json", dir.path().display());
+        assert!(!file_io.exists(&location).await.unwrap());
+        file_io.write(&location, Bytes::from("{}")).await.unwrap();
+        assert_eq!(Bytes::from("{}"), file_io.read(&location).await.unwrap());
+        let prefix = format!("file:{}/x", dir.path().display());
+        assert_eq!(1, file_io.list(&prefix).await.unwrap().len());
+        file_io.delete(&location).await.unwrap();
+        assert!(!file_io.exists(&location).await.unwrap());
+    }
+}
@@ -0,0 +1,353 @@
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+use arrow::array::{
+    new_null_array, ArrayRef, Int64Array, RecordBatch, StringArray, TimestampMicrosecondArray,
+};
+use arrow::datatypes::{DataType, Field, Schema};
+
+use crate::iceberg::arrow::UTC_TIMEZONE;
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::ident::{Namespace, TableIdent};
+use crate::iceberg::operations::current_time_ms;
+use crate::iceberg::spec::schema::{IcebergType, PrimitiveType, StructField, StructType};
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+use crate::iceberg::table::Table;
+use crate::iceberg::view::View;
+use crate::iceberg::writer::partitioned::PartitionedWriter;
+
+// Operations removing data or metadata from tables
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum AuditedOperation {
+    ExpireSnapshots,
+    PurgeTable,
+    RewriteDataFiles,
+    RewriteManifests,
+    DeleteOrphanFiles,
+}
+
+// A destructive operation performed on a table
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditRecord {
+    pub event_time_ms: i64,
+    pub operation: AuditedOperation,
+    pub namespace: String,
+    pub table_name: String,
+    pub principal: Option<String>,
+    // Snapshot committed by the operation, if any
+    pub snapshot_id: Option<i64>,
+    // Free form description, e.g. the number of removed files
+    pub details: Option<String>,
+}
+
+// Receives the records of destructive operations once they succeeded
+pub trait Auditor: Debug + Send + Sync {
+    fn record(&self, records: &[AuditRecord]) -> Result<()>;
+}
+
+// Auditor appending records to an Iceberg table created with TableAuditor::schema(). Every call
+// to record commits a snapshot to the audit table
+#[derive(Debug, Clone)]
+pub struct TableAuditor {
+    catalog: Arc<dyn IcebergCatalog>,
+    table: TableIdent,
+}
+
+// Catalog recording table purges with an auditor
+#[derive(Debug, Clone)]
+pub struct AuditedCatalog {
+    catalog: Arc<dyn IcebergCatalog>,
+    auditor: Arc<dyn Auditor>,
+    principal: Option<String>,
+}
+
+impl AuditedOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditedOperation::ExpireSnapshots => "expire-snapshots",
+            AuditedOperation::PurgeTable => "purge-table",
+            AuditedOperation::RewriteDataFiles => "rewrite-data-files",
+            AuditedOperation::RewriteManifests => "rewrite-manifests",
+            AuditedOperation::DeleteOrphanFiles => "delete-orphan-files",
+        }
+    }
+}
+
+impl fmt::Display for AuditedOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl AuditRecord {
+    pub fn new(operation: AuditedOperation, table: &TableIdent) -> Self {
+        AuditRecord {
+            event_time_ms: current_time_ms(),
+            operation,
+            namespace: table.namespace().to_string(),
+            table_name: table.name().to_string(),
+            principal: None,
+            snapshot_id: None,
+            details: None,
+        }
+    }
+
+    pub fn with_principal(mut self, principal: Option<&str>) -> Self {
+        self.principal = principal.map(str::to_string);
+        self
+    }
+
+    pub fn with_snapshot_id(mut self, snapshot_id: Option<i64>) -> Self {
+        self.snapshot_id = snapshot_id;
+        self
+    }
+
+    pub fn with_details(mut self, details: &str) -> Self {
+        self.details = Some(details.to_string());
+        self
+    }
+}
+
+impl TableAuditor {
+    pub fn new(catalog: Arc<dyn IcebergCatalog>, table: TableIdent) -> Self {
+        TableAuditor { catalog, table }
+    }
+
+    // Schema of audit tables
+    pub fn schema() -> StructType {
+        let field = |id: i32, name: &str, required: bool, primitive: PrimitiveType| StructField {
+            id,
+            name: name.to_string(),
+            required,
+            field_type: IcebergType::Primitive(primitive),
+            doc: None,
+            initial_default: None,
+            write_default: None,
+        };
+        StructType {
+            fields: vec![
+                field(1, "event_time", true, PrimitiveType::Timestamptz),
+                field(2, "operation", true, PrimitiveType::String),
+                field(3, "namespace", true, PrimitiveType::String),
+                field(4, "table_name", true, PrimitiveType::String),
+                field(5, "principal", false, PrimitiveType::String),
+                field(6, "snapshot_id", false, PrimitiveType::Long),
+                field(7, "details", false, PrimitiveType::String),
+            ],
+        }
+    }
+
+    // Builds a batch of the current schema of the audit table, matching columns by name
+    fn batch(metadata: &TableMetadataV2, records: &[AuditRecord]) -> Result<RecordBatch> {
+        let strings = |value: &dyn Fn(&AuditRecord) -> Option<&str>| -> ArrayRef {
+            Arc::new(records.iter().map(value).collect::<StringArray>())
+        };
+        let mut fields = vec![];
+        let mut columns = vec![];
+        for field in &metadata.current_schema()?.schema().fields {
+            let column: ArrayRef = match field.name.as_str() {
+                "event_time" => Arc::new(
+                    records
+                        .iter()
+                        .map(|record| Some(record.event_time_ms * 1000))
+                        .collect::<TimestampMicrosecondArray>()
+                        .with_timezone(UTC_TIMEZONE),
+                ),
+                "operation" => strings(&|record| Some(record.operation.as_str())),
+                "namespace" => strings(&|record| Some(&record.namespace)),
+                "table_name" => strings(&|record| Some(&record.table_name)),
+                "principal" => strings(&|record| record.principal.as_deref()),
+                "snapshot_id" => Arc::new(
+                    records
+                        .iter()
+                        .map(|record| record.snapshot_id)
+                        .collect::<Int64Array>(),
+                ),
+                "details" => strings(&|record| record.details.as_deref()),
+                _ if !field.required => new_null_array(&DataType::Null, records.len()),
+                _ => {
+                    return Err(IcebergError::Invalid(format!(
+                        "Required column {} of the audit table is not an audit record field",
+                        field.name
+                    )))
+                }
+            };
+            fields.push(Field::new(&field.name, column.data_type().clone(), true));
+            columns.push(column);
+        }
+        Ok(RecordBatch::try_new(
+            Arc::new(Schema::new(fields)),
+            columns,
+        )?)
+    }
+}
+
+impl Auditor for TableAuditor {
+    fn record(&self, records: &[AuditRecord]) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let table = self.catalog.load_table(&self.table)?;
+        let mut writer = PartitionedWriter::for_table(&table)?;
+        writer.write(&Self::batch(table.metadata(), records)?)?;
+        table
+            .new_append()
+            .add_files(writer.close()?)
+            .commit(self.catalog.as_ref())?;
+        Ok(())
+    }
+}
+
+impl AuditedCatalog {
+    pub fn new(catalog: Arc<dyn IcebergCatalog>, auditor: Arc<dyn Auditor>) -> Self {
+        AuditedCatalog {
+            catalog,
+            auditor,
+            principal: None,
+        }
+    }
+
+    // Principal stored in the records of the catalog
+    pub fn with_principal(mut self, principal: &str) -> Self {
+        self.principal = Some(principal.to_string());
+        self
+    }
+}
+
+impl IcebergCatalog for AuditedCatalog {
+    fn load_table(&self, ident: &TableIdent) -> Result<Table> {
+        self.catalog.load_table(ident)
+    }
+
+    fn refresh_table(&self, table: &Table) -> Result<Table> {
+        self.catalog.refresh_table(table)
+    }
+
+    fn commit_table(&self, base: &Table, metadata: TableMetadataV2) -> Result<Table> {
+        self.catalog.commit_table(base, metadata)
+    }
+
+    fn list_tables(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        self.catalog.list_tables(namespace)
+    }
+
+    fn load_view(&self, ident: &TableIdent) -> Result<View> {
+        self.catalog.load_view(ident)
+    }
+
+    fn list_views(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        self.catalog.list_views(namespace)
+    }
+
+    fn drop_table(&self, ident: &TableIdent, purge: bool) -> Result<()> {
+        self.catalog.drop_table(ident, purge)?;
+        if purge {
+            self.auditor
+                .record(&[AuditRecord::new(AuditedOperation::PurgeTable, ident)
+                    .with_principal(self.principal.as_deref())])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{Array, AsArray};
+
+    use super::*;
+    use crate::iceberg::test_utils::TestCatalog;
+
+    #[test]
+    fn test_purges_are_recorded_in_audit_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = Arc::new(TestCatalog::new());
+        catalog.create_table("db", "events", &dir.path().join("events"));
+        catalog.create_table("db", "scratch", &dir.path().join("scratch"));
+        catalog.create_table_with_schema(
+            "ops",
+            "audit",
+            &dir.path().join("audit"),
+            TableAuditor::schema(),
+        );
+
+        let auditor = Arc::new(TableAuditor::new(
+            catalog.clone(),
+            "ops.audit".parse().unwrap(),
+        ));
+        let audited =
+            AuditedCatalog::new(catalog.clone(), auditor.clone()).with_principal("ops-bot");
+        audited
+            .drop_table(&"db.scratch".parse().unwrap(), false)
+            .unwrap();
+        audited
+            .drop_table(&"db.events".parse().unwrap(), true)
+            .unwrap();
+        auditor
+            .record(&[AuditRecord::new(
+                AuditedOperation::ExpireSnapshots,
+                &"db.other".parse().unwrap(),
+            )
+            .with_snapshot_id(Some(42))
+            .with_details("expired 3 snapshots")])
+            .unwrap();
+
+        let audit = catalog.load_table(&"ops.audit".parse().unwrap()).unwrap();
+        let batches = audit
+            .scan()
+            .select(&["operation", "table_name", "principal", "snapshot_id"])
+            .plan_files()
+            .unwrap()
+            .to_arrow()
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let mut rows = vec![];
+        for batch in &batches {
+            for row in 0..batch.num_rows() {
+                let principal = batch.column(2).as_string::<i32>();
+                rows.push((
+                    batch.column(0).as_string::<i32>().value(row).to_string(),
+                    batch.column(1).as_string::<i32>().value(row).to_string(),
+                    (!principal.is_null(row)).then(|| principal.value(row).to_string()),
+                    batch
+                        .column(3)
+                        .as_primitive::<arrow::datatypes::Int64Type>()
+                        .is_valid(row),
+                ));
+            }
+        }
+        rows.sort();
+        assert_eq!(
+            vec![
+                (
+                    "expire-snapshots".to_string(),
+                    "other".to_string(),
+                    None,
+                    true
+                ),
+                (
+                    "purge-table".to_string(),
+                    "events".to_string(),
+                    Some("ops-bot".to_string()),
+                    false
+                ),
+            ],
+            rows
+        );
+    }
+
+    #[test]
+    fn test_audit_table_must_have_compatible_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = Arc::new(TestCatalog::new());
+        catalog.create_table("ops", "audit", dir.path());
+        let auditor = TableAuditor::new(catalog, "ops.audit".parse().unwrap());
+        assert!(auditor
+            .record(&[AuditRecord::new(
+                AuditedOperation::PurgeTable,
+                &"db.t".parse().unwrap()
+            )])
+            .is_err());
+    }
+}
@@ -0,0 +1,597 @@
+// Reads Avro data files into Arrow record batches shaped like the (projected) table schema, the
+// Avro counterpart of ParquetReader. Columns are resolved by the field ids of the Avro schema, or
+// by the name mapping of the table for files written without ids, so renamed or reordered
+// columns are read correctly and columns added after the file was written come back as nulls.
+// Avro logical types map to the Iceberg types they encode, see avro_to_schema
+use std::io::Cursor;
+use std::sync::Arc;
+
+use apache_avro::types::Value;
+use arrow::array::{
+    new_null_array, ArrayRef, BinaryArray, BooleanArray, Date32Array, Decimal128Array,
+    FixedSizeBinaryArray, Float32Array, Float64Array, Int32Array, Int64Array, ListArray, MapArray,
+    RecordBatch, RecordBatchOptions, StringArray, StructArray, Time64MicrosecondArray,
+    TimestampMicrosecondArray, TimestampNanosecondArray,
+};
+use arrow::buffer::{NullBuffer, OffsetBuffer};
+use arrow::datatypes::{DataType, Fields, SchemaRef, TimeUnit};
+use bytes::Bytes;
+
+use crate::iceberg::arrow::{field_id, schema_to_arrow};
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::reader::{projected_schema, RecordBatchIter, DEFAULT_BATCH_SIZE};
+use crate::iceberg::spec::avro::{avro_to_schema, avro_to_schema_with_name_mapping};
+use crate::iceberg::spec::name_mapping::NameMapping;
+use crate::iceberg::spec::schema::{IcebergType, StructType};
+
+#[derive(Debug, Clone)]
+pub struct AvroReader {
+    schema: SchemaRef,
+    projection: Option<Vec<i32>>,
+    batch_size: usize,
+    name_mapping: Option<NameMapping>,
+}
+
+impl AvroReader {
+    pub fn try_new(schema: &StructType) -> Result<Self> {
+        Ok(Self::for_arrow_schema(Arc::new(schema_to_arrow(schema)?)))
+    }
+
+    pub(crate) fn for_arrow_schema(schema: SchemaRef) -> Self {
+        AvroReader {
+            schema,
+            projection: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            name_mapping: None,
+        }
+    }
+
+    // Only read the top-level fields with the given ids, in the given order
+    pub fn with_projection(mut self, field_ids: Vec<i32>) -> Self {
+        self.projection = Some(field_ids);
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    // Resolves the fields of files without field ids by the names of the mapping
+    pub fn with_name_mapping(mut self, name_mapping: NameMapping) -> Self {
+        self.name_mapping = Some(name_mapping);
+        self
+    }
+
+    // Schema of the batches produced by this reader
+    pub fn output_schema(&self) -> Result<SchemaRef> {
+        projected_schema(&self.schema, self.projection.as_deref())
+    }
+
+    pub fn read(&self, data: Bytes) -> Result<RecordBatchIter> {
+        let reader = apache_avro::Reader::new(Cursor::new(data))?;
+        let file_schema = match &self.name_mapping {
+            Some(name_mapping) => {
+                avro_to_schema_with_name_mapping(reader.writer_schema(), name_mapping)?
+            }
+            None => avro_to_schema(reader.writer_schema())?,
+        };
+        let output_schema = self.output_schema()?;
+        // Fails early on required columns missing from the file
+        struct_columns(&[], output_schema.fields(), &file_schema)?;
+        Ok(Box::new(AvroBatchIter {
+            reader,
+            output_schema,
+            file_schema,
+            batch_size: self.batch_size,
+        }))
+    }
+}
+
+struct AvroBatchIter {
+    reader: apache_avro::Reader<'static, Cursor<Bytes>>,
+    output_schema: SchemaRef,
+    file_schema: StructType,
+    batch_size: usize,
+}
+
+impl AvroBatchIter {
+    fn batch(&self, records: &[Value]) -> Result<RecordBatch> {
+        let values: Vec<Option<&Value>> = records.iter().map(non_null).collect();
+        let columns = struct_columns(&values, self.output_schema.fields(), &self.file_schema)?;
+        Ok(RecordBatch::try_new_with_options(
+            self.output_schema.clone(),
+            columns,
+            &RecordBatchOptions::new().with_row_count(Some(records.len())),
+        )?)
+    }
+}
+
+impl Iterator for AvroBatchIter {
+    type Item = Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut records = Vec::with_capacity(self.batch_size);
+        for record in self.reader.by_ref().take(self.batch_size) {
+            match record {
+                Ok(record) => records.push(record),
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+        if records.is_empty() {
+            return None;
+        }
+        Some(self.batch(&records))
+    }
+}
+
+// The value, None for nulls
+fn non_null(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Null => None,
+        Value::Union(_, value) => non_null(value),
+        value => Some(value),
+    }
+}
+
+// Columns of the expected fields, out of the values of records of the file type. Records hold
+// their fields in the order of the file schema
+fn struct_columns(
+    values: &[Option<&Value>],
+    fields: &Fields,
+    file_type: &StructType,
+) -> Result<Vec<ArrayRef>> {
+    fields
+        .iter()
+        .map(|field| {
+            let id = field_id(field);
+            let Some(position) = file_type.fields.iter().position(|f| Some(f.id) == id) else {
+                if field.is_nullable() {
+                    return Ok(new_null_array(field.data_type(), values.len()));
+                }
+                return Err(IcebergError::Invalid(format!(
+                    "Required field '{}' not found in data file",
+                    field.name()
+                )));
+            };
+            let children = values
+                .iter()
+                .map(|value| match value {
+                    Some(Value::Record(record)) => {
+                        Ok(record.get(position).and_then(|(_, value)| non_null(value)))
+                    }
+                    Some(value) => Err(mismatch(value, &DataType::Struct(fields.clone()))),
+                    None => Ok(None),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            array(
+                &children,
+                field.data_type(),
+                &file_type.fields[position].field_type,
+            )
+        })
+        .collect()
+}
+
+fn array(
+    values: &[Option<&Value>],
+    data_type: &DataType,
+    file_type: &IcebergType,
+) -> Result<ArrayRef> {
+    let nulls = || NullBuffer::from(values.iter().map(Option::is_some).collect::<Vec<_>>());
+    match (data_type, file_type) {
+        (DataType::Struct(fields), IcebergType::Struct(file_type)) => {
+            let columns = struct_columns(values, fields, file_type)?;
+            Ok(Arc::new(StructArray::try_new(
+                fields.clone(),
+                columns,
+                Some(nulls()),
+            )?))
+        }
+        (DataType::List(element), IcebergType::List(file_type)) => {
+            let mut offsets = vec![0];
+            let mut elements = vec![];
+            for value in values {
+                match value {
+                    Some(Value::Array(items)) => elements.extend(items.iter().map(non_null)),
+                    Some(value) => return Err(mismatch(value, data_type)),
+                    None => {}
+                }
+                offsets.push(elements.len() as i32);
+            }
+            let elements = array(&elements, element.data_type(), &file_type.element)?;
+            Ok(Arc::new(ListArray::try_new(
+                element.clone(),
+                OffsetBuffer::new(offsets.into()),
+                elements,
+                Some(nulls()),
+            )?))
+        }
+        (DataType::Map(entries, sorted), IcebergType::Map(file_type)) => {
+            let DataType::Struct(entry_fields) = entries.data_type() else {
+                return Err(mismatch(&Value::Null, data_type));
+            };
+            let mut offsets = vec![0];
+            // Keys of maps with string keys are not Avro values
+            let mut keys: Vec<Value> = vec![];
+            let mut map_values = vec![];
+            for value in values {
+                match value {
+                    Some(Value::Map(map)) => {
+                        for (key, value) in map {
+                            keys.push(Value::String(key.clone()));
+                            map_values.push(non_null(value));
+                        }
+                    }
+                    // Maps with other keys are arrays of key-value records
+                    Some(Value::Array(entries)) => {
+                        for entry in entries {
+                            match entry {
+                                Value::Record(entry) if entry.len() == 2 => {
+                                    keys.push(entry[0].1.clone());
+                                    map_values.push(non_null(&entry[1].1));
+                                }
+                                entry => return Err(mismatch(entry, data_type)),
+                            }
+                        }
+                    }
+                    Some(value) => return Err(mismatch(value, data_type)),
+                    None => {}
+                }
+                offsets.push(keys.len() as i32);
+            }
+            let keys: Vec<Option<&Value>> = keys.iter().map(non_null).collect();
+            let entries_array = StructArray::try_new(
+                entry_fields.clone(),
+                vec![
+                    array(&keys, entry_fields[0].data_type(), &file_type.key)?,
+                    array(&map_values, entry_fields[1].data_type(), &file_type.value)?,
+                ],
+                None,
+            )?;
+            Ok(Arc::new(MapArray::try_new(
+                entries.clone(),
+                OffsetBuffer::new(offsets.into()),
+                entries_array,
+                Some(nulls()),
+                *sorted,
+            )?))
+        }
+        (data_type, IcebergType::Primitive(_)) => primitive_array(values, data_type),
+        (data_type, file_type) => Err(IcebergError::Invalid(format!(
+            "Avro field of type {:?} can't be read as {}",
+            file_type, data_type
+        ))),
+    }
+}
+
+fn primitive_array(values: &[Option<&Value>], data_type: &DataType) -> Result<ArrayRef> {
+    Ok(match data_type {
+        DataType::Boolean => Arc::new(BooleanArray::from(collect(
+            values,
+            data_type,
+            |v| match v {
+                Value::Boolean(b) => Some(*b),
+                _ => None,
+            },
+        )?)),
+        DataType::Int32 => Arc::new(Int32Array::from(collect(values, data_type, |v| match v {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        })?)),
+        // Int columns promoted to long, and so on
+        DataType::Int64 => Arc::new(Int64Array::from(collect(values, data_type, |v| match v {
+            Value::Long(l) => Some(*l),
+            Value::Int(i) => Some(*i as i64),
+            _ => None,
+        })?)),
+        DataType::Float32 => Arc::new(Float32Array::from(collect(
+            values,
+            data_type,
+            |v| match v {
+                Value::Float(f) => Some(*f),
+                _ => None,
+            },
+        )?)),
+        DataType::Float64 => Arc::new(Float64Array::from(collect(
+            values,
+            data_type,
+            |v| match v {
+                Value::Double(d) => Some(*d),
+                Value::Float(f) => Some(*f as f64),
+                _ => None,
+            },
+        )?)),
+        DataType::Date32 => Arc::new(Date32Array::from(collect(
+            values,
+            data_type,
+            |v| match v {
+                Value::Date(d) | Value::Int(d) => Some(*d),
+                _ => None,
+            },
+        )?)),
+        DataType::Time64(TimeUnit::Microsecond) => Arc::new(Time64MicrosecondArray::from(collect(
+            values,
+            data_type,
+            |v| match v {
+                Value::TimeMicros(t) | Value::Long(t) => Some(*t),
+                _ => None,
+            },
+        )?)),
+        DataType::Timestamp(TimeUnit::Microsecond, zone) => Arc::new(
+            TimestampMicrosecondArray::from(collect(values, data_type, |v| match v {
+                Value::TimestampMicros(t) | Value::LocalTimestampMicros(t) | Value::Long(t) => {
+                    Some(*t)
+                }
+                _ => None,
+            })?)
+            .with_timezone_opt(zone.clone()),
+        ),
+        DataType::Timestamp(TimeUnit::Nanosecond, zone) => Arc::new(
+            TimestampNanosecondArray::from(collect(values, data_type, |v| match v {
+                Value::TimestampNanos(t) | Value::LocalTimestampNanos(t) | Value::Long(t) => {
+                    Some(*t)
+                }
+                _ => None,
+            })?)
+            .with_timezone_opt(zone.clone()),
+        ),
+        DataType::Utf8 => Arc::new(StringArray::from(collect(
+            values,
+            data_type,
+            |v| match v {
+                Value::String(s) | Value::Enum(_, s) => Some(s.as_str()),
+                _ => None,
+            },
+        )?)),
+        DataType::Binary => Arc::new(BinaryArray::from(collect(
+            values,
+            data_type,
+            |v| match v {
+                Value::Bytes(b) | Value::Fixed(_, b) => Some(b.as_slice()),
+                _ => None,
+            },
+        )?)),
+        DataType::FixedSizeBinary(size) => {
+            let values = collect(values, data_type, |v| match v {
+                Value::Fixed(_, b) | Value::Bytes(b) => Some(b.as_slice()),
+                Value::Uuid(uuid) => Some(uuid.as_bytes().as_slice()),
+                _ => None,
+            })?;
+            Arc::new(FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+                values.into_iter(),
+                *size,
+            )?)
+        }
+        DataType::Decimal128(precision, scale) => Arc::new(
+            Decimal128Array::from(collect(values, data_type, |v| match v {
+                Value::Decimal(decimal) => Vec::<u8>::try_from(decimal)
+                    .ok()
+                    .and_then(|bytes| unscaled_value(&bytes)),
+                Value::Bytes(bytes) | Value::Fixed(_, bytes) => unscaled_value(bytes),
+                _ => None,
+            })?)
+            .with_precision_and_scale(*precision, *scale)?,
+        ),
+        data_type => {
+            return Err(IcebergError::Unsupported(format!(
+                "Reading Avro values as {}",
+                data_type
+            )))
+        }
+    })
+}
+
+// Values of a primitive column, failing on values of another type
+fn collect<'v, T>(
+    values: &[Option<&'v Value>],
+    data_type: &DataType,
+    value: impl Fn(&'v Value) -> Option<T>,
+) -> Result<Vec<Option<T>>> {
+    values
+        .iter()
+        .map(|v| {
+            v.map(|v| value(v).ok_or_else(|| mismatch(v, data_type)))
+                .transpose()
+        })
+        .collect()
+}
+
+// Unscaled value of a decimal stored as big-endian two's complement bytes
+fn unscaled_value(bytes: &[u8]) -> Option<i128> {
+    if bytes.len() > 16 {
+        return None;
+    }
+    let negative = bytes.first().is_some_and(|byte| byte & 0x80 != 0);
+    let mut buffer = [if negative { 0xff } else { 0 }; 16];
+    buffer[16 - bytes.len()..].copy_from_slice(bytes);
+    Some(i128::from_be_bytes(buffer))
+}
+
+fn mismatch(value: &Value, data_type: &DataType) -> IcebergError {
+    IcebergError::Invalid(format!(
+        "Avro value {:?} can't be read as {}",
+        value, data_type
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use apache_avro::Decimal;
+    use arrow::array::{Array, AsArray};
+    use arrow::datatypes::Int64Type;
+
+    use super::*;
+    use crate::iceberg::spec::avro::schema_to_avro;
+    use crate::iceberg::spec::schema::{ListType, MapType, PrimitiveType, StructField};
+
+    fn primitive(primitive: PrimitiveType) -> IcebergType {
+        IcebergType::Primitive(primitive)
+    }
+
+    fn file_schema() -> StructType {
+        StructType {
+            fields: vec![
+                StructField::new(1, "id", true, primitive(PrimitiveType::Int)),
+                StructField::new(2, "name", false, primitive(PrimitiveType::String)),
+                StructField::new(
+                    3,
+                    "tags",
+                    false,
+                    IcebergType::List(ListType {
+                        element_id: 4,
+                        element_required: true,
+                        element: Box::new(primitive(PrimitiveType::String)),
+                    }),
+                ),
+                StructField::new(
+                    5,
+                    "counts",
+                    true,
+                    IcebergType::Map(MapType {
+                        key_id: 6,
+                        key: Box::new(primitive(PrimitiveType::String)),
+                        value_id: 7,
+                        value_required: true,
+                        value: Box::new(primitive(PrimitiveType::Long)),
+                    }),
+                ),
+                StructField::new(
+                    8,
+                    "price",
+                    true,
+                    primitive(PrimitiveType::Decimal {
+                        precision: 9,
+                        scale: 2,
+                    }),
+                ),
+                StructField::new(9, "ts", true, primitive(PrimitiveType::Timestamptz)),
+            ],
+        }
+    }
+
+    fn write_file() -> Bytes {
+        let schema = schema_to_avro(&file_schema(), "r").unwrap();
+        let mut writer = apache_avro::Writer::new(&schema, Vec::new());
+        for (id, name) in [(1, Some("a")), (2, None)] {
+            let name = match name {
+                Some(name) => Value::Union(1, Box::new(Value::String(name.to_string()))),
+                None => Value::Union(0, Box::new(Value::Null)),
+            };
+            let tags = Value::Union(
+                1,
+                Box::new(Value::Array(vec![Value::String(format!("t{}", id))])),
+            );
+            let counts = Value::Map(HashMap::from([("n".to_string(), Value::Long(id * 10))]));
+            let price = Value::Decimal(Decimal::from(vec![0, 0, 0x30, 0x39]));
+            writer
+                .append(Value::Record(vec![
+                    ("id".to_string(), Value::Int(id as i32)),
+                    ("name".to_string(), name),
+                    ("tags".to_string(), tags),
+                    ("counts".to_string(), counts),
+                    ("price".to_string(), price),
+                    ("ts".to_string(), Value::TimestampMicros(id * 1000)),
+                ]))
+                .unwrap();
+        }
+        Bytes::from(writer.into_inner().unwrap())
+    }
+
+    #[test]
+    fn test_read_avro_data_file() {
+        // The table promoted id to long, renamed name and added a column
+        let mut table_schema = file_schema();
+        table_schema.fields[0].field_type = primitive(PrimitiveType::Long);
+        table_schema.fields[1].name = "label".to_string();
+        table_schema.fields.push(StructField::new(
+            10,
+            "added",
+            false,
+            primitive(PrimitiveType::Int),
+        ));
+        let reader = AvroReader::try_new(&table_schema).unwrap();
+        let batches = reader
+            .read(write_file())
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(1, batches.len());
+        let batch = &batches[0];
+        assert_eq!(reader.output_schema().unwrap(), batch.schema());
+        assert_eq!(
+            &[1, 2],
+            batch.column(0).as_primitive::<Int64Type>().values()
+        );
+        let labels = batch.column(1).as_string::<i32>();
+        assert_eq!(vec![Some("a"), None], labels.iter().collect::<Vec<_>>());
+        let tags = batch.column(2).as_list::<i32>();
+        assert_eq!("t2", tags.value(1).as_string::<i32>().value(0));
+        let counts = batch.column(3).as_map();
+        assert_eq!(
+            &[20],
+            counts
+                .value(1)
+                .column(1)
+                .as_primitive::<Int64Type>()
+                .values()
+        );
+        let prices = batch
+            .column(4)
+            .as_primitive::<arrow::datatypes::Decimal128Type>();
+        assert_eq!("123.45", prices.value_as_string(0));
+        let ts = batch
+            .column(5)
+            .as_primitive::<arrow::datatypes::TimestampMicrosecondType>();
+        assert_eq!(2000, ts.value(1));
+        assert_eq!(2, batch.column(6).null_count());
+
+        let reader = reader.with_projection(vec![8, 1]).with_batch_size(1);
+        let batches = reader
+            .read(write_file())
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(2, batches.len());
+        assert_eq!(
+            &[2],
+            batches[1].column(1).as_primitive::<Int64Type>().values()
+        );
+    }
+
+    #[test]
+    fn test_read_avro_without_field_ids() {
+        let mut table_schema = file_schema();
+        table_schema.fields[0].field_type = primitive(PrimitiveType::Long);
+        // Without ids nor name mapping the file can't be resolved
+        let data = {
+            let schema = apache_avro::Schema::parse_str(
+                r#"{"type": "record", "name": "r", "fields": [{"name": "id", "type": "long"}]}"#,
+            )
+            .unwrap();
+            let mut writer = apache_avro::Writer::new(&schema, Vec::new());
+            writer
+                .append(Value::Record(vec![("id".to_string(), Value::Long(7))]))
+                .unwrap();
+            Bytes::from(writer.into_inner().unwrap())
+        };
+        let reader = AvroReader::try_new(&table_schema)
+            .unwrap()
+            .with_projection(vec![1]);
+        assert!(reader.read(data.clone()).is_err());
+
+        let mapping: NameMapping =
+            serde_json::from_str(r#"[{"field-id": 1, "names": ["id"]}]"#).unwrap();
+        let batch = reader
+            .with_name_mapping(mapping)
+            .read(data)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(&[7], batch.column(0).as_primitive::<Int64Type>().values());
+        assert_eq!(0, batch.column(0).null_count());
+    }
+}
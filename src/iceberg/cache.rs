@@ -0,0 +1,353 @@
+// Cache of decoded metadata files, manifest lists and manifests. These files are immutable once
+// written, so repeated scans of the same snapshot can reuse them instead of fetching and decoding
+// them again. Files are keyed by location, along with their length when it is known (manifests),
+// and evicted least recently used first once the cached files exceed the capacity. The fetched
+// bytes can also be kept in a local directory, which outlives the process, to avoid fetching
+// them again from remote storage after a restart
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+
+use crate::iceberg::encryption::{decrypt_manifest, EncryptionManager};
+use crate::iceberg::error::Result;
+use crate::iceberg::ident::TableIdent;
+use crate::iceberg::io::FileIO;
+use crate::iceberg::puffin::theta::murmur3_x64_128;
+use crate::iceberg::spec::manifest::{read_manifest, ManifestEntry};
+use crate::iceberg::spec::manifest_list::{read_manifest_list, ManifestListV2};
+use crate::iceberg::spec::schema::StructType;
+use crate::iceberg::spec::table_metadata::TableMetadata;
+use crate::iceberg::table::Table;
+
+#[derive(Debug)]
+pub struct MetadataCache {
+    max_bytes: u64,
+    directory: Option<PathBuf>,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    // Total length of the cached files
+    pub size_in_bytes: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, CacheEntry>,
+    // Incremented on every access, entries remember the tick of their last access
+    tick: u64,
+    stats: CacheStats,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    location: String,
+    length: Option<i64>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    value: CachedFile,
+    size_in_bytes: u64,
+    last_used: u64,
+}
+
+#[derive(Debug, Clone)]
+enum CachedFile {
+    Metadata(Arc<TableMetadata>),
+    ManifestList(Arc<Vec<ManifestListV2>>),
+    Manifest(Arc<Vec<ManifestEntry>>),
+}
+
+impl MetadataCache {
+    // In-memory cache holding files up to a total length of `max_bytes`
+    pub fn new(max_bytes: u64) -> Self {
+        MetadataCache {
+            max_bytes,
+            directory: None,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    // Also keeps the fetched files in a local directory. Files in the directory aren't evicted
+    pub fn with_directory(mut self, directory: PathBuf) -> Self {
+        self.directory = Some(directory);
+        self
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.state.lock().unwrap().stats
+    }
+
+    // Loads a table like Table::load, reading its metadata file through the cache. Manifest
+    // lists and manifests of the returned table are read through the cache as well
+    pub fn load_table(
+        self: &Arc<Self>,
+        ident: TableIdent,
+        metadata_location: String,
+        file_io: Arc<dyn FileIO>,
+    ) -> Result<Table> {
+        let metadata = self.metadata(&metadata_location, file_io.as_ref())?;
+        Ok(
+            Table::try_new(ident, metadata.as_ref().clone(), metadata_location, file_io)?
+                .with_metadata_cache(self.clone()),
+        )
+    }
+
+    pub(crate) fn metadata(
+        &self,
+        location: &str,
+        file_io: &dyn FileIO,
+    ) -> Result<Arc<TableMetadata>> {
+        let key = CacheKey {
+            location: location.to_string(),
+            length: None,
+        };
+        let cached = self.get_or_load(key, file_io, |data| {
+            Ok(CachedFile::Metadata(Arc::new(serde_json::from_slice(
+                data,
+            )?)))
+        })?;
+        match cached {
+            CachedFile::Metadata(metadata) => Ok(metadata),
+            _ => unreachable!("metadata files are cached as metadata"),
+        }
+    }
+
+    pub(crate) fn manifest_list(
+        &self,
+        location: &str,
+        file_io: &dyn FileIO,
+    ) -> Result<Arc<Vec<ManifestListV2>>> {
+        let key = CacheKey {
+            location: location.to_string(),
+            length: None,
+        };
+        let cached = self.get_or_load(key, file_io, |data| {
+            Ok(CachedFile::ManifestList(Arc::new(read_manifest_list(
+                data,
+            )?)))
+        })?;
+        match cached {
+            CachedFile::ManifestList(manifests) => Ok(manifests),
+            _ => unreachable!("manifest lists are cached as manifest lists"),
+        }
+    }
+
+    pub(crate) fn manifest(
+        &self,
+        manifest: &ManifestListV2,
+        partition_type: &StructType,
+        file_io: &dyn FileIO,
+        encryption: Option<&dyn EncryptionManager>,
+    ) -> Result<Arc<Vec<ManifestEntry>>> {
+        let key = CacheKey {
+            location: manifest.manifest_path.clone(),
+            length: Some(manifest.manifest_length),
+        };
+        let cached = self.get_or_load(key, file_io, |data| {
+            Ok(CachedFile::Manifest(Arc::new(read_manifest(
+                manifest,
+                &decrypt_manifest(encryption, manifest, data)?,
+                partition_type,
+            )?)))
+        })?;
+        match cached {
+            CachedFile::Manifest(entries) => Ok(entries),
+            _ => unreachable!("manifests are cached as manifests"),
+        }
+    }
+
+    // The lock isn't held while loading, so that files can be loaded concurrently. Files loaded
+    // by several threads at once are decoded by each of them
+    fn get_or_load(
+        &self,
+        key: CacheKey,
+        file_io: &dyn FileIO,
+        decode: impl FnOnce(&[u8]) -> Result<CachedFile>,
+    ) -> Result<CachedFile> {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.tick += 1;
+            let tick = state.tick;
+            if let Some(entry) = state.entries.get_mut(&key) {
+                entry.last_used = tick;
+                let value = entry.value.clone();
+                state.stats.hits += 1;
+                return Ok(value);
+            }
+            state.stats.misses += 1;
+        }
+
+        let data = self.fetch(&key, file_io)?;
+        let value = decode(&data)?;
+        let size_in_bytes = data.len() as u64;
+        if size_in_bytes > self.max_bytes {
+            return Ok(value);
+        }
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let entry = CacheEntry {
+            value: value.clone(),
+            size_in_bytes,
+            last_used: state.tick,
+        };
+        if let Some(previous) = state.entries.insert(key, entry) {
+            state.stats.size_in_bytes -= previous.size_in_bytes;
+        }
+        state.stats.size_in_bytes += size_in_bytes;
+        while state.stats.size_in_bytes > self.max_bytes {
+            let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            let evicted = state.entries.remove(&oldest).unwrap();
+            state.stats.size_in_bytes -= evicted.size_in_bytes;
+            state.stats.evictions += 1;
+        }
+        Ok(value)
+    }
+
+    // Reads the file from the cache directory if it is there, from the FileIO otherwise
+    fn fetch(&self, key: &CacheKey, file_io: &dyn FileIO) -> Result<Bytes> {
+        let Some(directory) = &self.directory else {
+            return file_io.read(&key.location);
+        };
+        let path = directory.join(cache_file_name(key));
+        if let Ok(data) = std::fs::read(&path) {
+            return Ok(Bytes::from(data));
+        }
+        let data = file_io.read(&key.location)?;
+        // The cache directory is an optimization, failing to fill it doesn't fail the read. The
+        // file is renamed into place so that readers never see a partially written file
+        let temp = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+        let written = std::fs::create_dir_all(directory)
+            .and_then(|_| std::fs::write(&temp, &data))
+            .and_then(|_| std::fs::rename(&temp, &path));
+        if let Err(e) = written {
+            log::warn!(
+                "Couldn't keep {} in cache directory {}: {}",
+                key.location,
+                directory.display(),
+                e
+            );
+            let _ = std::fs::remove_file(&temp);
+        }
+        Ok(data)
+    }
+}
+
+fn cache_file_name(key: &CacheKey) -> String {
+    let (high, low) = murmur3_x64_128(key.location.as_bytes(), 0);
+    let name = key
+        .location
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .collect::<String>();
+    match key.length {
+        Some(length) => format!("{:016x}{:016x}-{}-{}", high, low, length, name),
+        None => format!("{:016x}{:016x}-{}", high, low, name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::io::LocalFileIO;
+    use crate::iceberg::test_utils::{append, create_table, ids_batch};
+
+    #[test]
+    fn test_metadata_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = create_table(dir.path());
+        let table = append(&table, &ids_batch(&[1, 2]));
+        let table = append(&table, &ids_batch(&[3]));
+        let cache = Arc::new(MetadataCache::new(1 << 20));
+        let load = || {
+            cache
+                .load_table(
+                    "db.table".parse().unwrap(),
+                    table.metadata_location().to_string(),
+                    Arc::new(LocalFileIO::new()),
+                )
+                .unwrap()
+        };
+
+        let cached = load();
+        let plan = cached.scan().plan_files().unwrap();
+        assert_eq!(2, plan.tasks().len());
+        // Metadata file, manifest list and 2 manifests
+        assert_eq!(4, cache.stats().misses);
+        assert_eq!(0, cache.stats().hits);
+
+        let cached = load();
+        assert_eq!(plan.tasks(), cached.scan().plan_files().unwrap().tasks());
+        assert_eq!(4, cache.stats().misses);
+        assert_eq!(4, cache.stats().hits);
+        assert!(cache.stats().size_in_bytes > 0);
+    }
+
+    #[test]
+    fn test_metadata_cache_eviction_and_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = create_table(dir.path());
+        let table = append(&table, &ids_batch(&[1, 2]));
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        let manifest = &table.manifests(snapshot).unwrap()[0];
+        let partition_type = StructType { fields: vec![] };
+        let file_io = LocalFileIO::new();
+
+        // Files larger than the cache aren't cached
+        let cache = MetadataCache::new(0);
+        cache
+            .manifest(manifest, &partition_type, &file_io, None)
+            .unwrap();
+        cache
+            .manifest(manifest, &partition_type, &file_io, None)
+            .unwrap();
+        assert_eq!(2, cache.stats().misses);
+        assert_eq!(0, cache.stats().size_in_bytes);
+
+        // Only the last file fits
+        let cache = MetadataCache::new(manifest.manifest_length as u64);
+        let list = cache
+            .manifest_list(&snapshot.manifest_list, &file_io)
+            .unwrap();
+        assert_eq!(1, list.len());
+        let entries = cache
+            .manifest(manifest, &partition_type, &file_io, None)
+            .unwrap();
+        assert_eq!(1, entries.len());
+        assert_eq!(1, cache.stats().evictions);
+        assert_eq!(manifest.manifest_length as u64, cache.stats().size_in_bytes);
+
+        // The cache directory still has the files after they were removed from the table
+        let cache_dir = dir.path().join("cache");
+        let cache = MetadataCache::new(1 << 20).with_directory(cache_dir.clone());
+        let entries = cache
+            .manifest(manifest, &partition_type, &file_io, None)
+            .unwrap();
+        file_io.delete(&manifest.manifest_path).unwrap();
+        let cache = MetadataCache::new(1 << 20).with_directory(cache_dir.clone());
+        assert_eq!(
+            entries,
+            cache
+                .manifest(manifest, &partition_type, &file_io, None)
+                .unwrap()
+        );
+        assert_eq!(1, std::fs::read_dir(&cache_dir).unwrap().count());
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use super::{IcebergCatalog, NamespaceMetadata};
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::ident::{Namespace, TableIdent};
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+use crate::iceberg::table::Table;
+use crate::iceberg::view::View;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum CatalogOperation {
+    // Also checked to load views
+    LoadTable,
+    CommitTable,
+    DropTable,
+    // Namespace operations are checked with an empty table name, so that only grants on all
+    // tables or on namespaces allow them. Listing only returns the namespaces allowed, and
+    // listing tables only the tables that may be loaded
+    ListNamespaces,
+    CreateNamespace,
+    DropNamespace,
+    UpdateNamespace,
+}
+
+// The caller on whose behalf catalog operations run
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Identity {
+    pub principal: String,
+    pub roles: Vec<String>,
+}
+
+// Decides which operations callers may perform on which tables. Services embedding rustberg
+// implement it to plug in their own permission model
+pub trait AccessPolicy: Debug + Send + Sync {
+    fn is_allowed(
+        &self,
+        identity: &Identity,
+        operation: CatalogOperation,
+        namespace: &str,
+        name: &str,
+    ) -> bool;
+}
+
+// Policy granting operations to roles, on every table, on the tables of a namespace or on a
+// single table. Anything not granted is denied
+#[derive(Debug, Clone, Default)]
+pub struct RoleBasedPolicy {
+    grants: Vec<Grant>,
+}
+
+#[derive(Debug, Clone)]
+struct Grant {
+    role: String,
+    // None grants on all namespaces, respectively all tables of the namespace
+    namespace: Option<String>,
+    name: Option<String>,
+    operations: Vec<CatalogOperation>,
+}
+
+// Catalog checking every operation against an access policy before delegating it
+#[derive(Debug, Clone)]
+pub struct AuthorizedCatalog {
+    catalog: Arc<dyn IcebergCatalog>,
+    policy: Arc<dyn AccessPolicy>,
+    identity: Identity,
+}
+
+impl Identity {
+    pub fn new(principal: &str, roles: &[&str]) -> Self {
+        Identity {
+            principal: principal.to_string(),
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+        }
+    }
+}
+
+impl RoleBasedPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn grant_all(self, role: &str, operations: &[CatalogOperation]) -> Self {
+        self.grant(role, None, None, operations)
+    }
+
+    pub fn grant_namespace(
+        self,
+        role: &str,
+        namespace: &str,
+        operations: &[CatalogOperation],
+    ) -> Self {
+        self.grant(role, Some(namespace), None, operations)
+    }
+
+    pub fn grant_table(
+        self,
+        role: &str,
+        namespace: &str,
+        name: &str,
+        operations: &[CatalogOperation],
+    ) -> Self {
+        self.grant(role, Some(namespace), Some(name), operations)
+    }
+
+    fn grant(
+        mut self,
+        role: &str,
+        namespace: Option<&str>,
+        name: Option<&str>,
+        operations: &[CatalogOperation],
+    ) -> Self {
+        self.grants.push(Grant {
+            role: role.to_string(),
+            namespace: namespace.map(str::to_string),
+            name: name.map(str::to_string),
+            operations: operations.to_vec(),
+        });
+        self
+    }
+}
+
+impl AccessPolicy for RoleBasedPolicy {
+    fn is_allowed(
+        &self,
+        identity: &Identity,
+        operation: CatalogOperation,
+        namespace: &str,
+        name: &str,
+    ) -> bool {
+        self.grants.iter().any(|grant| {
+            identity.roles.contains(&grant.role)
+                && grant.operations.contains(&operation)
+                && grant.namespace.as_deref().is_none_or(|ns| ns == namespace)
+                && grant.name.as_deref().is_none_or(|n| n == name)
+        })
+    }
+}
+
+impl AuthorizedCatalog {
+    pub fn new(
+        catalog: Arc<dyn IcebergCatalog>,
+        policy: Arc<dyn AccessPolicy>,
+        identity: Identity,
+    ) -> Self {
+        AuthorizedCatalog {
+            catalog,
+            policy,
+            identity,
+        }
+    }
+
+    pub fn identity(&self) -> &Identity {
+        &self.identity
+    }
+
+    fn check(&self, operation: CatalogOperation, namespace: &Namespace, name: &str) -> Result<()> {
+        if self
+            .policy
+            .is_allowed(&self.identity, operation, &namespace.to_string(), name)
+        {
+            Ok(())
+        } else {
+            Err(IcebergError::Forbidden(format!(
+                "{} is not allowed to {:?} on {}.{}",
+                self.identity.principal, operation, namespace, name
+            )))
+        }
+    }
+
+    fn check_namespace(&self, operation: CatalogOperation, namespace: &Namespace) -> Result<()> {
+        if self
+            .policy
+            .is_allowed(&self.identity, operation, &namespace.to_string(), "")
+        {
+            Ok(())
+        } else {
+            Err(IcebergError::Forbidden(format!(
+                "{} is not allowed to {:?} on namespace {}",
+                self.identity.principal, operation, namespace
+            )))
+        }
+    }
+}
+
+impl IcebergCatalog for AuthorizedCatalog {
+    fn load_table(&self, ident: &TableIdent) -> Result<Table> {
+        self.check(CatalogOperation::LoadTable, ident.namespace(), ident.name())?;
+        self.catalog.load_table(ident)
+    }
+
+    fn refresh_table(&self, table: &Table) -> Result<Table> {
+        self.check(CatalogOperation::LoadTable, table.namespace(), table.name())?;
+        self.catalog.refresh_table(table)
+    }
+
+    fn commit_table(&self, base: &Table, metadata: TableMetadataV2) -> Result<Table> {
+        self.check(CatalogOperation::CommitTable, base.namespace(), base.name())?;
+        self.catalog.commit_table(base, metadata)
+    }
+
+    fn drop_table(&self, ident: &TableIdent, purge: bool) -> Result<()> {
+        self.check(CatalogOperation::DropTable, ident.namespace(), ident.name())?;
+        self.catalog.drop_table(ident, purge)
+    }
+
+    fn list_tables(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        let mut tables = self.catalog.list_tables(namespace)?;
+        tables.retain(|name| {
+            self.check(CatalogOperation::LoadTable, namespace, name)
+                .is_ok()
+        });
+        Ok(tables)
+    }
+
+    fn load_view(&self, ident: &TableIdent) -> Result<View> {
+        self.check(CatalogOperation::LoadTable, ident.namespace(), ident.name())?;
+        self.catalog.load_view(ident)
+    }
+
+    fn list_views(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        let mut views = self.catalog.list_views(namespace)?;
+        views.retain(|name| {
+            self.check(CatalogOperation::LoadTable, namespace, name)
+                .is_ok()
+        });
+        Ok(views)
+    }
+
+    fn list_namespaces(&self) -> Result<Vec<NamespaceMetadata>> {
+        let mut namespaces = self.catalog.list_namespaces()?;
+        namespaces.retain(|namespace| {
+            self.check_namespace(CatalogOperation::ListNamespaces, &namespace.name)
+                .is_ok()
+        });
+        Ok(namespaces)
+    }
+
+    fn create_namespace(
+        &self,
+        namespace: &Namespace,
+        properties: HashMap<String, String>,
+    ) -> Result<NamespaceMetadata> {
+        self.check_namespace(CatalogOperation::CreateNamespace, namespace)?;
+        self.catalog.create_namespace(namespace, properties)
+    }
+
+    fn drop_namespace(&self, namespace: &Namespace) -> Result<()> {
+        self.check_namespace(CatalogOperation::DropNamespace, namespace)?;
+        self.catalog.drop_namespace(namespace)
+    }
+
+    fn update_namespace_properties(
+        &self,
+        namespace: &Namespace,
+        updates: HashMap<String, String>,
+        removals: &[&str],
+    ) -> Result<NamespaceMetadata> {
+        self.check_namespace(CatalogOperation::UpdateNamespace, namespace)?;
+        self.catalog
+            .update_namespace_properties(namespace, updates, removals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::test_utils::TestCatalog;
+
+    fn policy() -> Arc<dyn AccessPolicy> {
+        Arc::new(
+            RoleBasedPolicy::new()
+                .grant_all(
+                    "admin",
+                    &[CatalogOperation::LoadTable, CatalogOperation::DropTable],
+                )
+                .grant_namespace("analyst", "sales", &[CatalogOperation::LoadTable])
+                .grant_table(
+                    "etl",
+                    "sales",
+                    "orders",
+                    &[CatalogOperation::LoadTable, CatalogOperation::CommitTable],
+                ),
+        )
+    }
+
+    #[test]
+    fn test_role_based_policy() {
+        let policy = policy();
+        let analyst = Identity::new("alice", &["analyst"]);
+        let etl = Identity::new("bob", &["etl"]);
+
+        assert!(policy.is_allowed(&analyst, CatalogOperation::LoadTable, "sales", "orders"));
+        assert!(!policy.is_allowed(&analyst, CatalogOperation::LoadTable, "hr", "people"));
+        assert!(!policy.is_allowed(&analyst, CatalogOperation::CommitTable, "sales", "orders"));
+        assert!(policy.is_allowed(&etl, CatalogOperation::CommitTable, "sales", "orders"));
+        assert!(!policy.is_allowed(&etl, CatalogOperation::CommitTable, "sales", "returns"));
+        assert!(!policy.is_allowed(
+            &Identity::new("eve", &[]),
+            CatalogOperation::LoadTable,
+            "sales",
+            "orders"
+        ));
+    }
+
+    #[test]
+    fn test_authorized_catalog_checks_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = Arc::new(TestCatalog::new());
+        let table = catalog.create_table("sales", "orders", dir.path());
+
+        let analyst = AuthorizedCatalog::new(
+            catalog.clone(),
+            policy(),
+            Identity::new("alice", &["analyst"]),
+        );
+        assert!(analyst.load_table(&"sales.orders".parse().unwrap()).is_ok());
+        let error = analyst
+            .commit_table(&table, table.metadata().clone())
+            .err()
+            .unwrap();
+        assert!(matches!(error, IcebergError::Forbidden(_)));
+        assert!(error.to_string().contains("alice"));
+        assert!(analyst
+            .drop_table(&"sales.orders".parse().unwrap(), false)
+            .is_err());
+
+        let etl = AuthorizedCatalog::new(catalog.clone(), policy(), Identity::new("bob", &["etl"]));
+        let committed = etl.commit_table(&table, table.metadata().clone()).unwrap();
+        assert_ne!(table.metadata_location(), committed.metadata_location());
+
+        let admin =
+            AuthorizedCatalog::new(catalog.clone(), policy(), Identity::new("root", &["admin"]));
+        admin
+            .drop_table(&"sales.orders".parse().unwrap(), false)
+            .unwrap();
+        assert!(catalog
+            .load_table(&"sales.orders".parse().unwrap())
+            .is_err());
+
+        // Namespace operations are checked before reaching the catalog, which doesn't support them
+        let error = analyst
+            .create_namespace(&"hr".parse().unwrap(), HashMap::new())
+            .unwrap_err();
+        assert!(matches!(error, IcebergError::Forbidden(_)), "{}", error);
+        assert!(error.to_string().contains("namespace hr"));
+        let policy = RoleBasedPolicy::new().grant_namespace(
+            "steward",
+            "hr",
+            &[CatalogOperation::CreateNamespace],
+        );
+        let steward = AuthorizedCatalog::new(
+            catalog.clone(),
+            Arc::new(policy),
+            Identity::new("carol", &["steward"]),
+        );
+        assert!(matches!(
+            steward.create_namespace(&"hr".parse().unwrap(), HashMap::new()),
+            Err(IcebergError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,413 @@
+// Catalog of tables without a catalog service, laid out like Hadoop tables below a warehouse:
+// the table "a.b.t" lives in "<warehouse>/a/b/t", whose metadata directory holds the metadata
+// files "v<N>.metadata.json" and "version-hint.text", the number of the current one. Commits write
+// the next metadata file under a temporary name and rename it, which fails if a concurrent commit
+// created it first. This needs a FileIO renaming atomically without replacing, which object stores
+// don't offer. Namespaces are the directories of the warehouse, which can't be managed through
+// FileIO
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use uuid::Uuid;
+
+use super::{
+    delete_files, metadata_file_contents, metadata_to_commit, new_table_metadata, table_files,
+    IcebergCatalog,
+};
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::ident::{Namespace, TableIdent};
+use crate::iceberg::io::FileIO;
+use crate::iceberg::spec::partition_spec::PartitionSpec;
+use crate::iceberg::spec::schema::StructType;
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+use crate::iceberg::table::Table;
+
+pub const VERSION_HINT: &str = "version-hint.text";
+
+#[derive(Debug)]
+pub struct HadoopCatalog {
+    file_io: Arc<dyn FileIO>,
+    warehouse: String,
+}
+
+fn metadata_file(table_location: &str, version: u32) -> String {
+    format!("{}/metadata/v{}.metadata.json", table_location, version)
+}
+
+// Version of metadata files named "v<N>.metadata.json"
+fn metadata_file_version(file: &str) -> Option<u32> {
+    file.strip_prefix('v')?
+        .strip_suffix(".metadata.json")?
+        .parse()
+        .ok()
+}
+
+impl HadoopCatalog {
+    pub fn new(file_io: Arc<dyn FileIO>, warehouse: &str) -> Self {
+        HadoopCatalog {
+            file_io,
+            warehouse: warehouse.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn table_location(&self, ident: &TableIdent) -> String {
+        format!(
+            "{}/{}",
+            self.namespace_location(ident.namespace()),
+            ident.name()
+        )
+    }
+
+    fn namespace_location(&self, namespace: &Namespace) -> String {
+        format!("{}/{}", self.warehouse, namespace.levels().join("/"))
+    }
+
+    // Version of the current metadata file of the table at the location. The version hint is
+    // written after the metadata file, so that it may be behind, or missing after a failed
+    // write. Newer metadata files are looked for after the hinted one, and all of them are
+    // listed without a hint
+    pub fn current_version(&self, table_location: &str) -> Result<u32> {
+        let hint = format!("{}/metadata/{}", table_location, VERSION_HINT);
+        let hinted = match self.file_io.exists(&hint)? {
+            true => String::from_utf8_lossy(&self.file_io.read(&hint)?)
+                .trim()
+                .parse()
+                .ok(),
+            false => None,
+        };
+        let hinted = match hinted {
+            Some(version)
+                if self
+                    .file_io
+                    .exists(&metadata_file(table_location, version))? =>
+            {
+                Some(version)
+            }
+            _ => self.latest_listed_version(table_location)?,
+        };
+        let mut version =
+            hinted.ok_or_else(|| IcebergError::NotFound(format!("Table at {}", table_location)))?;
+        while self
+            .file_io
+            .exists(&metadata_file(table_location, version + 1))?
+        {
+            version += 1;
+        }
+        Ok(version)
+    }
+
+    fn latest_listed_version(&self, table_location: &str) -> Result<Option<u32>> {
+        let files = self.file_io.list(&format!("{}/metadata", table_location))?;
+        Ok(files
+            .iter()
+            .filter_map(|file| metadata_file_version(file.location.rsplit('/').next()?))
+            .max())
+    }
+
+    // Creates a table without snapshots at its location in the warehouse
+    pub fn create_table(
+        &self,
+        ident: &TableIdent,
+        schema: StructType,
+        spec: PartitionSpec,
+    ) -> Result<Table> {
+        let location = self.table_location(ident);
+        match self.current_version(&location) {
+            Ok(_) => {
+                return Err(IcebergError::Invalid(format!(
+                    "Table {} already exists",
+                    ident
+                )))
+            }
+            Err(IcebergError::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+        let metadata =
+            metadata_to_commit(None, new_table_metadata(location.clone(), schema, spec))?;
+        let metadata_location = self.write_version(ident, &location, 1, metadata)?;
+        Table::load(ident.clone(), metadata_location, self.file_io.clone())
+    }
+
+    // Writes the metadata file of the version, which fails if it exists, then the version hint
+    fn write_version(
+        &self,
+        ident: &TableIdent,
+        table_location: &str,
+        version: u32,
+        metadata: TableMetadataV2,
+    ) -> Result<String> {
+        let temp = format!(
+            "{}/metadata/{}.metadata.json.tmp",
+            table_location,
+            Uuid::new_v4()
+        );
+        self.file_io
+            .write(&temp, metadata_file_contents(metadata)?)?;
+        let location = metadata_file(table_location, version);
+        if let Err(e) = self.file_io.rename_if_absent(&temp, &location) {
+            let _ = self.file_io.delete(&temp);
+            return Err(match e {
+                IcebergError::Invalid(_) => IcebergError::CommitConflict(format!(
+                    "Table {} changed since it was loaded, version {} exists",
+                    ident, version
+                )),
+                e => e,
+            });
+        }
+        let hint = format!("{}/metadata/{}", table_location, VERSION_HINT);
+        self.file_io
+            .write(&hint, Bytes::from(version.to_string()))?;
+        Ok(location)
+    }
+}
+
+impl IcebergCatalog for HadoopCatalog {
+    fn load_table(&self, ident: &TableIdent) -> Result<Table> {
+        let location = self.table_location(ident);
+        let version = self.current_version(&location).map_err(|e| match e {
+            IcebergError::NotFound(_) => IcebergError::NotFound(format!("Table {}", ident)),
+            e => e,
+        })?;
+        Table::load(
+            ident.clone(),
+            metadata_file(&location, version),
+            self.file_io.clone(),
+        )
+    }
+
+    fn refresh_table(&self, table: &Table) -> Result<Table> {
+        let location = self.table_location(table.ident());
+        table.refresh(&metadata_file(&location, self.current_version(&location)?))
+    }
+
+    fn commit_table(&self, base: &Table, metadata: TableMetadataV2) -> Result<Table> {
+        let ident = base.ident();
+        let location = self.table_location(ident);
+        let version = self.current_version(&location)?;
+        if base.metadata_location() != metadata_file(&location, version) {
+            return Err(IcebergError::CommitConflict(format!(
+                "Table {} changed since it was loaded",
+                ident
+            )));
+        }
+        let metadata = metadata_to_commit(Some(base), metadata)?;
+        let metadata_location = self.write_version(ident, &location, version + 1, metadata)?;
+        Table::load(ident.clone(), metadata_location, base.file_io().clone())
+    }
+
+    // The table is its metadata directory, which is deleted. Purging also deletes the files of
+    // its snapshots
+    fn drop_table(&self, ident: &TableIdent, purge: bool) -> Result<()> {
+        let table = self.load_table(ident)?;
+        let mut files = match purge {
+            true => table_files(&table)?,
+            false => vec![],
+        };
+        let metadata = self
+            .file_io
+            .list(&format!("{}/metadata", self.table_location(ident)))?;
+        files.extend(metadata.into_iter().map(|file| file.location));
+        delete_files(self.file_io.as_ref(), &files);
+        Ok(())
+    }
+
+    // Directories of the namespace holding metadata files
+    fn list_tables(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        let location = self.namespace_location(namespace);
+        let prefix = format!("{}/", location);
+        let mut names = BTreeSet::new();
+        for file in self.file_io.list(&location)? {
+            let Some(path) = file.location.strip_prefix(&prefix) else {
+                continue;
+            };
+            if let [name, "metadata", file] = path.split('/').collect::<Vec<_>>()[..] {
+                if file == VERSION_HINT || metadata_file_version(file).is_some() {
+                    names.insert(name.to_string());
+                }
+            }
+        }
+        Ok(names.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::io::{LocalFileIO, MemoryFileIO};
+    use crate::iceberg::test_utils::{ids_batch, test_schema};
+    use crate::iceberg::writer::partitioned::PartitionedWriter;
+
+    fn append(catalog: &HadoopCatalog, table: &Table, ids: &[i64]) -> Result<Table> {
+        let mut writer = PartitionedWriter::for_table(table)?;
+        writer.write(&ids_batch(ids))?;
+        table
+            .new_append()
+            .add_files(writer.close()?)
+            .commit(catalog)
+    }
+
+    fn unpartitioned() -> PartitionSpec {
+        PartitionSpec {
+            spec_id: 0,
+            fields: vec![],
+        }
+    }
+
+    #[test]
+    fn test_hadoop_tables() {
+        let dir = tempfile::tempdir().unwrap();
+        let warehouse = format!("file:{}", dir.path().display());
+        let file_io = Arc::new(LocalFileIO::new());
+        let catalog = HadoopCatalog::new(file_io.clone(), &warehouse);
+        let ident: TableIdent = "db.t".parse().unwrap();
+        let table = catalog
+            .create_table(&ident, test_schema(), unpartitioned())
+            .unwrap();
+        let location = format!("{}/db/t", warehouse);
+        assert_eq!(metadata_file(&location, 1), table.metadata_location());
+        assert!(catalog
+            .create_table(&ident, test_schema(), unpartitioned())
+            .is_err());
+
+        let appended = append(&catalog, &table, &[1]).unwrap();
+        assert_eq!(metadata_file(&location, 2), appended.metadata_location());
+        let hint = format!("{}/metadata/{}", location, VERSION_HINT);
+        assert_eq!(Bytes::from("2"), file_io.read(&hint).unwrap());
+        // The table changed since it was loaded
+        assert!(matches!(
+            catalog.commit_table(&table, table.metadata().clone()),
+            Err(IcebergError::CommitConflict(_))
+        ));
+        let table = catalog.refresh_table(&table).unwrap();
+        let table = append(&catalog, &table, &[2]).unwrap();
+        assert_eq!(
+            Some(&metadata_file(&location, 2)),
+            table
+                .metadata()
+                .metadata_log
+                .iter()
+                .flatten()
+                .last()
+                .map(|log| &log.metadata_file)
+        );
+
+        // Hints behind the metadata files, invalid or missing ones still find the current version
+        for contents in [Some("1"), Some("x"), None] {
+            match contents {
+                Some(contents) => file_io.write(&hint, Bytes::from(contents)).unwrap(),
+                None => file_io.delete(&hint).unwrap(),
+            }
+            let loaded = catalog.load_table(&ident).unwrap();
+            assert_eq!(table.metadata_location(), loaded.metadata_location());
+        }
+
+        catalog
+            .create_table(&"db.u".parse().unwrap(), test_schema(), unpartitioned())
+            .unwrap();
+        assert_eq!(
+            vec!["t", "u"],
+            catalog.list_tables(&"db".parse().unwrap()).unwrap()
+        );
+        let files = table_files(&table).unwrap();
+        catalog.drop_table(&ident, true).unwrap();
+        assert!(files.iter().all(|file| !file_io.exists(file).unwrap()));
+        assert!(matches!(
+            catalog.load_table(&ident),
+            Err(IcebergError::NotFound(_))
+        ));
+        assert_eq!(
+            vec!["u"],
+            catalog.list_tables(&"db".parse().unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_concurrent_commits() {
+        let catalog = HadoopCatalog::new(Arc::new(MemoryFileIO::new()), "memory:/warehouse");
+        let ident: TableIdent = "db.t".parse().unwrap();
+        let table = catalog
+            .create_table(&ident, test_schema(), unpartitioned())
+            .unwrap();
+        // Another writer created the next version between the check and the rename
+        let location = catalog.table_location(&ident);
+        let next = metadata_file(&location, 2);
+        let metadata = metadata_to_commit(Some(&table), table.metadata().clone()).unwrap();
+        catalog
+            .write_version(&ident, &location, 2, metadata)
+            .unwrap();
+        let error = catalog
+            .write_version(&ident, &location, 2, table.metadata().clone())
+            .unwrap_err();
+        assert!(error.to_string().contains("changed since"), "{}", error);
+        assert_eq!(
+            vec![
+                next.clone(),
+                format!("{}/metadata/{}", location, VERSION_HINT)
+            ],
+            catalog
+                .file_io
+                .list(&format!("{}/metadata", location))
+                .unwrap()
+                .into_iter()
+                .map(|file| file.location)
+                .filter(|file| !file.ends_with("v1.metadata.json"))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            next,
+            catalog.load_table(&ident).unwrap().metadata_location()
+        );
+    }
+
+    #[test]
+    fn test_metadata_decimal_format() {
+        use std::collections::HashMap;
+
+        use crate::iceberg::properties::METADATA_DECIMAL_FORMAT_PROPERTY;
+        use crate::iceberg::spec::schema::{IcebergType, PrimitiveType, StructField};
+
+        let file_io = Arc::new(MemoryFileIO::new());
+        let catalog = HadoopCatalog::new(file_io.clone(), "memory:/warehouse");
+        let ident: TableIdent = "db.t".parse().unwrap();
+        let schema = StructType {
+            fields: vec![StructField::new(
+                1,
+                "price",
+                false,
+                IcebergType::Primitive(PrimitiveType::Decimal {
+                    precision: 10,
+                    scale: 2,
+                }),
+            )],
+        };
+        let table = catalog
+            .create_table(&ident, schema.clone(), unpartitioned())
+            .unwrap();
+        let contents = |table: &Table| {
+            String::from_utf8(file_io.read(table.metadata_location()).unwrap().to_vec()).unwrap()
+        };
+        assert!(contents(&table).contains(r#""type":"decimal(10, 2)""#));
+
+        let table = table
+            .new_transaction()
+            .update_properties(
+                HashMap::from([(
+                    METADATA_DECIMAL_FORMAT_PROPERTY.to_string(),
+                    "compact".to_string(),
+                )]),
+                &[],
+            )
+            .commit(&catalog)
+            .unwrap();
+        let location = catalog.table_location(&ident);
+        assert_eq!(metadata_file(&location, 2), table.metadata_location());
+        let compact = contents(&table);
+        assert!(compact.contains(r#""type":"decimal(10,2)""#), "{}", compact);
+        let loaded = catalog.load_table(&ident).unwrap();
+        assert_eq!(
+            &schema,
+            loaded.metadata().current_schema().unwrap().schema()
+        );
+    }
+}
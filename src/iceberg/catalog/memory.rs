@@ -0,0 +1,445 @@
+// Catalog keeping namespaces and the metadata locations of tables and views in memory, for tests,
+// examples and embedded use without a metastore. Metadata and data files go through its FileIO,
+// e.g. LocalFileIO to keep them or MemoryFileIO for tables that don't outlive the process
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::{
+    delete_files, new_table_metadata, table_files, write_metadata_file, IcebergCatalog,
+    NamespaceMetadata,
+};
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::ident::{Namespace, TableIdent};
+use crate::iceberg::io::FileIO;
+use crate::iceberg::spec::partition_spec::PartitionSpec;
+use crate::iceberg::spec::schema::StructType;
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+use crate::iceberg::table::Table;
+use crate::iceberg::view::View;
+
+#[derive(Debug)]
+pub struct MemoryCatalog {
+    file_io: Arc<dyn FileIO>,
+    // Location below which tables are created, in directories named after their identifier
+    warehouse: String,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    namespaces: BTreeMap<Namespace, HashMap<String, String>>,
+    tables: BTreeMap<TableIdent, String>,
+    views: BTreeMap<TableIdent, String>,
+}
+
+impl State {
+    fn check_namespace(&self, namespace: &Namespace) -> Result<()> {
+        match self.namespaces.contains_key(namespace) {
+            true => Ok(()),
+            false => Err(IcebergError::NotFound(format!("Namespace {}", namespace))),
+        }
+    }
+
+    // Tables and views share identifiers
+    fn check_new(&self, ident: &TableIdent) -> Result<()> {
+        self.check_namespace(ident.namespace())?;
+        if self.tables.contains_key(ident) || self.views.contains_key(ident) {
+            return Err(IcebergError::Invalid(format!(
+                "Table or view {} already exists",
+                ident
+            )));
+        }
+        Ok(())
+    }
+
+    fn table_location(&self, ident: &TableIdent) -> Result<String> {
+        self.tables
+            .get(ident)
+            .cloned()
+            .ok_or_else(|| IcebergError::NotFound(format!("Table {}", ident)))
+    }
+}
+
+// Names of the keys of the namespace, which are sorted
+fn names_in<V>(map: &BTreeMap<TableIdent, V>, namespace: &Namespace) -> Vec<String> {
+    map.keys()
+        .filter(|ident| ident.namespace() == namespace)
+        .map(|ident| ident.name().to_string())
+        .collect()
+}
+
+impl MemoryCatalog {
+    pub fn new(file_io: Arc<dyn FileIO>, warehouse: &str) -> Self {
+        MemoryCatalog {
+            file_io,
+            warehouse: warehouse.trim_end_matches('/').to_string(),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn file_io(&self) -> &Arc<dyn FileIO> {
+        &self.file_io
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    // Creates a table without snapshots in "<warehouse>/<namespace levels>/<name>". The namespace
+    // must exist
+    pub fn create_table(
+        &self,
+        ident: &TableIdent,
+        schema: StructType,
+        spec: PartitionSpec,
+    ) -> Result<Table> {
+        let mut state = self.state();
+        state.check_new(ident)?;
+        let location = format!(
+            "{}/{}/{}",
+            self.warehouse,
+            ident.namespace().levels().join("/"),
+            ident.name()
+        );
+        let metadata = new_table_metadata(location, schema, spec);
+        let metadata_location = write_metadata_file(self.file_io.as_ref(), None, metadata)?;
+        let table = Table::load(ident.clone(), metadata_location, self.file_io.clone())?;
+        state
+            .tables
+            .insert(ident.clone(), table.metadata_location().to_string());
+        Ok(table)
+    }
+
+    // Tracks an existing table, whose current metadata is at `metadata_location`
+    pub fn register_table(&self, ident: &TableIdent, metadata_location: &str) -> Result<Table> {
+        let mut state = self.state();
+        state.check_new(ident)?;
+        let table = Table::load(
+            ident.clone(),
+            metadata_location.to_string(),
+            self.file_io.clone(),
+        )?;
+        state
+            .tables
+            .insert(ident.clone(), metadata_location.to_string());
+        Ok(table)
+    }
+
+    // Tracks an existing view, whose current metadata is at `metadata_location`
+    pub fn register_view(&self, ident: &TableIdent, metadata_location: &str) -> Result<View> {
+        let mut state = self.state();
+        state.check_new(ident)?;
+        let view = View::load(
+            ident.clone(),
+            metadata_location.to_string(),
+            self.file_io.clone(),
+        )?;
+        state
+            .views
+            .insert(ident.clone(), metadata_location.to_string());
+        Ok(view)
+    }
+
+    // Renames the table, its files stay where they are
+    pub fn rename_table(&self, from: &TableIdent, to: &TableIdent) -> Result<()> {
+        let mut state = self.state();
+        let location = state.table_location(from)?;
+        state.check_new(to)?;
+        state.tables.remove(from);
+        state.tables.insert(to.clone(), location);
+        Ok(())
+    }
+}
+
+impl IcebergCatalog for MemoryCatalog {
+    fn load_table(&self, ident: &TableIdent) -> Result<Table> {
+        let location = self.state().table_location(ident)?;
+        Table::load(ident.clone(), location, self.file_io.clone())
+    }
+
+    fn refresh_table(&self, table: &Table) -> Result<Table> {
+        table.refresh(&self.state().table_location(table.ident())?)
+    }
+
+    fn commit_table(&self, base: &Table, metadata: TableMetadataV2) -> Result<Table> {
+        let mut state = self.state();
+        let ident = base.ident();
+        if state.table_location(ident)? != base.metadata_location() {
+            return Err(IcebergError::CommitConflict(format!(
+                "Table {} changed since it was loaded",
+                ident
+            )));
+        }
+        let location = write_metadata_file(base.file_io().as_ref(), Some(base), metadata)?;
+        let table = Table::load(ident.clone(), location, base.file_io().clone())?;
+        state
+            .tables
+            .insert(ident.clone(), table.metadata_location().to_string());
+        Ok(table)
+    }
+
+    fn drop_table(&self, ident: &TableIdent, purge: bool) -> Result<()> {
+        let mut state = self.state();
+        let location = state.table_location(ident)?;
+        // Lists the files before dropping the table, which stays if they can't be listed
+        let files = match purge {
+            true => table_files(&Table::load(ident.clone(), location, self.file_io.clone())?)?,
+            false => vec![],
+        };
+        state.tables.remove(ident);
+        drop(state);
+        delete_files(self.file_io.as_ref(), &files);
+        Ok(())
+    }
+
+    fn list_tables(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        let state = self.state();
+        state.check_namespace(namespace)?;
+        Ok(names_in(&state.tables, namespace))
+    }
+
+    fn load_view(&self, ident: &TableIdent) -> Result<View> {
+        let location = self
+            .state()
+            .views
+            .get(ident)
+            .cloned()
+            .ok_or_else(|| IcebergError::NotFound(format!("View {}", ident)))?;
+        View::load(ident.clone(), location, self.file_io.clone())
+    }
+
+    fn list_views(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        let state = self.state();
+        state.check_namespace(namespace)?;
+        Ok(names_in(&state.views, namespace))
+    }
+
+    fn list_namespaces(&self) -> Result<Vec<NamespaceMetadata>> {
+        Ok(self
+            .state()
+            .namespaces
+            .iter()
+            .map(|(name, properties)| NamespaceMetadata {
+                name: name.clone(),
+                properties: properties.clone(),
+            })
+            .collect())
+    }
+
+    fn create_namespace(
+        &self,
+        namespace: &Namespace,
+        properties: HashMap<String, String>,
+    ) -> Result<NamespaceMetadata> {
+        let mut state = self.state();
+        if state.namespaces.contains_key(namespace) {
+            return Err(IcebergError::Invalid(format!(
+                "Namespace {} already exists",
+                namespace
+            )));
+        }
+        state
+            .namespaces
+            .insert(namespace.clone(), properties.clone());
+        Ok(NamespaceMetadata {
+            name: namespace.clone(),
+            properties,
+        })
+    }
+
+    // Fails if the namespace has tables, views or nested namespaces
+    fn drop_namespace(&self, namespace: &Namespace) -> Result<()> {
+        let mut state = self.state();
+        state.check_namespace(namespace)?;
+        let levels = namespace.levels();
+        let nested = state
+            .namespaces
+            .keys()
+            .any(|other| other.levels().len() > levels.len() && other.levels().starts_with(levels));
+        let in_namespace = |ident: &TableIdent| ident.namespace() == namespace;
+        if nested || state.tables.keys().any(in_namespace) || state.views.keys().any(in_namespace) {
+            return Err(IcebergError::Invalid(format!(
+                "Namespace {} is not empty",
+                namespace
+            )));
+        }
+        state.namespaces.remove(namespace);
+        Ok(())
+    }
+
+    fn update_namespace_properties(
+        &self,
+        namespace: &Namespace,
+        updates: HashMap<String, String>,
+        removals: &[&str],
+    ) -> Result<NamespaceMetadata> {
+        let mut state = self.state();
+        let properties = state
+            .namespaces
+            .get_mut(namespace)
+            .ok_or_else(|| IcebergError::NotFound(format!("Namespace {}", namespace)))?;
+        for key in removals {
+            properties.remove(*key);
+        }
+        properties.extend(updates);
+        Ok(NamespaceMetadata {
+            name: namespace.clone(),
+            properties: properties.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::Int64Array;
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::iceberg::io::MemoryFileIO;
+    use crate::iceberg::spec::view_metadata::tests::VIEW_METADATA;
+    use crate::iceberg::test_utils::{ids_batch, test_schema};
+    use crate::iceberg::writer::partitioned::PartitionedWriter;
+
+    fn catalog() -> MemoryCatalog {
+        let catalog = MemoryCatalog::new(Arc::new(MemoryFileIO::new()), "memory:/warehouse/");
+        catalog
+            .create_namespace(&"db".parse().unwrap(), HashMap::new())
+            .unwrap();
+        catalog
+    }
+
+    fn append(catalog: &MemoryCatalog, table: &Table, ids: &[i64]) -> Result<Table> {
+        let mut writer = PartitionedWriter::for_table(table)?;
+        writer.write(&ids_batch(ids))?;
+        table
+            .new_append()
+            .add_files(writer.close()?)
+            .commit(catalog)
+    }
+
+    fn scan_ids(table: &Table) -> Vec<i64> {
+        let mut ids = vec![];
+        for batch in table.scan().plan_files().unwrap().to_arrow().unwrap() {
+            let batch = batch.unwrap();
+            let column = batch.column(0).as_any().downcast_ref::<Int64Array>();
+            ids.extend(column.unwrap().values());
+        }
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_tables_in_memory() {
+        let catalog = catalog();
+        let ident: TableIdent = "db.t".parse().unwrap();
+        let spec = PartitionSpec {
+            spec_id: 0,
+            fields: vec![],
+        };
+        let table = catalog
+            .create_table(&ident, test_schema(), spec.clone())
+            .unwrap();
+        assert_eq!("memory:/warehouse/db/t", table.metadata().location);
+        assert!(catalog
+            .create_table(&ident, test_schema(), spec.clone())
+            .is_err());
+        assert!(matches!(
+            catalog.create_table(&"other.t".parse().unwrap(), test_schema(), spec),
+            Err(IcebergError::NotFound(_))
+        ));
+
+        let appended = append(&catalog, &table, &[1, 2]).unwrap();
+        // The table changed since it was loaded
+        assert!(matches!(
+            catalog.commit_table(&table, table.metadata().clone()),
+            Err(IcebergError::CommitConflict(_))
+        ));
+        let refreshed = catalog.refresh_table(&table).unwrap();
+        assert_eq!(appended.metadata_location(), refreshed.metadata_location());
+        let table = append(&catalog, &refreshed, &[3]).unwrap();
+        assert_eq!(
+            vec![1, 2, 3],
+            scan_ids(&catalog.load_table(&ident).unwrap())
+        );
+
+        let renamed: TableIdent = "db.renamed".parse().unwrap();
+        catalog.rename_table(&ident, &renamed).unwrap();
+        assert_eq!(
+            vec!["renamed"],
+            catalog.list_tables(&"db".parse().unwrap()).unwrap()
+        );
+        assert!(matches!(
+            catalog.load_table(&ident),
+            Err(IcebergError::NotFound(_))
+        ));
+
+        let files = table_files(&table).unwrap();
+        catalog.drop_table(&renamed, true).unwrap();
+        assert!(files
+            .iter()
+            .all(|file| !catalog.file_io().exists(file).unwrap()));
+        assert!(catalog
+            .list_tables(&"db".parse().unwrap())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_namespaces_and_views() {
+        let catalog = catalog();
+        let db: Namespace = "db".parse().unwrap();
+        let nested: Namespace = "db.nested".parse().unwrap();
+        catalog
+            .create_namespace(
+                &nested,
+                HashMap::from([("owner".to_string(), "etl".to_string())]),
+            )
+            .unwrap();
+        assert!(catalog.create_namespace(&db, HashMap::new()).is_err());
+        let updated = catalog
+            .update_namespace_properties(
+                &nested,
+                HashMap::from([("retention".to_string(), "30d".to_string())]),
+                &["owner"],
+            )
+            .unwrap();
+        assert_eq!(
+            HashMap::from([("retention".to_string(), "30d".to_string())]),
+            updated.properties
+        );
+        assert_eq!(
+            vec!["db", "db.nested"],
+            catalog
+                .list_namespaces()
+                .unwrap()
+                .iter()
+                .map(|namespace| namespace.name.to_string())
+                .collect::<Vec<_>>()
+        );
+
+        let location = "memory:/warehouse/db/nested/v/metadata/v1.metadata.json";
+        catalog
+            .file_io()
+            .write(location, Bytes::from(VIEW_METADATA))
+            .unwrap();
+        let ident: TableIdent = "db.nested.v".parse().unwrap();
+        catalog.register_view(&ident, location).unwrap();
+        assert!(catalog.register_view(&ident, location).is_err());
+        assert_eq!(vec!["v"], catalog.list_views(&nested).unwrap());
+        assert!(catalog.list_tables(&nested).unwrap().is_empty());
+        let view = catalog.load_view(&ident).unwrap();
+        assert_eq!(&ident, view.ident());
+
+        // Namespaces with views or nested namespaces can't be dropped
+        assert!(catalog.drop_namespace(&nested).is_err());
+        assert!(catalog.drop_namespace(&db).is_err());
+        catalog.state().views.clear();
+        catalog.drop_namespace(&nested).unwrap();
+        catalog.drop_namespace(&db).unwrap();
+        assert!(catalog.list_namespaces().unwrap().is_empty());
+        assert!(matches!(
+            catalog.list_tables(&db),
+            Err(IcebergError::NotFound(_))
+        ));
+    }
+}
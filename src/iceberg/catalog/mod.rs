@@ -1 +1,372 @@
-pub trait IcebergCatalog {}
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+
+use bytes::Bytes;
+use uuid::Uuid;
+
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::ident::{Namespace, TableIdent};
+use crate::iceberg::io::FileIO;
+use crate::iceberg::operations::current_time_ms;
+use crate::iceberg::properties::TableProperties;
+use crate::iceberg::read_only::ensure_writable;
+use crate::iceberg::spec::partition_spec::PartitionSpec;
+use crate::iceberg::spec::schema::{to_json_with_decimal_format, IcebergSchemaV2, StructType};
+use crate::iceberg::spec::sort_orders::SortOrders;
+use crate::iceberg::spec::table_metadata::{MetadataLog, TableMetadata, TableMetadataV2};
+use crate::iceberg::table::{Table, TableCapability};
+use crate::iceberg::view::View;
+
+pub mod access;
+pub mod hadoop;
+pub mod memory;
+#[cfg(feature = "nessie")]
+pub mod nessie;
+pub mod read_only;
+pub mod retry;
+
+// A namespace, a database of the metastore, and its properties
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamespaceMetadata {
+    pub name: Namespace,
+    pub properties: HashMap<String, String>,
+}
+
+// Tracks the current metadata file of tables. Commits are atomic swaps of the metadata location,
+// which fail if the table changed since the base table was loaded
+pub trait IcebergCatalog: Debug + Send + Sync {
+    fn load_table(&self, ident: &TableIdent) -> Result<Table>;
+
+    // Loads the current version of a table that was loaded before. Catalogs that know the
+    // current metadata location without reading it should refresh the table with
+    // Table::refresh, which only decodes what changed
+    fn refresh_table(&self, table: &Table) -> Result<Table> {
+        self.load_table(table.ident())
+    }
+
+    // Writes the new metadata and makes it current, as long as the current metadata of the
+    // table is still the one `base` was loaded from
+    fn commit_table(&self, base: &Table, metadata: TableMetadataV2) -> Result<Table>;
+
+    // Removes the table from the catalog. Purging also deletes its metadata and data files
+    fn drop_table(&self, ident: &TableIdent, purge: bool) -> Result<()>;
+
+    // Clock used for the timestamps of commits. Catalogs backed by a server can return the time
+    // of the server, so that the timestamps of writers with skewed clocks stay ordered
+    fn current_time_ms(&self) -> Result<i64> {
+        Ok(current_time_ms())
+    }
+
+    // Names of the tables of a namespace, sorted
+    fn list_tables(&self, _namespace: &Namespace) -> Result<Vec<String>> {
+        Err(IcebergError::Unsupported(
+            "This catalog can't list tables".to_string(),
+        ))
+    }
+
+    // Views, unsupported by catalogs that only track tables
+    fn load_view(&self, ident: &TableIdent) -> Result<View> {
+        Err(IcebergError::Unsupported(format!(
+            "This catalog can't load views, such as {}",
+            ident
+        )))
+    }
+
+    // Names of the views of a namespace, sorted
+    fn list_views(&self, _namespace: &Namespace) -> Result<Vec<String>> {
+        Err(IcebergError::Unsupported(
+            "This catalog can't list views".to_string(),
+        ))
+    }
+
+    // Namespace management, unsupported by catalogs that only track tables
+    fn list_namespaces(&self) -> Result<Vec<NamespaceMetadata>> {
+        Err(namespaces_unsupported("list"))
+    }
+
+    fn create_namespace(
+        &self,
+        _namespace: &Namespace,
+        _properties: HashMap<String, String>,
+    ) -> Result<NamespaceMetadata> {
+        Err(namespaces_unsupported("create"))
+    }
+
+    // Fails if the namespace still has tables
+    fn drop_namespace(&self, _namespace: &Namespace) -> Result<()> {
+        Err(namespaces_unsupported("drop"))
+    }
+
+    // Sets the updated properties and removes the removed ones, returns the resulting namespace
+    fn update_namespace_properties(
+        &self,
+        _namespace: &Namespace,
+        _updates: HashMap<String, String>,
+        _removals: &[&str],
+    ) -> Result<NamespaceMetadata> {
+        Err(namespaces_unsupported("update"))
+    }
+}
+
+fn namespaces_unsupported(operation: &str) -> IcebergError {
+    IcebergError::Unsupported(format!("This catalog can't {} namespaces", operation))
+}
+
+// Metadata of a new table without snapshots at the location, with one schema, partition spec and
+// the unsorted sort order
+pub fn new_table_metadata(
+    location: String,
+    schema: StructType,
+    spec: PartitionSpec,
+) -> TableMetadataV2 {
+    let last_column_id = schema.fields.iter().map(|field| field.id).max();
+    let last_partition_id = spec.fields.iter().map(|field| field.field_id).max();
+    TableMetadataV2 {
+        format_version: 2,
+        table_uuid: Uuid::new_v4(),
+        location,
+        last_sequence_number: 0,
+        last_updated_ms: current_time_ms(),
+        last_column_id: last_column_id.unwrap_or(0),
+        schemas: vec![IcebergSchemaV2::new(0, None, schema)],
+        current_schema_id: 0,
+        default_spec_id: spec.spec_id,
+        partition_specs: vec![spec],
+        last_partition_id: last_partition_id.unwrap_or(999),
+        properties: None,
+        current_snapshot_id: None,
+        snapshots: None,
+        snapshot_log: None,
+        metadata_log: None,
+        sort_orders: vec![SortOrders {
+            order_id: 0,
+            fields: vec![],
+        }],
+        default_sort_order_id: 0,
+        refs: None,
+        statistics: None,
+        partition_statistics: None,
+    }
+}
+
+// Writes the metadata file following `base`, named "<version>-<uuid>.metadata.json" in the
+// metadata directory of the table, and returns its location. The metadata file of `base` is
+// added to the metadata log. Decimal types are written in the format of the
+// write.metadata.decimal-format property. Catalogs call this before swapping the metadata
+// location of the table
+pub fn write_metadata_file(
+    file_io: &dyn FileIO,
+    base: Option<&Table>,
+    metadata: TableMetadataV2,
+) -> Result<String> {
+    let metadata = metadata_to_commit(base, metadata)?;
+    let version = base.map_or(0, |base| {
+        metadata_file_version(base.metadata_location()) + 1
+    });
+    let location = format!(
+        "{}/metadata/{:05}-{}.metadata.json",
+        metadata.location.trim_end_matches('/'),
+        version,
+        Uuid::new_v4()
+    );
+    file_io.write(&location, metadata_file_contents(metadata)?)?;
+    Ok(location)
+}
+
+// JSON of a metadata file, writing decimal types in the format of the table properties
+pub fn metadata_file_contents(metadata: TableMetadataV2) -> Result<Bytes> {
+    let format = TableProperties::from_metadata(&metadata).metadata_decimal_format()?;
+    let data = to_json_with_decimal_format(&TableMetadata::V2(metadata), format)?;
+    Ok(Bytes::from(data))
+}
+
+// Checks that the metadata can be committed on top of `base` and adds the metadata file of
+// `base` to its metadata log. For catalogs naming metadata files their own way
+pub fn metadata_to_commit(
+    base: Option<&Table>,
+    mut metadata: TableMetadataV2,
+) -> Result<TableMetadataV2> {
+    ensure_writable(&format!("write metadata of table {}", metadata.location))?;
+    if let Some(base) = base.filter(|base| !base.supports(TableCapability::Commit)) {
+        return Err(IcebergError::Unsupported(format!(
+            "Committing to format version {} table {}",
+            base.format_version(),
+            base.ident()
+        )));
+    }
+    if let Some(base) = base {
+        let max_versions = TableProperties::from_metadata(&metadata).previous_versions_max()?;
+        let log = metadata.metadata_log.get_or_insert_with(Vec::new);
+        log.push(MetadataLog {
+            metadata_file: base.metadata_location().to_string(),
+            timestamp_ms: base.metadata().last_updated_ms,
+        });
+        if log.len() > max_versions {
+            log.drain(..log.len() - max_versions);
+        }
+    }
+    Ok(metadata)
+}
+
+// Files deleted when purging a table: data and delete files, manifests, manifest lists,
+// statistics files and metadata files, both current and logged, the latter last. Catalogs list
+// the files before dropping the table, so that a table whose manifests can't be read stays in
+// the catalog, and delete them with delete_files after
+pub fn table_files(table: &Table) -> Result<Vec<String>> {
+    let metadata = table.metadata();
+    let mut data_files = vec![];
+    let mut manifests = vec![];
+    let mut read = HashSet::new();
+    for snapshot in metadata.snapshots.iter().flatten() {
+        for manifest in table.manifests(snapshot)? {
+            // Manifests are shared by snapshots, read them once
+            if !read.insert(manifest.manifest_path.clone()) {
+                continue;
+            }
+            for entry in table.manifest_entries(&manifest)? {
+                data_files.push(entry.data_file.file_path);
+            }
+            manifests.push(manifest.manifest_path);
+        }
+    }
+    let snapshots = metadata.snapshots.iter().flatten();
+    let statistics = metadata.statistics.iter().flatten();
+    let partition_statistics = metadata.partition_statistics.iter().flatten();
+    let metadata_log = metadata.metadata_log.iter().flatten();
+    let mut files: Vec<String> = data_files
+        .into_iter()
+        .chain(manifests)
+        .chain(snapshots.map(|snapshot| snapshot.manifest_list.clone()))
+        .chain(statistics.map(|statistics| statistics.statistics_path.clone()))
+        .chain(partition_statistics.map(|statistics| statistics.statistics_path.clone()))
+        .chain(metadata_log.map(|entry| entry.metadata_file.clone()))
+        .chain([table.metadata_location().to_string()])
+        .collect();
+    let mut seen = HashSet::new();
+    files.retain(|file| seen.insert(file.clone()));
+    Ok(files)
+}
+
+// Deletes files as far as possible, failures are logged. Returns the number of deleted files
+pub fn delete_files(file_io: &dyn FileIO, files: &[String]) -> usize {
+    files
+        .iter()
+        .filter(|file| match file_io.delete(file) {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!("Couldn't delete {}: {}", file, e);
+                false
+            }
+        })
+        .count()
+}
+
+// Version of a metadata file named "<version>-<uuid>.metadata.json", 0 for other names
+fn metadata_file_version(location: &str) -> u32 {
+    location
+        .rsplit('/')
+        .next()
+        .and_then(|file| file.split('-').next())
+        .and_then(|version| version.parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_file_version() {
+        assert_eq!(
+            12,
+            metadata_file_version("file:/t/metadata/00012-8c1ed6a8.metadata.json")
+        );
+        assert_eq!(
+            0,
+            metadata_file_version("file:/t/metadata/v3.metadata.json")
+        );
+    }
+
+    #[test]
+    fn test_write_metadata_file_decimal_format() {
+        use crate::iceberg::io::MemoryFileIO;
+        use crate::iceberg::properties::METADATA_DECIMAL_FORMAT_PROPERTY;
+        use crate::iceberg::spec::schema::{
+            IcebergType, ListType, MapType, PrimitiveType, StructField,
+        };
+
+        let decimal =
+            |precision, scale| IcebergType::Primitive(PrimitiveType::Decimal { precision, scale });
+        let mut price = StructField::new(1, "price", false, decimal(10, 2));
+        price.doc = Some("decimal(1, 2)".to_string());
+        let schema = StructType {
+            fields: vec![
+                price,
+                StructField::new(
+                    2,
+                    "history",
+                    false,
+                    IcebergType::List(ListType {
+                        element_id: 3,
+                        element_required: false,
+                        element: Box::new(decimal(5, 1)),
+                    }),
+                ),
+                StructField::new(
+                    4,
+                    "rates",
+                    false,
+                    IcebergType::Map(MapType {
+                        key_id: 5,
+                        key: Box::new(IcebergType::Primitive(PrimitiveType::String)),
+                        value_id: 6,
+                        value_required: false,
+                        value: Box::new(decimal(7, 3)),
+                    }),
+                ),
+            ],
+        };
+        let file_io = MemoryFileIO::new();
+        let write = |properties: &[(&str, &str)]| {
+            let mut metadata = new_table_metadata(
+                "memory:/t".to_string(),
+                schema.clone(),
+                PartitionSpec {
+                    spec_id: 0,
+                    fields: vec![],
+                },
+            );
+            metadata.properties = Some(
+                properties
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            );
+            write_metadata_file(&file_io, None, metadata).map(|location| {
+                String::from_utf8(file_io.read(&location).unwrap().to_vec()).unwrap()
+            })
+        };
+
+        let spaced = write(&[]).unwrap();
+        assert!(spaced.contains(r#""type":"decimal(10, 2)""#));
+        let compact = write(&[
+            (METADATA_DECIMAL_FORMAT_PROPERTY, "compact"),
+            ("type", "decimal(1, 2)"),
+        ])
+        .unwrap();
+        for type_name in [
+            r#""type":"decimal(10,2)""#,
+            r#""element":"decimal(5,1)""#,
+            r#""value":"decimal(7,3)""#,
+        ] {
+            assert!(compact.contains(type_name), "{}", compact);
+        }
+        // Only types are rewritten
+        assert!(compact.contains(r#""doc":"decimal(1, 2)""#));
+        assert!(compact.contains(r#""type":"decimal(1, 2)""#));
+        let metadata: TableMetadataV2 = serde_json::from_str(&compact).unwrap();
+        assert_eq!(&schema, metadata.current_schema().unwrap().schema());
+
+        assert!(write(&[(METADATA_DECIMAL_FORMAT_PROPERTY, "tight")]).is_err());
+    }
+}
@@ -0,0 +1,1191 @@
+// Catalog of the Iceberg tables tracked by a Nessie server, over version 2 of its REST API. Nessie
+// versions the whole catalog like git: branches and tags point to commits, and each commit maps
+// content keys, the levels of the namespace followed by the name of the table, to the current
+// metadata location of tables. A catalog reads from one branch or tag and commits to it when it
+// is a branch. Commits are made against the hash the branch had when it was read, Nessie rejects
+// them when the table changed since
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::{IcebergCatalog, NamespaceMetadata};
+use crate::iceberg::catalog::write_metadata_file;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::ident::{Namespace, TableIdent};
+use crate::iceberg::io::FileIO;
+use crate::iceberg::retry::transient_error;
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+use crate::iceberg::table::Table;
+use crate::iceberg::view::View;
+
+// Branch of new Nessie repositories
+pub const DEFAULT_BRANCH: &str = "main";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReferenceType {
+    Branch,
+    Tag,
+}
+
+// A branch or tag and the hash of the commit it points to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reference {
+    #[serde(rename = "type")]
+    pub reference_type: ReferenceType,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+impl Reference {
+    // The reference as of its hash, "<name>@<hash>", as used in paths of the API
+    pub fn pinned(&self) -> String {
+        match &self.hash {
+            Some(hash) => format!("{}@{}", self.name, hash),
+            None => self.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ContentKey {
+    pub elements: Vec<String>,
+}
+
+impl ContentKey {
+    pub fn of_table(ident: &TableIdent) -> Self {
+        let mut elements = ident.namespace().levels().to_vec();
+        elements.push(ident.name().to_string());
+        ContentKey { elements }
+    }
+
+    pub fn of_namespace(namespace: &Namespace) -> Self {
+        ContentKey {
+            elements: namespace.levels().to_vec(),
+        }
+    }
+
+    // Dotted form used in paths of the API. Levels of namespaces and names of tables can't
+    // contain dots, so that elements don't need to be escaped
+    fn path(&self) -> String {
+        self.elements.join(".")
+    }
+
+    fn is_in(&self, namespace: &Namespace) -> bool {
+        self.elements.len() == namespace.levels().len() + 1
+            && self.elements.starts_with(namespace.levels())
+    }
+}
+
+// Content of a key. Contents are identified by an id assigned by Nessie when they are added, which
+// updates must keep
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Content {
+    IcebergTable(IcebergTableContent),
+    IcebergView(IcebergViewContent),
+    Namespace(NamespaceContent),
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IcebergTableContent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub metadata_location: String,
+    pub snapshot_id: i64,
+    pub schema_id: i32,
+    pub spec_id: i32,
+    pub sort_order_id: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IcebergViewContent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub metadata_location: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NamespaceContent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub elements: Vec<String>,
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+}
+
+impl IcebergTableContent {
+    fn new(id: Option<String>, metadata_location: &str, metadata: &TableMetadataV2) -> Self {
+        IcebergTableContent {
+            id,
+            metadata_location: metadata_location.to_string(),
+            snapshot_id: metadata.current_snapshot_id.unwrap_or(-1),
+            schema_id: metadata.current_schema_id,
+            spec_id: metadata.default_spec_id,
+            sort_order_id: metadata.default_sort_order_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Operation {
+    Put { key: ContentKey, content: Content },
+    Delete { key: ContentKey },
+}
+
+// A key of a reference and the type of its content, e.g. ICEBERG_TABLE
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    #[serde(rename = "type")]
+    pub content_type: String,
+    pub name: ContentKey,
+}
+
+const ICEBERG_TABLE: &str = "ICEBERG_TABLE";
+const ICEBERG_VIEW: &str = "ICEBERG_VIEW";
+const NAMESPACE: &str = "NAMESPACE";
+
+// The calls of the Nessie API made by the catalog. Implemented over HTTP by NessieHttpClient,
+// tests implement it in memory. References are given as "<name>" or "<name>@<hash>"
+pub trait NessieClient: fmt::Debug + Send + Sync {
+    fn get_reference(&self, name: &str) -> Result<Reference>;
+    // None when the key has no content on the reference
+    fn get_content(&self, reference: &str, key: &ContentKey) -> Result<Option<Content>>;
+    fn get_entries(&self, reference: &str) -> Result<Vec<Entry>>;
+    // Commits the operations to the branch and returns its new head. Fails if keys of the
+    // operations changed after the hash of `branch`
+    fn commit(
+        &self,
+        branch: &Reference,
+        message: &str,
+        operations: Vec<Operation>,
+    ) -> Result<Reference>;
+    fn create_reference(
+        &self,
+        name: &str,
+        reference_type: ReferenceType,
+        source: &Reference,
+    ) -> Result<Reference>;
+    // Merges the commits of `source` into the branch `target`, returns the new head of `target`
+    fn merge(&self, target: &Reference, source: &Reference) -> Result<Reference>;
+}
+
+// Client of the REST API v2 of a Nessie server, e.g. at http://localhost:19120/api/v2
+#[derive(Debug, Clone)]
+pub struct NessieHttpClient {
+    uri: String,
+    agent: ureq::Agent,
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ReferenceResponse {
+    reference: Reference,
+}
+
+#[derive(Deserialize)]
+struct ContentResponse {
+    content: Content,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntriesResponse {
+    entries: Vec<Entry>,
+    #[serde(default)]
+    has_more: bool,
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitResponse {
+    target_branch: Reference,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MergeResponse {
+    resultant_target_hash: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorResponse {
+    message: String,
+    error_code: Option<String>,
+}
+
+impl NessieHttpClient {
+    pub fn new(uri: &str) -> Self {
+        NessieHttpClient {
+            uri: uri.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new().build(),
+            token: None,
+        }
+    }
+
+    // Authenticates requests with the bearer token
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let request = self.agent.request(method, &format!("{}{}", self.uri, path));
+        match &self.token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+
+    fn send<T: for<'de> Deserialize<'de>>(
+        &self,
+        request: ureq::Request,
+        body: Option<serde_json::Value>,
+        object: &str,
+    ) -> Result<T> {
+        let response = match body {
+            Some(body) => request.send_json(body),
+            None => request.call(),
+        };
+        match response {
+            Ok(response) => Ok(response.into_json()?),
+            Err(ureq::Error::Status(status, response)) => {
+                let error = response.into_json::<ErrorResponse>().ok();
+                Err(nessie_error(status, error, object))
+            }
+            Err(e) => Err(transient_error(format!("{}: {}", object, e))),
+        }
+    }
+}
+
+// Maps the status of failed requests to errors of the crate, keeping the message of Nessie.
+// Throttled requests and server errors are transient, see RetryPolicy. Commits rejected because
+// the branch moved on are conflicts
+fn nessie_error(status: u16, error: Option<ErrorResponse>, object: &str) -> IcebergError {
+    let message = match &error {
+        Some(error) => format!("{}: {}", object, error.message),
+        None => format!("{}: HTTP status {}", object, status),
+    };
+    if error.as_ref().and_then(|e| e.error_code.as_deref()) == Some("REFERENCE_CONFLICT") {
+        return IcebergError::CommitConflict(message);
+    }
+    match status {
+        400 | 409 => IcebergError::Invalid(message),
+        401 | 403 => IcebergError::Forbidden(message),
+        404 => IcebergError::NotFound(message),
+        429 | 500..=599 => transient_error(message),
+        _ => IcebergError::Io(std::io::Error::other(message)),
+    }
+}
+
+impl NessieClient for NessieHttpClient {
+    #[tracing::instrument(
+        name = "nessie.get_reference",
+        level = "debug",
+        skip_all,
+        fields(reference = name),
+        err
+    )]
+    fn get_reference(&self, name: &str) -> Result<Reference> {
+        let request = self.request("GET", &format!("/trees/{}", encode(name)));
+        let response: ReferenceResponse =
+            self.send(request, None, &format!("Reference {}", name))?;
+        Ok(response.reference)
+    }
+
+    #[tracing::instrument(
+        name = "nessie.get_content",
+        level = "debug",
+        skip_all,
+        fields(reference, key = %key.path()),
+        err
+    )]
+    fn get_content(&self, reference: &str, key: &ContentKey) -> Result<Option<Content>> {
+        let path = format!(
+            "/trees/{}/contents/{}",
+            encode(reference),
+            encode(&key.path())
+        );
+        let object = format!("Content {} on {}", key.path(), reference);
+        let request = self.request("GET", &path);
+        match request.call() {
+            Ok(response) => Ok(Some(response.into_json::<ContentResponse>()?.content)),
+            Err(ureq::Error::Status(status, response)) => {
+                let error = response.into_json::<ErrorResponse>().ok();
+                // Missing references are errors, missing contents aren't
+                match error.as_ref().and_then(|e| e.error_code.as_deref()) {
+                    Some("CONTENT_NOT_FOUND") => Ok(None),
+                    _ => Err(nessie_error(status, error, &object)),
+                }
+            }
+            Err(e) => Err(transient_error(format!("{}: {}", object, e))),
+        }
+    }
+
+    #[tracing::instrument(
+        name = "nessie.get_entries",
+        level = "debug",
+        skip_all,
+        fields(reference),
+        err
+    )]
+    fn get_entries(&self, reference: &str) -> Result<Vec<Entry>> {
+        let mut entries = vec![];
+        let mut token: Option<String> = None;
+        loop {
+            let mut request = self.request("GET", &format!("/trees/{}/entries", encode(reference)));
+            if let Some(token) = &token {
+                request = request.query("token", token);
+            }
+            let response: EntriesResponse =
+                self.send(request, None, &format!("Entries of {}", reference))?;
+            entries.extend(response.entries);
+            match response.token.filter(|_| response.has_more) {
+                Some(next) => token = Some(next),
+                None => return Ok(entries),
+            }
+        }
+    }
+
+    #[tracing::instrument(
+        name = "nessie.commit",
+        level = "debug",
+        skip_all,
+        fields(branch = %branch.name),
+        err
+    )]
+    fn commit(
+        &self,
+        branch: &Reference,
+        message: &str,
+        operations: Vec<Operation>,
+    ) -> Result<Reference> {
+        let path = format!("/trees/{}/history/commit", encode(&branch.pinned()));
+        let body = serde_json::json!({
+            "commitMeta": {"message": message},
+            "operations": operations,
+        });
+        let request = self.request("POST", &path);
+        let response: CommitResponse =
+            self.send(request, Some(body), &format!("Commit to {}", branch.name))?;
+        Ok(response.target_branch)
+    }
+
+    #[tracing::instrument(
+        name = "nessie.create_reference",
+        level = "debug",
+        skip_all,
+        fields(reference = name),
+        err
+    )]
+    fn create_reference(
+        &self,
+        name: &str,
+        reference_type: ReferenceType,
+        source: &Reference,
+    ) -> Result<Reference> {
+        let reference_type = match reference_type {
+            ReferenceType::Branch => "BRANCH",
+            ReferenceType::Tag => "TAG",
+        };
+        let request = self
+            .request("POST", "/trees")
+            .query("name", name)
+            .query("type", reference_type);
+        let response: ReferenceResponse = self.send(
+            request,
+            Some(serde_json::to_value(source)?),
+            &format!("Reference {}", name),
+        )?;
+        Ok(response.reference)
+    }
+
+    #[tracing::instrument(
+        name = "nessie.merge",
+        level = "debug",
+        skip_all,
+        fields(source = %source.name, target = %target.name),
+        err
+    )]
+    fn merge(&self, target: &Reference, source: &Reference) -> Result<Reference> {
+        let path = format!("/trees/{}/history/merge", encode(&target.pinned()));
+        let body = serde_json::json!({
+            "fromRefName": source.name,
+            "fromHash": source.hash,
+        });
+        let request = self.request("POST", &path);
+        let object = format!("Merge of {} into {}", source.name, target.name);
+        let response: MergeResponse = self.send(request, Some(body), &object)?;
+        Ok(Reference {
+            hash: Some(response.resultant_target_hash),
+            ..target.clone()
+        })
+    }
+}
+
+// Percent-encodes a segment of a path. '@' separates the name and hash of references
+fn encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'@' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct NessieCatalog {
+    client: Arc<dyn NessieClient>,
+    file_io: Arc<dyn FileIO>,
+    // Branch or tag the catalog reads from, and commits to if it is a branch
+    reference: String,
+}
+
+impl NessieCatalog {
+    // Catalog of the default branch
+    pub fn new(client: Arc<dyn NessieClient>, file_io: Arc<dyn FileIO>) -> Self {
+        NessieCatalog {
+            client,
+            file_io,
+            reference: DEFAULT_BRANCH.to_string(),
+        }
+    }
+
+    // The catalog as of another branch or tag
+    pub fn on_reference(&self, reference: &str) -> Self {
+        NessieCatalog {
+            reference: reference.to_string(),
+            ..self.clone()
+        }
+    }
+
+    pub fn reference_name(&self) -> &str {
+        &self.reference
+    }
+
+    // Current head of the reference of the catalog
+    pub fn reference(&self) -> Result<Reference> {
+        self.client.get_reference(&self.reference)
+    }
+
+    // Creates a branch, respectively a tag, at the current head of the reference of the catalog
+    pub fn create_branch(&self, name: &str) -> Result<Reference> {
+        self.client
+            .create_reference(name, ReferenceType::Branch, &self.reference()?)
+    }
+
+    pub fn create_tag(&self, name: &str) -> Result<Reference> {
+        self.client
+            .create_reference(name, ReferenceType::Tag, &self.reference()?)
+    }
+
+    // Merges the commits of the reference of the catalog into the branch, e.g. to publish the
+    // changes made on a work branch to the default branch
+    pub fn merge_into(&self, branch: &str) -> Result<Reference> {
+        let target = self.client.get_reference(branch)?;
+        if target.reference_type != ReferenceType::Branch {
+            return Err(IcebergError::Unsupported(format!(
+                "Merging into tag {}, only branches can be changed",
+                branch
+            )));
+        }
+        self.client.merge(&target, &self.reference()?)
+    }
+
+    // Adds an existing table to the branch, whose current metadata is at `metadata_location`
+    pub fn register_table(&self, ident: &TableIdent, metadata_location: &str) -> Result<Table> {
+        let branch = self.branch()?;
+        let key = ContentKey::of_table(ident);
+        if self.client.get_content(&branch.pinned(), &key)?.is_some() {
+            return Err(IcebergError::Invalid(format!(
+                "Table {} already exists on {}",
+                ident, branch.name
+            )));
+        }
+        let table = Table::load(
+            ident.clone(),
+            metadata_location.to_string(),
+            self.file_io.clone(),
+        )?;
+        let content = IcebergTableContent::new(None, metadata_location, table.metadata());
+        self.client.commit(
+            &branch,
+            &format!("Register table {}", ident),
+            vec![Operation::Put {
+                key,
+                content: Content::IcebergTable(content),
+            }],
+        )?;
+        Ok(table)
+    }
+
+    // Head of the reference of the catalog, which must be a branch to be committed to
+    fn branch(&self) -> Result<Reference> {
+        let reference = self.reference()?;
+        match reference.reference_type {
+            ReferenceType::Branch => Ok(reference),
+            ReferenceType::Tag => Err(IcebergError::Unsupported(format!(
+                "Committing to tag {}, only branches can be changed",
+                reference.name
+            ))),
+        }
+    }
+
+    fn table_content(&self, reference: &str, ident: &TableIdent) -> Result<IcebergTableContent> {
+        match self
+            .client
+            .get_content(reference, &ContentKey::of_table(ident))?
+        {
+            Some(Content::IcebergTable(content)) => Ok(content),
+            Some(_) => Err(IcebergError::Invalid(format!(
+                "{} is not an Iceberg table",
+                ident
+            ))),
+            None => Err(IcebergError::NotFound(format!(
+                "Table {} on {}",
+                ident, self.reference
+            ))),
+        }
+    }
+
+    fn namespace_content(
+        &self,
+        reference: &str,
+        namespace: &Namespace,
+    ) -> Result<NamespaceContent> {
+        match self
+            .client
+            .get_content(reference, &ContentKey::of_namespace(namespace))?
+        {
+            Some(Content::Namespace(content)) => Ok(content),
+            Some(_) => Err(IcebergError::Invalid(format!(
+                "{} is not a namespace",
+                namespace
+            ))),
+            None => Err(IcebergError::NotFound(format!(
+                "Namespace {} on {}",
+                namespace, self.reference
+            ))),
+        }
+    }
+
+    // Names of the keys of the namespace with contents of the given type, sorted
+    fn list_of_type(&self, namespace: &Namespace, content_type: &str) -> Result<Vec<String>> {
+        let mut names: Vec<String> = self
+            .client
+            .get_entries(&self.reference)?
+            .into_iter()
+            .filter(|entry| entry.content_type == content_type && entry.name.is_in(namespace))
+            .filter_map(|entry| entry.name.elements.last().cloned())
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+}
+
+impl IcebergCatalog for NessieCatalog {
+    fn load_table(&self, ident: &TableIdent) -> Result<Table> {
+        let content = self.table_content(&self.reference, ident)?;
+        Table::load(
+            ident.clone(),
+            content.metadata_location,
+            self.file_io.clone(),
+        )
+    }
+
+    fn refresh_table(&self, table: &Table) -> Result<Table> {
+        let content = self.table_content(&self.reference, table.ident())?;
+        table.refresh(&content.metadata_location)
+    }
+
+    fn commit_table(&self, base: &Table, metadata: TableMetadataV2) -> Result<Table> {
+        let ident = base.ident();
+        let branch = self.branch()?;
+        let current = self.table_content(&branch.pinned(), ident)?;
+        if current.metadata_location != base.metadata_location() {
+            return Err(IcebergError::CommitConflict(format!(
+                "Table {} changed on {} since it was loaded",
+                ident, branch.name
+            )));
+        }
+        let location = write_metadata_file(base.file_io().as_ref(), Some(base), metadata)?;
+        let table = Table::load(ident.clone(), location.clone(), base.file_io().clone())?;
+        // Nessie rejects the commit if the table changed after the hash the branch was read at
+        let content = IcebergTableContent::new(current.id, &location, table.metadata());
+        self.client.commit(
+            &branch,
+            &format!("Update table {}", ident),
+            vec![Operation::Put {
+                key: ContentKey::of_table(ident),
+                content: Content::IcebergTable(content),
+            }],
+        )?;
+        Ok(table)
+    }
+
+    fn drop_table(&self, ident: &TableIdent, purge: bool) -> Result<()> {
+        if purge {
+            return Err(IcebergError::Unsupported(format!(
+                "Purging table {}, its files may be referenced by other branches and tags",
+                ident
+            )));
+        }
+        let branch = self.branch()?;
+        self.table_content(&branch.pinned(), ident)?;
+        self.client.commit(
+            &branch,
+            &format!("Drop table {}", ident),
+            vec![Operation::Delete {
+                key: ContentKey::of_table(ident),
+            }],
+        )?;
+        Ok(())
+    }
+
+    fn list_tables(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        self.list_of_type(namespace, ICEBERG_TABLE)
+    }
+
+    fn load_view(&self, ident: &TableIdent) -> Result<View> {
+        match self
+            .client
+            .get_content(&self.reference, &ContentKey::of_table(ident))?
+        {
+            Some(Content::IcebergView(content)) => View::load(
+                ident.clone(),
+                content.metadata_location,
+                self.file_io.clone(),
+            ),
+            Some(_) => Err(IcebergError::Invalid(format!(
+                "{} is not an Iceberg view",
+                ident
+            ))),
+            None => Err(IcebergError::NotFound(format!(
+                "View {} on {}",
+                ident, self.reference
+            ))),
+        }
+    }
+
+    fn list_views(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        self.list_of_type(namespace, ICEBERG_VIEW)
+    }
+
+    fn list_namespaces(&self) -> Result<Vec<NamespaceMetadata>> {
+        let mut namespaces = self
+            .client
+            .get_entries(&self.reference)?
+            .into_iter()
+            .filter(|entry| entry.content_type == NAMESPACE)
+            .map(|entry| {
+                let name = Namespace::try_new(entry.name.elements)?;
+                let content = self.namespace_content(&self.reference, &name)?;
+                Ok(NamespaceMetadata {
+                    name,
+                    properties: content.properties,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        namespaces.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(namespaces)
+    }
+
+    fn create_namespace(
+        &self,
+        namespace: &Namespace,
+        properties: HashMap<String, String>,
+    ) -> Result<NamespaceMetadata> {
+        let branch = self.branch()?;
+        let key = ContentKey::of_namespace(namespace);
+        if self.client.get_content(&branch.pinned(), &key)?.is_some() {
+            return Err(IcebergError::Invalid(format!(
+                "Namespace {} already exists on {}",
+                namespace, branch.name
+            )));
+        }
+        let content = NamespaceContent {
+            id: None,
+            elements: namespace.levels().to_vec(),
+            properties: properties.clone(),
+        };
+        self.client.commit(
+            &branch,
+            &format!("Create namespace {}", namespace),
+            vec![Operation::Put {
+                key,
+                content: Content::Namespace(content),
+            }],
+        )?;
+        Ok(NamespaceMetadata {
+            name: namespace.clone(),
+            properties,
+        })
+    }
+
+    fn drop_namespace(&self, namespace: &Namespace) -> Result<()> {
+        let branch = self.branch()?;
+        self.namespace_content(&branch.pinned(), namespace)?;
+        let levels = namespace.levels();
+        let nested = self
+            .client
+            .get_entries(&branch.pinned())?
+            .into_iter()
+            .any(|entry| {
+                entry.name.elements.len() > levels.len() && entry.name.elements.starts_with(levels)
+            });
+        if nested {
+            return Err(IcebergError::Invalid(format!(
+                "Namespace {} is not empty",
+                namespace
+            )));
+        }
+        self.client.commit(
+            &branch,
+            &format!("Drop namespace {}", namespace),
+            vec![Operation::Delete {
+                key: ContentKey::of_namespace(namespace),
+            }],
+        )?;
+        Ok(())
+    }
+
+    fn update_namespace_properties(
+        &self,
+        namespace: &Namespace,
+        updates: HashMap<String, String>,
+        removals: &[&str],
+    ) -> Result<NamespaceMetadata> {
+        let branch = self.branch()?;
+        let mut content = self.namespace_content(&branch.pinned(), namespace)?;
+        for key in removals {
+            content.properties.remove(*key);
+        }
+        content.properties.extend(updates);
+        let properties = content.properties.clone();
+        self.client.commit(
+            &branch,
+            &format!("Update properties of namespace {}", namespace),
+            vec![Operation::Put {
+                key: ContentKey::of_namespace(namespace),
+                content: Content::Namespace(content),
+            }],
+        )?;
+        Ok(NamespaceMetadata {
+            name: namespace.clone(),
+            properties,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::iceberg::io::LocalFileIO;
+    use crate::iceberg::retry::is_transient;
+    use crate::iceberg::test_utils::{create_table, ids_batch};
+    use crate::iceberg::writer::partitioned::PartitionedWriter;
+
+    // Nessie repository kept in memory. Merges only fast-forward, which is enough to test the
+    // catalog
+    #[derive(Debug)]
+    struct FakeNessie {
+        state: Mutex<FakeState>,
+    }
+
+    #[derive(Debug, Default)]
+    struct FakeState {
+        references: BTreeMap<String, Reference>,
+        // Contents and parent of commits by hash
+        commits: HashMap<String, (BTreeMap<ContentKey, Content>, Option<String>)>,
+        next_id: u64,
+    }
+
+    impl FakeNessie {
+        fn new() -> Self {
+            let mut state = FakeState::default();
+            state
+                .commits
+                .insert("0".to_string(), (BTreeMap::new(), None));
+            state.references.insert(
+                DEFAULT_BRANCH.to_string(),
+                Reference {
+                    reference_type: ReferenceType::Branch,
+                    name: DEFAULT_BRANCH.to_string(),
+                    hash: Some("0".to_string()),
+                },
+            );
+            FakeNessie {
+                state: Mutex::new(state),
+            }
+        }
+    }
+
+    impl FakeState {
+        fn resolve(&self, reference: &str) -> Result<String> {
+            let (name, hash) = match reference.split_once('@') {
+                Some((name, hash)) => (name, Some(hash.to_string())),
+                None => (reference, None),
+            };
+            let head = self
+                .references
+                .get(name)
+                .and_then(|reference| reference.hash.clone())
+                .ok_or_else(|| IcebergError::NotFound(format!("Reference {}", name)))?;
+            Ok(hash.unwrap_or(head))
+        }
+
+        fn contents(&self, hash: &str) -> &BTreeMap<ContentKey, Content> {
+            &self.commits[hash].0
+        }
+
+        fn is_ancestor(&self, ancestor: &str, hash: &str) -> bool {
+            let mut current = Some(hash.to_string());
+            while let Some(hash) = current {
+                if hash == ancestor {
+                    return true;
+                }
+                current = self.commits[&hash].1.clone();
+            }
+            false
+        }
+
+        fn add_commit(&mut self, contents: BTreeMap<ContentKey, Content>, parent: &str) -> String {
+            self.next_id += 1;
+            let hash = format!("{:x}", self.next_id);
+            self.commits
+                .insert(hash.clone(), (contents, Some(parent.to_string())));
+            hash
+        }
+    }
+
+    impl NessieClient for FakeNessie {
+        fn get_reference(&self, name: &str) -> Result<Reference> {
+            let state = self.state.lock().unwrap();
+            state
+                .references
+                .get(name)
+                .cloned()
+                .ok_or_else(|| IcebergError::NotFound(format!("Reference {}", name)))
+        }
+
+        fn get_content(&self, reference: &str, key: &ContentKey) -> Result<Option<Content>> {
+            let state = self.state.lock().unwrap();
+            let hash = state.resolve(reference)?;
+            Ok(state.contents(&hash).get(key).cloned())
+        }
+
+        fn get_entries(&self, reference: &str) -> Result<Vec<Entry>> {
+            let state = self.state.lock().unwrap();
+            let hash = state.resolve(reference)?;
+            Ok(state
+                .contents(&hash)
+                .iter()
+                .map(|(key, content)| Entry {
+                    content_type: match content {
+                        Content::IcebergTable(_) => ICEBERG_TABLE,
+                        Content::IcebergView(_) => ICEBERG_VIEW,
+                        Content::Namespace(_) => NAMESPACE,
+                        Content::Unknown => "UNKNOWN",
+                    }
+                    .to_string(),
+                    name: key.clone(),
+                })
+                .collect())
+        }
+
+        fn commit(
+            &self,
+            branch: &Reference,
+            _message: &str,
+            operations: Vec<Operation>,
+        ) -> Result<Reference> {
+            let mut state = self.state.lock().unwrap();
+            let head = state.resolve(&branch.name)?;
+            let expected = branch.hash.clone().unwrap_or_else(|| head.clone());
+            let mut contents = state.contents(&head).clone();
+            for operation in operations {
+                let key = match &operation {
+                    Operation::Put { key, .. } | Operation::Delete { key } => key.clone(),
+                };
+                if state.contents(&expected).get(&key) != contents.get(&key) {
+                    return Err(IcebergError::CommitConflict(format!(
+                        "Key {} changed on {} after {}",
+                        key.path(),
+                        branch.name,
+                        expected
+                    )));
+                }
+                match operation {
+                    Operation::Put { key, mut content } => {
+                        state.next_id += 1;
+                        let id = Some(format!("content-{}", state.next_id));
+                        match &mut content {
+                            Content::IcebergTable(table) => table.id = table.id.take().or(id),
+                            Content::Namespace(namespace) => {
+                                namespace.id = namespace.id.take().or(id)
+                            }
+                            _ => {}
+                        }
+                        contents.insert(key, content);
+                    }
+                    Operation::Delete { key } => {
+                        contents.remove(&key);
+                    }
+                }
+            }
+            let hash = state.add_commit(contents, &head);
+            let reference = state.references.get_mut(&branch.name).unwrap();
+            reference.hash = Some(hash);
+            Ok(reference.clone())
+        }
+
+        fn create_reference(
+            &self,
+            name: &str,
+            reference_type: ReferenceType,
+            source: &Reference,
+        ) -> Result<Reference> {
+            let mut state = self.state.lock().unwrap();
+            if state.references.contains_key(name) {
+                return Err(IcebergError::Invalid(format!(
+                    "Reference {} already exists",
+                    name
+                )));
+            }
+            let reference = Reference {
+                reference_type,
+                name: name.to_string(),
+                hash: source.hash.clone(),
+            };
+            state.references.insert(name.to_string(), reference.clone());
+            Ok(reference)
+        }
+
+        fn merge(&self, target: &Reference, source: &Reference) -> Result<Reference> {
+            let mut state = self.state.lock().unwrap();
+            let head = state.resolve(&target.name)?;
+            let source_hash = state.resolve(&source.pinned())?;
+            if !state.is_ancestor(&head, &source_hash) {
+                return Err(IcebergError::Invalid(format!(
+                    "Merge of {} into {} conflicts",
+                    source.name, target.name
+                )));
+            }
+            let reference = state.references.get_mut(&target.name).unwrap();
+            reference.hash = Some(source_hash);
+            Ok(reference.clone())
+        }
+    }
+
+    fn append(catalog: &NessieCatalog, table: &Table, ids: &[i64]) -> Result<Table> {
+        let mut writer = PartitionedWriter::for_table(table)?;
+        writer.write(&ids_batch(ids))?;
+        table
+            .new_append()
+            .add_files(writer.close()?)
+            .commit(catalog)
+    }
+
+    fn catalog() -> NessieCatalog {
+        NessieCatalog::new(Arc::new(FakeNessie::new()), Arc::new(LocalFileIO::new()))
+    }
+
+    #[test]
+    fn test_branches_and_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = catalog();
+        let ident: TableIdent = "db.t".parse().unwrap();
+        main.create_namespace(&"db".parse().unwrap(), HashMap::new())
+            .unwrap();
+        let created = create_table(dir.path());
+        main.register_table(&ident, created.metadata_location())
+            .unwrap();
+        assert!(main
+            .register_table(&ident, created.metadata_location())
+            .is_err());
+        assert_eq!(vec!["t"], main.list_tables(&"db".parse().unwrap()).unwrap());
+
+        // Changes made on a branch are invisible on main until they are merged
+        main.create_branch("etl").unwrap();
+        let etl = main.on_reference("etl");
+        let table = etl.load_table(&ident).unwrap();
+        let table = append(&etl, &table, &[1, 2]).unwrap();
+        assert!(main
+            .load_table(&ident)
+            .unwrap()
+            .metadata()
+            .current_snapshot()
+            .is_none());
+        let refreshed = etl
+            .refresh_table(&main.load_table(&ident).unwrap())
+            .unwrap();
+        assert_eq!(table.metadata_location(), refreshed.metadata_location());
+
+        main.create_tag("before-etl").unwrap();
+        etl.merge_into(DEFAULT_BRANCH).unwrap();
+        let merged = main.load_table(&ident).unwrap();
+        assert_eq!(table.metadata_location(), merged.metadata_location());
+
+        // Tags keep the table as it was, and can't be committed to
+        let tag = main.on_reference("before-etl");
+        let tagged = tag.load_table(&ident).unwrap();
+        assert!(tagged.metadata().current_snapshot().is_none());
+        assert!(matches!(
+            append(&tag, &tagged, &[3]),
+            Err(IcebergError::Unsupported(_))
+        ));
+        assert!(matches!(
+            etl.merge_into("before-etl"),
+            Err(IcebergError::Unsupported(_))
+        ));
+        assert!(matches!(
+            main.on_reference("missing").load_table(&ident),
+            Err(IcebergError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_concurrent_commits() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = catalog();
+        let ident: TableIdent = "db.t".parse().unwrap();
+        let created = create_table(dir.path());
+        let table = catalog
+            .register_table(&ident, created.metadata_location())
+            .unwrap();
+        append(&catalog, &table, &[1]).unwrap();
+        // The table changed since it was loaded
+        let error = catalog
+            .commit_table(&table, table.metadata().clone())
+            .unwrap_err();
+        assert!(
+            matches!(error, IcebergError::CommitConflict(_)),
+            "{}",
+            error
+        );
+
+        let table = catalog.load_table(&ident).unwrap();
+        let table = append(&catalog, &table, &[2]).unwrap();
+        assert_eq!(2, table.metadata().snapshots.as_ref().unwrap().len());
+
+        assert!(catalog.drop_table(&ident, true).is_err());
+        catalog.drop_table(&ident, false).unwrap();
+        assert!(matches!(
+            catalog.load_table(&ident),
+            Err(IcebergError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = catalog();
+        let db: Namespace = "db".parse().unwrap();
+        let properties = HashMap::from([("owner".to_string(), "etl".to_string())]);
+        catalog.create_namespace(&db, properties.clone()).unwrap();
+        assert!(catalog.create_namespace(&db, HashMap::new()).is_err());
+        catalog
+            .create_namespace(&"db.nested".parse().unwrap(), HashMap::new())
+            .unwrap();
+        let namespaces = catalog.list_namespaces().unwrap();
+        assert_eq!(
+            vec!["db", "db.nested"],
+            namespaces
+                .iter()
+                .map(|namespace| namespace.name.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(properties, namespaces[0].properties);
+
+        let updated = catalog
+            .update_namespace_properties(
+                &db,
+                HashMap::from([("retention".to_string(), "30d".to_string())]),
+                &["owner"],
+            )
+            .unwrap();
+        assert_eq!(
+            HashMap::from([("retention".to_string(), "30d".to_string())]),
+            updated.properties
+        );
+
+        let ident: TableIdent = "db.nested.t".parse().unwrap();
+        let created = create_table(dir.path());
+        catalog
+            .register_table(&ident, created.metadata_location())
+            .unwrap();
+        assert!(catalog.list_tables(&db).unwrap().is_empty());
+        assert_eq!(vec!["t"], catalog.list_tables(ident.namespace()).unwrap());
+        assert!(catalog.drop_namespace(&db).is_err());
+        catalog.drop_table(&ident, false).unwrap();
+        catalog.drop_namespace(ident.namespace()).unwrap();
+        catalog.drop_namespace(&db).unwrap();
+        assert!(catalog.list_namespaces().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_api_json() {
+        let content: Content = serde_json::from_str(
+            r#"{"type": "ICEBERG_TABLE", "id": "c1", "metadataLocation": "s3://b/t/v1.json",
+                "snapshotId": 5, "schemaId": 0, "specId": 0, "sortOrderId": 0}"#,
+        )
+        .unwrap();
+        let Content::IcebergTable(table) = &content else {
+            panic!("Unexpected content {:?}", content);
+        };
+        assert_eq!("s3://b/t/v1.json", table.metadata_location);
+        let content: Content =
+            serde_json::from_str(r#"{"type": "DELTA_LAKE_TABLE", "id": "c2"}"#).unwrap();
+        assert_eq!(Content::Unknown, content);
+
+        let operation = Operation::Delete {
+            key: ContentKey::of_table(&"db.t".parse().unwrap()),
+        };
+        assert_eq!(
+            r#"{"type":"DELETE","key":{"elements":["db","t"]}}"#,
+            serde_json::to_string(&operation).unwrap()
+        );
+        let reference: Reference =
+            serde_json::from_str(r#"{"type": "TAG", "name": "v1", "hash": "ab12"}"#).unwrap();
+        assert_eq!("v1@ab12", reference.pinned());
+        assert_eq!("db.t%2Fx@ab", encode("db.t/x@ab"));
+    }
+
+    #[test]
+    fn test_nessie_error() {
+        let error = nessie_error(409, None, "Commit to main");
+        assert!(matches!(error, IcebergError::Invalid(_)));
+        assert!(!is_transient(&error));
+        let conflict = ErrorResponse {
+            message: "Hash abc on main isn't the expected head".to_string(),
+            error_code: Some("REFERENCE_CONFLICT".to_string()),
+        };
+        let error = nessie_error(409, Some(conflict), "Commit to main");
+        assert!(matches!(error, IcebergError::CommitConflict(_)));
+        for status in [429, 503] {
+            let error = nessie_error(status, None, "Reference main");
+            assert!(is_transient(&error), "{}", status);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{IcebergCatalog, NamespaceMetadata};
+use crate::iceberg::error::Result;
+use crate::iceberg::ident::{Namespace, TableIdent};
+use crate::iceberg::read_only::{read_only_error, ReadOnlyFileIO};
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+use crate::iceberg::table::Table;
+use crate::iceberg::view::View;
+
+// Catalog rejecting commits and drops with IcebergError::ReadOnly. Loaded tables read through a
+// ReadOnlyFileIO, so that data and manifest files can't be written through them either
+#[derive(Debug, Clone)]
+pub struct ReadOnlyCatalog {
+    catalog: Arc<dyn IcebergCatalog>,
+}
+
+impl ReadOnlyCatalog {
+    pub fn new(catalog: Arc<dyn IcebergCatalog>) -> Self {
+        ReadOnlyCatalog { catalog }
+    }
+
+    fn read_only(table: Table) -> Table {
+        let file_io = Arc::new(ReadOnlyFileIO::new(table.file_io().clone()));
+        table.with_file_io(file_io)
+    }
+}
+
+impl IcebergCatalog for ReadOnlyCatalog {
+    fn load_table(&self, ident: &TableIdent) -> Result<Table> {
+        Ok(Self::read_only(self.catalog.load_table(ident)?))
+    }
+
+    fn refresh_table(&self, table: &Table) -> Result<Table> {
+        Ok(Self::read_only(self.catalog.refresh_table(table)?))
+    }
+
+    fn commit_table(&self, base: &Table, _metadata: TableMetadataV2) -> Result<Table> {
+        Err(read_only_error(&format!(
+            "commit to table {}",
+            base.ident()
+        )))
+    }
+
+    fn drop_table(&self, ident: &TableIdent, _purge: bool) -> Result<()> {
+        Err(read_only_error(&format!("drop table {}", ident)))
+    }
+
+    fn current_time_ms(&self) -> Result<i64> {
+        self.catalog.current_time_ms()
+    }
+
+    fn list_tables(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        self.catalog.list_tables(namespace)
+    }
+
+    fn load_view(&self, ident: &TableIdent) -> Result<View> {
+        self.catalog.load_view(ident)
+    }
+
+    fn list_views(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        self.catalog.list_views(namespace)
+    }
+
+    fn list_namespaces(&self) -> Result<Vec<NamespaceMetadata>> {
+        self.catalog.list_namespaces()
+    }
+
+    fn create_namespace(
+        &self,
+        namespace: &Namespace,
+        _properties: HashMap<String, String>,
+    ) -> Result<NamespaceMetadata> {
+        Err(read_only_error(&format!("create namespace {}", namespace)))
+    }
+
+    fn drop_namespace(&self, namespace: &Namespace) -> Result<()> {
+        Err(read_only_error(&format!("drop namespace {}", namespace)))
+    }
+
+    fn update_namespace_properties(
+        &self,
+        namespace: &Namespace,
+        _updates: HashMap<String, String>,
+        _removals: &[&str],
+    ) -> Result<NamespaceMetadata> {
+        Err(read_only_error(&format!(
+            "update properties of namespace {}",
+            namespace
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::error::IcebergError;
+    use crate::iceberg::test_utils::{append_ids, ids_batch, TestCatalog};
+    use crate::iceberg::writer::partitioned::PartitionedWriter;
+
+    #[test]
+    fn test_read_only_catalog() {
+        let dir = tempfile::tempdir().unwrap();
+        let test_catalog = Arc::new(TestCatalog::new());
+        let table = test_catalog.create_table("db", "t", dir.path());
+        append_ids(&test_catalog, &table, &[1, 2]);
+        let catalog = ReadOnlyCatalog::new(test_catalog.clone());
+
+        let table = catalog.load_table(&"db.t".parse().unwrap()).unwrap();
+        assert_eq!(1, table.scan().plan_files().unwrap().tasks().len());
+        let metadata = table.metadata().clone();
+        assert!(matches!(
+            catalog.commit_table(&table, metadata),
+            Err(IcebergError::ReadOnly(_))
+        ));
+        assert!(matches!(
+            catalog.drop_table(&"db.t".parse().unwrap(), false),
+            Err(IcebergError::ReadOnly(_))
+        ));
+        assert!(matches!(
+            catalog.create_namespace(&"db2".parse().unwrap(), HashMap::new()),
+            Err(IcebergError::ReadOnly(_))
+        ));
+        // Writing data files through the table fails before anything is committed
+        let mut writer = PartitionedWriter::for_table(&table).unwrap();
+        let written = writer.write(&ids_batch(&[3])).and_then(|_| writer.close());
+        assert!(matches!(written, Err(IcebergError::ReadOnly(_))));
+        assert!(test_catalog.load_table(&"db.t".parse().unwrap()).is_ok());
+    }
+}
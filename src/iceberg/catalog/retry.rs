@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{IcebergCatalog, NamespaceMetadata};
+use crate::iceberg::error::Result;
+use crate::iceberg::ident::{Namespace, TableIdent};
+use crate::iceberg::retry::{RetryPolicy, RetryingFileIO};
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+use crate::iceberg::table::Table;
+use crate::iceberg::view::View;
+
+// Catalog retrying the lookups of the wrapped catalog that fail with transient errors, see
+// RetryPolicy. Commits, drops and namespace changes are made once, since their outcome is unknown
+// when they fail without a response. Tables read and write their files through a RetryingFileIO
+#[derive(Debug, Clone)]
+pub struct RetryingCatalog {
+    catalog: Arc<dyn IcebergCatalog>,
+    policy: RetryPolicy,
+}
+
+impl RetryingCatalog {
+    pub fn new(catalog: Arc<dyn IcebergCatalog>, policy: RetryPolicy) -> Self {
+        RetryingCatalog { catalog, policy }
+    }
+
+    fn retrying(&self, table: Table) -> Table {
+        let file_io = Arc::new(RetryingFileIO::new(
+            table.file_io().clone(),
+            self.policy.clone(),
+        ));
+        table.with_file_io(file_io)
+    }
+}
+
+impl IcebergCatalog for RetryingCatalog {
+    fn load_table(&self, ident: &TableIdent) -> Result<Table> {
+        let table = self.policy.run(&format!("load of table {}", ident), || {
+            self.catalog.load_table(ident)
+        })?;
+        Ok(self.retrying(table))
+    }
+
+    fn refresh_table(&self, table: &Table) -> Result<Table> {
+        let refreshed = self
+            .policy
+            .run(&format!("refresh of table {}", table.ident()), || {
+                self.catalog.refresh_table(table)
+            })?;
+        Ok(self.retrying(refreshed))
+    }
+
+    fn commit_table(&self, base: &Table, metadata: TableMetadataV2) -> Result<Table> {
+        Ok(self.retrying(self.catalog.commit_table(base, metadata)?))
+    }
+
+    fn drop_table(&self, ident: &TableIdent, purge: bool) -> Result<()> {
+        self.catalog.drop_table(ident, purge)
+    }
+
+    fn current_time_ms(&self) -> Result<i64> {
+        self.policy
+            .run("lookup of the time", || self.catalog.current_time_ms())
+    }
+
+    fn list_tables(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        self.policy
+            .run(&format!("listing of the tables of {}", namespace), || {
+                self.catalog.list_tables(namespace)
+            })
+    }
+
+    fn load_view(&self, ident: &TableIdent) -> Result<View> {
+        self.policy.run(&format!("load of view {}", ident), || {
+            self.catalog.load_view(ident)
+        })
+    }
+
+    fn list_views(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        self.policy
+            .run(&format!("listing of the views of {}", namespace), || {
+                self.catalog.list_views(namespace)
+            })
+    }
+
+    fn list_namespaces(&self) -> Result<Vec<NamespaceMetadata>> {
+        self.policy
+            .run("listing of namespaces", || self.catalog.list_namespaces())
+    }
+
+    fn create_namespace(
+        &self,
+        namespace: &Namespace,
+        properties: HashMap<String, String>,
+    ) -> Result<NamespaceMetadata> {
+        self.catalog.create_namespace(namespace, properties)
+    }
+
+    fn drop_namespace(&self, namespace: &Namespace) -> Result<()> {
+        self.catalog.drop_namespace(namespace)
+    }
+
+    fn update_namespace_properties(
+        &self,
+        namespace: &Namespace,
+        updates: HashMap<String, String>,
+        removals: &[&str],
+    ) -> Result<NamespaceMetadata> {
+        self.catalog
+            .update_namespace_properties(namespace, updates, removals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::iceberg::error::IcebergError;
+    use crate::iceberg::retry::transient_error;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+
+    // Catalog failing the given number of calls before delegating to a TestCatalog
+    #[derive(Debug)]
+    struct FlakyCatalog {
+        catalog: TestCatalog,
+        failures: Mutex<usize>,
+    }
+
+    impl FlakyCatalog {
+        fn fail(&self) -> Result<()> {
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err(transient_error("Connection lost".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    impl IcebergCatalog for FlakyCatalog {
+        fn load_table(&self, ident: &TableIdent) -> Result<Table> {
+            self.fail()?;
+            self.catalog.load_table(ident)
+        }
+
+        fn commit_table(&self, base: &Table, metadata: TableMetadataV2) -> Result<Table> {
+            self.fail()?;
+            self.catalog.commit_table(base, metadata)
+        }
+
+        fn drop_table(&self, ident: &TableIdent, purge: bool) -> Result<()> {
+            self.fail()?;
+            self.catalog.drop_table(ident, purge)
+        }
+    }
+
+    #[test]
+    fn test_retrying_catalog() {
+        let dir = tempfile::tempdir().unwrap();
+        let flaky = Arc::new(FlakyCatalog {
+            catalog: TestCatalog::new(),
+            failures: Mutex::new(0),
+        });
+        let table = flaky.catalog.create_table("db", "t", dir.path());
+        append_ids(&flaky.catalog, &table, &[1]);
+        let policy = RetryPolicy::new()
+            .with_max_attempts(3)
+            .with_initial_backoff(Duration::from_millis(1));
+        let catalog = RetryingCatalog::new(flaky.clone(), policy);
+        let ident: TableIdent = "db.t".parse().unwrap();
+
+        *flaky.failures.lock().unwrap() = 2;
+        let table = catalog.load_table(&ident).unwrap();
+        assert_eq!(1, table.scan().plan_files().unwrap().tasks().len());
+        *flaky.failures.lock().unwrap() = 3;
+        assert!(matches!(
+            catalog.load_table(&ident),
+            Err(IcebergError::Io(_))
+        ));
+
+        // Commits aren't retried
+        *flaky.failures.lock().unwrap() = 1;
+        let metadata = table.metadata().clone();
+        assert!(catalog.commit_table(&table, metadata.clone()).is_err());
+        catalog.commit_table(&table, metadata).unwrap();
+    }
+}
@@ -0,0 +1,331 @@
+// Row-level changes of a table between two snapshots, e.g. to mirror the table in another
+// system. The snapshots after the first one up to the last one are replayed oldest first: each
+// of them emits the rows it deleted, by removing data files or adding delete files, then the
+// rows it inserted by adding data files. Rewrites (replace snapshots) don't change the rows of
+// the table and emit nothing
+use arrow::array::RecordBatch;
+
+use crate::iceberg::deletes::DeleteFileIndex;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::reader::{check_readable, DEFAULT_BATCH_SIZE};
+use crate::iceberg::scan::{snapshots_between, FileReader};
+use crate::iceberg::spec::manifest::{DataContentType, DataFile, ManifestStatus};
+use crate::iceberg::spec::manifest_list::FileType;
+use crate::iceberg::spec::snapshot::{Operation, SnapshotV2};
+use crate::iceberg::table::Table;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ChangeType {
+    Insert,
+    Delete,
+}
+
+// Rows inserted or deleted by a snapshot
+#[derive(Debug, Clone)]
+pub struct ChangelogBatch {
+    pub change_type: ChangeType,
+    pub snapshot_id: i64,
+    pub batch: RecordBatch,
+}
+
+pub type ChangelogIter = Box<dyn Iterator<Item = Result<ChangelogBatch>> + Send>;
+
+// A data file whose rows a snapshot changed. Inserts are the rows of a data file added by the
+// snapshot. Deletes are the rows of a data file the delete files added by the snapshot delete,
+// or all the rows of a data file removed by the snapshot, in which case there are no added
+// deletes. Rows deleted before the snapshot are left out in both cases
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangelogTask {
+    pub change_type: ChangeType,
+    pub snapshot_id: i64,
+    pub data_file: DataFile,
+    // Delete files applying to the data file before the snapshot
+    pub existing_deletes: Vec<DataFile>,
+    // Delete files added by the snapshot applying to the data file
+    pub added_deletes: Vec<DataFile>,
+}
+
+// Builds a changelog scan, see Table::changelog
+pub struct ChangelogScan<'a> {
+    table: &'a Table,
+    from_snapshot_id: i64,
+    to_snapshot_id: i64,
+    columns: Option<Vec<String>>,
+    batch_size: usize,
+}
+
+// The changed data files of a changelog scan, in the order of their changes
+pub struct ChangelogPlan {
+    tasks: Vec<ChangelogTask>,
+    reader: FileReader,
+}
+
+impl<'a> ChangelogScan<'a> {
+    pub(crate) fn new(table: &'a Table, from_snapshot_id: i64, to_snapshot_id: i64) -> Self {
+        ChangelogScan {
+            table,
+            from_snapshot_id,
+            to_snapshot_id,
+            columns: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+
+    // Only read the given top-level columns, in the given order
+    pub fn select(mut self, columns: &[&str]) -> Self {
+        self.columns = Some(columns.iter().map(|column| column.to_string()).collect());
+        self
+    }
+
+    // Maximum number of rows of the record batches read by the plan
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn plan_files(self) -> Result<ChangelogPlan> {
+        let metadata = self.table.metadata();
+        let to = metadata
+            .snapshot_by_id(self.to_snapshot_id)
+            .ok_or_else(|| {
+                IcebergError::NotFound(format!(
+                    "Snapshot {} of table {}",
+                    self.to_snapshot_id,
+                    self.table.ident()
+                ))
+            })?;
+        // Rows are read with the schema of the last snapshot
+        let schema = match to.schema_id {
+            Some(schema_id) => metadata.schema_by_id(schema_id).ok_or_else(|| {
+                IcebergError::Invalid(format!(
+                    "Schema {} is missing from table metadata",
+                    schema_id
+                ))
+            })?,
+            None => metadata.current_schema()?,
+        };
+        let projection = self
+            .columns
+            .as_ref()
+            .map(|columns| {
+                columns
+                    .iter()
+                    .map(|column| {
+                        schema
+                            .schema()
+                            .field_by_name(column)
+                            .map(|field| field.id)
+                            .ok_or_else(|| IcebergError::NotFound(format!("Column {}", column)))
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .transpose()?;
+
+        let mut tasks = vec![];
+        let snapshots = snapshots_between(self.table, self.from_snapshot_id, to)?;
+        for snapshot in snapshots.into_iter().rev() {
+            if snapshot.summary.operation != Operation::Replace {
+                tasks.extend(self.snapshot_changes(snapshot)?);
+            }
+        }
+        Ok(ChangelogPlan {
+            tasks,
+            reader: FileReader::try_new(self.table, schema.schema(), projection, self.batch_size)?,
+        })
+    }
+
+    // Deletes then inserts of the snapshot
+    fn snapshot_changes(&self, snapshot: &SnapshotV2) -> Result<Vec<ChangelogTask>> {
+        let mut data_entries = vec![];
+        let mut existing = DeleteFileIndex::default();
+        let mut added = DeleteFileIndex::default();
+        for manifest in self.table.manifests(snapshot)? {
+            for entry in self.table.manifest_entries(&manifest)? {
+                // Entries keep the id of the snapshot that last changed their status
+                let changed = entry.snapshot_id == Some(snapshot.snapshot_id);
+                match manifest.content {
+                    FileType::Data => data_entries.push((manifest.partition_spec_id, entry)),
+                    FileType::Delete if changed && entry.status == ManifestStatus::Added => {
+                        added.add(manifest.partition_spec_id, entry)
+                    }
+                    // Delete files removed by the snapshot still applied before it
+                    FileType::Delete if entry.is_live() || changed => {
+                        existing.add(manifest.partition_spec_id, entry)
+                    }
+                    FileType::Delete => {}
+                }
+            }
+        }
+
+        let mut deletes = vec![];
+        let mut inserts = vec![];
+        for (spec_id, entry) in data_entries {
+            if entry.data_file.content != DataContentType::Data {
+                continue;
+            }
+            let changed = entry.snapshot_id == Some(snapshot.snapshot_id);
+            let sequence_number = entry.sequence_number.unwrap_or_default();
+            let added_deletes = added.for_data_file(spec_id, sequence_number, &entry.data_file);
+            let task = |change_type, existing_deletes, added_deletes| ChangelogTask {
+                change_type,
+                snapshot_id: snapshot.snapshot_id,
+                data_file: entry.data_file.clone(),
+                existing_deletes,
+                added_deletes,
+            };
+            match entry.status {
+                ManifestStatus::Added if changed => {
+                    inserts.push(task(ChangeType::Insert, vec![], added_deletes))
+                }
+                ManifestStatus::Deleted if changed => deletes.push(task(
+                    ChangeType::Delete,
+                    existing.for_data_file(spec_id, sequence_number, &entry.data_file),
+                    vec![],
+                )),
+                ManifestStatus::Deleted => {}
+                _ if !added_deletes.is_empty() => deletes.push(task(
+                    ChangeType::Delete,
+                    existing.for_data_file(spec_id, sequence_number, &entry.data_file),
+                    added_deletes,
+                )),
+                _ => {}
+            }
+        }
+        deletes.extend(inserts);
+        Ok(deletes)
+    }
+}
+
+impl ChangelogPlan {
+    pub fn tasks(&self) -> &[ChangelogTask] {
+        &self.tasks
+    }
+
+    // Reads the changed rows of the plan, one task after the other
+    pub fn to_arrow(&self) -> Result<ChangelogIter> {
+        for task in &self.tasks {
+            check_readable(task.data_file.file_format, &task.data_file.file_path)?;
+        }
+        let reader = self.reader.clone();
+        let batches = self.tasks.clone().into_iter().flat_map(move |task| {
+            let read = || -> Result<_> {
+                let data = reader.file_io().read(&task.data_file.file_path)?;
+                // The groups of delete files are the existing then the added deletes
+                let keep: fn(&[bool]) -> bool =
+                    if task.change_type == ChangeType::Delete && !task.added_deletes.is_empty() {
+                        |kept| kept[0] && !kept[1]
+                    } else {
+                        |kept| kept[0] && kept[1]
+                    };
+                let deletes = [task.existing_deletes, task.added_deletes];
+                reader.read(&task.data_file, data, None, &deletes, keep)
+            };
+            let (change_type, snapshot_id) = (task.change_type, task.snapshot_id);
+            let batches: Box<dyn Iterator<Item = Result<RecordBatch>> + Send> = match read() {
+                Ok(batches) => batches,
+                Err(e) => Box::new(std::iter::once(Err(e))),
+            };
+            batches.map(move |batch| {
+                Ok(ChangelogBatch {
+                    change_type,
+                    snapshot_id,
+                    batch: batch?,
+                })
+            })
+        });
+        Ok(Box::new(batches))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::Int64Array;
+
+    use super::*;
+    use crate::iceberg::test_utils::{append_ids, ids_batch, TestCatalog};
+    use crate::iceberg::writer::partitioned::PartitionedWriter;
+    use crate::iceberg::writer::position_delete::PositionDeleteWriter;
+
+    fn changes(plan: &ChangelogPlan) -> Vec<(ChangeType, i64, Vec<i64>)> {
+        let mut changes: Vec<(ChangeType, i64, Vec<i64>)> = vec![];
+        for batch in plan.to_arrow().unwrap() {
+            let batch = batch.unwrap();
+            let ids = batch.batch.column(0).as_any().downcast_ref::<Int64Array>();
+            let ids = ids.unwrap().values().to_vec();
+            match changes.last_mut() {
+                Some((change_type, snapshot_id, rows))
+                    if *change_type == batch.change_type && *snapshot_id == batch.snapshot_id =>
+                {
+                    rows.extend(ids)
+                }
+                _ => changes.push((batch.change_type, batch.snapshot_id, ids)),
+            }
+        }
+        for (_, _, ids) in &mut changes {
+            ids.sort();
+        }
+        changes
+    }
+
+    #[test]
+    fn test_changelog() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1, 2, 3]);
+        let first_snapshot = table.metadata().current_snapshot_id.unwrap();
+        let first_file = table.scan().plan_files().unwrap().tasks()[0]
+            .data_file
+            .clone();
+        let table = append_ids(&catalog, &table, &[4, 5]);
+        let append_snapshot = table.metadata().current_snapshot_id.unwrap();
+
+        let mut writer = PositionDeleteWriter::for_table(&table, vec![]).unwrap();
+        writer.delete(&first_file.file_path, 1);
+        let table = table
+            .new_row_delta()
+            .add_deletes(writer.close().unwrap())
+            .commit(&catalog)
+            .unwrap();
+        let delete_snapshot = table.metadata().current_snapshot_id.unwrap();
+
+        // Rewriting manifests doesn't change rows
+        let table = table.rewrite_manifests().commit(&catalog).unwrap().table;
+
+        // Updates 3 to 6, the row deleted before isn't deleted again
+        let mut writer = PartitionedWriter::for_table(&table).unwrap();
+        writer.write(&ids_batch(&[1, 6])).unwrap();
+        let table = table
+            .new_overwrite()
+            .delete_file(&first_file)
+            .add_files(writer.close().unwrap())
+            .commit(&catalog)
+            .unwrap();
+        let overwrite_snapshot = table.metadata().current_snapshot_id.unwrap();
+
+        let plan = table
+            .changelog(first_snapshot, overwrite_snapshot)
+            .select(&["id"])
+            .plan_files()
+            .unwrap();
+        assert_eq!(
+            vec![
+                (ChangeType::Insert, append_snapshot, vec![4, 5]),
+                (ChangeType::Delete, delete_snapshot, vec![2]),
+                (ChangeType::Delete, overwrite_snapshot, vec![1, 3]),
+                (ChangeType::Insert, overwrite_snapshot, vec![1, 6]),
+            ],
+            changes(&plan)
+        );
+
+        let plan = table.changelog(append_snapshot, delete_snapshot);
+        assert_eq!(
+            vec![(ChangeType::Delete, delete_snapshot, vec![2])],
+            changes(&plan.plan_files().unwrap())
+        );
+        assert!(table
+            .changelog(overwrite_snapshot, first_snapshot)
+            .plan_files()
+            .is_err());
+    }
+}
@@ -0,0 +1,476 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+use arrow::array::{AsArray, BooleanBufferBuilder, RecordBatch};
+use arrow::buffer::BooleanBuffer;
+use arrow::datatypes::{Int64Type, SchemaRef};
+use arrow::row::{OwnedRow, RowConverter, SortField};
+
+use crate::iceberg::arrow::field_id;
+use crate::iceberg::encryption::TableDecryption;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::io::FileIO;
+use crate::iceberg::puffin::deletion_vector::DeletionVector;
+use crate::iceberg::reader::{check_readable, ParquetReader};
+use crate::iceberg::spec::manifest::{DataContentType, DataFile, FileFormat, ManifestEntry};
+use crate::iceberg::spec::schema::{
+    FieldNames, IcebergType, PrimitiveType, StructField, StructType,
+};
+
+// Reserved field ids of the columns of position delete files
+pub const POSITION_DELETE_FILE_PATH_FIELD_ID: i32 = 2147483546;
+pub const POSITION_DELETE_POS_FIELD_ID: i32 = 2147483545;
+
+// Schema of position delete files: the path of the data file and the position of the deleted
+// row in it
+pub fn position_delete_schema() -> StructType {
+    let field = |id: i32, name: &str, primitive: PrimitiveType| StructField {
+        id,
+        name: name.to_string(),
+        required: true,
+        field_type: IcebergType::Primitive(primitive),
+        doc: None,
+        initial_default: None,
+        write_default: None,
+    };
+    StructType {
+        fields: vec![
+            field(
+                POSITION_DELETE_FILE_PATH_FIELD_ID,
+                "file_path",
+                PrimitiveType::String,
+            ),
+            field(POSITION_DELETE_POS_FIELD_ID, "pos", PrimitiveType::Long),
+        ],
+    }
+}
+
+// Live delete files of a snapshot, matched to the data files they apply to. Position deletes
+// apply to data files of the same partition with a data sequence number not greater than the
+// one of the delete file, equality deletes to data files of the same partition with a smaller
+// data sequence number, or of any partition if the delete file is unpartitioned. Position delete
+// files referencing a data file, which deletion vectors always do, only apply to it
+#[derive(Debug, Default)]
+pub(crate) struct DeleteFileIndex {
+    deletes: Vec<IndexedDeleteFile>,
+}
+
+#[derive(Debug)]
+struct IndexedDeleteFile {
+    sequence_number: i64,
+    spec_id: i32,
+    data_file: DataFile,
+}
+
+impl DeleteFileIndex {
+    pub(crate) fn add(&mut self, spec_id: i32, entry: ManifestEntry) {
+        self.deletes.push(IndexedDeleteFile {
+            sequence_number: entry.sequence_number.unwrap_or_default(),
+            spec_id,
+            data_file: entry.data_file,
+        });
+    }
+
+    // Delete files to apply when reading the data file
+    pub(crate) fn for_data_file(
+        &self,
+        spec_id: i32,
+        sequence_number: i64,
+        data_file: &DataFile,
+    ) -> Vec<DataFile> {
+        self.deletes
+            .iter()
+            .filter(|delete| {
+                let same_partition =
+                    delete.spec_id == spec_id && delete.data_file.partition == data_file.partition;
+                match delete.data_file.content {
+                    DataContentType::PositionDeletes => {
+                        same_partition
+                            && sequence_number <= delete.sequence_number
+                            && delete
+                                .data_file
+                                .referenced_data_file
+                                .as_ref()
+                                .is_none_or(|path| *path == data_file.file_path)
+                    }
+                    DataContentType::EqualityDeletes => {
+                        (same_partition || delete.data_file.partition.is_empty())
+                            && sequence_number < delete.sequence_number
+                    }
+                    DataContentType::Data => false,
+                }
+            })
+            .map(|delete| delete.data_file.clone())
+            .collect()
+    }
+}
+
+// Removes the deleted rows of a data file from the batches read from it. Deleted positions are
+// kept in a roaring bitmap so that only those within a batch are visited
+pub(crate) struct DeleteFilter {
+    positions: DeletionVector,
+    equality: Vec<EqualityDeletes>,
+}
+
+// Rows deleted by equality delete files sharing the same equality columns
+struct EqualityDeletes {
+    field_ids: Vec<i32>,
+    converter: RowConverter,
+    rows: HashSet<OwnedRow>,
+}
+
+impl DeleteFilter {
+    pub(crate) fn load(
+        file_io: &dyn FileIO,
+        decryption: &TableDecryption,
+        schema: &StructType,
+        field_names: &dyn FieldNames,
+        data_file: &DataFile,
+        deletes: &[DataFile],
+    ) -> Result<Self> {
+        let mut positions = DeletionVector::new();
+        let mut equality: HashMap<Vec<i32>, EqualityDeletes> = HashMap::new();
+        for delete in deletes {
+            if delete.content == DataContentType::PositionDeletes
+                && delete.file_format == FileFormat::Puffin
+            {
+                positions.merge(&read_deletion_vector(file_io, delete)?);
+                continue;
+            }
+            check_readable(delete.file_format, &delete.file_path)?;
+            let data = file_io.read(&delete.file_path)?;
+            let decryption = decryption.file_properties(delete)?;
+            match delete.content {
+                DataContentType::PositionDeletes => {
+                    let reader = ParquetReader::try_new(&position_delete_schema())?;
+                    for batch in
+                        reader.read_file(delete.file_format, &delete.file_path, data, decryption)?
+                    {
+                        let batch = batch?;
+                        let paths = batch.column(0).as_string::<i32>();
+                        let pos = batch.column(1).as_primitive::<Int64Type>();
+                        for row in 0..batch.num_rows() {
+                            if paths.value(row) == data_file.file_path {
+                                positions.insert(pos.value(row));
+                            }
+                        }
+                    }
+                }
+                DataContentType::EqualityDeletes => {
+                    let field_ids = delete.equality_ids.clone().unwrap_or_default();
+                    let delete_schema = StructType {
+                        fields: field_ids
+                            .iter()
+                            .map(|id| {
+                                schema
+                                    .fields
+                                    .iter()
+                                    .find(|field| field.id == *id)
+                                    .cloned()
+                                    .ok_or_else(|| {
+                                        IcebergError::Invalid(format!(
+                                            "Equality field of delete file {} is not a \
+                                             top-level column of the scanned schema: {}",
+                                            delete.file_path,
+                                            field_names.describe_field(*id)
+                                        ))
+                                    })
+                            })
+                            .collect::<Result<_>>()?,
+                    };
+                    let reader = ParquetReader::try_new(&delete_schema)?;
+                    let deletes = match equality.entry(field_ids.clone()) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => {
+                            let sort_fields = reader
+                                .output_schema()?
+                                .fields()
+                                .iter()
+                                .map(|field| SortField::new(field.data_type().clone()))
+                                .collect();
+                            entry.insert(EqualityDeletes {
+                                field_ids,
+                                converter: RowConverter::new(sort_fields)?,
+                                rows: HashSet::new(),
+                            })
+                        }
+                    };
+                    for batch in
+                        reader.read_file(delete.file_format, &delete.file_path, data, decryption)?
+                    {
+                        let rows = deletes.converter.convert_columns(batch?.columns())?;
+                        deletes.rows.extend(rows.iter().map(|row| row.owned()));
+                    }
+                }
+                DataContentType::Data => {
+                    return Err(IcebergError::Invalid(format!(
+                        "{} is a data file, not a delete file",
+                        delete.file_path
+                    )))
+                }
+            }
+        }
+        Ok(DeleteFilter {
+            positions,
+            equality: equality.into_values().collect(),
+        })
+    }
+
+    // Field ids of the columns needed to apply the equality deletes
+    pub(crate) fn equality_field_ids(&self) -> Vec<i32> {
+        let mut ids: Vec<i32> = self
+            .equality
+            .iter()
+            .flat_map(|deletes| deletes.field_ids.iter().copied())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    // Whether each row of a batch whose first row is at the given position of the data file is
+    // kept, i.e. not deleted. The batch must have the equality columns
+    pub(crate) fn kept(&self, batch: &RecordBatch, offset: i64) -> Result<BooleanBuffer> {
+        let num_rows = batch.num_rows();
+        let mut keep = BooleanBufferBuilder::new(num_rows);
+        keep.append_n(num_rows, true);
+        for position in self.positions.range(offset, offset + num_rows as i64) {
+            keep.set_bit((position - offset) as usize, false);
+        }
+        for deletes in &self.equality {
+            let columns = deletes
+                .field_ids
+                .iter()
+                .map(|id| column_index(batch.schema_ref(), *id).map(|i| batch.column(i).clone()))
+                .collect::<Result<Vec<_>>>()?;
+            let rows = deletes.converter.convert_columns(&columns)?;
+            for row in 0..num_rows {
+                if keep.get_bit(row) && deletes.rows.contains(&rows.row(row).owned()) {
+                    keep.set_bit(row, false);
+                }
+            }
+        }
+        Ok(keep.finish())
+    }
+}
+
+// Reads the deletion vector blob of a Puffin file a delete file of a format version 3 table points
+// to. The footer of the file isn't needed as the manifest has the location of the blob
+fn read_deletion_vector(file_io: &dyn FileIO, delete: &DataFile) -> Result<DeletionVector> {
+    let (Some(offset), Some(length)) = (delete.content_offset, delete.content_size_in_bytes) else {
+        return Err(IcebergError::Invalid(format!(
+            "Deletion vector {} has no content offset and size",
+            delete.file_path
+        )));
+    };
+    let data = file_io.read(&delete.file_path)?;
+    let blob = usize::try_from(offset)
+        .ok()
+        .zip(usize::try_from(length).ok())
+        .and_then(|(offset, length)| data.get(offset..offset.checked_add(length)?))
+        .ok_or_else(|| {
+            IcebergError::Invalid(format!(
+                "Deletion vector at offset {} of {} bytes is out of {} of {} bytes",
+                offset,
+                length,
+                delete.file_path,
+                data.len()
+            ))
+        })?;
+    DeletionVector::try_from_bytes(blob)
+}
+
+fn column_index(schema: &SchemaRef, id: i32) -> Result<usize> {
+    schema
+        .fields()
+        .iter()
+        .position(|field| field_id(field) == Some(id))
+        .ok_or_else(|| IcebergError::NotFound(format!("Column with field id {}", id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::Int64Array;
+
+    use super::*;
+    use crate::iceberg::arrow::schema_to_arrow;
+    use crate::iceberg::puffin::PuffinWriter;
+    use crate::iceberg::table::Table;
+    use crate::iceberg::test_utils::{append_ids, ids_batch, test_schema, TestCatalog};
+    use crate::iceberg::writer::position_delete::PositionDeleteWriter;
+    use crate::iceberg::writer::ParquetWriter;
+
+    fn write_equality_deletes(table: &Table, schema: &StructType, batch: RecordBatch) -> DataFile {
+        let location = format!("{}/data/eq-deletes.parquet", table.metadata().location);
+        let mut writer = ParquetWriter::try_new(table.file_io().clone(), location, schema).unwrap();
+        writer.write(&batch).unwrap();
+        let mut data_file = writer.close().unwrap();
+        data_file.content = DataContentType::EqualityDeletes;
+        data_file.equality_ids = Some(schema.fields.iter().map(|field| field.id).collect());
+        data_file
+    }
+
+    fn ids(table: &Table, columns: &[&str]) -> Vec<i64> {
+        let mut ids = vec![];
+        let plan = table.scan().select(columns).plan_files().unwrap();
+        for batch in plan.to_arrow().unwrap() {
+            let batch = batch.unwrap();
+            assert_eq!(columns.len(), batch.num_columns());
+            let column = batch.column(0);
+            match column.as_any().downcast_ref::<Int64Array>() {
+                Some(values) => ids.extend(values.values()),
+                None => ids.extend(
+                    column
+                        .as_string::<i32>()
+                        .iter()
+                        .map(|data| data.unwrap()[4..].parse::<i64>().unwrap()),
+                ),
+            }
+        }
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_scan_applies_deletes() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1, 2, 3]);
+        let table = append_ids(&catalog, &table, &[4, 5]);
+        let plan = table.scan().plan_files().unwrap();
+        let first_file = plan
+            .tasks()
+            .iter()
+            .find(|task| task.data_file.record_count == 3)
+            .unwrap()
+            .data_file
+            .file_path
+            .clone();
+
+        let mut writer = PositionDeleteWriter::for_table(&table, vec![]).unwrap();
+        writer.delete(&first_file, 1);
+        writer.delete("file:/other", 0);
+        let position_deletes = writer.close().unwrap();
+        let id_schema = StructType {
+            fields: vec![test_schema().fields[0].clone()],
+        };
+        let equality_deletes = write_equality_deletes(
+            &table,
+            &id_schema,
+            RecordBatch::try_new(
+                Arc::new(schema_to_arrow(&id_schema).unwrap()),
+                vec![Arc::new(Int64Array::from(vec![4]))],
+            )
+            .unwrap(),
+        );
+        let table = table
+            .new_row_delta()
+            .add_deletes(position_deletes)
+            .add_deletes(equality_deletes)
+            .commit(&catalog)
+            .unwrap();
+
+        // Rows added after the deletes aren't deleted
+        let table = append_ids(&catalog, &table, &[4]);
+        let plan = table.scan().plan_files().unwrap();
+        let mut deletes: Vec<usize> = plan.tasks().iter().map(|t| t.deletes.len()).collect();
+        deletes.sort();
+        assert_eq!(vec![0, 2, 2], deletes);
+
+        assert_eq!(vec![1, 3, 4, 5], ids(&table, &["id", "data"]));
+        // The equality column is read to apply the deletes but not returned
+        assert_eq!(vec![1, 3, 4, 5], ids(&table, &["data"]));
+        let count: usize = table
+            .scan()
+            .plan_files()
+            .unwrap()
+            .to_arrow()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum();
+        assert_eq!(4, count);
+        assert_eq!(
+            ids_batch(&[1]).schema(),
+            table
+                .scan()
+                .plan_files()
+                .unwrap()
+                .to_arrow()
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap()
+                .schema()
+        );
+    }
+
+    #[test]
+    fn test_scan_applies_deletion_vectors() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1, 2, 3]);
+        let table = append_ids(&catalog, &table, &[4, 5]);
+        let plan = table.scan().plan_files().unwrap();
+        let mut paths: Vec<(i64, String)> = plan
+            .tasks()
+            .iter()
+            .map(|task| {
+                (
+                    task.data_file.record_count,
+                    task.data_file.file_path.clone(),
+                )
+            })
+            .collect();
+        paths.sort();
+
+        // One Puffin file holding the vectors of both data files
+        let vectors: Vec<(&str, DeletionVector)> = vec![
+            (&paths[1].1, [0, 2].into_iter().collect()),
+            (&paths[0].1, [1].into_iter().collect()),
+        ];
+        let mut writer = PuffinWriter::new();
+        for (path, vector) in &vectors {
+            writer.add_blob(vector.to_blob(path));
+        }
+        let file = writer.finish().unwrap();
+        let location = format!("{}/data/dv.puffin", table.metadata().location);
+        table
+            .file_io()
+            .write(&location, file.data.clone().into())
+            .unwrap();
+        let mut row_delta = table.new_row_delta();
+        for ((path, vector), blob) in vectors.iter().zip(&file.metadata.blobs) {
+            row_delta = row_delta.add_deletes(DataFile {
+                content: DataContentType::PositionDeletes,
+                file_path: location.clone(),
+                file_format: FileFormat::Puffin,
+                partition: vec![],
+                record_count: vector.cardinality() as i64,
+                file_size_in_bytes: file.data.len() as i64,
+                column_sizes: None,
+                value_counts: None,
+                null_value_counts: None,
+                nan_value_counts: None,
+                lower_bounds: None,
+                upper_bounds: None,
+                key_metadata: None,
+                split_offsets: None,
+                equality_ids: None,
+                sort_order_id: None,
+                referenced_data_file: Some(path.to_string()),
+                content_offset: Some(blob.offset),
+                content_size_in_bytes: Some(blob.length),
+            });
+        }
+        let table = row_delta.commit(&catalog).unwrap();
+
+        // Each vector only applies to the data file it references
+        let plan = table.scan().plan_files().unwrap();
+        assert!(plan.tasks().iter().all(|task| task.deletes.len() == 1));
+        assert_eq!(vec![2, 4], ids(&table, &["id"]));
+    }
+}
@@ -0,0 +1,317 @@
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use parquet::encryption::decrypt::{FileDecryptionProperties, KeyRetriever};
+use parquet::errors::ParquetError;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_128_GCM, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::spec::manifest::DataFile;
+use crate::iceberg::spec::manifest_list::ManifestListV2;
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+
+// Id of the master key of the table in the key management service. Keys of data files are
+// wrapped with it
+pub const ENCRYPTION_KEY_ID_PROPERTY: &str = "encryption.key-id";
+
+// Client of the key management service holding the master keys of tables. Keys never leave the
+// service, it only unwraps the data encryption keys of files
+pub trait KeyManagementClient: Debug + Send + Sync {
+    fn unwrap_key(&self, wrapped_key: &[u8], wrapping_key_id: &str) -> Result<Vec<u8>>;
+}
+
+// Decryption of the files of a table other than Parquet data files, e.g. manifests, given the
+// key metadata stored next to their location
+pub trait EncryptionManager: Debug + Send + Sync {
+    fn decrypt(&self, data: &[u8], key_metadata: &[u8]) -> Result<Vec<u8>>;
+}
+
+// Magic of AES-GCM streams, followed by the length of the plaintext of the blocks as a little
+// endian int
+const GCM_STREAM_MAGIC: &[u8] = b"AGS1";
+const GCM_STREAM_HEADER_LENGTH: usize = 8;
+const GCM_TAG_LENGTH: usize = 16;
+pub const DEFAULT_GCM_BLOCK_LENGTH: usize = 1024 * 1024;
+
+// Envelope encryption of standard table encryption: the key metadata of a file is its data key
+// wrapped with the master key of the table, and the file is an AES-GCM stream. Each block of the
+// stream is its nonce, ciphertext and tag, authenticated with the index of the block so that
+// blocks can't be reordered
+#[derive(Debug, Clone)]
+pub struct StandardEncryptionManager {
+    kms: Arc<dyn KeyManagementClient>,
+    key_id: String,
+}
+
+impl StandardEncryptionManager {
+    pub fn new(kms: Arc<dyn KeyManagementClient>, key_id: &str) -> Self {
+        StandardEncryptionManager {
+            kms,
+            key_id: key_id.to_string(),
+        }
+    }
+
+    // Manager of the table, None unless the table has a master key
+    pub fn for_table(
+        kms: Arc<dyn KeyManagementClient>,
+        metadata: &TableMetadataV2,
+    ) -> Option<Self> {
+        metadata
+            .property(ENCRYPTION_KEY_ID_PROPERTY)
+            .map(|key_id| Self::new(kms, key_id))
+    }
+
+    // Encrypts the data with the data key wrapped in the key metadata, the inverse of decrypt
+    pub fn encrypt(&self, data: &[u8], key_metadata: &[u8]) -> Result<Vec<u8>> {
+        self.encrypt_with_block_length(data, key_metadata, DEFAULT_GCM_BLOCK_LENGTH)
+    }
+
+    pub fn encrypt_with_block_length(
+        &self,
+        data: &[u8],
+        key_metadata: &[u8],
+        block_length: usize,
+    ) -> Result<Vec<u8>> {
+        let header = block_length_header(block_length)?;
+        let key = gcm_key(&self.kms.unwrap_key(key_metadata, &self.key_id)?)?;
+        let random = SystemRandom::new();
+        let mut encrypted = Vec::with_capacity(
+            GCM_STREAM_HEADER_LENGTH
+                + data.len()
+                + data.len().div_ceil(block_length) * (NONCE_LEN + GCM_TAG_LENGTH),
+        );
+        encrypted.extend_from_slice(GCM_STREAM_MAGIC);
+        encrypted.extend_from_slice(&header);
+        for (index, block) in data.chunks(block_length).enumerate() {
+            let mut nonce = [0; NONCE_LEN];
+            random
+                .fill(&mut nonce)
+                .map_err(|_| IcebergError::Invalid("Failed to generate a nonce".to_string()))?;
+            let mut sealed = block.to_vec();
+            key.seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(block_aad(index)?),
+                &mut sealed,
+            )
+            .map_err(|_| IcebergError::Invalid("Failed to encrypt block".to_string()))?;
+            encrypted.extend_from_slice(&nonce);
+            encrypted.extend_from_slice(&sealed);
+        }
+        Ok(encrypted)
+    }
+}
+
+impl EncryptionManager for StandardEncryptionManager {
+    fn decrypt(&self, data: &[u8], key_metadata: &[u8]) -> Result<Vec<u8>> {
+        let key = gcm_key(&self.kms.unwrap_key(key_metadata, &self.key_id)?)?;
+        if data.len() < GCM_STREAM_HEADER_LENGTH || &data[..4] != GCM_STREAM_MAGIC {
+            return Err(IcebergError::Invalid(
+                "Encrypted file is not an AES-GCM stream".to_string(),
+            ));
+        }
+        let block_length = i32::from_le_bytes(data[4..8].try_into().unwrap());
+        if block_length <= 0 {
+            return Err(IcebergError::Invalid(format!(
+                "Invalid AES-GCM stream block length {}",
+                block_length
+            )));
+        }
+        let cipher_block_length = block_length as usize + NONCE_LEN + GCM_TAG_LENGTH;
+        let mut decrypted = Vec::with_capacity(data.len());
+        for (index, block) in data[GCM_STREAM_HEADER_LENGTH..]
+            .chunks(cipher_block_length)
+            .enumerate()
+        {
+            if block.len() < NONCE_LEN + GCM_TAG_LENGTH {
+                return Err(IcebergError::Invalid(format!(
+                    "Block {} of AES-GCM stream is truncated",
+                    index
+                )));
+            }
+            let (nonce, sealed) = block.split_at(NONCE_LEN);
+            let mut sealed = sealed.to_vec();
+            let plaintext = key
+                .open_in_place(
+                    Nonce::try_assume_unique_for_key(nonce).unwrap(),
+                    Aad::from(block_aad(index)?),
+                    &mut sealed,
+                )
+                .map_err(|_| {
+                    IcebergError::Invalid(format!(
+                        "Failed to decrypt block {} of AES-GCM stream, the key or the block is \
+                         invalid",
+                        index
+                    ))
+                })?;
+            decrypted.extend_from_slice(plaintext);
+        }
+        Ok(decrypted)
+    }
+}
+
+fn gcm_key(key: &[u8]) -> Result<LessSafeKey> {
+    let algorithm = match key.len() {
+        16 => &AES_128_GCM,
+        32 => &AES_256_GCM,
+        length => {
+            return Err(IcebergError::Unsupported(format!(
+                "AES-GCM keys of {} bytes",
+                length
+            )))
+        }
+    };
+    let key = UnboundKey::new(algorithm, key)
+        .map_err(|_| IcebergError::Invalid("Invalid AES-GCM key".to_string()))?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn block_length_header(block_length: usize) -> Result<[u8; 4]> {
+    match i32::try_from(block_length) {
+        Ok(length) if length > 0 => Ok(length.to_le_bytes()),
+        _ => Err(IcebergError::Invalid(format!(
+            "Invalid AES-GCM stream block length {}",
+            block_length
+        ))),
+    }
+}
+
+fn block_aad(index: usize) -> Result<[u8; 4]> {
+    i32::try_from(index)
+        .map(i32::to_le_bytes)
+        .map_err(|_| IcebergError::Invalid("AES-GCM stream has too many blocks".to_string()))
+}
+
+// Contents of the manifest, decrypted if the manifest list entry has key metadata
+pub(crate) fn decrypt_manifest<'a>(
+    encryption: Option<&dyn EncryptionManager>,
+    manifest: &ManifestListV2,
+    data: &'a [u8],
+) -> Result<Cow<'a, [u8]>> {
+    let Some(key_metadata) = &manifest.key_metadata else {
+        return Ok(Cow::Borrowed(data));
+    };
+    let Some(encryption) = encryption else {
+        return Err(IcebergError::Unsupported(format!(
+            "Manifest {} is encrypted, reading it requires a key management client and the {} \
+             table property",
+            manifest.manifest_path, ENCRYPTION_KEY_ID_PROPERTY
+        )));
+    };
+    Ok(Cow::Owned(encryption.decrypt(data, key_metadata)?))
+}
+
+// Decryption of the Parquet data files of a table with modular encryption. The `key_metadata`
+// of a data file holds its wrapped key, which encrypts the footer and all columns. Encrypted
+// files of tables with a master key but without key metadata in the manifest are decrypted with
+// the keys referenced by the key metadata stored in the Parquet file
+#[derive(Debug, Clone)]
+pub(crate) struct TableDecryption {
+    kms: Option<Arc<dyn KeyManagementClient>>,
+    key_id: Option<String>,
+}
+
+// Resolves the keys referenced by key metadata stored in Parquet files
+#[derive(Debug)]
+struct KmsKeyRetriever {
+    kms: Arc<dyn KeyManagementClient>,
+    key_id: String,
+}
+
+impl TableDecryption {
+    pub(crate) fn new(
+        kms: Option<Arc<dyn KeyManagementClient>>,
+        metadata: &TableMetadataV2,
+    ) -> Self {
+        TableDecryption {
+            kms,
+            key_id: metadata
+                .property(ENCRYPTION_KEY_ID_PROPERTY)
+                .map(str::to_string),
+        }
+    }
+
+    // Properties to read the data file with, None for files stored in plaintext
+    pub(crate) fn file_properties(
+        &self,
+        data_file: &DataFile,
+    ) -> Result<Option<FileDecryptionProperties>> {
+        if data_file.key_metadata.is_none() && self.key_id.is_none() {
+            return Ok(None);
+        }
+        let (Some(kms), Some(key_id)) = (&self.kms, &self.key_id) else {
+            return Err(IcebergError::Unsupported(format!(
+                "Data file {} is encrypted, reading it requires a key management client and \
+                 the {} table property",
+                data_file.file_path, ENCRYPTION_KEY_ID_PROPERTY
+            )));
+        };
+        let properties = match &data_file.key_metadata {
+            Some(wrapped_key) => {
+                FileDecryptionProperties::builder(kms.unwrap_key(wrapped_key, key_id)?).build()?
+            }
+            None => FileDecryptionProperties::with_key_retriever(Arc::new(KmsKeyRetriever {
+                kms: kms.clone(),
+                key_id: key_id.clone(),
+            }))
+            .build()?,
+        };
+        Ok(Some(properties))
+    }
+}
+
+impl KeyRetriever for KmsKeyRetriever {
+    fn retrieve_key(&self, key_metadata: &[u8]) -> parquet::errors::Result<Vec<u8>> {
+        self.kms
+            .unwrap_key(key_metadata, &self.key_id)
+            .map_err(|e| ParquetError::General(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::test_utils::XorKms;
+
+    #[test]
+    fn test_gcm_stream() {
+        let manager = StandardEncryptionManager::new(Arc::new(XorKms), "master");
+        let key_metadata = XorKms::wrap_key(b"0123456789012345", "master");
+        let data: Vec<u8> = (0..100).collect();
+        for block_length in [1, 7, 100, DEFAULT_GCM_BLOCK_LENGTH] {
+            let encrypted = manager
+                .encrypt_with_block_length(&data, &key_metadata, block_length)
+                .unwrap();
+            assert_eq!(data, manager.decrypt(&encrypted, &key_metadata).unwrap());
+        }
+        let encrypted = manager
+            .encrypt_with_block_length(&data, &key_metadata, 40)
+            .unwrap();
+        assert_eq!(8 + 100 + 3 * (NONCE_LEN + GCM_TAG_LENGTH), encrypted.len());
+        let empty = manager.encrypt(&[], &key_metadata).unwrap();
+        assert!(manager.decrypt(&empty, &key_metadata).unwrap().is_empty());
+
+        let mut tampered = encrypted.clone();
+        tampered[30] ^= 1;
+        assert!(manager.decrypt(&tampered, &key_metadata).is_err());
+        // Blocks are authenticated with their index, so they can't be reordered
+        let block = 40 + NONCE_LEN + GCM_TAG_LENGTH;
+        let mut swapped = encrypted[..8].to_vec();
+        swapped.extend_from_slice(&encrypted[8 + block..8 + 2 * block]);
+        swapped.extend_from_slice(&encrypted[8..8 + block]);
+        assert!(manager.decrypt(&swapped, &key_metadata).is_err());
+        assert!(manager
+            .decrypt(&encrypted[..encrypted.len() - 1], &key_metadata)
+            .is_err());
+
+        let other_key = XorKms::wrap_key(b"5432109876543210", "master");
+        assert!(manager.decrypt(&encrypted, &other_key).is_err());
+        assert!(manager.decrypt(&data, &key_metadata).is_err());
+        let short_key = XorKms::wrap_key(b"0123", "master");
+        assert!(matches!(
+            manager.encrypt(&data, &short_key),
+            Err(IcebergError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+
+// Crate wide error type. Errors from the underlying libraries are wrapped as is so that callers
+// can still inspect them, while errors detected by rustberg itself carry a descriptive message
+#[derive(Debug)]
+pub enum IcebergError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    // Boxed as Avro errors are large and would bloat every Result of the crate
+    #[cfg(feature = "avro")]
+    Avro(Box<apache_avro::Error>),
+    #[cfg(feature = "arrow")]
+    Arrow(arrow::error::ArrowError),
+    #[cfg(feature = "arrow")]
+    Parquet(parquet::errors::ParquetError),
+    // Metadata or data that violates the Iceberg spec or is inconsistent with the table
+    Invalid(String),
+    NotFound(String),
+    Unsupported(String),
+    // The caller is not allowed to perform the operation
+    Forbidden(String),
+    // A write was attempted in read-only mode, see read_only
+    ReadOnly(String),
+    // The table changed since the base of a commit was loaded. The commit can be applied again on
+    // top of the current table, see commit_with_retries
+    CommitConflict(String),
+}
+
+pub type Result<T> = std::result::Result<T, IcebergError>;
+
+impl fmt::Display for IcebergError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IcebergError::Io(e) => write!(f, "IO error: {}", e),
+            IcebergError::Json(e) => write!(f, "JSON error: {}", e),
+            #[cfg(feature = "avro")]
+            IcebergError::Avro(e) => write!(f, "Avro error: {}", e),
+            #[cfg(feature = "arrow")]
+            IcebergError::Arrow(e) => write!(f, "Arrow error: {}", e),
+            #[cfg(feature = "arrow")]
+            IcebergError::Parquet(e) => write!(f, "Parquet error: {}", e),
+            IcebergError::Invalid(msg) => write!(f, "Invalid: {}", msg),
+            IcebergError::NotFound(msg) => write!(f, "Not found: {}", msg),
+            IcebergError::Unsupported(msg) => write!(f, "Unsupported: {}", msg),
+            IcebergError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
+            IcebergError::ReadOnly(msg) => write!(f, "Read-only: {}", msg),
+            IcebergError::CommitConflict(msg) => write!(f, "Commit conflict: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for IcebergError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IcebergError::Io(e) => Some(e),
+            IcebergError::Json(e) => Some(e),
+            #[cfg(feature = "avro")]
+            IcebergError::Avro(e) => Some(e.as_ref()),
+            #[cfg(feature = "arrow")]
+            IcebergError::Arrow(e) => Some(e),
+            #[cfg(feature = "arrow")]
+            IcebergError::Parquet(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for IcebergError {
+    fn from(e: std::io::Error) -> Self {
+        IcebergError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for IcebergError {
+    fn from(e: serde_json::Error) -> Self {
+        IcebergError::Json(e)
+    }
+}
+
+#[cfg(feature = "avro")]
+impl From<apache_avro::Error> for IcebergError {
+    fn from(e: apache_avro::Error) -> Self {
+        IcebergError::Avro(Box::new(e))
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl From<arrow::error::ArrowError> for IcebergError {
+    fn from(e: arrow::error::ArrowError) -> Self {
+        IcebergError::Arrow(e)
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl From<parquet::errors::ParquetError> for IcebergError {
+    fn from(e: parquet::errors::ParquetError) -> Self {
+        IcebergError::Parquet(e)
+    }
+}
@@ -0,0 +1,230 @@
+// Reads the files of a scan plan on several threads, as reading them one at a time like
+// ScanPlan::to_arrow does leaves the object store idle while batches are decoded and consumed.
+// Memory is bounded by a budget shared by the workers: a file is only started while the files
+// being read and the batches waiting for the consumer fit in it, or when nothing else is held so
+// that files larger than the budget are still read, one at a time. Batches of different files
+// are interleaved, those of a file keep their order
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::{Arc, Condvar, Mutex};
+
+use arrow::array::RecordBatch;
+
+use crate::iceberg::error::Result;
+use crate::iceberg::reader::RecordBatchIter;
+use crate::iceberg::scan::{ScanPlan, TaskReader};
+
+pub const DEFAULT_READ_CONCURRENCY: usize = 8;
+pub const DEFAULT_MEMORY_BUDGET: usize = 512 * 1024 * 1024;
+// Capacity of the channel of batches, per worker
+const BUFFERED_BATCHES_PER_WORKER: usize = 2;
+
+#[derive(Debug, Clone)]
+pub struct ReadExecutor {
+    concurrency: usize,
+    memory_budget: usize,
+}
+
+impl Default for ReadExecutor {
+    fn default() -> Self {
+        ReadExecutor {
+            concurrency: DEFAULT_READ_CONCURRENCY,
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+        }
+    }
+}
+
+impl ReadExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Maximum number of files read at the same time
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    // Bytes of the files being read and of the decoded batches not consumed yet
+    pub fn with_memory_budget(mut self, memory_budget: usize) -> Self {
+        self.memory_budget = memory_budget;
+        self
+    }
+
+    // Reads the files of the plan into record batches of the scan schema, see ScanPlan::to_arrow.
+    // Dropping the batches stops the workers
+    pub fn read(&self, plan: &ScanPlan) -> Result<RecordBatchIter> {
+        let reader = plan.task_reader()?;
+        let tasks = Arc::new(plan.tasks().to_vec());
+        let budget = Arc::new(MemoryBudget::new(self.memory_budget));
+        let next = Arc::new(AtomicUsize::new(0));
+        let workers = self.concurrency.min(tasks.len());
+        let (sender, receiver) = sync_channel(workers * BUFFERED_BATCHES_PER_WORKER);
+        for _ in 0..workers {
+            let reader = reader.clone();
+            let tasks = tasks.clone();
+            let budget = budget.clone();
+            let next = next.clone();
+            let sender = sender.clone();
+            std::thread::spawn(move || {
+                while let Some(task) = tasks.get(next.fetch_add(1, Ordering::SeqCst)) {
+                    // Files are read whole, even by tasks reading part of them
+                    let file_size = task.data_file.file_size_in_bytes.max(0) as usize;
+                    if !budget.acquire(file_size) {
+                        break;
+                    }
+                    for batch in reader.read(task.clone()) {
+                        // Batches are accounted without waiting, the channel bounds their number
+                        let size = batch.as_ref().map_or(0, RecordBatch::get_array_memory_size);
+                        budget.add(size);
+                        if sender.send((batch, size)).is_err() {
+                            return;
+                        }
+                    }
+                    budget.release(file_size);
+                }
+            });
+        }
+        Ok(Box::new(ConcurrentBatches {
+            receiver,
+            budget,
+            reader: Some(reader),
+        }))
+    }
+}
+
+struct ConcurrentBatches {
+    receiver: Receiver<(Result<RecordBatch>, usize)>,
+    budget: Arc<MemoryBudget>,
+    // Finishes reporting the progress once the workers are done
+    reader: Option<TaskReader>,
+}
+
+impl Iterator for ConcurrentBatches {
+    type Item = Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.receiver.recv() {
+            Ok((batch, size)) => {
+                self.budget.release(size);
+                Some(batch)
+            }
+            Err(_) => {
+                if let Some(reader) = self.reader.take() {
+                    reader.finish();
+                }
+                None
+            }
+        }
+    }
+}
+
+impl Drop for ConcurrentBatches {
+    // Wakes the workers waiting for memory, those waiting to send fail once the receiver is
+    // dropped
+    fn drop(&mut self) {
+        self.budget.close();
+    }
+}
+
+struct MemoryBudget {
+    limit: usize,
+    state: Mutex<BudgetState>,
+    released: Condvar,
+}
+
+#[derive(Default)]
+struct BudgetState {
+    used: usize,
+    closed: bool,
+}
+
+impl MemoryBudget {
+    fn new(limit: usize) -> Self {
+        MemoryBudget {
+            limit,
+            state: Mutex::new(BudgetState::default()),
+            released: Condvar::new(),
+        }
+    }
+
+    // Waits until the bytes fit in the budget, or nothing else is held. Returns false if the
+    // consumer is gone
+    fn acquire(&self, bytes: usize) -> bool {
+        let mut state = self.state.lock().unwrap();
+        while !state.closed && state.used > 0 && state.used + bytes > self.limit {
+            state = self.released.wait(state).unwrap();
+        }
+        if state.closed {
+            return false;
+        }
+        state.used += bytes;
+        true
+    }
+
+    fn add(&self, bytes: usize) {
+        self.state.lock().unwrap().used += bytes;
+    }
+
+    fn release(&self, bytes: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.used = state.used.saturating_sub(bytes);
+        self.released.notify_all();
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{AsArray, Int64Array};
+    use arrow::datatypes::Int64Type;
+
+    use super::*;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+
+    fn ids(batches: RecordBatchIter) -> Vec<i64> {
+        let mut ids: Vec<i64> = batches
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                let ids: &Int64Array = batch.column(0).as_primitive::<Int64Type>();
+                ids.values().to_vec()
+            })
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_read_executor() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let mut table = catalog.create_table("db", "t", dir.path());
+        for id in 0..10 {
+            table = append_ids(&catalog, &table, &[2 * id, 2 * id + 1]);
+        }
+        let plan = table.scan().plan_files().unwrap();
+        assert_eq!(10, plan.tasks().len());
+        let expected: Vec<i64> = (0..20).collect();
+        assert_eq!(expected, ids(plan.to_arrow().unwrap()));
+
+        assert_eq!(expected, ids(ReadExecutor::new().read(&plan).unwrap()));
+        // Files larger than the budget are read one at a time
+        let executor = ReadExecutor::new()
+            .with_concurrency(3)
+            .with_memory_budget(1);
+        assert_eq!(expected, ids(executor.read(&plan).unwrap()));
+
+        // Dropping the batches early stops the workers
+        let mut batches = executor.read(&plan).unwrap();
+        assert!(batches.next().unwrap().is_ok());
+        drop(batches);
+
+        let mut empty = plan.clone();
+        empty.retain_tasks(|_| false);
+        assert!(executor.read(&empty).unwrap().next().is_none());
+    }
+}
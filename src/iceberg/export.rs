@@ -0,0 +1,334 @@
+// Listings of the data files of a table snapshot for engines without Iceberg support, such as
+// Arrow Dataset (FileSystemDataset.from_paths with a partition expression per file) or DuckDB
+// (read_parquet over the listed files). Reading the listed files gives the rows of the snapshot
+// as long as it has no delete files, which is why snapshots with deletes can't be exported.
+// Alternatively the rows themselves, deletes applied, are streamed in the Arrow IPC stream format
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use arrow::ipc::writer::StreamWriter;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::io::FileIO;
+use crate::iceberg::scan::ScanPlan;
+use crate::iceberg::spec::manifest::FileFormat;
+use crate::iceberg::table::Table;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct DatasetManifest {
+    pub table_uuid: Uuid,
+    pub metadata_location: String,
+    // None for tables without snapshots
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_id: Option<i64>,
+    // Names of the partition fields of the listed files, across all their partition specs
+    pub partition_columns: Vec<String>,
+    pub files: Vec<DatasetFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct DatasetFile {
+    // Location of the file, local files being listed as plain paths rather than "file:" URIs
+    pub path: String,
+    pub format: String,
+    pub record_count: i64,
+    pub file_size_in_bytes: i64,
+    // Human readable partition values by partition column, None for null values. Columns the
+    // partition spec of the file doesn't have are left out
+    pub partition: BTreeMap<String, Option<String>>,
+    // Hive style partition path of the file, e.g. "category=books/ts_day=2022-10-08"
+    pub hive_partition: String,
+}
+
+impl DatasetManifest {
+    // Lists the files of a planned scan of the table. Scans with delete files or files in other
+    // formats than Parquet can't be read consistently without Iceberg and are rejected
+    pub fn from_plan(table: &Table, plan: &ScanPlan) -> Result<Self> {
+        let metadata = table.metadata();
+        let mut partition_columns: Vec<String> = vec![];
+        let mut files = vec![];
+        for task in plan.tasks() {
+            let file = &task.data_file;
+            if !task.deletes.is_empty() {
+                return Err(IcebergError::Unsupported(format!(
+                    "Exporting data file {} with {} delete files, compact the table first",
+                    file.file_path,
+                    task.deletes.len()
+                )));
+            }
+            if file.file_format != FileFormat::Parquet {
+                return Err(IcebergError::Unsupported(format!(
+                    "Exporting {} files ({})",
+                    file.file_format, file.file_path
+                )));
+            }
+            let spec = metadata.partition_spec_by_id(task.spec_id).ok_or_else(|| {
+                IcebergError::NotFound(format!(
+                    "Partition spec {} of data file {}",
+                    task.spec_id, file.file_path
+                ))
+            })?;
+            let mut partition = BTreeMap::new();
+            for (field, value) in spec.fields.iter().zip(&file.partition) {
+                if !partition_columns.contains(&field.name) {
+                    partition_columns.push(field.name.clone());
+                }
+                partition.insert(
+                    field.name.clone(),
+                    value
+                        .as_ref()
+                        .map(|value| field.transform.to_human_string(Some(value))),
+                );
+            }
+            files.push(DatasetFile {
+                path: engine_path(&file.file_path).to_string(),
+                format: file.file_format.to_string(),
+                record_count: file.record_count,
+                file_size_in_bytes: file.file_size_in_bytes,
+                partition,
+                hive_partition: spec.partition_path(&file.partition),
+            });
+        }
+        let fingerprint = plan.fingerprint();
+        Ok(DatasetManifest {
+            table_uuid: fingerprint.table_uuid,
+            metadata_location: fingerprint.metadata_location.clone(),
+            snapshot_id: fingerprint.snapshot_id,
+            partition_columns,
+            files,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn write(&self, file_io: &dyn FileIO, location: &str) -> Result<()> {
+        file_io.write(location, Bytes::from(self.to_json()?))
+    }
+
+    // A CSV line per file, with a column per partition column. Null partition values are empty
+    pub fn to_csv(&self) -> String {
+        let mut header = vec![
+            "path",
+            "format",
+            "record_count",
+            "file_size_in_bytes",
+            "hive_partition",
+        ];
+        header.extend(self.partition_columns.iter().map(String::as_str));
+        let mut csv = csv_line(header);
+        for file in &self.files {
+            let record_count = file.record_count.to_string();
+            let file_size = file.file_size_in_bytes.to_string();
+            let mut line = vec![
+                file.path.as_str(),
+                file.format.as_str(),
+                &record_count,
+                &file_size,
+                &file.hive_partition,
+            ];
+            line.extend(self.partition_columns.iter().map(|column| {
+                file.partition
+                    .get(column)
+                    .and_then(Option::as_deref)
+                    .unwrap_or_default()
+            }));
+            csv.push_str(&csv_line(line));
+        }
+        csv
+    }
+
+    // A DuckDB query reading the files. Columns are matched by name across files so that files
+    // written before columns were added can be read, and partition source columns are read from
+    // the files rather than from their paths
+    pub fn to_duckdb_sql(&self) -> Result<String> {
+        if self.files.is_empty() {
+            return Err(IcebergError::Invalid(
+                "DuckDB can't read an empty list of files".to_string(),
+            ));
+        }
+        let files: Vec<String> = self
+            .files
+            .iter()
+            .map(|file| format!("'{}'", file.path.replace('\'', "''")))
+            .collect();
+        Ok(format!(
+            "SELECT * FROM read_parquet([{}], union_by_name = true, hive_partitioning = false)",
+            files.join(", ")
+        ))
+    }
+}
+
+impl ScanPlan {
+    // Writes the rows of the plan as an Arrow IPC stream, which pyarrow.ipc.open_stream and other
+    // Arrow implementations read batch by batch. Plans without files write the schema alone.
+    // Returns the number of rows written
+    pub fn write_ipc_stream(&self, writer: impl Write) -> Result<u64> {
+        let schema = self.arrow_schema()?;
+        let mut stream = StreamWriter::try_new(writer, &schema)?;
+        let mut rows = 0;
+        for batch in self.to_arrow()? {
+            let batch = batch?;
+            rows += batch.num_rows() as u64;
+            stream.write(&batch)?;
+        }
+        stream.finish()?;
+        Ok(rows)
+    }
+}
+
+// Engines reading Parquet without Iceberg don't all understand "file:" URIs
+fn engine_path(location: &str) -> &str {
+    location
+        .strip_prefix("file://")
+        .or_else(|| location.strip_prefix("file:"))
+        .unwrap_or(location)
+}
+
+fn csv_line(values: Vec<&str>) -> String {
+    let values: Vec<String> = values
+        .into_iter()
+        .map(|value| {
+            if value.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            } else {
+                value.to_string()
+            }
+        })
+        .collect();
+    values.join(",") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, Int64Array};
+    use arrow::ipc::reader::StreamReader;
+
+    use crate::iceberg::catalog::IcebergCatalog;
+    use crate::iceberg::spec::partition_spec::{PartitionField, PartitionSpec, Transform};
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+    use crate::iceberg::writer::position_delete::PositionDeleteWriter;
+
+    #[test]
+    fn test_dataset_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1, 2]);
+        let mut metadata = table.metadata().clone();
+        metadata.partition_specs.push(PartitionSpec {
+            spec_id: 1,
+            fields: vec![PartitionField {
+                source_id: 2,
+                source_ids: vec![],
+                field_id: 1000,
+                name: "data".to_string(),
+                transform: Transform::Identity,
+            }],
+        });
+        metadata.default_spec_id = 1;
+        let table = catalog.commit_table(&table, metadata).unwrap();
+        let table = append_ids(&catalog, &table, &[3]);
+
+        let plan = table.scan().plan_files().unwrap();
+        let mut manifest = DatasetManifest::from_plan(&table, &plan).unwrap();
+        manifest.files.sort_by_key(|file| file.record_count);
+        assert_eq!(vec!["data".to_string()], manifest.partition_columns);
+        assert_eq!(table.metadata().current_snapshot_id, manifest.snapshot_id);
+        let partitioned = &manifest.files[0];
+        assert_eq!(
+            BTreeMap::from([("data".to_string(), Some("row-3".to_string()))]),
+            partitioned.partition
+        );
+        assert_eq!("data=row-3", partitioned.hive_partition);
+        assert!(!partitioned.path.starts_with("file:"));
+        assert!(std::path::Path::new(&partitioned.path).exists());
+        assert!(manifest.files[1].partition.is_empty());
+        assert_eq!(
+            3,
+            manifest.files.iter().map(|f| f.record_count).sum::<i64>()
+        );
+
+        assert_eq!(
+            manifest,
+            DatasetManifest::from_json(&manifest.to_json().unwrap()).unwrap()
+        );
+        let csv = manifest.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            "path,format,record_count,file_size_in_bytes,hive_partition,data",
+            lines[0]
+        );
+        assert!(lines[1].ends_with("data=row-3,row-3"));
+        assert!(lines[2].ends_with(","));
+        let sql = manifest.to_duckdb_sql().unwrap();
+        assert!(sql.starts_with("SELECT * FROM read_parquet(['"));
+        assert!(sql.contains(&partitioned.path));
+
+        // Deleted rows would be read by other engines
+        let data_file = plan
+            .tasks()
+            .iter()
+            .find(|task| task.spec_id == 1)
+            .unwrap()
+            .data_file
+            .clone();
+        let mut writer =
+            PositionDeleteWriter::for_table(&table, data_file.partition.clone()).unwrap();
+        writer.delete(&data_file.file_path, 0);
+        let table = table
+            .new_row_delta()
+            .add_deletes(writer.close().unwrap())
+            .commit(&catalog)
+            .unwrap();
+        let plan = table.scan().plan_files().unwrap();
+        assert!(DatasetManifest::from_plan(&table, &plan).is_err());
+    }
+
+    #[test]
+    fn test_write_ipc_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+
+        // The schema is written without rows for empty tables
+        let mut stream = vec![];
+        let plan = table.scan().select(&["id"]).plan_files().unwrap();
+        assert_eq!(0, plan.write_ipc_stream(&mut stream).unwrap());
+        let reader = StreamReader::try_new(stream.as_slice(), None).unwrap();
+        assert_eq!(plan.arrow_schema().unwrap(), reader.schema());
+        assert_eq!(0, reader.count());
+
+        let table = append_ids(&catalog, &table, &[1, 2]);
+        let table = append_ids(&catalog, &table, &[3]);
+        let mut stream = vec![];
+        let plan = table.scan().select(&["id"]).plan_files().unwrap();
+        assert_eq!(3, plan.write_ipc_stream(&mut stream).unwrap());
+        let reader = StreamReader::try_new(stream.as_slice(), None).unwrap();
+        assert_eq!(1, reader.schema().fields().len());
+        let mut ids: Vec<i64> = reader
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                let ids = batch
+                    .column(0)
+                    .as_any()
+                    .downcast_ref::<Int64Array>()
+                    .unwrap();
+                ids.values().to_vec()
+            })
+            .collect();
+        ids.sort();
+        assert_eq!(vec![1, 2, 3], ids);
+    }
+}
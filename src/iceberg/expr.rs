@@ -0,0 +1,1209 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::spec::manifest::DataFile;
+use crate::iceberg::spec::schema::{FieldNames, IcebergType, PrimitiveType, StructType};
+use crate::iceberg::spec::values::{Bound, Literal};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum UnaryOperator {
+    IsNull,
+    NotNull,
+    IsNan,
+    NotNan,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BinaryOperator {
+    LessThan,
+    LessThanOrEq,
+    GreaterThan,
+    GreaterThanOrEq,
+    Eq,
+    NotEq,
+    StartsWith,
+    NotStartsWith,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SetOperator {
+    In,
+    NotIn,
+}
+
+// A filter on the rows of a table. Columns are referenced by name (nested struct fields with
+// dotted names) and resolved against a schema by binding the predicate
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    AlwaysTrue,
+    AlwaysFalse,
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+    Not(Box<Predicate>),
+    Unary {
+        op: UnaryOperator,
+        column: String,
+    },
+    Binary {
+        op: BinaryOperator,
+        column: String,
+        literal: Literal,
+    },
+    Set {
+        op: SetOperator,
+        column: String,
+        literals: Vec<Literal>,
+    },
+}
+
+// A column resolved against a schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundReference {
+    pub field_id: i32,
+    pub name: String,
+    pub primitive: PrimitiveType,
+}
+
+// A predicate bound to a schema. Literals have the type of the column they are compared with
+// and negations have been pushed down to the leaves
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoundPredicate {
+    AlwaysTrue,
+    AlwaysFalse,
+    And(Box<BoundPredicate>, Box<BoundPredicate>),
+    Or(Box<BoundPredicate>, Box<BoundPredicate>),
+    Unary {
+        op: UnaryOperator,
+        term: BoundReference,
+    },
+    Binary {
+        op: BinaryOperator,
+        term: BoundReference,
+        literal: Literal,
+    },
+    Set {
+        op: SetOperator,
+        term: BoundReference,
+        literals: Vec<Literal>,
+    },
+}
+
+impl UnaryOperator {
+    fn negate(self) -> Self {
+        match self {
+            UnaryOperator::IsNull => UnaryOperator::NotNull,
+            UnaryOperator::NotNull => UnaryOperator::IsNull,
+            UnaryOperator::IsNan => UnaryOperator::NotNan,
+            UnaryOperator::NotNan => UnaryOperator::IsNan,
+        }
+    }
+}
+
+impl BinaryOperator {
+    fn negate(self) -> Self {
+        match self {
+            BinaryOperator::LessThan => BinaryOperator::GreaterThanOrEq,
+            BinaryOperator::LessThanOrEq => BinaryOperator::GreaterThan,
+            BinaryOperator::GreaterThan => BinaryOperator::LessThanOrEq,
+            BinaryOperator::GreaterThanOrEq => BinaryOperator::LessThan,
+            BinaryOperator::Eq => BinaryOperator::NotEq,
+            BinaryOperator::NotEq => BinaryOperator::Eq,
+            BinaryOperator::StartsWith => BinaryOperator::NotStartsWith,
+            BinaryOperator::NotStartsWith => BinaryOperator::StartsWith,
+        }
+    }
+}
+
+impl SetOperator {
+    fn negate(self) -> Self {
+        match self {
+            SetOperator::In => SetOperator::NotIn,
+            SetOperator::NotIn => SetOperator::In,
+        }
+    }
+}
+
+impl Predicate {
+    pub fn is_null(column: &str) -> Self {
+        Self::unary(UnaryOperator::IsNull, column)
+    }
+
+    pub fn not_null(column: &str) -> Self {
+        Self::unary(UnaryOperator::NotNull, column)
+    }
+
+    pub fn is_nan(column: &str) -> Self {
+        Self::unary(UnaryOperator::IsNan, column)
+    }
+
+    pub fn not_nan(column: &str) -> Self {
+        Self::unary(UnaryOperator::NotNan, column)
+    }
+
+    pub fn less_than(column: &str, literal: Literal) -> Self {
+        Self::binary(BinaryOperator::LessThan, column, literal)
+    }
+
+    pub fn less_than_or_eq(column: &str, literal: Literal) -> Self {
+        Self::binary(BinaryOperator::LessThanOrEq, column, literal)
+    }
+
+    pub fn greater_than(column: &str, literal: Literal) -> Self {
+        Self::binary(BinaryOperator::GreaterThan, column, literal)
+    }
+
+    pub fn greater_than_or_eq(column: &str, literal: Literal) -> Self {
+        Self::binary(BinaryOperator::GreaterThanOrEq, column, literal)
+    }
+
+    pub fn equal(column: &str, literal: Literal) -> Self {
+        Self::binary(BinaryOperator::Eq, column, literal)
+    }
+
+    pub fn not_equal(column: &str, literal: Literal) -> Self {
+        Self::binary(BinaryOperator::NotEq, column, literal)
+    }
+
+    pub fn starts_with(column: &str, prefix: &str) -> Self {
+        Self::binary(
+            BinaryOperator::StartsWith,
+            column,
+            Literal::String(prefix.to_string()),
+        )
+    }
+
+    pub fn is_in(column: &str, literals: Vec<Literal>) -> Self {
+        Predicate::Set {
+            op: SetOperator::In,
+            column: column.to_string(),
+            literals,
+        }
+    }
+
+    pub fn not_in(column: &str, literals: Vec<Literal>) -> Self {
+        Predicate::Set {
+            op: SetOperator::NotIn,
+            column: column.to_string(),
+            literals,
+        }
+    }
+
+    pub fn and(self, other: Predicate) -> Self {
+        Predicate::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Predicate) -> Self {
+        Predicate::Or(Box::new(self), Box::new(other))
+    }
+
+    pub fn negate(self) -> Self {
+        Predicate::Not(Box::new(self))
+    }
+
+    fn unary(op: UnaryOperator, column: &str) -> Self {
+        Predicate::Unary {
+            op,
+            column: column.to_string(),
+        }
+    }
+
+    fn binary(op: BinaryOperator, column: &str, literal: Literal) -> Self {
+        Predicate::Binary {
+            op,
+            column: column.to_string(),
+            literal,
+        }
+    }
+
+    // Names of the columns referenced by the predicate
+    pub fn columns(&self) -> Vec<&str> {
+        match self {
+            Predicate::AlwaysTrue | Predicate::AlwaysFalse => vec![],
+            Predicate::And(left, right) | Predicate::Or(left, right) => {
+                let mut columns = left.columns();
+                columns.extend(right.columns());
+                columns
+            }
+            Predicate::Not(child) => child.columns(),
+            Predicate::Unary { column, .. }
+            | Predicate::Binary { column, .. }
+            | Predicate::Set { column, .. } => vec![column.as_str()],
+        }
+    }
+
+    // Resolves the columns of the predicate against the schema and converts the literals to the
+    // types of the columns
+    pub fn bind(&self, schema: &StructType, case_sensitive: bool) -> Result<BoundPredicate> {
+        self.bind_negated(schema, case_sensitive, false)
+    }
+
+    fn bind_negated(
+        &self,
+        schema: &StructType,
+        case_sensitive: bool,
+        negated: bool,
+    ) -> Result<BoundPredicate> {
+        let bind_child = |child: &Predicate| child.bind_negated(schema, case_sensitive, negated);
+        Ok(match (self, negated) {
+            (Predicate::AlwaysTrue, false) | (Predicate::AlwaysFalse, true) => {
+                BoundPredicate::AlwaysTrue
+            }
+            (Predicate::AlwaysTrue, true) | (Predicate::AlwaysFalse, false) => {
+                BoundPredicate::AlwaysFalse
+            }
+            (Predicate::And(left, right), false) | (Predicate::Or(left, right), true) => {
+                BoundPredicate::And(Box::new(bind_child(left)?), Box::new(bind_child(right)?))
+            }
+            (Predicate::Or(left, right), false) | (Predicate::And(left, right), true) => {
+                BoundPredicate::Or(Box::new(bind_child(left)?), Box::new(bind_child(right)?))
+            }
+            (Predicate::Not(child), _) => child.bind_negated(schema, case_sensitive, !negated)?,
+            (Predicate::Unary { op, column }, _) => {
+                let term = bind_reference(schema, column, case_sensitive)?;
+                if matches!(op, UnaryOperator::IsNan | UnaryOperator::NotNan)
+                    && !matches!(term.primitive, PrimitiveType::Float | PrimitiveType::Double)
+                {
+                    return Err(IcebergError::Invalid(format!(
+                        "NaN checks are only valid on floating point columns, {} is {:?}",
+                        column, term.primitive
+                    )));
+                }
+                BoundPredicate::Unary {
+                    op: if negated { op.negate() } else { *op },
+                    term,
+                }
+            }
+            (
+                Predicate::Binary {
+                    op,
+                    column,
+                    literal,
+                },
+                _,
+            ) => {
+                let term = bind_reference(schema, column, case_sensitive)?;
+                if matches!(
+                    op,
+                    BinaryOperator::StartsWith | BinaryOperator::NotStartsWith
+                ) && term.primitive != PrimitiveType::String
+                {
+                    return Err(IcebergError::Invalid(format!(
+                        "starts_with is only valid on string columns, {} is {:?}",
+                        column, term.primitive
+                    )));
+                }
+                BoundPredicate::Binary {
+                    op: if negated { op.negate() } else { *op },
+                    literal: literal.to_type(&term.primitive)?,
+                    term,
+                }
+            }
+            (
+                Predicate::Set {
+                    op,
+                    column,
+                    literals,
+                },
+                _,
+            ) => {
+                let term = bind_reference(schema, column, case_sensitive)?;
+                BoundPredicate::Set {
+                    op: if negated { op.negate() } else { *op },
+                    literals: literals
+                        .iter()
+                        .map(|literal| literal.to_type(&term.primitive))
+                        .collect::<Result<_>>()?,
+                    term,
+                }
+            }
+        })
+    }
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Predicate::AlwaysTrue => write!(f, "true"),
+            Predicate::AlwaysFalse => write!(f, "false"),
+            Predicate::And(left, right) => write!(f, "({} and {})", left, right),
+            Predicate::Or(left, right) => write!(f, "({} or {})", left, right),
+            Predicate::Not(child) => write!(f, "not({})", child),
+            Predicate::Unary { op, column } => match op {
+                UnaryOperator::IsNull => write!(f, "{} is null", column),
+                UnaryOperator::NotNull => write!(f, "{} is not null", column),
+                UnaryOperator::IsNan => write!(f, "is_nan({})", column),
+                UnaryOperator::NotNan => write!(f, "not_nan({})", column),
+            },
+            Predicate::Binary {
+                op,
+                column,
+                literal,
+            } => {
+                let op = match op {
+                    BinaryOperator::LessThan => "<",
+                    BinaryOperator::LessThanOrEq => "<=",
+                    BinaryOperator::GreaterThan => ">",
+                    BinaryOperator::GreaterThanOrEq => ">=",
+                    BinaryOperator::Eq => "=",
+                    BinaryOperator::NotEq => "!=",
+                    BinaryOperator::StartsWith => "starts with",
+                    BinaryOperator::NotStartsWith => "not starts with",
+                };
+                write!(f, "{} {} {}", column, op, literal)
+            }
+            Predicate::Set {
+                op,
+                column,
+                literals,
+            } => {
+                let literals: Vec<String> = literals.iter().map(Literal::to_string).collect();
+                let op = match op {
+                    SetOperator::In => "in",
+                    SetOperator::NotIn => "not in",
+                };
+                write!(f, "{} {} ({})", column, op, literals.join(", "))
+            }
+        }
+    }
+}
+
+// Parses SQL-like filters such as `id > 5 and (name = 'bob' or name is null)`. Besides
+// comparisons, filters can use `is [not] null`, `is [not] nan`, `[not] in (...)` and prefix
+// patterns with `[not] like 'abc%'`. Strings are single quoted, numbers are parsed as longs or
+// doubles and converted to the type of their column when binding
+impl FromStr for Predicate {
+    type Err = IcebergError;
+
+    fn from_str(filter: &str) -> Result<Self> {
+        let mut parser = FilterParser {
+            filter,
+            tokens: tokenize(filter)?,
+            position: 0,
+        };
+        let predicate = parser.or()?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(predicate),
+            Some(token) => Err(parser.invalid(&format!("unexpected {}", token))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    // Column names and keywords
+    Word(String),
+    Quoted(String),
+    Number(String),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) | Token::Number(word) => write!(f, "{}", word),
+            Token::Quoted(string) => write!(f, "'{}'", string),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+const SYMBOLS: [&str; 11] = ["<=", ">=", "!=", "<>", "==", "<", ">", "=", "(", ")", ","];
+
+fn tokenize(filter: &str) -> Result<Vec<Token>> {
+    let invalid =
+        |reason: &str| IcebergError::Invalid(format!("Invalid filter {:?}: {}", filter, reason));
+    let mut tokens = vec![];
+    let mut chars = filter.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' {
+            // Quotes are escaped by doubling them
+            chars.next();
+            let mut string = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '\'')) if chars.peek().map(|(_, c)| *c) == Some('\'') => {
+                        chars.next();
+                        string.push('\'');
+                    }
+                    Some((_, '\'')) => break,
+                    Some((_, c)) => string.push(c),
+                    None => return Err(invalid("unterminated string")),
+                }
+            }
+            tokens.push(Token::Quoted(string));
+        } else if c.is_ascii_digit()
+            || (c == '-' && filter[start + 1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            chars.next();
+            let mut end = start + 1;
+            while let Some(&(index, c)) = chars.peek() {
+                let exponent_sign = (c == '-' || c == '+') && filter[..index].ends_with(['e', 'E']);
+                if !(c.is_ascii_alphanumeric() || c == '.' || exponent_sign) {
+                    break;
+                }
+                chars.next();
+                end = index + c.len_utf8();
+            }
+            tokens.push(Token::Number(filter[start..end].to_string()));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(index, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_' || c == '.') {
+                    break;
+                }
+                chars.next();
+                end = index + c.len_utf8();
+            }
+            tokens.push(Token::Word(filter[start..end].to_string()));
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| filter[start..].starts_with(*symbol))
+                .ok_or_else(|| invalid(&format!("unexpected {:?}", c)))?;
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            tokens.push(Token::Symbol(symbol));
+        }
+    }
+    Ok(tokens)
+}
+
+struct FilterParser<'a> {
+    filter: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl FilterParser<'_> {
+    fn invalid(&self, reason: &str) -> IcebergError {
+        IcebergError::Invalid(format!("Invalid filter {:?}: {}", self.filter, reason))
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| self.invalid("unexpected end"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    // Consumes the next token if it is the keyword, ignoring case
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.position) {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn symbol(&mut self, symbol: &'static str) -> bool {
+        if self.tokens.get(self.position) == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_symbol(&mut self, symbol: &'static str) -> Result<()> {
+        match self.symbol(symbol) {
+            true => Ok(()),
+            false => Err(self.invalid(&format!("expected {}", symbol))),
+        }
+    }
+
+    fn or(&mut self) -> Result<Predicate> {
+        let mut predicate = self.and()?;
+        while self.keyword("or") {
+            predicate = predicate.or(self.and()?);
+        }
+        Ok(predicate)
+    }
+
+    fn and(&mut self) -> Result<Predicate> {
+        let mut predicate = self.not()?;
+        while self.keyword("and") {
+            predicate = predicate.and(self.not()?);
+        }
+        Ok(predicate)
+    }
+
+    fn not(&mut self) -> Result<Predicate> {
+        if self.keyword("not") {
+            return Ok(self.not()?.negate());
+        }
+        if self.symbol("(") {
+            let predicate = self.or()?;
+            self.expect_symbol(")")?;
+            return Ok(predicate);
+        }
+        if self.keyword("true") {
+            return Ok(Predicate::AlwaysTrue);
+        }
+        if self.keyword("false") {
+            return Ok(Predicate::AlwaysFalse);
+        }
+        self.term()
+    }
+
+    fn term(&mut self) -> Result<Predicate> {
+        let column = match self.next()? {
+            Token::Word(column) => column,
+            token => return Err(self.invalid(&format!("expected a column, found {}", token))),
+        };
+        if self.keyword("is") {
+            let negated = self.keyword("not");
+            let predicate = if self.keyword("null") {
+                Predicate::is_null(&column)
+            } else if self.keyword("nan") {
+                Predicate::is_nan(&column)
+            } else {
+                return Err(self.invalid("expected null or nan after is"));
+            };
+            return Ok(if negated {
+                predicate.negate()
+            } else {
+                predicate
+            });
+        }
+        let negated = self.keyword("not");
+        if self.keyword("in") {
+            self.expect_symbol("(")?;
+            let mut literals = vec![self.literal()?];
+            while self.symbol(",") {
+                literals.push(self.literal()?);
+            }
+            self.expect_symbol(")")?;
+            return Ok(match negated {
+                true => Predicate::not_in(&column, literals),
+                false => Predicate::is_in(&column, literals),
+            });
+        }
+        if self.keyword("like") {
+            let prefix = match self.literal()? {
+                Literal::String(pattern) => pattern
+                    .strip_suffix('%')
+                    .filter(|prefix| !prefix.contains(['%', '_']))
+                    .map(str::to_string),
+                _ => None,
+            }
+            .ok_or_else(|| self.invalid("only prefix patterns such as 'abc%' are supported"))?;
+            let predicate = Predicate::starts_with(&column, &prefix);
+            return Ok(if negated {
+                predicate.negate()
+            } else {
+                predicate
+            });
+        }
+        if negated {
+            return Err(self.invalid("expected in or like after not"));
+        }
+        let op = match self.next()? {
+            Token::Symbol("<") => BinaryOperator::LessThan,
+            Token::Symbol("<=") => BinaryOperator::LessThanOrEq,
+            Token::Symbol(">") => BinaryOperator::GreaterThan,
+            Token::Symbol(">=") => BinaryOperator::GreaterThanOrEq,
+            Token::Symbol("=" | "==") => BinaryOperator::Eq,
+            Token::Symbol("!=" | "<>") => BinaryOperator::NotEq,
+            token => return Err(self.invalid(&format!("expected an operator, found {}", token))),
+        };
+        Ok(Predicate::binary(op, &column, self.literal()?))
+    }
+
+    fn literal(&mut self) -> Result<Literal> {
+        match self.next()? {
+            Token::Quoted(string) => Ok(Literal::String(string)),
+            Token::Number(number) => match number.parse() {
+                Ok(value) => Ok(Literal::Long(value)),
+                Err(_) => number
+                    .parse()
+                    .map(Literal::Double)
+                    .map_err(|_| self.invalid(&format!("invalid number {}", number))),
+            },
+            Token::Word(word) if word.eq_ignore_ascii_case("true") => Ok(Literal::Boolean(true)),
+            Token::Word(word) if word.eq_ignore_ascii_case("false") => Ok(Literal::Boolean(false)),
+            token => Err(self.invalid(&format!("expected a literal, found {}", token))),
+        }
+    }
+}
+
+impl BoundPredicate {
+    // Field ids of the columns referenced by the predicate
+    pub fn field_ids(&self) -> Vec<i32> {
+        match self {
+            BoundPredicate::AlwaysTrue | BoundPredicate::AlwaysFalse => vec![],
+            BoundPredicate::And(left, right) | BoundPredicate::Or(left, right) => {
+                let mut ids = left.field_ids();
+                ids.extend(right.field_ids());
+                ids
+            }
+            BoundPredicate::Unary { term, .. }
+            | BoundPredicate::Binary { term, .. }
+            | BoundPredicate::Set { term, .. } => vec![term.field_id],
+        }
+    }
+
+    // Evaluates the predicate on a single row, given the values of its columns by field id.
+    // Comparisons with null values never match, except for the negated operators
+    pub fn evaluate(&self, value: &dyn Fn(i32) -> Option<Literal>) -> bool {
+        match self {
+            BoundPredicate::AlwaysTrue => true,
+            BoundPredicate::AlwaysFalse => false,
+            BoundPredicate::And(left, right) => left.evaluate(value) && right.evaluate(value),
+            BoundPredicate::Or(left, right) => left.evaluate(value) || right.evaluate(value),
+            BoundPredicate::Unary { op, term } => {
+                let value = value(term.field_id);
+                match op {
+                    UnaryOperator::IsNull => value.is_none(),
+                    UnaryOperator::NotNull => value.is_some(),
+                    UnaryOperator::IsNan => value.is_some_and(|value| is_nan(&value)),
+                    UnaryOperator::NotNan => !value.is_some_and(|value| is_nan(&value)),
+                }
+            }
+            BoundPredicate::Binary { op, term, literal } => {
+                let Some(value) = value(term.field_id) else {
+                    return matches!(op, BinaryOperator::NotEq | BinaryOperator::NotStartsWith);
+                };
+                let starts_with = || match (&value, literal) {
+                    (Literal::String(value), Literal::String(prefix)) => value.starts_with(prefix),
+                    _ => false,
+                };
+                match op {
+                    BinaryOperator::LessThan => value < *literal,
+                    BinaryOperator::LessThanOrEq => value <= *literal,
+                    BinaryOperator::GreaterThan => value > *literal,
+                    BinaryOperator::GreaterThanOrEq => value >= *literal,
+                    BinaryOperator::Eq => value == *literal,
+                    BinaryOperator::NotEq => value != *literal,
+                    BinaryOperator::StartsWith => starts_with(),
+                    BinaryOperator::NotStartsWith => !starts_with(),
+                }
+            }
+            BoundPredicate::Set { op, term, literals } => {
+                let contained = value(term.field_id).is_some_and(|value| literals.contains(&value));
+                match op {
+                    SetOperator::In => contained,
+                    SetOperator::NotIn => !contained,
+                }
+            }
+        }
+    }
+}
+
+// Resolves a possibly dotted column name to a primitive field of the schema, descending into
+// structs only
+fn bind_reference(
+    schema: &StructType,
+    column: &str,
+    case_sensitive: bool,
+) -> Result<BoundReference> {
+    let not_found = || IcebergError::NotFound(format!("Column {} in table schema", column));
+    let mut fields = &schema.fields;
+    let mut parts = column.split('.').peekable();
+    while let Some(part) = parts.next() {
+        let field = fields
+            .iter()
+            .find(|field| field.name == part)
+            .or_else(|| {
+                (!case_sensitive)
+                    .then(|| {
+                        fields
+                            .iter()
+                            .find(|field| field.name.eq_ignore_ascii_case(part))
+                    })
+                    .flatten()
+            })
+            .ok_or_else(not_found)?;
+        match (&field.field_type, parts.peek()) {
+            (IcebergType::Primitive(primitive), None) => {
+                return Ok(BoundReference {
+                    field_id: field.id,
+                    name: column.to_string(),
+                    primitive: primitive.clone(),
+                })
+            }
+            (IcebergType::Struct(nested), Some(_)) => fields = &nested.fields,
+            _ => {
+                return Err(IcebergError::Unsupported(format!(
+                    "Filtering on non primitive column {}",
+                    column
+                )))
+            }
+        }
+    }
+    Err(not_found())
+}
+
+// Decides whether a data file may contain rows matching a predicate, based on the column
+// metrics in its manifest entry. Missing metrics never exclude a file
+pub struct InclusiveMetricsEvaluator<'a> {
+    predicate: &'a BoundPredicate,
+    field_names: Option<&'a dyn FieldNames>,
+}
+
+impl<'a> InclusiveMetricsEvaluator<'a> {
+    pub fn new(predicate: &'a BoundPredicate) -> Self {
+        InclusiveMetricsEvaluator {
+            predicate,
+            field_names: None,
+        }
+    }
+
+    // Names the columns of invalid metrics in errors, with the schema they come from
+    pub fn with_field_names(mut self, field_names: &'a dyn FieldNames) -> Self {
+        self.field_names = Some(field_names);
+        self
+    }
+
+    pub fn might_match(&self, data_file: &DataFile) -> Result<bool> {
+        // Files without rows can't match anything
+        if data_file.record_count == 0 {
+            return Ok(false);
+        }
+        Metrics {
+            data_file,
+            field_names: self.field_names,
+        }
+        .eval(self.predicate)
+    }
+}
+
+struct Metrics<'a> {
+    data_file: &'a DataFile,
+    field_names: Option<&'a dyn FieldNames>,
+}
+
+impl<'a> Metrics<'a> {
+    fn count(map: &Option<HashMap<i32, i64>>, id: i32) -> Option<i64> {
+        map.as_ref().and_then(|map| map.get(&id)).copied()
+    }
+
+    fn bound(
+        &self,
+        map: &Option<HashMap<i32, Bound>>,
+        term: &BoundReference,
+    ) -> Result<Option<Literal>> {
+        map.as_ref()
+            .and_then(|map| map.get(&term.field_id))
+            .map(|bound| {
+                bound
+                    .decode(&term.primitive)
+                    .map(Cow::into_owned)
+                    .map_err(|e| {
+                        let column = match self.field_names {
+                            Some(field_names) => field_names.describe_field(term.field_id),
+                            None => format!("column {} (field id {})", term.name, term.field_id),
+                        };
+                        IcebergError::Invalid(format!(
+                            "Metrics of {} in {}: {}",
+                            column, self.data_file.file_path, e
+                        ))
+                    })
+            })
+            .transpose()
+    }
+
+    // True when the column is known to only contain nulls (and NaNs)
+    fn only_nulls_or_nans(&self, id: i32) -> bool {
+        let values = Self::count(&self.data_file.value_counts, id);
+        let nulls = Self::count(&self.data_file.null_value_counts, id).unwrap_or(0);
+        let nans = Self::count(&self.data_file.nan_value_counts, id).unwrap_or(0);
+        values.is_some_and(|values| values == nulls + nans)
+    }
+
+    fn eval(&self, predicate: &BoundPredicate) -> Result<bool> {
+        let file = self.data_file;
+        Ok(match predicate {
+            BoundPredicate::AlwaysTrue => true,
+            BoundPredicate::AlwaysFalse => false,
+            BoundPredicate::And(left, right) => self.eval(left)? && self.eval(right)?,
+            BoundPredicate::Or(left, right) => self.eval(left)? || self.eval(right)?,
+            BoundPredicate::Unary { op, term } => {
+                let id = term.field_id;
+                let values = Self::count(&file.value_counts, id);
+                let nulls = Self::count(&file.null_value_counts, id);
+                let nans = Self::count(&file.nan_value_counts, id);
+                match op {
+                    UnaryOperator::IsNull => nulls != Some(0),
+                    UnaryOperator::NotNull => !(values.is_some() && values == nulls),
+                    UnaryOperator::IsNan => {
+                        nans != Some(0) && !(values.is_some() && values == nulls)
+                    }
+                    UnaryOperator::NotNan => !(values.is_some() && values == nans),
+                }
+            }
+            BoundPredicate::Binary { op, term, literal } => {
+                if matches!(op, BinaryOperator::NotEq | BinaryOperator::NotStartsWith) {
+                    return Ok(true);
+                }
+                if self.only_nulls_or_nans(term.field_id) {
+                    return Ok(false);
+                }
+                let lower = self
+                    .bound(&file.lower_bounds, term)?
+                    .filter(|bound| !is_nan(bound));
+                let upper = self
+                    .bound(&file.upper_bounds, term)?
+                    .filter(|bound| !is_nan(bound));
+                match op {
+                    BinaryOperator::LessThan => lower.is_none_or(|lower| lower < *literal),
+                    BinaryOperator::LessThanOrEq => lower.is_none_or(|lower| lower <= *literal),
+                    BinaryOperator::GreaterThan => upper.is_none_or(|upper| upper > *literal),
+                    BinaryOperator::GreaterThanOrEq => upper.is_none_or(|upper| upper >= *literal),
+                    BinaryOperator::Eq => {
+                        lower.is_none_or(|lower| lower <= *literal)
+                            && upper.is_none_or(|upper| upper >= *literal)
+                    }
+                    BinaryOperator::StartsWith => match literal {
+                        Literal::String(prefix) => {
+                            let truncate = |bound: &str| {
+                                bound
+                                    .chars()
+                                    .take(prefix.chars().count())
+                                    .collect::<String>()
+                            };
+                            lower.is_none_or(|lower| match lower {
+                                Literal::String(lower) => truncate(&lower) <= *prefix,
+                                _ => true,
+                            }) && upper.is_none_or(|upper| match upper {
+                                Literal::String(upper) => truncate(&upper) >= *prefix,
+                                _ => true,
+                            })
+                        }
+                        _ => true,
+                    },
+                    BinaryOperator::NotEq | BinaryOperator::NotStartsWith => true,
+                }
+            }
+            BoundPredicate::Set { op, term, literals } => match op {
+                SetOperator::NotIn => true,
+                SetOperator::In => {
+                    if self.only_nulls_or_nans(term.field_id) {
+                        return Ok(false);
+                    }
+                    let lower = self.bound(&file.lower_bounds, term)?;
+                    let upper = self.bound(&file.upper_bounds, term)?;
+                    literals.iter().any(|literal| {
+                        lower.as_ref().is_none_or(|lower| lower <= literal)
+                            && upper.as_ref().is_none_or(|upper| upper >= literal)
+                    })
+                }
+            },
+        })
+    }
+}
+
+fn is_nan(literal: &Literal) -> bool {
+    match literal {
+        Literal::Float(v) => v.is_nan(),
+        Literal::Double(v) => v.is_nan(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::spec::manifest::{DataContentType, FileFormat};
+    use crate::iceberg::spec::schema::{IcebergSchemaV2, StructField};
+
+    fn schema() -> StructType {
+        let field = |id: i32, name: &str, field_type: IcebergType| StructField {
+            id,
+            name: name.to_string(),
+            required: false,
+            field_type,
+            doc: None,
+            initial_default: None,
+            write_default: None,
+        };
+        StructType {
+            fields: vec![
+                field(1, "id", IcebergType::Primitive(PrimitiveType::Long)),
+                field(2, "name", IcebergType::Primitive(PrimitiveType::String)),
+                field(3, "score", IcebergType::Primitive(PrimitiveType::Double)),
+                field(
+                    4,
+                    "location",
+                    IcebergType::Struct(StructType {
+                        fields: vec![field(
+                            5,
+                            "city",
+                            IcebergType::Primitive(PrimitiveType::String),
+                        )],
+                    }),
+                ),
+            ],
+        }
+    }
+
+    // A file with ids in [10, 20], names in ["b", "d"] and only null scores
+    fn data_file() -> DataFile {
+        DataFile {
+            content: DataContentType::Data,
+            file_path: "file:/data/a.parquet".to_string(),
+            file_format: FileFormat::Parquet,
+            partition: vec![],
+            record_count: 10,
+            file_size_in_bytes: 100,
+            column_sizes: None,
+            value_counts: Some(HashMap::from([(1, 10), (2, 10), (3, 10)])),
+            null_value_counts: Some(HashMap::from([(1, 0), (2, 2), (3, 10)])),
+            nan_value_counts: Some(HashMap::from([(3, 0)])),
+            lower_bounds: Some(HashMap::from([
+                (1, Bound::from(&Literal::Long(10))),
+                (2, Bound::from(&Literal::String("b".to_string()))),
+            ])),
+            upper_bounds: Some(HashMap::from([
+                (1, Bound::from(&Literal::Long(20))),
+                (2, Bound::from(&Literal::String("d".to_string()))),
+            ])),
+            key_metadata: None,
+            split_offsets: None,
+            equality_ids: None,
+            sort_order_id: None,
+            referenced_data_file: None,
+            content_offset: None,
+            content_size_in_bytes: None,
+        }
+    }
+
+    fn might_match(predicate: Predicate) -> bool {
+        let bound = predicate.bind(&schema(), true).unwrap();
+        InclusiveMetricsEvaluator::new(&bound)
+            .might_match(&data_file())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_metrics_evaluator_names_invalid_bounds() {
+        let mut file = data_file();
+        file.lower_bounds
+            .as_mut()
+            .unwrap()
+            .insert(1, Bound::from(vec![1, 2, 3]));
+        let bound = Predicate::less_than("id", Literal::Long(5))
+            .bind(&schema(), true)
+            .unwrap();
+        let error = InclusiveMetricsEvaluator::new(&bound)
+            .might_match(&file)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("column id (field id 1) in file:/data/a.parquet"),
+            "{}",
+            error
+        );
+
+        let schema = IcebergSchemaV2::new(7, None, schema());
+        let error = InclusiveMetricsEvaluator::new(&bound)
+            .with_field_names(&schema)
+            .might_match(&file)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("column id (field id 1, schema 7)"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_bind_converts_literals_and_pushes_down_not() {
+        let predicate = Predicate::less_than("id", Literal::Int(5))
+            .and(Predicate::is_null("location.city"))
+            .negate();
+        let bound = predicate.bind(&schema(), true).unwrap();
+
+        let id = BoundReference {
+            field_id: 1,
+            name: "id".to_string(),
+            primitive: PrimitiveType::Long,
+        };
+        let city = BoundReference {
+            field_id: 5,
+            name: "location.city".to_string(),
+            primitive: PrimitiveType::String,
+        };
+        assert_eq!(
+            BoundPredicate::Or(
+                Box::new(BoundPredicate::Binary {
+                    op: BinaryOperator::GreaterThanOrEq,
+                    term: id,
+                    literal: Literal::Long(5),
+                }),
+                Box::new(BoundPredicate::Unary {
+                    op: UnaryOperator::NotNull,
+                    term: city,
+                }),
+            ),
+            bound
+        );
+        assert_eq!(vec![1, 5], bound.field_ids());
+    }
+
+    #[test]
+    fn test_bind_errors() {
+        assert!(Predicate::is_null("missing").bind(&schema(), true).is_err());
+        assert!(Predicate::is_null("ID").bind(&schema(), true).is_err());
+        assert!(Predicate::is_null("ID").bind(&schema(), false).is_ok());
+        assert!(Predicate::is_null("location")
+            .bind(&schema(), true)
+            .is_err());
+        assert!(Predicate::is_nan("id").bind(&schema(), true).is_err());
+        assert!(Predicate::equal("id", Literal::Boolean(true))
+            .bind(&schema(), true)
+            .is_err());
+    }
+
+    #[test]
+    fn test_metrics_evaluator_comparisons() {
+        assert!(!might_match(Predicate::less_than("id", Literal::Long(10))));
+        assert!(might_match(Predicate::less_than_or_eq(
+            "id",
+            Literal::Long(10)
+        )));
+        assert!(!might_match(Predicate::greater_than(
+            "id",
+            Literal::Long(20)
+        )));
+        assert!(might_match(Predicate::greater_than_or_eq(
+            "id",
+            Literal::Long(20)
+        )));
+        assert!(might_match(Predicate::equal("id", Literal::Long(15))));
+        assert!(!might_match(Predicate::equal("id", Literal::Long(21))));
+        assert!(might_match(Predicate::not_equal("id", Literal::Long(15))));
+        assert!(!might_match(Predicate::is_in(
+            "id",
+            vec![Literal::Long(1), Literal::Long(30)]
+        )));
+        assert!(might_match(Predicate::is_in(
+            "id",
+            vec![Literal::Long(1), Literal::Long(12)]
+        )));
+        assert!(!might_match(
+            Predicate::equal("id", Literal::Long(21)).or(Predicate::equal("id", Literal::Long(5)))
+        ));
+        assert!(might_match(
+            Predicate::equal("id", Literal::Long(21)).negate()
+        ));
+    }
+
+    #[test]
+    fn test_metrics_evaluator_nulls_and_strings() {
+        assert!(!might_match(Predicate::is_null("id")));
+        assert!(might_match(Predicate::is_null("name")));
+        assert!(!might_match(Predicate::not_null("score")));
+        assert!(!might_match(Predicate::is_nan("score")));
+        assert!(!might_match(Predicate::greater_than(
+            "score",
+            Literal::Double(1.0)
+        )));
+        assert!(might_match(Predicate::starts_with("name", "c")));
+        assert!(might_match(Predicate::starts_with("name", "bob")));
+        assert!(!might_match(Predicate::starts_with("name", "e")));
+        assert!(!might_match(Predicate::starts_with("name", "a")));
+        // No metrics for the nested column
+        assert!(might_match(Predicate::equal(
+            "location.city",
+            Literal::String("Paris".to_string())
+        )));
+    }
+
+    #[test]
+    fn test_evaluate_rows() {
+        let row = |id: i32| match id {
+            1 => Some(Literal::Long(15)),
+            2 => Some(Literal::String("bob".to_string())),
+            _ => None,
+        };
+        let evaluate =
+            |predicate: Predicate| predicate.bind(&schema(), true).unwrap().evaluate(&row);
+        assert!(evaluate(Predicate::greater_than("id", Literal::Int(10))));
+        assert!(!evaluate(
+            Predicate::less_than("id", Literal::Long(15)).or(Predicate::is_null("name"))
+        ));
+        assert!(evaluate(Predicate::starts_with("name", "bo")));
+        assert!(!evaluate(Predicate::starts_with("name", "bo").negate()));
+        assert!(evaluate(Predicate::is_in(
+            "id",
+            vec![Literal::Long(1), Literal::Long(15)]
+        )));
+        // Null values only match negated comparisons
+        assert!(evaluate(Predicate::is_null("score")));
+        assert!(!evaluate(Predicate::equal("score", Literal::Double(1.0))));
+        assert!(evaluate(Predicate::not_equal(
+            "score",
+            Literal::Double(1.0)
+        )));
+        assert!(evaluate(Predicate::not_in("location.city", vec![])));
+    }
+
+    #[test]
+    fn test_display() {
+        let predicate = Predicate::greater_than("id", Literal::Long(5))
+            .and(Predicate::is_in(
+                "name",
+                vec![
+                    Literal::String("a".to_string()),
+                    Literal::String("b".to_string()),
+                ],
+            ))
+            .negate();
+        assert_eq!("not((id > 5 and name in (a, b)))", predicate.to_string());
+    }
+
+    #[test]
+    fn test_parse() {
+        let parse = |filter: &str| filter.parse::<Predicate>().unwrap().to_string();
+        assert_eq!("id > 5", parse("id > 5"));
+        assert_eq!(
+            "(id >= -2 and (name = it's or name is null))",
+            parse("id >= -2 AND (name = 'it''s' or name IS NULL)")
+        );
+        assert_eq!(
+            "score not in (1.5, 0.002)",
+            parse("score not in (1.5, 2e-3)")
+        );
+        assert_eq!(
+            "(location.city starts with Par and not(is_nan(score)))",
+            parse("location.city like 'Par%' and score is not nan")
+        );
+        assert_eq!("not(id != 1)", parse("not id <> 1"));
+
+        let predicate: Predicate = "id = 3 and name != 'bob'".parse().unwrap();
+        let bound = predicate.bind(&schema(), true).unwrap();
+        assert!(bound.evaluate(&|id| match id {
+            1 => Some(Literal::Long(3)),
+            2 => Some(Literal::String("alice".to_string())),
+            _ => None,
+        }));
+
+        for filter in [
+            "",
+            "id >",
+            "id > 5 5",
+            "(id > 5",
+            "name = 'bob",
+            "name like '%b'",
+            "id ~ 5",
+            "5 = id",
+        ] {
+            assert!(
+                matches!(filter.parse::<Predicate>(), Err(IcebergError::Invalid(_))),
+                "{}",
+                filter
+            );
+        }
+    }
+}
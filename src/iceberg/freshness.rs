@@ -0,0 +1,137 @@
+// Data freshness checks: a table, or a branch of it, is stale when its latest snapshot is older
+// than a maximum age. Meant for probes of schedulers and monitors, see `rustberg table freshness`
+use std::fmt;
+
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::ident::TableIdent;
+use crate::iceberg::table::{SnapshotSelector, Table};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreshnessReport {
+    pub table: TableIdent,
+    // None for the current snapshot of the table
+    pub branch: Option<String>,
+    // None for tables without snapshots, which are always stale
+    pub snapshot_id: Option<i64>,
+    pub snapshot_timestamp_ms: Option<i64>,
+    pub age_ms: Option<i64>,
+    pub max_age_ms: i64,
+    pub stale: bool,
+}
+
+// Checks the age of the current snapshot of the table, or of the head of a branch, at `now_ms`
+pub fn check_freshness(
+    table: &Table,
+    branch: Option<&str>,
+    max_age_ms: i64,
+    now_ms: i64,
+) -> Result<FreshnessReport> {
+    let selector = match branch {
+        Some(branch) => SnapshotSelector::Ref(branch.to_string()),
+        None => SnapshotSelector::Current,
+    };
+    let snapshot = if table.metadata().snapshots.iter().flatten().next().is_none() {
+        None
+    } else {
+        Some(table.snapshot_at(&selector)?)
+    };
+    let age_ms = snapshot.map(|snapshot| now_ms.saturating_sub(snapshot.timestamp_ms));
+    Ok(FreshnessReport {
+        table: table.ident().clone(),
+        branch: branch.map(str::to_string),
+        snapshot_id: snapshot.map(|snapshot| snapshot.snapshot_id),
+        snapshot_timestamp_ms: snapshot.map(|snapshot| snapshot.timestamp_ms),
+        age_ms,
+        max_age_ms,
+        stale: age_ms.is_none_or(|age| age > max_age_ms),
+    })
+}
+
+impl fmt::Display for FreshnessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.table)?;
+        if let Some(branch) = &self.branch {
+            write!(f, " (branch {})", branch)?;
+        }
+        let status = if self.stale { "STALE" } else { "FRESH" };
+        match (self.snapshot_id, self.age_ms) {
+            (Some(snapshot_id), Some(age_ms)) => write!(
+                f,
+                ": {}, snapshot {} is {} old, max age {}",
+                status,
+                snapshot_id,
+                format_duration_ms(age_ms),
+                format_duration_ms(self.max_age_ms)
+            ),
+            _ => write!(f, ": {}, no snapshot", status),
+        }
+    }
+}
+
+// Parses durations such as "90s", "15m", "2h" or "1d", and plain milliseconds
+pub fn parse_duration_ms(duration: &str) -> Result<i64> {
+    let duration = duration.trim();
+    let split = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(duration.len());
+    let (value, unit) = duration.split_at(split);
+    let invalid = || IcebergError::Invalid(format!("Invalid duration {:?}", duration));
+    let value: i64 = value.parse().map_err(|_| invalid())?;
+    let unit_ms = match unit {
+        "" | "ms" => 1,
+        "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 60 * 60 * 1000,
+        "d" => 24 * 60 * 60 * 1000,
+        _ => return Err(invalid()),
+    };
+    value.checked_mul(unit_ms).ok_or_else(invalid)
+}
+
+fn format_duration_ms(duration_ms: i64) -> String {
+    let seconds = duration_ms / 1000;
+    match seconds {
+        s if s < 60 => format!("{}s", s),
+        s if s < 60 * 60 => format!("{}m{}s", s / 60, s % 60),
+        s => format!("{}h{}m", s / 3600, s / 60 % 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::test_utils::{append, create_table, ids_batch};
+
+    #[test]
+    fn test_check_freshness() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = create_table(dir.path());
+        let report = check_freshness(&table, None, 1000, 0).unwrap();
+        assert!(report.stale);
+        assert_eq!(None, report.snapshot_id);
+
+        let table = append(&table, &ids_batch(&[1]));
+        let timestamp_ms = table.metadata().current_snapshot().unwrap().timestamp_ms;
+        let hour = parse_duration_ms("1h").unwrap();
+        let report = check_freshness(&table, None, hour, timestamp_ms + 1000).unwrap();
+        assert!(!report.stale);
+        assert_eq!(Some(1000), report.age_ms);
+        assert!(report.to_string().starts_with("db.table: FRESH, snapshot"));
+
+        let report = check_freshness(&table, Some("main"), hour, timestamp_ms + 2 * hour).unwrap();
+        assert!(report.stale);
+        assert!(report.to_string().contains("is 2h0m old, max age 1h0m"));
+        assert!(check_freshness(&table, Some("missing"), hour, timestamp_ms).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration_ms() {
+        assert_eq!(7_200_000, parse_duration_ms("2h").unwrap());
+        assert_eq!(90_000, parse_duration_ms("90s").unwrap());
+        assert_eq!(86_400_000, parse_duration_ms("1d").unwrap());
+        assert_eq!(250, parse_duration_ms("250").unwrap());
+        assert_eq!(250, parse_duration_ms("250ms").unwrap());
+        assert!(parse_duration_ms("h").is_err());
+        assert!(parse_duration_ms("2w").is_err());
+    }
+}
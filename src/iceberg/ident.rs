@@ -0,0 +1,138 @@
+// Names of namespaces and tables in a catalog. Both print as dotted names, "db" or "warehouse.db"
+// for namespaces and "db.table" for tables, and parse back from them, so levels of namespaces
+// and names of tables can't be empty or contain dots
+use std::fmt;
+use std::str::FromStr;
+
+use crate::iceberg::error::{IcebergError, Result};
+
+// A namespace of a catalog, made of one or more levels
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Namespace(Vec<String>);
+
+impl Namespace {
+    pub fn try_new(levels: Vec<String>) -> Result<Self> {
+        if levels.is_empty() {
+            return Err(IcebergError::Invalid("Empty namespace".to_string()));
+        }
+        for level in &levels {
+            validate_part("namespace", &levels.join("."), level)?;
+        }
+        Ok(Namespace(levels))
+    }
+
+    pub fn levels(&self) -> &[String] {
+        &self.0
+    }
+
+    // Name of a namespace with a single level, for catalogs without nested namespaces such as
+    // the metastore, where namespaces are databases
+    pub fn single_level(&self) -> Result<&str> {
+        match self.0.as_slice() {
+            [level] => Ok(level),
+            _ => Err(IcebergError::Unsupported(format!(
+                "Nested namespace {}",
+                self
+            ))),
+        }
+    }
+}
+
+impl FromStr for Namespace {
+    type Err = IcebergError;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Namespace::try_new(name.split('.').map(str::to_string).collect())
+    }
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join("."))
+    }
+}
+
+// A table of a catalog, the name of the table in its namespace
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TableIdent {
+    namespace: Namespace,
+    name: String,
+}
+
+impl TableIdent {
+    pub fn try_new(namespace: Namespace, name: &str) -> Result<Self> {
+        validate_part("table", &format!("{}.{}", namespace, name), name)?;
+        Ok(TableIdent {
+            namespace,
+            name: name.to_string(),
+        })
+    }
+
+    pub fn namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl FromStr for TableIdent {
+    type Err = IcebergError;
+
+    // The last part of the dotted name is the name of the table, the others the namespace
+    fn from_str(name: &str) -> Result<Self> {
+        let (namespace, table) = name.rsplit_once('.').ok_or_else(|| {
+            IcebergError::Invalid(format!(
+                "Invalid table {}, expected <namespace>.<table>",
+                name
+            ))
+        })?;
+        TableIdent::try_new(namespace.parse()?, table)
+    }
+}
+
+impl fmt::Display for TableIdent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.namespace, self.name)
+    }
+}
+
+fn validate_part(kind: &str, name: &str, part: &str) -> Result<()> {
+    if part.is_empty() || part.contains('.') {
+        return Err(IcebergError::Invalid(format!(
+            "Invalid {} {}, names can't be empty or contain dots",
+            kind, name
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_idents() {
+        let ident: TableIdent = "warehouse.db.t".parse().unwrap();
+        assert_eq!(&["warehouse", "db"], ident.namespace().levels());
+        assert_eq!("t", ident.name());
+        assert_eq!("warehouse.db.t", ident.to_string());
+        assert!(ident.namespace().single_level().is_err());
+
+        let ident: TableIdent = "db.t".parse().unwrap();
+        assert_eq!("db", ident.namespace().single_level().unwrap());
+        assert_eq!(
+            ident,
+            TableIdent::try_new("db".parse().unwrap(), "t").unwrap()
+        );
+
+        for invalid in ["t", "db.", ".t", "db..t", ""] {
+            assert!(invalid.parse::<TableIdent>().is_err(), "{}", invalid);
+        }
+        assert!("".parse::<Namespace>().is_err());
+        assert!(Namespace::try_new(vec![]).is_err());
+        assert!(Namespace::try_new(vec!["a.b".to_string()]).is_err());
+        assert!(TableIdent::try_new("db".parse().unwrap(), "a.b").is_err());
+    }
+}
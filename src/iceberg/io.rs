@@ -0,0 +1,395 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::read_only::ensure_writable;
+
+// Abstraction over the storage holding table metadata and data files. Locations are the URIs
+// found in table metadata (e.g. "file:/warehouse/db.db/table/metadata/00000-x.metadata.json")
+pub trait FileIO: Debug + Send + Sync {
+    fn read(&self, location: &str) -> Result<Bytes>;
+
+    // Writes the whole file, replacing it if it already exists
+    fn write(&self, location: &str, data: Bytes) -> Result<()>;
+
+    fn exists(&self, location: &str) -> Result<bool>;
+
+    fn delete(&self, location: &str) -> Result<()>;
+
+    // Lists the files below a directory location, recursively. Listing a missing directory
+    // returns no files
+    fn list(&self, location: &str) -> Result<Vec<FileInfo>>;
+
+    // Moves a file, failing with Invalid if the destination exists. Catalogs committing by
+    // rename rely on it being atomic, FileIOs that can't guarantee that don't support it
+    fn rename_if_absent(&self, from: &str, _to: &str) -> Result<()> {
+        Err(IcebergError::Unsupported(format!(
+            "Atomic rename of {} with this FileIO",
+            from
+        )))
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FileInfo {
+    pub location: String,
+    pub size_in_bytes: u64,
+    pub last_modified_ms: i64,
+}
+
+// Settings of the FileIO of a warehouse, whose scheme selects the implementation. Only local
+// filesystems are supported for now, which take no options
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct FileIOConfig {
+    pub warehouse: Option<String>,
+    pub options: BTreeMap<String, String>,
+}
+
+impl FileIOConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_warehouse(mut self, warehouse: &str) -> Self {
+        self.warehouse = Some(warehouse.to_string());
+        self
+    }
+
+    pub fn with_option(mut self, option: &str, value: &str) -> Self {
+        self.options.insert(option.to_string(), value.to_string());
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let Some(warehouse) = &self.warehouse {
+            let scheme = warehouse.split_once("://").map(|(scheme, _)| scheme);
+            if scheme.is_some_and(|scheme| scheme != "file") {
+                return Err(IcebergError::Unsupported(format!(
+                    "No FileIO for warehouse {}",
+                    warehouse
+                )));
+            }
+        }
+        if let Some(option) = self.options.keys().next() {
+            return Err(IcebergError::Unsupported(format!(
+                "LocalFileIO has no option {}",
+                option
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn build(&self) -> Result<Arc<dyn FileIO>> {
+        self.validate()?;
+        Ok(Arc::new(LocalFileIO::new()))
+    }
+}
+
+// FileIO for locally mounted filesystems (including NFS). Accepts plain paths as well as
+// "file:" URIs
+#[derive(Debug, Default, Clone)]
+pub struct LocalFileIO {}
+
+impl LocalFileIO {
+    pub fn new() -> Self {
+        LocalFileIO {}
+    }
+
+    fn path(location: &str) -> Result<PathBuf> {
+        let path = if let Some(path) = location.strip_prefix("file://") {
+            path
+        } else if let Some(path) = location.strip_prefix("file:") {
+            path
+        } else if location.contains("://") {
+            return Err(IcebergError::Unsupported(format!(
+                "LocalFileIO can't handle location {}",
+                location
+            )));
+        } else {
+            location
+        };
+        Ok(PathBuf::from(path))
+    }
+}
+
+impl FileIO for LocalFileIO {
+    #[tracing::instrument(name = "file_io.read", level = "debug", skip(self), fields(bytes), err)]
+    fn read(&self, location: &str) -> Result<Bytes> {
+        let data = std::fs::read(Self::path(location)?)?;
+        tracing::Span::current().record("bytes", data.len());
+        Ok(Bytes::from(data))
+    }
+
+    #[tracing::instrument(
+        name = "file_io.write",
+        level = "debug",
+        skip(self, data),
+        fields(bytes = data.len()),
+        err
+    )]
+    fn write(&self, location: &str, data: Bytes) -> Result<()> {
+        ensure_writable(&format!("write {}", location))?;
+        let path = Self::path(location)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(std::fs::write(path, data)?)
+    }
+
+    fn exists(&self, location: &str) -> Result<bool> {
+        Ok(Self::path(location)?.exists())
+    }
+
+    #[tracing::instrument(name = "file_io.delete", level = "debug", skip(self), err)]
+    fn delete(&self, location: &str) -> Result<()> {
+        ensure_writable(&format!("delete {}", location))?;
+        Ok(std::fs::remove_file(Self::path(location)?)?)
+    }
+
+    // Linking fails if the destination exists, unlike renaming which replaces it
+    #[tracing::instrument(name = "file_io.rename", level = "debug", skip(self), err)]
+    fn rename_if_absent(&self, from: &str, to: &str) -> Result<()> {
+        ensure_writable(&format!("rename {} to {}", from, to))?;
+        let (from, to) = (Self::path(from)?, Self::path(to)?);
+        match std::fs::hard_link(&from, &to) {
+            Ok(()) => Ok(std::fs::remove_file(from)?),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Err(IcebergError::Invalid(
+                format!("{} already exists", to.display()),
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[tracing::instrument(name = "file_io.list", level = "debug", skip(self), err)]
+    fn list(&self, location: &str) -> Result<Vec<FileInfo>> {
+        let mut files = vec![];
+        let mut directories = vec![(
+            Self::path(location)?,
+            location.trim_end_matches('/').to_string(),
+        )];
+        while let Some((path, location)) = directories.pop() {
+            let entries = match std::fs::read_dir(&path) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            for entry in entries {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                let child = format!("{}/{}", location, entry.file_name().to_string_lossy());
+                if metadata.is_dir() {
+                    directories.push((entry.path(), child));
+                } else {
+                    let modified = metadata.modified()?.duration_since(UNIX_EPOCH);
+                    files.push(FileInfo {
+                        location: child,
+                        size_in_bytes: metadata.len(),
+                        last_modified_ms: modified.map_or(0, |d| d.as_millis() as i64),
+                    });
+                }
+            }
+        }
+        files.sort_by(|a, b| a.location.cmp(&b.location));
+        Ok(files)
+    }
+}
+
+// FileIO keeping files in memory, for tests and embedded catalogs whose tables don't outlive the
+// process. Any location is accepted, e.g. "memory:/warehouse/db/table"
+#[derive(Debug, Default)]
+pub struct MemoryFileIO {
+    files: Mutex<BTreeMap<String, (Bytes, i64)>>,
+}
+
+impl MemoryFileIO {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl FileIO for MemoryFileIO {
+    fn read(&self, location: &str) -> Result<Bytes> {
+        let files = self.files.lock().unwrap();
+        match files.get(location) {
+            Some((data, _)) => Ok(data.clone()),
+            None => Err(IcebergError::NotFound(format!("File {}", location))),
+        }
+    }
+
+    fn write(&self, location: &str, data: Bytes) -> Result<()> {
+        ensure_writable(&format!("write {}", location))?;
+        let modified = SystemTime::now().duration_since(UNIX_EPOCH);
+        let modified_ms = modified.map_or(0, |d| d.as_millis() as i64);
+        let mut files = self.files.lock().unwrap();
+        files.insert(location.to_string(), (data, modified_ms));
+        Ok(())
+    }
+
+    fn exists(&self, location: &str) -> Result<bool> {
+        Ok(self.files.lock().unwrap().contains_key(location))
+    }
+
+    fn delete(&self, location: &str) -> Result<()> {
+        ensure_writable(&format!("delete {}", location))?;
+        match self.files.lock().unwrap().remove(location) {
+            Some(_) => Ok(()),
+            None => Err(IcebergError::NotFound(format!("File {}", location))),
+        }
+    }
+
+    fn rename_if_absent(&self, from: &str, to: &str) -> Result<()> {
+        ensure_writable(&format!("rename {} to {}", from, to))?;
+        let mut files = self.files.lock().unwrap();
+        if files.contains_key(to) {
+            return Err(IcebergError::Invalid(format!("{} already exists", to)));
+        }
+        let file = files
+            .remove(from)
+            .ok_or_else(|| IcebergError::NotFound(format!("File {}", from)))?;
+        files.insert(to.to_string(), file);
+        Ok(())
+    }
+
+    fn list(&self, location: &str) -> Result<Vec<FileInfo>> {
+        let prefix = format!("{}/", location.trim_end_matches('/'));
+        let files = self.files.lock().unwrap();
+        Ok(files
+            .range(prefix.clone()..)
+            .take_while(|(file, _)| file.starts_with(&prefix))
+            .map(|(file, (data, modified_ms))| FileInfo {
+                location: file.clone(),
+                size_in_bytes: data.len() as u64,
+                last_modified_ms: *modified_ms,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_file_io_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = format!("file:{}/metadata/a.json", dir.path().display());
+        let file_io = LocalFileIO::new();
+
+        assert!(!file_io.exists(&location).unwrap());
+        file_io.write(&location, Bytes::from("{}")).unwrap();
+        assert!(file_io.exists(&location).unwrap());
+        assert_eq!(Bytes::from("{}"), file_io.read(&location).unwrap());
+
+        file_io.delete(&location).unwrap();
+        assert!(!file_io.exists(&location).unwrap());
+    }
+
+    #[test]
+    fn test_rename_if_absent() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = format!("file:{}", dir.path().display());
+        let file_ios: [(Arc<dyn FileIO>, &str); 2] = [
+            (Arc::new(LocalFileIO::new()), &local),
+            (Arc::new(MemoryFileIO::new()), "memory:/dir"),
+        ];
+        for (file_io, dir) in file_ios {
+            let (a, b) = (format!("{}/a", dir), format!("{}/b", dir));
+            file_io.write(&a, Bytes::from("a")).unwrap();
+            file_io.rename_if_absent(&a, &b).unwrap();
+            assert!(!file_io.exists(&a).unwrap());
+            assert_eq!(Bytes::from("a"), file_io.read(&b).unwrap());
+
+            file_io.write(&a, Bytes::from("other")).unwrap();
+            assert!(matches!(
+                file_io.rename_if_absent(&a, &b),
+                Err(IcebergError::Invalid(_))
+            ));
+            assert_eq!(Bytes::from("a"), file_io.read(&b).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_local_file_io_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = format!("file:{}/table", dir.path().display());
+        let file_io = LocalFileIO::new();
+        assert!(file_io.list(&location).unwrap().is_empty());
+
+        for file in ["metadata/a.json", "data/x=1/b.parquet", "data/c.parquet"] {
+            let file = format!("{}/{}", location, file);
+            file_io.write(&file, Bytes::from("abc")).unwrap();
+        }
+        let files = file_io.list(&format!("{}/", location)).unwrap();
+        assert_eq!(
+            vec![
+                format!("{}/data/c.parquet", location),
+                format!("{}/data/x=1/b.parquet", location),
+                format!("{}/metadata/a.json", location),
+            ],
+            files.iter().map(|f| f.location.clone()).collect::<Vec<_>>()
+        );
+        assert!(files.iter().all(|f| f.size_in_bytes == 3));
+        assert!(files.iter().all(|f| f.last_modified_ms > 0));
+    }
+
+    #[test]
+    fn test_memory_file_io() {
+        let file_io = MemoryFileIO::new();
+        let location = "memory:/warehouse/table";
+        assert!(file_io.list(location).unwrap().is_empty());
+        for file in ["metadata/a.json", "data/b.parquet"] {
+            let file = format!("{}/{}", location, file);
+            file_io.write(&file, Bytes::from("abc")).unwrap();
+        }
+        file_io
+            .write("memory:/warehouse/table2/c.json", Bytes::from("{}"))
+            .unwrap();
+        let files = file_io.list(location).unwrap();
+        assert_eq!(
+            vec![
+                format!("{}/data/b.parquet", location),
+                format!("{}/metadata/a.json", location),
+            ],
+            files.iter().map(|f| f.location.clone()).collect::<Vec<_>>()
+        );
+        assert!(files.iter().all(|f| f.size_in_bytes == 3));
+
+        let file = format!("{}/metadata/a.json", location);
+        assert_eq!(Bytes::from("abc"), file_io.read(&file).unwrap());
+        file_io.delete(&file).unwrap();
+        assert!(!file_io.exists(&file).unwrap());
+        assert!(matches!(
+            file_io.read(&file),
+            Err(IcebergError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_file_io_config() {
+        let config = FileIOConfig::new().with_warehouse("file:/warehouse");
+        assert!(config.build().is_ok());
+        assert!(FileIOConfig::new().build().is_ok());
+        let error = config
+            .clone()
+            .with_option("region", "eu")
+            .build()
+            .unwrap_err();
+        assert!(error.to_string().contains("no option region"));
+        let error = FileIOConfig::new()
+            .with_warehouse("s3://bucket/warehouse")
+            .validate()
+            .unwrap_err();
+        assert!(error.to_string().contains("s3://bucket/warehouse"));
+    }
+
+    #[test]
+    fn test_local_file_io_rejects_other_schemes() {
+        assert!(LocalFileIO::new().read("s3://bucket/key").is_err());
+    }
+}
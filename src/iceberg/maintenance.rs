@@ -0,0 +1,329 @@
+// Runs maintenance actions (snapshot expiration, compaction, manifest rewrites) on the tables of a
+// catalog on a schedule. Tables due for maintenance are processed by a bounded number of worker
+// threads, a table being maintained by one run at a time. Runs of a table are spread out by
+// adding a random jitter to its interval, so that tables registered together don't keep being
+// maintained at the same time
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::Result;
+use crate::iceberg::ident::TableIdent;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaintenanceAction {
+    // Expires the snapshots older than the given age at the time of the run, see ExpireSnapshots
+    ExpireSnapshots {
+        max_snapshot_age_ms: i64,
+        retain_last: usize,
+    },
+    // Compacts small data files, see RewriteDataFiles
+    RewriteDataFiles,
+    // Merges small manifests, see RewriteManifests
+    RewriteManifests,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionOutcome {
+    ExpiredSnapshots { expired: usize },
+    RewroteDataFiles { rewritten: usize, added: usize },
+    RewroteManifests { rewritten: usize, added: usize },
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableReport {
+    pub table: TableIdent,
+    // Set when the table was skipped because another run was still maintaining it
+    pub skipped: bool,
+    pub actions: Vec<(MaintenanceAction, ActionOutcome)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    pub started_at_ms: i64,
+    // Tables that were due, in the order they were registered
+    pub tables: Vec<TableReport>,
+}
+
+impl MaintenanceReport {
+    pub fn failed_actions(&self) -> usize {
+        self.tables
+            .iter()
+            .flat_map(|table| &table.actions)
+            .filter(|(_, outcome)| matches!(outcome, ActionOutcome::Failed { .. }))
+            .count()
+    }
+
+    // A line per maintained table
+    pub fn summary(&self) -> String {
+        self.tables
+            .iter()
+            .map(|table| {
+                let outcome = if table.skipped {
+                    "skipped, still running".to_string()
+                } else {
+                    table
+                        .actions
+                        .iter()
+                        .map(|(_, outcome)| match outcome {
+                            ActionOutcome::ExpiredSnapshots { expired } => {
+                                format!("expired {} snapshots", expired)
+                            }
+                            ActionOutcome::RewroteDataFiles { rewritten, added } => {
+                                format!("rewrote {} data files into {}", rewritten, added)
+                            }
+                            ActionOutcome::RewroteManifests { rewritten, added } => {
+                                format!("rewrote {} manifests into {}", rewritten, added)
+                            }
+                            ActionOutcome::Failed { error } => format!("failed: {}", error),
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                format!("{}: {}\n", table.table, outcome)
+            })
+            .collect()
+    }
+}
+
+struct ScheduledTable {
+    ident: TableIdent,
+    actions: Vec<MaintenanceAction>,
+    interval_ms: i64,
+    next_run_ms: Mutex<i64>,
+    // Held while the table is maintained
+    lock: Mutex<()>,
+}
+
+pub struct MaintenanceScheduler {
+    catalog: Arc<dyn IcebergCatalog>,
+    tables: Vec<ScheduledTable>,
+    max_concurrency: usize,
+    // Maximum jitter added to the interval of tables, as a fraction of the interval
+    jitter: f64,
+}
+
+const DEFAULT_MAX_CONCURRENCY: usize = 4;
+const DEFAULT_JITTER: f64 = 0.1;
+
+impl MaintenanceScheduler {
+    pub fn new(catalog: Arc<dyn IcebergCatalog>) -> Self {
+        MaintenanceScheduler {
+            catalog,
+            tables: vec![],
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            jitter: DEFAULT_JITTER,
+        }
+    }
+
+    // Maximum number of tables maintained at the same time
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.max(0.0);
+        self
+    }
+
+    // Runs the actions on the table every `interval_ms`, in order. Tables are due as soon as
+    // they are added
+    pub fn add_table(
+        mut self,
+        ident: TableIdent,
+        interval_ms: i64,
+        actions: Vec<MaintenanceAction>,
+    ) -> Self {
+        self.tables.push(ScheduledTable {
+            ident,
+            actions,
+            interval_ms,
+            next_run_ms: Mutex::new(i64::MIN),
+            lock: Mutex::new(()),
+        });
+        self
+    }
+
+    // Maintains the tables that are due. Failing actions are reported rather than returned, so
+    // that one broken table doesn't hold back the others
+    pub fn run_due(&self) -> Result<MaintenanceReport> {
+        let started_at_ms = self.catalog.current_time_ms()?;
+        let due: Vec<&ScheduledTable> = self
+            .tables
+            .iter()
+            .filter(|table| *table.next_run_ms.lock().unwrap() <= started_at_ms)
+            .collect();
+        let reports: Vec<Mutex<Option<TableReport>>> =
+            due.iter().map(|_| Mutex::new(None)).collect();
+        let next = AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..self.max_concurrency.min(due.len()) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(table) = due.get(index) else {
+                        break;
+                    };
+                    *reports[index].lock().unwrap() = Some(self.maintain(table, started_at_ms));
+                });
+            }
+        });
+        Ok(MaintenanceReport {
+            started_at_ms,
+            tables: reports
+                .into_iter()
+                .filter_map(|report| report.into_inner().unwrap())
+                .collect(),
+        })
+    }
+
+    // Runs the due tables every `poll_interval` until `stop` is set, logging the reports
+    pub fn run(&self, poll_interval: Duration, stop: &AtomicBool) {
+        while !stop.load(Ordering::SeqCst) {
+            match self.run_due() {
+                Ok(report) if !report.tables.is_empty() => {
+                    log::info!("Table maintenance:\n{}", report.summary())
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Table maintenance failed: {}", e),
+            }
+            std::thread::sleep(poll_interval);
+        }
+    }
+
+    fn maintain(&self, table: &ScheduledTable, now_ms: i64) -> TableReport {
+        let mut report = TableReport {
+            table: table.ident.clone(),
+            skipped: false,
+            actions: vec![],
+        };
+        let Ok(_lock) = table.lock.try_lock() else {
+            report.skipped = true;
+            return report;
+        };
+        for action in &table.actions {
+            let outcome =
+                self.run_action(table, action, now_ms)
+                    .unwrap_or_else(|e| ActionOutcome::Failed {
+                        error: e.to_string(),
+                    });
+            report.actions.push((action.clone(), outcome));
+        }
+        let jitter = (table.interval_ms as f64 * self.jitter * random_fraction()) as i64;
+        *table.next_run_ms.lock().unwrap() = now_ms
+            .saturating_add(table.interval_ms)
+            .saturating_add(jitter);
+        report
+    }
+
+    fn run_action(
+        &self,
+        table: &ScheduledTable,
+        action: &MaintenanceAction,
+        now_ms: i64,
+    ) -> Result<ActionOutcome> {
+        let catalog = self.catalog.as_ref();
+        let loaded = catalog.load_table(&table.ident)?;
+        Ok(match action {
+            MaintenanceAction::ExpireSnapshots {
+                max_snapshot_age_ms,
+                retain_last,
+            } => {
+                let result = loaded
+                    .expire_snapshots(now_ms.saturating_sub(*max_snapshot_age_ms), *retain_last)
+                    .commit(catalog)?;
+                ActionOutcome::ExpiredSnapshots {
+                    expired: result.expired_snapshot_ids.len(),
+                }
+            }
+            MaintenanceAction::RewriteDataFiles => {
+                let result = loaded.rewrite_data_files().commit(catalog)?;
+                ActionOutcome::RewroteDataFiles {
+                    rewritten: result.rewritten_files.len(),
+                    added: result.added_files.len(),
+                }
+            }
+            MaintenanceAction::RewriteManifests => {
+                let result = loaded.rewrite_manifests().commit(catalog)?;
+                ActionOutcome::RewroteManifests {
+                    rewritten: result.rewritten_manifests.len(),
+                    added: result.added_manifests.len(),
+                }
+            }
+        })
+    }
+}
+
+// Uniformly distributed in [0, 1)
+fn random_fraction() -> f64 {
+    (Uuid::new_v4().as_u128() as u64 >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+
+    #[test]
+    fn test_maintenance_scheduler() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = Arc::new(TestCatalog::new());
+        for name in ["a", "b"] {
+            let table = catalog.create_table("db", name, &dir.path().join(name));
+            let table = append_ids(&catalog, &table, &[1]);
+            append_ids(&catalog, &table, &[2]);
+        }
+        catalog.set_time_ms(i64::MAX / 2);
+        let actions = vec![
+            MaintenanceAction::RewriteDataFiles,
+            MaintenanceAction::ExpireSnapshots {
+                max_snapshot_age_ms: 0,
+                retain_last: 1,
+            },
+            MaintenanceAction::RewriteManifests,
+        ];
+        let scheduler = MaintenanceScheduler::new(catalog.clone())
+            .with_max_concurrency(2)
+            .add_table("db.a".parse().unwrap(), 60_000, actions.clone())
+            .add_table("db.b".parse().unwrap(), 60_000, actions.clone())
+            .add_table("db.missing".parse().unwrap(), 60_000, actions);
+
+        let report = scheduler.run_due().unwrap();
+        assert_eq!(3, report.tables.len());
+        assert_eq!(
+            (
+                MaintenanceAction::RewriteDataFiles,
+                ActionOutcome::RewroteDataFiles {
+                    rewritten: 2,
+                    added: 1
+                }
+            ),
+            report.tables[0].actions[0]
+        );
+        assert_eq!(
+            ActionOutcome::ExpiredSnapshots { expired: 2 },
+            report.tables[1].actions[1].1
+        );
+        assert_eq!(3, report.failed_actions());
+        assert!(report
+            .summary()
+            .starts_with("db.a: rewrote 2 data files into 1"));
+        let table = catalog.load_table(&"db.a".parse().unwrap()).unwrap();
+        // The data file rewrite and the manifest rewrite committed after expiring
+        assert_eq!(2, table.metadata().snapshots.as_ref().unwrap().len());
+
+        // Not due again before the interval
+        assert!(scheduler.run_due().unwrap().tables.is_empty());
+
+        // Tables still maintained by another run are skipped
+        catalog.set_time_ms(i64::MAX / 2 + 120_000);
+        let _lock = scheduler.tables[0].lock.lock().unwrap();
+        let report = scheduler.run_due().unwrap();
+        assert!(report.tables[0].skipped);
+        assert!(!report.tables[1].skipped);
+    }
+}
@@ -0,0 +1,605 @@
+// Metadata tables expose the snapshots, history, manifests, files and partitions of a table as
+// Arrow record batches, like the $snapshots, $history, $manifests, $files and $partitions tables
+// of other engines. They are computed from the table metadata and from the manifests of a
+// snapshot, the current one unless another is selected. Column names and field ids follow the
+// Java implementation; the column metrics of files and the partition summaries of manifests are
+// left out. Partitions are typed with the unified partition type of the table, and the partition
+// column is left out of unpartitioned tables
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, BooleanArray, Int32Array, Int64Array, MapArray, RecordBatch, StringArray,
+    StructArray, TimestampMicrosecondArray,
+};
+use arrow::buffer::OffsetBuffer;
+use arrow::datatypes::{DataType, SchemaRef};
+
+use crate::iceberg::arrow::{partitions_to_array, schema_to_arrow, UTC_TIMEZONE};
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::operations::statistics::{
+    read_or_compute_partition_statistics, unified_partition, unified_positions,
+};
+use crate::iceberg::spec::manifest::ManifestEntry;
+use crate::iceberg::spec::manifest_list::{FileType, ManifestListV2};
+use crate::iceberg::spec::partition_statistics::PartitionStatistics;
+use crate::iceberg::spec::schema::{IcebergType, MapType, PrimitiveType, StructField, StructType};
+use crate::iceberg::spec::snapshot::SnapshotV2;
+use crate::iceberg::spec::values::Literal;
+use crate::iceberg::table::Table;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataTableType {
+    // Every snapshot of the table
+    Snapshots,
+    // Snapshots that were current, in the order they became current
+    History,
+    // Manifests of the snapshot
+    Manifests,
+    // Data and delete files of the snapshot
+    Files,
+    // Record and file counts of the partitions of the snapshot
+    Partitions,
+}
+
+impl MetadataTableType {
+    pub const ALL: [MetadataTableType; 5] = [
+        MetadataTableType::Snapshots,
+        MetadataTableType::History,
+        MetadataTableType::Manifests,
+        MetadataTableType::Files,
+        MetadataTableType::Partitions,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetadataTableType::Snapshots => "snapshots",
+            MetadataTableType::History => "history",
+            MetadataTableType::Manifests => "manifests",
+            MetadataTableType::Files => "files",
+            MetadataTableType::Partitions => "partitions",
+        }
+    }
+}
+
+impl fmt::Display for MetadataTableType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MetadataTableType {
+    type Err = IcebergError;
+
+    fn from_str(name: &str) -> Result<Self> {
+        MetadataTableType::ALL
+            .into_iter()
+            .find(|table_type| table_type.as_str().eq_ignore_ascii_case(name))
+            .ok_or_else(|| IcebergError::Invalid(format!("Unknown metadata table {}", name)))
+    }
+}
+
+// A metadata table of a table, see Table::metadata_table
+#[derive(Debug, Clone)]
+pub struct MetadataTable<'a> {
+    table: &'a Table,
+    table_type: MetadataTableType,
+    snapshot_id: Option<i64>,
+}
+
+impl<'a> MetadataTable<'a> {
+    pub(crate) fn new(table: &'a Table, table_type: MetadataTableType) -> Self {
+        MetadataTable {
+            table,
+            table_type,
+            snapshot_id: None,
+        }
+    }
+
+    // Computes the manifests, files and partitions of the given snapshot instead of the current
+    // one. Snapshots and history always cover the whole table
+    pub fn with_snapshot_id(mut self, snapshot_id: i64) -> Self {
+        self.snapshot_id = Some(snapshot_id);
+        self
+    }
+
+    pub fn table_type(&self) -> MetadataTableType {
+        self.table_type
+    }
+
+    // Iceberg schema of the metadata table
+    pub fn schema(&self) -> Result<StructType> {
+        let field = |id, name: &str, required, field_type| StructField {
+            id,
+            name: name.to_string(),
+            required,
+            field_type,
+            doc: None,
+            initial_default: None,
+            write_default: None,
+        };
+        let primitive = IcebergType::Primitive;
+        let int = || primitive(PrimitiveType::Int);
+        let long = || primitive(PrimitiveType::Long);
+        let string = || primitive(PrimitiveType::String);
+        let timestamptz = || primitive(PrimitiveType::Timestamptz);
+        let partition = || -> Result<Vec<StructField>> {
+            let partition_type = self.table.metadata().unified_partition_type()?;
+            Ok(match partition_type.fields.is_empty() {
+                true => vec![],
+                false => vec![field(
+                    102,
+                    "partition",
+                    true,
+                    IcebergType::Struct(partition_type),
+                )],
+            })
+        };
+        let fields = match self.table_type {
+            MetadataTableType::Snapshots => vec![
+                field(1, "committed_at", true, timestamptz()),
+                field(2, "snapshot_id", true, long()),
+                field(3, "parent_id", false, long()),
+                field(4, "operation", false, string()),
+                field(5, "manifest_list", false, string()),
+                field(
+                    6,
+                    "summary",
+                    false,
+                    IcebergType::Map(MapType {
+                        key_id: 7,
+                        key: Box::new(string()),
+                        value_id: 8,
+                        value: Box::new(string()),
+                        value_required: true,
+                    }),
+                ),
+            ],
+            MetadataTableType::History => vec![
+                field(1, "made_current_at", true, timestamptz()),
+                field(2, "snapshot_id", true, long()),
+                field(3, "parent_id", false, long()),
+                field(
+                    4,
+                    "is_current_ancestor",
+                    true,
+                    primitive(PrimitiveType::Boolean),
+                ),
+            ],
+            MetadataTableType::Manifests => vec![
+                field(14, "content", true, int()),
+                field(1, "path", true, string()),
+                field(2, "length", true, long()),
+                field(3, "partition_spec_id", true, int()),
+                field(4, "added_snapshot_id", true, long()),
+                field(5, "added_data_files_count", true, int()),
+                field(6, "existing_data_files_count", true, int()),
+                field(7, "deleted_data_files_count", true, int()),
+                field(15, "added_delete_files_count", true, int()),
+                field(16, "existing_delete_files_count", true, int()),
+                field(17, "deleted_delete_files_count", true, int()),
+            ],
+            MetadataTableType::Files => {
+                let mut fields = vec![
+                    field(134, "content", true, int()),
+                    field(100, "file_path", true, string()),
+                    field(101, "file_format", true, string()),
+                    field(141, "spec_id", true, int()),
+                ];
+                fields.extend(partition()?);
+                fields.extend([
+                    field(103, "record_count", true, long()),
+                    field(104, "file_size_in_bytes", true, long()),
+                    field(140, "sort_order_id", false, int()),
+                ]);
+                fields
+            }
+            MetadataTableType::Partitions => {
+                let mut fields = partition()?;
+                if let Some(partition) = fields.first_mut() {
+                    partition.id = 1;
+                }
+                fields.extend([
+                    field(4, "spec_id", true, int()),
+                    field(2, "record_count", true, long()),
+                    field(10, "file_count", true, int()),
+                    field(11, "total_data_file_size_in_bytes", true, long()),
+                    field(5, "position_delete_record_count", true, long()),
+                    field(6, "position_delete_file_count", true, int()),
+                    field(7, "equality_delete_record_count", true, long()),
+                    field(8, "equality_delete_file_count", true, int()),
+                    field(9, "last_updated_at", false, timestamptz()),
+                    field(12, "last_updated_snapshot_id", false, long()),
+                ]);
+                fields
+            }
+        };
+        Ok(StructType { fields })
+    }
+
+    pub fn arrow_schema(&self) -> Result<SchemaRef> {
+        Ok(Arc::new(schema_to_arrow(&self.schema()?)?))
+    }
+
+    // Computes the rows of the metadata table. Tables without snapshots have no manifests, files
+    // or partitions
+    pub fn to_record_batch(&self) -> Result<RecordBatch> {
+        let schema = self.arrow_schema()?;
+        let columns = match self.table_type {
+            MetadataTableType::Snapshots => self.snapshots(&schema)?,
+            MetadataTableType::History => self.history(),
+            MetadataTableType::Manifests => self.manifests()?,
+            MetadataTableType::Files => self.files()?,
+            MetadataTableType::Partitions => self.partitions()?,
+        };
+        if columns.is_empty() {
+            return Ok(RecordBatch::new_empty(schema));
+        }
+        Ok(RecordBatch::try_new(schema, columns)?)
+    }
+
+    fn snapshot(&self) -> Result<Option<&'a SnapshotV2>> {
+        let metadata = self.table.metadata();
+        match self.snapshot_id {
+            Some(snapshot_id) => metadata
+                .snapshot_by_id(snapshot_id)
+                .map(Some)
+                .ok_or_else(|| IcebergError::NotFound(format!("Snapshot {}", snapshot_id))),
+            None => Ok(metadata.current_snapshot()),
+        }
+    }
+
+    fn snapshots(&self, schema: &SchemaRef) -> Result<Vec<ArrayRef>> {
+        let snapshots: Vec<&SnapshotV2> =
+            self.table.metadata().snapshots.iter().flatten().collect();
+        let summaries: Vec<Vec<(&str, &str)>> = snapshots
+            .iter()
+            .map(|snapshot| {
+                let mut summary: Vec<_> = snapshot
+                    .summary
+                    .rest
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str()))
+                    .collect();
+                summary.sort();
+                summary
+            })
+            .collect();
+        let operations = snapshots
+            .iter()
+            .map(|snapshot| {
+                Ok(serde_json::to_value(&snapshot.summary.operation)?
+                    .as_str()
+                    .map(str::to_string))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(vec![
+            timestamps(snapshots.iter().map(|snapshot| snapshot.timestamp_ms)),
+            Arc::new(Int64Array::from_iter_values(
+                snapshots.iter().map(|snapshot| snapshot.snapshot_id),
+            )),
+            Arc::new(Int64Array::from_iter(
+                snapshots.iter().map(|snapshot| snapshot.parent_snapshot_id),
+            )),
+            Arc::new(StringArray::from(operations)),
+            Arc::new(StringArray::from_iter_values(
+                snapshots.iter().map(|snapshot| &snapshot.manifest_list),
+            )),
+            string_map_array(schema.field(5).data_type(), &summaries)?,
+        ])
+    }
+
+    fn history(&self) -> Vec<ArrayRef> {
+        let metadata = self.table.metadata();
+        let mut ancestors = HashSet::new();
+        let mut ancestor = metadata.current_snapshot();
+        while let Some(snapshot) = ancestor {
+            ancestors.insert(snapshot.snapshot_id);
+            ancestor = snapshot
+                .parent_snapshot_id
+                .and_then(|parent_id| metadata.snapshot_by_id(parent_id));
+        }
+        let log: Vec<_> = metadata.snapshot_log.iter().flatten().collect();
+        let parent_id = |snapshot_id| {
+            metadata
+                .snapshot_by_id(snapshot_id)
+                .and_then(|snapshot| snapshot.parent_snapshot_id)
+        };
+        vec![
+            timestamps(log.iter().map(|entry| entry.timestamp_ms)),
+            Arc::new(Int64Array::from_iter_values(
+                log.iter().map(|entry| entry.snapshot_id),
+            )),
+            Arc::new(Int64Array::from_iter(
+                log.iter().map(|entry| parent_id(entry.snapshot_id)),
+            )),
+            Arc::new(BooleanArray::from_iter(
+                log.iter()
+                    .map(|entry| Some(ancestors.contains(&entry.snapshot_id))),
+            )),
+        ]
+    }
+
+    fn manifests(&self) -> Result<Vec<ArrayRef>> {
+        let manifests = match self.snapshot()? {
+            Some(snapshot) => self.table.manifests(snapshot)?,
+            None => return Ok(vec![]),
+        };
+        let counts = |content: FileType, count: fn(&ManifestListV2) -> i32| -> ArrayRef {
+            Arc::new(Int32Array::from_iter_values(manifests.iter().map(
+                |manifest| match manifest.content == content {
+                    true => count(manifest),
+                    false => 0,
+                },
+            )))
+        };
+        Ok(vec![
+            Arc::new(Int32Array::from_iter_values(
+                manifests
+                    .iter()
+                    .map(|manifest| manifest.content.clone() as i32),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                manifests.iter().map(|manifest| &manifest.manifest_path),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                manifests.iter().map(|manifest| manifest.manifest_length),
+            )),
+            Arc::new(Int32Array::from_iter_values(
+                manifests.iter().map(|manifest| manifest.partition_spec_id),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                manifests.iter().map(|manifest| manifest.added_snapshot_id),
+            )),
+            counts(FileType::Data, |manifest| manifest.added_files_count),
+            counts(FileType::Data, |manifest| manifest.existing_files_count),
+            counts(FileType::Data, |manifest| manifest.deleted_files_count),
+            counts(FileType::Delete, |manifest| manifest.added_files_count),
+            counts(FileType::Delete, |manifest| manifest.existing_files_count),
+            counts(FileType::Delete, |manifest| manifest.deleted_files_count),
+        ])
+    }
+
+    fn files(&self) -> Result<Vec<ArrayRef>> {
+        let Some(snapshot) = self.snapshot()? else {
+            return Ok(vec![]);
+        };
+        let metadata = self.table.metadata();
+        let partition_type = metadata.unified_partition_type()?;
+        let mut entries: Vec<(i32, ManifestEntry)> = vec![];
+        let mut partitions = vec![];
+        for manifest in self.table.manifests(snapshot)? {
+            let spec_id = manifest.partition_spec_id;
+            let positions = unified_positions(metadata, &partition_type, spec_id);
+            for entry in self.table.manifest_entries(&manifest)? {
+                if entry.is_live() {
+                    partitions.push(unified_partition(
+                        &positions,
+                        &partition_type,
+                        &entry.data_file,
+                    ));
+                    entries.push((spec_id, entry));
+                }
+            }
+        }
+        let files = || entries.iter().map(|(_, entry)| &entry.data_file);
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(Int32Array::from_iter_values(
+                files().map(|file| file.content as i32),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                files().map(|file| &file.file_path),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                files().map(|file| file.file_format.to_string()),
+            )),
+            Arc::new(Int32Array::from_iter_values(
+                entries.iter().map(|(spec_id, _)| *spec_id),
+            )),
+        ];
+        if !partition_type.fields.is_empty() {
+            columns.push(partition_array(&partitions, &partition_type)?);
+        }
+        columns.extend([
+            Arc::new(Int64Array::from_iter_values(
+                files().map(|file| file.record_count),
+            )) as ArrayRef,
+            Arc::new(Int64Array::from_iter_values(
+                files().map(|file| file.file_size_in_bytes),
+            )),
+            Arc::new(Int32Array::from_iter(
+                files().map(|file| file.sort_order_id),
+            )),
+        ]);
+        Ok(columns)
+    }
+
+    fn partitions(&self) -> Result<Vec<ArrayRef>> {
+        let Some(snapshot) = self.snapshot()? else {
+            return Ok(vec![]);
+        };
+        let statistics = read_or_compute_partition_statistics(self.table, snapshot)?;
+        let partition_type = self.table.metadata().unified_partition_type()?;
+        let mut columns: Vec<ArrayRef> = vec![];
+        if !partition_type.fields.is_empty() {
+            let partitions: Vec<_> = statistics
+                .iter()
+                .map(|statistics| statistics.partition.clone())
+                .collect();
+            columns.push(partition_array(&partitions, &partition_type)?);
+        }
+        let ints = |value: fn(&PartitionStatistics) -> i32| -> ArrayRef {
+            Arc::new(Int32Array::from_iter_values(statistics.iter().map(value)))
+        };
+        let longs = |value: fn(&PartitionStatistics) -> i64| -> ArrayRef {
+            Arc::new(Int64Array::from_iter_values(statistics.iter().map(value)))
+        };
+        columns.extend([
+            ints(|s| s.spec_id),
+            longs(|s| s.data_record_count),
+            ints(|s| s.data_file_count),
+            longs(|s| s.total_data_file_size_in_bytes),
+            longs(|s| s.position_delete_record_count),
+            ints(|s| s.position_delete_file_count),
+            longs(|s| s.equality_delete_record_count),
+            ints(|s| s.equality_delete_file_count),
+            Arc::new(
+                TimestampMicrosecondArray::from_iter(
+                    statistics
+                        .iter()
+                        .map(|s| s.last_updated_at.map(|ms| ms * 1000)),
+                )
+                .with_timezone(UTC_TIMEZONE),
+            ),
+            Arc::new(Int64Array::from_iter(
+                statistics.iter().map(|s| s.last_updated_snapshot_id),
+            )),
+        ]);
+        Ok(columns)
+    }
+}
+
+// Timestamptz column of timestamps in milliseconds
+fn timestamps(timestamps_ms: impl Iterator<Item = i64>) -> ArrayRef {
+    Arc::new(
+        TimestampMicrosecondArray::from_iter_values(timestamps_ms.map(|ms| ms * 1000))
+            .with_timezone(UTC_TIMEZONE),
+    )
+}
+
+fn partition_array(
+    partitions: &[Vec<Option<Literal>>],
+    partition_type: &StructType,
+) -> Result<ArrayRef> {
+    let partitions: Vec<&[Option<Literal>]> = partitions.iter().map(Vec::as_slice).collect();
+    Ok(Arc::new(partitions_to_array(&partitions, partition_type)?))
+}
+
+// Column of maps with string keys and values, of the given arrow map type
+fn string_map_array(data_type: &DataType, maps: &[Vec<(&str, &str)>]) -> Result<ArrayRef> {
+    let DataType::Map(entries_field, ordered) = data_type else {
+        unreachable!()
+    };
+    let DataType::Struct(fields) = entries_field.data_type() else {
+        unreachable!()
+    };
+    let (keys, values): (Vec<&str>, Vec<&str>) = maps.iter().flatten().copied().unzip();
+    let entries_array = StructArray::try_new(
+        fields.clone(),
+        vec![
+            Arc::new(StringArray::from(keys)),
+            Arc::new(StringArray::from(values)),
+        ],
+        None,
+    )?;
+    Ok(Arc::new(MapArray::try_new(
+        entries_field.clone(),
+        OffsetBuffer::from_lengths(maps.iter().map(Vec::len)),
+        entries_array,
+        None,
+        *ordered,
+    )?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::catalog::IcebergCatalog;
+    use crate::iceberg::spec::partition_spec::{PartitionField, PartitionSpec, Transform};
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+    use arrow::array::AsArray;
+    use arrow::datatypes::{Int32Type, Int64Type};
+
+    fn metadata_table(table: &Table, table_type: MetadataTableType) -> RecordBatch {
+        table.metadata_table(table_type).to_record_batch().unwrap()
+    }
+
+    #[test]
+    fn test_metadata_tables() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        for table_type in MetadataTableType::ALL {
+            assert_eq!(table_type, table_type.as_str().parse().unwrap());
+            let batch = metadata_table(&table, table_type);
+            assert_eq!(0, batch.num_rows(), "{}", table_type);
+        }
+        assert!("unknown".parse::<MetadataTableType>().is_err());
+
+        let table = append_ids(&catalog, &table, &[1, 2]);
+        let first = table.metadata().current_snapshot_id.unwrap();
+        // Identity partitioning on data, so that each new row is in its own partition
+        let mut metadata = table.metadata().clone();
+        metadata.partition_specs.push(PartitionSpec {
+            spec_id: 1,
+            fields: vec![PartitionField {
+                source_id: 2,
+                source_ids: vec![],
+                field_id: 1000,
+                name: "data".to_string(),
+                transform: Transform::Identity,
+            }],
+        });
+        metadata.default_spec_id = 1;
+        let table = catalog.commit_table(&table, metadata).unwrap();
+        let table = append_ids(&catalog, &table, &[3, 4]);
+        let current = table.metadata().current_snapshot_id.unwrap();
+
+        let snapshots = metadata_table(&table, MetadataTableType::Snapshots);
+        assert_eq!(2, snapshots.num_rows());
+        let ids = snapshots.column(1).as_primitive::<Int64Type>();
+        assert_eq!(vec![first, current], ids.values().to_vec());
+        assert!(snapshots.column(2).is_null(0));
+        assert_eq!("append", snapshots.column(3).as_string::<i32>().value(1));
+        let summary = snapshots.column(5).as_map().value(1);
+        let keys = summary.column(0).as_string::<i32>();
+        assert!(keys.iter().any(|key| key == Some("added-records")));
+
+        let history = metadata_table(&table, MetadataTableType::History);
+        assert_eq!(2, history.num_rows());
+        let ancestors = history.column(3).as_boolean();
+        assert!(ancestors.value(0) && ancestors.value(1));
+
+        let manifests = metadata_table(&table, MetadataTableType::Manifests);
+        assert_eq!(2, manifests.num_rows());
+        let added = manifests.column(5).as_primitive::<Int32Type>();
+        assert_eq!(3, added.iter().flatten().sum::<i32>());
+
+        let files = metadata_table(&table, MetadataTableType::Files);
+        assert_eq!(
+            "partition",
+            files.schema().field(4).name(),
+            "{:?}",
+            files.schema()
+        );
+        assert_eq!(3, files.num_rows());
+        let records = files.column(5).as_primitive::<Int64Type>();
+        assert_eq!(4, records.iter().flatten().sum::<i64>());
+
+        // Earlier snapshots are read with the unified partition type
+        let files = table
+            .metadata_table(MetadataTableType::Files)
+            .with_snapshot_id(first)
+            .to_record_batch()
+            .unwrap();
+        assert_eq!(1, files.num_rows());
+        assert!(files.column(4).as_struct().column(0).is_null(0));
+        assert!(table
+            .metadata_table(MetadataTableType::Files)
+            .with_snapshot_id(-1)
+            .to_record_batch()
+            .is_err());
+
+        let partitions = metadata_table(&table, MetadataTableType::Partitions);
+        assert_eq!(3, partitions.num_rows());
+        let data = partitions.column(0).as_struct().column(0);
+        assert_eq!(
+            Some("row-3"),
+            data.as_string::<i32>().iter().nth(1).flatten()
+        );
+        let records = partitions.column(2).as_primitive::<Int64Type>();
+        assert_eq!(vec![2, 1, 1], records.values().to_vec());
+    }
+}
@@ -0,0 +1,289 @@
+// Metrics of scan planning and commits, reported per operation so that services embedding
+// rustberg can tell which tables are slow to plan or contended. Tables report to the reporter set
+// with Table::with_metrics_reporter, which logs the metrics by default. With the metrics feature,
+// RecorderMetricsReporter forwards them to the recorder installed for the metrics crate, e.g. the
+// Prometheus exporter of metrics-exporter-prometheus
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+
+use crate::iceberg::ident::TableIdent;
+use crate::iceberg::spec::snapshot::Operation;
+use crate::iceberg::spec::snapshot_summary::{
+    ADDED_DATA_FILES, ADDED_DELETE_FILES, ADDED_FILES_SIZE, ADDED_RECORDS, DELETED_DATA_FILES,
+    DELETED_RECORDS, REMOVED_DELETE_FILES, REMOVED_FILES_SIZE,
+};
+use crate::iceberg::table::Table;
+
+// Planning of a scan, see TableScan::plan_files
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanMetrics {
+    pub table: TableIdent,
+    // None when the table has no snapshot
+    pub snapshot_id: Option<i64>,
+    // The row filter of the scan, if any
+    pub filter: Option<String>,
+    pub planning_duration: Duration,
+    pub total_data_manifests: u64,
+    pub total_delete_manifests: u64,
+    // Data manifests read, the others were skipped for their partition spec
+    pub scanned_data_manifests: u64,
+    pub skipped_data_manifests: u64,
+    // Live data files of the scanned manifests matching the filters, and those skipped
+    pub result_data_files: u64,
+    pub skipped_data_files: u64,
+    // Delete files applying to the result data files
+    pub result_delete_files: u64,
+    pub total_file_size_bytes: u64,
+}
+
+// A committed snapshot, with the changes counted in its summary
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitMetrics {
+    pub table: TableIdent,
+    pub snapshot_id: i64,
+    pub sequence_number: i64,
+    pub operation: Operation,
+    // Attempts made, more than one when the commit conflicted with concurrent ones
+    pub attempts: u32,
+    pub commit_duration: Duration,
+    pub added_data_files: u64,
+    pub removed_data_files: u64,
+    pub added_delete_files: u64,
+    pub removed_delete_files: u64,
+    pub added_records: u64,
+    pub removed_records: u64,
+    pub added_files_size_bytes: u64,
+    pub removed_files_size_bytes: u64,
+}
+
+// Receives the metrics of operations once they succeeded. Reporters are called by the thread
+// running the operation and shouldn't block it for long
+pub trait MetricsReporter: Debug + Send + Sync {
+    fn report_scan(&self, metrics: &ScanMetrics);
+
+    fn report_commit(&self, metrics: &CommitMetrics);
+}
+
+// Reporter logging the metrics at debug level
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingMetricsReporter;
+
+impl MetricsReporter for LoggingMetricsReporter {
+    fn report_scan(&self, metrics: &ScanMetrics) {
+        log::debug!("Planned scan of table {}: {:?}", metrics.table, metrics);
+    }
+
+    fn report_commit(&self, metrics: &CommitMetrics) {
+        log::debug!("Committed to table {}: {:?}", metrics.table, metrics);
+    }
+}
+
+// Reporter recording the metrics with the recorder installed for the metrics crate, as counters
+// and histograms labeled with the table
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RecorderMetricsReporter;
+
+#[cfg(feature = "metrics")]
+impl MetricsReporter for RecorderMetricsReporter {
+    fn report_scan(&self, metrics: &ScanMetrics) {
+        let table = metrics.table.to_string();
+        metrics::counter!("rustberg_scans_total", "table" => table.clone()).increment(1);
+        metrics::histogram!("rustberg_scan_planning_seconds", "table" => table.clone())
+            .record(metrics.planning_duration.as_secs_f64());
+        let counters = [
+            (
+                "rustberg_scan_scanned_manifests_total",
+                metrics.scanned_data_manifests,
+            ),
+            (
+                "rustberg_scan_skipped_manifests_total",
+                metrics.skipped_data_manifests,
+            ),
+            (
+                "rustberg_scan_result_data_files_total",
+                metrics.result_data_files,
+            ),
+            (
+                "rustberg_scan_skipped_data_files_total",
+                metrics.skipped_data_files,
+            ),
+            (
+                "rustberg_scan_result_delete_files_total",
+                metrics.result_delete_files,
+            ),
+        ];
+        for (name, value) in counters {
+            metrics::counter!(name, "table" => table.clone()).increment(value);
+        }
+    }
+
+    fn report_commit(&self, metrics: &CommitMetrics) {
+        let labels = [
+            ("table", metrics.table.to_string()),
+            ("operation", metrics.operation.to_string()),
+        ];
+        metrics::counter!("rustberg_commits_total", &labels).increment(1);
+        metrics::counter!("rustberg_commit_retries_total", &labels)
+            .increment(u64::from(metrics.attempts.saturating_sub(1)));
+        metrics::histogram!("rustberg_commit_seconds", &labels)
+            .record(metrics.commit_duration.as_secs_f64());
+        let counters = [
+            (
+                "rustberg_commit_added_data_files_total",
+                metrics.added_data_files,
+            ),
+            (
+                "rustberg_commit_removed_data_files_total",
+                metrics.removed_data_files,
+            ),
+            (
+                "rustberg_commit_added_delete_files_total",
+                metrics.added_delete_files,
+            ),
+            ("rustberg_commit_added_records_total", metrics.added_records),
+            (
+                "rustberg_commit_removed_records_total",
+                metrics.removed_records,
+            ),
+        ];
+        for (name, value) in counters {
+            metrics::counter!(name, &labels).increment(value);
+        }
+    }
+}
+
+// Reports the snapshot committed by an operation started at `start` on `base`, if `committed`
+// has a new one. The snapshot of the commit is the latest one, concurrent commits it was
+// applied on top of have lower sequence numbers
+pub(crate) fn report_commit(base: &Table, committed: &Table, attempts: u32, start: Instant) {
+    let metadata = committed.metadata();
+    let Some(snapshot) = metadata
+        .snapshots
+        .iter()
+        .flatten()
+        .find(|snapshot| snapshot.sequence_number == metadata.last_sequence_number)
+    else {
+        return;
+    };
+    if base
+        .metadata()
+        .snapshot_by_id(snapshot.snapshot_id)
+        .is_some()
+    {
+        return;
+    }
+    let count = |metric: &str| {
+        snapshot
+            .summary
+            .rest
+            .get(metric)
+            .and_then(|count| count.parse::<u64>().ok())
+            .unwrap_or_default()
+    };
+    base.metrics_reporter().report_commit(&CommitMetrics {
+        table: committed.ident().clone(),
+        snapshot_id: snapshot.snapshot_id,
+        sequence_number: snapshot.sequence_number,
+        operation: snapshot.summary.operation.clone(),
+        attempts,
+        commit_duration: start.elapsed(),
+        added_data_files: count(ADDED_DATA_FILES),
+        removed_data_files: count(DELETED_DATA_FILES),
+        added_delete_files: count(ADDED_DELETE_FILES),
+        removed_delete_files: count(REMOVED_DELETE_FILES),
+        added_records: count(ADDED_RECORDS),
+        removed_records: count(DELETED_RECORDS),
+        added_files_size_bytes: count(ADDED_FILES_SIZE),
+        removed_files_size_bytes: count(REMOVED_FILES_SIZE),
+    });
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::iceberg::catalog::IcebergCatalog;
+    use crate::iceberg::expr::Predicate;
+    use crate::iceberg::spec::values::Literal;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+
+    // Reporter recording the metrics it receives
+    #[derive(Debug, Default)]
+    pub(crate) struct RecordingMetricsReporter {
+        pub scans: Mutex<Vec<ScanMetrics>>,
+        pub commits: Mutex<Vec<CommitMetrics>>,
+    }
+
+    impl MetricsReporter for RecordingMetricsReporter {
+        fn report_scan(&self, metrics: &ScanMetrics) {
+            self.scans.lock().unwrap().push(metrics.clone());
+        }
+
+        fn report_commit(&self, metrics: &CommitMetrics) {
+            self.commits.lock().unwrap().push(metrics.clone());
+        }
+    }
+
+    #[test]
+    fn test_scan_and_commit_metrics() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let reporter = Arc::new(RecordingMetricsReporter::default());
+        let table = catalog
+            .create_table("db", "t", dir.path())
+            .with_metrics_reporter(reporter.clone());
+        let table = append_ids(&catalog, &table, &[1, 2]);
+        let table = append_ids(
+            &catalog,
+            &table.with_metrics_reporter(reporter.clone()),
+            &[10],
+        );
+
+        let commits = reporter.commits.lock().unwrap().clone();
+        assert_eq!(2, commits.len());
+        let commit = &commits[1];
+        assert_eq!("db.t", commit.table.to_string());
+        assert_eq!(
+            table.metadata().current_snapshot_id,
+            Some(commit.snapshot_id)
+        );
+        assert_eq!(Operation::Append, commit.operation);
+        assert_eq!(1, commit.attempts);
+        assert_eq!((1, 1), (commit.added_data_files, commit.added_records));
+
+        // Commits rebased on concurrent ones count their attempts
+        let stale = table.clone().with_metrics_reporter(reporter.clone());
+        append_ids(&catalog, &table, &[20]);
+        append_ids(&catalog, &stale, &[30]);
+        assert_eq!(2, reporter.commits.lock().unwrap()[2].attempts);
+
+        let table = catalog
+            .load_table(&"db.t".parse().unwrap())
+            .unwrap()
+            .with_metrics_reporter(reporter.clone());
+        let plan = table
+            .scan()
+            .filter(Predicate::less_than("id", Literal::Long(5)))
+            .plan_files()
+            .unwrap();
+        let scans = reporter.scans.lock().unwrap();
+        let scan = &scans[0];
+        assert_eq!(table.metadata().current_snapshot_id, scan.snapshot_id);
+        assert!(scan.filter.as_ref().unwrap().contains("id"));
+        assert_eq!(
+            (4, 0),
+            (scan.total_data_manifests, scan.total_delete_manifests)
+        );
+        assert_eq!(
+            (4, 0),
+            (scan.scanned_data_manifests, scan.skipped_data_manifests)
+        );
+        assert_eq!((1, 3), (scan.result_data_files, scan.skipped_data_files));
+        assert_eq!(
+            plan.tasks()[0].data_file.file_size_in_bytes as u64,
+            scan.total_file_size_bytes
+        );
+    }
+}
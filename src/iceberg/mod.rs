@@ -1,2 +1,64 @@
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "tokio")]
+pub mod asynchronous;
+#[cfg(feature = "arrow")]
+pub mod audit;
+#[cfg(feature = "arrow")]
+pub mod avro_reader;
+#[cfg(feature = "arrow")]
+pub mod cache;
+#[cfg(feature = "arrow")]
 pub mod catalog;
+#[cfg(feature = "arrow")]
+pub mod changelog;
+#[cfg(feature = "arrow")]
+pub mod deletes;
+#[cfg(feature = "arrow")]
+pub mod encryption;
+pub mod error;
+#[cfg(feature = "arrow")]
+pub mod executor;
+#[cfg(feature = "arrow")]
+pub mod export;
+#[cfg(feature = "arrow")]
+pub mod expr;
+#[cfg(feature = "arrow")]
+pub mod freshness;
+pub mod ident;
+pub mod io;
+#[cfg(feature = "arrow")]
+pub mod maintenance;
+#[cfg(feature = "arrow")]
+pub mod metadata_tables;
+#[cfg(feature = "arrow")]
+pub mod metrics;
+#[cfg(feature = "arrow")]
+pub mod operations;
+#[cfg(feature = "arrow")]
+pub mod progress;
+#[cfg(feature = "arrow")]
+pub mod properties;
+pub mod puffin;
+pub mod read_only;
+#[cfg(feature = "arrow")]
+pub mod read_set;
+#[cfg(feature = "arrow")]
+pub mod reader;
+pub mod retry;
+#[cfg(feature = "arrow")]
+pub mod rows;
+#[cfg(feature = "arrow")]
+pub mod scan;
 pub mod spec;
+#[cfg(feature = "arrow")]
+pub mod static_table;
+#[cfg(feature = "arrow")]
+pub mod summary;
+#[cfg(feature = "arrow")]
+pub mod table;
+#[cfg(all(test, feature = "arrow"))]
+pub(crate) mod test_utils;
+pub mod view;
+#[cfg(feature = "arrow")]
+pub mod writer;
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::operations::{check_default_spec, commit_with_retries, SnapshotProducer};
+use crate::iceberg::spec::manifest::{DataContentType, DataFile};
+use crate::iceberg::spec::snapshot::Operation;
+use crate::iceberg::spec::snapshot_summary::WAP_ID;
+use crate::iceberg::table::Table;
+
+// Appends data files to a table in a new manifest, keeping the manifests of the current snapshot
+// as they are. Files must be partitioned by the default partition spec of the table
+pub struct FastAppend<'a> {
+    table: &'a Table,
+    data_files: Vec<DataFile>,
+    summary: HashMap<String, String>,
+    stage_only: bool,
+}
+
+impl<'a> FastAppend<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        FastAppend {
+            table,
+            data_files: vec![],
+            summary: HashMap::new(),
+            stage_only: false,
+        }
+    }
+
+    pub fn add_file(mut self, data_file: DataFile) -> Self {
+        self.data_files.push(data_file);
+        self
+    }
+
+    pub fn add_files(mut self, data_files: Vec<DataFile>) -> Self {
+        self.data_files.extend(data_files);
+        self
+    }
+
+    // Adds a property to the summary of the new snapshot
+    pub fn set_summary(mut self, key: &str, value: &str) -> Self {
+        self.summary.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    // Commits the snapshot without making it current, with the write id in its wap.id summary
+    // property. The staged snapshot can be audited, e.g. scanned by id, before being published
+    // with Table::publish_wap_id
+    pub fn stage(mut self, wap_id: &str) -> Self {
+        self.summary.insert(WAP_ID.to_string(), wap_id.to_string());
+        self.stage_only = true;
+        self
+    }
+
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<Table> {
+        let spec = self.table.metadata().default_partition_spec()?;
+        for data_file in &self.data_files {
+            if data_file.content != DataContentType::Data {
+                return Err(IcebergError::Invalid(format!(
+                    "Can't append delete file {}",
+                    data_file.file_path
+                )));
+            }
+            if data_file.partition.len() != spec.fields.len() {
+                return Err(IcebergError::Invalid(format!(
+                    "Data file {} has {} partition values but partition spec {} has {} fields",
+                    data_file.file_path,
+                    data_file.partition.len(),
+                    spec.spec_id,
+                    spec.fields.len()
+                )));
+            }
+        }
+
+        // Appended files apply to any later version of the table with the same partition spec
+        commit_with_retries(catalog, self.table, |table| {
+            check_default_spec(table, spec)?;
+            let mut producer = SnapshotProducer::new(table);
+            producer.set_summary(self.summary.clone());
+            if self.stage_only {
+                producer.stage_only();
+            }
+            let mut manifests = vec![];
+            if !self.data_files.is_empty() {
+                manifests.push(producer.add_files(spec, self.data_files.clone())?);
+            }
+            manifests.extend(producer.current_manifests()?);
+            producer.commit(catalog, Operation::Append, &manifests)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use crate::iceberg::catalog::IcebergCatalog;
+    use crate::iceberg::error::IcebergError;
+    use crate::iceberg::properties::COMMIT_NUM_RETRIES_PROPERTY;
+    use crate::iceberg::test_utils::{append_ids, ids_batch, TestCatalog};
+    use crate::iceberg::writer::partitioned::PartitionedWriter;
+
+    #[test]
+    fn test_fast_append_keeps_existing_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = Arc::new(TestCatalog::new());
+        let mut table = catalog.create_table("db", "t", dir.path());
+        for ids in [vec![1, 2], vec![3]] {
+            let mut writer = PartitionedWriter::for_table(&table).unwrap();
+            writer.write(&ids_batch(&ids)).unwrap();
+            table = table
+                .new_append()
+                .add_files(writer.close().unwrap())
+                .set_summary("app", "test")
+                .commit(catalog.as_ref())
+                .unwrap();
+        }
+
+        let metadata = table.metadata();
+        let snapshot = metadata.current_snapshot().unwrap();
+        assert_eq!(2, snapshot.sequence_number);
+        assert_eq!(
+            Some("1"),
+            snapshot
+                .summary
+                .rest
+                .get("added-records")
+                .map(String::as_str)
+        );
+        assert_eq!(
+            Some("test"),
+            snapshot.summary.rest.get("app").map(String::as_str)
+        );
+        assert_eq!(2, metadata.metadata_log.as_ref().unwrap().len());
+        assert_eq!(
+            catalog
+                .load_table(&"db.t".parse().unwrap())
+                .unwrap()
+                .metadata_location(),
+            table.metadata_location()
+        );
+        assert_eq!(2, table.scan().plan_files().unwrap().tasks().len());
+
+        // The base table is stale now, the append is applied again to the current table
+        let stale = catalog.load_table(&"db.t".parse().unwrap()).unwrap();
+        let _ = table.new_append().commit(catalog.as_ref()).unwrap();
+        let mut writer = PartitionedWriter::for_table(&stale).unwrap();
+        writer.write(&ids_batch(&[4])).unwrap();
+        let table = stale
+            .new_append()
+            .add_files(writer.close().unwrap())
+            .commit(catalog.as_ref())
+            .unwrap();
+        assert_eq!(4, table.metadata().last_sequence_number);
+        assert_eq!(3, table.scan().plan_files().unwrap().tasks().len());
+    }
+
+    #[test]
+    fn test_conflicting_append_deletes_its_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let mut metadata = table.metadata().clone();
+        metadata.properties = Some(HashMap::from([(
+            COMMIT_NUM_RETRIES_PROPERTY.to_string(),
+            "0".to_string(),
+        )]));
+        let base = catalog.commit_table(&table, metadata).unwrap();
+        append_ids(&catalog, &base, &[1]);
+        let metadata_files = || {
+            std::fs::read_dir(dir.path().join("metadata"))
+                .unwrap()
+                .count()
+        };
+        let before = metadata_files();
+
+        let mut writer = PartitionedWriter::for_table(&base).unwrap();
+        writer.write(&ids_batch(&[2])).unwrap();
+        let error = base
+            .new_append()
+            .add_files(writer.close().unwrap())
+            .commit(&catalog)
+            .unwrap_err();
+        assert!(
+            matches!(error, IcebergError::CommitConflict(_)),
+            "{}",
+            error
+        );
+        // The manifest and manifest list of the append are gone
+        assert_eq!(before, metadata_files());
+    }
+
+    #[test]
+    fn test_commit_timestamps_never_go_backwards() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let created_ms = table.metadata().last_updated_ms;
+
+        catalog.set_time_ms(created_ms + 10);
+        let table = table.new_append().commit(&catalog).unwrap();
+        assert_eq!(created_ms + 10, table.metadata().last_updated_ms);
+
+        // The clock of the committer is behind the last update
+        catalog.set_time_ms(created_ms - 3_600_000);
+        let table = table.new_append().commit(&catalog).unwrap();
+        let metadata = table.metadata();
+        assert_eq!(created_ms + 10, metadata.last_updated_ms);
+        assert_eq!(
+            created_ms + 10,
+            metadata.current_snapshot().unwrap().timestamp_ms
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::metrics::report_commit;
+use crate::iceberg::operations::refs::ManageRefs;
+use crate::iceberg::operations::SnapshotProducer;
+use crate::iceberg::spec::manifest::{DataFile, ManifestStatus};
+use crate::iceberg::spec::manifest_list::FileType;
+use crate::iceberg::spec::snapshot::{Operation, SnapshotV2};
+use crate::iceberg::spec::snapshot_summary::{PUBLISHED_WAP_ID, SOURCE_SNAPSHOT_ID, WAP_ID};
+use crate::iceberg::table::Table;
+
+// Publishes a snapshot that isn't an ancestor of the current one, e.g. a staged snapshot of a
+// write-audit-publish workflow. The current snapshot is fast-forwarded to the snapshot when it
+// is its parent. Otherwise the files the snapshot appended are committed again on top of the
+// current snapshot, which only works for appends. A write id can only be published once
+pub struct CherryPick<'a> {
+    table: &'a Table,
+    target: CherryPickTarget,
+}
+
+#[derive(Debug, Clone)]
+enum CherryPickTarget {
+    SnapshotId(i64),
+    // Latest snapshot staged with the wap.id
+    WapId(String),
+}
+
+impl<'a> CherryPick<'a> {
+    pub(crate) fn snapshot(table: &'a Table, snapshot_id: i64) -> Self {
+        CherryPick {
+            table,
+            target: CherryPickTarget::SnapshotId(snapshot_id),
+        }
+    }
+
+    pub(crate) fn wap_id(table: &'a Table, wap_id: &str) -> Self {
+        CherryPick {
+            table,
+            target: CherryPickTarget::WapId(wap_id.to_string()),
+        }
+    }
+
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<Table> {
+        let metadata = self.table.metadata();
+        let snapshot = match &self.target {
+            CherryPickTarget::SnapshotId(snapshot_id) => {
+                metadata.snapshot_by_id(*snapshot_id).ok_or_else(|| {
+                    IcebergError::NotFound(format!(
+                        "Snapshot {} of table {}",
+                        snapshot_id,
+                        self.table.ident()
+                    ))
+                })?
+            }
+            CherryPickTarget::WapId(wap_id) => self.table.staged_snapshot(wap_id)?,
+        };
+        let current = metadata.current_snapshot();
+        let ancestors = std::iter::successors(current, |snapshot| {
+            snapshot
+                .parent_snapshot_id
+                .and_then(|parent| metadata.snapshot_by_id(parent))
+        });
+        let wap_id = snapshot.summary.rest.get(WAP_ID);
+        for ancestor in ancestors {
+            if ancestor.snapshot_id == snapshot.snapshot_id {
+                return Err(IcebergError::Invalid(format!(
+                    "Snapshot {} is already an ancestor of the current snapshot",
+                    snapshot.snapshot_id
+                )));
+            }
+            let published = [WAP_ID, PUBLISHED_WAP_ID]
+                .iter()
+                .any(|key| wap_id.is_some() && ancestor.summary.rest.get(*key) == wap_id);
+            if published {
+                return Err(IcebergError::Invalid(format!(
+                    "Write {} was already published by snapshot {}",
+                    wap_id.map_or("", String::as_str),
+                    ancestor.snapshot_id
+                )));
+            }
+        }
+
+        if snapshot.parent_snapshot_id == current.map(|current| current.snapshot_id) {
+            return ManageRefs::new(self.table)
+                .set_current_snapshot(snapshot.snapshot_id)
+                .commit(catalog);
+        }
+        if snapshot.summary.operation != Operation::Append {
+            return Err(IcebergError::Unsupported(format!(
+                "Cherry-picking {:?} snapshot {}, only appends can be cherry-picked onto a \
+                 snapshot other than their parent",
+                snapshot.summary.operation, snapshot.snapshot_id
+            )));
+        }
+        self.commit_appended_files(catalog, snapshot)
+    }
+
+    fn commit_appended_files(
+        &self,
+        catalog: &dyn IcebergCatalog,
+        snapshot: &SnapshotV2,
+    ) -> Result<Table> {
+        let start = Instant::now();
+        let mut summary = HashMap::from([(
+            SOURCE_SNAPSHOT_ID.to_string(),
+            snapshot.snapshot_id.to_string(),
+        )]);
+        if let Some(wap_id) = snapshot.summary.rest.get(WAP_ID) {
+            summary.insert(PUBLISHED_WAP_ID.to_string(), wap_id.clone());
+        }
+        let mut producer = SnapshotProducer::new(self.table);
+        producer.set_summary(summary);
+        let mut manifests = vec![];
+        for manifest in self.table.manifests(snapshot)? {
+            if manifest.content != FileType::Data
+                || manifest.added_snapshot_id != snapshot.snapshot_id
+            {
+                continue;
+            }
+            let spec = self
+                .table
+                .metadata()
+                .partition_spec_by_id(manifest.partition_spec_id)
+                .ok_or_else(|| {
+                    IcebergError::Invalid(format!(
+                        "Partition spec {} of manifest {} is missing from table metadata",
+                        manifest.partition_spec_id, manifest.manifest_path
+                    ))
+                })?;
+            let files: Vec<DataFile> = self
+                .table
+                .manifest_entries(&manifest)?
+                .into_iter()
+                .filter(|entry| {
+                    entry.status == ManifestStatus::Added
+                        && entry.snapshot_id == Some(snapshot.snapshot_id)
+                })
+                .map(|entry| entry.data_file)
+                .collect();
+            if !files.is_empty() {
+                manifests.push(producer.add_files(spec, files)?);
+            }
+        }
+        manifests.extend(producer.current_manifests()?);
+        let table = producer.commit(catalog, Operation::Append, &manifests)?;
+        report_commit(self.table, &table, 1, start);
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::test_utils::{append_ids, ids_batch, TestCatalog};
+    use crate::iceberg::writer::partitioned::PartitionedWriter;
+
+    fn stage_ids(catalog: &TestCatalog, table: &Table, ids: &[i64], wap_id: &str) -> Table {
+        let mut writer = PartitionedWriter::for_table(table).unwrap();
+        writer.write(&ids_batch(ids)).unwrap();
+        table
+            .new_append()
+            .add_files(writer.close().unwrap())
+            .stage(wap_id)
+            .commit(catalog)
+            .unwrap()
+    }
+
+    fn record_count(table: &Table) -> i64 {
+        let plan = table.scan().plan_files().unwrap();
+        plan.tasks().iter().map(|t| t.data_file.record_count).sum()
+    }
+
+    #[test]
+    fn test_stage_and_publish() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1]);
+        let current = table.metadata().current_snapshot_id;
+
+        let table = stage_ids(&catalog, &table, &[2, 3], "audit-1");
+        assert_eq!(current, table.metadata().current_snapshot_id);
+        assert_eq!(1, table.metadata().snapshot_log.as_ref().unwrap().len());
+        assert_eq!(1, record_count(&table));
+        let staged = table.staged_snapshot("audit-1").unwrap().snapshot_id;
+        let plan = table.scan().with_snapshot_id(staged).plan_files().unwrap();
+        assert_eq!(2, plan.tasks().len());
+
+        // The staged snapshot's parent is current: the table is fast-forwarded to it
+        let table = table.publish_wap_id("audit-1").commit(&catalog).unwrap();
+        assert_eq!(Some(staged), table.metadata().current_snapshot_id);
+        assert_eq!(3, record_count(&table));
+        assert!(table.publish_wap_id("audit-1").commit(&catalog).is_err());
+        assert!(table.publish_wap_id("missing").commit(&catalog).is_err());
+    }
+
+    #[test]
+    fn test_cherry_pick_onto_new_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1]);
+        let table = stage_ids(&catalog, &table, &[2, 3], "audit-1");
+        let staged = table.staged_snapshot("audit-1").unwrap().snapshot_id;
+        let table = append_ids(&catalog, &table, &[4]);
+
+        let table = table.cherry_pick(staged).commit(&catalog).unwrap();
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        assert_ne!(staged, snapshot.snapshot_id);
+        assert_eq!(
+            Some(&staged.to_string()),
+            snapshot.summary.rest.get(SOURCE_SNAPSHOT_ID)
+        );
+        assert_eq!(
+            Some(&"audit-1".to_string()),
+            snapshot.summary.rest.get(PUBLISHED_WAP_ID)
+        );
+        assert_eq!("4", snapshot.summary.rest["total-records"]);
+        assert_eq!(4, record_count(&table));
+
+        let error = table
+            .publish_wap_id("audit-1")
+            .commit(&catalog)
+            .unwrap_err();
+        assert!(error.to_string().contains("already published"));
+    }
+}
@@ -0,0 +1,425 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::iceberg::audit::{AuditRecord, AuditedOperation, Auditor};
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::Result;
+use crate::iceberg::operations::{commit_time_ms, commit_with_retries};
+use crate::iceberg::properties::TableProperties;
+use crate::iceberg::spec::snapshot::{RefType, SnapshotRefV2, SnapshotV2};
+use crate::iceberg::spec::table_metadata::{TableMetadataV2, MAIN_BRANCH};
+use crate::iceberg::table::Table;
+
+// Removes old snapshots from the table metadata. Every branch keeps its head, its latest
+// `retain_last` ancestors and the ancestors not older than `older_than_ms`, unless the branch
+// overrides these with min-snapshots-to-keep and max-snapshot-age-ms. Tagged snapshots are kept,
+// snapshots not reachable from any ref only if not older than `older_than_ms`. Refs other than
+// main are removed once older than their max-ref-age-ms
+pub struct ExpireSnapshots<'a> {
+    table: &'a Table,
+    older_than_ms: i64,
+    retain_last: usize,
+    auditor: Option<Arc<dyn Auditor>>,
+}
+
+#[derive(Debug)]
+pub struct ExpireSnapshotsResult {
+    pub table: Table,
+    pub expired_snapshot_ids: Vec<i64>,
+    pub removed_refs: Vec<String>,
+    // Manifest lists, manifests and data files only referenced by expired snapshots. They can
+    // be deleted once no reader uses the expired snapshots anymore
+    pub unreachable_files: Vec<String>,
+}
+
+impl<'a> ExpireSnapshots<'a> {
+    pub(crate) fn new(table: &'a Table, older_than_ms: i64, retain_last: usize) -> Self {
+        ExpireSnapshots {
+            table,
+            older_than_ms,
+            retain_last: retain_last.max(1),
+            auditor: None,
+        }
+    }
+
+    // Records the expiration with the auditor once committed
+    pub fn with_auditor(mut self, auditor: Arc<dyn Auditor>) -> Self {
+        self.auditor = Some(auditor);
+        self
+    }
+
+    // Commits the expiration, computed again on top of the current table after concurrent commits
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<ExpireSnapshotsResult> {
+        let mut expiration = None;
+        let table = commit_with_retries(catalog, self.table, |table| {
+            let (metadata, expired) =
+                self.expire(table, commit_time_ms(catalog, table.metadata())?)?;
+            expiration = Some(expired);
+            match metadata {
+                Some(metadata) => catalog.commit_table(table, metadata),
+                None => Ok(table.clone()),
+            }
+        })?;
+        let Expiration {
+            expired_snapshot_ids,
+            removed_refs,
+            unreachable_files,
+        } = expiration.expect("the expiration is computed before committing");
+
+        if let Some(auditor) = self
+            .auditor
+            .as_ref()
+            .filter(|_| !expired_snapshot_ids.is_empty() || !removed_refs.is_empty())
+        {
+            auditor.record(&[AuditRecord::new(
+                AuditedOperation::ExpireSnapshots,
+                table.ident(),
+            )
+            .with_details(&format!(
+                "expired snapshots {:?}, removed refs {:?}, {} unreachable files",
+                expired_snapshot_ids,
+                removed_refs,
+                unreachable_files.len()
+            ))])?;
+        }
+        Ok(ExpireSnapshotsResult {
+            table,
+            expired_snapshot_ids,
+            removed_refs,
+            unreachable_files,
+        })
+    }
+
+    // Metadata of the table without the expired snapshots and refs, None if nothing expired
+    fn expire(&self, table: &Table, now: i64) -> Result<(Option<TableMetadataV2>, Expiration)> {
+        let base = table.metadata();
+        let snapshots: HashMap<i64, &SnapshotV2> = base
+            .snapshots
+            .iter()
+            .flatten()
+            .map(|snapshot| (snapshot.snapshot_id, snapshot))
+            .collect();
+
+        let mut refs = base.refs.clone().unwrap_or_default();
+        if let Some(current) = base.current_snapshot() {
+            refs.entry(MAIN_BRANCH.to_string())
+                .or_insert_with(|| SnapshotRefV2 {
+                    snapshot_id: current.snapshot_id,
+                    ref_type: RefType::Branch {
+                        min_snapshots_to_keep: None,
+                        max_snapshot_age_ms: None,
+                    },
+                    max_ref_age_ms: None,
+                });
+        }
+        let default_max_ref_age_ms = TableProperties::from_metadata(base).max_ref_age_ms()?;
+        let mut removed_refs = vec![];
+        refs.retain(|name, snapshot_ref| {
+            let max_age = snapshot_ref.max_ref_age_ms.or(default_max_ref_age_ms);
+            let expired = name != MAIN_BRANCH
+                && match (snapshots.get(&snapshot_ref.snapshot_id), max_age) {
+                    (None, _) => true,
+                    (Some(snapshot), Some(max_age)) => now - snapshot.timestamp_ms > max_age,
+                    (Some(_), None) => false,
+                };
+            if expired {
+                removed_refs.push(name.clone());
+            }
+            !expired
+        });
+        removed_refs.sort();
+
+        let ancestors = |snapshot_id: i64| {
+            std::iter::successors(snapshots.get(&snapshot_id).copied(), |snapshot| {
+                snapshot
+                    .parent_snapshot_id
+                    .and_then(|parent| snapshots.get(&parent).copied())
+            })
+        };
+        let mut retained = HashSet::new();
+        let mut referenced = HashSet::new();
+        for snapshot_ref in refs.values() {
+            referenced.extend(ancestors(snapshot_ref.snapshot_id).map(|s| s.snapshot_id));
+            match &snapshot_ref.ref_type {
+                RefType::Tag => {
+                    retained.insert(snapshot_ref.snapshot_id);
+                }
+                RefType::Branch {
+                    min_snapshots_to_keep,
+                    max_snapshot_age_ms,
+                } => {
+                    let min_to_keep =
+                        min_snapshots_to_keep.map_or(self.retain_last, |min| min.max(1) as usize);
+                    let oldest = max_snapshot_age_ms.map_or(self.older_than_ms, |age| now - age);
+                    for (index, snapshot) in ancestors(snapshot_ref.snapshot_id).enumerate() {
+                        if index >= min_to_keep && snapshot.timestamp_ms < oldest {
+                            break;
+                        }
+                        retained.insert(snapshot.snapshot_id);
+                    }
+                }
+            }
+        }
+        for snapshot in snapshots.values() {
+            if !referenced.contains(&snapshot.snapshot_id)
+                && snapshot.timestamp_ms >= self.older_than_ms
+            {
+                retained.insert(snapshot.snapshot_id);
+            }
+        }
+
+        let mut expired_snapshot_ids: Vec<i64> = snapshots
+            .keys()
+            .filter(|id| !retained.contains(id))
+            .copied()
+            .collect();
+        expired_snapshot_ids.sort();
+        if expired_snapshot_ids.is_empty() && removed_refs.is_empty() {
+            return Ok((
+                None,
+                Expiration {
+                    expired_snapshot_ids,
+                    removed_refs,
+                    unreachable_files: vec![],
+                },
+            ));
+        }
+
+        let mut unreachable_files = unreachable_files(table, &snapshots, &retained)?;
+        let mut metadata: TableMetadataV2 = base.clone();
+        metadata.last_updated_ms = now;
+        if let Some(snapshots) = metadata.snapshots.as_mut() {
+            snapshots.retain(|snapshot| retained.contains(&snapshot.snapshot_id));
+        }
+        if let Some(statistics) = metadata.statistics.as_mut() {
+            statistics.retain(|file| {
+                let keep = retained.contains(&file.snapshot_id);
+                if !keep {
+                    unreachable_files.push(file.statistics_path.clone());
+                }
+                keep
+            });
+        }
+        if let Some(statistics) = metadata.partition_statistics.as_mut() {
+            statistics.retain(|file| {
+                let keep = retained.contains(&file.snapshot_id);
+                if !keep {
+                    unreachable_files.push(file.statistics_path.clone());
+                }
+                keep
+            });
+        }
+        if let Some(log) = metadata.snapshot_log.as_mut() {
+            log.retain(|entry| retained.contains(&entry.snapshot_id));
+        }
+        metadata.refs = Some(refs);
+        Ok((
+            Some(metadata),
+            Expiration {
+                expired_snapshot_ids,
+                removed_refs,
+                unreachable_files,
+            },
+        ))
+    }
+}
+
+// Snapshots and refs removed by an expiration and the files only they referenced
+struct Expiration {
+    expired_snapshot_ids: Vec<i64>,
+    removed_refs: Vec<String>,
+    unreachable_files: Vec<String>,
+}
+
+fn unreachable_files(
+    table: &Table,
+    snapshots: &HashMap<i64, &SnapshotV2>,
+    retained: &HashSet<i64>,
+) -> Result<Vec<String>> {
+    let mut retained_manifests = HashMap::new();
+    let mut expired_manifests = HashMap::new();
+    let mut unreachable = vec![];
+    for (id, snapshot) in snapshots {
+        let manifests = if retained.contains(id) {
+            &mut retained_manifests
+        } else {
+            unreachable.push(snapshot.manifest_list.clone());
+            &mut expired_manifests
+        };
+        for manifest in table.manifests(snapshot)? {
+            manifests.insert(manifest.manifest_path.clone(), manifest);
+        }
+    }
+    expired_manifests.retain(|path, _| !retained_manifests.contains_key(path));
+
+    // Data files can only become unreachable when manifests do
+    if !expired_manifests.is_empty() {
+        let mut reachable_files = HashSet::new();
+        for manifest in retained_manifests.values() {
+            for entry in table.manifest_entries(manifest)? {
+                reachable_files.insert(entry.data_file.file_path);
+            }
+        }
+        let mut unreachable_files = HashSet::new();
+        for manifest in expired_manifests.values() {
+            for entry in table.manifest_entries(manifest)? {
+                if !reachable_files.contains(&entry.data_file.file_path) {
+                    unreachable_files.insert(entry.data_file.file_path);
+                }
+            }
+        }
+        unreachable.extend(unreachable_files);
+        unreachable.extend(expired_manifests.into_keys());
+    }
+    unreachable.sort();
+    Ok(unreachable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+
+    fn snapshot_ids(table: &Table) -> Vec<i64> {
+        table
+            .metadata()
+            .snapshots
+            .iter()
+            .flatten()
+            .map(|snapshot| snapshot.snapshot_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_expire_keeps_last_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let mut table = catalog.create_table("db", "t", dir.path());
+        for ids in [[1], [2], [3]] {
+            table = append_ids(&catalog, &table, &ids);
+        }
+        let all = snapshot_ids(&table);
+
+        let result = table
+            .expire_snapshots(i64::MAX, 2)
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(vec![all[0]], result.expired_snapshot_ids);
+        assert_eq!(all[1..].to_vec(), snapshot_ids(&result.table));
+        // Fast appends keep manifests, so only the manifest list became unreachable
+        let manifest_list = &table.metadata().snapshots.as_ref().unwrap()[0].manifest_list;
+        assert_eq!(vec![manifest_list.clone()], result.unreachable_files);
+        assert_eq!(
+            catalog
+                .load_table(&"db.t".parse().unwrap())
+                .unwrap()
+                .metadata_location(),
+            result.table.metadata_location()
+        );
+        assert_eq!(3, result.table.scan().plan_files().unwrap().tasks().len());
+
+        // Nothing is older than the given timestamp
+        let result = result
+            .table
+            .expire_snapshots(0, 1)
+            .commit(&catalog)
+            .unwrap();
+        assert!(result.expired_snapshot_ids.is_empty());
+    }
+
+    #[test]
+    fn test_expire_removes_statistics() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1]);
+        let table = table.compute_statistics().commit(&catalog).unwrap();
+        let statistics = table.metadata().statistics.clone().unwrap();
+        let table = append_ids(&catalog, &table, &[2]);
+
+        let result = table
+            .expire_snapshots(i64::MAX, 1)
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(Some(vec![]), result.table.metadata().statistics);
+        assert!(result
+            .unreachable_files
+            .contains(&statistics[0].statistics_path));
+    }
+
+    #[test]
+    fn test_expire_respects_tags_and_ref_age() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let mut table = catalog.create_table("db", "t", dir.path());
+        for ids in [[1], [2], [3]] {
+            table = append_ids(&catalog, &table, &ids);
+        }
+        let all = snapshot_ids(&table);
+
+        // The snapshots were committed an hour ago
+        let mut metadata = table.metadata().clone();
+        for snapshot in metadata.snapshots.iter_mut().flatten() {
+            snapshot.timestamp_ms -= 3_600_000;
+        }
+        for entry in metadata.snapshot_log.iter_mut().flatten() {
+            entry.timestamp_ms -= 3_600_000;
+        }
+        let refs = metadata.refs.get_or_insert_with(HashMap::new);
+        refs.insert(
+            "release".to_string(),
+            SnapshotRefV2 {
+                snapshot_id: all[0],
+                ref_type: RefType::Tag,
+                max_ref_age_ms: None,
+            },
+        );
+        refs.insert(
+            "stale".to_string(),
+            SnapshotRefV2 {
+                snapshot_id: all[1],
+                ref_type: RefType::Tag,
+                max_ref_age_ms: Some(60_000),
+            },
+        );
+        let table = catalog.commit_table(&table, metadata).unwrap();
+
+        let result = table
+            .expire_snapshots(i64::MAX, 1)
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(vec!["stale".to_string()], result.removed_refs);
+        assert_eq!(vec![all[1]], result.expired_snapshot_ids);
+        assert_eq!(vec![all[0], all[2]], snapshot_ids(&result.table));
+        let refs = result.table.metadata().refs.as_ref().unwrap();
+        assert!(refs.contains_key("release") && refs.contains_key(MAIN_BRANCH));
+    }
+
+    #[test]
+    fn test_expire_after_concurrent_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let mut table = catalog.create_table("db", "t", dir.path());
+        for ids in [[1], [2]] {
+            table = append_ids(&catalog, &table, &ids);
+        }
+        // Another writer appends after the table was loaded
+        let appended = append_ids(&catalog, &table, &[3]);
+        let all = snapshot_ids(&appended);
+
+        let result = table
+            .expire_snapshots(i64::MAX, 1)
+            .commit(&catalog)
+            .unwrap();
+        // Expired snapshots are sorted by id
+        let mut expired = all[..2].to_vec();
+        expired.sort();
+        assert_eq!(expired, result.expired_snapshot_ids);
+        assert_eq!(vec![all[2]], snapshot_ids(&result.table));
+        assert_eq!(
+            appended.metadata().current_snapshot_id,
+            result.table.metadata().current_snapshot_id
+        );
+        assert_eq!(3, result.table.scan().plan_files().unwrap().tasks().len());
+    }
+}
@@ -0,0 +1,275 @@
+// Validation of commits deleting or replacing rows against the commits made since the snapshot
+// they were planned from. Files matching the conflict detection filter that concurrent commits
+// added or deleted may hold rows the commit should have seen. Like in other Iceberg
+// implementations, serializable isolation rejects any of them, while snapshot isolation accepts
+// new data files: the commit behaves as if it ran on the snapshot it was planned from
+use std::fmt;
+use std::str::FromStr;
+
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::expr::{InclusiveMetricsEvaluator, Predicate};
+use crate::iceberg::operations::snapshots_after;
+use crate::iceberg::spec::manifest::{DataContentType, ManifestStatus};
+use crate::iceberg::spec::snapshot::{Operation, SnapshotV2};
+use crate::iceberg::table::Table;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IsolationLevel {
+    #[default]
+    Serializable,
+    Snapshot,
+}
+
+impl fmt::Display for IsolationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IsolationLevel::Serializable => f.write_str("serializable"),
+            IsolationLevel::Snapshot => f.write_str("snapshot"),
+        }
+    }
+}
+
+impl FromStr for IsolationLevel {
+    type Err = IcebergError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "serializable" => Ok(IsolationLevel::Serializable),
+            "snapshot" => Ok(IsolationLevel::Snapshot),
+            _ => Err(IcebergError::Invalid(format!(
+                "Unknown isolation level {}",
+                s
+            ))),
+        }
+    }
+}
+
+// Validation set on an operation, disabled unless an isolation level or a filter is given
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ConflictDetection {
+    isolation: Option<IsolationLevel>,
+    filter: Option<Predicate>,
+    from_snapshot_id: Option<i64>,
+}
+
+impl ConflictDetection {
+    pub(crate) fn set_isolation(&mut self, isolation: IsolationLevel) {
+        self.isolation = Some(isolation);
+    }
+
+    pub(crate) fn set_filter(&mut self, filter: Predicate) {
+        self.filter = Some(filter);
+    }
+
+    pub(crate) fn set_from_snapshot(&mut self, snapshot_id: i64) {
+        self.from_snapshot_id = Some(snapshot_id);
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.isolation.is_some() || self.filter.is_some()
+    }
+
+    // Checks the snapshots of `table` committed since the snapshot the operation was planned
+    // from, the current snapshot of `base` unless set
+    pub(crate) fn validate(&self, table: &Table, base: &Table) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let isolation = self.isolation.unwrap_or_default();
+        let from_snapshot_id = self.from_snapshot_id.or_else(|| {
+            base.metadata()
+                .current_snapshot()
+                .map(|snapshot| snapshot.snapshot_id)
+        });
+        if let Some(snapshot_id) = from_snapshot_id {
+            if table.metadata().snapshot_by_id(snapshot_id).is_none() {
+                return Err(IcebergError::NotFound(format!(
+                    "Snapshot {} of table {} to validate from",
+                    snapshot_id,
+                    table.ident()
+                )));
+            }
+        }
+        let filter = self.filter.clone().unwrap_or(Predicate::AlwaysTrue);
+        let schema = table.metadata().current_schema()?;
+        let bound = filter.bind(schema.schema(), true)?;
+        let evaluator = InclusiveMetricsEvaluator::new(&bound).with_field_names(schema);
+
+        for snapshot in snapshots_after(table, from_snapshot_id) {
+            for manifest in table.manifests(snapshot)? {
+                if manifest.added_snapshot_id != snapshot.snapshot_id {
+                    continue;
+                }
+                for entry in table.manifest_entries(&manifest)? {
+                    if entry.snapshot_id != Some(snapshot.snapshot_id) {
+                        continue;
+                    }
+                    let change = match (&entry.status, &entry.data_file.content) {
+                        (ManifestStatus::Added, DataContentType::Data)
+                            if isolation == IsolationLevel::Serializable && adds_data(snapshot) =>
+                        {
+                            "added data file"
+                        }
+                        (ManifestStatus::Added, DataContentType::PositionDeletes)
+                        | (ManifestStatus::Added, DataContentType::EqualityDeletes)
+                            if adds_deletes(snapshot) =>
+                        {
+                            "added delete file"
+                        }
+                        (ManifestStatus::Deleted, DataContentType::Data)
+                            if deletes_data(snapshot) =>
+                        {
+                            "deleted data file"
+                        }
+                        _ => continue,
+                    };
+                    if evaluator.might_match(&entry.data_file)? {
+                        return Err(IcebergError::Invalid(format!(
+                            "Snapshot {} of table {} {} {}, which may hold rows matching {}, \
+                             conflicting with a commit with {} isolation",
+                            snapshot.snapshot_id,
+                            table.ident(),
+                            change,
+                            entry.data_file.file_path,
+                            filter,
+                            isolation
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Operations whose changes may conflict, replace snapshots compact files without changing rows
+fn adds_data(snapshot: &SnapshotV2) -> bool {
+    matches!(
+        snapshot.summary.operation,
+        Operation::Append | Operation::Overwrite
+    )
+}
+
+fn adds_deletes(snapshot: &SnapshotV2) -> bool {
+    matches!(
+        snapshot.summary.operation,
+        Operation::Overwrite | Operation::Delete
+    )
+}
+
+// Replace snapshots count as well, the rows of the files they delete live on in files the commit
+// didn't plan from
+fn deletes_data(snapshot: &SnapshotV2) -> bool {
+    matches!(
+        snapshot.summary.operation,
+        Operation::Overwrite | Operation::Replace | Operation::Delete
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::catalog::IcebergCatalog;
+    use crate::iceberg::spec::manifest::DataFile;
+    use crate::iceberg::spec::values::Literal;
+    use crate::iceberg::test_utils::{append_ids, ids_batch, TestCatalog};
+    use crate::iceberg::writer::partitioned::PartitionedWriter;
+
+    fn write(table: &Table, ids: &[i64]) -> Vec<DataFile> {
+        let mut writer = PartitionedWriter::for_table(table).unwrap();
+        writer.write(&ids_batch(ids)).unwrap();
+        writer.close().unwrap()
+    }
+
+    fn overwrite(
+        catalog: &dyn IcebergCatalog,
+        base: &Table,
+        isolation: IsolationLevel,
+        filter: Predicate,
+    ) -> Result<Table> {
+        base.new_overwrite()
+            .add_files(write(base, &[100]))
+            .with_isolation_level(isolation)
+            .with_conflict_detection_filter(filter)
+            .commit(catalog)
+    }
+
+    #[test]
+    fn test_conflicting_data_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let base = append_ids(&catalog, &table, &[1]);
+        let appended = append_ids(&catalog, &base, &[10]);
+        let small_ids = Predicate::less_than("id", Literal::Long(5));
+
+        // The added file may match under serializable isolation only
+        let error = overwrite(
+            &catalog,
+            &base,
+            IsolationLevel::Serializable,
+            Predicate::AlwaysTrue,
+        )
+        .unwrap_err();
+        assert!(matches!(error, IcebergError::Invalid(_)), "{}", error);
+        assert!(error.to_string().contains("added data file"), "{}", error);
+        let table = overwrite(
+            &catalog,
+            &base,
+            IsolationLevel::Serializable,
+            small_ids.clone(),
+        )
+        .unwrap();
+        let table = overwrite(
+            &catalog,
+            &table,
+            IsolationLevel::Snapshot,
+            Predicate::AlwaysTrue,
+        )
+        .unwrap();
+
+        // The deleted file conflicts under both isolation levels
+        let deleted = appended.scan().plan_files().unwrap().tasks()[0]
+            .data_file
+            .clone();
+        let current = table
+            .new_overwrite()
+            .delete_file(&deleted)
+            .commit(&catalog)
+            .unwrap();
+        for isolation in [IsolationLevel::Serializable, IsolationLevel::Snapshot] {
+            let error = overwrite(&catalog, &table, isolation, Predicate::AlwaysTrue).unwrap_err();
+            assert!(error.to_string().contains("deleted data file"), "{}", error);
+        }
+        // Unless it can't match the filter
+        let filter = Predicate::greater_than("id", Literal::Long(50));
+        overwrite(&catalog, &current, IsolationLevel::Snapshot, filter.clone()).unwrap();
+
+        // Commits already in the table are validated from an older snapshot
+        let base_snapshot_id = base.metadata().current_snapshot_id.unwrap();
+        let table = catalog.load_table(&"db.t".parse().unwrap()).unwrap();
+        let error = table
+            .new_overwrite()
+            .validate_from_snapshot(base_snapshot_id)
+            .with_isolation_level(IsolationLevel::Serializable)
+            .commit(&catalog)
+            .unwrap_err();
+        assert!(matches!(error, IcebergError::Invalid(_)), "{}", error);
+        assert!(table
+            .new_overwrite()
+            .validate_from_snapshot(-1)
+            .with_isolation_level(IsolationLevel::Snapshot)
+            .commit(&catalog)
+            .is_err());
+    }
+
+    #[test]
+    fn test_isolation_level() {
+        assert_eq!(
+            IsolationLevel::Snapshot,
+            "Snapshot".parse::<IsolationLevel>().unwrap()
+        );
+        assert_eq!("serializable", IsolationLevel::default().to_string());
+        assert!("repeatable-read".parse::<IsolationLevel>().is_err());
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use uuid::Uuid;
+
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::metrics::report_commit;
+use crate::iceberg::properties::TableProperties;
+use crate::iceberg::read_only::ensure_writable;
+use crate::iceberg::retry::RetryPolicy;
+use crate::iceberg::spec::manifest::{DataFile, ManifestEntry, ManifestStatus, ManifestWriter};
+use crate::iceberg::spec::manifest_list::{write_manifest_list, FileType, ManifestListV2};
+use crate::iceberg::spec::partition_spec::PartitionSpec;
+use crate::iceberg::spec::snapshot::{Operation, RefType, SnapshotRefV2, SnapshotV2};
+use crate::iceberg::spec::snapshot_summary::SummaryBuilder;
+use crate::iceberg::spec::table_metadata::{SnapshotLog, TableMetadataV2, MAIN_BRANCH};
+use crate::iceberg::table::Table;
+
+pub mod append;
+pub mod cherry_pick;
+pub mod expire;
+pub mod isolation;
+pub mod orphan;
+pub mod overwrite;
+pub mod refs;
+pub mod rewrite_files;
+pub mod rewrite_manifests;
+pub mod row_delta;
+pub mod sort_order;
+pub mod statistics;
+pub mod transaction;
+
+// Writes the manifests and manifest list of a new snapshot of a table and commits it to the
+// main branch. Operations decide which manifests make up the snapshot. The files added and
+// deleted through the producer are counted in the summary of the snapshot
+pub(crate) struct SnapshotProducer<'a> {
+    table: &'a Table,
+    snapshot_id: i64,
+    sequence_number: i64,
+    commit_uuid: Uuid,
+    manifest_count: usize,
+    summary: SummaryBuilder,
+    stage_only: bool,
+    // Manifests written so far, deleted if the commit conflicts
+    written: Vec<String>,
+}
+
+impl<'a> SnapshotProducer<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        SnapshotProducer {
+            table,
+            snapshot_id: new_snapshot_id(),
+            sequence_number: table.metadata().last_sequence_number + 1,
+            commit_uuid: Uuid::new_v4(),
+            manifest_count: 0,
+            summary: SummaryBuilder::new(),
+            stage_only: false,
+            written: vec![],
+        }
+    }
+
+    // Adds the snapshot to the table without making it current. The staged snapshot is only
+    // reachable by id until it is published, see CherryPick
+    pub(crate) fn stage_only(&mut self) {
+        self.stage_only = true;
+    }
+
+    // Properties of the summary of the snapshot other than its metrics
+    pub(crate) fn set_summary(&mut self, properties: HashMap<String, String>) {
+        self.summary.set_all(properties);
+    }
+
+    // Manifests of the current snapshot of the table
+    pub(crate) fn current_manifests(&self) -> Result<Vec<ManifestListV2>> {
+        match self.table.metadata().current_snapshot() {
+            Some(snapshot) => self.table.manifests(snapshot),
+            None => Ok(vec![]),
+        }
+    }
+
+    // Manifests of the current snapshot where the live data files matching `delete` (given the
+    // spec id of their manifest) are marked as deleted. Manifests without matching files are kept
+    // as they are, the others are rewritten. Returns the manifests along with the deleted files
+    pub(crate) fn delete_data_files(
+        &mut self,
+        delete: impl Fn(i32, &DataFile) -> bool,
+    ) -> Result<(Vec<ManifestListV2>, Vec<DataFile>)> {
+        let mut manifests = vec![];
+        let mut deleted = vec![];
+        for manifest in self.current_manifests()? {
+            if manifest.content != FileType::Data {
+                manifests.push(manifest);
+                continue;
+            }
+            let spec_id = manifest.partition_spec_id;
+            let entries: Vec<ManifestEntry> = self
+                .table
+                .manifest_entries(&manifest)?
+                .into_iter()
+                .filter(ManifestEntry::is_live)
+                .collect();
+            if !entries
+                .iter()
+                .any(|entry| delete(spec_id, &entry.data_file))
+            {
+                manifests.push(manifest);
+                continue;
+            }
+            let spec = self
+                .table
+                .metadata()
+                .partition_spec_by_id(spec_id)
+                .ok_or_else(|| {
+                    IcebergError::Invalid(format!(
+                        "Partition spec {} of manifest {} is missing from table metadata",
+                        spec_id, manifest.manifest_path
+                    ))
+                })?;
+            let entries = entries
+                .into_iter()
+                .map(|mut entry| {
+                    if delete(spec_id, &entry.data_file) {
+                        self.summary.removed_file(spec, &entry.data_file);
+                        deleted.push(entry.data_file.clone());
+                        entry.status = ManifestStatus::Deleted;
+                        entry.snapshot_id = Some(self.snapshot_id);
+                    } else {
+                        entry.status = ManifestStatus::Existing;
+                    }
+                    entry
+                })
+                .collect();
+            manifests.push(self.write_manifest(spec, entries)?);
+        }
+        Ok((manifests, deleted))
+    }
+
+    // Writes a manifest of the new snapshot holding the given entries
+    pub(crate) fn write_manifest(
+        &mut self,
+        spec: &PartitionSpec,
+        entries: Vec<ManifestEntry>,
+    ) -> Result<ManifestListV2> {
+        let metadata = self.table.metadata();
+        let location = format!(
+            "{}/metadata/{}-m{}.avro",
+            metadata.location.trim_end_matches('/'),
+            self.commit_uuid,
+            self.manifest_count
+        );
+        self.manifest_count += 1;
+        let schema = metadata.current_schema()?;
+        let mut writer = ManifestWriter::new(
+            location.clone(),
+            self.snapshot_id,
+            self.sequence_number,
+            schema,
+            spec,
+        );
+        for entry in entries {
+            writer.add_entry(entry);
+        }
+        let (data, manifest) = writer.finish()?;
+        self.table.file_io().write(&location, Bytes::from(data))?;
+        self.written.push(location);
+        Ok(manifest)
+    }
+
+    // Writes a manifest of the new snapshot with files added by it, written with the given spec
+    pub(crate) fn add_files(
+        &mut self,
+        spec: &PartitionSpec,
+        files: Vec<DataFile>,
+    ) -> Result<ManifestListV2> {
+        for file in &files {
+            self.summary.added_file(spec, file);
+        }
+        let entries = files.into_iter().map(ManifestEntry::added).collect();
+        self.write_manifest(spec, entries)
+    }
+
+    // Commits a snapshot made of a new manifest with the added data files, partitioned by the
+    // default spec, and the `current` manifests returned by delete_data_files
+    pub(crate) fn commit_file_changes(
+        mut self,
+        catalog: &dyn IcebergCatalog,
+        operation: Operation,
+        added: Vec<DataFile>,
+        current: Vec<ManifestListV2>,
+        summary: HashMap<String, String>,
+    ) -> Result<Table> {
+        self.set_summary(summary);
+        let mut manifests = vec![];
+        if !added.is_empty() {
+            let spec = self.table.metadata().default_partition_spec()?;
+            manifests.push(self.add_files(spec, added)?);
+        }
+        manifests.extend(current);
+        self.commit(catalog, operation, &manifests)
+    }
+
+    // Writes the manifest list and commits the snapshot through the catalog. The files of the
+    // snapshot are deleted when the commit conflicts, the next attempt writes its own
+    pub(crate) fn commit(
+        mut self,
+        catalog: &dyn IcebergCatalog,
+        operation: Operation,
+        manifests: &[ManifestListV2],
+    ) -> Result<Table> {
+        let base = self.table.metadata();
+        let parent = base.current_snapshot();
+        let parent_snapshot_id = parent.map(|snapshot| snapshot.snapshot_id);
+        let summary = self
+            .summary
+            .build(operation, parent.map(|snapshot| &snapshot.summary));
+        let manifest_list = format!(
+            "{}/metadata/snap-{}-1-{}.avro",
+            base.location.trim_end_matches('/'),
+            self.snapshot_id,
+            self.commit_uuid
+        );
+        let data = write_manifest_list(
+            manifests,
+            self.snapshot_id,
+            parent_snapshot_id,
+            self.sequence_number,
+        )?;
+        self.table
+            .file_io()
+            .write(&manifest_list, Bytes::from(data))?;
+        self.written.push(manifest_list.clone());
+
+        let timestamp_ms = commit_time_ms(catalog, base)?;
+        let mut metadata = base.clone();
+        metadata.last_sequence_number = self.sequence_number;
+        metadata.last_updated_ms = timestamp_ms;
+        metadata
+            .snapshots
+            .get_or_insert_with(Vec::new)
+            .push(SnapshotV2 {
+                snapshot_id: self.snapshot_id,
+                parent_snapshot_id,
+                sequence_number: self.sequence_number,
+                timestamp_ms,
+                summary,
+                manifest_list,
+                schema_id: Some(base.current_schema_id),
+                first_row_id: None,
+                added_rows: None,
+            });
+        if self.stage_only {
+            return self.commit_metadata(catalog, metadata);
+        }
+        metadata.current_snapshot_id = Some(self.snapshot_id);
+        metadata
+            .snapshot_log
+            .get_or_insert_with(Vec::new)
+            .push(SnapshotLog {
+                snapshot_id: self.snapshot_id,
+                timestamp_ms,
+            });
+        let refs = metadata.refs.get_or_insert_with(HashMap::new);
+        let main = refs
+            .entry(MAIN_BRANCH.to_string())
+            .or_insert_with(|| SnapshotRefV2 {
+                snapshot_id: self.snapshot_id,
+                ref_type: RefType::Branch {
+                    min_snapshots_to_keep: None,
+                    max_snapshot_age_ms: None,
+                },
+                max_ref_age_ms: None,
+            });
+        main.snapshot_id = self.snapshot_id;
+        self.commit_metadata(catalog, metadata)
+    }
+
+    fn commit_metadata(
+        &self,
+        catalog: &dyn IcebergCatalog,
+        metadata: TableMetadataV2,
+    ) -> Result<Table> {
+        let result = catalog.commit_table(self.table, metadata);
+        // Other failures may happen after the commit succeeded, the files are kept then
+        if let Err(IcebergError::CommitConflict(_)) = &result {
+            for location in &self.written {
+                if let Err(e) = self.table.file_io().delete(location) {
+                    log::warn!("Couldn't delete uncommitted file {}: {}", location, e);
+                }
+            }
+        }
+        result
+    }
+}
+
+// Commits changes to `table` with `commit`, called with the table to apply them to. When the
+// commit conflicts with a concurrent one, the table is refreshed and the changes applied again on
+// top of it, as set by the commit.retry table properties. `commit` must check that its changes
+// still apply to the refreshed table, e.g. that the files it deletes weren't deleted since. The
+// snapshot committed, if any, is reported to the metrics reporter of `table`
+#[tracing::instrument(level = "debug", skip_all, fields(table = %table.ident(), attempts))]
+pub(crate) fn commit_with_retries(
+    catalog: &dyn IcebergCatalog,
+    table: &Table,
+    mut commit: impl FnMut(&Table) -> Result<Table>,
+) -> Result<Table> {
+    let start = Instant::now();
+    let policy = commit_retry_policy(table.metadata())?;
+    let mut attempt = 0;
+    let committed = policy.run_with(
+        &format!("commit to table {}", table.ident()),
+        |e| matches!(e, IcebergError::CommitConflict(_)),
+        || {
+            attempt += 1;
+            if attempt == 1 {
+                return commit(table);
+            }
+            commit(&catalog.refresh_table(table)?)
+        },
+    )?;
+    tracing::Span::current().record("attempts", attempt);
+    report_commit(table, &committed, attempt, start);
+    Ok(committed)
+}
+
+fn commit_retry_policy(metadata: &TableMetadataV2) -> Result<RetryPolicy> {
+    let properties = TableProperties::from_metadata(metadata);
+    let min_wait_ms = properties.commit_min_retry_wait_ms()?;
+    let max_wait_ms = properties.commit_max_retry_wait_ms()?;
+    let policy = RetryPolicy::new()
+        .with_max_attempts(properties.commit_num_retries()?.saturating_add(1))
+        .with_initial_backoff(Duration::from_millis(min_wait_ms))
+        .with_max_backoff(Duration::from_millis(max_wait_ms.max(min_wait_ms)));
+    Ok(policy)
+}
+
+// Files are added with the default spec of the base table, which must still be the default when
+// the commit is applied again to a refreshed table
+pub(crate) fn check_default_spec(table: &Table, spec: &PartitionSpec) -> Result<()> {
+    if table.metadata().default_partition_spec()? != spec {
+        return Err(IcebergError::Invalid(format!(
+            "Default partition spec of table {} changed since it was loaded",
+            table.ident()
+        )));
+    }
+    Ok(())
+}
+
+// Snapshots committed to `table` since `base` was loaded, newest first. They are the ancestors of
+// the current snapshot of `table` down to that of `base`
+pub(crate) fn snapshots_since<'t>(table: &'t Table, base: &Table) -> Vec<&'t SnapshotV2> {
+    let base_snapshot_id = base
+        .metadata()
+        .current_snapshot()
+        .map(|snapshot| snapshot.snapshot_id);
+    snapshots_after(table, base_snapshot_id)
+}
+
+// Ancestors of the current snapshot of `table` down to the given one, excluded, newest first. All
+// of them when the given snapshot isn't an ancestor
+pub(crate) fn snapshots_after(table: &Table, snapshot_id: Option<i64>) -> Vec<&SnapshotV2> {
+    let metadata = table.metadata();
+    let mut snapshots = vec![];
+    let mut snapshot = metadata.current_snapshot();
+    while let Some(current) = snapshot {
+        if Some(current.snapshot_id) == snapshot_id {
+            break;
+        }
+        snapshots.push(current);
+        snapshot = current
+            .parent_snapshot_id
+            .and_then(|id| metadata.snapshot_by_id(id));
+    }
+    snapshots
+}
+
+// Whether any of the snapshots counts a metric of its summary, e.g. deleted-data-files
+pub(crate) fn any_counted(snapshots: &[&SnapshotV2], metric: &str) -> bool {
+    snapshots.iter().any(|snapshot| {
+        snapshot
+            .summary
+            .rest
+            .get(metric)
+            .and_then(|count| count.parse::<i64>().ok())
+            .is_some_and(|count| count > 0)
+    })
+}
+
+// Random positive snapshot id
+pub(crate) fn new_snapshot_id() -> i64 {
+    let (high, low) = Uuid::new_v4().as_u64_pair();
+    ((high ^ low) & i64::MAX as u64) as i64
+}
+
+// Skew between the clock of the committer and the timestamp of the base metadata above which
+// commits log a warning
+const CLOCK_SKEW_WARNING_MS: i64 = 60_000;
+
+// Timestamp of a commit on top of `base`, taken from the clock of the catalog. Timestamps never
+// go backwards: when the clock is behind the last update of the table (e.g. the previous commit
+// came from a writer whose clock is ahead), the commit gets the timestamp of the last update
+pub(crate) fn commit_time_ms(catalog: &dyn IcebergCatalog, base: &TableMetadataV2) -> Result<i64> {
+    ensure_writable(&format!("commit to table {}", base.location))?;
+    let now = catalog.current_time_ms()?;
+    let skew = base.last_updated_ms - now;
+    if skew > CLOCK_SKEW_WARNING_MS {
+        log::warn!(
+            "Table {} was last updated {} ms in the future of the local clock, committing with \
+             its last-updated-ms instead",
+            base.location,
+            skew
+        );
+    }
+    Ok(now.max(base.last_updated_ms))
+}
+
+pub(crate) fn current_time_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as i64)
+}
@@ -0,0 +1,202 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::iceberg::audit::{AuditRecord, AuditedOperation, Auditor};
+use crate::iceberg::error::Result;
+use crate::iceberg::read_only::ensure_writable;
+use crate::iceberg::table::Table;
+
+// Hint of the current version written next to metadata files by filesystem catalogs
+const VERSION_HINT_FILE: &str = "version-hint.text";
+
+// Finds the files below the table location that aren't referenced by the table metadata: data
+// and delete files, manifests and manifest lists of none of the snapshots, and metadata files
+// that are neither current nor in the metadata log. Such files are left behind by failed writes
+// and by snapshot expiration. Only files last modified before `older_than_ms` are considered, so
+// that files of writes still in progress are kept
+pub struct DeleteOrphanFiles<'a> {
+    table: &'a Table,
+    older_than_ms: i64,
+    location: Option<String>,
+    dry_run: bool,
+    auditor: Option<Arc<dyn Auditor>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteOrphanFilesResult {
+    // Deleted files, respectively the files that would be deleted for dry runs
+    pub orphan_files: Vec<String>,
+}
+
+impl<'a> DeleteOrphanFiles<'a> {
+    pub(crate) fn new(table: &'a Table, older_than_ms: i64) -> Self {
+        DeleteOrphanFiles {
+            table,
+            older_than_ms,
+            location: None,
+            dry_run: false,
+            auditor: None,
+        }
+    }
+
+    // Only look for orphan files below the given location instead of the table location
+    pub fn location(mut self, location: &str) -> Self {
+        self.location = Some(location.to_string());
+        self
+    }
+
+    // Report the orphan files without deleting them
+    pub fn dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    // Records the deleted files with the auditor
+    pub fn with_auditor(mut self, auditor: Arc<dyn Auditor>) -> Self {
+        self.auditor = Some(auditor);
+        self
+    }
+
+    pub fn execute(self) -> Result<DeleteOrphanFilesResult> {
+        let reachable = self.reachable_files()?;
+        let location = self
+            .location
+            .as_deref()
+            .unwrap_or(&self.table.metadata().location);
+        let file_io = self.table.file_io();
+        let orphan_files: Vec<String> = file_io
+            .list(location)?
+            .into_iter()
+            .filter(|file| {
+                file.last_modified_ms < self.older_than_ms
+                    && !file.location.ends_with(VERSION_HINT_FILE)
+                    && !reachable.contains(normalize(&file.location))
+            })
+            .map(|file| file.location)
+            .collect();
+        if self.dry_run || orphan_files.is_empty() {
+            return Ok(DeleteOrphanFilesResult { orphan_files });
+        }
+
+        ensure_writable(&format!("delete orphan files below {}", location))?;
+        for file in &orphan_files {
+            file_io.delete(file)?;
+        }
+        if let Some(auditor) = &self.auditor {
+            auditor.record(&[AuditRecord::new(
+                AuditedOperation::DeleteOrphanFiles,
+                self.table.ident(),
+            )
+            .with_snapshot_id(self.table.metadata().current_snapshot_id)
+            .with_details(&format!(
+                "deleted {} orphan files below {}",
+                orphan_files.len(),
+                location
+            ))])?;
+        }
+        Ok(DeleteOrphanFilesResult { orphan_files })
+    }
+
+    // Files referenced by the metadata of the table, normalized
+    fn reachable_files(&self) -> Result<HashSet<String>> {
+        let metadata = self.table.metadata();
+        let mut reachable = HashSet::new();
+        reachable.insert(normalize(self.table.metadata_location()).to_string());
+        for entry in metadata.metadata_log.iter().flatten() {
+            reachable.insert(normalize(&entry.metadata_file).to_string());
+        }
+        for statistics in metadata.statistics.iter().flatten() {
+            reachable.insert(normalize(&statistics.statistics_path).to_string());
+        }
+        for statistics in metadata.partition_statistics.iter().flatten() {
+            reachable.insert(normalize(&statistics.statistics_path).to_string());
+        }
+
+        let mut manifests = HashSet::new();
+        for snapshot in metadata.snapshots.iter().flatten() {
+            reachable.insert(normalize(&snapshot.manifest_list).to_string());
+            for manifest in self.table.manifests(snapshot)? {
+                // Manifests are shared by snapshots, read them once
+                if !manifests.insert(manifest.manifest_path.clone()) {
+                    continue;
+                }
+                reachable.insert(normalize(&manifest.manifest_path).to_string());
+                for entry in self.table.manifest_entries(&manifest)? {
+                    reachable.insert(normalize(&entry.data_file.file_path).to_string());
+                }
+            }
+        }
+        Ok(reachable)
+    }
+}
+
+// Path of a location without its scheme, so that "file:/a" and "file:///a" match
+fn normalize(location: &str) -> &str {
+    let path = match location.split_once(':') {
+        Some((scheme, path)) if !scheme.contains('/') => path,
+        _ => location,
+    };
+    path.trim_start_matches('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!("a/b", normalize("file:/a/b"));
+        assert_eq!("a/b", normalize("file:///a/b"));
+        assert_eq!("bucket/a", normalize("s3://bucket/a"));
+        assert_eq!("a/b", normalize("/a/b"));
+    }
+
+    #[test]
+    fn test_delete_orphan_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1, 2]);
+        let table = append_ids(&catalog, &table, &[3]);
+        // Statistics files are referenced by the metadata
+        let table = table.compute_statistics().commit(&catalog).unwrap();
+
+        let location = &table.metadata().location;
+        let orphans = vec![
+            format!("{}/data/failed-write.parquet", location),
+            format!("{}/metadata/stale-m0.avro", location),
+        ];
+        for orphan in &orphans {
+            table.file_io().write(orphan, Bytes::from("x")).unwrap();
+        }
+        let before = table.file_io().list(location).unwrap().len();
+
+        // Files newer than the threshold are kept
+        let result = table.delete_orphan_files(0).execute().unwrap();
+        assert!(result.orphan_files.is_empty());
+
+        let result = table
+            .delete_orphan_files(i64::MAX)
+            .dry_run()
+            .execute()
+            .unwrap();
+        assert_eq!(orphans, result.orphan_files);
+        assert_eq!(before, table.file_io().list(location).unwrap().len());
+
+        let result = table.delete_orphan_files(i64::MAX).execute().unwrap();
+        assert_eq!(orphans, result.orphan_files);
+        assert_eq!(before - 2, table.file_io().list(location).unwrap().len());
+        let rows: usize = table
+            .scan()
+            .plan_files()
+            .unwrap()
+            .to_arrow()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum();
+        assert_eq!(3, rows);
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::expr::Predicate;
+use crate::iceberg::operations::isolation::{ConflictDetection, IsolationLevel};
+use crate::iceberg::operations::{check_default_spec, commit_with_retries, SnapshotProducer};
+use crate::iceberg::spec::manifest::{DataContentType, DataFile};
+use crate::iceberg::spec::partition_spec::PartitionSpec;
+use crate::iceberg::spec::snapshot::Operation;
+use crate::iceberg::spec::snapshot_summary::WAP_ID;
+use crate::iceberg::table::Table;
+
+// Replaces data files of a table: the deleted files are marked as deleted in rewritten
+// manifests and the added files are written to a new manifest. Added files must be partitioned
+// by the default partition spec of the table
+pub struct OverwriteFiles<'a> {
+    table: &'a Table,
+    added: Vec<DataFile>,
+    deleted: HashSet<String>,
+    summary: HashMap<String, String>,
+    stage_only: bool,
+    conflict_detection: ConflictDetection,
+}
+
+// Dynamic partition overwrite: the added files replace all the data files of the partitions
+// they belong to, in the default partition spec. The snapshot is an overwrite flagged with the
+// replace-partitions summary property, like in other Iceberg implementations. Tables without
+// partition fields are replaced as a whole
+pub struct ReplacePartitions<'a> {
+    table: &'a Table,
+    added: Vec<DataFile>,
+    summary: HashMap<String, String>,
+}
+
+impl<'a> OverwriteFiles<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        OverwriteFiles {
+            table,
+            added: vec![],
+            deleted: HashSet::new(),
+            summary: HashMap::new(),
+            stage_only: false,
+            conflict_detection: ConflictDetection::default(),
+        }
+    }
+
+    pub fn add_file(mut self, data_file: DataFile) -> Self {
+        self.added.push(data_file);
+        self
+    }
+
+    pub fn add_files(mut self, data_files: Vec<DataFile>) -> Self {
+        self.added.extend(data_files);
+        self
+    }
+
+    // Removes a data file of the current snapshot, matched by path
+    pub fn delete_file(mut self, data_file: &DataFile) -> Self {
+        self.deleted.insert(data_file.file_path.clone());
+        self
+    }
+
+    // Fails the commit when files matching the conflict detection filter were changed since the
+    // snapshot the operation was planned from, as allowed by the isolation level, see
+    // IsolationLevel. Validation is enabled by setting the level or the filter, which matches
+    // all rows unless given
+    pub fn with_isolation_level(mut self, isolation: IsolationLevel) -> Self {
+        self.conflict_detection.set_isolation(isolation);
+        self
+    }
+
+    pub fn with_conflict_detection_filter(mut self, filter: Predicate) -> Self {
+        self.conflict_detection.set_filter(filter);
+        self
+    }
+
+    // Validates the commits made since the given snapshot instead of the current snapshot of the
+    // table, e.g. the snapshot the rows to change were read from
+    pub fn validate_from_snapshot(mut self, snapshot_id: i64) -> Self {
+        self.conflict_detection.set_from_snapshot(snapshot_id);
+        self
+    }
+
+    // Adds a property to the summary of the new snapshot
+    pub fn set_summary(mut self, key: &str, value: &str) -> Self {
+        self.summary.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    // Commits the snapshot without making it current, with the write id in its wap.id summary
+    // property. The staged snapshot can be audited, e.g. scanned by id, before being published
+    // with Table::publish_wap_id
+    pub fn stage(mut self, wap_id: &str) -> Self {
+        self.summary.insert(WAP_ID.to_string(), wap_id.to_string());
+        self.stage_only = true;
+        self
+    }
+
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<Table> {
+        let spec = self.table.metadata().default_partition_spec()?;
+        check_added_files(spec, &self.added)?;
+
+        // Rebased on a concurrent commit, the overwrite still applies if the files it deletes
+        // weren't deleted by that commit
+        commit_with_retries(catalog, self.table, |table| {
+            check_default_spec(table, spec)?;
+            self.conflict_detection.validate(table, self.table)?;
+            let mut producer = SnapshotProducer::new(table);
+            if self.stage_only {
+                producer.stage_only();
+            }
+            let (current, deleted) = producer
+                .delete_data_files(|_, data_file| self.deleted.contains(&data_file.file_path))?;
+            if let Some(missing) = self
+                .deleted
+                .iter()
+                .find(|path| !deleted.iter().any(|file| &file.file_path == *path))
+            {
+                return Err(IcebergError::NotFound(format!(
+                    "Data file {} in the current snapshot of table {}",
+                    missing,
+                    table.ident()
+                )));
+            }
+            producer.commit_file_changes(
+                catalog,
+                Operation::Overwrite,
+                self.added.clone(),
+                current,
+                self.summary.clone(),
+            )
+        })
+    }
+}
+
+impl<'a> ReplacePartitions<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        ReplacePartitions {
+            table,
+            added: vec![],
+            summary: HashMap::new(),
+        }
+    }
+
+    pub fn add_file(mut self, data_file: DataFile) -> Self {
+        self.added.push(data_file);
+        self
+    }
+
+    pub fn add_files(mut self, data_files: Vec<DataFile>) -> Self {
+        self.added.extend(data_files);
+        self
+    }
+
+    // Adds a property to the summary of the new snapshot
+    pub fn set_summary(mut self, key: &str, value: &str) -> Self {
+        self.summary.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<Table> {
+        let spec = self.table.metadata().default_partition_spec()?;
+        check_added_files(spec, &self.added)?;
+
+        let mut summary = self.summary;
+        summary.insert("replace-partitions".to_string(), "true".to_string());
+        // Rebased on a concurrent commit, the partitions are replaced with the files added by
+        // that commit, like in other Iceberg implementations without conflict validation
+        commit_with_retries(catalog, self.table, |table| {
+            check_default_spec(table, spec)?;
+            let mut producer = SnapshotProducer::new(table);
+            let (current, _) = producer.delete_data_files(|spec_id, data_file| {
+                spec.fields.is_empty()
+                    || (spec_id == spec.spec_id
+                        && self
+                            .added
+                            .iter()
+                            .any(|added| added.partition == data_file.partition))
+            })?;
+            producer.commit_file_changes(
+                catalog,
+                Operation::Overwrite,
+                self.added.clone(),
+                current,
+                summary.clone(),
+            )
+        })
+    }
+}
+
+pub(crate) fn check_added_files(spec: &PartitionSpec, data_files: &[DataFile]) -> Result<()> {
+    for data_file in data_files {
+        if data_file.content != DataContentType::Data {
+            return Err(IcebergError::Invalid(format!(
+                "Can't add delete file {}",
+                data_file.file_path
+            )));
+        }
+        if data_file.partition.len() != spec.fields.len() {
+            return Err(IcebergError::Invalid(format!(
+                "Data file {} has {} partition values but partition spec {} has {} fields",
+                data_file.file_path,
+                data_file.partition.len(),
+                spec.spec_id,
+                spec.fields.len()
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::{Int64Array, RecordBatch, StringArray};
+
+    use crate::iceberg::catalog::IcebergCatalog;
+    use crate::iceberg::error::IcebergError;
+    use crate::iceberg::spec::manifest::{DataFile, ManifestStatus};
+    use crate::iceberg::spec::partition_spec::{PartitionField, PartitionSpec, Transform};
+    use crate::iceberg::spec::snapshot::Operation;
+    use crate::iceberg::table::Table;
+    use crate::iceberg::test_utils::{append_ids, ids_batch, TestCatalog};
+    use crate::iceberg::writer::partitioned::PartitionedWriter;
+
+    fn scan_ids(table: &Table) -> Vec<i64> {
+        let mut ids = vec![];
+        for batch in table.scan().plan_files().unwrap().to_arrow().unwrap() {
+            let batch = batch.unwrap();
+            let column = batch.column(0);
+            ids.extend(
+                column
+                    .as_any()
+                    .downcast_ref::<Int64Array>()
+                    .unwrap()
+                    .values(),
+            );
+        }
+        ids.sort();
+        ids
+    }
+
+    fn write(table: &Table, batch: &RecordBatch) -> Vec<DataFile> {
+        let mut writer = PartitionedWriter::for_table(table).unwrap();
+        writer.write(batch).unwrap();
+        writer.close().unwrap()
+    }
+
+    #[test]
+    fn test_overwrite_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1, 2]);
+        let table = append_ids(&catalog, &table, &[3]);
+        let replaced = table
+            .scan()
+            .plan_files()
+            .unwrap()
+            .tasks()
+            .iter()
+            .find(|task| task.data_file.record_count == 2)
+            .unwrap()
+            .data_file
+            .clone();
+
+        let table = table
+            .new_overwrite()
+            .delete_file(&replaced)
+            .add_files(write(&table, &ids_batch(&[10, 20])))
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(vec![3, 10, 20], scan_ids(&table));
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        assert_eq!(Operation::Overwrite, snapshot.summary.operation);
+        assert_eq!("1", snapshot.summary.rest["deleted-data-files"]);
+        assert_eq!("2", snapshot.summary.rest["deleted-records"]);
+
+        // The rewritten manifest records the deletion
+        let deleted = table
+            .manifests(snapshot)
+            .unwrap()
+            .iter()
+            .flat_map(|manifest| table.manifest_entries(manifest).unwrap())
+            .filter(|entry| entry.status == ManifestStatus::Deleted)
+            .collect::<Vec<_>>();
+        assert_eq!(1, deleted.len());
+        assert_eq!(replaced.file_path, deleted[0].data_file.file_path);
+        assert_eq!(Some(snapshot.snapshot_id), deleted[0].snapshot_id);
+
+        // The file is gone now
+        assert!(table
+            .new_overwrite()
+            .delete_file(&replaced)
+            .commit(&catalog)
+            .is_err());
+    }
+
+    #[test]
+    fn test_concurrent_overwrites() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1]);
+        let base = append_ids(&catalog, &table, &[2]);
+        let files: Vec<DataFile> = base
+            .scan()
+            .plan_files()
+            .unwrap()
+            .tasks()
+            .iter()
+            .map(|task| task.data_file.clone())
+            .collect();
+
+        base.new_overwrite()
+            .delete_file(&files[0])
+            .add_files(write(&base, &ids_batch(&[10])))
+            .commit(&catalog)
+            .unwrap();
+        // Overwrites of other files are applied to the current table
+        let table = base
+            .new_overwrite()
+            .delete_file(&files[1])
+            .add_files(write(&base, &ids_batch(&[20])))
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(vec![10, 20], scan_ids(&table));
+        // The file was deleted by a concurrent commit
+        let error = base
+            .new_overwrite()
+            .delete_file(&files[0])
+            .commit(&catalog)
+            .unwrap_err();
+        assert!(matches!(error, IcebergError::NotFound(_)), "{}", error);
+    }
+
+    #[test]
+    fn test_replace_partitions() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let mut metadata = table.metadata().clone();
+        metadata.partition_specs = vec![PartitionSpec {
+            spec_id: 0,
+            fields: vec![PartitionField {
+                source_id: 2,
+                source_ids: vec![],
+                field_id: 1000,
+                name: "data".to_string(),
+                transform: Transform::Identity,
+            }],
+        }];
+        let table = catalog.commit_table(&table, metadata).unwrap();
+        let batch = |ids: &[i64], data: &[&str]| {
+            RecordBatch::try_new(
+                ids_batch(&[]).schema(),
+                vec![
+                    Arc::new(Int64Array::from(ids.to_vec())),
+                    Arc::new(StringArray::from(data.to_vec())),
+                ],
+            )
+            .unwrap()
+        };
+        let table = table
+            .new_append()
+            .add_files(write(&table, &batch(&[1, 2, 3], &["a", "b", "b"])))
+            .commit(&catalog)
+            .unwrap();
+
+        let table = table
+            .new_replace_partitions()
+            .add_files(write(&table, &batch(&[4, 5], &["b", "c"])))
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(vec![1, 4, 5], scan_ids(&table));
+        let summary = &table.metadata().current_snapshot().unwrap().summary;
+        assert_eq!(Operation::Overwrite, summary.operation);
+        assert_eq!("true", summary.rest["replace-partitions"]);
+        assert_eq!("1", summary.rest["deleted-data-files"]);
+    }
+}
@@ -0,0 +1,485 @@
+use std::collections::HashMap;
+
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::operations::{commit_time_ms, current_time_ms};
+use crate::iceberg::spec::snapshot::{RefType, SnapshotRefV2};
+use crate::iceberg::spec::table_metadata::{SnapshotLog, TableMetadataV2, MAIN_BRANCH};
+use crate::iceberg::table::Table;
+
+// Creates, moves and drops the branches and tags of a table. Changes are validated and applied
+// in order when committing, moving the main branch also changes the current snapshot
+pub struct ManageRefs<'a> {
+    table: &'a Table,
+    updates: Vec<RefUpdate>,
+}
+
+#[derive(Debug, Clone)]
+enum RefUpdate {
+    CreateBranch {
+        name: String,
+        snapshot_id: i64,
+    },
+    CreateTag {
+        name: String,
+        snapshot_id: i64,
+    },
+    Drop {
+        name: String,
+    },
+    FastForward {
+        branch: String,
+        to: String,
+    },
+    SetRetention {
+        name: String,
+        min_snapshots_to_keep: Option<i32>,
+        max_snapshot_age_ms: Option<i64>,
+        max_ref_age_ms: Option<i64>,
+    },
+    SetCurrentSnapshot {
+        snapshot_id: i64,
+        // Whether the snapshot must be an ancestor of the current one
+        rollback: bool,
+    },
+}
+
+impl<'a> ManageRefs<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        ManageRefs {
+            table,
+            updates: vec![],
+        }
+    }
+
+    pub fn create_branch(mut self, name: &str, snapshot_id: i64) -> Self {
+        self.updates.push(RefUpdate::CreateBranch {
+            name: name.to_string(),
+            snapshot_id,
+        });
+        self
+    }
+
+    pub fn create_tag(mut self, name: &str, snapshot_id: i64) -> Self {
+        self.updates.push(RefUpdate::CreateTag {
+            name: name.to_string(),
+            snapshot_id,
+        });
+        self
+    }
+
+    // Drops a branch or tag other than main. Snapshots only referenced by the ref are removed by
+    // the next snapshot expiration
+    pub fn drop_ref(mut self, name: &str) -> Self {
+        self.updates.push(RefUpdate::Drop {
+            name: name.to_string(),
+        });
+        self
+    }
+
+    // Moves `branch` to the snapshot of the ref `to`, which must be a descendant of the
+    // snapshot of `branch`
+    pub fn fast_forward(mut self, branch: &str, to: &str) -> Self {
+        self.updates.push(RefUpdate::FastForward {
+            branch: branch.to_string(),
+            to: to.to_string(),
+        });
+        self
+    }
+
+    // Makes the snapshot the current snapshot of the table, moving the main branch to it
+    pub fn set_current_snapshot(mut self, snapshot_id: i64) -> Self {
+        self.updates.push(RefUpdate::SetCurrentSnapshot {
+            snapshot_id,
+            rollback: false,
+        });
+        self
+    }
+
+    // Like set_current_snapshot, for a snapshot that must be an ancestor of the current one,
+    // e.g. to undo the last commits
+    pub fn rollback_to(mut self, snapshot_id: i64) -> Self {
+        self.updates.push(RefUpdate::SetCurrentSnapshot {
+            snapshot_id,
+            rollback: true,
+        });
+        self
+    }
+
+    // Sets the retention of a ref used by snapshot expiration, None resets a setting to the
+    // table default. Tags only have a maximum ref age
+    pub fn set_ref_retention(
+        mut self,
+        name: &str,
+        min_snapshots_to_keep: Option<i32>,
+        max_snapshot_age_ms: Option<i64>,
+        max_ref_age_ms: Option<i64>,
+    ) -> Self {
+        self.updates.push(RefUpdate::SetRetention {
+            name: name.to_string(),
+            min_snapshots_to_keep,
+            max_snapshot_age_ms,
+            max_ref_age_ms,
+        });
+        self
+    }
+
+    // Returns the metadata with the changes applied, without committing it
+    pub fn apply(&self) -> Result<TableMetadataV2> {
+        let base = self.table.metadata();
+        self.apply_at(current_time_ms().max(base.last_updated_ms))
+    }
+
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<Table> {
+        let metadata = self.apply_at(commit_time_ms(catalog, self.table.metadata())?)?;
+        catalog.commit_table(self.table, metadata)
+    }
+
+    fn apply_at(&self, timestamp_ms: i64) -> Result<TableMetadataV2> {
+        let base = self.table.metadata();
+        let mut refs = base.refs.clone().unwrap_or_default();
+        if let Some(current) = base.current_snapshot() {
+            refs.entry(MAIN_BRANCH.to_string())
+                .or_insert_with(|| branch(current.snapshot_id));
+        }
+        for update in &self.updates {
+            self.apply_update(&mut refs, update)?;
+        }
+
+        let mut metadata = base.clone();
+        metadata.last_updated_ms = timestamp_ms;
+        let main = refs.get(MAIN_BRANCH).map(|main| main.snapshot_id);
+        if main.is_some() && main != base.current_snapshot().map(|s| s.snapshot_id) {
+            metadata.current_snapshot_id = main;
+            metadata
+                .snapshot_log
+                .get_or_insert_with(Vec::new)
+                .extend(main.map(|snapshot_id| SnapshotLog {
+                    snapshot_id,
+                    timestamp_ms,
+                }));
+        }
+        metadata.refs = Some(refs);
+        Ok(metadata)
+    }
+
+    fn apply_update(
+        &self,
+        refs: &mut HashMap<String, SnapshotRefV2>,
+        update: &RefUpdate,
+    ) -> Result<()> {
+        match update {
+            RefUpdate::CreateBranch { name, snapshot_id } => {
+                self.check_new_ref(refs, name, *snapshot_id)?;
+                refs.insert(name.clone(), branch(*snapshot_id));
+            }
+            RefUpdate::CreateTag { name, snapshot_id } => {
+                if name == MAIN_BRANCH {
+                    return Err(IcebergError::Invalid(format!(
+                        "{} is reserved for the main branch",
+                        MAIN_BRANCH
+                    )));
+                }
+                self.check_new_ref(refs, name, *snapshot_id)?;
+                refs.insert(
+                    name.clone(),
+                    SnapshotRefV2 {
+                        snapshot_id: *snapshot_id,
+                        ref_type: RefType::Tag,
+                        max_ref_age_ms: None,
+                    },
+                );
+            }
+            RefUpdate::Drop { name } => {
+                if name == MAIN_BRANCH {
+                    return Err(IcebergError::Invalid(
+                        "The main branch can't be dropped".to_string(),
+                    ));
+                }
+                self.get_ref(refs, name)?;
+                refs.remove(name);
+            }
+            RefUpdate::FastForward { branch, to } => {
+                let from = self.get_ref(refs, branch)?;
+                if from.ref_type == RefType::Tag {
+                    return Err(IcebergError::Invalid(format!(
+                        "{} is a tag, only branches can be fast-forwarded",
+                        branch
+                    )));
+                }
+                let from_id = from.snapshot_id;
+                let to_id = self.get_ref(refs, to)?.snapshot_id;
+                if !self.is_ancestor(from_id, to_id) {
+                    return Err(IcebergError::Invalid(format!(
+                        "Can't fast-forward {} to {}: snapshot {} isn't an ancestor of \
+                         snapshot {}",
+                        branch, to, from_id, to_id
+                    )));
+                }
+                if let Some(from) = refs.get_mut(branch) {
+                    from.snapshot_id = to_id;
+                }
+            }
+            RefUpdate::SetRetention {
+                name,
+                min_snapshots_to_keep,
+                max_snapshot_age_ms,
+                max_ref_age_ms,
+            } => {
+                self.get_ref(refs, name)?;
+                let snapshot_ref = refs.get_mut(name).expect("ref exists");
+                match &mut snapshot_ref.ref_type {
+                    RefType::Branch {
+                        min_snapshots_to_keep: min,
+                        max_snapshot_age_ms: max_age,
+                    } => {
+                        *min = *min_snapshots_to_keep;
+                        *max_age = *max_snapshot_age_ms;
+                    }
+                    RefType::Tag
+                        if min_snapshots_to_keep.is_some() || max_snapshot_age_ms.is_some() =>
+                    {
+                        return Err(IcebergError::Invalid(format!(
+                            "{} is a tag, tags only have a maximum ref age",
+                            name
+                        )));
+                    }
+                    RefType::Tag => {}
+                }
+                if name == MAIN_BRANCH && max_ref_age_ms.is_some() {
+                    return Err(IcebergError::Invalid(
+                        "The main branch never expires and has no maximum ref age".to_string(),
+                    ));
+                }
+                snapshot_ref.max_ref_age_ms = *max_ref_age_ms;
+            }
+            RefUpdate::SetCurrentSnapshot {
+                snapshot_id,
+                rollback,
+            } => {
+                self.check_snapshot(*snapshot_id)?;
+                if *rollback {
+                    let current = refs.get(MAIN_BRANCH).map(|main| main.snapshot_id);
+                    if !current.is_some_and(|current| self.is_ancestor(*snapshot_id, current)) {
+                        return Err(IcebergError::Invalid(format!(
+                            "Can't roll back to snapshot {}: it isn't an ancestor of the \
+                             current snapshot {:?}",
+                            snapshot_id, current
+                        )));
+                    }
+                }
+                refs.entry(MAIN_BRANCH.to_string())
+                    .or_insert_with(|| branch(*snapshot_id))
+                    .snapshot_id = *snapshot_id;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_new_ref(
+        &self,
+        refs: &HashMap<String, SnapshotRefV2>,
+        name: &str,
+        snapshot_id: i64,
+    ) -> Result<()> {
+        if refs.contains_key(name) {
+            return Err(IcebergError::Invalid(format!(
+                "Ref {} already exists",
+                name
+            )));
+        }
+        self.check_snapshot(snapshot_id)
+    }
+
+    fn check_snapshot(&self, snapshot_id: i64) -> Result<()> {
+        if self.table.metadata().snapshot_by_id(snapshot_id).is_none() {
+            return Err(IcebergError::NotFound(format!(
+                "Snapshot {} of table {}",
+                snapshot_id,
+                self.table.ident()
+            )));
+        }
+        Ok(())
+    }
+
+    fn get_ref<'r>(
+        &self,
+        refs: &'r HashMap<String, SnapshotRefV2>,
+        name: &str,
+    ) -> Result<&'r SnapshotRefV2> {
+        refs.get(name).ok_or_else(|| {
+            IcebergError::NotFound(format!("Ref {} of table {}", name, self.table.ident()))
+        })
+    }
+
+    // Whether `ancestor` is `snapshot_id` or one of its ancestors
+    fn is_ancestor(&self, ancestor: i64, snapshot_id: i64) -> bool {
+        let metadata = self.table.metadata();
+        std::iter::successors(metadata.snapshot_by_id(snapshot_id), |snapshot| {
+            snapshot
+                .parent_snapshot_id
+                .and_then(|parent| metadata.snapshot_by_id(parent))
+        })
+        .any(|snapshot| snapshot.snapshot_id == ancestor)
+    }
+}
+
+fn branch(snapshot_id: i64) -> SnapshotRefV2 {
+    SnapshotRefV2 {
+        snapshot_id,
+        ref_type: RefType::Branch {
+            min_snapshots_to_keep: None,
+            max_snapshot_age_ms: None,
+        },
+        max_ref_age_ms: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+
+    #[test]
+    fn test_manage_refs() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1]);
+        let first = table.metadata().current_snapshot_id.unwrap();
+        let table = append_ids(&catalog, &table, &[2]);
+        let second = table.metadata().current_snapshot_id.unwrap();
+
+        let table = table
+            .manage_refs()
+            .create_branch("dev", first)
+            .create_tag("v1", first)
+            .set_ref_retention("dev", Some(5), None, Some(86_400_000))
+            .set_ref_retention("v1", None, None, Some(1000))
+            .commit(&catalog)
+            .unwrap();
+        let refs = table.metadata().refs.as_ref().unwrap();
+        assert_eq!(first, refs["dev"].snapshot_id);
+        assert_eq!(
+            RefType::Branch {
+                min_snapshots_to_keep: Some(5),
+                max_snapshot_age_ms: None
+            },
+            refs["dev"].ref_type
+        );
+        assert_eq!(Some(86_400_000), refs["dev"].max_ref_age_ms);
+        assert_eq!(RefType::Tag, refs["v1"].ref_type);
+        assert_eq!(Some(1000), refs["v1"].max_ref_age_ms);
+
+        let table = table
+            .manage_refs()
+            .fast_forward("dev", MAIN_BRANCH)
+            .drop_ref("v1")
+            .commit(&catalog)
+            .unwrap();
+        let refs = table.metadata().refs.as_ref().unwrap();
+        assert_eq!(second, refs["dev"].snapshot_id);
+        assert!(!refs.contains_key("v1"));
+        assert_eq!(Some(second), table.metadata().current_snapshot_id);
+    }
+
+    #[test]
+    fn test_manage_refs_moves_main() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1]);
+        let first = table.metadata().current_snapshot_id.unwrap();
+        let table = append_ids(&catalog, &table, &[2]);
+        let second = table.metadata().current_snapshot_id.unwrap();
+
+        // Main can't go backwards by fast-forwarding
+        let table = table
+            .manage_refs()
+            .create_branch("old", first)
+            .commit(&catalog)
+            .unwrap();
+        assert!(table
+            .manage_refs()
+            .fast_forward(MAIN_BRANCH, "old")
+            .apply()
+            .is_err());
+
+        let metadata = table
+            .manage_refs()
+            .drop_ref("old")
+            .create_branch("old", second)
+            .fast_forward("old", MAIN_BRANCH)
+            .apply()
+            .unwrap();
+        assert_eq!(second, metadata.refs.as_ref().unwrap()["old"].snapshot_id);
+        assert_eq!(
+            table.metadata().snapshot_log,
+            metadata.snapshot_log,
+            "main didn't move"
+        );
+    }
+
+    #[test]
+    fn test_rollback_and_set_current_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1]);
+        let first = table.metadata().current_snapshot_id.unwrap();
+        let table = append_ids(&catalog, &table, &[2]);
+        let second = table.metadata().current_snapshot_id.unwrap();
+
+        let table = table.rollback_to(first).commit(&catalog).unwrap();
+        assert_eq!(Some(first), table.metadata().current_snapshot_id);
+        assert_eq!(
+            first,
+            table.metadata().refs.as_ref().unwrap()[MAIN_BRANCH].snapshot_id
+        );
+        let log = table.metadata().snapshot_log.as_ref().unwrap();
+        assert_eq!(first, log.last().unwrap().snapshot_id);
+        assert_eq!(3, log.len());
+
+        // The second snapshot is no longer an ancestor of the current one
+        let error = table.rollback_to(second).apply().unwrap_err();
+        assert!(matches!(error, IcebergError::Invalid(_)));
+        assert!(matches!(
+            table.set_current_snapshot(42).apply().unwrap_err(),
+            IcebergError::NotFound(_)
+        ));
+        let table = table.set_current_snapshot(second).commit(&catalog).unwrap();
+        assert_eq!(Some(second), table.metadata().current_snapshot_id);
+        assert_eq!(4, table.metadata().snapshot_log.as_ref().unwrap().len());
+    }
+
+    #[test]
+    fn test_manage_refs_validation() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1]);
+        let snapshot_id = table.metadata().current_snapshot_id.unwrap();
+
+        let invalid = [
+            table.manage_refs().create_branch(MAIN_BRANCH, snapshot_id),
+            table.manage_refs().create_tag(MAIN_BRANCH, snapshot_id),
+            table.manage_refs().create_tag("v1", 42),
+            table.manage_refs().drop_ref(MAIN_BRANCH),
+            table.manage_refs().drop_ref("missing"),
+            table
+                .manage_refs()
+                .create_tag("v1", snapshot_id)
+                .fast_forward("v1", MAIN_BRANCH),
+            table
+                .manage_refs()
+                .create_tag("v1", snapshot_id)
+                .set_ref_retention("v1", Some(1), None, None),
+            table
+                .manage_refs()
+                .set_ref_retention(MAIN_BRANCH, None, None, Some(1)),
+        ];
+        for refs in invalid {
+            assert!(refs.apply().is_err());
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::iceberg::audit::{AuditRecord, AuditedOperation, Auditor};
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::operations::statistics::refresh_partition_statistics;
+use crate::iceberg::operations::{
+    any_counted, commit_with_retries, snapshots_since, SnapshotProducer,
+};
+use crate::iceberg::progress::{ProgressPhase, ProgressReporter};
+use crate::iceberg::properties::TableProperties;
+use crate::iceberg::scan::{FileScanTask, ScanPlan};
+use crate::iceberg::spec::manifest::DataFile;
+use crate::iceberg::spec::snapshot::Operation;
+use crate::iceberg::spec::snapshot_summary::ADDED_DELETE_FILES;
+use crate::iceberg::table::Table;
+use crate::iceberg::writer::partitioned::PartitionedWriter;
+
+// Compacts the small data files of the current snapshot. Files smaller than 3/4 of the target
+// size are grouped by partition and packed into bins of at most the target size, and every bin
+// with enough files is rewritten into a single file, applying the delete files of its input
+// files. Only files of the default partition spec are rewritten. The result is committed as a
+// replace snapshot, which doesn't change the rows of the table.
+//
+// Partitions are rewritten independently, several at the same time with with_parallelism. On
+// very large tables, with_partitions_per_commit commits the rewrite in several snapshots, so that
+// writers committing concurrently only conflict with the partitions of a single commit
+pub struct RewriteDataFiles<'a> {
+    table: &'a Table,
+    target_file_size: Option<i64>,
+    min_input_files: usize,
+    parallelism: usize,
+    partitions_per_commit: Option<usize>,
+    auditor: Option<Arc<dyn Auditor>>,
+    progress: Option<Arc<dyn ProgressReporter>>,
+}
+
+#[derive(Debug)]
+pub struct RewriteDataFilesResult {
+    pub table: Table,
+    pub rewritten_files: Vec<DataFile>,
+    pub added_files: Vec<DataFile>,
+    // The snapshots committed, in order
+    pub snapshot_ids: Vec<i64>,
+}
+
+impl<'a> RewriteDataFiles<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        RewriteDataFiles {
+            table,
+            target_file_size: None,
+            min_input_files: 2,
+            parallelism: 1,
+            partitions_per_commit: None,
+            auditor: None,
+            progress: None,
+        }
+    }
+
+    // Size of the rewritten files, write.target-file-size-bytes by default
+    pub fn with_target_file_size(mut self, target_file_size: i64) -> Self {
+        self.target_file_size = Some(target_file_size);
+        self
+    }
+
+    // Bins with fewer files are not rewritten
+    pub fn with_min_input_files(mut self, min_input_files: usize) -> Self {
+        self.min_input_files = min_input_files.max(1);
+        self
+    }
+
+    // Number of partitions rewritten at the same time, 1 by default. Rewritten files are written
+    // by one thread per partition
+    pub fn with_parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+
+    // Commits the rewritten files every `partitions` partitions instead of once at the end.
+    // Commits that succeeded are kept when a later one fails
+    pub fn with_partitions_per_commit(mut self, partitions: usize) -> Self {
+        self.partitions_per_commit = Some(partitions.max(1));
+        self
+    }
+
+    // Records the rewrite with the auditor once committed
+    pub fn with_auditor(mut self, auditor: Arc<dyn Auditor>) -> Self {
+        self.auditor = Some(auditor);
+        self
+    }
+
+    // Reports the manifests read when planning the rewrite and the files rewritten
+    pub fn with_progress(mut self, progress: Arc<dyn ProgressReporter>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<RewriteDataFilesResult> {
+        let metadata = self.table.metadata();
+        let target_file_size = match self.target_file_size {
+            Some(size) => size,
+            None => TableProperties::from_metadata(metadata).target_file_size_bytes()?,
+        };
+        let spec = metadata.default_partition_spec()?;
+        let mut scan = self.table.scan();
+        if let Some(progress) = &self.progress {
+            scan = scan.with_progress(progress.clone());
+        }
+        let plan = scan.plan_files()?;
+
+        let mut partitions: BTreeMap<String, Vec<&FileScanTask>> = BTreeMap::new();
+        for task in plan.tasks() {
+            if task.spec_id == spec.spec_id
+                && task.data_file.file_size_in_bytes < target_file_size / 4 * 3
+            {
+                partitions
+                    .entry(spec.partition_path(&task.data_file.partition))
+                    .or_default()
+                    .push(task);
+            }
+        }
+        // The bins to rewrite of every partition
+        let partitions: Vec<Vec<Vec<&FileScanTask>>> = partitions
+            .into_values()
+            .map(|tasks| {
+                pack(tasks, target_file_size)
+                    .into_iter()
+                    .filter(|bin| bin.len() >= self.min_input_files)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|bins| !bins.is_empty())
+            .collect();
+        if partitions.is_empty() {
+            return Ok(RewriteDataFilesResult {
+                table: self.table.clone(),
+                rewritten_files: vec![],
+                added_files: vec![],
+                snapshot_ids: vec![],
+            });
+        }
+
+        let phase = ProgressPhase::RewritingFiles;
+        if let Some(progress) = &self.progress {
+            let total = partitions.iter().flatten().map(|bin| bin.len() as u64);
+            progress.start(phase, Some(total.sum()));
+        }
+        let mut table = self.table.clone();
+        let mut rewritten_files = vec![];
+        let mut added_files = vec![];
+        let mut snapshot_ids = vec![];
+        let partitions_per_commit = self.partitions_per_commit.unwrap_or(partitions.len());
+        for partitions in partitions.chunks(partitions_per_commit) {
+            let added = self.rewrite_partitions(&plan, partitions)?;
+            let rewritten: Vec<DataFile> = partitions
+                .iter()
+                .flatten()
+                .flatten()
+                .map(|task| task.data_file.clone())
+                .collect();
+            table = self.commit_rewrite(&table, catalog, spec.spec_id, &rewritten, &added)?;
+            snapshot_ids.extend(table.metadata().current_snapshot_id);
+            rewritten_files.extend(rewritten);
+            added_files.extend(added);
+        }
+        if let Some(progress) = &self.progress {
+            progress.finish(phase);
+        }
+
+        Ok(RewriteDataFilesResult {
+            table,
+            rewritten_files,
+            added_files,
+            snapshot_ids,
+        })
+    }
+
+    // Rewrites the bins of the partitions, up to `parallelism` partitions at the same time, and
+    // returns the added files
+    fn rewrite_partitions(
+        &self,
+        plan: &ScanPlan,
+        partitions: &[Vec<Vec<&FileScanTask>>],
+    ) -> Result<Vec<DataFile>> {
+        if self.parallelism <= 1 || partitions.len() <= 1 {
+            let mut added_files = vec![];
+            for bins in partitions {
+                added_files.extend(self.rewrite_partition(plan, bins)?);
+            }
+            return Ok(added_files);
+        }
+        let added: Vec<Mutex<Option<Result<Vec<DataFile>>>>> =
+            partitions.iter().map(|_| Mutex::new(None)).collect();
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            for _ in 0..self.parallelism.min(partitions.len()) {
+                scope.spawn(|| {
+                    // Stop rewriting partitions once one of them failed
+                    while !failed.load(Ordering::SeqCst) {
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        let Some(bins) = partitions.get(index) else {
+                            break;
+                        };
+                        let rewritten = self.rewrite_partition(plan, bins);
+                        if rewritten.is_err() {
+                            failed.store(true, Ordering::SeqCst);
+                        }
+                        *added[index].lock().unwrap() = Some(rewritten);
+                    }
+                });
+            }
+        });
+        let mut added_files = vec![];
+        for added in added {
+            if let Some(added) = added.into_inner().unwrap() {
+                added_files.extend(added?);
+            }
+        }
+        Ok(added_files)
+    }
+
+    fn rewrite_partition(
+        &self,
+        plan: &ScanPlan,
+        bins: &[Vec<&FileScanTask>],
+    ) -> Result<Vec<DataFile>> {
+        let phase = ProgressPhase::RewritingFiles;
+        let mut added_files = vec![];
+        for bin in bins {
+            let paths: HashSet<&str> = bin
+                .iter()
+                .map(|task| task.data_file.file_path.as_str())
+                .collect();
+            // The progress of the bins is reported per bin rather than per file read
+            let mut bin_plan = plan.clone().without_progress();
+            bin_plan.retain_tasks(|task| paths.contains(task.data_file.file_path.as_str()));
+            let mut writer = PartitionedWriter::for_table(self.table)?;
+            for batch in bin_plan.to_arrow()? {
+                writer.write(&batch?)?;
+            }
+            added_files.extend(writer.close()?);
+            if let Some(progress) = &self.progress {
+                let bytes = bin.iter().map(|task| task.data_file.file_size_in_bytes);
+                progress.bytes_read(phase, bytes.sum::<i64>().max(0) as u64);
+                progress.advance(phase, bin.len() as u64);
+            }
+        }
+        Ok(added_files)
+    }
+
+    // Replaces the rewritten files of `table` with the added files
+    fn commit_rewrite(
+        &self,
+        table: &Table,
+        catalog: &dyn IcebergCatalog,
+        spec_id: i32,
+        rewritten_files: &[DataFile],
+        added_files: &[DataFile],
+    ) -> Result<Table> {
+        let rewritten: HashSet<&str> = rewritten_files
+            .iter()
+            .map(|file| file.file_path.as_str())
+            .collect();
+        // Rebased on concurrent commits, the rewrite still applies if they didn't delete any of
+        // the rewritten files nor add deletes, which may apply to the rewritten files but not to
+        // the files replacing them
+        let committed = commit_with_retries(catalog, table, |current| {
+            if any_counted(&snapshots_since(current, table), ADDED_DELETE_FILES) {
+                return Err(IcebergError::Invalid(format!(
+                    "Concurrent commits added deletes to table {} while its files were rewritten",
+                    current.ident()
+                )));
+            }
+            let mut producer = SnapshotProducer::new(current);
+            let (manifests, deleted) = producer.delete_data_files(|file_spec_id, data_file| {
+                file_spec_id == spec_id && rewritten.contains(data_file.file_path.as_str())
+            })?;
+            if deleted.len() != rewritten.len() {
+                return Err(IcebergError::Invalid(format!(
+                    "Concurrent commits deleted files of table {} while they were rewritten",
+                    current.ident()
+                )));
+            }
+            producer.commit_file_changes(
+                catalog,
+                Operation::Replace,
+                added_files.to_vec(),
+                manifests,
+                HashMap::new(),
+            )
+        })?;
+        let committed = refresh_partition_statistics(table, committed, catalog)?;
+
+        if let Some(auditor) = &self.auditor {
+            auditor.record(&[AuditRecord::new(
+                AuditedOperation::RewriteDataFiles,
+                committed.ident(),
+            )
+            .with_snapshot_id(committed.metadata().current_snapshot_id)
+            .with_details(&format!(
+                "rewrote {} data files into {}",
+                rewritten_files.len(),
+                added_files.len()
+            ))])?;
+        }
+        Ok(committed)
+    }
+}
+
+// Packs the files into bins of at most `target_size` bytes, first fit by decreasing size
+fn pack(mut tasks: Vec<&FileScanTask>, target_size: i64) -> Vec<Vec<&FileScanTask>> {
+    tasks.sort_by_key(|task| std::cmp::Reverse(task.data_file.file_size_in_bytes));
+    let mut bins: Vec<(i64, Vec<&FileScanTask>)> = vec![];
+    for task in tasks {
+        let size = task.data_file.file_size_in_bytes;
+        match bins
+            .iter_mut()
+            .find(|(total, _)| total + size <= target_size)
+        {
+            Some((total, bin)) => {
+                *total += size;
+                bin.push(task);
+            }
+            None => bins.push((size, vec![task])),
+        }
+    }
+    bins.into_iter().map(|(_, bin)| bin).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{Int64Array, RecordBatch, StringArray};
+
+    use super::*;
+    use crate::iceberg::progress::tests::RecordingReporter;
+    use crate::iceberg::spec::manifest::{DataContentType, FileFormat};
+    use crate::iceberg::spec::partition_spec::{PartitionField, PartitionSpec, Transform};
+    use crate::iceberg::test_utils::{append_ids, ids_batch, TestCatalog};
+    use crate::iceberg::writer::position_delete::PositionDeleteWriter;
+
+    fn count(table: &Table) -> usize {
+        table
+            .scan()
+            .plan_files()
+            .unwrap()
+            .to_arrow()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum()
+    }
+
+    #[test]
+    fn test_rewrite_data_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let mut table = catalog.create_table("db", "t", dir.path());
+        for ids in [[1, 2], [3, 4], [5, 6]] {
+            table = append_ids(&catalog, &table, &ids);
+        }
+        let first = table.scan().plan_files().unwrap().tasks()[0]
+            .data_file
+            .file_path
+            .clone();
+        let mut writer = PositionDeleteWriter::for_table(&table, vec![]).unwrap();
+        writer.delete(&first, 0);
+        let table = table
+            .new_row_delta()
+            .add_deletes(writer.close().unwrap())
+            .commit(&catalog)
+            .unwrap();
+
+        // Too few files for a bin
+        let result = table
+            .rewrite_data_files()
+            .with_min_input_files(4)
+            .commit(&catalog)
+            .unwrap();
+        assert!(result.rewritten_files.is_empty());
+        assert_eq!(table.metadata_location(), result.table.metadata_location());
+
+        let progress = Arc::new(RecordingReporter::default());
+        let result = table
+            .rewrite_data_files()
+            .with_progress(progress.clone())
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(3, result.rewritten_files.len());
+        // 3 data manifests and a delete manifest
+        assert_eq!(4, progress.units(ProgressPhase::PlanningManifests));
+        assert_eq!(Some(3), progress.total(ProgressPhase::RewritingFiles));
+        assert_eq!(3, progress.units(ProgressPhase::RewritingFiles));
+        let input_bytes: i64 = result
+            .rewritten_files
+            .iter()
+            .map(|file| file.file_size_in_bytes)
+            .sum();
+        assert_eq!(
+            input_bytes as u64,
+            progress.bytes(ProgressPhase::RewritingFiles)
+        );
+        assert_eq!(1, result.added_files.len());
+        assert_eq!(5, result.added_files[0].record_count);
+        let table = result.table;
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        assert_eq!(Operation::Replace, snapshot.summary.operation);
+        assert_eq!("3", snapshot.summary.rest["deleted-data-files"]);
+        let plan = table.scan().plan_files().unwrap();
+        assert_eq!(1, plan.tasks().len());
+        assert!(plan.tasks()[0].deletes.is_empty());
+        assert_eq!(5, count(&table));
+    }
+
+    #[test]
+    fn test_rewrite_partitions_in_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let mut metadata = table.metadata().clone();
+        metadata.partition_specs = vec![PartitionSpec {
+            spec_id: 0,
+            fields: vec![PartitionField {
+                source_id: 2,
+                source_ids: vec![],
+                field_id: 1000,
+                name: "data".to_string(),
+                transform: Transform::Identity,
+            }],
+        }];
+        let mut table = catalog.commit_table(&table, metadata).unwrap();
+        // 2 files in each of 3 partitions
+        for ids in [[1, 2, 3], [4, 5, 6]] {
+            let batch = RecordBatch::try_new(
+                ids_batch(&[]).schema(),
+                vec![
+                    Arc::new(Int64Array::from(ids.to_vec())),
+                    Arc::new(StringArray::from(vec!["a", "b", "c"])),
+                ],
+            )
+            .unwrap();
+            let mut writer = PartitionedWriter::for_table(&table).unwrap();
+            writer.write(&batch).unwrap();
+            table = table
+                .new_append()
+                .add_files(writer.close().unwrap())
+                .commit(&catalog)
+                .unwrap();
+        }
+        let snapshots = table.metadata().snapshots.as_ref().unwrap().len();
+
+        let result = table
+            .rewrite_data_files()
+            .with_parallelism(3)
+            .with_partitions_per_commit(2)
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(6, result.rewritten_files.len());
+        assert_eq!(3, result.added_files.len());
+        assert_eq!(2, result.snapshot_ids.len());
+        let table = result.table;
+        assert_eq!(
+            snapshots + 2,
+            table.metadata().snapshots.as_ref().unwrap().len()
+        );
+        assert_eq!(
+            result.snapshot_ids.last().copied(),
+            table.metadata().current_snapshot_id
+        );
+        let plan = table.scan().plan_files().unwrap();
+        assert_eq!(3, plan.tasks().len());
+        assert!(plan
+            .tasks()
+            .iter()
+            .all(|task| task.data_file.record_count == 2));
+        assert_eq!(6, count(&table));
+    }
+
+    #[test]
+    fn test_pack() {
+        let task = |size: i64| FileScanTask {
+            data_file: DataFile {
+                content: DataContentType::Data,
+                file_path: format!("file:/{}.parquet", size),
+                file_format: FileFormat::Parquet,
+                partition: vec![],
+                record_count: 1,
+                file_size_in_bytes: size,
+                column_sizes: None,
+                value_counts: None,
+                null_value_counts: None,
+                nan_value_counts: None,
+                lower_bounds: None,
+                upper_bounds: None,
+                key_metadata: None,
+                split_offsets: None,
+                equality_ids: None,
+                sort_order_id: None,
+                referenced_data_file: None,
+                content_offset: None,
+                content_size_in_bytes: None,
+            },
+            spec_id: 0,
+            sequence_number: 1,
+            deletes: vec![],
+            start: 0,
+            length: size,
+        };
+        let tasks = [task(60), task(30), task(50), task(20), task(40)];
+        let bins = pack(tasks.iter().collect(), 100);
+        let sizes: Vec<Vec<i64>> = bins
+            .iter()
+            .map(|bin| bin.iter().map(|t| t.data_file.file_size_in_bytes).collect())
+            .collect();
+        assert_eq!(vec![vec![60, 40], vec![50, 30, 20]], sizes);
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::iceberg::audit::{AuditRecord, AuditedOperation, Auditor};
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::metrics::report_commit;
+use crate::iceberg::operations::statistics::refresh_partition_statistics;
+use crate::iceberg::operations::SnapshotProducer;
+use crate::iceberg::progress::{ProgressPhase, ProgressReporter};
+use crate::iceberg::properties::TableProperties;
+use crate::iceberg::spec::manifest::{ManifestEntry, ManifestStatus};
+use crate::iceberg::spec::manifest_list::{FileType, ManifestListV2};
+use crate::iceberg::spec::snapshot::Operation;
+use crate::iceberg::table::Table;
+
+// Compacts the manifests of the current snapshot. Manifests smaller than the target size are
+// grouped by content and partition spec, and the live entries of every group with more than one
+// manifest are clustered by partition and written to new manifests of about the target size.
+// Entries keep their snapshot ids and sequence numbers, so the data of the table doesn't change.
+// The result is committed as a replace snapshot
+pub struct RewriteManifests<'a> {
+    table: &'a Table,
+    target_size: Option<i64>,
+    auditor: Option<Arc<dyn Auditor>>,
+    progress: Option<Arc<dyn ProgressReporter>>,
+}
+
+#[derive(Debug)]
+pub struct RewriteManifestsResult {
+    pub table: Table,
+    pub rewritten_manifests: Vec<ManifestListV2>,
+    pub added_manifests: Vec<ManifestListV2>,
+}
+
+impl<'a> RewriteManifests<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        RewriteManifests {
+            table,
+            target_size: None,
+            auditor: None,
+            progress: None,
+        }
+    }
+
+    // Size of the rewritten manifests, commit.manifest.target-size-bytes by default
+    pub fn with_target_size(mut self, target_size: i64) -> Self {
+        self.target_size = Some(target_size);
+        self
+    }
+
+    // Records the rewrite with the auditor once committed
+    pub fn with_auditor(mut self, auditor: Arc<dyn Auditor>) -> Self {
+        self.auditor = Some(auditor);
+        self
+    }
+
+    // Reports the manifests read for the rewrite
+    pub fn with_progress(mut self, progress: Arc<dyn ProgressReporter>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<RewriteManifestsResult> {
+        let start = Instant::now();
+        let metadata = self.table.metadata();
+        let target_size = match self.target_size {
+            Some(size) => size,
+            None => TableProperties::from_metadata(metadata).manifest_target_size_bytes()?,
+        };
+
+        let mut producer = SnapshotProducer::new(self.table);
+        let mut kept = vec![];
+        let mut groups: BTreeMap<(bool, i32), Vec<ManifestListV2>> = BTreeMap::new();
+        for manifest in producer.current_manifests()? {
+            if manifest.manifest_length < target_size {
+                groups
+                    .entry((
+                        manifest.content == FileType::Delete,
+                        manifest.partition_spec_id,
+                    ))
+                    .or_default()
+                    .push(manifest);
+            } else {
+                kept.push(manifest);
+            }
+        }
+
+        let phase = ProgressPhase::RewritingManifests;
+        if let Some(progress) = &self.progress {
+            let total = groups
+                .values()
+                .filter(|manifests| manifests.len() > 1)
+                .map(|manifests| manifests.len() as u64)
+                .sum();
+            progress.start(phase, Some(total));
+        }
+        let mut rewritten_manifests = vec![];
+        let mut added_manifests = vec![];
+        let mut entries_processed = 0;
+        for ((_, spec_id), manifests) in groups {
+            if manifests.len() < 2 {
+                kept.extend(manifests);
+                continue;
+            }
+            let spec = metadata.partition_spec_by_id(spec_id).ok_or_else(|| {
+                IcebergError::Invalid(format!(
+                    "Partition spec {} of manifest {} is missing from table metadata",
+                    spec_id, manifests[0].manifest_path
+                ))
+            })?;
+            let mut entries = vec![];
+            for manifest in &manifests {
+                entries.extend(
+                    self.table
+                        .manifest_entries(manifest)?
+                        .into_iter()
+                        .filter(ManifestEntry::is_live),
+                );
+                if let Some(progress) = &self.progress {
+                    progress.bytes_read(phase, manifest.manifest_length.max(0) as u64);
+                    progress.advance(phase, 1);
+                }
+            }
+            entries_processed += entries.len();
+            let entries_per_manifest = entries_per_manifest(&manifests, target_size);
+            let mut entries: Vec<(String, ManifestEntry)> = entries
+                .into_iter()
+                .map(|mut entry| {
+                    entry.status = ManifestStatus::Existing;
+                    (spec.partition_path(&entry.data_file.partition), entry)
+                })
+                .collect();
+            entries.sort_by(|(left, _), (right, _)| left.cmp(right));
+            let mut entries = entries.into_iter().map(|(_, entry)| entry).peekable();
+            while entries.peek().is_some() {
+                let chunk = entries.by_ref().take(entries_per_manifest).collect();
+                added_manifests.push(producer.write_manifest(spec, chunk)?);
+            }
+            rewritten_manifests.extend(manifests);
+        }
+        if let Some(progress) = &self.progress {
+            progress.finish(phase);
+        }
+        if rewritten_manifests.is_empty() {
+            return Ok(RewriteManifestsResult {
+                table: self.table.clone(),
+                rewritten_manifests,
+                added_manifests,
+            });
+        }
+
+        let summary = HashMap::from([
+            (
+                "manifests-created".to_string(),
+                added_manifests.len().to_string(),
+            ),
+            (
+                "manifests-replaced".to_string(),
+                rewritten_manifests.len().to_string(),
+            ),
+            ("manifests-kept".to_string(), kept.len().to_string()),
+            (
+                "entries-processed".to_string(),
+                entries_processed.to_string(),
+            ),
+        ]);
+        let manifests: Vec<ManifestListV2> = added_manifests.iter().cloned().chain(kept).collect();
+        producer.set_summary(summary);
+        let table = producer.commit(catalog, Operation::Replace, &manifests)?;
+        report_commit(self.table, &table, 1, start);
+        let table = refresh_partition_statistics(self.table, table, catalog)?;
+
+        if let Some(auditor) = &self.auditor {
+            auditor.record(&[AuditRecord::new(
+                AuditedOperation::RewriteManifests,
+                table.ident(),
+            )
+            .with_snapshot_id(table.metadata().current_snapshot_id)
+            .with_details(&format!(
+                "rewrote {} manifests into {}",
+                rewritten_manifests.len(),
+                added_manifests.len()
+            ))])?;
+        }
+        Ok(RewriteManifestsResult {
+            table,
+            rewritten_manifests,
+            added_manifests,
+        })
+    }
+}
+
+// Number of entries fitting in a manifest of the target size, estimated from the average size
+// of the entries of the rewritten manifests
+fn entries_per_manifest(manifests: &[ManifestListV2], target_size: i64) -> usize {
+    let bytes: i64 = manifests
+        .iter()
+        .map(|manifest| manifest.manifest_length)
+        .sum();
+    let entries: i64 = manifests
+        .iter()
+        .map(|manifest| {
+            (manifest.added_files_count
+                + manifest.existing_files_count
+                + manifest.deleted_files_count) as i64
+        })
+        .sum();
+    let entry_size = (bytes / entries.max(1)).max(1);
+    (target_size / entry_size).max(1) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::progress::tests::RecordingReporter;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+
+    fn scan_count(table: &Table) -> usize {
+        table
+            .scan()
+            .plan_files()
+            .unwrap()
+            .to_arrow()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum()
+    }
+
+    #[test]
+    fn test_rewrite_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let mut table = catalog.create_table("db", "t", dir.path());
+        for ids in [[1, 2], [3, 4], [5, 6], [7, 8]] {
+            table = append_ids(&catalog, &table, &ids);
+        }
+        let before = table.scan().plan_files().unwrap();
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        assert_eq!(4, table.manifests(snapshot).unwrap().len());
+
+        let progress = Arc::new(RecordingReporter::default());
+        let result = table
+            .rewrite_manifests()
+            .with_progress(progress.clone())
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(4, result.rewritten_manifests.len());
+        assert_eq!(Some(4), progress.total(ProgressPhase::RewritingManifests));
+        assert_eq!(4, progress.units(ProgressPhase::RewritingManifests));
+        assert_eq!(1, result.added_manifests.len());
+        let table = result.table;
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        assert_eq!(Operation::Replace, snapshot.summary.operation);
+        assert_eq!("4", snapshot.summary.rest["entries-processed"]);
+        let manifests = table.manifests(snapshot).unwrap();
+        assert_eq!(1, manifests.len());
+        assert_eq!(4, manifests[0].existing_files_count);
+
+        // Same files with the same sequence numbers
+        let mut before = before.tasks().to_vec();
+        let mut after = table.scan().plan_files().unwrap().tasks().to_vec();
+        before.sort_by(|a, b| a.data_file.file_path.cmp(&b.data_file.file_path));
+        after.sort_by(|a, b| a.data_file.file_path.cmp(&b.data_file.file_path));
+        assert_eq!(before, after);
+        assert_eq!(8, scan_count(&table));
+
+        // A single manifest is left alone
+        let result = table.rewrite_manifests().commit(&catalog).unwrap();
+        assert!(result.added_manifests.is_empty());
+        assert_eq!(table.metadata_location(), result.table.metadata_location());
+    }
+
+    #[test]
+    fn test_rewrite_manifests_target_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let mut table = catalog.create_table("db", "t", dir.path());
+        for ids in [[1], [2], [3], [4]] {
+            table = append_ids(&catalog, &table, &ids);
+        }
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        let manifests = table.manifests(snapshot).unwrap();
+        let target_size = manifests[0].manifest_length * 2 + 1;
+
+        let result = table
+            .rewrite_manifests()
+            .with_target_size(target_size)
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(2, result.added_manifests.len());
+        assert_eq!(4, scan_count(&result.table));
+    }
+}
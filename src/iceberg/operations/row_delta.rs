@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::expr::Predicate;
+use crate::iceberg::operations::isolation::{ConflictDetection, IsolationLevel};
+use crate::iceberg::operations::{
+    any_counted, check_default_spec, commit_with_retries, snapshots_since, SnapshotProducer,
+};
+use crate::iceberg::spec::manifest::{DataContentType, DataFile};
+use crate::iceberg::spec::snapshot::Operation;
+use crate::iceberg::spec::snapshot_summary::{ADDED_DATA_FILES, DELETED_DATA_FILES, WAP_ID};
+use crate::iceberg::table::Table;
+
+// Commits row-level changes: delete files removing rows from existing data files, and data
+// files with new rows (e.g. the new versions of updated rows). Delete files apply to the data
+// files of earlier snapshots only. The snapshot is a delete when it only adds delete files, an
+// append when it only adds data files and an overwrite otherwise
+pub struct RowDelta<'a> {
+    table: &'a Table,
+    data_files: Vec<DataFile>,
+    delete_files: Vec<DataFile>,
+    summary: HashMap<String, String>,
+    stage_only: bool,
+    conflict_detection: ConflictDetection,
+}
+
+impl<'a> RowDelta<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        RowDelta {
+            table,
+            data_files: vec![],
+            delete_files: vec![],
+            summary: HashMap::new(),
+            stage_only: false,
+            conflict_detection: ConflictDetection::default(),
+        }
+    }
+
+    pub fn add_rows(mut self, data_file: DataFile) -> Self {
+        self.data_files.push(data_file);
+        self
+    }
+
+    // Adds a position or equality delete file
+    pub fn add_deletes(mut self, delete_file: DataFile) -> Self {
+        self.delete_files.push(delete_file);
+        self
+    }
+
+    // Fails the commit when files matching the conflict detection filter were changed since the
+    // snapshot the operation was planned from, as allowed by the isolation level, see
+    // IsolationLevel. Validation is enabled by setting the level or the filter, which matches
+    // all rows unless given
+    pub fn with_isolation_level(mut self, isolation: IsolationLevel) -> Self {
+        self.conflict_detection.set_isolation(isolation);
+        self
+    }
+
+    pub fn with_conflict_detection_filter(mut self, filter: Predicate) -> Self {
+        self.conflict_detection.set_filter(filter);
+        self
+    }
+
+    // Validates the commits made since the given snapshot instead of the current snapshot of the
+    // table, e.g. the snapshot the rows to change were read from
+    pub fn validate_from_snapshot(mut self, snapshot_id: i64) -> Self {
+        self.conflict_detection.set_from_snapshot(snapshot_id);
+        self
+    }
+
+    // Adds a property to the summary of the new snapshot
+    pub fn set_summary(mut self, key: &str, value: &str) -> Self {
+        self.summary.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    // Commits the snapshot without making it current, with the write id in its wap.id summary
+    // property. The staged snapshot can be audited, e.g. scanned by id, before being published
+    // with Table::publish_wap_id
+    pub fn stage(mut self, wap_id: &str) -> Self {
+        self.summary.insert(WAP_ID.to_string(), wap_id.to_string());
+        self.stage_only = true;
+        self
+    }
+
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<Table> {
+        let spec = self.table.metadata().default_partition_spec()?;
+        for file in self.data_files.iter().chain(&self.delete_files) {
+            if file.partition.len() != spec.fields.len() {
+                return Err(IcebergError::Invalid(format!(
+                    "File {} has {} partition values but partition spec {} has {} fields",
+                    file.file_path,
+                    file.partition.len(),
+                    spec.spec_id,
+                    spec.fields.len()
+                )));
+            }
+        }
+        for data_file in &self.data_files {
+            if data_file.content != DataContentType::Data {
+                return Err(IcebergError::Invalid(format!(
+                    "Delete file {} was added as a data file",
+                    data_file.file_path
+                )));
+            }
+        }
+        for delete_file in &self.delete_files {
+            match delete_file.content {
+                DataContentType::Data => {
+                    return Err(IcebergError::Invalid(format!(
+                        "Data file {} was added as a delete file",
+                        delete_file.file_path
+                    )))
+                }
+                DataContentType::EqualityDeletes
+                    if delete_file
+                        .equality_ids
+                        .as_ref()
+                        .is_none_or(|ids| ids.is_empty()) =>
+                {
+                    return Err(IcebergError::Invalid(format!(
+                        "Equality delete file {} has no equality ids",
+                        delete_file.file_path
+                    )))
+                }
+                _ => {}
+            }
+        }
+
+        let operation = match (self.data_files.is_empty(), self.delete_files.is_empty()) {
+            (_, true) => Operation::Append,
+            (true, false) => Operation::Delete,
+            (false, false) => Operation::Overwrite,
+        };
+        commit_with_retries(catalog, self.table, |table| {
+            check_default_spec(table, spec)?;
+            self.check_concurrent_commits(table)?;
+            let mut producer = SnapshotProducer::new(table);
+            producer.set_summary(self.summary.clone());
+            if self.stage_only {
+                producer.stage_only();
+            }
+            let mut manifests = vec![];
+            // Manifests hold either data files or delete files
+            for files in [&self.data_files, &self.delete_files] {
+                if !files.is_empty() {
+                    manifests.push(producer.add_files(spec, files.clone())?);
+                }
+            }
+            manifests.extend(producer.current_manifests()?);
+            producer.commit(catalog, operation.clone(), &manifests)
+        })
+    }
+
+    // Deletes were written against the base table. Rebased on concurrent commits, position
+    // deletes would miss the rows of the data files those commits rewrote, and equality deletes
+    // would apply to the rows those commits added. Conflict detection, when set, tells which of
+    // those changes conflict instead
+    fn check_concurrent_commits(&self, table: &Table) -> Result<()> {
+        if self.conflict_detection.is_enabled() {
+            return self.conflict_detection.validate(table, self.table);
+        }
+        let concurrent = snapshots_since(table, self.table);
+        let equality_deletes = self
+            .delete_files
+            .iter()
+            .any(|file| file.content == DataContentType::EqualityDeletes);
+        if (!self.delete_files.is_empty() && any_counted(&concurrent, DELETED_DATA_FILES))
+            || (equality_deletes && any_counted(&concurrent, ADDED_DATA_FILES))
+        {
+            return Err(IcebergError::Invalid(format!(
+                "Deletes conflict with the data files changed by concurrent commits to table {}",
+                table.ident()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::iceberg::spec::snapshot::Operation;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+    use crate::iceberg::writer::position_delete::PositionDeleteWriter;
+
+    #[test]
+    fn test_row_delta_commits_position_deletes() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1, 2, 3]);
+        let data_file = table.scan().plan_files().unwrap().tasks()[0]
+            .data_file
+            .clone();
+
+        let mut writer = PositionDeleteWriter::for_table(&table, vec![]).unwrap();
+        writer.delete(&data_file.file_path, 0);
+        writer.delete(&data_file.file_path, 2);
+        let delete_file = writer.close().unwrap();
+        assert!(table
+            .new_row_delta()
+            .add_rows(delete_file.clone())
+            .commit(&catalog)
+            .is_err());
+        assert!(table
+            .new_row_delta()
+            .add_deletes(data_file)
+            .commit(&catalog)
+            .is_err());
+
+        let table = table
+            .new_row_delta()
+            .add_deletes(delete_file)
+            .commit(&catalog)
+            .unwrap();
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        assert_eq!(Operation::Delete, snapshot.summary.operation);
+        assert_eq!(
+            Some("2"),
+            snapshot
+                .summary
+                .rest
+                .get("added-position-deletes")
+                .map(String::as_str)
+        );
+        let rows: usize = table
+            .scan()
+            .plan_files()
+            .unwrap()
+            .to_arrow()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum();
+        assert_eq!(1, rows);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::operations::{commit_time_ms, current_time_ms};
+use crate::iceberg::spec::partition_spec::Transform;
+use crate::iceberg::spec::sort_orders::{
+    Direction, NullOrder, SortField, SortOrders, UNSORTED_ORDER_ID,
+};
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+use crate::iceberg::table::Table;
+
+// Changes the default sort order of a table, either to a new order built from columns of the
+// current schema or to an existing order. New orders reuse the id of an existing order with the
+// same fields, sorting by no field is the unsorted order. Orders are validated against the current
+// schema, see SortOrders::validate
+pub struct UpdateSortOrder<'a> {
+    table: &'a Table,
+    // (column, transform, direction, null order) of the fields of a new order
+    fields: Vec<(String, Transform, Direction, NullOrder)>,
+    order_id: Option<i32>,
+}
+
+impl<'a> UpdateSortOrder<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        UpdateSortOrder {
+            table,
+            fields: vec![],
+            order_id: None,
+        }
+    }
+
+    // Appends a field to the new order, sorting a top-level column by its transformed values
+    pub fn sort_by(
+        mut self,
+        column: &str,
+        transform: Transform,
+        direction: Direction,
+        null_order: NullOrder,
+    ) -> Self {
+        self.fields
+            .push((column.to_string(), transform, direction, null_order));
+        self
+    }
+
+    pub fn asc(self, column: &str, null_order: NullOrder) -> Self {
+        self.sort_by(column, Transform::Identity, Direction::Asc, null_order)
+    }
+
+    pub fn desc(self, column: &str, null_order: NullOrder) -> Self {
+        self.sort_by(column, Transform::Identity, Direction::Desc, null_order)
+    }
+
+    // Switches to an existing order of the table instead of a new one
+    pub fn set_default(mut self, order_id: i32) -> Self {
+        self.order_id = Some(order_id);
+        self
+    }
+
+    // Returns the metadata with the changes applied, without committing it
+    pub fn apply(&self) -> Result<TableMetadataV2> {
+        let base = self.table.metadata();
+        self.apply_at(current_time_ms().max(base.last_updated_ms))
+    }
+
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<Table> {
+        let metadata = self.apply_at(commit_time_ms(catalog, self.table.metadata())?)?;
+        catalog.commit_table(self.table, metadata)
+    }
+
+    fn apply_at(&self, timestamp_ms: i64) -> Result<TableMetadataV2> {
+        let mut metadata = self.table.metadata().clone();
+        let schema = metadata.current_schema()?.schema();
+        let order = match self.order_id {
+            Some(_) if !self.fields.is_empty() => {
+                return Err(IcebergError::Invalid(
+                    "A sort order update either sorts by columns or sets an existing order"
+                        .to_string(),
+                ))
+            }
+            Some(order_id) => metadata
+                .sort_orders
+                .iter()
+                .find(|order| order.order_id == order_id)
+                .cloned()
+                .ok_or_else(|| IcebergError::NotFound(format!("Sort order {}", order_id)))?,
+            None => {
+                let fields = self
+                    .fields
+                    .iter()
+                    .map(|(column, transform, direction, null_order)| {
+                        let field = schema.field_by_name(column).ok_or_else(|| {
+                            IcebergError::NotFound(format!(
+                                "Column {} in table {}",
+                                column,
+                                self.table.ident()
+                            ))
+                        })?;
+                        Ok(SortField {
+                            transform: transform.clone(),
+                            source_id: field.id,
+                            source_ids: vec![],
+                            direction: direction.clone(),
+                            null_order: null_order.clone(),
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let orders = &metadata.sort_orders;
+                let order_id = match orders.iter().find(|order| order.fields == fields) {
+                    Some(existing) => existing.order_id,
+                    None if fields.is_empty() => UNSORTED_ORDER_ID,
+                    None => {
+                        let last = orders.iter().map(|order| order.order_id).max();
+                        last.unwrap_or(UNSORTED_ORDER_ID) + 1
+                    }
+                };
+                SortOrders { order_id, fields }
+            }
+        };
+        order.validate(schema)?;
+        if !metadata.sort_orders.contains(&order) {
+            metadata.sort_orders.push(order.clone());
+        }
+        metadata.default_sort_order_id = order.order_id;
+        metadata.last_updated_ms = timestamp_ms;
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::test_utils::TestCatalog;
+
+    #[test]
+    fn test_update_sort_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        assert_eq!(0, table.metadata().default_sort_order_id);
+
+        let table = table
+            .update_sort_order()
+            .desc("id", NullOrder::NullsLast)
+            .sort_by(
+                "data",
+                Transform::Truncate(4),
+                Direction::Asc,
+                NullOrder::NullsFirst,
+            )
+            .commit(&catalog)
+            .unwrap();
+        let metadata = table.metadata();
+        assert_eq!(1, metadata.default_sort_order_id);
+        let order = &metadata.sort_orders[1];
+        assert_eq!(
+            vec![1, 2],
+            order.fields.iter().map(|f| f.source_id).collect::<Vec<_>>()
+        );
+        assert_eq!(Direction::Desc, order.fields[0].direction);
+
+        // An identical order is reused, a different one gets the next id
+        let same = table
+            .update_sort_order()
+            .desc("id", NullOrder::NullsLast)
+            .sort_by(
+                "data",
+                Transform::Truncate(4),
+                Direction::Asc,
+                NullOrder::NullsFirst,
+            )
+            .apply()
+            .unwrap();
+        assert_eq!(2, same.sort_orders.len());
+        let table = table
+            .update_sort_order()
+            .asc("id", NullOrder::NullsFirst)
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(2, table.metadata().default_sort_order_id);
+        assert_eq!(3, table.metadata().sort_orders.len());
+
+        // Switching back to an existing order, then to unsorted
+        let table = table
+            .update_sort_order()
+            .set_default(1)
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(1, table.metadata().default_sort_order_id);
+        let table = table.update_sort_order().commit(&catalog).unwrap();
+        assert_eq!(UNSORTED_ORDER_ID, table.metadata().default_sort_order_id);
+        assert_eq!(3, table.metadata().sort_orders.len());
+
+        let invalid = [
+            table.update_sort_order().set_default(7),
+            table
+                .update_sort_order()
+                .asc("missing", NullOrder::NullsFirst),
+            table.update_sort_order().sort_by(
+                "id",
+                Transform::Bucket(8),
+                Direction::Asc,
+                NullOrder::NullsFirst,
+            ),
+            table.update_sort_order().sort_by(
+                "data",
+                Transform::Day,
+                Direction::Asc,
+                NullOrder::NullsFirst,
+            ),
+            table
+                .update_sort_order()
+                .asc("id", NullOrder::NullsFirst)
+                .set_default(1),
+        ];
+        for update in invalid {
+            assert!(update.apply().is_err());
+        }
+    }
+}
@@ -0,0 +1,552 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bytes::Bytes;
+use uuid::Uuid;
+
+use crate::iceberg::arrow::literal_from_array;
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::operations::commit_time_ms;
+use crate::iceberg::puffin::theta::{ThetaSketch, NDV_PROPERTY, THETA_SKETCH_BLOB_TYPE};
+use crate::iceberg::puffin::{Blob, PuffinWriter};
+use crate::iceberg::spec::manifest::DataFile;
+use crate::iceberg::spec::partition_statistics::{
+    read_partition_statistics, write_partition_statistics, PartitionStatistics,
+};
+use crate::iceberg::spec::schema::{IcebergType, PrimitiveType, StructType};
+use crate::iceberg::spec::snapshot::SnapshotV2;
+use crate::iceberg::spec::table_metadata::{
+    BlobMetadata, PartitionStatisticsFile, StatisticsFile, TableMetadataV2,
+};
+use crate::iceberg::spec::values::Literal;
+use crate::iceberg::table::Table;
+
+// Sets and removes the statistics files of snapshots. Setting the statistics of a snapshot
+// replaces its previous statistics file
+pub struct UpdateStatistics<'a> {
+    table: &'a Table,
+    set: Vec<StatisticsFile>,
+    removed: Vec<i64>,
+}
+
+// Computes the number of distinct values of columns of the current snapshot with theta sketches,
+// writes them to a Puffin file and sets it as the statistics file of the snapshot
+pub struct ComputeTableStatistics<'a> {
+    table: &'a Table,
+    columns: Option<Vec<String>>,
+}
+
+// Sets and removes the partition statistics files of snapshots, like UpdateStatistics
+pub struct UpdatePartitionStatistics<'a> {
+    table: &'a Table,
+    set: Vec<PartitionStatisticsFile>,
+    removed: Vec<i64>,
+}
+
+// Computes the record and file counts of the partitions of the current snapshot from its
+// manifests, writes them to a partition statistics file and sets it for the snapshot
+pub struct ComputePartitionStatistics<'a> {
+    table: &'a Table,
+}
+
+impl<'a> UpdateStatistics<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        UpdateStatistics {
+            table,
+            set: vec![],
+            removed: vec![],
+        }
+    }
+
+    pub fn set_statistics(mut self, statistics: StatisticsFile) -> Self {
+        self.set.push(statistics);
+        self
+    }
+
+    pub fn remove_statistics(mut self, snapshot_id: i64) -> Self {
+        self.removed.push(snapshot_id);
+        self
+    }
+
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<Table> {
+        let base = self.table.metadata();
+        if let Some(statistics) = self
+            .set
+            .iter()
+            .find(|statistics| base.snapshot_by_id(statistics.snapshot_id).is_none())
+        {
+            return Err(IcebergError::NotFound(format!(
+                "Snapshot {} of statistics file {} in table {}",
+                statistics.snapshot_id,
+                statistics.statistics_path,
+                self.table.ident()
+            )));
+        }
+        let mut metadata = base.clone();
+        metadata.last_updated_ms = commit_time_ms(catalog, base)?;
+        let files = metadata.statistics.get_or_insert_with(Vec::new);
+        files.retain(|file| {
+            !self.removed.contains(&file.snapshot_id)
+                && !self
+                    .set
+                    .iter()
+                    .any(|set| set.snapshot_id == file.snapshot_id)
+        });
+        files.extend(self.set);
+        catalog.commit_table(self.table, metadata)
+    }
+}
+
+impl<'a> ComputeTableStatistics<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        ComputeTableStatistics {
+            table,
+            columns: None,
+        }
+    }
+
+    // Top-level primitive columns to compute statistics for, all of them by default
+    pub fn with_columns(mut self, columns: &[&str]) -> Self {
+        self.columns = Some(columns.iter().map(|column| column.to_string()).collect());
+        self
+    }
+
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<Table> {
+        let metadata = self.table.metadata();
+        let snapshot = metadata.current_snapshot().ok_or_else(|| {
+            IcebergError::Invalid(format!(
+                "Table {} has no snapshot to compute statistics for",
+                self.table.ident()
+            ))
+        })?;
+        let schema = metadata.current_schema()?.schema();
+        let mut columns: Vec<(String, i32, PrimitiveType, ThetaSketch)> = vec![];
+        for field in &schema.fields {
+            let selected = self
+                .columns
+                .as_ref()
+                .is_none_or(|columns| columns.contains(&field.name));
+            match &field.field_type {
+                IcebergType::Primitive(primitive) if selected => columns.push((
+                    field.name.clone(),
+                    field.id,
+                    primitive.clone(),
+                    ThetaSketch::new(),
+                )),
+                _ if selected && self.columns.is_some() => {
+                    return Err(IcebergError::Unsupported(format!(
+                        "Statistics of column {} of type {:?}",
+                        field.name, field.field_type
+                    )))
+                }
+                _ => {}
+            }
+        }
+        if let Some(missing) = self
+            .columns
+            .iter()
+            .flatten()
+            .find(|column| !columns.iter().any(|(name, ..)| name == *column))
+        {
+            return Err(IcebergError::NotFound(format!(
+                "Column {} in table {}",
+                missing,
+                self.table.ident()
+            )));
+        }
+
+        let names: Vec<&str> = columns.iter().map(|(name, ..)| name.as_str()).collect();
+        let plan = self
+            .table
+            .scan()
+            .with_snapshot_id(snapshot.snapshot_id)
+            .select(&names)
+            .plan_files()?;
+        for batch in plan.to_arrow()? {
+            let batch = batch?;
+            for (index, (_, _, primitive, sketch)) in columns.iter_mut().enumerate() {
+                let array = batch.column(index);
+                for row in 0..batch.num_rows() {
+                    if let Some(value) = literal_from_array(array.as_ref(), row, primitive)? {
+                        sketch.update(&value.to_bytes());
+                    }
+                }
+            }
+        }
+
+        let mut writer = PuffinWriter::new();
+        for (_, field_id, _, sketch) in columns {
+            writer.add_blob(Blob {
+                blob_type: THETA_SKETCH_BLOB_TYPE.to_string(),
+                fields: vec![field_id],
+                snapshot_id: snapshot.snapshot_id,
+                sequence_number: snapshot.sequence_number,
+                properties: HashMap::from([(
+                    NDV_PROPERTY.to_string(),
+                    (sketch.estimate().round() as u64).to_string(),
+                )]),
+                data: sketch.to_bytes(),
+            });
+        }
+        let file = writer.finish()?;
+        let location = format!(
+            "{}/metadata/{}-{}.stats",
+            metadata.location.trim_end_matches('/'),
+            snapshot.snapshot_id,
+            Uuid::new_v4()
+        );
+        let file_size = file.data.len() as i64;
+        self.table
+            .file_io()
+            .write(&location, Bytes::from(file.data))?;
+
+        let statistics = StatisticsFile {
+            snapshot_id: snapshot.snapshot_id,
+            statistics_path: location,
+            file_size_in_bytes: file_size,
+            file_footer_size_in_bytes: file.footer_size,
+            key_metadata: None,
+            blob_metadata: file
+                .metadata
+                .blobs
+                .into_iter()
+                .map(|blob| BlobMetadata {
+                    blob_type: blob.blob_type,
+                    snapshot_id: blob.snapshot_id,
+                    sequence_number: blob.sequence_number,
+                    fields: blob.fields,
+                    properties: Some(blob.properties),
+                })
+                .collect(),
+        };
+        self.table
+            .update_statistics()
+            .set_statistics(statistics)
+            .commit(catalog)
+    }
+}
+
+impl<'a> UpdatePartitionStatistics<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        UpdatePartitionStatistics {
+            table,
+            set: vec![],
+            removed: vec![],
+        }
+    }
+
+    pub fn set_partition_statistics(mut self, statistics: PartitionStatisticsFile) -> Self {
+        self.set.push(statistics);
+        self
+    }
+
+    pub fn remove_partition_statistics(mut self, snapshot_id: i64) -> Self {
+        self.removed.push(snapshot_id);
+        self
+    }
+
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<Table> {
+        let base = self.table.metadata();
+        if let Some(statistics) = self
+            .set
+            .iter()
+            .find(|statistics| base.snapshot_by_id(statistics.snapshot_id).is_none())
+        {
+            return Err(IcebergError::NotFound(format!(
+                "Snapshot {} of partition statistics file {} in table {}",
+                statistics.snapshot_id,
+                statistics.statistics_path,
+                self.table.ident()
+            )));
+        }
+        let mut metadata = base.clone();
+        metadata.last_updated_ms = commit_time_ms(catalog, base)?;
+        let files = metadata.partition_statistics.get_or_insert_with(Vec::new);
+        files.retain(|file| {
+            !self.removed.contains(&file.snapshot_id)
+                && !self
+                    .set
+                    .iter()
+                    .any(|set| set.snapshot_id == file.snapshot_id)
+        });
+        files.extend(self.set);
+        catalog.commit_table(self.table, metadata)
+    }
+}
+
+impl<'a> ComputePartitionStatistics<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        ComputePartitionStatistics { table }
+    }
+
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<Table> {
+        let metadata = self.table.metadata();
+        let snapshot = metadata.current_snapshot().ok_or_else(|| {
+            IcebergError::Invalid(format!(
+                "Table {} has no snapshot to compute partition statistics for",
+                self.table.ident()
+            ))
+        })?;
+        let statistics = partition_statistics(self.table, snapshot)?;
+        let data = write_partition_statistics(&statistics, &metadata.unified_partition_type()?)?;
+        let location = format!(
+            "{}/metadata/partition-stats-{}-{}.parquet",
+            metadata.location.trim_end_matches('/'),
+            snapshot.snapshot_id,
+            Uuid::new_v4()
+        );
+        let file_size = data.len() as i64;
+        self.table.file_io().write(&location, Bytes::from(data))?;
+        self.table
+            .update_partition_statistics()
+            .set_partition_statistics(PartitionStatisticsFile {
+                snapshot_id: snapshot.snapshot_id,
+                statistics_path: location,
+                file_size_in_bytes: file_size,
+            })
+            .commit(catalog)
+    }
+}
+
+// Statistics of the partitions with live files in the snapshot, read from the partition
+// statistics file of the snapshot if there is one and computed from its manifests otherwise
+pub(crate) fn read_or_compute_partition_statistics(
+    table: &Table,
+    snapshot: &SnapshotV2,
+) -> Result<Vec<PartitionStatistics>> {
+    let metadata = table.metadata();
+    match metadata.partition_statistics_file(snapshot.snapshot_id) {
+        Some(file) => read_partition_statistics(
+            table.file_io().read(&file.statistics_path)?,
+            &metadata.unified_partition_type()?,
+        ),
+        None => partition_statistics(table, snapshot),
+    }
+}
+
+fn partition_statistics(table: &Table, snapshot: &SnapshotV2) -> Result<Vec<PartitionStatistics>> {
+    let metadata = table.metadata();
+    let partition_type = metadata.unified_partition_type()?;
+    // Keyed by the binary serialization of the partition values
+    let mut partitions: BTreeMap<Vec<Option<Vec<u8>>>, PartitionStatistics> = BTreeMap::new();
+    for manifest in table.manifests(snapshot)? {
+        let entries = table.manifest_entries(&manifest)?;
+        let spec_id = manifest.partition_spec_id;
+        let positions = unified_positions(metadata, &partition_type, spec_id);
+        for entry in entries.iter().filter(|entry| entry.is_live()) {
+            let partition = unified_partition(&positions, &partition_type, &entry.data_file);
+            let key = partition
+                .iter()
+                .map(|value| value.as_ref().map(|value| value.to_bytes()))
+                .collect();
+            let committed_at = entry
+                .snapshot_id
+                .and_then(|snapshot_id| metadata.snapshot_by_id(snapshot_id))
+                .map(|snapshot| snapshot.timestamp_ms);
+            partitions
+                .entry(key)
+                .or_insert_with(|| PartitionStatistics::new(partition, spec_id))
+                .add(entry, spec_id, committed_at);
+        }
+    }
+    Ok(partitions.into_values().collect())
+}
+
+// Positions in the unified partition type of the table of the fields of a partition spec.
+// manifest_entries checks that the specs of the entries it returns exist
+pub(crate) fn unified_positions(
+    metadata: &TableMetadataV2,
+    partition_type: &StructType,
+    spec_id: i32,
+) -> Vec<usize> {
+    metadata
+        .partition_spec_by_id(spec_id)
+        .iter()
+        .flat_map(|spec| &spec.fields)
+        .filter_map(|field| {
+            partition_type
+                .fields
+                .iter()
+                .position(|unified| unified.id == field.field_id)
+        })
+        .collect()
+}
+
+// Partition of a file as a tuple of the unified partition type, see unified_positions
+pub(crate) fn unified_partition(
+    positions: &[usize],
+    partition_type: &StructType,
+    file: &DataFile,
+) -> Vec<Option<Literal>> {
+    let mut partition = vec![None; partition_type.fields.len()];
+    for (position, value) in positions.iter().zip(&file.partition) {
+        partition[*position] = value.clone();
+    }
+    partition
+}
+
+// Keeps the partition statistics of a table up to date across a maintenance action: when the
+// snapshot the action started from had a partition statistics file, statistics are computed for
+// the snapshot the action committed
+pub(crate) fn refresh_partition_statistics(
+    base: &Table,
+    table: Table,
+    catalog: &dyn IcebergCatalog,
+) -> Result<Table> {
+    let tracked = base
+        .metadata()
+        .current_snapshot_id
+        .and_then(|snapshot_id| base.metadata().partition_statistics_file(snapshot_id))
+        .is_some();
+    if !tracked || table.metadata().current_snapshot_id == base.metadata().current_snapshot_id {
+        return Ok(table);
+    }
+    table.compute_partition_statistics().commit(catalog)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::catalog::IcebergCatalog;
+    use crate::iceberg::puffin::PuffinReader;
+    use crate::iceberg::spec::partition_spec::{PartitionField, PartitionSpec, Transform};
+    use crate::iceberg::spec::values::Literal;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+
+    #[test]
+    fn test_compute_statistics() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        assert!(table.compute_statistics().commit(&catalog).is_err());
+        let table = append_ids(&catalog, &table, &[1, 2, 3]);
+        let table = append_ids(&catalog, &table, &[3, 4]);
+
+        let table = table.compute_statistics().commit(&catalog).unwrap();
+        let snapshot_id = table.metadata().current_snapshot_id.unwrap();
+        let statistics = table.metadata().statistics_file(snapshot_id).unwrap();
+        let blobs = &statistics.blob_metadata;
+        assert_eq!(2, blobs.len());
+        assert_eq!(THETA_SKETCH_BLOB_TYPE, blobs[0].blob_type);
+        assert_eq!(vec![1], blobs[0].fields);
+        assert_eq!("4", blobs[0].properties.as_ref().unwrap()[NDV_PROPERTY]);
+
+        let data = table.file_io().read(&statistics.statistics_path).unwrap();
+        assert_eq!(statistics.file_size_in_bytes, data.len() as i64);
+        let reader = PuffinReader::try_new(data).unwrap();
+        let blob = &reader.metadata().blobs[1];
+        assert_eq!(vec![2], blob.fields);
+        let sketch = ThetaSketch::try_from_bytes(&reader.blob(blob).unwrap()).unwrap();
+        assert_eq!(4.0, sketch.estimate());
+
+        // Computing again replaces the statistics file of the snapshot
+        let table = table
+            .compute_statistics()
+            .with_columns(&["id"])
+            .commit(&catalog)
+            .unwrap();
+        let files = table.metadata().statistics.as_ref().unwrap();
+        assert_eq!(1, files.len());
+        assert_eq!(1, files[0].blob_metadata.len());
+        assert!(table
+            .compute_statistics()
+            .with_columns(&["missing"])
+            .commit(&catalog)
+            .is_err());
+
+        let table = table
+            .update_statistics()
+            .remove_statistics(snapshot_id)
+            .commit(&catalog)
+            .unwrap();
+        assert!(table.metadata().statistics_file(snapshot_id).is_none());
+    }
+
+    // Partition, spec id, record count and file count of partition statistics
+    fn counts(statistics: &[PartitionStatistics]) -> Vec<(Vec<Option<Literal>>, i32, i64, i32)> {
+        statistics
+            .iter()
+            .map(|stats| {
+                (
+                    stats.partition.clone(),
+                    stats.spec_id,
+                    stats.data_record_count,
+                    stats.data_file_count,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_partition_statistics() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1, 2]);
+        let mut metadata = table.metadata().clone();
+        metadata.partition_specs.push(PartitionSpec {
+            spec_id: 1,
+            fields: vec![PartitionField {
+                source_id: 2,
+                source_ids: vec![],
+                field_id: 1000,
+                name: "data".to_string(),
+                transform: Transform::Identity,
+            }],
+        });
+        metadata.default_spec_id = 1;
+        let table = catalog.commit_table(&table, metadata).unwrap();
+        let table = append_ids(&catalog, &table, &[3, 4]);
+        let table = append_ids(&catalog, &table, &[3]);
+
+        let computed = table.partition_statistics().unwrap();
+        let counts = counts(&computed);
+        let partition = |data: &str| vec![Some(Literal::String(data.to_string()))];
+        assert_eq!(
+            vec![
+                (vec![None], 0, 2, 1),
+                (partition("row-3"), 1, 2, 2),
+                (partition("row-4"), 1, 1, 1),
+            ],
+            counts
+        );
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        assert_eq!(
+            Some(snapshot.snapshot_id),
+            computed[1].last_updated_snapshot_id
+        );
+        assert_eq!(Some(snapshot.timestamp_ms), computed[1].last_updated_at);
+
+        let table = table
+            .compute_partition_statistics()
+            .commit(&catalog)
+            .unwrap();
+        let snapshot_id = table.metadata().current_snapshot_id.unwrap();
+        let file = table
+            .metadata()
+            .partition_statistics_file(snapshot_id)
+            .unwrap();
+        let data = table.file_io().read(&file.statistics_path).unwrap();
+        assert_eq!(file.file_size_in_bytes, data.len() as i64);
+        assert_eq!(computed, table.partition_statistics().unwrap());
+
+        // Maintenance actions compute the statistics of the snapshots they commit
+        let table = table.rewrite_manifests().commit(&catalog).unwrap().table;
+        let snapshot_id = table.metadata().current_snapshot_id.unwrap();
+        assert!(table
+            .metadata()
+            .partition_statistics_file(snapshot_id)
+            .is_some());
+        assert_eq!(counts, self::counts(&table.partition_statistics().unwrap()));
+
+        let table = table
+            .update_partition_statistics()
+            .remove_partition_statistics(snapshot_id)
+            .commit(&catalog)
+            .unwrap();
+        assert!(table
+            .metadata()
+            .partition_statistics_file(snapshot_id)
+            .is_none());
+    }
+}
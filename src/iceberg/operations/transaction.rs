@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::operations::{commit_time_ms, commit_with_retries, current_time_ms};
+use crate::iceberg::properties::TableProperties;
+use crate::iceberg::spec::schema::{IcebergSchemaV2, IcebergType, StructField};
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+use crate::iceberg::table::Table;
+
+// Metadata changes of a table committed together as a single new metadata version. Changes are
+// applied in order when committing, and applied again to the current table when the commit
+// conflicts with a concurrent one
+pub struct Transaction<'a> {
+    table: &'a Table,
+    updates: Vec<TableUpdate>,
+}
+
+#[derive(Debug, Clone)]
+enum TableUpdate {
+    // Docs by column name, an empty doc removes the doc of its column
+    ColumnDocs(HashMap<String, String>),
+    // Properties to set and properties to remove
+    Properties(HashMap<String, String>, Vec<String>),
+}
+
+impl<'a> Transaction<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        Transaction {
+            table,
+            updates: vec![],
+        }
+    }
+
+    // Sets the docs of columns of the current schema as a new schema version. Nested struct
+    // fields are named by their dotted path, e.g. "address.city"
+    pub fn update_column_docs(mut self, docs: HashMap<String, String>) -> Self {
+        self.updates.push(TableUpdate::ColumnDocs(docs));
+        self
+    }
+
+    // Sets and removes table properties. Values of well-known properties are validated, see
+    // TableProperties
+    pub fn update_properties(
+        mut self,
+        updates: HashMap<String, String>,
+        removals: &[&str],
+    ) -> Self {
+        let removals = removals.iter().map(|key| key.to_string()).collect();
+        self.updates
+            .push(TableUpdate::Properties(updates, removals));
+        self
+    }
+
+    // Returns the metadata with the changes applied, without committing it
+    pub fn apply(&self) -> Result<TableMetadataV2> {
+        let base = self.table.metadata();
+        self.apply_at(self.table, current_time_ms().max(base.last_updated_ms))
+    }
+
+    pub fn commit(self, catalog: &dyn IcebergCatalog) -> Result<Table> {
+        commit_with_retries(catalog, self.table, |table| {
+            let metadata = self.apply_at(table, commit_time_ms(catalog, table.metadata())?)?;
+            catalog.commit_table(table, metadata)
+        })
+    }
+
+    fn apply_at(&self, table: &Table, timestamp_ms: i64) -> Result<TableMetadataV2> {
+        let mut metadata = table.metadata().clone();
+        metadata.last_updated_ms = timestamp_ms;
+        for update in &self.updates {
+            match update {
+                TableUpdate::ColumnDocs(docs) => {
+                    let mut schema = metadata.current_schema()?.clone();
+                    for (column, doc) in docs {
+                        let path: Vec<&str> = column.split('.').collect();
+                        let field = field_by_path(&mut schema.schema_mut().fields, &path)
+                            .ok_or_else(|| {
+                                IcebergError::NotFound(format!(
+                                    "Column {} in table {}",
+                                    column,
+                                    table.ident()
+                                ))
+                            })?;
+                        field.doc = Some(doc.clone()).filter(|doc| !doc.is_empty());
+                    }
+                    set_current_schema(&mut metadata, schema);
+                }
+                TableUpdate::Properties(updates, removals) => {
+                    if let Some(key) = removals.iter().find(|key| updates.contains_key(*key)) {
+                        return Err(IcebergError::Invalid(format!(
+                            "Property {} of table {} both set and removed",
+                            key,
+                            table.ident()
+                        )));
+                    }
+                    let mut properties = TableProperties::from_metadata(&metadata);
+                    for key in removals {
+                        properties.remove(key);
+                    }
+                    let mut properties = properties.into_map();
+                    properties.extend(updates.clone());
+                    // Checked once all are set, as some are checked against others
+                    let properties = TableProperties::from(properties);
+                    for key in updates.keys() {
+                        properties.validate_key(key)?;
+                    }
+                    metadata.properties = Some(properties.into_map());
+                }
+            }
+        }
+        Ok(metadata)
+    }
+}
+
+// Makes the schema current, reusing the id of an identical schema of the table if there is one
+fn set_current_schema(metadata: &mut TableMetadataV2, mut schema: IcebergSchemaV2) {
+    if let Some(existing) = metadata.schemas.iter().find(|existing| {
+        existing.schema() == schema.schema()
+            && existing.identifier_field_ids == schema.identifier_field_ids
+    }) {
+        metadata.current_schema_id = existing.schema_id;
+        return;
+    }
+    schema.schema_id = metadata
+        .schemas
+        .iter()
+        .map(|schema| schema.schema_id)
+        .max()
+        .map_or(0, |id| id + 1);
+    metadata.current_schema_id = schema.schema_id;
+    metadata.schemas.push(schema);
+}
+
+fn field_by_path<'a>(fields: &'a mut [StructField], path: &[&str]) -> Option<&'a mut StructField> {
+    let (name, rest) = path.split_first()?;
+    let field = fields.iter_mut().find(|field| field.name == *name)?;
+    if rest.is_empty() {
+        return Some(field);
+    }
+    match &mut field.field_type {
+        IcebergType::Struct(nested) => field_by_path(&mut nested.fields, rest),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::properties::{
+        COMMIT_MAX_RETRY_WAIT_MS_PROPERTY, COMMIT_MIN_RETRY_WAIT_MS_PROPERTY,
+        COMMIT_NUM_RETRIES_PROPERTY, TARGET_FILE_SIZE_PROPERTY,
+    };
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+
+    #[test]
+    fn test_update_column_docs() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1]);
+        let docs = |docs: &[(&str, &str)]| {
+            docs.iter()
+                .map(|(column, doc)| (column.to_string(), doc.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+
+        let table = table
+            .new_transaction()
+            .update_column_docs(docs(&[("id", "Row id"), ("data", "Payload")]))
+            .commit(&catalog)
+            .unwrap();
+        let metadata = table.metadata();
+        assert_eq!(1, metadata.current_schema_id);
+        assert_eq!(2, metadata.schemas.len());
+        let schema = metadata.current_schema().unwrap().schema();
+        assert_eq!(Some("Row id"), schema.fields[0].doc.as_deref());
+        assert_eq!(Some("Payload"), schema.fields[1].doc.as_deref());
+        // Data written with the previous schema is still read
+        assert_eq!(1, table.scan().plan_files().unwrap().tasks().len());
+
+        // Removing the docs goes back to the first schema
+        let table = table
+            .new_transaction()
+            .update_column_docs(docs(&[("id", ""), ("data", "")]))
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(0, table.metadata().current_schema_id);
+        assert_eq!(2, table.metadata().schemas.len());
+
+        assert!(table
+            .new_transaction()
+            .update_column_docs(docs(&[("missing", "doc")]))
+            .commit(&catalog)
+            .is_err());
+        assert!(table
+            .new_transaction()
+            .update_column_docs(docs(&[("id.nested", "doc")]))
+            .apply()
+            .is_err());
+    }
+
+    #[test]
+    fn test_update_properties() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let properties = |properties: &[(&str, &str)]| {
+            properties
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+
+        let table = table
+            .new_transaction()
+            .update_properties(
+                properties(&[(TARGET_FILE_SIZE_PROPERTY, "1024"), ("owner", "analytics")]),
+                &[],
+            )
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(1024, table.properties().target_file_size_bytes().unwrap());
+        assert_eq!(Some("analytics"), table.properties().get("owner"));
+
+        // Properties checked against each other are checked once all are set
+        let table = table
+            .new_transaction()
+            .update_properties(
+                properties(&[
+                    (COMMIT_MIN_RETRY_WAIT_MS_PROPERTY, "70000"),
+                    (COMMIT_MAX_RETRY_WAIT_MS_PROPERTY, "80000"),
+                ]),
+                &["owner"],
+            )
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(
+            70000,
+            table.properties().commit_min_retry_wait_ms().unwrap()
+        );
+        assert_eq!(None, table.properties().get("owner"));
+
+        for (updates, removals) in [
+            (properties(&[(TARGET_FILE_SIZE_PROPERTY, "-1")]), vec![]),
+            (
+                properties(&[(COMMIT_MAX_RETRY_WAIT_MS_PROPERTY, "1")]),
+                vec![],
+            ),
+            (properties(&[("owner", "a")]), vec!["owner"]),
+        ] {
+            let error = table
+                .new_transaction()
+                .update_properties(updates, &removals)
+                .apply()
+                .unwrap_err();
+            assert!(matches!(error, IcebergError::Invalid(_)), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_commit_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let base = catalog.create_table("db", "t", dir.path());
+        let docs = HashMap::from([("id".to_string(), "Row id".to_string())]);
+
+        // The changes are applied again on top of the concurrent append
+        let appended = append_ids(&catalog, &base, &[1]);
+        let table = base
+            .new_transaction()
+            .update_column_docs(docs.clone())
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(
+            appended.metadata().current_snapshot_id,
+            table.metadata().current_snapshot_id
+        );
+        assert_eq!(1, table.metadata().current_schema_id);
+
+        // Without retries the conflict is returned
+        let mut metadata = table.metadata().clone();
+        metadata.properties = Some(HashMap::from([(
+            COMMIT_NUM_RETRIES_PROPERTY.to_string(),
+            "0".to_string(),
+        )]));
+        let table = catalog.commit_table(&table, metadata).unwrap();
+        append_ids(&catalog, &table, &[2]);
+        let error = table
+            .new_transaction()
+            .update_column_docs(docs)
+            .commit(&catalog)
+            .unwrap_err();
+        assert!(
+            matches!(error, IcebergError::CommitConflict(_)),
+            "{}",
+            error
+        );
+    }
+}
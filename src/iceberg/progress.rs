@@ -0,0 +1,203 @@
+// Progress of long running operations (scan planning and reads, compactions), so that jobs
+// running for hours can tell how far they got. Operations report the phases they go through and
+// the units of work of every phase: manifests when planning or rewriting manifests, data files
+// when reading or rewriting them
+use std::fmt::{self, Debug};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ProgressPhase {
+    // Reading the manifests of a snapshot to plan a scan
+    PlanningManifests,
+    // Reading the data files of a planned scan
+    ReadingFiles,
+    // Rewriting small data files into larger ones
+    RewritingFiles,
+    // Merging small manifests
+    RewritingManifests,
+}
+
+// Receives the progress of operations. Reporters can be called from several threads at once,
+// e.g. by parallel scan planning
+pub trait ProgressReporter: Debug + Send + Sync {
+    // The phase started, with the total number of units to process when known
+    fn start(&self, phase: ProgressPhase, total: Option<u64>);
+
+    // Units of the phase were processed
+    fn advance(&self, phase: ProgressPhase, units: u64);
+
+    // Bytes were read from storage during the phase
+    fn bytes_read(&self, _phase: ProgressPhase, _bytes: u64) {}
+
+    fn finish(&self, phase: ProgressPhase);
+}
+
+impl fmt::Display for ProgressPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ProgressPhase::PlanningManifests => "planning manifests",
+            ProgressPhase::ReadingFiles => "reading files",
+            ProgressPhase::RewritingFiles => "rewriting files",
+            ProgressPhase::RewritingManifests => "rewriting manifests",
+        })
+    }
+}
+
+// Reporter showing a progress bar per phase on the terminal
+#[cfg(feature = "progress-bar")]
+#[derive(Debug, Default)]
+pub struct ProgressBarReporter {
+    bars: indicatif::MultiProgress,
+    phases: std::sync::Mutex<std::collections::HashMap<ProgressPhase, indicatif::ProgressBar>>,
+}
+
+#[cfg(feature = "progress-bar")]
+impl ProgressBarReporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn bar(&self, phase: ProgressPhase) -> Option<indicatif::ProgressBar> {
+        self.phases.lock().unwrap().get(&phase).cloned()
+    }
+}
+
+#[cfg(feature = "progress-bar")]
+impl ProgressReporter for ProgressBarReporter {
+    fn start(&self, phase: ProgressPhase, total: Option<u64>) {
+        let (bar, template) = match total {
+            Some(total) => (
+                indicatif::ProgressBar::new(total),
+                "{prefix:>20} [{bar:40}] {pos}/{len} {msg} ({elapsed})",
+            ),
+            None => (
+                indicatif::ProgressBar::new_spinner(),
+                "{prefix:>20} {spinner} {pos} {msg} ({elapsed})",
+            ),
+        };
+        if let Ok(style) = indicatif::ProgressStyle::with_template(template) {
+            bar.set_style(style.progress_chars("=> "));
+        }
+        bar.set_prefix(phase.to_string());
+        let bar = self.bars.add(bar);
+        if let Some(previous) = self.phases.lock().unwrap().insert(phase, bar) {
+            previous.finish_and_clear();
+        }
+    }
+
+    fn advance(&self, phase: ProgressPhase, units: u64) {
+        if let Some(bar) = self.bar(phase) {
+            bar.inc(units);
+        }
+    }
+
+    fn bytes_read(&self, phase: ProgressPhase, bytes: u64) {
+        if let Some(bar) = self.bar(phase) {
+            // The message holds the bytes read so far
+            let read = bar
+                .message()
+                .split(' ')
+                .next()
+                .and_then(|bytes| bytes.parse::<u64>().ok())
+                .unwrap_or_default();
+            bar.set_message(format!("{} bytes", read + bytes));
+        }
+    }
+
+    fn finish(&self, phase: ProgressPhase) {
+        if let Some(bar) = self.phases.lock().unwrap().remove(&phase) {
+            bar.finish();
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    // Reporter recording the calls it receives
+    #[derive(Debug, Default)]
+    pub(crate) struct RecordingReporter {
+        pub events: Mutex<Vec<(ProgressPhase, String)>>,
+    }
+
+    impl RecordingReporter {
+        // Sum of the units of the phase, checking that it started and finished
+        pub fn units(&self, phase: ProgressPhase) -> u64 {
+            let events = self.events.lock().unwrap();
+            let events: Vec<&String> = events
+                .iter()
+                .filter(|(p, _)| *p == phase)
+                .map(|(_, event)| event)
+                .collect();
+            assert!(events.first().unwrap().starts_with("start"));
+            assert_eq!("finish", events.last().unwrap().as_str());
+            events
+                .iter()
+                .filter_map(|event| event.strip_prefix("advance "))
+                .map(|units| units.parse::<u64>().unwrap())
+                .sum()
+        }
+
+        pub fn total(&self, phase: ProgressPhase) -> Option<u64> {
+            self.events
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|(p, event)| *p == phase && event.starts_with("start"))
+                .and_then(|(_, event)| event.strip_prefix("start ")?.parse().ok())
+        }
+
+        pub fn bytes(&self, phase: ProgressPhase) -> u64 {
+            self.events
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(p, _)| *p == phase)
+                .filter_map(|(_, event)| event.strip_prefix("bytes ")?.parse::<u64>().ok())
+                .sum()
+        }
+    }
+
+    impl ProgressReporter for RecordingReporter {
+        fn start(&self, phase: ProgressPhase, total: Option<u64>) {
+            let event = total.map_or("start".to_string(), |total| format!("start {}", total));
+            self.events.lock().unwrap().push((phase, event));
+        }
+
+        fn advance(&self, phase: ProgressPhase, units: u64) {
+            let event = format!("advance {}", units);
+            self.events.lock().unwrap().push((phase, event));
+        }
+
+        fn bytes_read(&self, phase: ProgressPhase, bytes: u64) {
+            let event = format!("bytes {}", bytes);
+            self.events.lock().unwrap().push((phase, event));
+        }
+
+        fn finish(&self, phase: ProgressPhase) {
+            self.events
+                .lock()
+                .unwrap()
+                .push((phase, "finish".to_string()));
+        }
+    }
+
+    #[cfg(feature = "progress-bar")]
+    #[test]
+    fn test_progress_bar_reporter() {
+        let reporter = ProgressBarReporter::new();
+        reporter.start(ProgressPhase::ReadingFiles, Some(3));
+        reporter.advance(ProgressPhase::ReadingFiles, 2);
+        reporter.bytes_read(ProgressPhase::ReadingFiles, 100);
+        reporter.bytes_read(ProgressPhase::ReadingFiles, 20);
+        let bar = reporter.bar(ProgressPhase::ReadingFiles).unwrap();
+        assert_eq!(2, bar.position());
+        assert_eq!("120 bytes", bar.message());
+        reporter.finish(ProgressPhase::ReadingFiles);
+        assert!(bar.is_finished());
+        // Units of phases that didn't start are ignored
+        reporter.advance(ProgressPhase::RewritingFiles, 1);
+    }
+}
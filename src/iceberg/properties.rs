@@ -0,0 +1,352 @@
+// Typed access to the well-known table properties. Getters return the default of unset properties
+// and fail on values that don't parse or are out of range, setters check values before setting
+// them, so that tables don't get properties their readers and writers would reject. Properties of
+// single features live with them, e.g. ENCRYPTION_KEY_ID_PROPERTY or the bloom filter columns of
+// BLOOM_FILTER_ENABLED_PROPERTY_PREFIX
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use parquet::basic::{Compression, ZstdLevel};
+
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::spec::manifest::FileFormat;
+use crate::iceberg::spec::schema::DecimalFormat;
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+
+// Size that writers aim for when rolling data files, and compactions for the files they write
+pub const TARGET_FILE_SIZE_PROPERTY: &str = "write.target-file-size-bytes";
+const DEFAULT_TARGET_FILE_SIZE: i64 = 512 * 1024 * 1024;
+// Format of the data files written, only Parquet is written by rustberg
+pub const DEFAULT_FILE_FORMAT_PROPERTY: &str = "write.format.default";
+// zstd, snappy or uncompressed
+pub const PARQUET_COMPRESSION_PROPERTY: &str = "write.parquet.compression-codec";
+// Size that manifest writers aim for
+pub const MANIFEST_TARGET_SIZE_PROPERTY: &str = "commit.manifest.target-size-bytes";
+const DEFAULT_MANIFEST_TARGET_SIZE: i64 = 8 * 1024 * 1024;
+// Times a commit conflicting with a concurrent one is applied again on top of the current table,
+// see commit_with_retries, and the bounds of the wait before each retry
+pub const COMMIT_NUM_RETRIES_PROPERTY: &str = "commit.retry.num-retries";
+pub const COMMIT_MIN_RETRY_WAIT_MS_PROPERTY: &str = "commit.retry.min-wait-ms";
+pub const COMMIT_MAX_RETRY_WAIT_MS_PROPERTY: &str = "commit.retry.max-wait-ms";
+const DEFAULT_COMMIT_NUM_RETRIES: u32 = 4;
+const DEFAULT_COMMIT_MIN_RETRY_WAIT_MS: u64 = 100;
+const DEFAULT_COMMIT_MAX_RETRY_WAIT_MS: u64 = 60_000;
+// Number of previous metadata files kept in the metadata log
+pub const PREVIOUS_VERSIONS_MAX_PROPERTY: &str = "write.metadata.previous-versions-max";
+const DEFAULT_PREVIOUS_VERSIONS_MAX: usize = 100;
+// Format of the decimal types of metadata files, spaced or compact, see DecimalFormat
+pub const METADATA_DECIMAL_FORMAT_PROPERTY: &str = "write.metadata.decimal-format";
+// Default maximum age of refs other than main, unlimited when not set
+pub const MAX_REF_AGE_MS_PROPERTY: &str = "history.expire.max-ref-age-ms";
+// Makes scans of the table require a filter on a partition column, like
+// ScanLimits::require_partition_filter does for a single table handle
+pub const PARTITION_FILTER_REQUIRED_PROPERTY: &str = "read.partition-filter.required";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableProperties {
+    properties: HashMap<String, String>,
+}
+
+impl From<HashMap<String, String>> for TableProperties {
+    fn from(properties: HashMap<String, String>) -> Self {
+        TableProperties { properties }
+    }
+}
+
+impl TableProperties {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_metadata(metadata: &TableMetadataV2) -> Self {
+        Self::from(metadata.properties.clone().unwrap_or_default())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).map(String::as_str)
+    }
+
+    // Sets a property, checking the value of well-known ones
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let previous = self.properties.insert(key.to_string(), value.to_string());
+        if let Err(e) = self.validate_key(key) {
+            match previous {
+                Some(previous) => self.properties.insert(key.to_string(), previous),
+                None => self.properties.remove(key),
+            };
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.properties.remove(key)
+    }
+
+    pub fn as_map(&self) -> &HashMap<String, String> {
+        &self.properties
+    }
+
+    pub fn into_map(self) -> HashMap<String, String> {
+        self.properties
+    }
+
+    // Checks the values of all the well-known properties, e.g. of properties set by other
+    // implementations
+    pub fn validate(&self) -> Result<()> {
+        self.properties
+            .keys()
+            .try_for_each(|key| self.validate_key(key))
+    }
+
+    // Checks the value of a single property, which is valid if it isn't a well-known one
+    pub fn validate_key(&self, key: &str) -> Result<()> {
+        match key {
+            TARGET_FILE_SIZE_PROPERTY => self.target_file_size_bytes().map(drop),
+            DEFAULT_FILE_FORMAT_PROPERTY => self.default_file_format().map(drop),
+            PARQUET_COMPRESSION_PROPERTY => self.parquet_compression().map(drop),
+            MANIFEST_TARGET_SIZE_PROPERTY => self.manifest_target_size_bytes().map(drop),
+            COMMIT_NUM_RETRIES_PROPERTY => self.commit_num_retries().map(drop),
+            COMMIT_MIN_RETRY_WAIT_MS_PROPERTY | COMMIT_MAX_RETRY_WAIT_MS_PROPERTY => {
+                let (min, max) = (
+                    self.commit_min_retry_wait_ms()?,
+                    self.commit_max_retry_wait_ms()?,
+                );
+                if min > max {
+                    return Err(IcebergError::Invalid(format!(
+                        "{} {} exceeds {} {}",
+                        COMMIT_MIN_RETRY_WAIT_MS_PROPERTY,
+                        min,
+                        COMMIT_MAX_RETRY_WAIT_MS_PROPERTY,
+                        max
+                    )));
+                }
+                Ok(())
+            }
+            PREVIOUS_VERSIONS_MAX_PROPERTY => self.previous_versions_max().map(drop),
+            METADATA_DECIMAL_FORMAT_PROPERTY => self.metadata_decimal_format().map(drop),
+            MAX_REF_AGE_MS_PROPERTY => self.max_ref_age_ms().map(drop),
+            PARTITION_FILTER_REQUIRED_PROPERTY => self.partition_filter_required().map(drop),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn target_file_size_bytes(&self) -> Result<i64> {
+        self.parse(TARGET_FILE_SIZE_PROPERTY, |size: &i64| *size > 0)
+            .map(|size| size.unwrap_or(DEFAULT_TARGET_FILE_SIZE))
+    }
+
+    pub fn set_target_file_size_bytes(&mut self, size: i64) -> Result<()> {
+        self.set(TARGET_FILE_SIZE_PROPERTY, &size.to_string())
+    }
+
+    pub fn default_file_format(&self) -> Result<FileFormat> {
+        let format = self.parse(DEFAULT_FILE_FORMAT_PROPERTY, |format: &FileFormat| {
+            matches!(
+                format,
+                FileFormat::Parquet | FileFormat::Avro | FileFormat::Orc
+            )
+        })?;
+        Ok(format.unwrap_or(FileFormat::Parquet))
+    }
+
+    pub fn set_default_file_format(&mut self, format: FileFormat) -> Result<()> {
+        self.set(DEFAULT_FILE_FORMAT_PROPERTY, format.extension())
+    }
+
+    pub fn parquet_compression(&self) -> Result<Compression> {
+        let Some(codec) = self.get(PARQUET_COMPRESSION_PROPERTY) else {
+            return Ok(Compression::ZSTD(ZstdLevel::default()));
+        };
+        match codec.to_ascii_lowercase().as_str() {
+            "zstd" => Ok(Compression::ZSTD(ZstdLevel::default())),
+            "snappy" => Ok(Compression::SNAPPY),
+            "uncompressed" | "none" => Ok(Compression::UNCOMPRESSED),
+            "gzip" | "lz4" | "brotli" => Err(IcebergError::Unsupported(format!(
+                "Parquet compression codec {}, rustberg writes zstd, snappy or uncompressed",
+                codec
+            ))),
+            _ => Err(invalid(PARQUET_COMPRESSION_PROPERTY, codec)),
+        }
+    }
+
+    pub fn set_parquet_compression(&mut self, codec: &str) -> Result<()> {
+        self.set(PARQUET_COMPRESSION_PROPERTY, codec)
+    }
+
+    pub fn manifest_target_size_bytes(&self) -> Result<i64> {
+        self.parse(MANIFEST_TARGET_SIZE_PROPERTY, |size: &i64| *size > 0)
+            .map(|size| size.unwrap_or(DEFAULT_MANIFEST_TARGET_SIZE))
+    }
+
+    pub fn set_manifest_target_size_bytes(&mut self, size: i64) -> Result<()> {
+        self.set(MANIFEST_TARGET_SIZE_PROPERTY, &size.to_string())
+    }
+
+    pub fn commit_num_retries(&self) -> Result<u32> {
+        self.parse(COMMIT_NUM_RETRIES_PROPERTY, |_: &u32| true)
+            .map(|retries| retries.unwrap_or(DEFAULT_COMMIT_NUM_RETRIES))
+    }
+
+    pub fn set_commit_num_retries(&mut self, retries: u32) -> Result<()> {
+        self.set(COMMIT_NUM_RETRIES_PROPERTY, &retries.to_string())
+    }
+
+    pub fn commit_min_retry_wait_ms(&self) -> Result<u64> {
+        self.parse(COMMIT_MIN_RETRY_WAIT_MS_PROPERTY, |_: &u64| true)
+            .map(|wait| wait.unwrap_or(DEFAULT_COMMIT_MIN_RETRY_WAIT_MS))
+    }
+
+    pub fn commit_max_retry_wait_ms(&self) -> Result<u64> {
+        self.parse(COMMIT_MAX_RETRY_WAIT_MS_PROPERTY, |_: &u64| true)
+            .map(|wait| wait.unwrap_or(DEFAULT_COMMIT_MAX_RETRY_WAIT_MS))
+    }
+
+    // Sets both bounds, since each is checked against the other
+    pub fn set_commit_retry_wait_ms(&mut self, min_wait_ms: u64, max_wait_ms: u64) -> Result<()> {
+        if min_wait_ms > max_wait_ms {
+            return Err(IcebergError::Invalid(format!(
+                "Minimum retry wait {} ms exceeds maximum {} ms",
+                min_wait_ms, max_wait_ms
+            )));
+        }
+        // The maximum first, so that the minimum is checked against it
+        self.properties.insert(
+            COMMIT_MAX_RETRY_WAIT_MS_PROPERTY.to_string(),
+            max_wait_ms.to_string(),
+        );
+        self.set(COMMIT_MIN_RETRY_WAIT_MS_PROPERTY, &min_wait_ms.to_string())
+    }
+
+    pub fn previous_versions_max(&self) -> Result<usize> {
+        self.parse(PREVIOUS_VERSIONS_MAX_PROPERTY, |_: &usize| true)
+            .map(|versions| versions.unwrap_or(DEFAULT_PREVIOUS_VERSIONS_MAX))
+    }
+
+    pub fn set_previous_versions_max(&mut self, versions: usize) -> Result<()> {
+        self.set(PREVIOUS_VERSIONS_MAX_PROPERTY, &versions.to_string())
+    }
+
+    pub fn metadata_decimal_format(&self) -> Result<DecimalFormat> {
+        self.parse(METADATA_DECIMAL_FORMAT_PROPERTY, |_: &DecimalFormat| true)
+            .map(Option::unwrap_or_default)
+    }
+
+    pub fn set_metadata_decimal_format(&mut self, format: DecimalFormat) -> Result<()> {
+        self.set(METADATA_DECIMAL_FORMAT_PROPERTY, format.as_str())
+    }
+
+    pub fn max_ref_age_ms(&self) -> Result<Option<i64>> {
+        self.parse(MAX_REF_AGE_MS_PROPERTY, |age: &i64| *age > 0)
+    }
+
+    pub fn set_max_ref_age_ms(&mut self, age_ms: i64) -> Result<()> {
+        self.set(MAX_REF_AGE_MS_PROPERTY, &age_ms.to_string())
+    }
+
+    pub fn partition_filter_required(&self) -> Result<bool> {
+        match self.get(PARTITION_FILTER_REQUIRED_PROPERTY) {
+            None => Ok(false),
+            Some(value) if value.eq_ignore_ascii_case("true") => Ok(true),
+            Some(value) if value.eq_ignore_ascii_case("false") => Ok(false),
+            Some(value) => Err(invalid(PARTITION_FILTER_REQUIRED_PROPERTY, value)),
+        }
+    }
+
+    pub fn set_partition_filter_required(&mut self, required: bool) -> Result<()> {
+        self.set(PARTITION_FILTER_REQUIRED_PROPERTY, &required.to_string())
+    }
+
+    fn parse<T: FromStr>(&self, key: &str, valid: impl Fn(&T) -> bool) -> Result<Option<T>> {
+        self.get(key)
+            .map(|value| {
+                value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|parsed| valid(parsed))
+                    .ok_or_else(|| invalid(key, value))
+            })
+            .transpose()
+    }
+}
+
+fn invalid(key: &str, value: &str) -> IcebergError {
+    IcebergError::Invalid(format!("Invalid {} table property {}", key, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_properties() {
+        let mut properties = TableProperties::new();
+        assert_eq!(
+            512 * 1024 * 1024,
+            properties.target_file_size_bytes().unwrap()
+        );
+        assert_eq!(
+            FileFormat::Parquet,
+            properties.default_file_format().unwrap()
+        );
+        assert_eq!(4, properties.commit_num_retries().unwrap());
+        assert_eq!(None, properties.max_ref_age_ms().unwrap());
+        assert!(!properties.partition_filter_required().unwrap());
+        assert_eq!(
+            DecimalFormat::Spaced,
+            properties.metadata_decimal_format().unwrap()
+        );
+
+        properties.set_target_file_size_bytes(1024).unwrap();
+        properties.set_parquet_compression("SNAPPY").unwrap();
+        properties.set_commit_retry_wait_ms(10, 20).unwrap();
+        properties.set_partition_filter_required(true).unwrap();
+        properties
+            .set_metadata_decimal_format(DecimalFormat::Compact)
+            .unwrap();
+        properties.set("owner", "analytics").unwrap();
+        assert_eq!(1024, properties.target_file_size_bytes().unwrap());
+        assert_eq!(
+            Compression::SNAPPY,
+            properties.parquet_compression().unwrap()
+        );
+        assert_eq!(10, properties.commit_min_retry_wait_ms().unwrap());
+        assert_eq!(
+            Some("true"),
+            properties.get(PARTITION_FILTER_REQUIRED_PROPERTY)
+        );
+        assert_eq!(Some("analytics"), properties.get("owner"));
+        assert_eq!(
+            Some("compact"),
+            properties.get(METADATA_DECIMAL_FORMAT_PROPERTY)
+        );
+
+        // Invalid values are rejected and the previous ones kept
+        assert!(properties.set_target_file_size_bytes(0).is_err());
+        assert!(properties.set(TARGET_FILE_SIZE_PROPERTY, "big").is_err());
+        assert_eq!(1024, properties.target_file_size_bytes().unwrap());
+        assert!(properties
+            .set(COMMIT_MIN_RETRY_WAIT_MS_PROPERTY, "30")
+            .is_err());
+        assert!(properties.set_commit_retry_wait_ms(30, 20).is_err());
+        assert!(matches!(
+            properties.set_parquet_compression("gzip"),
+            Err(IcebergError::Unsupported(_))
+        ));
+        assert!(properties
+            .set_default_file_format(FileFormat::Puffin)
+            .is_err());
+        assert!(properties.set(MAX_REF_AGE_MS_PROPERTY, "-1").is_err());
+        assert_eq!(None, properties.get(MAX_REF_AGE_MS_PROPERTY));
+        properties.validate().unwrap();
+
+        // Properties written by other implementations are checked when validated or read
+        let properties = TableProperties::from(HashMap::from([(
+            COMMIT_NUM_RETRIES_PROPERTY.to_string(),
+            "many".to_string(),
+        )]));
+        assert!(properties.validate().is_err());
+        assert!(properties.commit_num_retries().is_err());
+    }
+}
@@ -0,0 +1,186 @@
+// Deletion vectors of format version 3 tables: the positions of the deleted rows of a single
+// data file, as a 64-bit roaring bitmap. They are stored as deletion-vector-v1 blobs of Puffin
+// files, made of the length of the magic and vector, the magic, the vector in the portable
+// serialization of roaring bitmaps, and a CRC-32 of the magic and vector:
+//   Length(4, big endian) Magic(4) Vector Crc(4, big endian)
+use std::collections::HashMap;
+
+use roaring::RoaringTreemap;
+
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::puffin::Blob;
+
+pub const DELETION_VECTOR_BLOB_TYPE: &str = "deletion-vector-v1";
+// Blob properties holding the location of the data file and the number of deleted rows
+pub const REFERENCED_DATA_FILE_PROPERTY: &str = "referenced-data-file";
+pub const CARDINALITY_PROPERTY: &str = "cardinality";
+
+const MAGIC: [u8; 4] = [0xD1, 0xD3, 0x39, 0x64];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeletionVector {
+    positions: RoaringTreemap,
+}
+
+impl DeletionVector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Marks the row at the position deleted, returns false if it already was. Negative
+    // positions are not rows and are ignored
+    pub fn insert(&mut self, position: i64) -> bool {
+        position >= 0 && self.positions.insert(position as u64)
+    }
+
+    pub fn contains(&self, position: i64) -> bool {
+        position >= 0 && self.positions.contains(position as u64)
+    }
+
+    // Adds the deleted positions of another vector
+    pub fn merge(&mut self, other: &DeletionVector) {
+        self.positions |= &other.positions;
+    }
+
+    // Number of deleted rows
+    pub fn cardinality(&self) -> u64 {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    // Deleted positions from `start` up to `end` excluded, in order
+    pub fn range(&self, start: i64, end: i64) -> impl Iterator<Item = i64> + '_ {
+        let mut positions = self.positions.iter();
+        positions.advance_to(start.max(0) as u64);
+        positions
+            .map(|position| position as i64)
+            .take_while(move |position| *position < end)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut vector = MAGIC.to_vec();
+        self.positions
+            .serialize_into(&mut vector)
+            .expect("writes to a Vec don't fail");
+        let mut data = Vec::with_capacity(vector.len() + 8);
+        data.extend((vector.len() as u32).to_be_bytes());
+        data.extend(&vector);
+        data.extend(crc32fast::hash(&vector).to_be_bytes());
+        data
+    }
+
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self> {
+        let invalid = |message: &str| IcebergError::Invalid(format!("Deletion vector {}", message));
+        if data.len() < 12 {
+            return Err(invalid("is truncated"));
+        }
+        let length = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+        if length + 8 != data.len() {
+            return Err(invalid(&format!(
+                "has a length of {} bytes, the blob holds {}",
+                length,
+                data.len() - 8
+            )));
+        }
+        let vector = &data[4..4 + length];
+        if vector[..4] != MAGIC {
+            return Err(invalid("has an invalid magic"));
+        }
+        let crc = u32::from_be_bytes(data[4 + length..].try_into().unwrap());
+        if crc32fast::hash(vector) != crc {
+            return Err(invalid("has an invalid checksum"));
+        }
+        let positions = RoaringTreemap::deserialize_from(&vector[4..])
+            .map_err(|e| invalid(&format!("can't be decoded: {}", e)))?;
+        Ok(DeletionVector { positions })
+    }
+
+    // Blob of a Puffin file holding the vector. Deletion vectors inherit the snapshot id and
+    // sequence number of their manifest entry, their blobs have -1 instead
+    pub fn to_blob(&self, referenced_data_file: &str) -> Blob {
+        Blob {
+            blob_type: DELETION_VECTOR_BLOB_TYPE.to_string(),
+            fields: vec![],
+            snapshot_id: -1,
+            sequence_number: -1,
+            properties: HashMap::from([
+                (
+                    REFERENCED_DATA_FILE_PROPERTY.to_string(),
+                    referenced_data_file.to_string(),
+                ),
+                (
+                    CARDINALITY_PROPERTY.to_string(),
+                    self.cardinality().to_string(),
+                ),
+            ]),
+            data: self.to_bytes(),
+        }
+    }
+}
+
+impl FromIterator<i64> for DeletionVector {
+    fn from_iter<T: IntoIterator<Item = i64>>(positions: T) -> Self {
+        let mut vector = DeletionVector::new();
+        for position in positions {
+            vector.insert(position);
+        }
+        vector
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::puffin::{PuffinReader, PuffinWriter};
+
+    #[test]
+    fn test_deletion_vector_roundtrip() {
+        let vector: DeletionVector = [5, 1, 3, 1 << 40, -1].into_iter().collect();
+        assert_eq!(4, vector.cardinality());
+        assert!(vector.contains(1 << 40) && !vector.contains(2) && !vector.contains(-1));
+        assert_eq!(vec![3, 5], vector.range(2, 1 << 40).collect::<Vec<_>>());
+
+        let data = vector.to_bytes();
+        assert_eq!(&MAGIC, &data[4..8]);
+        assert_eq!(vector, DeletionVector::try_from_bytes(&data).unwrap());
+        assert_eq!(
+            DeletionVector::new(),
+            DeletionVector::try_from_bytes(&DeletionVector::new().to_bytes()).unwrap()
+        );
+
+        let mut corrupted = data.clone();
+        corrupted[10] ^= 1;
+        assert!(DeletionVector::try_from_bytes(&corrupted).is_err());
+        assert!(DeletionVector::try_from_bytes(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_deletion_vector_blob() {
+        // Positions 1, 3 and 5: one bitmap of key 0 holding an array container
+        let expected = [
+            0, 0, 0, 38, 209, 211, 57, 100, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 58, 48, 0, 0, 1, 0,
+            0, 0, 0, 0, 2, 0, 16, 0, 0, 0, 1, 0, 3, 0, 5, 0,
+        ];
+        let vector: DeletionVector = [1, 3, 5].into_iter().collect();
+        let data = vector.to_bytes();
+        assert_eq!(&expected[..], &data[..data.len() - 4]);
+
+        let mut writer = PuffinWriter::new();
+        writer.add_blob(vector.to_blob("file:/data/a.parquet"));
+        let file = writer.finish().unwrap();
+        let reader = PuffinReader::try_new(file.data.into()).unwrap();
+        let blob = &reader.metadata().blobs[0];
+        assert_eq!(DELETION_VECTOR_BLOB_TYPE, blob.blob_type);
+        assert_eq!(
+            Some("3"),
+            blob.properties
+                .get(CARDINALITY_PROPERTY)
+                .map(String::as_str)
+        );
+        let data = reader.blob(blob).unwrap();
+        assert_eq!(vector, DeletionVector::try_from_bytes(&data).unwrap());
+    }
+}
@@ -0,0 +1,247 @@
+// Puffin files hold blobs of statistics and indexes about the data of a table, such as the NDV
+// sketches referenced by the statistics files of table metadata. A file is made of a magic, the
+// blobs, and a footer describing the blobs:
+//   Magic Blob* Magic FooterPayload FooterPayloadSize Flags Magic
+// Compressed blobs and footers are not supported yet
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::iceberg::error::{IcebergError, Result};
+
+#[cfg(feature = "arrow")]
+pub mod deletion_vector;
+pub mod theta;
+
+const MAGIC: [u8; 4] = [0x50, 0x46, 0x41, 0x31];
+// Magic, payload size and flags around the footer payload
+const FOOTER_STRUCT_LENGTH: usize = 16;
+const FOOTER_COMPRESSED_FLAG: u8 = 0x01;
+
+pub const CREATED_BY_PROPERTY: &str = "created-by";
+
+// Footer of a Puffin file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileMetadata {
+    pub blobs: Vec<BlobMetadata>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub properties: HashMap<String, String>,
+}
+
+// A blob of a Puffin file along with its position in the file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct BlobMetadata {
+    #[serde(rename = "type")]
+    pub blob_type: String,
+    pub fields: Vec<i32>,
+    pub snapshot_id: i64,
+    pub sequence_number: i64,
+    pub offset: i64,
+    pub length: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression_codec: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub properties: HashMap<String, String>,
+}
+
+// A blob to write, computed from the given fields of a snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blob {
+    pub blob_type: String,
+    pub fields: Vec<i32>,
+    pub snapshot_id: i64,
+    pub sequence_number: i64,
+    pub properties: HashMap<String, String>,
+    pub data: Vec<u8>,
+}
+
+// A written Puffin file
+#[derive(Debug, Clone, PartialEq)]
+pub struct PuffinFile {
+    pub data: Vec<u8>,
+    pub metadata: FileMetadata,
+    // Size of the footer including its magics, as recorded in statistics files
+    pub footer_size: i64,
+}
+
+// Writes the blobs uncompressed, in the order they were added
+#[derive(Debug)]
+pub struct PuffinWriter {
+    blobs: Vec<Blob>,
+    properties: HashMap<String, String>,
+}
+
+impl Default for PuffinWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PuffinWriter {
+    pub fn new() -> Self {
+        PuffinWriter {
+            blobs: vec![],
+            properties: HashMap::from([(
+                CREATED_BY_PROPERTY.to_string(),
+                format!("rustberg version {}", env!("CARGO_PKG_VERSION")),
+            )]),
+        }
+    }
+
+    // Adds a property to the footer of the file
+    pub fn set_property(&mut self, key: &str, value: &str) {
+        self.properties.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn add_blob(&mut self, blob: Blob) {
+        self.blobs.push(blob);
+    }
+
+    pub fn finish(self) -> Result<PuffinFile> {
+        let mut data = MAGIC.to_vec();
+        let mut blobs = vec![];
+        for blob in self.blobs {
+            blobs.push(BlobMetadata {
+                blob_type: blob.blob_type,
+                fields: blob.fields,
+                snapshot_id: blob.snapshot_id,
+                sequence_number: blob.sequence_number,
+                offset: data.len() as i64,
+                length: blob.data.len() as i64,
+                compression_codec: None,
+                properties: blob.properties,
+            });
+            data.extend(blob.data);
+        }
+        let metadata = FileMetadata {
+            blobs,
+            properties: self.properties,
+        };
+        let payload = serde_json::to_vec(&metadata)?;
+        let footer_size = payload.len() + FOOTER_STRUCT_LENGTH;
+        data.extend(MAGIC);
+        data.extend(&payload);
+        data.extend((payload.len() as i32).to_le_bytes());
+        data.extend([0; 4]);
+        data.extend(MAGIC);
+        Ok(PuffinFile {
+            data,
+            metadata,
+            footer_size: footer_size as i64,
+        })
+    }
+}
+
+// Reads the footer of a Puffin file and the blobs it describes
+#[derive(Debug, Clone)]
+pub struct PuffinReader {
+    data: Bytes,
+    metadata: FileMetadata,
+}
+
+impl PuffinReader {
+    pub fn try_new(data: Bytes) -> Result<Self> {
+        let invalid = |message: &str| IcebergError::Invalid(format!("Puffin file {}", message));
+        if data.len() < MAGIC.len() + FOOTER_STRUCT_LENGTH || data[..4] != MAGIC {
+            return Err(invalid("doesn't start with the Puffin magic"));
+        }
+        let end = data.len();
+        if data[end - 4..] != MAGIC {
+            return Err(invalid("doesn't end with the Puffin magic"));
+        }
+        let flags = &data[end - 8..end - 4];
+        if flags[0] & FOOTER_COMPRESSED_FLAG != 0 {
+            return Err(IcebergError::Unsupported(
+                "Reading compressed Puffin footers".to_string(),
+            ));
+        }
+        let payload_size = i32::from_le_bytes(data[end - 12..end - 8].try_into().unwrap());
+        let payload_end = end - 12;
+        let payload_start = usize::try_from(payload_size)
+            .ok()
+            .and_then(|size| payload_end.checked_sub(size))
+            .filter(|start| *start >= MAGIC.len() * 2)
+            .ok_or_else(|| invalid("has an invalid footer payload size"))?;
+        if data[payload_start - 4..payload_start] != MAGIC {
+            return Err(invalid("footer doesn't start with the Puffin magic"));
+        }
+        let metadata = serde_json::from_slice(&data[payload_start..payload_end])?;
+        Ok(PuffinReader { data, metadata })
+    }
+
+    pub fn metadata(&self) -> &FileMetadata {
+        &self.metadata
+    }
+
+    pub fn blob(&self, blob: &BlobMetadata) -> Result<Bytes> {
+        if let Some(codec) = &blob.compression_codec {
+            return Err(IcebergError::Unsupported(format!(
+                "Reading {} compressed Puffin blobs",
+                codec
+            )));
+        }
+        let start = blob.offset as usize;
+        let end = start + blob.length as usize;
+        if blob.offset < 0 || blob.length < 0 || end > self.data.len() {
+            return Err(IcebergError::Invalid(format!(
+                "Puffin blob at offset {} with length {} is outside of the file",
+                blob.offset, blob.length
+            )));
+        }
+        Ok(self.data.slice(start..end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_puffin_roundtrip() {
+        let mut writer = PuffinWriter::new();
+        for (field, data) in [(1, b"abc".to_vec()), (2, b"de".to_vec())] {
+            writer.add_blob(Blob {
+                blob_type: "some-blob".to_string(),
+                fields: vec![field],
+                snapshot_id: 7,
+                sequence_number: 3,
+                properties: HashMap::from([("ndv".to_string(), "2".to_string())]),
+                data,
+            });
+        }
+        let file = writer.finish().unwrap();
+        assert_eq!(MAGIC, file.data[..4]);
+        assert_eq!(
+            file.footer_size as usize,
+            file.data.len() - 4 - 3 - 2,
+            "footer follows the blobs"
+        );
+
+        let reader = PuffinReader::try_new(Bytes::from(file.data)).unwrap();
+        assert_eq!(&file.metadata, reader.metadata());
+        let blobs = &reader.metadata().blobs;
+        assert_eq!((4, 3), (blobs[0].offset, blobs[0].length));
+        assert_eq!(&b"de"[..], &reader.blob(&blobs[1]).unwrap()[..]);
+        assert!(reader.metadata().properties[CREATED_BY_PROPERTY].starts_with("rustberg"));
+    }
+
+    #[test]
+    fn test_read_file_without_blobs() {
+        let mut data = MAGIC.to_vec();
+        data.extend(MAGIC);
+        let payload = br#"{"blobs":[]}"#;
+        data.extend(payload);
+        data.extend((payload.len() as i32).to_le_bytes());
+        data.extend([0; 4]);
+        data.extend(MAGIC);
+        let reader = PuffinReader::try_new(Bytes::from(data.clone())).unwrap();
+        assert!(reader.metadata().blobs.is_empty());
+
+        let flags = data.len() - 8;
+        data[flags] = FOOTER_COMPRESSED_FLAG;
+        assert!(PuffinReader::try_new(Bytes::from(data.clone())).is_err());
+        assert!(PuffinReader::try_new(Bytes::copy_from_slice(&data[4..])).is_err());
+    }
+}
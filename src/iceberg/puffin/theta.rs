@@ -0,0 +1,268 @@
+// Theta sketches estimating the number of distinct values of a column, serialized like the
+// compact sketches of Apache DataSketches so that engines can read the
+// apache-datasketches-theta-v1 blobs of rustberg tables. Values are fed in their binary
+// single-value serialization, as the Iceberg spec requires for these blobs
+use std::collections::BTreeSet;
+
+use crate::iceberg::error::{IcebergError, Result};
+
+pub const THETA_SKETCH_BLOB_TYPE: &str = "apache-datasketches-theta-v1";
+// Blob property holding the estimated number of distinct values
+pub const NDV_PROPERTY: &str = "ndv";
+
+const DEFAULT_NOMINAL_ENTRIES: usize = 4096;
+const DEFAULT_UPDATE_SEED: u64 = 9001;
+const MAX_THETA: u64 = i64::MAX as u64;
+
+const SERIAL_VERSION: u8 = 3;
+const COMPACT_FAMILY_ID: u8 = 3;
+const READ_ONLY_FLAG: u8 = 1 << 1;
+const EMPTY_FLAG: u8 = 1 << 2;
+const COMPACT_FLAG: u8 = 1 << 3;
+const ORDERED_FLAG: u8 = 1 << 4;
+const SINGLE_ITEM_FLAG: u8 = 1 << 5;
+
+// Keeps the smallest hashes of the values (a KMV sketch). Once more than the nominal number of
+// entries were seen, theta is lowered to the largest dropped hash
+#[derive(Debug, Clone)]
+pub struct ThetaSketch {
+    nominal_entries: usize,
+    theta: u64,
+    hashes: BTreeSet<u64>,
+    empty: bool,
+}
+
+impl Default for ThetaSketch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ThetaSketch {
+    pub fn new() -> Self {
+        Self::with_nominal_entries(DEFAULT_NOMINAL_ENTRIES)
+    }
+
+    pub fn with_nominal_entries(nominal_entries: usize) -> Self {
+        ThetaSketch {
+            nominal_entries: nominal_entries.max(1),
+            theta: MAX_THETA,
+            hashes: BTreeSet::new(),
+            empty: true,
+        }
+    }
+
+    pub fn update(&mut self, value: &[u8]) {
+        if value.is_empty() {
+            return;
+        }
+        self.empty = false;
+        let hash = murmur3_x64_128(value, DEFAULT_UPDATE_SEED).0 >> 1;
+        if hash == 0 || hash >= self.theta || !self.hashes.insert(hash) {
+            return;
+        }
+        if self.hashes.len() > self.nominal_entries {
+            self.theta = self.hashes.pop_last().unwrap();
+        }
+    }
+
+    // Estimated number of distinct values
+    pub fn estimate(&self) -> f64 {
+        self.hashes.len() as f64 * (MAX_THETA as f64 / self.theta as f64)
+    }
+
+    // Compact ordered sketch in the serialization format version 3 of DataSketches
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let exact = self.theta == MAX_THETA;
+        let (pre_longs, flags) = if self.empty && exact {
+            (1, EMPTY_FLAG)
+        } else if exact && self.hashes.len() == 1 {
+            (1, SINGLE_ITEM_FLAG)
+        } else if exact {
+            (2, 0)
+        } else {
+            (3, 0)
+        };
+        let mut data = vec![
+            pre_longs,
+            SERIAL_VERSION,
+            COMPACT_FAMILY_ID,
+            0,
+            0,
+            flags | READ_ONLY_FLAG | COMPACT_FLAG | ORDERED_FLAG,
+        ];
+        data.extend(seed_hash(DEFAULT_UPDATE_SEED).to_le_bytes());
+        if pre_longs > 1 {
+            data.extend((self.hashes.len() as u32).to_le_bytes());
+            data.extend(1.0f32.to_le_bytes());
+        }
+        if pre_longs > 2 {
+            data.extend(self.theta.to_le_bytes());
+        }
+        for hash in &self.hashes {
+            data.extend(hash.to_le_bytes());
+        }
+        data
+    }
+
+    // Reads a compact sketch written with the default seed
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self> {
+        let invalid = |message: &str| IcebergError::Invalid(format!("Theta sketch {}", message));
+        if data.len() < 8 {
+            return Err(invalid("is truncated"));
+        }
+        let pre_longs = (data[0] & 0x3f) as usize;
+        if data[1] != SERIAL_VERSION || data[2] != COMPACT_FAMILY_ID {
+            return Err(IcebergError::Unsupported(format!(
+                "Theta sketch with serial version {} and family {}",
+                data[1], data[2]
+            )));
+        }
+        let flags = data[5];
+        if flags & COMPACT_FLAG == 0 {
+            return Err(invalid("is not compact"));
+        }
+        if u16::from_le_bytes([data[6], data[7]]) != seed_hash(DEFAULT_UPDATE_SEED) {
+            return Err(invalid("wasn't built with the default seed"));
+        }
+        let long = |index: usize| {
+            data.get(index * 8..index * 8 + 8)
+                .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+                .ok_or_else(|| invalid("is truncated"))
+        };
+        let (count, theta) = match pre_longs {
+            1 if flags & EMPTY_FLAG != 0 => (0, MAX_THETA),
+            1 if flags & SINGLE_ITEM_FLAG != 0 => (1, MAX_THETA),
+            2 => (long(1)? as u32 as usize, MAX_THETA),
+            3 => (long(1)? as u32 as usize, long(2)?),
+            _ => return Err(invalid("has an invalid preamble")),
+        };
+        let hashes = (0..count)
+            .map(|index| long(pre_longs + index))
+            .collect::<Result<BTreeSet<u64>>>()?;
+        Ok(ThetaSketch {
+            nominal_entries: DEFAULT_NOMINAL_ENTRIES.max(hashes.len()),
+            theta,
+            empty: flags & EMPTY_FLAG != 0,
+            hashes,
+        })
+    }
+}
+
+// 16 bit hash of the update seed stored in sketches, so that sketches built with different seeds
+// aren't mixed
+fn seed_hash(seed: u64) -> u16 {
+    (murmur3_x64_128(&seed.to_le_bytes(), 0).0 & 0xffff) as u16
+}
+
+// MurmurHash3 x64 128 bit variant, returning the two halves of the hash
+pub(crate) fn murmur3_x64_128(data: &[u8], seed: u64) -> (u64, u64) {
+    const C1: u64 = 0x87c3_7b91_1142_53d5;
+    const C2: u64 = 0x4cf5_ad43_2745_937f;
+    let mix_k1 = |k: u64| k.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+    let mix_k2 = |k: u64| k.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+    let fmix = |mut k: u64| {
+        k ^= k >> 33;
+        k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        k ^= k >> 33;
+        k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        k ^ (k >> 33)
+    };
+
+    let (mut h1, mut h2) = (seed, seed);
+    let mut chunks = data.chunks_exact(16);
+    for chunk in &mut chunks {
+        let k1 = u64::from_le_bytes(chunk[..8].try_into().unwrap());
+        let k2 = u64::from_le_bytes(chunk[8..].try_into().unwrap());
+        h1 ^= mix_k1(k1);
+        h1 = h1
+            .rotate_left(27)
+            .wrapping_add(h2)
+            .wrapping_mul(5)
+            .wrapping_add(0x52dc_e729);
+        h2 ^= mix_k2(k2);
+        h2 = h2
+            .rotate_left(31)
+            .wrapping_add(h1)
+            .wrapping_mul(5)
+            .wrapping_add(0x3849_5ab5);
+    }
+    let tail = chunks.remainder();
+    let (mut k1, mut k2) = (0u64, 0u64);
+    for (index, byte) in tail.iter().enumerate() {
+        if index < 8 {
+            k1 |= (*byte as u64) << (8 * index);
+        } else {
+            k2 |= (*byte as u64) << (8 * (index - 8));
+        }
+    }
+    if tail.len() > 8 {
+        h2 ^= mix_k2(k2);
+    }
+    if !tail.is_empty() {
+        h1 ^= mix_k1(k1);
+    }
+
+    h1 ^= data.len() as u64;
+    h2 ^= data.len() as u64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = fmix(h1);
+    h2 = fmix(h2);
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    (h1, h2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::spec::values::Literal;
+
+    #[test]
+    fn test_murmur3() {
+        assert_eq!(
+            (0xcbd8a7b341bd9b02, 0x5b1e906a48ae1d19),
+            murmur3_x64_128(b"hello", 0)
+        );
+        // Seed hash DataSketches stores for its default seed
+        assert_eq!(0x93cc, seed_hash(DEFAULT_UPDATE_SEED));
+    }
+
+    #[test]
+    fn test_exact_sketch() {
+        let mut sketch = ThetaSketch::new();
+        assert_eq!(
+            vec![1, 3, 3, 0, 0, 0x1e, 0xcc, 0x93],
+            sketch.to_bytes(),
+            "empty sketch"
+        );
+        for id in [1i64, 2, 3, 2, 1] {
+            sketch.update(&Literal::Long(id).to_bytes());
+        }
+        assert_eq!(3.0, sketch.estimate());
+        let data = sketch.to_bytes();
+        assert_eq!(2, data[0]);
+        assert_eq!(16 + 3 * 8, data.len());
+        let read = ThetaSketch::try_from_bytes(&data).unwrap();
+        assert_eq!(3.0, read.estimate());
+        assert_eq!(data, read.to_bytes());
+    }
+
+    #[test]
+    fn test_estimation_mode() {
+        let mut sketch = ThetaSketch::with_nominal_entries(256);
+        for id in 0..10_000i64 {
+            sketch.update(&Literal::Long(id).to_bytes());
+        }
+        let estimate = sketch.estimate();
+        assert!((8_000.0..12_000.0).contains(&estimate), "{}", estimate);
+        let data = sketch.to_bytes();
+        assert_eq!(3, data[0]);
+        assert_eq!(24 + 256 * 8, data.len());
+        assert_eq!(
+            estimate,
+            ThetaSketch::try_from_bytes(&data).unwrap().estimate()
+        );
+    }
+}
@@ -0,0 +1,119 @@
+// Read-only mode, for deployments that must never write to tables (e.g. query services). When
+// enabled, every mutating API of rustberg fails with IcebergError::ReadOnly before changing
+// anything: table commits, metadata files, writes and deletes through LocalFileIO and orphan file
+// deletion. The mode is enabled for the whole process with set_read_only or by setting the
+// RUSTBERG_READ_ONLY environment variable to "true", and can't be disabled once enabled. Single
+// catalogs or FileIOs can be made read-only with ReadOnlyCatalog and ReadOnlyFileIO
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use once_cell::sync::Lazy;
+
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::io::{FileIO, FileInfo};
+
+pub const READ_ONLY_ENV: &str = "RUSTBERG_READ_ONLY";
+
+static READ_ONLY: Lazy<AtomicBool> = Lazy::new(|| {
+    AtomicBool::new(std::env::var(READ_ONLY_ENV).is_ok_and(|value| enables_read_only(&value)))
+});
+
+// Enables read-only mode for the rest of the process
+pub fn set_read_only() {
+    READ_ONLY.store(true, Ordering::SeqCst);
+}
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::SeqCst)
+}
+
+// Fails with IcebergError::ReadOnly in read-only mode. `operation` describes the rejected write
+pub(crate) fn ensure_writable(operation: &str) -> Result<()> {
+    if is_read_only() {
+        return Err(read_only_error(operation));
+    }
+    Ok(())
+}
+
+pub(crate) fn read_only_error(operation: &str) -> IcebergError {
+    IcebergError::ReadOnly(format!("Can't {} in read-only mode", operation))
+}
+
+fn enables_read_only(value: &str) -> bool {
+    matches!(value.trim().to_lowercase().as_str(), "true" | "1")
+}
+
+// FileIO rejecting writes and deletes, reads being delegated
+#[derive(Debug, Clone)]
+pub struct ReadOnlyFileIO {
+    file_io: Arc<dyn FileIO>,
+}
+
+impl ReadOnlyFileIO {
+    pub fn new(file_io: Arc<dyn FileIO>) -> Self {
+        ReadOnlyFileIO { file_io }
+    }
+}
+
+impl FileIO for ReadOnlyFileIO {
+    fn read(&self, location: &str) -> Result<Bytes> {
+        self.file_io.read(location)
+    }
+
+    fn write(&self, location: &str, _data: Bytes) -> Result<()> {
+        Err(read_only_error(&format!("write {}", location)))
+    }
+
+    fn exists(&self, location: &str) -> Result<bool> {
+        self.file_io.exists(location)
+    }
+
+    fn delete(&self, location: &str) -> Result<()> {
+        Err(read_only_error(&format!("delete {}", location)))
+    }
+
+    fn list(&self, location: &str) -> Result<Vec<FileInfo>> {
+        self.file_io.list(location)
+    }
+
+    fn rename_if_absent(&self, from: &str, to: &str) -> Result<()> {
+        Err(read_only_error(&format!("rename {} to {}", from, to)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::io::LocalFileIO;
+
+    #[test]
+    fn test_read_only_file_io() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = format!("file:{}/a.json", dir.path().display());
+        LocalFileIO::new()
+            .write(&location, Bytes::from("{}"))
+            .unwrap();
+        let file_io = ReadOnlyFileIO::new(Arc::new(LocalFileIO::new()));
+
+        assert_eq!(Bytes::from("{}"), file_io.read(&location).unwrap());
+        assert!(matches!(
+            file_io.write(&location, Bytes::new()),
+            Err(IcebergError::ReadOnly(_))
+        ));
+        assert!(matches!(
+            file_io.delete(&location),
+            Err(IcebergError::ReadOnly(_))
+        ));
+        assert!(file_io.exists(&location).unwrap());
+    }
+
+    #[test]
+    fn test_enables_read_only() {
+        assert!(enables_read_only("true"));
+        assert!(enables_read_only(" TRUE "));
+        assert!(enables_read_only("1"));
+        assert!(!enables_read_only("false"));
+        assert!(!enables_read_only(""));
+    }
+}
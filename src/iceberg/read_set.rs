@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::ident::TableIdent;
+use crate::iceberg::io::FileIO;
+use crate::iceberg::scan::TableScan;
+use crate::iceberg::table::Table;
+
+// The snapshots of several tables read together. Jobs capture a read set once, store it as JSON
+// and plan every later scan against exactly these snapshots, so that reruns see the same data
+// even if the tables changed in between
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ReadSet {
+    pub captured_at_ms: i64,
+    pub tables: Vec<PinnedTable>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct PinnedTable {
+    pub namespace: String,
+    pub name: String,
+    // Detects tables dropped and recreated under the same name
+    pub table_uuid: Uuid,
+    pub metadata_location: String,
+    // None for tables without snapshots when captured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_id: Option<i64>,
+}
+
+impl ReadSet {
+    // Pins the current snapshot of the given tables
+    pub fn capture(catalog: &dyn IcebergCatalog, tables: &[TableIdent]) -> Result<Self> {
+        let tables = tables
+            .iter()
+            .map(|ident| {
+                let table = catalog.load_table(ident)?;
+                Ok(PinnedTable {
+                    namespace: ident.namespace().to_string(),
+                    name: ident.name().to_string(),
+                    table_uuid: table.metadata().table_uuid,
+                    metadata_location: table.metadata_location().to_string(),
+                    snapshot_id: table.metadata().current_snapshot().map(|s| s.snapshot_id),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ReadSet {
+            captured_at_ms: catalog.current_time_ms()?,
+            tables,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn write(&self, file_io: &dyn FileIO, location: &str) -> Result<()> {
+        file_io.write(location, Bytes::from(self.to_json()?))
+    }
+
+    pub fn read(file_io: &dyn FileIO, location: &str) -> Result<Self> {
+        Ok(serde_json::from_slice(&file_io.read(location)?)?)
+    }
+
+    pub fn pinned(&self, ident: &TableIdent) -> Result<&PinnedTable> {
+        let namespace = ident.namespace().to_string();
+        self.tables
+            .iter()
+            .find(|pin| pin.namespace == namespace && pin.name == ident.name())
+            .ok_or_else(|| IcebergError::NotFound(format!("Table {} in read set", ident)))
+    }
+
+    // Loads a table of the read set. Tables that had no snapshot when captured are loaded from
+    // the captured metadata, so that their scans stay empty
+    pub fn load_table(&self, catalog: &dyn IcebergCatalog, ident: &TableIdent) -> Result<Table> {
+        let pin = self.pinned(ident)?;
+        let table = catalog.load_table(ident)?;
+        pin.check(&table)?;
+        if pin.snapshot_id.is_none() && table.metadata().current_snapshot().is_some() {
+            return Table::load(
+                ident.clone(),
+                pin.metadata_location.clone(),
+                Arc::clone(table.file_io()),
+            );
+        }
+        Ok(table)
+    }
+
+    // Scan of the pinned snapshot of the table
+    pub fn scan<'t>(&self, table: &'t Table) -> Result<TableScan<'t>> {
+        let pin = self.pinned(table.ident())?;
+        pin.check(table)?;
+        match pin.snapshot_id {
+            Some(snapshot_id) => Ok(table.scan().with_snapshot_id(snapshot_id)),
+            None if table.metadata().current_snapshot().is_none() => Ok(table.scan()),
+            None => Err(IcebergError::Invalid(format!(
+                "Table {} had no snapshot when the read set was captured, load it with \
+                 ReadSet::load_table",
+                table.ident()
+            ))),
+        }
+    }
+}
+
+impl PinnedTable {
+    // Checks that the table is the pinned one and still has the pinned snapshot
+    fn check(&self, table: &Table) -> Result<()> {
+        let metadata = table.metadata();
+        if metadata.table_uuid != self.table_uuid {
+            return Err(IcebergError::Invalid(format!(
+                "Table {}.{} was replaced since the read set was captured (uuid {} instead of {})",
+                self.namespace, self.name, metadata.table_uuid, self.table_uuid
+            )));
+        }
+        if let Some(snapshot_id) = self.snapshot_id {
+            if metadata.snapshot_by_id(snapshot_id).is_none() {
+                return Err(IcebergError::NotFound(format!(
+                    "Snapshot {} of table {}.{} pinned by the read set, it was most likely \
+                     expired",
+                    snapshot_id, self.namespace, self.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::io::LocalFileIO;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+
+    fn count(scan: TableScan) -> usize {
+        scan.plan_files()
+            .unwrap()
+            .to_arrow()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum()
+    }
+
+    #[test]
+    fn test_read_set_pins_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let orders = catalog.create_table("db", "orders", &dir.path().join("orders"));
+        let orders = append_ids(&catalog, &orders, &[1, 2]);
+        catalog.create_table("db", "returns", &dir.path().join("returns"));
+
+        let read_set = ReadSet::capture(
+            &catalog,
+            &["db.orders".parse().unwrap(), "db.returns".parse().unwrap()],
+        )
+        .unwrap();
+        let location = format!("file:{}/read-set.json", dir.path().display());
+        read_set.write(&LocalFileIO::new(), &location).unwrap();
+
+        // Both tables change after the capture
+        append_ids(&catalog, &orders, &[3]);
+        let returns = catalog.load_table(&"db.returns".parse().unwrap()).unwrap();
+        append_ids(&catalog, &returns, &[4]);
+
+        let read_set = ReadSet::read(&LocalFileIO::new(), &location).unwrap();
+        let orders = read_set
+            .load_table(&catalog, &"db.orders".parse().unwrap())
+            .unwrap();
+        assert_eq!(2, count(read_set.scan(&orders).unwrap()));
+        let returns = read_set
+            .load_table(&catalog, &"db.returns".parse().unwrap())
+            .unwrap();
+        assert_eq!(0, count(read_set.scan(&returns).unwrap()));
+
+        // Current tables can only be scanned at pinned snapshots
+        let returns = catalog.load_table(&"db.returns".parse().unwrap()).unwrap();
+        assert!(read_set.scan(&returns).is_err());
+        assert!(read_set.pinned(&"db.other".parse().unwrap()).is_err());
+        assert_eq!(
+            read_set,
+            ReadSet::from_json(&read_set.to_json().unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_read_set_detects_replaced_tables() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", &dir.path().join("a"));
+        append_ids(&catalog, &table, &[1]);
+        let read_set = ReadSet::capture(&catalog, &["db.t".parse().unwrap()]).unwrap();
+
+        catalog.drop_table(&"db.t".parse().unwrap(), false).unwrap();
+        catalog.create_table("db", "t", &dir.path().join("b"));
+        let error = read_set
+            .load_table(&catalog, &"db.t".parse().unwrap())
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("replaced"));
+    }
+}
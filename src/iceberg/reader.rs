@@ -0,0 +1,827 @@
+use std::sync::Arc;
+
+use arrow::array::{new_null_array, ArrayRef, RecordBatch, RecordBatchOptions};
+use arrow::compute::cast;
+use arrow::datatypes::{Schema, SchemaRef};
+use arrow::error::ArrowError;
+use bytes::Bytes;
+use parquet::arrow::arrow_reader::{
+    ArrowReaderMetadata, ArrowReaderOptions, ParquetRecordBatchReaderBuilder,
+};
+use parquet::arrow::ProjectionMask;
+use parquet::basic::Type as PhysicalType;
+use parquet::bloom_filter::Sbbf;
+use parquet::encryption::decrypt::FileDecryptionProperties;
+use parquet::file::metadata::{ParquetMetaData, RowGroupMetaData};
+
+use crate::iceberg::arrow::{field_id, schema_to_arrow};
+use crate::iceberg::avro_reader::AvroReader;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::expr::{BinaryOperator, BoundPredicate, SetOperator};
+use crate::iceberg::spec::manifest::FileFormat;
+use crate::iceberg::spec::name_mapping::NameMapping;
+use crate::iceberg::spec::schema::StructType;
+use crate::iceberg::spec::values::Literal;
+
+pub const DEFAULT_BATCH_SIZE: usize = 8192;
+
+// How to resolve columns of data files that were written without Iceberg field ids (e.g. files
+// imported from Hive tables). Per the spec such columns can't be matched and are read as nulls,
+// which is rarely what the user wants, so a best effort resolution can be opted into
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum FieldIdFallback {
+    // Spec behaviour: columns without field ids are treated as missing, unless the table has a name
+    // mapping
+    #[default]
+    Disabled,
+    // Match the n-th top-level column of the file to the n-th field of the table schema
+    Position,
+    // Match top-level columns by name, preferring an exact match over a case-insensitive one
+    Name,
+}
+
+pub type RecordBatchIter = Box<dyn Iterator<Item = Result<RecordBatch>> + Send>;
+
+// Reads Parquet data files into Arrow record batches shaped like the (projected) table schema.
+// Columns are resolved by Iceberg field id, so renamed or reordered columns are read correctly
+// and columns added after the file was written come back as nulls
+#[derive(Debug, Clone)]
+pub struct ParquetReader {
+    schema: SchemaRef,
+    projection: Option<Vec<i32>>,
+    batch_size: usize,
+    fallback: FieldIdFallback,
+    name_mapping: Option<NameMapping>,
+    filter: Option<BoundPredicate>,
+    // Start and length of the byte range whose row groups are read
+    range: Option<(i64, i64)>,
+}
+
+// Where the data for an expected column comes from
+enum ColumnSource {
+    // Index of the top-level column in the file
+    File(usize),
+    Missing,
+}
+
+impl ParquetReader {
+    pub fn try_new(schema: &StructType) -> Result<Self> {
+        Ok(ParquetReader {
+            schema: Arc::new(schema_to_arrow(schema)?),
+            projection: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            fallback: FieldIdFallback::default(),
+            name_mapping: None,
+            filter: None,
+            range: None,
+        })
+    }
+
+    // Only read the top-level fields with the given ids, in the given order
+    pub fn with_projection(mut self, field_ids: Vec<i32>) -> Self {
+        self.projection = Some(field_ids);
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn with_field_id_fallback(mut self, fallback: FieldIdFallback) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    // Resolves top-level columns of files without field ids by the names of the mapping, see
+    // TableMetadataV2::name_mapping. Takes precedence over the field id fallback
+    pub fn with_name_mapping(mut self, name_mapping: NameMapping) -> Self {
+        self.name_mapping = Some(name_mapping);
+        self
+    }
+
+    // Skips the row groups whose bloom filters show they hold no rows matching the filter, e.g.
+    // for point lookups. Rows of the other row groups are not filtered
+    pub fn with_filter(mut self, filter: BoundPredicate) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    // Only reads the row groups starting within the byte range of the file, e.g. the range of a
+    // FileScanTask of a file split in several tasks. Applies to Parquet files only
+    pub fn with_range(mut self, start: i64, length: i64) -> Self {
+        self.range = Some((start, length));
+        self
+    }
+
+    pub(crate) fn without_filter(mut self) -> Self {
+        self.filter = None;
+        self
+    }
+
+    // Schema of the batches produced by this reader
+    pub fn output_schema(&self) -> Result<SchemaRef> {
+        projected_schema(&self.schema, self.projection.as_deref())
+    }
+
+    pub fn read(&self, data: Bytes) -> Result<RecordBatchIter> {
+        self.read_with_decryption(data, None)
+    }
+
+    // Reads a file encrypted with Parquet modular encryption, or a plaintext file when no
+    // decryption properties are given
+    pub fn read_with_decryption(
+        &self,
+        data: Bytes,
+        decryption: Option<FileDecryptionProperties>,
+    ) -> Result<RecordBatchIter> {
+        let mut options = ArrowReaderOptions::new();
+        let encrypted = decryption.is_some();
+        if let Some(decryption) = decryption {
+            options = options.with_file_decryption_properties(decryption);
+        }
+        let mut builder = ParquetRecordBatchReaderBuilder::try_new_with_options(data, options)?;
+        let num_row_groups = builder.metadata().num_row_groups();
+        let mut row_groups: Vec<usize> = match self.range {
+            Some(range) => range_row_groups(builder.metadata(), range).collect(),
+            None => (0..num_row_groups).collect(),
+        };
+        // Bloom filters of encrypted files are not used
+        if let Some(filter) = self.filter.as_ref().filter(|_| !encrypted) {
+            let matching = row_groups
+                .into_iter()
+                .map(|row_group| {
+                    Ok(bloom_filters_might_match(filter, &mut builder, row_group)?
+                        .then_some(row_group))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            row_groups = matching.into_iter().flatten().collect();
+        }
+        if row_groups.len() < num_row_groups {
+            builder = builder.with_row_groups(row_groups);
+        }
+        let file_schema = builder.schema().clone();
+        let output_schema = self.output_schema()?;
+
+        let sources = self.resolve_columns(FileFormat::Parquet, &file_schema, self.fallback)?;
+        let roots = read_roots(&sources);
+
+        let mask = ProjectionMask::roots(builder.parquet_schema(), roots.iter().copied());
+        let reader = builder
+            .with_projection(mask)
+            .with_batch_size(self.batch_size)
+            .build()?;
+
+        Ok(Box::new(ProjectingIter {
+            reader: Box::new(reader),
+            output_schema,
+            sources,
+            roots,
+        }))
+    }
+
+    // Reads an ORC file. The ids of ORC columns are type attributes orc-rust doesn't expose, so
+    // columns are resolved by the name mapping, or else by name unless a position fallback is
+    // configured. Files are read whole, ignoring the range of the reader
+    #[cfg(feature = "orc")]
+    fn read_orc(&self, data: Bytes) -> Result<RecordBatchIter> {
+        let builder = orc_rust::ArrowReaderBuilder::try_new(data).map_err(ArrowError::from)?;
+        let fallback = match self.fallback {
+            FieldIdFallback::Disabled => FieldIdFallback::Name,
+            fallback => fallback,
+        };
+        let sources = self.resolve_columns(FileFormat::Orc, &builder.schema(), fallback)?;
+        let roots = read_roots(&sources);
+
+        // Projections of ORC files select columns by their index in the type tree of the file,
+        // where the root struct is column 0
+        let root_type = builder.file_metadata().root_data_type().clone();
+        let columns = root_type.children();
+        let mask = orc_rust::projection::ProjectionMask::roots(
+            &root_type,
+            roots.iter().map(|i| columns[*i].data_type().column_index()),
+        );
+        let reader = builder
+            .with_projection(mask)
+            .with_batch_size(self.batch_size)
+            .build();
+
+        Ok(Box::new(ProjectingIter {
+            reader: Box::new(reader),
+            output_schema: self.output_schema()?,
+            sources,
+            roots,
+        }))
+    }
+
+    // Position in the file of the first row read, i.e. the number of rows of the row groups
+    // before the range of the reader
+    pub(crate) fn first_row_position(
+        &self,
+        data: Bytes,
+        decryption: Option<FileDecryptionProperties>,
+    ) -> Result<i64> {
+        let Some(range) = self.range else {
+            return Ok(0);
+        };
+        let mut options = ArrowReaderOptions::new();
+        if let Some(decryption) = decryption {
+            options = options.with_file_decryption_properties(decryption);
+        }
+        let metadata = ArrowReaderMetadata::load(&data, options)?;
+        let metadata = metadata.metadata();
+        let first = range_row_groups(metadata, range).next();
+        Ok(
+            metadata.row_groups()[..first.unwrap_or(metadata.num_row_groups())]
+                .iter()
+                .map(|row_group| row_group.num_rows())
+                .sum(),
+        )
+    }
+
+    // Reads a data or delete file in any readable format, with the schema, projection and name
+    // mapping of this reader. Only Parquet files can be encrypted, ORC files need the orc feature
+    pub(crate) fn read_file(
+        &self,
+        format: FileFormat,
+        location: &str,
+        data: Bytes,
+        decryption: Option<FileDecryptionProperties>,
+    ) -> Result<RecordBatchIter> {
+        check_readable(format, location)?;
+        match format {
+            FileFormat::Avro | FileFormat::Orc if decryption.is_some() => {
+                Err(IcebergError::Unsupported(format!(
+                    "Reading encrypted {} files ({})",
+                    format, location
+                )))
+            }
+            FileFormat::Avro => {
+                let mut reader = AvroReader::for_arrow_schema(self.schema.clone())
+                    .with_batch_size(self.batch_size);
+                if let Some(projection) = &self.projection {
+                    reader = reader.with_projection(projection.clone());
+                }
+                if let Some(name_mapping) = &self.name_mapping {
+                    reader = reader.with_name_mapping(name_mapping.clone());
+                }
+                reader.read(data)
+            }
+            #[cfg(feature = "orc")]
+            FileFormat::Orc => self.read_orc(data),
+            _ => self.read_with_decryption(data, decryption),
+        }
+    }
+
+    fn resolve_columns(
+        &self,
+        format: FileFormat,
+        file_schema: &Schema,
+        fallback: FieldIdFallback,
+    ) -> Result<Vec<ColumnSource>> {
+        let has_field_ids = file_schema.fields().iter().any(|f| field_id(f).is_some());
+        if !has_field_ids && self.name_mapping.is_none() {
+            match fallback {
+                FieldIdFallback::Disabled => log::warn!(
+                    "{} file has no Iceberg field ids, all projected columns will be read as nulls. \
+                     Consider enabling a field id fallback",
+                    format
+                ),
+                FieldIdFallback::Position => log::warn!(
+                    "{} file has no Iceberg field ids, resolving columns BY POSITION. \
+                     Results are wrong if columns were reordered, added or dropped",
+                    format
+                ),
+                FieldIdFallback::Name => log::warn!(
+                    "{} file has no Iceberg field ids, resolving columns BY NAME. \
+                     Results are wrong if columns were renamed",
+                    format
+                ),
+            }
+        }
+
+        projected_indices(&self.schema, self.projection.as_deref())?
+            .into_iter()
+            .map(|index| {
+                let expected = self.schema.field(index);
+                let source = if has_field_ids {
+                    let id = field_id(expected);
+                    file_schema.fields().iter().position(|f| field_id(f) == id)
+                } else if let Some(mapping) = &self.name_mapping {
+                    let id = field_id(expected);
+                    file_schema
+                        .fields()
+                        .iter()
+                        .position(|f| id.is_some() && mapping.field_id(&[f.name()]) == id)
+                } else {
+                    match fallback {
+                        FieldIdFallback::Disabled => None,
+                        FieldIdFallback::Position => {
+                            Some(index).filter(|i| *i < file_schema.fields().len())
+                        }
+                        FieldIdFallback::Name => {
+                            let fields = file_schema.fields();
+                            fields
+                                .iter()
+                                .position(|f| f.name() == expected.name())
+                                .or_else(|| {
+                                    fields.iter().position(|f| {
+                                        f.name().eq_ignore_ascii_case(expected.name())
+                                    })
+                                })
+                        }
+                    }
+                };
+
+                match source {
+                    Some(i) => Ok(ColumnSource::File(i)),
+                    None if expected.is_nullable() => Ok(ColumnSource::Missing),
+                    None => Err(IcebergError::Invalid(format!(
+                        "Required field '{}' not found in data file",
+                        expected.name()
+                    ))),
+                }
+            })
+            .collect()
+    }
+}
+
+// Sorted indices of the file columns the sources read
+fn read_roots(sources: &[ColumnSource]) -> Vec<usize> {
+    let mut roots = sources
+        .iter()
+        .filter_map(|source| match source {
+            ColumnSource::File(i) => Some(*i),
+            ColumnSource::Missing => None,
+        })
+        .collect::<Vec<_>>();
+    roots.sort_unstable();
+    roots.dedup();
+    roots
+}
+
+type ArrowBatchIter = Box<dyn Iterator<Item = std::result::Result<RecordBatch, ArrowError>> + Send>;
+
+struct ProjectingIter {
+    reader: ArrowBatchIter,
+    output_schema: SchemaRef,
+    sources: Vec<ColumnSource>,
+    // Sorted file column indices read from the file. The n-th column of a batch read from the
+    // file corresponds to the n-th entry
+    roots: Vec<usize>,
+}
+
+impl ProjectingIter {
+    fn project(&self, batch: RecordBatch) -> Result<RecordBatch> {
+        let columns = self
+            .sources
+            .iter()
+            .zip(self.output_schema.fields())
+            .map(|(source, expected)| match source {
+                ColumnSource::File(i) => {
+                    let position = self.roots.binary_search(i).map_err(|_| {
+                        IcebergError::Invalid(format!("Column {} was not read from file", i))
+                    })?;
+                    let column = batch.column(position);
+                    if column.data_type() == expected.data_type() {
+                        Ok(column.clone())
+                    } else {
+                        Ok(cast(column, expected.data_type())?)
+                    }
+                }
+                ColumnSource::Missing => Ok(new_null_array(expected.data_type(), batch.num_rows())),
+            })
+            .collect::<Result<Vec<ArrayRef>>>()?;
+
+        Ok(RecordBatch::try_new_with_options(
+            self.output_schema.clone(),
+            columns,
+            &RecordBatchOptions::new().with_row_count(Some(batch.num_rows())),
+        )?)
+    }
+}
+
+impl Iterator for ProjectingIter {
+    type Item = Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reader.next().map(|batch| {
+            batch
+                .map_err(IcebergError::from)
+                .and_then(|b| self.project(b))
+        })
+    }
+}
+
+// Row groups whose first page starts within the byte range of a split, so that each row group
+// is read by exactly one of the splits of a file
+fn range_row_groups(
+    metadata: &ParquetMetaData,
+    (start, length): (i64, i64),
+) -> impl Iterator<Item = usize> + '_ {
+    metadata
+        .row_groups()
+        .iter()
+        .enumerate()
+        .filter(move |(_, row_group)| {
+            row_group_offset(row_group)
+                .is_some_and(|offset| offset >= start && offset < start + length)
+        })
+        .map(|(index, _)| index)
+}
+
+// Offset of the first page of a row group, the split offsets of the data files written
+pub(crate) fn row_group_offset(row_group: &RowGroupMetaData) -> Option<i64> {
+    let first_column = row_group.columns().first();
+    row_group.file_offset().or_else(|| {
+        first_column.map(|c| c.dictionary_page_offset().unwrap_or(c.data_page_offset()))
+    })
+}
+
+// Whether rows of the row group may match the filter according to the bloom filters of its
+// columns. Only equality and in predicates can be ruled out, on columns with a bloom filter
+fn bloom_filters_might_match(
+    filter: &BoundPredicate,
+    builder: &mut ParquetRecordBatchReaderBuilder<Bytes>,
+    row_group: usize,
+) -> Result<bool> {
+    let (term, literals) = match filter {
+        BoundPredicate::AlwaysFalse => return Ok(false),
+        BoundPredicate::And(left, right) => {
+            return Ok(bloom_filters_might_match(left, builder, row_group)?
+                && bloom_filters_might_match(right, builder, row_group)?)
+        }
+        BoundPredicate::Or(left, right) => {
+            return Ok(bloom_filters_might_match(left, builder, row_group)?
+                || bloom_filters_might_match(right, builder, row_group)?)
+        }
+        BoundPredicate::Binary {
+            op: BinaryOperator::Eq,
+            term,
+            literal,
+        } => (term, std::slice::from_ref(literal)),
+        BoundPredicate::Set {
+            op: SetOperator::In,
+            term,
+            literals,
+        } => (term, literals.as_slice()),
+        _ => return Ok(true),
+    };
+    let column = builder
+        .parquet_schema()
+        .columns()
+        .iter()
+        .position(|column| {
+            let info = column.self_type().get_basic_info();
+            info.has_id() && info.id() == term.field_id
+        });
+    let Some(column) = column else {
+        return Ok(true);
+    };
+    let physical_type = builder.parquet_schema().column(column).physical_type();
+    let Some(bloom_filter) = builder.get_row_group_column_bloom_filter(row_group, column)? else {
+        return Ok(true);
+    };
+    Ok(literals
+        .iter()
+        .any(|literal| bloom_filter_contains(&bloom_filter, physical_type, literal)))
+}
+
+// Whether the value may be in a bloom filter of a column with the physical type. Values are
+// hashed in their plain encoding, values of types that can't be hashed may always be there
+fn bloom_filter_contains(
+    bloom_filter: &Sbbf,
+    physical_type: PhysicalType,
+    literal: &Literal,
+) -> bool {
+    match (physical_type, literal) {
+        (PhysicalType::INT32, Literal::Int(value) | Literal::Date(value)) => {
+            bloom_filter.check(value)
+        }
+        // Columns promoted from int to long
+        (PhysicalType::INT32, Literal::Long(value)) => {
+            i32::try_from(*value).is_ok_and(|value| bloom_filter.check(&value))
+        }
+        (
+            PhysicalType::INT64,
+            Literal::Long(value)
+            | Literal::Time(value)
+            | Literal::Timestamp(value)
+            | Literal::Timestamptz(value),
+        ) => bloom_filter.check(value),
+        (PhysicalType::FLOAT, Literal::Float(value)) => bloom_filter.check(value),
+        (PhysicalType::DOUBLE, Literal::Double(value)) => bloom_filter.check(value),
+        (PhysicalType::BYTE_ARRAY, Literal::String(value)) => bloom_filter.check(&value.as_str()),
+        (PhysicalType::BYTE_ARRAY, Literal::Binary(value)) => bloom_filter.check(value),
+        _ => true,
+    }
+}
+
+// Positions in the schema of the projected top-level fields, all of them without projection
+fn projected_indices(schema: &SchemaRef, projection: Option<&[i32]>) -> Result<Vec<usize>> {
+    match projection {
+        None => Ok((0..schema.fields().len()).collect()),
+        Some(field_ids) => field_ids
+            .iter()
+            .map(|id| {
+                schema
+                    .fields()
+                    .iter()
+                    .position(|f| field_id(f) == Some(*id))
+                    .ok_or_else(|| {
+                        IcebergError::NotFound(format!(
+                            "Field id {} not found in the table schema",
+                            id
+                        ))
+                    })
+            })
+            .collect(),
+    }
+}
+
+pub(crate) fn projected_schema(
+    schema: &SchemaRef,
+    projection: Option<&[i32]>,
+) -> Result<SchemaRef> {
+    let indices = projected_indices(schema, projection)?;
+    Ok(Arc::new(Schema::new(
+        indices
+            .iter()
+            .map(|i| schema.field(*i).clone())
+            .collect::<Vec<_>>(),
+    )))
+}
+
+// Fails for files in formats that can't be read yet. Readers dispatch on the format through
+// this check, so that supporting a new format means handling it here
+pub(crate) fn check_readable(format: FileFormat, location: &str) -> Result<()> {
+    match format {
+        FileFormat::Parquet | FileFormat::Avro => Ok(()),
+        #[cfg(feature = "orc")]
+        FileFormat::Orc => Ok(()),
+        #[cfg(not(feature = "orc"))]
+        FileFormat::Orc => Err(IcebergError::Unsupported(format!(
+            "Reading ORC files ({}): build rustberg with the orc feature",
+            location
+        ))),
+        FileFormat::Puffin => Err(IcebergError::Unsupported(format!(
+            "Reading {} files ({})",
+            format, location
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, Int32Array, Int64Array, StringArray};
+    use arrow::datatypes::{DataType, Field};
+    use parquet::arrow::ArrowWriter;
+
+    use crate::iceberg::spec::schema::{IcebergType, PrimitiveType, StructField};
+
+    fn table_schema() -> StructType {
+        StructType {
+            fields: vec![
+                StructField {
+                    id: 1,
+                    name: "id".to_string(),
+                    required: false,
+                    field_type: IcebergType::Primitive(PrimitiveType::Long),
+                    doc: None,
+                    initial_default: None,
+                    write_default: None,
+                },
+                StructField {
+                    id: 2,
+                    name: "name".to_string(),
+                    required: false,
+                    field_type: IcebergType::Primitive(PrimitiveType::String),
+                    doc: None,
+                    initial_default: None,
+                    write_default: None,
+                },
+            ],
+        }
+    }
+
+    // Writes a file with the columns ("NAME", "id") i.e. in a different order and case than the
+    // table schema, with the given field ids (if any)
+    fn write_file(field_ids: Option<(i32, i32)>) -> Bytes {
+        let mut name = Field::new("NAME", DataType::Utf8, true);
+        let mut id = Field::new("id", DataType::Int32, true);
+        if let Some((name_id, id_id)) = field_ids {
+            name =
+                name.with_metadata([("PARQUET:field_id".to_string(), name_id.to_string())].into());
+            id = id.with_metadata([("PARQUET:field_id".to_string(), id_id.to_string())].into());
+        }
+        let schema = Arc::new(Schema::new(vec![name, id]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(StringArray::from(vec!["a", "b"])),
+                Arc::new(Int32Array::from(vec![10, 20])),
+            ],
+        )
+        .unwrap();
+
+        let mut buffer = Vec::new();
+        let mut writer = ArrowWriter::try_new(&mut buffer, schema, None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+        Bytes::from(buffer)
+    }
+
+    fn read_all(reader: &ParquetReader, data: Bytes) -> Vec<RecordBatch> {
+        reader
+            .read(data)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_read_resolves_by_field_id() {
+        let reader = ParquetReader::try_new(&table_schema()).unwrap();
+        let batches = read_all(&reader, write_file(Some((2, 1))));
+
+        let batch = &batches[0];
+        assert_eq!(
+            &Int64Array::from(vec![10, 20]),
+            batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap()
+        );
+        assert_eq!(
+            &StringArray::from(vec!["a", "b"]),
+            batch
+                .column(1)
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_read_without_field_ids_is_null_by_default() {
+        let reader = ParquetReader::try_new(&table_schema()).unwrap();
+        let batches = read_all(&reader, write_file(None));
+
+        assert_eq!(2, batches[0].num_rows());
+        assert_eq!(2, batches[0].column(0).null_count());
+        assert_eq!(2, batches[0].column(1).null_count());
+    }
+
+    #[test]
+    fn test_read_without_field_ids_by_name() {
+        let reader = ParquetReader::try_new(&table_schema())
+            .unwrap()
+            .with_field_id_fallback(FieldIdFallback::Name);
+        let batches = read_all(&reader, write_file(None));
+
+        let batch = &batches[0];
+        assert_eq!(0, batch.column(0).null_count());
+        assert_eq!(
+            &StringArray::from(vec!["a", "b"]),
+            batch
+                .column(1)
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_read_without_field_ids_by_name_mapping() {
+        // Names of the mapping are matched exactly, so "NAME" isn't mapped and the name fallback
+        // isn't used for files resolved with the mapping
+        let mapping = NameMapping::from_json(
+            r#"[{"field-id": 1, "names": ["key", "id"]}, {"field-id": 2, "names": ["name"]}]"#,
+        )
+        .unwrap();
+        let reader = ParquetReader::try_new(&table_schema())
+            .unwrap()
+            .with_field_id_fallback(FieldIdFallback::Name)
+            .with_name_mapping(mapping);
+        let batches = read_all(&reader, write_file(None));
+
+        let batch = &batches[0];
+        assert_eq!(
+            &Int64Array::from(vec![10, 20]),
+            batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap()
+        );
+        assert_eq!(2, batch.column(1).null_count());
+
+        // Files with field ids ignore the mapping
+        let batches = read_all(&reader, write_file(Some((2, 1))));
+        assert_eq!(0, batches[0].column(1).null_count());
+    }
+
+    #[test]
+    fn test_read_without_field_ids_by_position() {
+        // Columns are swapped in the file, so resolving by position can't cast "NAME" to long
+        let reader = ParquetReader::try_new(&table_schema())
+            .unwrap()
+            .with_field_id_fallback(FieldIdFallback::Position)
+            .with_projection(vec![2]);
+        let batches = read_all(&reader, write_file(None));
+
+        // Second table column maps to the second file column ("id"), cast to string
+        assert_eq!(
+            &StringArray::from(vec!["10", "20"]),
+            batches[0]
+                .column(0)
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_read_with_projection() {
+        let reader = ParquetReader::try_new(&table_schema())
+            .unwrap()
+            .with_projection(vec![2]);
+        let batches = read_all(&reader, write_file(Some((2, 1))));
+
+        assert_eq!(1, batches[0].num_columns());
+        assert_eq!("name", batches[0].schema().field(0).name());
+    }
+
+    #[cfg(feature = "orc")]
+    #[test]
+    fn test_read_orc() {
+        // Columns ("NAME", "id", "extra") of an ORC file, resolved by name
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("NAME", DataType::Utf8, true),
+            Field::new("id", DataType::Int32, true),
+            Field::new("extra", DataType::Int32, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(StringArray::from(vec!["a", "b", "c"])),
+                Arc::new(Int32Array::from(vec![10, 20, 30])),
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+            ],
+        )
+        .unwrap();
+        let mut buffer = Vec::new();
+        let mut writer = orc_rust::ArrowWriterBuilder::new(&mut buffer, schema)
+            .try_build()
+            .unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+        let data = Bytes::from(buffer);
+
+        let reader = ParquetReader::try_new(&table_schema())
+            .unwrap()
+            .with_projection(vec![2, 1])
+            .with_batch_size(2);
+        let batches = reader
+            .read_file(FileFormat::Orc, "file:/a.orc", data.clone(), None)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            vec![2, 1],
+            batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>()
+        );
+        let batch = arrow::compute::concat_batches(&batches[0].schema(), &batches).unwrap();
+        assert_eq!(reader.output_schema().unwrap(), batch.schema());
+        assert_eq!(
+            &StringArray::from(vec!["a", "b", "c"]),
+            batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+        );
+        assert_eq!(
+            &Int64Array::from(vec![10, 20, 30]),
+            batch
+                .column(1)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap()
+        );
+
+        // Names of the mapping are matched exactly
+        let mapping = NameMapping::from_json(r#"[{"field-id": 1, "names": ["id"]}]"#).unwrap();
+        let batches = ParquetReader::try_new(&table_schema())
+            .unwrap()
+            .with_name_mapping(mapping)
+            .read_file(FileFormat::Orc, "file:/a.orc", data, None)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(0, batches[0].column(0).null_count());
+        assert_eq!(3, batches[0].column(1).null_count());
+    }
+}
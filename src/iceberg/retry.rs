@@ -0,0 +1,372 @@
+// Retries of operations failing with transient errors, such as timeouts, dropped connections or
+// throttling by object stores and metastores, with exponential backoff and jitter. Only idempotent
+// operations are retried: reads, listings and whole-file writes. Commits aren't, a commit whose
+// response was lost may have succeeded
+use std::fmt;
+use std::io::ErrorKind;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use bytes::Bytes;
+use uuid::Uuid;
+
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::io::{FileIO, FileInfo};
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 4;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(10);
+const DEFAULT_MULTIPLIER: f64 = 2.0;
+const DEFAULT_JITTER: f64 = 0.2;
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct RetryPolicy {
+    // Attempts of an operation including the first one, 1 disables retries
+    pub max_attempts: u32,
+    // Wait before the first retry, multiplied by `multiplier` for each following retry up to
+    // `max_backoff`
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    // Fraction of the backoff randomly added or removed, so that clients failing together don't
+    // retry together
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            multiplier: DEFAULT_MULTIPLIER,
+            jitter: DEFAULT_JITTER,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Policy making a single attempt
+    pub fn no_retries() -> Self {
+        Self::default().with_max_attempts(1)
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.max_attempts == 0 {
+            return Err(IcebergError::Invalid(
+                "Retries need at least one attempt".to_string(),
+            ));
+        }
+        if self.initial_backoff > self.max_backoff {
+            return Err(IcebergError::Invalid(format!(
+                "Initial backoff {:?} exceeds max backoff {:?}",
+                self.initial_backoff, self.max_backoff
+            )));
+        }
+        if self.multiplier.is_nan() || self.multiplier < 1.0 {
+            return Err(IcebergError::Invalid(format!(
+                "Backoff multiplier {} is below 1",
+                self.multiplier
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(IcebergError::Invalid(format!(
+                "Jitter {} isn't between 0 and 1",
+                self.jitter
+            )));
+        }
+        Ok(())
+    }
+
+    // Wait before the given retry, the first one being 1, without jitter
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.powi(retry.saturating_sub(1) as i32);
+        self.initial_backoff
+            .mul_f64(factor.min(u32::MAX as f64))
+            .min(self.max_backoff)
+    }
+
+    fn jittered_backoff(&self, retry: u32) -> Duration {
+        // Uniform in [-1, 1]
+        let random = (Uuid::new_v4().as_u128() as u64) as f64 / u64::MAX as f64 * 2.0 - 1.0;
+        self.backoff(retry)
+            .mul_f64((1.0 + self.jitter * random).max(0.0))
+    }
+
+    // Runs the operation until it succeeds, fails with an error that isn't transient or runs out
+    // of attempts. `operation` describes it in logs
+    pub fn run<T>(&self, operation: &str, f: impl FnMut() -> Result<T>) -> Result<T> {
+        self.run_with(operation, is_transient, f)
+    }
+
+    // Like run, for operations failing with other errors, e.g. those of thrift clients
+    pub fn run_with<T, E: fmt::Display>(
+        &self,
+        operation: &str,
+        is_transient: impl Fn(&E) -> bool,
+        mut f: impl FnMut() -> std::result::Result<T, E>,
+    ) -> std::result::Result<T, E> {
+        let mut attempt = 1;
+        loop {
+            match f() {
+                Err(e) if attempt < self.max_attempts && is_transient(&e) => {
+                    let backoff = self.jittered_backoff(attempt);
+                    log::warn!(
+                        "Retrying {} in {:?} after attempt {} failed: {}",
+                        operation,
+                        backoff,
+                        attempt,
+                        e
+                    );
+                    thread::sleep(backoff);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+// Error of transient failures not described by an io::ErrorKind, e.g. HTTP 503 responses or
+// broken thrift connections, wrapped in IcebergError::Io by transient_error
+#[derive(Debug)]
+pub struct TransientError(pub String);
+
+impl fmt::Display for TransientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TransientError {}
+
+pub fn transient_error(message: String) -> IcebergError {
+    IcebergError::Io(std::io::Error::other(TransientError(message)))
+}
+
+// Whether retrying the failed operation may succeed: IO errors of interrupted or timed out
+// connections, and errors made with transient_error
+pub fn is_transient(error: &IcebergError) -> bool {
+    let IcebergError::Io(e) = error else {
+        return false;
+    };
+    match e.kind() {
+        ErrorKind::TimedOut
+        | ErrorKind::Interrupted
+        | ErrorKind::WouldBlock
+        | ErrorKind::ConnectionReset
+        | ErrorKind::ConnectionAborted
+        | ErrorKind::ConnectionRefused
+        | ErrorKind::BrokenPipe
+        | ErrorKind::UnexpectedEof => true,
+        _ => e
+            .get_ref()
+            .is_some_and(|inner| inner.is::<TransientError>()),
+    }
+}
+
+// FileIO retrying reads, listings and writes of the wrapped FileIO. Deletes and renames aren't
+// retried, since a retry of one that succeeded without a response would fail
+#[derive(Debug, Clone)]
+pub struct RetryingFileIO {
+    file_io: Arc<dyn FileIO>,
+    policy: RetryPolicy,
+}
+
+impl RetryingFileIO {
+    pub fn new(file_io: Arc<dyn FileIO>, policy: RetryPolicy) -> Self {
+        RetryingFileIO { file_io, policy }
+    }
+}
+
+impl FileIO for RetryingFileIO {
+    fn read(&self, location: &str) -> Result<Bytes> {
+        self.policy.run(&format!("read of {}", location), || {
+            self.file_io.read(location)
+        })
+    }
+
+    fn write(&self, location: &str, data: Bytes) -> Result<()> {
+        self.policy.run(&format!("write of {}", location), || {
+            self.file_io.write(location, data.clone())
+        })
+    }
+
+    fn exists(&self, location: &str) -> Result<bool> {
+        self.policy.run(&format!("lookup of {}", location), || {
+            self.file_io.exists(location)
+        })
+    }
+
+    fn delete(&self, location: &str) -> Result<()> {
+        self.file_io.delete(location)
+    }
+
+    fn list(&self, location: &str) -> Result<Vec<FileInfo>> {
+        self.policy.run(&format!("listing of {}", location), || {
+            self.file_io.list(location)
+        })
+    }
+
+    fn rename_if_absent(&self, from: &str, to: &str) -> Result<()> {
+        self.file_io.rename_if_absent(from, to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::iceberg::io::MemoryFileIO;
+
+    // FileIO failing the first calls with the given errors
+    #[derive(Debug)]
+    struct FlakyFileIO {
+        file_io: MemoryFileIO,
+        failures: Mutex<Vec<IcebergError>>,
+    }
+
+    impl FlakyFileIO {
+        fn fail(&self) -> Result<()> {
+            match self.failures.lock().unwrap().pop() {
+                Some(e) => Err(e),
+                None => Ok(()),
+            }
+        }
+    }
+
+    impl FileIO for FlakyFileIO {
+        fn read(&self, location: &str) -> Result<Bytes> {
+            self.fail()?;
+            self.file_io.read(location)
+        }
+
+        fn write(&self, location: &str, data: Bytes) -> Result<()> {
+            self.fail()?;
+            self.file_io.write(location, data)
+        }
+
+        fn exists(&self, location: &str) -> Result<bool> {
+            self.fail()?;
+            self.file_io.exists(location)
+        }
+
+        fn delete(&self, location: &str) -> Result<()> {
+            self.fail()?;
+            self.file_io.delete(location)
+        }
+
+        fn list(&self, location: &str) -> Result<Vec<FileInfo>> {
+            self.fail()?;
+            self.file_io.list(location)
+        }
+    }
+
+    fn timeout() -> IcebergError {
+        IcebergError::Io(std::io::Error::from(ErrorKind::TimedOut))
+    }
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy::new()
+            .with_initial_backoff(Duration::from_millis(1))
+            .with_max_backoff(Duration::from_millis(2))
+    }
+
+    #[test]
+    fn test_retrying_file_io() {
+        let flaky = Arc::new(FlakyFileIO {
+            file_io: MemoryFileIO::new(),
+            failures: Mutex::new(vec![]),
+        });
+        let file_io = RetryingFileIO::new(flaky.clone(), fast_policy());
+        *flaky.failures.lock().unwrap() = vec![transient_error("HTTP 503".to_string()), timeout()];
+        file_io.write("memory:/a", Bytes::from("a")).unwrap();
+        *flaky.failures.lock().unwrap() = vec![timeout(), timeout(), timeout()];
+        assert_eq!(Bytes::from("a"), file_io.read("memory:/a").unwrap());
+
+        // Gives up after the last attempt
+        *flaky.failures.lock().unwrap() = (0..4).map(|_| timeout()).collect();
+        assert!(is_transient(&file_io.read("memory:/a").unwrap_err()));
+        // Other errors aren't retried, nor are deletes
+        *flaky.failures.lock().unwrap() =
+            vec![IcebergError::NotFound("memory:/b".to_string()), timeout()];
+        assert!(matches!(
+            file_io.exists("memory:/a"),
+            Err(IcebergError::NotFound(_))
+        ));
+        *flaky.failures.lock().unwrap() = vec![timeout()];
+        assert!(file_io.delete("memory:/a").is_err());
+        assert!(file_io.exists("memory:/a").unwrap());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::new()
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_max_backoff(Duration::from_secs(1));
+        let backoffs: Vec<_> = (1..=5).map(|retry| policy.backoff(retry)).collect();
+        assert_eq!(
+            vec![100, 200, 400, 800, 1000],
+            backoffs
+                .iter()
+                .map(|backoff| backoff.as_millis())
+                .collect::<Vec<_>>()
+        );
+        for _ in 0..100 {
+            let backoff = policy.jittered_backoff(2);
+            assert!(backoff >= Duration::from_millis(160) && backoff <= Duration::from_millis(240));
+        }
+        assert_eq!(Duration::from_secs(1), policy.backoff(1000));
+
+        assert!(policy.validate().is_ok());
+        assert!(RetryPolicy::new().with_max_attempts(0).validate().is_err());
+        assert!(RetryPolicy::new().with_jitter(2.0).validate().is_err());
+        assert!(RetryPolicy::new().with_multiplier(0.5).validate().is_err());
+        assert!(RetryPolicy::new()
+            .with_initial_backoff(Duration::from_secs(60))
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&timeout()));
+        assert!(is_transient(&transient_error("throttled".to_string())));
+        assert!(!is_transient(&IcebergError::Io(std::io::Error::other(
+            "disk full"
+        ))));
+        assert!(!is_transient(&IcebergError::Invalid("x".to_string())));
+    }
+}
@@ -0,0 +1,295 @@
+// Rows of a table as typed values, for callers who just want the data without dealing with
+// scan plans and Arrow. Planning, reading the data files, applying delete files and projecting
+// the columns are done by a table scan, see Table::rows
+use std::sync::Arc;
+
+use arrow::array::{Array, AsArray, RecordBatch};
+
+use crate::iceberg::arrow::literal_from_array;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::expr::{BoundPredicate, Predicate};
+use crate::iceberg::reader::RecordBatchIter;
+use crate::iceberg::spec::schema::{IcebergType, StructField, StructType};
+use crate::iceberg::spec::values::Literal;
+use crate::iceberg::table::Table;
+
+// Value of a column of a row, nulls are None
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Primitive(Literal),
+    // Values of the fields of the struct, in the order of the schema
+    Struct(Vec<Option<Value>>),
+    List(Vec<Option<Value>>),
+    Map(Vec<(Value, Option<Value>)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    columns: Arc<[String]>,
+    values: Vec<Option<Value>>,
+}
+
+pub type RowIter = Box<dyn Iterator<Item = Result<Row>> + Send>;
+
+impl Row {
+    // Names of the columns of the row, in the order of its values
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn values(&self) -> &[Option<Value>] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<Option<Value>> {
+        self.values
+    }
+
+    // Value of a top-level column, None for nulls and unknown columns
+    pub fn get(&self, column: &str) -> Option<&Value> {
+        let index = self.columns.iter().position(|name| name == column)?;
+        self.values[index].as_ref()
+    }
+}
+
+// Reads the rows of the current snapshot of the table matching the filter, with the given
+// top-level columns or all of them
+pub(crate) fn read_rows(
+    table: &Table,
+    filter: Option<Predicate>,
+    columns: Option<&[&str]>,
+) -> Result<RowIter> {
+    let metadata = table.metadata();
+    // Scans read the current snapshot with the schema it was written with
+    let schema = match metadata
+        .current_snapshot()
+        .and_then(|snapshot| snapshot.schema_id)
+    {
+        Some(schema_id) => metadata.schema_by_id(schema_id).ok_or_else(|| {
+            IcebergError::Invalid(format!(
+                "Schema {} is missing from table metadata",
+                schema_id
+            ))
+        })?,
+        None => metadata.current_schema()?,
+    };
+    let schema = schema.schema();
+    let fields = match columns {
+        Some(columns) => columns
+            .iter()
+            .map(|column| {
+                schema
+                    .field_by_name(column)
+                    .cloned()
+                    .ok_or_else(|| IcebergError::NotFound(format!("Column {}", column)))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => schema.fields.clone(),
+    };
+
+    // Columns of the filter missing from the row are read to evaluate it, and dropped
+    let bound = filter
+        .as_ref()
+        .map(|filter| filter.bind(schema, true))
+        .transpose()?;
+    let mut read_fields = fields.clone();
+    if let Some(bound) = &bound {
+        for id in bound.field_ids() {
+            if read_fields.iter().any(|field| contains(field, id)) {
+                continue;
+            }
+            if let Some(field) = schema.fields.iter().find(|field| contains(field, id)) {
+                read_fields.push(field.clone());
+            }
+        }
+    }
+
+    let names: Vec<&str> = read_fields
+        .iter()
+        .map(|field| field.name.as_str())
+        .collect();
+    let mut scan = table.scan().select(&names);
+    if let Some(filter) = filter {
+        scan = scan.filter(filter);
+    }
+    let batches = scan.plan_files()?.to_arrow()?;
+    Ok(Box::new(RowBatches {
+        batches,
+        columns: fields.iter().map(|field| field.name.clone()).collect(),
+        fields: read_fields,
+        filter: bound,
+        rows: Vec::new().into_iter(),
+    }))
+}
+
+struct RowBatches {
+    batches: RecordBatchIter,
+    columns: Arc<[String]>,
+    // Columns read, the columns of the rows followed by the filter columns
+    fields: Vec<StructField>,
+    filter: Option<BoundPredicate>,
+    rows: std::vec::IntoIter<Row>,
+}
+
+impl RowBatches {
+    fn rows(&self, batch: &RecordBatch) -> Result<Vec<Row>> {
+        let mut rows = Vec::with_capacity(batch.num_rows());
+        for row in 0..batch.num_rows() {
+            let mut values = batch
+                .columns()
+                .iter()
+                .zip(&self.fields)
+                .map(|(array, field)| value(array.as_ref(), row, &field.field_type))
+                .collect::<Result<Vec<_>>>()?;
+            if let Some(filter) = &self.filter {
+                let matches = filter.evaluate(&|id| lookup(&self.fields, &values, id));
+                if !matches {
+                    continue;
+                }
+            }
+            values.truncate(self.columns.len());
+            rows.push(Row {
+                columns: self.columns.clone(),
+                values,
+            });
+        }
+        Ok(rows)
+    }
+}
+
+impl Iterator for RowBatches {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.rows.next() {
+                return Some(Ok(row));
+            }
+            let rows = match self.batches.next()? {
+                Ok(batch) => self.rows(&batch),
+                Err(e) => Err(e),
+            };
+            match rows {
+                Ok(rows) => self.rows = rows.into_iter(),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+// Whether the field is the field with the id or a struct holding it. Filters only reference
+// primitive fields of nested structs, see Predicate::bind
+fn contains(field: &StructField, id: i32) -> bool {
+    field.id == id
+        || match &field.field_type {
+            IcebergType::Struct(nested) => nested.fields.iter().any(|field| contains(field, id)),
+            _ => false,
+        }
+}
+
+// Primitive value of the field with the id among the values of the fields
+fn lookup(fields: &[StructField], values: &[Option<Value>], id: i32) -> Option<Literal> {
+    let (field, value) = fields
+        .iter()
+        .zip(values)
+        .find(|(field, _)| contains(field, id))?;
+    match (value.as_ref()?, &field.field_type) {
+        (Value::Primitive(literal), _) if field.id == id => Some(literal.clone()),
+        (Value::Struct(values), IcebergType::Struct(nested)) => lookup(&nested.fields, values, id),
+        _ => None,
+    }
+}
+
+fn value(array: &dyn Array, row: usize, field_type: &IcebergType) -> Result<Option<Value>> {
+    if array.is_null(row) {
+        return Ok(None);
+    }
+    let value = match field_type {
+        IcebergType::Primitive(primitive) => {
+            literal_from_array(array, row, primitive)?.map(Value::Primitive)
+        }
+        IcebergType::Struct(StructType { fields }) => {
+            let array = array.as_struct();
+            let values = array
+                .columns()
+                .iter()
+                .zip(fields)
+                .map(|(column, field)| value(column.as_ref(), row, &field.field_type))
+                .collect::<Result<_>>()?;
+            Some(Value::Struct(values))
+        }
+        IcebergType::List(list) => {
+            let elements = array.as_list::<i32>().value(row);
+            let values = (0..elements.len())
+                .map(|i| value(elements.as_ref(), i, &list.element))
+                .collect::<Result<_>>()?;
+            Some(Value::List(values))
+        }
+        IcebergType::Map(map) => {
+            let entries = array.as_map().value(row);
+            let (keys, values) = (entries.column(0), entries.column(1));
+            let entries = (0..entries.len())
+                .map(|i| {
+                    let key = value(keys.as_ref(), i, &map.key)?
+                        .ok_or_else(|| IcebergError::Invalid("Map with a null key".to_string()))?;
+                    Ok((key, value(values.as_ref(), i, &map.value)?))
+                })
+                .collect::<Result<_>>()?;
+            Some(Value::Map(entries))
+        }
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+    use crate::iceberg::writer::position_delete::PositionDeleteWriter;
+
+    fn ids(rows: RowIter) -> Vec<i64> {
+        let mut ids = rows
+            .map(|row| match row.unwrap().get("id") {
+                Some(Value::Primitive(Literal::Long(id))) => *id,
+                value => panic!("Unexpected id {:?}", value),
+            })
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1, 2, 3]);
+        let file = table.scan().plan_files().unwrap().tasks()[0]
+            .data_file
+            .clone();
+        let mut writer = PositionDeleteWriter::for_table(&table, vec![]).unwrap();
+        writer.delete(&file.file_path, 0);
+        let table = table
+            .new_row_delta()
+            .add_deletes(writer.close().unwrap())
+            .commit(&catalog)
+            .unwrap();
+        let table = append_ids(&catalog, &table, &[4, 5]);
+
+        assert_eq!(vec![2, 3, 4, 5], ids(table.rows(None, None).unwrap()));
+        let row = table.rows(None, None).unwrap().next().unwrap().unwrap();
+        assert_eq!(&["id".to_string(), "data".to_string()], row.columns());
+
+        // The filter applies to rows, on columns that aren't selected too
+        let filter = Predicate::greater_than("id", Literal::Long(3));
+        let rows = table.rows(Some(filter.clone()), Some(&["id"])).unwrap();
+        assert_eq!(vec![4, 5], ids(rows));
+        let rows = table.rows(Some(filter), Some(&["data"])).unwrap();
+        let rows = rows.collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(2, rows.len());
+        assert_eq!(&["data".to_string()], rows[0].columns());
+        assert_eq!(1, rows[0].values().len());
+
+        assert!(table.rows(None, Some(&["missing"])).is_err());
+    }
+}
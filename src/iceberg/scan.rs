@@ -0,0 +1,2208 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use arrow::array::BooleanArray;
+use arrow::compute::filter_record_batch;
+use arrow::datatypes::SchemaRef;
+use bytes::Bytes;
+use uuid::Uuid;
+
+use crate::iceberg::deletes::{DeleteFileIndex, DeleteFilter};
+use crate::iceberg::encryption::TableDecryption;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::expr::{BoundPredicate, InclusiveMetricsEvaluator, Predicate};
+use crate::iceberg::io::FileIO;
+use crate::iceberg::metrics::ScanMetrics;
+use crate::iceberg::progress::{ProgressPhase, ProgressReporter};
+use crate::iceberg::reader::{check_readable, ParquetReader, RecordBatchIter, DEFAULT_BATCH_SIZE};
+use crate::iceberg::spec::manifest::{
+    DataContentType, DataFile, FileFormat, ManifestEntry, ManifestStatus,
+};
+use crate::iceberg::spec::manifest_list::{FileType, ManifestListV2};
+use crate::iceberg::spec::name_mapping::NameMapping;
+use crate::iceberg::spec::schema::{
+    FieldNames, IcebergType, PrimitiveType, StructField, StructType,
+};
+use crate::iceberg::spec::snapshot::{Operation, SnapshotV2};
+use crate::iceberg::spec::values::Literal;
+use crate::iceberg::table::{SnapshotSelector, Table};
+
+// Builds a scan of a table. Scans read the current snapshot unless a snapshot is selected
+pub struct TableScan<'a> {
+    table: &'a Table,
+    snapshot: Option<SnapshotSelector>,
+    columns: Option<Vec<String>>,
+    filter: Option<Predicate>,
+    // Filter on the columns of file_metadata_schema
+    file_filter: Option<Predicate>,
+    spec_ids: Option<Vec<i32>>,
+    // Only plan the files appended after this snapshot, see appends_between
+    appended_after: Option<i64>,
+    config: ScanConfig,
+    progress: Option<Arc<dyn ProgressReporter>>,
+}
+
+// Settings of scans, e.g. shared by the scans of an application, applied with
+// TableScan::with_config
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ScanConfig {
+    // Resolve the columns of filters ignoring case when false
+    pub case_sensitive: bool,
+    // See TableScan::require_snapshot_stability
+    pub require_snapshot_stability: bool,
+    // Number of manifests read at the same time when planning, by default the number of CPUs up
+    // to MAX_DEFAULT_PLANNING_PARALLELISM
+    pub planning_parallelism: usize,
+    // Maximum number of rows of the record batches read by the plan
+    pub batch_size: usize,
+    // Parquet data files larger than this are planned as several tasks of consecutive row
+    // groups, see TableScan::with_split_size. Files are planned whole when None
+    pub split_size: Option<i64>,
+    // Number of open bins tasks are packed in by ScanPlan::task_groups
+    pub split_lookback: usize,
+    // Minimum weight of a task when packing tasks, so that groups don't open too many files
+    pub split_open_file_cost: i64,
+}
+
+// Guardrails for the scans of a table handle, e.g. a service exposing huge tables to self-serve
+// users. Planning fails instead of returning plans exceeding the limits
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScanLimits {
+    max_files: Option<usize>,
+    max_bytes: Option<i64>,
+    require_partition_filter: bool,
+    // When set, scans must select their columns among these
+    allowed_columns: Option<Vec<String>>,
+}
+
+// Identifies the table state a scan was planned against
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ScanFingerprint {
+    pub table_uuid: Uuid,
+    pub metadata_location: String,
+    // None for tables without snapshots
+    pub snapshot_id: Option<i64>,
+}
+
+// A data file to read as part of a scan, along with the delete files to apply to it. Tasks
+// read the row groups starting within their byte range, the whole file unless the scan splits
+// files
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileScanTask {
+    pub data_file: DataFile,
+    pub spec_id: i32,
+    pub sequence_number: i64,
+    pub deletes: Vec<DataFile>,
+    pub start: i64,
+    pub length: i64,
+}
+
+// Size of the files a scan reads, known once planned and before reading them. Record counts
+// are those of the data files, before applying delete files
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct ScanEstimate {
+    pub data_files: usize,
+    pub record_count: i64,
+    pub total_bytes: i64,
+    // Delete files applying to the data files, each counted once
+    pub delete_files: usize,
+    pub delete_bytes: i64,
+}
+
+// The files of a planned scan along with the table state they were planned from
+#[derive(Debug, Clone)]
+pub struct ScanPlan {
+    fingerprint: ScanFingerprint,
+    schema: StructType,
+    // Names the columns of the table in errors, including dropped ones
+    field_names: Arc<dyn FieldNames>,
+    projection: Option<Vec<i32>>,
+    // Skips row groups by their bloom filters
+    filter: Option<BoundPredicate>,
+    tasks: Vec<FileScanTask>,
+    require_snapshot_stability: bool,
+    batch_size: usize,
+    // Target size, lookback and open file cost of task_groups
+    split_size: i64,
+    split_lookback: usize,
+    split_open_file_cost: i64,
+    // Resolves the columns of data files without field ids
+    name_mapping: Option<NameMapping>,
+    decryption: TableDecryption,
+    file_io: Arc<dyn FileIO>,
+    progress: Option<Arc<dyn ProgressReporter>>,
+}
+
+impl ScanLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Maximum number of data files of a plan, after pruning
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = Some(max_files);
+        self
+    }
+
+    // Maximum total size of the data files of a plan, after pruning
+    pub fn with_max_bytes(mut self, max_bytes: i64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    // Scans must filter on a source column of the partition spec
+    pub fn require_partition_filter(mut self) -> Self {
+        self.require_partition_filter = true;
+        self
+    }
+
+    pub fn with_allowed_columns(mut self, columns: &[&str]) -> Self {
+        self.allowed_columns = Some(columns.iter().map(|column| column.to_string()).collect());
+        self
+    }
+
+    fn check_columns(&self, table: &Table, columns: Option<&[String]>) -> Result<()> {
+        let Some(allowed) = &self.allowed_columns else {
+            return Ok(());
+        };
+        let denied = match columns {
+            Some(columns) => columns
+                .iter()
+                .filter(|column| !allowed.contains(column))
+                .cloned()
+                .collect::<Vec<_>>(),
+            None => {
+                return Err(IcebergError::Forbidden(format!(
+                    "Scans of table {} must select columns among {}",
+                    table.ident(),
+                    allowed.join(", ")
+                )))
+            }
+        };
+        if denied.is_empty() {
+            Ok(())
+        } else {
+            Err(IcebergError::Forbidden(format!(
+                "Columns {} of table {} can't be scanned, allowed columns are {}",
+                denied.join(", "),
+                table.ident(),
+                allowed.join(", ")
+            )))
+        }
+    }
+
+    fn check_filter(
+        &self,
+        table: &Table,
+        schema: &StructType,
+        filter: Option<&BoundPredicate>,
+    ) -> Result<()> {
+        let required =
+            self.require_partition_filter || table.properties().partition_filter_required()?;
+        if !required {
+            return Ok(());
+        }
+        let spec = table.metadata().default_partition_spec()?;
+        let source_ids: Vec<i32> = spec.fields.iter().map(|field| field.source_id).collect();
+        if source_ids.is_empty() || filter.is_some_and(|filter| constrains(filter, &source_ids)) {
+            return Ok(());
+        }
+        let columns: Vec<&str> = schema
+            .fields
+            .iter()
+            .filter(|field| source_ids.contains(&field.id))
+            .map(|field| field.name.as_str())
+            .collect();
+        Err(IcebergError::Invalid(format!(
+            "Scans of table {} require a filter on a partition column: {}",
+            table.ident(),
+            columns.join(", ")
+        )))
+    }
+
+    fn check_plan(&self, table: &Table, tasks: &[FileScanTask]) -> Result<()> {
+        if let Some(max_files) = self.max_files.filter(|max| tasks.len() > *max) {
+            return Err(IcebergError::Invalid(format!(
+                "Scan of table {} reads {} files, more than the limit of {}. Add a more \
+                 selective filter",
+                table.ident(),
+                tasks.len(),
+                max_files
+            )));
+        }
+        let bytes: i64 = tasks
+            .iter()
+            .map(|task| task.data_file.file_size_in_bytes)
+            .sum();
+        if let Some(max_bytes) = self.max_bytes.filter(|max| bytes > *max) {
+            return Err(IcebergError::Invalid(format!(
+                "Scan of table {} reads {} bytes, more than the limit of {}. Add a more \
+                 selective filter",
+                table.ident(),
+                bytes,
+                max_bytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+pub const MAX_DEFAULT_PLANNING_PARALLELISM: usize = 8;
+// Size of the task groups of plans that don't split files
+pub const DEFAULT_SPLIT_SIZE: i64 = 128 * 1024 * 1024;
+const DEFAULT_SPLIT_LOOKBACK: usize = 10;
+const DEFAULT_SPLIT_OPEN_FILE_COST: i64 = 4 * 1024 * 1024;
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        ScanConfig {
+            case_sensitive: true,
+            require_snapshot_stability: false,
+            planning_parallelism: default_planning_parallelism(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            split_size: None,
+            split_lookback: DEFAULT_SPLIT_LOOKBACK,
+            split_open_file_cost: DEFAULT_SPLIT_OPEN_FILE_COST,
+        }
+    }
+}
+
+impl ScanConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    pub fn with_snapshot_stability(mut self, require: bool) -> Self {
+        self.require_snapshot_stability = require;
+        self
+    }
+
+    pub fn with_planning_parallelism(mut self, parallelism: usize) -> Self {
+        self.planning_parallelism = parallelism;
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn with_split_size(mut self, split_size: i64) -> Self {
+        self.split_size = Some(split_size);
+        self
+    }
+
+    pub fn with_split_lookback(mut self, lookback: usize) -> Self {
+        self.split_lookback = lookback;
+        self
+    }
+
+    pub fn with_split_open_file_cost(mut self, open_file_cost: i64) -> Self {
+        self.split_open_file_cost = open_file_cost;
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.planning_parallelism == 0 || self.batch_size == 0 {
+            return Err(IcebergError::Invalid(format!(
+                "Scans need a positive planning parallelism and batch size, got {} and {}",
+                self.planning_parallelism, self.batch_size
+            )));
+        }
+        if self.split_size.is_some_and(|size| size <= 0)
+            || self.split_lookback == 0
+            || self.split_open_file_cost < 0
+        {
+            return Err(IcebergError::Invalid(format!(
+                "Scans need a positive split size and lookback and an open file cost of at \
+                 least 0, got {:?}, {} and {}",
+                self.split_size, self.split_lookback, self.split_open_file_cost
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn default_planning_parallelism() -> usize {
+    std::thread::available_parallelism()
+        .map_or(1, |cpus| cpus.get())
+        .min(MAX_DEFAULT_PLANNING_PARALLELISM)
+}
+
+// Reads the entries of the manifests, in order, with up to `parallelism` manifests fetched and
+// decoded at the same time
+fn read_manifests(
+    table: &Table,
+    manifests: &[&ManifestListV2],
+    parallelism: usize,
+    progress: Option<&dyn ProgressReporter>,
+) -> Result<Vec<Vec<ManifestEntry>>> {
+    let phase = ProgressPhase::PlanningManifests;
+    if let Some(progress) = progress {
+        progress.start(phase, Some(manifests.len() as u64));
+    }
+    let read_manifest = |manifest: &ManifestListV2| {
+        let entries = table.manifest_entries(manifest)?;
+        if let Some(progress) = progress {
+            progress.bytes_read(phase, manifest.manifest_length.max(0) as u64);
+            progress.advance(phase, 1);
+        }
+        Ok(entries)
+    };
+    let entries = read_manifests_with(manifests, parallelism, read_manifest);
+    if let Some(progress) = progress {
+        progress.finish(phase);
+    }
+    entries
+}
+
+fn read_manifests_with(
+    manifests: &[&ManifestListV2],
+    parallelism: usize,
+    read_manifest: impl Fn(&ManifestListV2) -> Result<Vec<ManifestEntry>> + Sync,
+) -> Result<Vec<Vec<ManifestEntry>>> {
+    if parallelism <= 1 || manifests.len() <= 1 {
+        return manifests
+            .iter()
+            .map(|manifest| read_manifest(manifest))
+            .collect();
+    }
+    let entries: Vec<Mutex<Option<Result<Vec<ManifestEntry>>>>> =
+        manifests.iter().map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    // Reads of the workers belong to the span of the planning, and go to the subscriber of the
+    // calling thread
+    let span = tracing::Span::current();
+    let dispatch = tracing::dispatcher::get_default(Clone::clone);
+    std::thread::scope(|scope| {
+        for _ in 0..parallelism.min(manifests.len()) {
+            scope.spawn(|| {
+                let _dispatch = tracing::dispatcher::set_default(&dispatch);
+                let _span = span.enter();
+                // Stop fetching manifests once one of them failed
+                while !failed.load(Ordering::SeqCst) {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(manifest) = manifests.get(index) else {
+                        break;
+                    };
+                    let read = read_manifest(manifest);
+                    if read.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    *entries[index].lock().unwrap() = Some(read);
+                }
+            });
+        }
+    });
+    // Manifests are claimed in order, so the manifests left unread after a failure all come
+    // after the failed one
+    entries
+        .into_iter()
+        .map_while(|entries| entries.into_inner().unwrap())
+        .collect()
+}
+
+// Snapshots after `from_snapshot_id` up to `to`, newest first. `to` must descend from
+// `from_snapshot_id`
+pub(crate) fn snapshots_between<'t>(
+    table: &'t Table,
+    from_snapshot_id: i64,
+    to: &'t SnapshotV2,
+) -> Result<Vec<&'t SnapshotV2>> {
+    let metadata = table.metadata();
+    let mut snapshots = vec![];
+    let mut snapshot = Some(to);
+    while let Some(current) = snapshot {
+        if current.snapshot_id == from_snapshot_id {
+            return Ok(snapshots);
+        }
+        snapshots.push(current);
+        snapshot = current
+            .parent_snapshot_id
+            .and_then(|parent| metadata.snapshot_by_id(parent));
+    }
+    Err(IcebergError::Invalid(format!(
+        "Snapshot {} isn't an ancestor of snapshot {} of table {}",
+        from_snapshot_id,
+        to.snapshot_id,
+        table.ident()
+    )))
+}
+
+// Whether every row matching the predicate is restricted by a predicate on one of the fields
+fn constrains(predicate: &BoundPredicate, field_ids: &[i32]) -> bool {
+    match predicate {
+        BoundPredicate::AlwaysTrue => false,
+        BoundPredicate::AlwaysFalse => true,
+        BoundPredicate::And(left, right) => {
+            constrains(left, field_ids) || constrains(right, field_ids)
+        }
+        BoundPredicate::Or(left, right) => {
+            constrains(left, field_ids) && constrains(right, field_ids)
+        }
+        BoundPredicate::Unary { term, .. }
+        | BoundPredicate::Binary { term, .. }
+        | BoundPredicate::Set { term, .. } => field_ids.contains(&term.field_id),
+    }
+}
+
+// Columns of the manifest entries of data files that TableScan::filter_files can filter on
+pub fn file_metadata_schema() -> StructType {
+    let field = |id: i32, name: &str, primitive: PrimitiveType| StructField {
+        id,
+        name: name.to_string(),
+        required: true,
+        field_type: IcebergType::Primitive(primitive),
+        doc: None,
+        initial_default: None,
+        write_default: None,
+    };
+    StructType {
+        fields: vec![
+            field(1, "file_path", PrimitiveType::String),
+            field(2, "file_format", PrimitiveType::String),
+            field(3, "record_count", PrimitiveType::Long),
+            field(4, "file_size_in_bytes", PrimitiveType::Long),
+            field(5, "spec_id", PrimitiveType::Int),
+            field(6, "sequence_number", PrimitiveType::Long),
+        ],
+    }
+}
+
+// Values of the columns of file_metadata_schema for a data file, by field id
+fn file_metadata<'f>(
+    data_file: &'f DataFile,
+    spec_id: i32,
+    sequence_number: i64,
+) -> impl Fn(i32) -> Option<Literal> + 'f {
+    move |field_id| match field_id {
+        1 => Some(Literal::String(data_file.file_path.clone())),
+        2 => Some(Literal::String(data_file.file_format.to_string())),
+        3 => Some(Literal::Long(data_file.record_count)),
+        4 => Some(Literal::Long(data_file.file_size_in_bytes)),
+        5 => Some(Literal::Int(spec_id)),
+        6 => Some(Literal::Long(sequence_number)),
+        _ => None,
+    }
+}
+
+impl<'a> TableScan<'a> {
+    pub(crate) fn new(table: &'a Table) -> Self {
+        TableScan {
+            table,
+            snapshot: None,
+            columns: None,
+            filter: None,
+            file_filter: None,
+            spec_ids: None,
+            appended_after: None,
+            config: ScanConfig::default(),
+            progress: None,
+        }
+    }
+
+    // Time travel to the given snapshot
+    pub fn with_snapshot_id(mut self, snapshot_id: i64) -> Self {
+        self.snapshot = Some(SnapshotSelector::Id(snapshot_id));
+        self
+    }
+
+    // Time travel to a snapshot selected by id, time or ref
+    pub fn as_of(mut self, selector: SnapshotSelector) -> Self {
+        self.snapshot = Some(selector);
+        self
+    }
+
+    // Only read the given top-level columns, in the given order
+    pub fn select(mut self, columns: &[&str]) -> Self {
+        self.columns = Some(columns.iter().map(|column| column.to_string()).collect());
+        self
+    }
+
+    // Skip data files whose column metrics show they can't contain rows matching the filter, and
+    // row groups whose bloom filters show the same. Rows of the remaining row groups are not
+    // filtered
+    pub fn filter(mut self, predicate: Predicate) -> Self {
+        self.filter = Some(match self.filter.take() {
+            Some(filter) => filter.and(predicate),
+            None => predicate,
+        });
+        self
+    }
+
+    // Only plan data files whose manifest entry matches the predicate, e.g. files larger than
+    // some size or under some path prefix. The predicate references the columns of
+    // file_metadata_schema instead of the table schema
+    pub fn filter_files(mut self, predicate: Predicate) -> Self {
+        self.file_filter = Some(match self.file_filter.take() {
+            Some(filter) => filter.and(predicate),
+            None => predicate,
+        });
+        self
+    }
+
+    // Only plan the data files of manifests written with one of the given partition specs, e.g.
+    // to look at the data written before a partition spec change. Delete files of all specs
+    // still apply to the planned files
+    pub fn with_spec_ids(mut self, spec_ids: &[i32]) -> Self {
+        self.spec_ids = Some(spec_ids.to_vec());
+        self
+    }
+
+    // Only plan the data files appended by the snapshots after `from_snapshot_id` up to
+    // `to_snapshot_id` included, e.g. to ingest the new rows of a table incrementally. The
+    // snapshots are the ancestors of `to_snapshot_id` down to `from_snapshot_id`, and files are
+    // only planned from the ones committing appends: files added by overwrites and rewrites are
+    // left out, and delete files are not applied to the appended files
+    pub fn appends_between(mut self, from_snapshot_id: i64, to_snapshot_id: i64) -> Self {
+        self.snapshot = Some(SnapshotSelector::Id(to_snapshot_id));
+        self.appended_after = Some(from_snapshot_id);
+        self
+    }
+
+    // Resolve the columns of the filter ignoring case
+    pub fn case_insensitive(mut self) -> Self {
+        self.config.case_sensitive = false;
+        self
+    }
+
+    // Fail reads of the plan if any of its files disappeared since planning (e.g. removed by
+    // snapshot expiration), instead of failing half way or returning partial results. Repeated
+    // reads of such a plan either return the same rows or an error
+    pub fn require_snapshot_stability(mut self) -> Self {
+        self.config.require_snapshot_stability = true;
+        self
+    }
+
+    // Maximum number of manifests fetched and decoded at the same time when planning, by
+    // default the number of CPUs up to MAX_DEFAULT_PLANNING_PARALLELISM
+    pub fn with_planning_parallelism(mut self, parallelism: usize) -> Self {
+        self.config.planning_parallelism = parallelism.max(1);
+        self
+    }
+
+    // Plans the Parquet data files larger than the split size as several tasks, each reading
+    // the consecutive row groups starting within its byte range, so that executors can read a
+    // large file in parallel. Files are split at their split offsets, files without them are
+    // planned whole
+    pub fn with_split_size(mut self, split_size: i64) -> Self {
+        self.config.split_size = Some(split_size);
+        self
+    }
+
+    // Replaces the settings of the scan, checked when planning
+    pub fn with_config(mut self, config: ScanConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    // Reports the manifests read when planning, and the files read by the plan
+    pub fn with_progress(mut self, progress: Arc<dyn ProgressReporter>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn plan_files(self) -> Result<ScanPlan> {
+        self.plan(false)
+    }
+
+    // Plans the scan to estimate the size of the files it reads. Unlike plan_files, the scan
+    // limits on files and bytes of the table don't apply, so that callers can show the cost of
+    // scans exceeding them, and no scan metrics are reported
+    pub fn estimate(self) -> Result<ScanEstimate> {
+        Ok(self.plan(true)?.estimate())
+    }
+
+    #[tracing::instrument(
+        name = "plan_files",
+        level = "debug",
+        skip(self),
+        fields(table = %self.table.ident())
+    )]
+    fn plan(self, estimate: bool) -> Result<ScanPlan> {
+        let start = Instant::now();
+        self.config.validate()?;
+        let metadata = self.table.metadata();
+        let snapshot = match &self.snapshot {
+            Some(selector) => Some(self.table.snapshot_at(selector)?),
+            None => metadata.current_snapshot(),
+        };
+
+        // Snapshots are read with the schema they were written with
+        let schema = match snapshot.and_then(|snapshot| snapshot.schema_id) {
+            Some(schema_id) => metadata.schema_by_id(schema_id).ok_or_else(|| {
+                IcebergError::Invalid(format!(
+                    "Schema {} is missing from table metadata",
+                    schema_id
+                ))
+            })?,
+            None => metadata.current_schema()?,
+        };
+        let limits = self.table.scan_limits();
+        limits.check_columns(self.table, self.columns.as_deref())?;
+        let projection = self
+            .columns
+            .map(|columns| {
+                columns
+                    .iter()
+                    .map(|column| {
+                        schema
+                            .schema()
+                            .field_by_name(column)
+                            .map(|field| field.id)
+                            .ok_or_else(|| IcebergError::NotFound(format!("Column {}", column)))
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .transpose()?;
+        let filter_description = self.filter.as_ref().map(ToString::to_string);
+        let filter = self
+            .filter
+            .map(|filter| filter.bind(schema.schema(), self.config.case_sensitive))
+            .transpose()?;
+        limits.check_filter(self.table, schema.schema(), filter.as_ref())?;
+        let evaluator = filter
+            .as_ref()
+            .map(|filter| InclusiveMetricsEvaluator::new(filter).with_field_names(schema));
+        let file_filter = self
+            .file_filter
+            .map(|filter| filter.bind(&file_metadata_schema(), self.config.case_sensitive))
+            .transpose()?;
+
+        let file_io = self.table.file_io();
+        let mut tasks = vec![];
+        let mut metrics = ScanMetrics {
+            table: self.table.ident().clone(),
+            snapshot_id: snapshot.map(|snapshot| snapshot.snapshot_id),
+            filter: filter_description,
+            planning_duration: Duration::ZERO,
+            total_data_manifests: 0,
+            total_delete_manifests: 0,
+            scanned_data_manifests: 0,
+            skipped_data_manifests: 0,
+            result_data_files: 0,
+            skipped_data_files: 0,
+            result_delete_files: 0,
+            total_file_size_bytes: 0,
+        };
+        if let Some(snapshot) = snapshot {
+            let manifests = match self.appended_after {
+                Some(from_snapshot_id) => {
+                    // The manifests each append wrote, which hold its files as added even after
+                    // later snapshots rewrote the manifests or removed the files
+                    let mut manifests = vec![];
+                    let snapshots = snapshots_between(self.table, from_snapshot_id, snapshot)?;
+                    let appends = snapshots
+                        .into_iter()
+                        .filter(|snapshot| snapshot.summary.operation == Operation::Append);
+                    for append in appends {
+                        manifests.extend(self.table.manifests(append)?.into_iter().filter(
+                            |manifest| {
+                                manifest.content == FileType::Data
+                                    && manifest.added_snapshot_id == append.snapshot_id
+                            },
+                        ));
+                    }
+                    manifests
+                }
+                None => self.table.manifests(snapshot)?,
+            };
+            let delete_manifests: Vec<&ManifestListV2> = manifests
+                .iter()
+                .filter(|m| m.content == FileType::Delete)
+                .collect();
+            let data_manifests: Vec<&ManifestListV2> = manifests
+                .iter()
+                .filter(|manifest| {
+                    manifest.content == FileType::Data
+                        && self
+                            .spec_ids
+                            .as_ref()
+                            .is_none_or(|ids| ids.contains(&manifest.partition_spec_id))
+                })
+                .collect();
+            metrics.total_delete_manifests = delete_manifests.len() as u64;
+            metrics.scanned_data_manifests = data_manifests.len() as u64;
+            metrics.total_data_manifests = manifests
+                .iter()
+                .filter(|manifest| manifest.content == FileType::Data)
+                .count() as u64;
+            metrics.skipped_data_manifests =
+                metrics.total_data_manifests - metrics.scanned_data_manifests;
+            let mut entries = read_manifests(
+                self.table,
+                &[delete_manifests.as_slice(), data_manifests.as_slice()].concat(),
+                self.config.planning_parallelism,
+                self.progress.as_deref(),
+            )?;
+            let data_entries = entries.split_off(delete_manifests.len());
+
+            let mut deletes = DeleteFileIndex::default();
+            for (manifest, entries) in delete_manifests.iter().zip(entries) {
+                for entry in entries {
+                    if entry.is_live() {
+                        deletes.add(manifest.partition_spec_id, entry);
+                    }
+                }
+            }
+            for (manifest, entries) in data_manifests.iter().zip(data_entries) {
+                for entry in entries {
+                    if !entry.is_live() || entry.data_file.content != DataContentType::Data {
+                        continue;
+                    }
+                    if self.appended_after.is_some() && entry.status != ManifestStatus::Added {
+                        continue;
+                    }
+                    let sequence_number = entry.sequence_number.unwrap_or_default();
+                    if let Some(file_filter) = &file_filter {
+                        let metadata = file_metadata(
+                            &entry.data_file,
+                            manifest.partition_spec_id,
+                            sequence_number,
+                        );
+                        if !file_filter.evaluate(&metadata) {
+                            metrics.skipped_data_files += 1;
+                            continue;
+                        }
+                    }
+                    if evaluator
+                        .as_ref()
+                        .map_or(Ok(true), |e| e.might_match(&entry.data_file))?
+                    {
+                        tasks.push(FileScanTask {
+                            deletes: deletes.for_data_file(
+                                manifest.partition_spec_id,
+                                sequence_number,
+                                &entry.data_file,
+                            ),
+                            sequence_number,
+                            start: 0,
+                            length: entry.data_file.file_size_in_bytes,
+                            data_file: entry.data_file,
+                            spec_id: manifest.partition_spec_id,
+                        });
+                    } else {
+                        metrics.skipped_data_files += 1;
+                    }
+                }
+            }
+        }
+
+        if !estimate {
+            limits.check_plan(self.table, &tasks)?;
+        }
+        let delete_files: HashSet<&str> = tasks
+            .iter()
+            .flat_map(|task| &task.deletes)
+            .map(|delete| delete.file_path.as_str())
+            .collect();
+        metrics.result_data_files = tasks.len() as u64;
+        metrics.result_delete_files = delete_files.len() as u64;
+        metrics.total_file_size_bytes = tasks
+            .iter()
+            .map(|task| task.data_file.file_size_in_bytes.max(0) as u64)
+            .sum();
+        metrics.planning_duration = start.elapsed();
+        if !estimate {
+            self.table.metrics_reporter().report_scan(&metrics);
+        }
+        if let Some(split_size) = self.config.split_size {
+            tasks = tasks
+                .into_iter()
+                .flat_map(|task| split_task(task, split_size))
+                .collect();
+        }
+
+        Ok(ScanPlan {
+            fingerprint: ScanFingerprint {
+                table_uuid: metadata.table_uuid,
+                metadata_location: self.table.metadata_location().to_string(),
+                snapshot_id: snapshot.map(|snapshot| snapshot.snapshot_id),
+            },
+            schema: schema.schema().clone(),
+            field_names: Arc::new(metadata.clone()),
+            projection,
+            filter: filter.clone(),
+            tasks,
+            require_snapshot_stability: self.config.require_snapshot_stability,
+            batch_size: self.config.batch_size,
+            split_size: self.config.split_size.unwrap_or(DEFAULT_SPLIT_SIZE),
+            split_lookback: self.config.split_lookback,
+            split_open_file_cost: self.config.split_open_file_cost,
+            name_mapping: metadata.name_mapping()?,
+            decryption: TableDecryption::new(self.table.key_management_client().cloned(), metadata),
+            file_io: file_io.clone(),
+            progress: self.progress,
+        })
+    }
+}
+
+// Splits the task of a Parquet file larger than the split size in tasks of the consecutive row
+// groups starting within ranges of at least the split size, the last one excepted
+fn split_task(task: FileScanTask, split_size: i64) -> Vec<FileScanTask> {
+    let file = &task.data_file;
+    let file_size = file.file_size_in_bytes;
+    let offsets = match &file.split_offsets {
+        Some(offsets) if file.file_format == FileFormat::Parquet && file_size > split_size => {
+            offsets
+        }
+        _ => return vec![task],
+    };
+    // Offsets written by other implementations are only trusted if ordered and within the file
+    let valid = offsets.windows(2).all(|pair| pair[0] < pair[1])
+        && offsets.first().is_some_and(|first| *first >= 0)
+        && offsets.last().is_some_and(|last| *last < file_size);
+    if !valid {
+        return vec![task];
+    }
+    let mut ranges = vec![];
+    let mut start = 0;
+    for offset in &offsets[1..] {
+        if offset - start >= split_size {
+            ranges.push((start, offset - start));
+            start = *offset;
+        }
+    }
+    ranges.push((start, file_size - start));
+    if ranges.len() == 1 {
+        return vec![task];
+    }
+    ranges
+        .into_iter()
+        .map(|(start, length)| FileScanTask {
+            start,
+            length,
+            ..task.clone()
+        })
+        .collect()
+}
+
+// Reads the data files of a table with a schema and projection, applying delete files
+#[derive(Clone)]
+pub(crate) struct FileReader {
+    reader: ParquetReader,
+    schema: StructType,
+    field_names: Arc<dyn FieldNames>,
+    projection: Option<Vec<i32>>,
+    decryption: TableDecryption,
+    file_io: Arc<dyn FileIO>,
+}
+
+impl FileReader {
+    pub(crate) fn try_new(
+        table: &Table,
+        schema: &StructType,
+        projection: Option<Vec<i32>>,
+        batch_size: usize,
+    ) -> Result<Self> {
+        let metadata = table.metadata();
+        let mut reader = ParquetReader::try_new(schema)?.with_batch_size(batch_size);
+        if let Some(name_mapping) = metadata.name_mapping()? {
+            reader = reader.with_name_mapping(name_mapping);
+        }
+        if let Some(projection) = &projection {
+            reader = reader.with_projection(projection.clone());
+        }
+        Ok(FileReader {
+            reader,
+            schema: schema.clone(),
+            field_names: Arc::new(metadata.clone()),
+            projection,
+            decryption: TableDecryption::new(table.key_management_client().cloned(), metadata),
+            file_io: table.file_io().clone(),
+        })
+    }
+
+    pub(crate) fn file_io(&self) -> &Arc<dyn FileIO> {
+        &self.file_io
+    }
+
+    // Reads the rows of the data file for which `keep` returns true, given whether the filter
+    // of each group of delete files keeps them, e.g. the rows the single group of a scan task
+    // keeps. Only the row groups starting within the byte range are read when given
+    pub(crate) fn read(
+        &self,
+        data_file: &DataFile,
+        data: Bytes,
+        range: Option<(i64, i64)>,
+        deletes: &[Vec<DataFile>],
+        keep: fn(&[bool]) -> bool,
+    ) -> Result<RecordBatchIter> {
+        let file_decryption = self.decryption.file_properties(data_file)?;
+        let reader = match range {
+            Some((start, length)) => self.reader.clone().with_range(start, length),
+            None => self.reader.clone(),
+        };
+        if deletes.iter().all(Vec::is_empty) {
+            if keep(&vec![true; deletes.len()]) {
+                return reader.read_file(
+                    data_file.file_format,
+                    &data_file.file_path,
+                    data,
+                    file_decryption,
+                );
+            }
+            return Ok(Box::new(std::iter::empty()));
+        }
+
+        let filters = deletes
+            .iter()
+            .map(|deletes| {
+                DeleteFilter::load(
+                    self.file_io.as_ref(),
+                    &self.decryption,
+                    &self.schema,
+                    self.field_names.as_ref(),
+                    data_file,
+                    deletes,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // Equality columns missing from the projection are read for the filters and dropped
+        // afterwards. Deleted positions count the rows of all row groups, so none is skipped
+        // but those before the range
+        let mut reader = reader.without_filter();
+        let mut columns = None;
+        if let Some(projection) = &self.projection {
+            let mut field_ids = projection.clone();
+            for id in filters.iter().flat_map(DeleteFilter::equality_field_ids) {
+                if !field_ids.contains(&id) {
+                    field_ids.push(id);
+                }
+            }
+            reader = reader.with_projection(field_ids);
+            columns = Some((0..projection.len()).collect::<Vec<_>>());
+        }
+        let mut position = match data_file.file_format {
+            FileFormat::Parquet => {
+                reader.first_row_position(data.clone(), file_decryption.clone())?
+            }
+            _ => 0,
+        };
+        let batches = reader.read_file(
+            data_file.file_format,
+            &data_file.file_path,
+            data,
+            file_decryption,
+        )?;
+        Ok(Box::new(batches.map(move |batch| {
+            let batch = batch?;
+            let offset = position;
+            position += batch.num_rows() as i64;
+            let kept = filters
+                .iter()
+                .map(|filter| filter.kept(&batch, offset))
+                .collect::<Result<Vec<_>>>()?;
+            // A single group of deletes kept as is is applied as a whole, otherwise the groups
+            // are combined row by row
+            let selected = if kept.len() == 1 && keep(&[true]) && !keep(&[false]) {
+                kept.into_iter().next().unwrap()
+            } else {
+                let mut row_kept = vec![false; kept.len()];
+                (0..batch.num_rows())
+                    .map(|row| {
+                        for (row_kept, kept) in row_kept.iter_mut().zip(&kept) {
+                            *row_kept = kept.value(row);
+                        }
+                        keep(&row_kept)
+                    })
+                    .collect()
+            };
+            let batch = if selected.count_set_bits() == batch.num_rows() {
+                batch
+            } else {
+                filter_record_batch(&batch, &BooleanArray::new(selected, None))?
+            };
+            match &columns {
+                Some(columns) => Ok(batch.project(columns)?),
+                None => Ok(batch),
+            }
+        })))
+    }
+}
+
+impl ScanPlan {
+    pub fn fingerprint(&self) -> &ScanFingerprint {
+        &self.fingerprint
+    }
+
+    pub fn tasks(&self) -> &[FileScanTask] {
+        &self.tasks
+    }
+
+    // Size of the files of the plan, files split in several tasks counted once
+    pub fn estimate(&self) -> ScanEstimate {
+        let mut data_files = HashSet::new();
+        let mut deletes = HashSet::new();
+        let mut estimate = ScanEstimate::default();
+        for task in &self.tasks {
+            if !data_files.insert(task.data_file.file_path.as_str()) {
+                continue;
+            }
+            estimate.data_files += 1;
+            estimate.record_count += task.data_file.record_count;
+            estimate.total_bytes += task.data_file.file_size_in_bytes;
+            for delete in &task.deletes {
+                if deletes.insert(delete.file_path.as_str()) {
+                    estimate.delete_files += 1;
+                    estimate.delete_bytes += delete.file_size_in_bytes;
+                }
+            }
+        }
+        estimate
+    }
+
+    // Packs the tasks in groups of about the split size, e.g. one group per executor thread.
+    // Tasks weigh their length, at least the open file cost, and go to the first of the last
+    // `split_lookback` groups with room for them. A new group is started otherwise, closing the
+    // oldest one when there are too many
+    pub fn task_groups(&self) -> Vec<Vec<FileScanTask>> {
+        let mut groups = vec![];
+        let mut open: Vec<(i64, Vec<FileScanTask>)> = vec![];
+        for task in &self.tasks {
+            let weight = task.length.max(self.split_open_file_cost);
+            match open
+                .iter_mut()
+                .find(|(size, _)| size + weight <= self.split_size)
+            {
+                Some((size, tasks)) => {
+                    *size += weight;
+                    tasks.push(task.clone());
+                }
+                None => {
+                    if open.len() >= self.split_lookback {
+                        groups.push(open.remove(0).1);
+                    }
+                    open.push((weight, vec![task.clone()]));
+                }
+            }
+        }
+        groups.extend(open.into_iter().map(|(_, tasks)| tasks));
+        groups
+    }
+
+    // Schema of the record batches read by to_arrow
+    pub fn arrow_schema(&self) -> Result<SchemaRef> {
+        self.reader()?.output_schema()
+    }
+
+    fn reader(&self) -> Result<ParquetReader> {
+        let mut reader = ParquetReader::try_new(&self.schema)?.with_batch_size(self.batch_size);
+        if let Some(name_mapping) = &self.name_mapping {
+            reader = reader.with_name_mapping(name_mapping.clone());
+        }
+        if let Some(filter) = &self.filter {
+            reader = reader.with_filter(filter.clone());
+        }
+        Ok(match &self.projection {
+            Some(projection) => reader.with_projection(projection.clone()),
+            None => reader,
+        })
+    }
+
+    fn file_reader(&self) -> Result<FileReader> {
+        Ok(FileReader {
+            reader: self.reader()?,
+            schema: self.schema.clone(),
+            field_names: self.field_names.clone(),
+            projection: self.projection.clone(),
+            decryption: self.decryption.clone(),
+            file_io: self.file_io.clone(),
+        })
+    }
+
+    // Stops reporting the progress of reads of the plan
+    pub fn without_progress(mut self) -> Self {
+        self.progress = None;
+        self
+    }
+
+    // Only keep the tasks matching the predicate, e.g. to read part of the plan
+    pub fn retain_tasks(&mut self, predicate: impl FnMut(&FileScanTask) -> bool) {
+        self.tasks.retain(predicate);
+    }
+
+    // Checks that every file of the plan can still be read
+    pub fn verify_files(&self) -> Result<()> {
+        for task in &self.tasks {
+            if !self.file_io.exists(&task.data_file.file_path)? {
+                return Err(IcebergError::NotFound(format!(
+                    "Data file {} of snapshot {} (metadata {}) no longer exists, it was most \
+                     likely removed by snapshot expiration. Plan the scan again to read the \
+                     current state of the table",
+                    task.data_file.file_path,
+                    self.fingerprint
+                        .snapshot_id
+                        .map_or("none".to_string(), |id| id.to_string()),
+                    self.fingerprint.metadata_location
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // Reads the files of the plan into record batches of the scan schema
+    pub fn to_arrow(&self) -> Result<RecordBatchIter> {
+        let reader = self.task_reader()?;
+        let finish = reader.clone();
+        let batches = self
+            .tasks
+            .clone()
+            .into_iter()
+            .flat_map(move |task| reader.read(task))
+            // The phase finishes once every batch was read
+            .chain(
+                std::iter::once_with(move || {
+                    finish.finish();
+                    None
+                })
+                .flatten(),
+            );
+        Ok(Box::new(batches))
+    }
+
+    // Checks that the files of the plan can be read and starts reporting the progress of reads
+    pub(crate) fn task_reader(&self) -> Result<TaskReader> {
+        if self.require_snapshot_stability {
+            self.verify_files()?;
+        }
+        for task in &self.tasks {
+            check_readable(task.data_file.file_format, &task.data_file.file_path)?;
+        }
+        if let Some(progress) = &self.progress {
+            progress.start(ProgressPhase::ReadingFiles, Some(self.tasks.len() as u64));
+        }
+        Ok(TaskReader {
+            reader: self.file_reader()?,
+            file_io: self.file_io.clone(),
+            progress: self.progress.clone(),
+        })
+    }
+}
+
+// Reads the tasks of a plan, see ScanPlan::task_reader
+#[derive(Clone)]
+pub(crate) struct TaskReader {
+    reader: FileReader,
+    file_io: Arc<dyn FileIO>,
+    progress: Option<Arc<dyn ProgressReporter>>,
+}
+
+impl TaskReader {
+    // Reads the batches of a task, a failure being the only item. Batches are decoded as they
+    // are consumed, within the span of their file
+    pub(crate) fn read(&self, task: FileScanTask) -> RecordBatchIter {
+        let span = tracing::debug_span!(
+            "read_data_file",
+            file_path = %task.data_file.file_path,
+            file_size_in_bytes = task.data_file.file_size_in_bytes,
+            delete_files = task.deletes.len(),
+        );
+        let read = || -> Result<RecordBatchIter> {
+            let data = self.file_io.read(&task.data_file.file_path)?;
+            if let Some(progress) = &self.progress {
+                progress.bytes_read(ProgressPhase::ReadingFiles, data.len() as u64);
+                progress.advance(ProgressPhase::ReadingFiles, 1);
+            }
+            let range = (task.length < task.data_file.file_size_in_bytes)
+                .then_some((task.start, task.length));
+            self.reader
+                .read(&task.data_file, data, range, &[task.deletes], |kept| {
+                    kept[0]
+                })
+        };
+        match span.in_scope(read) {
+            Ok(mut batches) => {
+                Box::new(std::iter::from_fn(move || span.in_scope(|| batches.next())))
+            }
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        }
+    }
+
+    // Finishes reporting the progress once every task was read
+    pub(crate) fn finish(&self) {
+        if let Some(progress) = &self.progress {
+            progress.finish(ProgressPhase::ReadingFiles);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{Array, Int64Array};
+
+    use std::collections::HashMap;
+
+    use std::sync::{Arc, Mutex};
+
+    use bytes::Bytes;
+    use parquet::arrow::ArrowWriter;
+    use parquet::encryption::encrypt::FileEncryptionProperties;
+    use parquet::file::properties::WriterProperties;
+
+    use super::{ScanConfig, ScanEstimate, ScanLimits, ScanPlan};
+    use crate::iceberg::arrow::schema_to_arrow;
+    use crate::iceberg::catalog::IcebergCatalog;
+    use crate::iceberg::encryption::ENCRYPTION_KEY_ID_PROPERTY;
+    use crate::iceberg::error::IcebergError;
+    use crate::iceberg::expr::Predicate;
+    use crate::iceberg::progress::tests::RecordingReporter;
+    use crate::iceberg::progress::ProgressPhase;
+    use crate::iceberg::properties::PARTITION_FILTER_REQUIRED_PROPERTY;
+    use crate::iceberg::spec::avro::schema_to_avro;
+    use crate::iceberg::spec::manifest::FileFormat;
+    use crate::iceberg::spec::partition_spec::{PartitionField, PartitionSpec, Transform};
+    use crate::iceberg::spec::table_metadata::TableMetadata;
+    use crate::iceberg::spec::values::Literal;
+    use crate::iceberg::table::Table;
+    use crate::iceberg::writer::partitioned::PartitionedWriter;
+    use crate::iceberg::writer::position_delete::PositionDeleteWriter;
+    use crate::iceberg::writer::{
+        ParquetWriter, WriterConfig, BLOOM_FILTER_ENABLED_PROPERTY_PREFIX,
+    };
+
+    use crate::iceberg::test_utils::{
+        append, append_ids, create_table, ids_batch, test_schema, TestCatalog, XorKms,
+    };
+
+    #[test]
+    fn test_scan_reads_current_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = create_table(dir.path());
+        let table = append(&table, &ids_batch(&[1, 2, 3]));
+        let table = append(&table, &ids_batch(&[4]));
+
+        let plan = table.scan().plan_files().unwrap();
+        assert_eq!(2, plan.tasks().len());
+        let rows: usize = plan
+            .to_arrow()
+            .unwrap()
+            .map(|b| b.unwrap().num_rows())
+            .sum();
+        assert_eq!(4, rows);
+    }
+
+    #[test]
+    fn test_scan_time_travel_and_projection() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = create_table(dir.path());
+        let table = append(&table, &ids_batch(&[1, 2, 3]));
+        let first_snapshot = table.metadata().current_snapshot_id.unwrap();
+        let table = append(&table, &ids_batch(&[4]));
+
+        let plan = table
+            .scan()
+            .with_snapshot_id(first_snapshot)
+            .select(&["id"])
+            .plan_files()
+            .unwrap();
+        assert_eq!(Some(first_snapshot), plan.fingerprint().snapshot_id);
+        assert_eq!(
+            table.metadata_location(),
+            plan.fingerprint().metadata_location
+        );
+
+        let batches = plan.to_arrow().unwrap().collect::<Vec<_>>();
+        assert_eq!(1, batches.len());
+        let batch = batches[0].as_ref().unwrap();
+        assert_eq!(1, batch.num_columns());
+        let ids = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(vec![1, 2, 3], ids.values().to_vec());
+        assert!(table.scan().with_snapshot_id(42).plan_files().is_err());
+    }
+
+    #[test]
+    fn test_parallel_planning() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut table = create_table(dir.path());
+        for id in 0..6 {
+            table = append(&table, &ids_batch(&[id]));
+        }
+
+        let sequential = table
+            .scan()
+            .with_planning_parallelism(1)
+            .plan_files()
+            .unwrap();
+        let parallel = table
+            .scan()
+            .with_planning_parallelism(4)
+            .plan_files()
+            .unwrap();
+        assert_eq!(6, parallel.tasks().len());
+        assert_eq!(sequential.tasks(), parallel.tasks());
+
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        let manifests = table.manifests(snapshot).unwrap();
+        table.file_io().delete(&manifests[3].manifest_path).unwrap();
+        assert!(table
+            .scan()
+            .with_planning_parallelism(4)
+            .plan_files()
+            .is_err());
+    }
+
+    #[test]
+    fn test_scan_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = append(&create_table(dir.path()), &ids_batch(&[1, 2, 3, 4, 5]));
+
+        let config = ScanConfig::new()
+            .with_batch_size(2)
+            .with_planning_parallelism(1);
+        let plan = table.scan().with_config(config).plan_files().unwrap();
+        let sizes: Vec<usize> = plan
+            .to_arrow()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .collect();
+        assert_eq!(vec![2, 2, 1], sizes);
+
+        let config = ScanConfig::new().with_batch_size(0);
+        let error = table.scan().with_config(config).plan_files().unwrap_err();
+        assert!(matches!(error, IcebergError::Invalid(_)));
+    }
+
+    #[test]
+    fn test_scan_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut table = create_table(dir.path());
+        for id in 0..3 {
+            table = append(&table, &ids_batch(&[id]));
+        }
+        let progress = Arc::new(RecordingReporter::default());
+        let plan = table
+            .scan()
+            .with_planning_parallelism(2)
+            .with_progress(progress.clone())
+            .plan_files()
+            .unwrap();
+        assert_eq!(Some(3), progress.total(ProgressPhase::PlanningManifests));
+        assert_eq!(3, progress.units(ProgressPhase::PlanningManifests));
+
+        let batches = plan.to_arrow().unwrap();
+        assert_eq!(Some(3), progress.total(ProgressPhase::ReadingFiles));
+        assert_eq!(3, batches.count());
+        assert_eq!(3, progress.units(ProgressPhase::ReadingFiles));
+        let file_sizes: i64 = plan
+            .tasks()
+            .iter()
+            .map(|task| task.data_file.file_size_in_bytes)
+            .sum();
+        assert_eq!(
+            file_sizes as u64,
+            progress.bytes(ProgressPhase::ReadingFiles)
+        );
+    }
+
+    // Subscriber recording the spans created, with the name of their parent span
+    #[derive(Default)]
+    struct SpanRecorder {
+        spans: Mutex<Vec<(&'static tracing::Metadata<'static>, Option<String>)>>,
+        entered: Mutex<HashMap<std::thread::ThreadId, Vec<u64>>>,
+    }
+
+    impl tracing::Subscriber for SpanRecorder {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let parent = self
+                .entered
+                .lock()
+                .unwrap()
+                .get(&std::thread::current().id())
+                .and_then(|entered| entered.last().copied());
+            let mut spans = self.spans.lock().unwrap();
+            let parent = parent.map(|id| spans[id as usize - 1].0.name().to_string());
+            spans.push((span.metadata(), parent));
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+        fn event(&self, _event: &tracing::Event<'_>) {}
+
+        fn enter(&self, span: &tracing::span::Id) {
+            let mut entered = self.entered.lock().unwrap();
+            let entered = entered.entry(std::thread::current().id()).or_default();
+            entered.push(span.into_u64());
+        }
+
+        fn exit(&self, _span: &tracing::span::Id) {
+            let mut entered = self.entered.lock().unwrap();
+            entered.get_mut(&std::thread::current().id()).unwrap().pop();
+        }
+
+        fn current_span(&self) -> tracing_core::span::Current {
+            let entered = self.entered.lock().unwrap();
+            match entered
+                .get(&std::thread::current().id())
+                .and_then(|entered| entered.last())
+            {
+                Some(id) => tracing_core::span::Current::new(
+                    tracing::span::Id::from_u64(*id),
+                    self.spans.lock().unwrap()[*id as usize - 1].0,
+                ),
+                None => tracing_core::span::Current::none(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_tracing_spans() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut table = create_table(dir.path());
+        for id in 0..3 {
+            table = append(&table, &ids_batch(&[id]));
+        }
+        let recorder = Arc::new(SpanRecorder::default());
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let plan = table
+                .scan()
+                .with_planning_parallelism(2)
+                .plan_files()
+                .unwrap();
+            assert_eq!(3, plan.to_arrow().unwrap().count());
+        });
+
+        let spans = recorder.spans.lock().unwrap();
+        let count = |name: &str, parent: Option<&str>| {
+            spans
+                .iter()
+                .filter(|(metadata, p)| metadata.name() == name && p.as_deref() == parent)
+                .count()
+        };
+        assert_eq!(1, count("plan_files", None));
+        assert_eq!(1, count("manifests", Some("plan_files")));
+        // Manifests read by the planning threads are within the planning span
+        assert_eq!(3, count("manifest_entries", Some("plan_files")));
+        assert_eq!(3, count("file_io.read", Some("manifest_entries")));
+        assert_eq!(3, count("read_data_file", None));
+        assert_eq!(3, count("file_io.read", Some("read_data_file")));
+    }
+
+    #[test]
+    fn test_scan_filter_prunes_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = create_table(dir.path());
+        let table = append(&table, &ids_batch(&[1, 2, 3]));
+        let table = append(&table, &ids_batch(&[10, 11]));
+
+        let plan = table
+            .scan()
+            .filter(Predicate::greater_than("id", Literal::Int(5)))
+            .plan_files()
+            .unwrap();
+        assert_eq!(1, plan.tasks().len());
+        assert_eq!(2, plan.tasks()[0].data_file.record_count);
+
+        let plan = table
+            .scan()
+            .filter(Predicate::equal("ID", Literal::Long(2)))
+            .case_insensitive()
+            .plan_files()
+            .unwrap();
+        assert_eq!(1, plan.tasks().len());
+        assert_eq!(3, plan.tasks()[0].data_file.record_count);
+
+        assert!(table
+            .scan()
+            .filter(Predicate::is_null("missing"))
+            .plan_files()
+            .is_err());
+    }
+
+    #[test]
+    fn test_scan_filter_files_on_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = create_table(dir.path());
+        let table = append(&table, &ids_batch(&[1, 2, 3]));
+        let table = append(&table, &ids_batch(&[10, 11]));
+        let tasks = table.scan().plan_files().unwrap().tasks().to_vec();
+        let smallest = tasks
+            .iter()
+            .map(|task| task.data_file.file_size_in_bytes)
+            .min()
+            .unwrap();
+
+        let plan = table
+            .scan()
+            .filter_files(Predicate::greater_than(
+                "file_size_in_bytes",
+                Literal::Long(smallest),
+            ))
+            .plan_files()
+            .unwrap();
+        assert!(plan.tasks().len() < tasks.len());
+
+        let plan = table
+            .scan()
+            .filter_files(Predicate::starts_with(
+                "file_path",
+                &tasks[1].data_file.file_path,
+            ))
+            .filter_files(Predicate::equal(
+                "FILE_FORMAT",
+                Literal::String("PARQUET".to_string()),
+            ))
+            .case_insensitive()
+            .plan_files()
+            .unwrap();
+        assert_eq!(1, plan.tasks().len());
+        assert_eq!(tasks[1].data_file, plan.tasks()[0].data_file);
+
+        let plan = table
+            .scan()
+            .filter_files(Predicate::equal("record_count", Literal::Int(2)))
+            .filter(Predicate::less_than("id", Literal::Long(5)))
+            .plan_files()
+            .unwrap();
+        assert!(plan.tasks().is_empty());
+        assert!(table
+            .scan()
+            .filter_files(Predicate::is_null("id"))
+            .plan_files()
+            .is_err());
+    }
+
+    #[test]
+    fn test_scan_with_spec_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1, 2]);
+        let mut metadata = table.metadata().clone();
+        metadata.partition_specs.push(PartitionSpec {
+            spec_id: 1,
+            fields: vec![PartitionField {
+                source_id: 2,
+                source_ids: vec![],
+                field_id: 1000,
+                name: "data".to_string(),
+                transform: Transform::Identity,
+            }],
+        });
+        metadata.default_spec_id = 1;
+        let table = catalog.commit_table(&table, metadata).unwrap();
+        let table = append_ids(&catalog, &table, &[3]);
+
+        let spec_tasks = |spec_ids: &[i32]| {
+            let plan = table.scan().with_spec_ids(spec_ids).plan_files().unwrap();
+            plan.tasks()
+                .iter()
+                .map(|task| (task.spec_id, task.data_file.record_count))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![(0, 2)], spec_tasks(&[0]));
+        assert_eq!(vec![(1, 1)], spec_tasks(&[1]));
+        assert_eq!(2, spec_tasks(&[0, 1]).len());
+        assert!(spec_tasks(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_scan_of_empty_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = create_table(dir.path());
+
+        let plan = table.scan().plan_files().unwrap();
+        assert_eq!(None, plan.fingerprint().snapshot_id);
+        assert_eq!(0, plan.to_arrow().unwrap().count());
+    }
+
+    #[test]
+    fn test_incremental_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "table", dir.path());
+        let table = append_ids(&catalog, &table, &[1]);
+        let first_snapshot = table.metadata().current_snapshot_id.unwrap();
+        let table = append_ids(&catalog, &table, &[2]);
+        let second_snapshot = table.metadata().current_snapshot_id.unwrap();
+        let table = append_ids(&catalog, &table, &[3]);
+
+        // Removing an appended file and rewriting the manifests don't hide the append
+        let plan = table.incremental_scan(first_snapshot, second_snapshot);
+        let files = plan.plan_files().unwrap().tasks().to_vec();
+        let mut writer = PartitionedWriter::for_table(&table).unwrap();
+        writer.write(&ids_batch(&[2, 4])).unwrap();
+        let table = table
+            .new_overwrite()
+            .delete_file(&files[0].data_file)
+            .add_files(writer.close().unwrap())
+            .commit(&catalog)
+            .unwrap();
+        let table = table.rewrite_manifests().commit(&catalog).unwrap().table;
+        let last_snapshot = table.metadata().current_snapshot_id.unwrap();
+
+        let ids = |plan: super::ScanPlan| {
+            let mut ids: Vec<i64> = plan
+                .to_arrow()
+                .unwrap()
+                .flat_map(|batch| {
+                    let batch = batch.unwrap();
+                    let ids = batch.column(0).as_any().downcast_ref::<Int64Array>();
+                    ids.unwrap().values().to_vec()
+                })
+                .collect();
+            ids.sort();
+            ids
+        };
+        let plan = table
+            .incremental_scan(first_snapshot, last_snapshot)
+            .select(&["id"])
+            .plan_files()
+            .unwrap();
+        assert_eq!(Some(last_snapshot), plan.fingerprint().snapshot_id);
+        assert_eq!(vec![2, 3], ids(plan));
+        let plan = table
+            .incremental_scan(first_snapshot, last_snapshot)
+            .filter(Predicate::equal("id", Literal::Long(3)))
+            .plan_files()
+            .unwrap();
+        assert_eq!(vec![3], ids(plan));
+
+        let plan = table.incremental_scan(last_snapshot, last_snapshot);
+        assert!(plan.plan_files().unwrap().tasks().is_empty());
+        let error = table
+            .incremental_scan(last_snapshot, first_snapshot)
+            .plan_files()
+            .unwrap_err();
+        assert!(matches!(error, IcebergError::Invalid(_)));
+    }
+
+    #[cfg(not(feature = "orc"))]
+    #[test]
+    fn test_scan_of_orc_files_is_unsupported() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let mut writer = PartitionedWriter::for_table(&table).unwrap();
+        writer.write(&ids_batch(&[1])).unwrap();
+        let mut data_file = writer.close().unwrap().remove(0);
+        data_file.file_format = FileFormat::Orc;
+        data_file.file_path = data_file.file_path.replace(".parquet", ".orc");
+        let table = table
+            .new_append()
+            .add_file(data_file)
+            .commit(&catalog)
+            .unwrap();
+
+        let plan = table.scan().plan_files().unwrap();
+        let error = plan.to_arrow().err().unwrap();
+        assert!(matches!(error, IcebergError::Unsupported(_)), "{}", error);
+        assert!(error.to_string().contains(".orc"));
+    }
+
+    #[cfg(feature = "orc")]
+    #[test]
+    fn test_scan_of_orc_files() {
+        use crate::iceberg::writer::position_delete::PositionDeleteWriter;
+
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        // Writes the rows as Parquet for the metrics of the data file, then as ORC
+        let batch = ids_batch(&[1, 2, 3]);
+        let mut writer = PartitionedWriter::for_table(&table).unwrap();
+        writer.write(&batch).unwrap();
+        let mut data_file = writer.close().unwrap().remove(0);
+        let mut buffer = Vec::new();
+        let mut orc_writer = orc_rust::ArrowWriterBuilder::new(&mut buffer, batch.schema())
+            .try_build()
+            .unwrap();
+        orc_writer.write(&batch).unwrap();
+        orc_writer.close().unwrap();
+        data_file.file_format = FileFormat::Orc;
+        data_file.file_path = data_file.file_path.replace(".parquet", ".orc");
+        data_file.file_size_in_bytes = buffer.len() as i64;
+        table
+            .file_io()
+            .write(&data_file.file_path, Bytes::from(buffer))
+            .unwrap();
+        let table = table
+            .new_append()
+            .add_file(data_file.clone())
+            .commit(&catalog)
+            .unwrap();
+        let ids = |table: &Table| {
+            table
+                .scan()
+                .plan_files()
+                .unwrap()
+                .to_arrow()
+                .unwrap()
+                .flat_map(|batch| {
+                    let batch = batch.unwrap();
+                    let ids = batch.column(0).as_any().downcast_ref::<Int64Array>();
+                    ids.unwrap().values().to_vec()
+                })
+                .collect::<Vec<i64>>()
+        };
+        assert_eq!(vec![1, 2, 3], ids(&table));
+
+        let mut writer = PositionDeleteWriter::for_table(&table, vec![]).unwrap();
+        writer.delete(&data_file.file_path, 1);
+        let table = table
+            .new_row_delta()
+            .add_deletes(writer.close().unwrap())
+            .commit(&catalog)
+            .unwrap();
+        assert_eq!(vec![1, 3], ids(&table));
+    }
+
+    #[test]
+    fn test_scan_skips_row_groups_by_bloom_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let mut metadata = table.metadata().clone();
+        metadata.properties = Some(HashMap::from([(
+            format!("{}id", BLOOM_FILTER_ENABLED_PROPERTY_PREFIX),
+            "true".to_string(),
+        )]));
+        let config = WriterConfig::for_table(&metadata)
+            .unwrap()
+            .with_max_row_group_size(2);
+        assert_eq!(vec!["id".to_string()], config.bloom_filter_columns);
+        let mut writer = PartitionedWriter::for_table(&table)
+            .unwrap()
+            .with_config(&config)
+            .unwrap();
+        writer.write(&ids_batch(&[1, 2, 3, 4, 5, 6])).unwrap();
+        let table = table
+            .new_append()
+            .add_files(writer.close().unwrap())
+            .commit(&catalog)
+            .unwrap();
+
+        let ids = |filter: Predicate| {
+            let plan = table.scan().filter(filter).plan_files().unwrap();
+            let mut ids = vec![];
+            for batch in plan.to_arrow().unwrap() {
+                let batch = batch.unwrap();
+                let column = batch.column(0).as_any().downcast_ref::<Int64Array>();
+                ids.extend(column.unwrap().values().iter().copied());
+            }
+            ids
+        };
+        // Only the row group holding the value is read, its other rows aren't filtered
+        assert_eq!(vec![3, 4], ids(Predicate::equal("id", Literal::Long(3))));
+        let filter = Predicate::is_in("id", vec![Literal::Long(1), Literal::Long(6)]);
+        assert_eq!(vec![1, 2, 5, 6], ids(filter));
+        let filter = Predicate::greater_than("id", Literal::Long(4));
+        assert_eq!(6, ids(filter).len());
+        // Columns without bloom filters can't skip row groups
+        let filter = Predicate::equal("data", Literal::String("row-3".to_string()));
+        assert_eq!(6, ids(filter).len());
+    }
+
+    #[test]
+    fn test_scan_splits_files_by_row_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let config = WriterConfig::for_table(table.metadata())
+            .unwrap()
+            .with_max_row_group_size(2);
+        let mut writer = PartitionedWriter::for_table(&table)
+            .unwrap()
+            .with_config(&config)
+            .unwrap();
+        writer.write(&ids_batch(&[1, 2, 3, 4, 5, 6])).unwrap();
+        let table = table
+            .new_append()
+            .add_files(writer.close().unwrap())
+            .commit(&catalog)
+            .unwrap();
+        let whole = table.scan().plan_files().unwrap();
+        assert_eq!(1, whole.tasks().len());
+        let file = whole.tasks()[0].data_file.clone();
+        let offsets = file.split_offsets.clone().unwrap();
+        assert_eq!(3, offsets.len());
+
+        let ids = |plan: &ScanPlan| {
+            let mut ids = vec![];
+            for batch in plan.to_arrow().unwrap() {
+                let batch = batch.unwrap();
+                let column = batch.column(0).as_any().downcast_ref::<Int64Array>();
+                ids.extend(column.unwrap().values().iter().copied());
+            }
+            ids
+        };
+        // Every row group in its own task, the ranges covering the file
+        let plan = table.scan().with_split_size(1).plan_files().unwrap();
+        let ranges: Vec<(i64, i64)> = plan
+            .tasks()
+            .iter()
+            .map(|task| (task.start, task.length))
+            .collect();
+        assert_eq!(
+            vec![
+                (0, offsets[1]),
+                (offsets[1], offsets[2] - offsets[1]),
+                (offsets[2], file.file_size_in_bytes - offsets[2])
+            ],
+            ranges
+        );
+        assert_eq!(vec![1, 2, 3, 4, 5, 6], ids(&plan));
+        assert_eq!(whole.estimate(), plan.estimate());
+        // Ranges hold row groups until they reach the split size
+        let plan = table
+            .scan()
+            .with_split_size(offsets[2] - 1)
+            .plan_files()
+            .unwrap();
+        assert_eq!(2, plan.tasks().len());
+        assert_eq!(offsets[2], plan.tasks()[1].start);
+        assert_eq!(vec![1, 2, 3, 4, 5, 6], ids(&plan));
+        let plan = table
+            .scan()
+            .with_split_size(file.file_size_in_bytes)
+            .plan_files()
+            .unwrap();
+        assert_eq!(whole.tasks(), plan.tasks());
+
+        // Deleted positions count the rows of the row groups before the range
+        let mut writer = PositionDeleteWriter::for_table(&table, vec![]).unwrap();
+        writer.delete(&file.file_path, 0);
+        writer.delete(&file.file_path, 3);
+        let table = table
+            .new_row_delta()
+            .add_deletes(writer.close().unwrap())
+            .commit(&catalog)
+            .unwrap();
+        let plan = table.scan().with_split_size(1).plan_files().unwrap();
+        assert_eq!(3, plan.tasks().len());
+        assert_eq!(vec![2, 3, 5, 6], ids(&plan));
+        let estimate = plan.estimate();
+        assert_eq!((1, 1), (estimate.data_files, estimate.delete_files));
+    }
+
+    #[test]
+    fn test_task_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut table = create_table(dir.path());
+        for id in 0..4 {
+            table = append(&table, &ids_batch(&[id]));
+        }
+        let plan = table.scan().plan_files().unwrap();
+        assert_eq!(vec![4], group_sizes(&plan));
+
+        let size = plan.tasks()[0].length;
+        let config = ScanConfig::new()
+            .with_split_size(2 * size)
+            .with_split_open_file_cost(0);
+        let plan = table
+            .scan()
+            .with_config(config.clone())
+            .plan_files()
+            .unwrap();
+        assert_eq!(vec![2, 2], group_sizes(&plan));
+        // The open file cost makes tasks weigh more than their length
+        let config = config.with_split_open_file_cost(2 * size);
+        let plan = table
+            .scan()
+            .with_config(config.clone())
+            .plan_files()
+            .unwrap();
+        assert_eq!(vec![1, 1, 1, 1], group_sizes(&plan));
+        assert!(table
+            .scan()
+            .with_config(config.with_split_lookback(0))
+            .plan_files()
+            .is_err());
+    }
+
+    fn group_sizes(plan: &ScanPlan) -> Vec<usize> {
+        plan.task_groups().iter().map(Vec::len).collect()
+    }
+
+    #[test]
+    fn test_scan_of_avro_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let mut writer = PartitionedWriter::for_table(&table).unwrap();
+        writer.write(&ids_batch(&[1, 2])).unwrap();
+        let mut data_file = writer.close().unwrap().remove(0);
+        data_file.file_format = FileFormat::Avro;
+        data_file.file_path = data_file.file_path.replace(".parquet", ".avro");
+
+        let avro_schema = schema_to_avro(&test_schema(), "r").unwrap();
+        let mut avro_writer = apache_avro::Writer::new(&avro_schema, Vec::new());
+        for id in [1, 2] {
+            let data = apache_avro::types::Value::String(format!("d{}", id));
+            avro_writer
+                .append(apache_avro::types::Value::Record(vec![
+                    ("id".to_string(), apache_avro::types::Value::Long(id)),
+                    (
+                        "data".to_string(),
+                        apache_avro::types::Value::Union(1, Box::new(data)),
+                    ),
+                ]))
+                .unwrap();
+        }
+        let data = Bytes::from(avro_writer.into_inner().unwrap());
+        table.file_io().write(&data_file.file_path, data).unwrap();
+        let table = table
+            .new_append()
+            .add_file(data_file)
+            .commit(&catalog)
+            .unwrap();
+
+        let plan = table.scan().select(&["data", "id"]).plan_files().unwrap();
+        let batches = plan.to_arrow().unwrap().collect::<Result<Vec<_>, _>>();
+        let batch = &batches.unwrap()[0];
+        assert_eq!(plan.arrow_schema().unwrap(), batch.schema());
+        let ids = batch.column(1).as_any().downcast_ref::<Int64Array>();
+        assert_eq!(&[1, 2], ids.unwrap().values());
+    }
+
+    #[test]
+    fn test_stable_scan_fails_when_files_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = create_table(dir.path());
+        let table = append(&table, &ids_batch(&[1, 2, 3]));
+        let table = append(&table, &ids_batch(&[4]));
+
+        let plan = table
+            .scan()
+            .require_snapshot_stability()
+            .plan_files()
+            .unwrap();
+        assert!(plan.to_arrow().is_ok());
+
+        // Simulate retention removing a file after planning
+        let removed = &plan.tasks()[1].data_file.file_path;
+        table.file_io().delete(removed).unwrap();
+
+        let error = plan.to_arrow().err().unwrap().to_string();
+        assert!(error.contains(removed));
+        assert!(error.contains(table.metadata_location()));
+
+        // Without the requirement the read fails half way through
+        let plan = table.scan().plan_files().unwrap();
+        let results = plan.to_arrow().unwrap().collect::<Vec<_>>();
+        assert!(results[0].is_ok());
+        assert!(results.last().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_scan_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = create_table(dir.path());
+        let table = append(&table, &ids_batch(&[1, 2, 3]));
+        let table = append(&table, &ids_batch(&[10, 11]));
+
+        let limited = table
+            .clone()
+            .with_scan_limits(ScanLimits::new().with_max_files(1));
+        let error = limited.scan().plan_files().err().unwrap();
+        assert!(error.to_string().contains("2 files"));
+        let plan = limited
+            .scan()
+            .filter(Predicate::greater_than("id", Literal::Long(5)))
+            .plan_files()
+            .unwrap();
+        assert_eq!(1, plan.tasks().len());
+
+        let size = plan.tasks()[0].data_file.file_size_in_bytes;
+        let limited = table
+            .clone()
+            .with_scan_limits(ScanLimits::new().with_max_bytes(size));
+        assert!(limited.scan().plan_files().is_err());
+
+        let limited = table
+            .clone()
+            .with_scan_limits(ScanLimits::new().with_allowed_columns(&["id"]));
+        assert!(limited.scan().select(&["id"]).plan_files().is_ok());
+        for scan in [limited.scan(), limited.scan().select(&["id", "data"])] {
+            let error = scan.plan_files().err().unwrap();
+            assert!(matches!(error, IcebergError::Forbidden(_)));
+        }
+    }
+
+    #[test]
+    fn test_scan_estimate() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = create_table(dir.path());
+        assert_eq!(ScanEstimate::default(), table.scan().estimate().unwrap());
+        let table = append(&table, &ids_batch(&[1, 2, 3]));
+        let table = append(&table, &ids_batch(&[10, 11]));
+
+        let plan = table.scan().plan_files().unwrap();
+        let estimate = table.scan().estimate().unwrap();
+        assert_eq!(plan.estimate(), estimate);
+        assert_eq!(2, estimate.data_files);
+        assert_eq!(5, estimate.record_count);
+        let bytes: i64 = plan
+            .tasks()
+            .iter()
+            .map(|task| task.data_file.file_size_in_bytes)
+            .sum();
+        assert_eq!(bytes, estimate.total_bytes);
+        assert_eq!(0, estimate.delete_files);
+
+        let estimate = table
+            .scan()
+            .filter(Predicate::greater_than("id", Literal::Long(5)))
+            .estimate()
+            .unwrap();
+        assert_eq!((1, 2), (estimate.data_files, estimate.record_count));
+
+        // Estimates show the size of scans exceeding the limits
+        let limited = table.with_scan_limits(ScanLimits::new().with_max_files(1));
+        assert!(limited.scan().plan_files().is_err());
+        assert_eq!(2, limited.scan().estimate().unwrap().data_files);
+    }
+
+    #[test]
+    fn test_scan_limits_require_partition_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = create_table(dir.path());
+        let mut metadata = table.metadata().clone();
+        metadata.partition_specs = vec![PartitionSpec {
+            spec_id: 0,
+            fields: vec![PartitionField {
+                source_id: 2,
+                source_ids: vec![],
+                field_id: 1000,
+                name: "data".to_string(),
+                transform: Transform::Identity,
+            }],
+        }];
+        let table = Table::try_new(
+            "db.t".parse().unwrap(),
+            TableMetadata::V2(metadata),
+            table.metadata_location().to_string(),
+            table.file_io().clone(),
+        )
+        .unwrap()
+        .with_scan_limits(ScanLimits::new().require_partition_filter());
+
+        let error = table
+            .scan()
+            .filter(Predicate::equal("id", Literal::Long(1)))
+            .plan_files()
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("partition column: data"));
+        assert!(table
+            .scan()
+            .filter(Predicate::equal("data", Literal::String("a".to_string())))
+            .plan_files()
+            .is_ok());
+
+        // Same requirement through the table property
+        let mut metadata = table.metadata().clone();
+        metadata.properties = Some(HashMap::from([(
+            PARTITION_FILTER_REQUIRED_PROPERTY.to_string(),
+            "true".to_string(),
+        )]));
+        let table = Table::try_new(
+            "db.t".parse().unwrap(),
+            TableMetadata::V2(metadata),
+            table.metadata_location().to_string(),
+            table.file_io().clone(),
+        )
+        .unwrap();
+        assert!(table.scan().plan_files().is_err());
+        // Rows matching the second branch aren't restricted to some partitions
+        assert!(table
+            .scan()
+            .filter(Predicate::is_null("data").or(Predicate::is_null("id")))
+            .plan_files()
+            .is_err());
+        assert!(table
+            .scan()
+            .filter(Predicate::is_null("data").and(Predicate::is_null("id")))
+            .plan_files()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_scan_decrypts_data_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1, 2]);
+        let mut metadata = table.metadata().clone();
+        metadata.properties = Some(HashMap::from([(
+            ENCRYPTION_KEY_ID_PROPERTY.to_string(),
+            "master".to_string(),
+        )]));
+        let mut table = catalog.commit_table(&table, metadata).unwrap();
+
+        // One file keyed by the manifest, one by the key metadata in its footer
+        let key = b"0123456789012345".to_vec();
+        let wrapped_key = XorKms::wrap_key(&key, "master");
+        for in_manifest in [true, false] {
+            let mut writer = ParquetWriter::try_new(
+                table.file_io().clone(),
+                format!("{}/data/{}.parquet", table.metadata().location, in_manifest),
+                &test_schema(),
+            )
+            .unwrap();
+            writer.write(&ids_batch(&[3])).unwrap();
+            let mut data_file = writer.close().unwrap();
+
+            let mut encryption = FileEncryptionProperties::builder(key.clone());
+            if in_manifest {
+                data_file.key_metadata = Some(wrapped_key.clone());
+            } else {
+                encryption = encryption.with_footer_key_metadata(wrapped_key.clone());
+            }
+            let properties = WriterProperties::builder()
+                .with_file_encryption_properties(encryption.build().unwrap())
+                .build();
+            let mut encrypted = ArrowWriter::try_new(
+                Vec::new(),
+                Arc::new(schema_to_arrow(&test_schema()).unwrap()),
+                Some(properties),
+            )
+            .unwrap();
+            encrypted.write(&ids_batch(&[3])).unwrap();
+            let data = encrypted.into_inner().unwrap();
+            data_file.file_size_in_bytes = data.len() as i64;
+            table
+                .file_io()
+                .write(&data_file.file_path, Bytes::from(data))
+                .unwrap();
+            table = table
+                .new_append()
+                .add_file(data_file)
+                .commit(&catalog)
+                .unwrap();
+        }
+
+        let plan = table.scan().plan_files().unwrap();
+        assert!(plan.to_arrow().unwrap().any(|batch| batch.is_err()));
+        let table = table.with_key_management_client(Arc::new(XorKms));
+        let rows: usize = table
+            .scan()
+            .plan_files()
+            .unwrap()
+            .to_arrow()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum();
+        assert_eq!(4, rows);
+    }
+}
@@ -0,0 +1,714 @@
+// Conversion between Iceberg and Avro schemas, and resolution of the fields of Iceberg Avro
+// files (manifests and manifest lists) by field id.
+// Like any other Iceberg file, these files identify fields by the field-id attribute of their
+// schema rather than by name, and writers don't agree on names: Spark writes
+// added_data_files_count where the spec says added_files_count. Records read from a file are
+// renamed to the names of the expected schema before being decoded. Fields written without an
+// id, or with an id the expected schema doesn't know, keep their name
+use std::collections::{BTreeMap, HashMap};
+
+use apache_avro::schema::{Name, RecordField, RecordSchema, Schema};
+use apache_avro::types::Value;
+use serde_json::json;
+
+use super::name_mapping::{find, MappedField, NameMapping};
+use super::schema::{IcebergType, ListType, MapType, PrimitiveType, StructField, StructType};
+use crate::iceberg::error::{IcebergError, Result};
+
+// Attributes holding field ids. The spec uses field-id, older files (including the manifest
+// lists written by earlier versions of rustberg) use field_id
+const FIELD_ID_ATTRIBUTES: [&str; 2] = ["field-id", "field_id"];
+
+// Avro schema of records of the given Iceberg struct, named `name`. Field ids are written in the
+// field-id attribute and nested records are named after their field id (r102 for the partition of
+// data files), following the Java implementation. Optional fields are unions with null defaulting
+// to null, and maps with keys other than strings are arrays of key-value records
+pub fn schema_to_avro(schema: &StructType, name: &str) -> Result<Schema> {
+    Ok(Schema::parse(&struct_to_avro(schema, name))?)
+}
+
+fn struct_to_avro(struct_type: &StructType, name: &str) -> serde_json::Value {
+    let fields: Vec<_> = struct_type
+        .fields
+        .iter()
+        .map(|field| {
+            let field_type = type_to_avro(&field.field_type, field.id);
+            let mut avro = if field.required {
+                json!({"name": field.name, "type": field_type})
+            } else {
+                json!({"name": field.name, "type": ["null", field_type], "default": null})
+            };
+            avro["field-id"] = json!(field.id);
+            if let Some(doc) = &field.doc {
+                avro["doc"] = json!(doc);
+            }
+            avro
+        })
+        .collect();
+    json!({"type": "record", "name": name, "fields": fields})
+}
+
+// Avro schema of a type, `id` being the field id of the field, element or value of that type
+fn type_to_avro(field_type: &IcebergType, id: i32) -> serde_json::Value {
+    let optional = |avro, required| {
+        if required {
+            avro
+        } else {
+            json!(["null", avro])
+        }
+    };
+    match field_type {
+        // Variants are records of their binary metadata and value, as in Parquet
+        IcebergType::Primitive(PrimitiveType::Variant) => json!({
+            "type": "record",
+            "name": format!("r{}", id),
+            "fields": [
+                {"name": "metadata", "type": "bytes"},
+                {"name": "value", "type": "bytes"},
+            ],
+        }),
+        IcebergType::Primitive(primitive) => primitive_to_avro(primitive),
+        IcebergType::Struct(struct_type) => struct_to_avro(struct_type, &format!("r{}", id)),
+        IcebergType::List(list) => json!({
+            "type": "array",
+            "items": optional(type_to_avro(&list.element, list.element_id), list.element_required),
+            "element-id": list.element_id,
+        }),
+        IcebergType::Map(map) => {
+            let value = optional(type_to_avro(&map.value, map.value_id), map.value_required);
+            match map.key.as_ref() {
+                IcebergType::Primitive(PrimitiveType::String) => json!({
+                    "type": "map",
+                    "values": value,
+                    "key-id": map.key_id,
+                    "value-id": map.value_id,
+                }),
+                key => json!({
+                    "type": "array",
+                    "items": {
+                        "type": "record",
+                        "name": format!("k{}_v{}", map.key_id, map.value_id),
+                        "fields": [
+                            {"name": "key", "type": type_to_avro(key, map.key_id), "field-id": map.key_id},
+                            {"name": "value", "type": value, "field-id": map.value_id},
+                        ],
+                    },
+                    "logicalType": "map",
+                }),
+            }
+        }
+    }
+}
+
+fn primitive_to_avro(primitive: &PrimitiveType) -> serde_json::Value {
+    match primitive {
+        PrimitiveType::Boolean => json!("boolean"),
+        PrimitiveType::Int => json!("int"),
+        PrimitiveType::Long => json!("long"),
+        PrimitiveType::Float => json!("float"),
+        PrimitiveType::Double => json!("double"),
+        PrimitiveType::Decimal { precision, scale } => json!({
+            "type": "fixed",
+            "name": format!("decimal_{}_{}", precision, scale),
+            "size": decimal_required_bytes(*precision),
+            "logicalType": "decimal",
+            "precision": precision,
+            "scale": scale,
+        }),
+        PrimitiveType::Date => json!({"type": "int", "logicalType": "date"}),
+        PrimitiveType::Time => json!({"type": "long", "logicalType": "time-micros"}),
+        // apache_avro drops the adjust-to-utc attribute of the spec, timestamps without zone use
+        // the local timestamp type of Avro instead so that they can be told apart
+        PrimitiveType::Timestamp => {
+            json!({"type": "long", "logicalType": "local-timestamp-micros"})
+        }
+        PrimitiveType::Timestamptz => json!({
+            "type": "long",
+            "logicalType": "timestamp-micros",
+            "adjust-to-utc": true,
+        }),
+        PrimitiveType::String => json!("string"),
+        // The uuid logical type is left out as apache_avro only supports it on strings
+        PrimitiveType::Uuid => json!({"type": "fixed", "name": "uuid_fixed", "size": 16}),
+        PrimitiveType::Fixed(size) => json!({
+            "type": "fixed",
+            "name": format!("fixed_{}", size),
+            "size": size,
+        }),
+        PrimitiveType::Binary => json!("bytes"),
+        PrimitiveType::TimestampNs => {
+            json!({"type": "long", "logicalType": "local-timestamp-nanos"})
+        }
+        PrimitiveType::TimestamptzNs => json!({
+            "type": "long",
+            "logicalType": "timestamp-nanos",
+            "adjust-to-utc": true,
+        }),
+        PrimitiveType::Variant => unreachable!("variants are records, see type_to_avro"),
+    }
+}
+
+// Iceberg schema of records of the given Avro schema, the inverse of schema_to_avro. Every field
+// must have a field id, as must list elements and map keys and values
+pub fn avro_to_schema(schema: &Schema) -> Result<StructType> {
+    avro_to_schema_with_name_mapping(schema, &NameMapping::default())
+}
+
+// Like avro_to_schema, taking the ids of fields, list elements and map keys and values without
+// one from the name mapping, e.g. for Avro data files of tables imported from Hive
+pub fn avro_to_schema_with_name_mapping(
+    schema: &Schema,
+    name_mapping: &NameMapping,
+) -> Result<StructType> {
+    let mut named = HashMap::new();
+    collect_named(schema, &mut named);
+    let converter = AvroToIceberg { named };
+    match converter.resolve(schema) {
+        Schema::Record(record) => converter.record(record, &name_mapping.fields),
+        schema => Err(IcebergError::Invalid(format!(
+            "Avro schema {:?} is not a record",
+            schema
+        ))),
+    }
+}
+
+struct AvroToIceberg<'a> {
+    named: HashMap<Name, &'a Schema>,
+}
+
+impl<'a> AvroToIceberg<'a> {
+    fn resolve(&self, schema: &'a Schema) -> &'a Schema {
+        match schema {
+            Schema::Ref { name } => self.named.get(name).copied().unwrap_or(schema),
+            schema => schema,
+        }
+    }
+
+    // Fields of the record, with the mapped fields of the record for fields without ids
+    fn record(&self, record: &'a RecordSchema, mapped: &[MappedField]) -> Result<StructType> {
+        let fields = record
+            .fields
+            .iter()
+            .map(|field| {
+                let mapping = find(mapped, &field.name);
+                let id = field_id(field)
+                    .or_else(|| mapping.and_then(|mapping| mapping.field_id))
+                    .ok_or_else(|| {
+                        IcebergError::Invalid(format!("Avro field {} has no field id", field.name))
+                    })?;
+                let (field_type, required) = self.optional(&field.schema, nested(mapping))?;
+                let mut iceberg = StructField::new(id, &field.name, required, field_type);
+                iceberg.doc = field.doc.clone();
+                Ok(iceberg)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(StructType { fields })
+    }
+
+    // Type of a schema along with whether it is required, optional values being unions with null
+    fn optional(&self, schema: &'a Schema, mapped: &[MappedField]) -> Result<(IcebergType, bool)> {
+        match self.resolve(schema) {
+            Schema::Union(union) => match union.variants() {
+                [Schema::Null, schema] | [schema, Schema::Null] => {
+                    Ok((self.convert(schema, mapped)?, false))
+                }
+                _ => Err(IcebergError::Unsupported(format!(
+                    "Avro union {:?}, only unions of null and another type are supported",
+                    union
+                ))),
+            },
+            schema => Ok((self.convert(schema, mapped)?, true)),
+        }
+    }
+
+    fn convert(&self, schema: &'a Schema, mapped: &[MappedField]) -> Result<IcebergType> {
+        let id = |attributes: &BTreeMap<String, serde_json::Value>, attribute: &str, name: &str| {
+            attributes
+                .get(attribute)
+                .and_then(|id| id.as_i64())
+                .and_then(|id| i32::try_from(id).ok())
+                .or_else(|| find(mapped, name).and_then(|mapping| mapping.field_id))
+                .ok_or_else(|| {
+                    IcebergError::Invalid(format!("Avro schema {:?} has no {}", schema, attribute))
+                })
+        };
+        let primitive = match self.resolve(schema) {
+            Schema::Record(record) => return Ok(IcebergType::Struct(self.record(record, mapped)?)),
+            Schema::Array(array) if array.attributes.get("logicalType") == Some(&json!("map")) => {
+                let entry = match self.resolve(&array.items) {
+                    Schema::Record(entry) if entry.fields.len() == 2 => {
+                        self.record(entry, mapped)?
+                    }
+                    _ => {
+                        return Err(IcebergError::Invalid(format!(
+                            "Avro map {:?} is not an array of key-value records",
+                            array
+                        )))
+                    }
+                };
+                let [key, value] = <[StructField; 2]>::try_from(entry.fields).unwrap();
+                return Ok(IcebergType::Map(MapType {
+                    key_id: key.id,
+                    key: Box::new(key.field_type),
+                    value_id: value.id,
+                    value_required: value.required,
+                    value: Box::new(value.field_type),
+                }));
+            }
+            Schema::Array(array) => {
+                let (element, element_required) =
+                    self.optional(&array.items, nested(find(mapped, "element")))?;
+                return Ok(IcebergType::List(ListType {
+                    element_id: id(&array.attributes, "element-id", "element")?,
+                    element_required,
+                    element: Box::new(element),
+                }));
+            }
+            Schema::Map(map) => {
+                let (value, value_required) =
+                    self.optional(&map.types, nested(find(mapped, "value")))?;
+                return Ok(IcebergType::Map(MapType {
+                    key_id: id(&map.attributes, "key-id", "key")?,
+                    key: Box::new(IcebergType::Primitive(PrimitiveType::String)),
+                    value_id: id(&map.attributes, "value-id", "value")?,
+                    value_required,
+                    value: Box::new(value),
+                }));
+            }
+            Schema::Boolean => PrimitiveType::Boolean,
+            Schema::Int => PrimitiveType::Int,
+            Schema::Long => PrimitiveType::Long,
+            Schema::Float => PrimitiveType::Float,
+            Schema::Double => PrimitiveType::Double,
+            Schema::Decimal(decimal) => PrimitiveType::Decimal {
+                precision: decimal.precision as u8,
+                scale: decimal.scale as u32,
+            },
+            Schema::Date => PrimitiveType::Date,
+            Schema::TimeMicros => PrimitiveType::Time,
+            Schema::LocalTimestampMicros => PrimitiveType::Timestamp,
+            Schema::TimestampMicros => PrimitiveType::Timestamptz,
+            Schema::LocalTimestampNanos => PrimitiveType::TimestampNs,
+            Schema::TimestampNanos => PrimitiveType::TimestamptzNs,
+            Schema::String => PrimitiveType::String,
+            Schema::Uuid => PrimitiveType::Uuid,
+            Schema::Fixed(fixed) if fixed.size == 16 && fixed.name.name == "uuid_fixed" => {
+                PrimitiveType::Uuid
+            }
+            Schema::Fixed(fixed) => PrimitiveType::Fixed(fixed.size as u32),
+            Schema::Bytes => PrimitiveType::Binary,
+            schema => {
+                return Err(IcebergError::Unsupported(format!(
+                    "Converting Avro schema {:?} to Iceberg",
+                    schema
+                )))
+            }
+        };
+        Ok(IcebergType::Primitive(primitive))
+    }
+}
+
+fn nested(mapping: Option<&MappedField>) -> &[MappedField] {
+    mapping.map_or(&[], |mapping| mapping.fields.as_slice())
+}
+
+// Minimum number of bytes holding any unscaled value of the given precision
+fn decimal_required_bytes(precision: u8) -> usize {
+    (1..=16)
+        .find(|bytes| (8 * bytes - 1) as f64 >= precision as f64 * 10f64.log2())
+        .unwrap_or(16)
+}
+
+fn field_id(field: &RecordField) -> Option<i32> {
+    FIELD_ID_ATTRIBUTES
+        .iter()
+        .find_map(|attribute| field.custom_attributes.get(*attribute))
+        .and_then(|id| id.as_i64())
+        .and_then(|id| i32::try_from(id).ok())
+}
+
+// Schema, key-value metadata and records of an Avro file as JSON, to inspect manifests and
+// manifest lists as they were written. Bytes and fixed values are arrays of numbers
+pub fn avro_file_to_json(data: &[u8]) -> Result<serde_json::Value> {
+    let reader = apache_avro::Reader::new(data)?;
+    let schema = serde_json::to_value(reader.writer_schema())?;
+    let metadata: serde_json::Map<_, _> = reader
+        .user_metadata()
+        .iter()
+        .map(|(key, value)| (key.clone(), json!(String::from_utf8_lossy(value))))
+        .collect();
+    let records = reader
+        .map(|record| Ok(serde_json::Value::try_from(record?)?))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(json!({"schema": schema, "metadata": metadata, "records": records}))
+}
+
+// Names of the fields of a schema by field id, including the fields of nested records
+pub(crate) fn field_names(schema: &Schema) -> HashMap<i32, String> {
+    let mut names = HashMap::new();
+    visit_records(schema, &mut |field| {
+        if let Some(id) = field_id(field) {
+            names.insert(id, field.name.clone());
+        }
+    });
+    names
+}
+
+fn visit_records(schema: &Schema, visit: &mut impl FnMut(&RecordField)) {
+    match schema {
+        Schema::Record(record) => {
+            for field in &record.fields {
+                visit(field);
+                visit_records(&field.schema, visit);
+            }
+        }
+        Schema::Union(union) => {
+            for variant in union.variants() {
+                visit_records(variant, visit);
+            }
+        }
+        Schema::Array(array) => visit_records(&array.items, visit),
+        Schema::Map(map) => visit_records(&map.types, visit),
+        _ => {}
+    }
+}
+
+// Renames the fields of values read with the writer schema of a file to the names the expected
+// schema gives to their field ids
+pub(crate) struct FieldIdResolver<'a> {
+    writer: &'a Schema,
+    // Named schemas of the writer schema, referenced by later fields
+    named: HashMap<Name, &'a Schema>,
+    expected: &'a HashMap<i32, String>,
+}
+
+impl<'a> FieldIdResolver<'a> {
+    pub(crate) fn new(writer: &'a Schema, expected: &'a HashMap<i32, String>) -> Self {
+        let mut named = HashMap::new();
+        collect_named(writer, &mut named);
+        FieldIdResolver {
+            writer,
+            named,
+            expected,
+        }
+    }
+
+    pub(crate) fn resolve(&self, value: Value) -> Value {
+        self.resolve_with(value, self.writer)
+    }
+
+    fn resolve_with(&self, value: Value, schema: &Schema) -> Value {
+        match (value, schema) {
+            (value, Schema::Ref { name }) => match self.named.get(name) {
+                Some(schema) => self.resolve_with(value, schema),
+                None => value,
+            },
+            (Value::Record(fields), Schema::Record(record)) => Value::Record(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| {
+                        match record.lookup.get(&name).map(|i| &record.fields[*i]) {
+                            Some(field) => (
+                                field_id(field)
+                                    .and_then(|id| self.expected.get(&id))
+                                    .cloned()
+                                    .unwrap_or(name),
+                                self.resolve_with(value, &field.schema),
+                            ),
+                            None => (name, value),
+                        }
+                    })
+                    .collect(),
+            ),
+            (Value::Union(index, value), Schema::Union(union)) => {
+                match union.variants().get(index as usize) {
+                    Some(variant) => {
+                        Value::Union(index, Box::new(self.resolve_with(*value, variant)))
+                    }
+                    None => Value::Union(index, value),
+                }
+            }
+            (Value::Array(values), Schema::Array(array)) => Value::Array(
+                values
+                    .into_iter()
+                    .map(|value| self.resolve_with(value, &array.items))
+                    .collect(),
+            ),
+            (Value::Map(values), Schema::Map(map)) => Value::Map(
+                values
+                    .into_iter()
+                    .map(|(key, value)| (key, self.resolve_with(value, &map.types)))
+                    .collect(),
+            ),
+            (value, _) => value,
+        }
+    }
+}
+
+fn collect_named<'a>(schema: &'a Schema, named: &mut HashMap<Name, &'a Schema>) {
+    match schema {
+        Schema::Record(record) => {
+            named.insert(record.name.clone(), schema);
+            for field in &record.fields {
+                collect_named(&field.schema, named);
+            }
+        }
+        Schema::Union(union) => {
+            for variant in union.variants() {
+                collect_named(variant, named);
+            }
+        }
+        Schema::Array(array) => collect_named(&array.items, named),
+        Schema::Map(map) => collect_named(&map.types, named),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::spec::schema::{ListType, MapType, StructField};
+
+    #[test]
+    fn test_schema_to_avro() {
+        let primitive = |primitive| Box::new(IcebergType::Primitive(primitive));
+        let mut doc = StructField::new(1, "id", true, *primitive(PrimitiveType::Long));
+        doc.doc = Some("Row id".to_string());
+        let schema = StructType {
+            fields: vec![
+                doc,
+                StructField::new(
+                    2,
+                    "tags",
+                    false,
+                    IcebergType::List(ListType {
+                        element_id: 3,
+                        element_required: false,
+                        element: primitive(PrimitiveType::String),
+                    }),
+                ),
+                StructField::new(
+                    4,
+                    "counts",
+                    true,
+                    IcebergType::Map(MapType {
+                        key_id: 5,
+                        key: primitive(PrimitiveType::Int),
+                        value_id: 6,
+                        value_required: true,
+                        value: primitive(PrimitiveType::Long),
+                    }),
+                ),
+                StructField::new(
+                    7,
+                    "point",
+                    false,
+                    IcebergType::Struct(StructType {
+                        fields: vec![StructField::new(
+                            8,
+                            "x",
+                            true,
+                            *primitive(PrimitiveType::Double),
+                        )],
+                    }),
+                ),
+            ],
+        };
+        let avro = schema_to_avro(&schema, "row").unwrap();
+        let names = field_names(&avro);
+        assert_eq!(
+            HashMap::from([
+                (1, "id".to_string()),
+                (2, "tags".to_string()),
+                (4, "counts".to_string()),
+                (5, "key".to_string()),
+                (6, "value".to_string()),
+                (7, "point".to_string()),
+                (8, "x".to_string()),
+            ]),
+            names
+        );
+        let Schema::Record(record) = &avro else {
+            unreachable!()
+        };
+        assert_eq!(Some("Row id"), record.fields[0].doc.as_deref());
+        let Schema::Union(point) = &record.fields[3].schema else {
+            unreachable!()
+        };
+        assert!(matches!(&point.variants()[1], Schema::Record(r) if r.name.name == "r7"));
+    }
+
+    #[test]
+    fn test_avro_to_schema() {
+        let primitives = [
+            PrimitiveType::Boolean,
+            PrimitiveType::Int,
+            PrimitiveType::Long,
+            PrimitiveType::Float,
+            PrimitiveType::Double,
+            PrimitiveType::Decimal {
+                precision: 10,
+                scale: 2,
+            },
+            PrimitiveType::Date,
+            PrimitiveType::Time,
+            PrimitiveType::Timestamp,
+            PrimitiveType::Timestamptz,
+            PrimitiveType::String,
+            PrimitiveType::Uuid,
+            PrimitiveType::Fixed(3),
+            PrimitiveType::Binary,
+        ];
+        let mut fields: Vec<StructField> = primitives
+            .into_iter()
+            .enumerate()
+            .map(|(i, primitive)| {
+                StructField::new(
+                    i as i32 + 1,
+                    &format!("c{}", i),
+                    i % 2 == 0,
+                    IcebergType::Primitive(primitive),
+                )
+            })
+            .collect();
+        let string = || Box::new(IcebergType::Primitive(PrimitiveType::String));
+        fields.push(StructField::new(
+            100,
+            "by_name",
+            false,
+            IcebergType::Map(MapType {
+                key_id: 101,
+                key: string(),
+                value_id: 102,
+                value_required: false,
+                value: Box::new(IcebergType::List(ListType {
+                    element_id: 103,
+                    element_required: true,
+                    element: string(),
+                })),
+            }),
+        ));
+        fields[0].doc = Some("Flag".to_string());
+        let schema = StructType { fields };
+
+        let avro = schema_to_avro(&schema, "row").unwrap();
+        assert_eq!(schema, avro_to_schema(&avro).unwrap());
+        // Survives the JSON representation written to Avro files, except for docs which
+        // apache_avro doesn't write
+        let json = serde_json::to_string(&avro).unwrap();
+        let mut without_docs = schema.clone();
+        without_docs.fields[0].doc = None;
+        assert_eq!(
+            without_docs,
+            avro_to_schema(&Schema::parse_str(&json).unwrap()).unwrap()
+        );
+
+        let without_ids = Schema::parse_str(
+            r#"{"type": "record", "name": "r", "fields": [{"name": "a", "type": "int"}]}"#,
+        )
+        .unwrap();
+        assert!(avro_to_schema(&without_ids).is_err());
+        assert!(avro_to_schema(&Schema::Int).is_err());
+    }
+
+    #[test]
+    fn test_avro_to_schema_with_name_mapping() {
+        fn strip_ids(value: &mut serde_json::Value) {
+            match value {
+                serde_json::Value::Object(object) => {
+                    for attribute in ["field-id", "element-id", "key-id", "value-id"] {
+                        object.remove(attribute);
+                    }
+                    object.values_mut().for_each(strip_ids);
+                }
+                serde_json::Value::Array(values) => values.iter_mut().for_each(strip_ids),
+                _ => {}
+            }
+        }
+        let string = || Box::new(IcebergType::Primitive(PrimitiveType::String));
+        let schema = StructType {
+            fields: vec![
+                StructField::new(1, "id", true, IcebergType::Primitive(PrimitiveType::Long)),
+                StructField::new(
+                    2,
+                    "point",
+                    false,
+                    IcebergType::Struct(StructType {
+                        fields: vec![StructField::new(
+                            3,
+                            "x",
+                            true,
+                            IcebergType::Primitive(PrimitiveType::Double),
+                        )],
+                    }),
+                ),
+                StructField::new(
+                    4,
+                    "tags",
+                    false,
+                    IcebergType::Map(MapType {
+                        key_id: 5,
+                        key: string(),
+                        value_id: 6,
+                        value_required: false,
+                        value: Box::new(IcebergType::List(ListType {
+                            element_id: 7,
+                            element_required: true,
+                            element: string(),
+                        })),
+                    }),
+                ),
+            ],
+        };
+        let mut json = serde_json::to_value(schema_to_avro(&schema, "row").unwrap()).unwrap();
+        strip_ids(&mut json);
+        let avro = Schema::parse(&json).unwrap();
+        assert!(avro_to_schema(&avro).is_err());
+        let mapping = NameMapping::from_schema(&schema);
+        assert_eq!(
+            schema,
+            avro_to_schema_with_name_mapping(&avro, &mapping).unwrap()
+        );
+
+        // Mappings missing a field fail like files without ids
+        let mut partial = mapping.clone();
+        partial.fields[1].fields.clear();
+        assert!(avro_to_schema_with_name_mapping(&avro, &partial).is_err());
+    }
+
+    #[test]
+    fn test_avro_file_to_json() {
+        let schema = Schema::parse_str(
+            r#"{"type": "record", "name": "r", "fields": [
+                {"name": "id", "type": "long", "field-id": 1},
+                {"name": "key", "type": ["null", "bytes"], "field-id": 2}
+            ]}"#,
+        )
+        .unwrap();
+        let mut writer = apache_avro::Writer::new(&schema, Vec::new());
+        writer
+            .add_user_metadata("format-version".to_string(), "2")
+            .unwrap();
+        let record = Value::Record(vec![
+            ("id".to_string(), Value::Long(7)),
+            (
+                "key".to_string(),
+                Value::Union(1, Box::new(Value::Bytes(vec![1, 2]))),
+            ),
+        ]);
+        writer.append(record).unwrap();
+        let data = writer.into_inner().unwrap();
+
+        let file = avro_file_to_json(&data).unwrap();
+        assert_eq!(json!("2"), file["metadata"]["format-version"]);
+        assert_eq!(json!([{"id": 7, "key": [1, 2]}]), file["records"]);
+        assert_eq!(json!(1), file["schema"]["fields"][0]["field-id"]);
+    }
+
+    #[test]
+    fn test_decimal_required_bytes() {
+        assert_eq!(1, decimal_required_bytes(2));
+        assert_eq!(5, decimal_required_bytes(10));
+        assert_eq!(16, decimal_required_bytes(38));
+    }
+}
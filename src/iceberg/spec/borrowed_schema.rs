@@ -0,0 +1,312 @@
+// Schemas borrowing the names, docs and defaults of their fields from the JSON they are decoded
+// from, for serving the schemas of tables with thousands of columns without allocating a string
+// per field. Strings are only owned when the JSON escapes them. Decoding the current schema of a
+// metadata file skips the rest of the metadata, see current_schema_of. Borrowed schemas convert
+// to owned ones with to_schema when they need to outlive the data
+use std::borrow::Cow;
+
+use serde::{Deserialize, Deserializer};
+use serde_json::value::RawValue;
+
+use super::schema::{
+    IcebergSchemaV2, IcebergType, ListType, MapType, PrimitiveType, StructField, StructType,
+};
+use crate::iceberg::error::{IcebergError, Result};
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct BorrowedSchema<'a> {
+    // Format version 1 schemas may have no id, they are schema 0
+    #[serde(default)]
+    pub schema_id: i32,
+    pub identifier_field_ids: Option<Vec<i32>>,
+    #[serde(borrow)]
+    pub fields: Vec<BorrowedField<'a>>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct BorrowedField<'a> {
+    pub id: i32,
+    #[serde(borrow)]
+    pub name: Cow<'a, str>,
+    pub required: bool,
+    #[serde(rename = "type", borrow)]
+    pub field_type: BorrowedType<'a>,
+    #[serde(default, borrow, deserialize_with = "optional_cow")]
+    pub doc: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "optional_cow")]
+    pub initial_default: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "optional_cow")]
+    pub write_default: Option<Cow<'a, str>>,
+}
+
+// See IcebergType
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum BorrowedType<'a> {
+    Primitive(PrimitiveType),
+    #[serde(borrow)]
+    Struct(BorrowedStruct<'a>),
+    #[serde(borrow)]
+    List(BorrowedList<'a>),
+    #[serde(borrow)]
+    Map(BorrowedMap<'a>),
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", tag = "type", rename = "struct")]
+pub struct BorrowedStruct<'a> {
+    #[serde(borrow)]
+    pub fields: Vec<BorrowedField<'a>>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", tag = "type", rename = "list")]
+pub struct BorrowedList<'a> {
+    pub element_id: i32,
+    pub element_required: bool,
+    #[serde(borrow)]
+    pub element: Box<BorrowedType<'a>>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", tag = "type", rename = "map")]
+pub struct BorrowedMap<'a> {
+    pub key_id: i32,
+    #[serde(borrow)]
+    pub key: Box<BorrowedType<'a>>,
+    pub value_id: i32,
+    pub value_required: bool,
+    #[serde(borrow)]
+    pub value: Box<BorrowedType<'a>>,
+}
+
+// Serde only borrows strings of fields typed Cow<str>, not of optional ones
+fn optional_cow<'de: 'a, 'a, D>(
+    deserializer: D,
+) -> std::result::Result<Option<Cow<'a, str>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Borrowed<'a>(#[serde(borrow)] Cow<'a, str>);
+    Ok(Option::<Borrowed>::deserialize(deserializer)?.map(|borrowed| borrowed.0))
+}
+
+impl<'a> BorrowedSchema<'a> {
+    pub fn from_json(data: &'a str) -> Result<Self> {
+        Ok(serde_json::from_str(data)?)
+    }
+
+    // Top-level field with the given name
+    pub fn field_by_name(&self, name: &str) -> Option<&BorrowedField<'a>> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    // Field with the given id anywhere in the schema, see StructType::field_by_id
+    pub fn field_by_id(&self, id: i32) -> Option<&BorrowedField<'a>> {
+        field_by_id(&self.fields, id)
+    }
+
+    pub fn to_schema(&self) -> IcebergSchemaV2 {
+        IcebergSchemaV2::new(
+            self.schema_id,
+            self.identifier_field_ids.clone(),
+            to_struct(&self.fields),
+        )
+    }
+}
+
+impl BorrowedField<'_> {
+    pub fn to_field(&self) -> StructField {
+        StructField {
+            id: self.id,
+            name: self.name.to_string(),
+            required: self.required,
+            field_type: self.field_type.to_type(),
+            doc: self.doc.as_deref().map(str::to_string),
+            initial_default: self.initial_default.as_deref().map(str::to_string),
+            write_default: self.write_default.as_deref().map(str::to_string),
+        }
+    }
+}
+
+impl<'a> BorrowedType<'a> {
+    pub fn to_type(&self) -> IcebergType {
+        match self {
+            BorrowedType::Primitive(primitive) => IcebergType::Primitive(primitive.clone()),
+            BorrowedType::Struct(struct_type) => {
+                IcebergType::Struct(to_struct(&struct_type.fields))
+            }
+            BorrowedType::List(list) => IcebergType::List(ListType {
+                element_id: list.element_id,
+                element_required: list.element_required,
+                element: Box::new(list.element.to_type()),
+            }),
+            BorrowedType::Map(map) => IcebergType::Map(MapType {
+                key_id: map.key_id,
+                key: Box::new(map.key.to_type()),
+                value_id: map.value_id,
+                value_required: map.value_required,
+                value: Box::new(map.value.to_type()),
+            }),
+        }
+    }
+
+    fn field_by_id(&self, id: i32) -> Option<&BorrowedField<'a>> {
+        match self {
+            BorrowedType::Primitive(_) => None,
+            BorrowedType::Struct(struct_type) => field_by_id(&struct_type.fields, id),
+            BorrowedType::List(list) => list.element.field_by_id(id),
+            BorrowedType::Map(map) => map
+                .key
+                .field_by_id(id)
+                .or_else(|| map.value.field_by_id(id)),
+        }
+    }
+}
+
+fn field_by_id<'s, 'a>(fields: &'s [BorrowedField<'a>], id: i32) -> Option<&'s BorrowedField<'a>> {
+    fields.iter().find_map(|field| {
+        if field.id == id {
+            Some(field)
+        } else {
+            field.field_type.field_by_id(id)
+        }
+    })
+}
+
+fn to_struct(fields: &[BorrowedField]) -> StructType {
+    StructType {
+        fields: fields.iter().map(BorrowedField::to_field).collect(),
+    }
+}
+
+// The schemas of table metadata, undecoded
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawSchemas<'a> {
+    current_schema_id: Option<i32>,
+    #[serde(borrow)]
+    schemas: Option<Vec<&'a RawValue>>,
+    // Single schema of format version 1 metadata
+    #[serde(borrow)]
+    schema: Option<&'a RawValue>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct SchemaId {
+    #[serde(default)]
+    schema_id: i32,
+}
+
+// Decodes the current schema of a table metadata file, of any format version, without decoding
+// the rest of the metadata or the other schemas
+pub fn current_schema_of(data: &str) -> Result<BorrowedSchema<'_>> {
+    let raw: RawSchemas = serde_json::from_str(data)?;
+    let schema = match (raw.current_schema_id, raw.schemas) {
+        (Some(current_schema_id), Some(schemas)) => {
+            let mut current = None;
+            for schema in schemas {
+                if serde_json::from_str::<SchemaId>(schema.get())?.schema_id == current_schema_id {
+                    current = Some(schema);
+                    break;
+                }
+            }
+            current.ok_or_else(|| {
+                IcebergError::Invalid(format!(
+                    "Current schema {} is missing from table metadata",
+                    current_schema_id
+                ))
+            })?
+        }
+        _ => raw.schema.ok_or_else(|| {
+            IcebergError::Invalid("Table metadata has no current schema".to_string())
+        })?,
+    };
+    BorrowedSchema::from_json(schema.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::spec::table_metadata::TableMetadataV2;
+
+    const METADATA: &str = r#"
+    {
+      "format-version" : 2,
+      "table-uuid" : "9c12d441-03fe-4693-9a96-a0705ddf69c1",
+      "location" : "s3://bucket/test/location",
+      "last-sequence-number" : 0,
+      "last-updated-ms" : 1602638573590,
+      "last-column-id" : 7,
+      "current-schema-id" : 1,
+      "schemas" : [ {
+        "type" : "struct",
+        "schema-id" : 0,
+        "fields" : [ { "id" : 1, "name" : "x", "required" : true, "type" : "long" } ]
+      }, {
+        "type" : "struct",
+        "schema-id" : 1,
+        "identifier-field-ids" : [ 1 ],
+        "fields" : [ {
+          "id" : 1, "name" : "x", "required" : true, "type" : "long", "doc" : "The \"x\""
+        }, {
+          "id" : 2, "name" : "location", "required" : false, "type" : {
+            "type" : "struct",
+            "fields" : [ {
+              "id" : 3, "name" : "lat", "required" : false, "type" : "decimal(9, 6)",
+              "initial-default" : "0"
+            } ]
+          }
+        }, {
+          "id" : 4, "name" : "tags", "required" : false, "type" : {
+            "type" : "map", "key-id" : 5, "key" : "string", "value-id" : 6,
+            "value-required" : false,
+            "value" : {
+              "type" : "list", "element-id" : 7, "element-required" : true, "element" : "uuid"
+            }
+          }
+        } ]
+      } ],
+      "default-spec-id" : 0,
+      "partition-specs" : [ { "spec-id" : 0, "fields" : [ ] } ],
+      "last-partition-id" : 999,
+      "default-sort-order-id" : 0,
+      "sort-orders" : [ { "order-id" : 0, "fields" : [ ] } ]
+    }
+    "#;
+
+    #[test]
+    fn test_current_schema_of() {
+        let schema = current_schema_of(METADATA).unwrap();
+        assert_eq!(1, schema.schema_id);
+        let location = schema.field_by_name("location").unwrap();
+        assert!(matches!(location.name, Cow::Borrowed("location")));
+        let lat = schema.field_by_id(3).unwrap();
+        assert_eq!(Some("0"), lat.initial_default.as_deref());
+        assert!(matches!(lat.initial_default, Some(Cow::Borrowed(_))));
+        // Escaped strings are owned
+        let x = schema.field_by_id(1).unwrap();
+        assert!(matches!(&x.doc, Some(Cow::Owned(doc)) if doc == "The \"x\""));
+        assert!(schema.field_by_id(7).is_none());
+
+        let metadata: TableMetadataV2 = serde_json::from_str(METADATA).unwrap();
+        assert_eq!(metadata.current_schema().unwrap(), &schema.to_schema());
+
+        let missing = METADATA.replace("\"current-schema-id\" : 1", "\"current-schema-id\" : 2");
+        assert!(current_schema_of(&missing).is_err());
+
+        // Format version 1 metadata has a single schema
+        let v1 = r#"{"format-version": 1, "schema": {"type": "struct", "fields": [
+            {"id": 1, "name": "x", "required": false, "type": "string"}]}}"#;
+        let schema = current_schema_of(v1).unwrap();
+        assert_eq!(0, schema.schema_id);
+        assert_eq!(
+            StructField::new(1, "x", false, IcebergType::Primitive(PrimitiveType::String)),
+            schema.fields[0].to_field()
+        );
+    }
+}
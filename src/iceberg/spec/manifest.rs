@@ -0,0 +1,1168 @@
+use std::collections::HashMap;
+
+use apache_avro::types::Value;
+use apache_avro::{Reader, Schema, Writer};
+
+use super::avro::{field_names, schema_to_avro, FieldIdResolver};
+use super::manifest_list::{FieldSummaryV2, FileType, ManifestListV2};
+use super::partition_spec::PartitionSpec;
+use super::schema::{
+    IcebergSchemaV2, IcebergType, ListType, MapType, PrimitiveType, StructField, StructType,
+};
+use super::values::{Bound, Literal};
+use crate::iceberg::error::{IcebergError, Result};
+
+// Type of content stored by a data file. Data files for data, and delete files for row-level
+// deletes (V2 tables)
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DataContentType {
+    Data = 0,
+    PositionDeletes = 1,
+    EqualityDeletes = 2,
+}
+
+// Format of a data or delete file. Manifests store the format as a string, which engines write
+// in upper or lower case
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum FileFormat {
+    Parquet,
+    Avro,
+    Orc,
+    Puffin,
+}
+
+// A data or delete file tracked by a manifest. Mirrors the data_file struct of the spec
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataFile {
+    pub content: DataContentType,
+    pub file_path: String,
+    pub file_format: FileFormat,
+    // Partition values in the order of the fields of the partition spec the file was written with
+    pub partition: Vec<Option<Literal>>,
+    pub record_count: i64,
+    pub file_size_in_bytes: i64,
+    // Metrics keyed by column field id
+    pub column_sizes: Option<HashMap<i32, i64>>,
+    pub value_counts: Option<HashMap<i32, i64>>,
+    pub null_value_counts: Option<HashMap<i32, i64>>,
+    pub nan_value_counts: Option<HashMap<i32, i64>>,
+    // Bounds are stored in their binary single-value serialization
+    pub lower_bounds: Option<HashMap<i32, Bound>>,
+    pub upper_bounds: Option<HashMap<i32, Bound>>,
+    pub key_metadata: Option<Vec<u8>>,
+    pub split_offsets: Option<Vec<i64>>,
+    pub equality_ids: Option<Vec<i32>>,
+    pub sort_order_id: Option<i32>,
+    // Data file whose rows a position delete file deletes, required for deletion vectors
+    pub referenced_data_file: Option<String>,
+    // Location of the deletion vector blob in the Puffin file of format version 3 tables
+    pub content_offset: Option<i64>,
+    pub content_size_in_bytes: Option<i64>,
+}
+
+// Status of a file in the snapshot that wrote the manifest
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ManifestStatus {
+    Existing = 0,
+    Added = 1,
+    Deleted = 2,
+}
+
+// An entry of a manifest file. Snapshot id and sequence numbers are optional in the file as they
+// are inherited from the manifest list for added files. Entries returned by read_manifest always
+// have them set
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub status: ManifestStatus,
+    pub snapshot_id: Option<i64>,
+    pub sequence_number: Option<i64>,
+    pub file_sequence_number: Option<i64>,
+    pub data_file: DataFile,
+}
+
+impl DataContentType {
+    pub fn try_from_i32(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(DataContentType::Data),
+            1 => Some(DataContentType::PositionDeletes),
+            2 => Some(DataContentType::EqualityDeletes),
+            _ => None,
+        }
+    }
+}
+
+impl FileFormat {
+    // Name written to manifests
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileFormat::Parquet => "PARQUET",
+            FileFormat::Avro => "AVRO",
+            FileFormat::Orc => "ORC",
+            FileFormat::Puffin => "PUFFIN",
+        }
+    }
+
+    // Extension of the files of the format, without the dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::Parquet => "parquet",
+            FileFormat::Avro => "avro",
+            FileFormat::Orc => "orc",
+            FileFormat::Puffin => "puffin",
+        }
+    }
+
+    // Format of a file according to the extension of its location, if it has a known one
+    pub fn from_location(location: &str) -> Option<Self> {
+        let (_, extension) = location.rsplit_once('.')?;
+        extension.parse().ok()
+    }
+}
+
+impl std::str::FromStr for FileFormat {
+    type Err = IcebergError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "parquet" => Ok(FileFormat::Parquet),
+            "avro" => Ok(FileFormat::Avro),
+            "orc" => Ok(FileFormat::Orc),
+            "puffin" => Ok(FileFormat::Puffin),
+            _ => Err(IcebergError::Unsupported(format!("File format {}", s))),
+        }
+    }
+}
+
+impl std::fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ManifestStatus {
+    pub fn try_from_i32(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(ManifestStatus::Existing),
+            1 => Some(ManifestStatus::Added),
+            2 => Some(ManifestStatus::Deleted),
+            _ => None,
+        }
+    }
+}
+
+impl ManifestEntry {
+    // Entry for a file added by the snapshot writing the manifest. Snapshot id and sequence
+    // numbers are inherited when the manifest is read back
+    pub fn added(data_file: DataFile) -> Self {
+        ManifestEntry {
+            status: ManifestStatus::Added,
+            snapshot_id: None,
+            sequence_number: None,
+            file_sequence_number: None,
+            data_file,
+        }
+    }
+
+    // Whether the file is part of the snapshot the manifest belongs to
+    pub fn is_live(&self) -> bool {
+        self.status != ManifestStatus::Deleted
+    }
+}
+
+// Writes a V2 manifest. All the entries must share the partition spec of the writer and be
+// either data or delete files
+pub struct ManifestWriter<'a> {
+    location: String,
+    snapshot_id: i64,
+    sequence_number: i64,
+    schema: &'a IcebergSchemaV2,
+    spec: &'a PartitionSpec,
+    entries: Vec<ManifestEntry>,
+}
+
+impl<'a> ManifestWriter<'a> {
+    // snapshot_id and sequence_number are the ones of the snapshot committing the manifest
+    pub fn new(
+        location: String,
+        snapshot_id: i64,
+        sequence_number: i64,
+        schema: &'a IcebergSchemaV2,
+        spec: &'a PartitionSpec,
+    ) -> Self {
+        ManifestWriter {
+            location,
+            snapshot_id,
+            sequence_number,
+            schema,
+            spec,
+            entries: vec![],
+        }
+    }
+
+    pub fn add_entry(&mut self, entry: ManifestEntry) {
+        self.entries.push(entry);
+    }
+
+    // Serializes the manifest and returns it along with its entry for the manifest list
+    pub fn finish(self) -> Result<(Vec<u8>, ManifestListV2)> {
+        let partition_type = self
+            .spec
+            .partition_type(self.schema.schema(), self.schema)?;
+        let avro_schema = manifest_entry_schema(&partition_type)?;
+        let content = self.content()?;
+
+        let mut writer = Writer::new(&avro_schema, Vec::new());
+        writer.add_user_metadata("schema".to_string(), serde_json::to_string(self.schema)?)?;
+        writer.add_user_metadata("schema-id".to_string(), self.schema.schema_id.to_string())?;
+        writer.add_user_metadata(
+            "partition-spec".to_string(),
+            serde_json::to_string(&self.spec.fields)?,
+        )?;
+        writer.add_user_metadata(
+            "partition-spec-id".to_string(),
+            self.spec.spec_id.to_string(),
+        )?;
+        writer.add_user_metadata("format-version".to_string(), "2")?;
+        writer.add_user_metadata(
+            "content".to_string(),
+            match content {
+                FileType::Data => "data",
+                FileType::Delete => "deletes",
+            },
+        )?;
+
+        let mut summaries = PartitionSummaries::new(&partition_type);
+        let mut manifest = ManifestListV2 {
+            manifest_path: self.location.clone(),
+            manifest_length: 0,
+            partition_spec_id: self.spec.spec_id,
+            content,
+            sequence_number: self.sequence_number,
+            min_sequence_number: self.sequence_number,
+            added_snapshot_id: self.snapshot_id,
+            added_files_count: 0,
+            existing_files_count: 0,
+            deleted_files_count: 0,
+            added_rows_count: 0,
+            existing_rows_count: 0,
+            deleted_rows_count: 0,
+            partitions: None,
+            key_metadata: None,
+        };
+
+        for entry in &self.entries {
+            let rows = entry.data_file.record_count;
+            match entry.status {
+                ManifestStatus::Added => {
+                    manifest.added_files_count += 1;
+                    manifest.added_rows_count += rows;
+                }
+                ManifestStatus::Existing => {
+                    manifest.existing_files_count += 1;
+                    manifest.existing_rows_count += rows;
+                }
+                ManifestStatus::Deleted => {
+                    manifest.deleted_files_count += 1;
+                    manifest.deleted_rows_count += rows;
+                }
+            }
+            if entry.is_live() {
+                let sequence_number = entry.sequence_number.unwrap_or(self.sequence_number);
+                manifest.min_sequence_number = manifest.min_sequence_number.min(sequence_number);
+            }
+            summaries.update(&entry.data_file.partition)?;
+
+            // Added entries inherit the snapshot id, existing and deleted ones keep theirs
+            let snapshot_id = match entry.status {
+                ManifestStatus::Added => entry.snapshot_id.or(Some(self.snapshot_id)),
+                _ => entry.snapshot_id,
+            };
+            writer.append(entry_to_avro(entry, snapshot_id, &partition_type)?)?;
+        }
+
+        let bytes = writer.into_inner()?;
+        manifest.manifest_length = bytes.len() as i64;
+        manifest.partitions = Some(summaries.finish());
+        Ok((bytes, manifest))
+    }
+
+    fn content(&self) -> Result<FileType> {
+        let deletes = self
+            .entries
+            .iter()
+            .filter(|entry| entry.data_file.content != DataContentType::Data)
+            .count();
+        match deletes {
+            0 => Ok(FileType::Data),
+            n if n == self.entries.len() => Ok(FileType::Delete),
+            _ => Err(IcebergError::Invalid(
+                "A manifest can't track both data and delete files".to_string(),
+            )),
+        }
+    }
+}
+
+// Reads the entries of a manifest of either format version. Fields are resolved by field id, so
+// the partition fields and the fields of the spec may have been written under other names.
+// Snapshot ids and sequence numbers that are not set in the file are inherited from the manifest
+// list entry as described by the spec
+pub fn read_manifest(
+    manifest: &ManifestListV2,
+    data: &[u8],
+    partition_type: &StructType,
+) -> Result<Vec<ManifestEntry>> {
+    Ok(read_entries(data, partition_type)?
+        .into_iter()
+        .map(|mut entry| {
+            if entry.snapshot_id.is_none() {
+                entry.snapshot_id = Some(manifest.added_snapshot_id);
+            }
+            // Only added files may inherit their sequence numbers. V1 manifests are read as if
+            // written at sequence number 0, which is what the manifest list defaults to
+            if entry.sequence_number.is_none()
+                && (entry.status == ManifestStatus::Added || manifest.sequence_number == 0)
+            {
+                entry.sequence_number = Some(manifest.sequence_number);
+            }
+            if entry.file_sequence_number.is_none()
+                && (entry.status == ManifestStatus::Added || manifest.sequence_number == 0)
+            {
+                entry.file_sequence_number = Some(manifest.sequence_number);
+            }
+            entry
+        })
+        .collect())
+}
+
+// Reads the entries of a manifest without its manifest list entry, with the partition type of
+// the schema and partition spec stored in the file. Inherited snapshot ids and sequence numbers
+// are left unset
+pub fn read_manifest_file(data: &[u8]) -> Result<Vec<ManifestEntry>> {
+    let reader = Reader::new(data)?;
+    let metadata = |key: &str| {
+        reader.user_metadata().get(key).ok_or_else(|| {
+            IcebergError::Invalid(format!("Manifest has no {} in its metadata", key))
+        })
+    };
+    let schema: StructType = serde_json::from_slice(metadata("schema")?)?;
+    let spec = PartitionSpec {
+        spec_id: match reader.user_metadata().get("partition-spec-id") {
+            Some(id) => String::from_utf8_lossy(id).parse().map_err(|_| {
+                IcebergError::Invalid("Manifest has an invalid partition-spec-id".to_string())
+            })?,
+            None => 0,
+        },
+        fields: serde_json::from_slice(metadata("partition-spec")?)?,
+    };
+    read_entries(data, &spec.partition_type(&schema, &schema)?)
+}
+
+fn read_entries(data: &[u8], partition_type: &StructType) -> Result<Vec<ManifestEntry>> {
+    let reader = Reader::new(data)?;
+    let writer_schema = reader.writer_schema().clone();
+    let expected = field_names(&manifest_entry_schema(partition_type)?);
+    let resolver = FieldIdResolver::new(&writer_schema, &expected);
+    reader
+        .map(|record| entry_from_avro(resolver.resolve(record?), partition_type))
+        .collect()
+}
+
+// Tracks the partition field summaries stored in manifest lists
+struct PartitionSummaries<'a> {
+    partition_type: &'a StructType,
+    summaries: Vec<FieldSummary>,
+}
+
+#[derive(Default)]
+struct FieldSummary {
+    contains_null: bool,
+    // Only tracked for floating point fields
+    contains_nan: Option<bool>,
+    lower: Option<Literal>,
+    upper: Option<Literal>,
+}
+
+impl<'a> PartitionSummaries<'a> {
+    fn new(partition_type: &'a StructType) -> Self {
+        let summaries = partition_type
+            .fields
+            .iter()
+            .map(|field| {
+                let contains_nan = match field.field_type {
+                    IcebergType::Primitive(PrimitiveType::Float | PrimitiveType::Double) => {
+                        Some(false)
+                    }
+                    _ => None,
+                };
+                FieldSummary {
+                    contains_nan,
+                    ..Default::default()
+                }
+            })
+            .collect();
+        PartitionSummaries {
+            partition_type,
+            summaries,
+        }
+    }
+
+    fn update(&mut self, partition: &[Option<Literal>]) -> Result<()> {
+        if partition.len() != self.partition_type.fields.len() {
+            return Err(IcebergError::Invalid(format!(
+                "Partition tuple has {} values, expected {}",
+                partition.len(),
+                self.partition_type.fields.len()
+            )));
+        }
+        for (value, summary) in partition.iter().zip(self.summaries.iter_mut()) {
+            match value {
+                None => summary.contains_null = true,
+                Some(Literal::Float(v)) if v.is_nan() => summary.contains_nan = Some(true),
+                Some(Literal::Double(v)) if v.is_nan() => summary.contains_nan = Some(true),
+                Some(value) => {
+                    if summary.lower.as_ref().is_none_or(|lower| value < lower) {
+                        summary.lower = Some(value.clone());
+                    }
+                    if summary.upper.as_ref().is_none_or(|upper| value > upper) {
+                        summary.upper = Some(value.clone());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Vec<FieldSummaryV2> {
+        self.summaries
+            .into_iter()
+            .map(|summary| FieldSummaryV2 {
+                contains_null: summary.contains_null,
+                contains_nan: summary.contains_nan,
+                lower_bound: summary.lower.as_ref().map(Bound::from),
+                upper_bound: summary.upper.as_ref().map(Bound::from),
+            })
+            .collect()
+    }
+}
+
+// Avro schema of V2 manifest entries for the given partition type. Field ids and record names
+// follow the spec so that other implementations can read the files
+fn manifest_entry_schema(partition_type: &StructType) -> Result<Schema> {
+    schema_to_avro(&manifest_entry_type(partition_type), "manifest_entry")
+}
+
+// Iceberg type of V2 manifest entries, as defined by the spec
+fn manifest_entry_type(partition_type: &StructType) -> StructType {
+    let primitive = |primitive| IcebergType::Primitive(primitive);
+    let map = |name: &str, id, key_id, value_id, value| {
+        let map = MapType {
+            key_id,
+            key: Box::new(primitive(PrimitiveType::Int)),
+            value_id,
+            value_required: true,
+            value: Box::new(primitive(value)),
+        };
+        StructField::new(id, name, false, IcebergType::Map(map))
+    };
+    let list = |name: &str, id, element_id, element| {
+        let list = ListType {
+            element_id,
+            element_required: true,
+            element: Box::new(primitive(element)),
+        };
+        StructField::new(id, name, false, IcebergType::List(list))
+    };
+    let data_file = StructType {
+        fields: vec![
+            StructField::new(134, "content", true, primitive(PrimitiveType::Int)),
+            StructField::new(100, "file_path", true, primitive(PrimitiveType::String)),
+            StructField::new(101, "file_format", true, primitive(PrimitiveType::String)),
+            StructField::new(
+                102,
+                "partition",
+                true,
+                IcebergType::Struct(partition_type.clone()),
+            ),
+            StructField::new(103, "record_count", true, primitive(PrimitiveType::Long)),
+            StructField::new(
+                104,
+                "file_size_in_bytes",
+                true,
+                primitive(PrimitiveType::Long),
+            ),
+            map("column_sizes", 108, 117, 118, PrimitiveType::Long),
+            map("value_counts", 109, 119, 120, PrimitiveType::Long),
+            map("null_value_counts", 110, 121, 122, PrimitiveType::Long),
+            map("nan_value_counts", 137, 138, 139, PrimitiveType::Long),
+            map("lower_bounds", 125, 126, 127, PrimitiveType::Binary),
+            map("upper_bounds", 128, 129, 130, PrimitiveType::Binary),
+            StructField::new(131, "key_metadata", false, primitive(PrimitiveType::Binary)),
+            list("split_offsets", 132, 133, PrimitiveType::Long),
+            list("equality_ids", 135, 136, PrimitiveType::Int),
+            StructField::new(140, "sort_order_id", false, primitive(PrimitiveType::Int)),
+            StructField::new(
+                143,
+                "referenced_data_file",
+                false,
+                primitive(PrimitiveType::String),
+            ),
+            StructField::new(144, "content_offset", false, primitive(PrimitiveType::Long)),
+            StructField::new(
+                145,
+                "content_size_in_bytes",
+                false,
+                primitive(PrimitiveType::Long),
+            ),
+        ],
+    };
+    StructType {
+        fields: vec![
+            StructField::new(0, "status", true, primitive(PrimitiveType::Int)),
+            StructField::new(1, "snapshot_id", false, primitive(PrimitiveType::Long)),
+            StructField::new(3, "sequence_number", false, primitive(PrimitiveType::Long)),
+            StructField::new(
+                4,
+                "file_sequence_number",
+                false,
+                primitive(PrimitiveType::Long),
+            ),
+            StructField::new(2, "data_file", true, IcebergType::Struct(data_file)),
+        ],
+    }
+}
+
+fn literal_to_avro(literal: &Literal) -> Value {
+    match literal {
+        Literal::Boolean(v) => Value::Boolean(*v),
+        Literal::Int(v) => Value::Int(*v),
+        Literal::Long(v) => Value::Long(*v),
+        Literal::Float(v) => Value::Float(*v),
+        Literal::Double(v) => Value::Double(*v),
+        Literal::Decimal(_) => Value::Decimal(literal.to_bytes().into()),
+        Literal::Date(v) => Value::Date(*v),
+        Literal::Time(v) => Value::TimeMicros(*v),
+        Literal::Timestamp(v) => Value::LocalTimestampMicros(*v),
+        Literal::Timestamptz(v) => Value::TimestampMicros(*v),
+        Literal::String(v) => Value::String(v.clone()),
+        Literal::Uuid(v) => Value::Fixed(16, v.as_bytes().to_vec()),
+        Literal::Fixed(v) => Value::Fixed(v.len(), v.clone()),
+        Literal::Binary(v) => Value::Bytes(v.clone()),
+    }
+}
+
+fn literal_from_avro(value: Value, primitive: &PrimitiveType) -> Result<Option<Literal>> {
+    let literal = match (value, primitive) {
+        (Value::Null, _) => return Ok(None),
+        (Value::Union(_, value), _) => return literal_from_avro(*value, primitive),
+        (Value::Boolean(v), PrimitiveType::Boolean) => Literal::Boolean(v),
+        (Value::Int(v), PrimitiveType::Int) => Literal::Int(v),
+        (Value::Int(v) | Value::Date(v), PrimitiveType::Date) => Literal::Date(v),
+        (Value::Int(v), PrimitiveType::Long) => Literal::Long(v as i64),
+        (Value::Long(v), PrimitiveType::Long) => Literal::Long(v),
+        (Value::Long(v) | Value::TimeMicros(v), PrimitiveType::Time) => Literal::Time(v),
+        (
+            Value::Long(v) | Value::TimestampMicros(v) | Value::LocalTimestampMicros(v),
+            PrimitiveType::Timestamp,
+        ) => Literal::Timestamp(v),
+        (
+            Value::Long(v) | Value::TimestampMicros(v) | Value::LocalTimestampMicros(v),
+            PrimitiveType::Timestamptz,
+        ) => Literal::Timestamptz(v),
+        (Value::Float(v), PrimitiveType::Float) => Literal::Float(v),
+        (Value::Float(v), PrimitiveType::Double) => Literal::Double(v as f64),
+        (Value::Double(v), PrimitiveType::Double) => Literal::Double(v),
+        (Value::Decimal(v), PrimitiveType::Decimal { .. }) => {
+            Literal::try_from_bytes(&Vec::<u8>::try_from(v)?, primitive)?
+        }
+        (Value::Fixed(_, v) | Value::Bytes(v), PrimitiveType::Decimal { .. }) => {
+            Literal::try_from_bytes(&v, primitive)?
+        }
+        (Value::String(v), PrimitiveType::String) => Literal::String(v),
+        (Value::Uuid(v), PrimitiveType::Uuid) => Literal::Uuid(v),
+        (Value::Fixed(_, v) | Value::Bytes(v), PrimitiveType::Uuid) => {
+            Literal::try_from_bytes(&v, primitive)?
+        }
+        (Value::Fixed(_, v), PrimitiveType::Fixed(_)) => Literal::Fixed(v),
+        (Value::Bytes(v), PrimitiveType::Binary) => Literal::Binary(v),
+        (value, primitive) => {
+            return Err(IcebergError::Invalid(format!(
+                "Can't read Avro value {:?} as {:?}",
+                value, primitive
+            )))
+        }
+    };
+    Ok(Some(literal))
+}
+
+fn optional<T>(value: Option<T>, to_avro: impl FnOnce(T) -> Value) -> Value {
+    match value {
+        Some(value) => Value::Union(1, Box::new(to_avro(value))),
+        None => Value::Union(0, Box::new(Value::Null)),
+    }
+}
+
+// Maps with non string keys are stored as arrays of key/value records. Entries are sorted by key
+// to keep the files deterministic
+fn map_to_avro<V: Clone>(map: &HashMap<i32, V>, to_avro: impl Fn(V) -> Value) -> Value {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by_key(|(key, _)| **key);
+    Value::Array(
+        entries
+            .into_iter()
+            .map(|(key, value)| {
+                Value::Record(vec![
+                    ("key".to_string(), Value::Int(*key)),
+                    ("value".to_string(), to_avro(value.clone())),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn entry_to_avro(
+    entry: &ManifestEntry,
+    snapshot_id: Option<i64>,
+    partition_type: &StructType,
+) -> Result<Value> {
+    let data_file = &entry.data_file;
+    let partition = partition_type
+        .fields
+        .iter()
+        .zip(data_file.partition.iter())
+        .map(|(field, value)| {
+            (
+                field.name.clone(),
+                optional(value.as_ref(), literal_to_avro),
+            )
+        })
+        .collect();
+    let long_map = |map: &Option<HashMap<i32, i64>>| {
+        optional(map.as_ref(), |map| map_to_avro(map, Value::Long))
+    };
+    let bound_map = |map: &Option<HashMap<i32, Bound>>| {
+        optional(map.as_ref(), |map| {
+            map_to_avro(map, |bound: Bound| Value::Bytes(bound.into_bytes()))
+        })
+    };
+
+    Ok(Value::Record(vec![
+        ("status".to_string(), Value::Int(entry.status as i32)),
+        (
+            "snapshot_id".to_string(),
+            optional(snapshot_id, Value::Long),
+        ),
+        (
+            "sequence_number".to_string(),
+            optional(entry.sequence_number, Value::Long),
+        ),
+        (
+            "file_sequence_number".to_string(),
+            optional(entry.file_sequence_number, Value::Long),
+        ),
+        (
+            "data_file".to_string(),
+            Value::Record(vec![
+                ("content".to_string(), Value::Int(data_file.content as i32)),
+                (
+                    "file_path".to_string(),
+                    Value::String(data_file.file_path.clone()),
+                ),
+                (
+                    "file_format".to_string(),
+                    Value::String(data_file.file_format.as_str().to_string()),
+                ),
+                ("partition".to_string(), Value::Record(partition)),
+                (
+                    "record_count".to_string(),
+                    Value::Long(data_file.record_count),
+                ),
+                (
+                    "file_size_in_bytes".to_string(),
+                    Value::Long(data_file.file_size_in_bytes),
+                ),
+                (
+                    "column_sizes".to_string(),
+                    long_map(&data_file.column_sizes),
+                ),
+                (
+                    "value_counts".to_string(),
+                    long_map(&data_file.value_counts),
+                ),
+                (
+                    "null_value_counts".to_string(),
+                    long_map(&data_file.null_value_counts),
+                ),
+                (
+                    "nan_value_counts".to_string(),
+                    long_map(&data_file.nan_value_counts),
+                ),
+                (
+                    "lower_bounds".to_string(),
+                    bound_map(&data_file.lower_bounds),
+                ),
+                (
+                    "upper_bounds".to_string(),
+                    bound_map(&data_file.upper_bounds),
+                ),
+                (
+                    "key_metadata".to_string(),
+                    optional(data_file.key_metadata.clone(), Value::Bytes),
+                ),
+                (
+                    "split_offsets".to_string(),
+                    optional(data_file.split_offsets.as_ref(), |offsets| {
+                        Value::Array(offsets.iter().map(|v| Value::Long(*v)).collect())
+                    }),
+                ),
+                (
+                    "equality_ids".to_string(),
+                    optional(data_file.equality_ids.as_ref(), |ids| {
+                        Value::Array(ids.iter().map(|v| Value::Int(*v)).collect())
+                    }),
+                ),
+                (
+                    "sort_order_id".to_string(),
+                    optional(data_file.sort_order_id, Value::Int),
+                ),
+                (
+                    "referenced_data_file".to_string(),
+                    optional(data_file.referenced_data_file.clone(), Value::String),
+                ),
+                (
+                    "content_offset".to_string(),
+                    optional(data_file.content_offset, Value::Long),
+                ),
+                (
+                    "content_size_in_bytes".to_string(),
+                    optional(data_file.content_size_in_bytes, Value::Long),
+                ),
+            ]),
+        ),
+    ]))
+}
+
+// Fields of a record by name. Optional fields are unwrapped from their union
+fn record_fields(value: Value, name: &str) -> Result<HashMap<String, Value>> {
+    match value {
+        Value::Record(fields) => Ok(fields
+            .into_iter()
+            .map(|(name, value)| match value {
+                Value::Union(_, value) => (name, *value),
+                value => (name, value),
+            })
+            .collect()),
+        value => Err(IcebergError::Invalid(format!(
+            "Expected {} to be an Avro record, found {:?}",
+            name, value
+        ))),
+    }
+}
+
+fn invalid_field(name: &str, value: &Value) -> IcebergError {
+    IcebergError::Invalid(format!("Invalid manifest field {}: {:?}", name, value))
+}
+
+fn required_field(fields: &mut HashMap<String, Value>, name: &str) -> Result<Value> {
+    fields
+        .remove(name)
+        .ok_or_else(|| IcebergError::Invalid(format!("Manifest field {} is missing", name)))
+}
+
+fn int_field(fields: &mut HashMap<String, Value>, name: &str) -> Result<Option<i32>> {
+    match fields.remove(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Int(v)) => Ok(Some(v)),
+        Some(value) => Err(invalid_field(name, &value)),
+    }
+}
+
+fn long_field(fields: &mut HashMap<String, Value>, name: &str) -> Result<Option<i64>> {
+    match fields.remove(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Long(v)) => Ok(Some(v)),
+        Some(Value::Int(v)) => Ok(Some(v as i64)),
+        Some(value) => Err(invalid_field(name, &value)),
+    }
+}
+
+fn string_field(fields: &mut HashMap<String, Value>, name: &str) -> Result<String> {
+    match required_field(fields, name)? {
+        Value::String(v) => Ok(v),
+        value => Err(invalid_field(name, &value)),
+    }
+}
+
+fn optional_string_field(
+    fields: &mut HashMap<String, Value>,
+    name: &str,
+) -> Result<Option<String>> {
+    match fields.remove(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(v)) => Ok(Some(v)),
+        Some(value) => Err(invalid_field(name, &value)),
+    }
+}
+
+fn bytes_field(fields: &mut HashMap<String, Value>, name: &str) -> Result<Option<Vec<u8>>> {
+    match fields.remove(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Bytes(v)) => Ok(Some(v)),
+        Some(value) => Err(invalid_field(name, &value)),
+    }
+}
+
+fn array_field(fields: &mut HashMap<String, Value>, name: &str) -> Result<Option<Vec<Value>>> {
+    match fields.remove(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Array(values)) => Ok(Some(values)),
+        Some(value) => Err(invalid_field(name, &value)),
+    }
+}
+
+fn map_field<V>(
+    fields: &mut HashMap<String, Value>,
+    name: &str,
+    from_avro: impl Fn(Value) -> Option<V>,
+) -> Result<Option<HashMap<i32, V>>> {
+    array_field(fields, name)?
+        .map(|entries| {
+            entries
+                .into_iter()
+                .map(|entry| {
+                    let mut entry = record_fields(entry, name)?;
+                    let key = int_field(&mut entry, "key")?
+                        .ok_or_else(|| IcebergError::Invalid(format!("{} has a null key", name)))?;
+                    let value = required_field(&mut entry, "value")?;
+                    let value =
+                        from_avro(value.clone()).ok_or_else(|| invalid_field(name, &value))?;
+                    Ok((key, value))
+                })
+                .collect()
+        })
+        .transpose()
+}
+
+fn entry_from_avro(value: Value, partition_type: &StructType) -> Result<ManifestEntry> {
+    let mut entry = record_fields(value, "manifest_entry")?;
+    let status = int_field(&mut entry, "status")?
+        .and_then(ManifestStatus::try_from_i32)
+        .ok_or_else(|| IcebergError::Invalid("Invalid manifest entry status".to_string()))?;
+    let snapshot_id = long_field(&mut entry, "snapshot_id")?;
+    let sequence_number = long_field(&mut entry, "sequence_number")?;
+    let file_sequence_number = long_field(&mut entry, "file_sequence_number")?;
+
+    let mut data_file = record_fields(required_field(&mut entry, "data_file")?, "data_file")?;
+    // V1 manifests only track data files
+    let content = int_field(&mut data_file, "content")?.unwrap_or(0);
+    let content = DataContentType::try_from_i32(content)
+        .ok_or_else(|| IcebergError::Invalid(format!("Invalid data file content {}", content)))?;
+
+    let mut partition = record_fields(required_field(&mut data_file, "partition")?, "partition")?;
+    let partition = partition_type
+        .fields
+        .iter()
+        .map(
+            |field| match (&field.field_type, partition.remove(&field.name)) {
+                (_, None) => Ok(None),
+                (IcebergType::Primitive(primitive), Some(value)) => {
+                    literal_from_avro(value, primitive)
+                }
+                (field_type, _) => Err(IcebergError::Invalid(format!(
+                    "Partition field {} has non primitive type {:?}",
+                    field.name, field_type
+                ))),
+            },
+        )
+        .collect::<Result<Vec<_>>>()?;
+
+    let long_value = |value: Value| match value {
+        Value::Long(v) => Some(v),
+        _ => None,
+    };
+    let bound_value = |value: Value| match value {
+        Value::Bytes(v) => Some(Bound::new(v)),
+        _ => None,
+    };
+
+    Ok(ManifestEntry {
+        status,
+        snapshot_id,
+        sequence_number,
+        file_sequence_number,
+        data_file: DataFile {
+            content,
+            file_path: string_field(&mut data_file, "file_path")?,
+            file_format: string_field(&mut data_file, "file_format")?.parse()?,
+            partition,
+            record_count: long_field(&mut data_file, "record_count")?.unwrap_or_default(),
+            file_size_in_bytes: long_field(&mut data_file, "file_size_in_bytes")?
+                .unwrap_or_default(),
+            column_sizes: map_field(&mut data_file, "column_sizes", long_value)?,
+            value_counts: map_field(&mut data_file, "value_counts", long_value)?,
+            null_value_counts: map_field(&mut data_file, "null_value_counts", long_value)?,
+            nan_value_counts: map_field(&mut data_file, "nan_value_counts", long_value)?,
+            lower_bounds: map_field(&mut data_file, "lower_bounds", bound_value)?,
+            upper_bounds: map_field(&mut data_file, "upper_bounds", bound_value)?,
+            key_metadata: bytes_field(&mut data_file, "key_metadata")?,
+            split_offsets: array_field(&mut data_file, "split_offsets")?
+                .map(|values| {
+                    values
+                        .into_iter()
+                        .map(long_value)
+                        .collect::<Option<Vec<_>>>()
+                })
+                .map(|values| values.ok_or_else(|| invalid_field("split_offsets", &Value::Null)))
+                .transpose()?,
+            equality_ids: array_field(&mut data_file, "equality_ids")?
+                .map(|values| {
+                    values
+                        .into_iter()
+                        .map(|value| match value {
+                            Value::Int(v) => Some(v),
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<_>>>()
+                })
+                .map(|values| values.ok_or_else(|| invalid_field("equality_ids", &Value::Null)))
+                .transpose()?,
+            sort_order_id: int_field(&mut data_file, "sort_order_id")?,
+            referenced_data_file: optional_string_field(&mut data_file, "referenced_data_file")?,
+            content_offset: long_field(&mut data_file, "content_offset")?,
+            content_size_in_bytes: long_field(&mut data_file, "content_size_in_bytes")?,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::spec::partition_spec::{PartitionField, Transform};
+    use crate::iceberg::spec::schema::StructField;
+
+    fn schema() -> IcebergSchemaV2 {
+        let field = |id: i32, name: &str, primitive: PrimitiveType| StructField {
+            id,
+            name: name.to_string(),
+            required: false,
+            field_type: IcebergType::Primitive(primitive),
+            doc: None,
+            initial_default: None,
+            write_default: None,
+        };
+        IcebergSchemaV2::new(
+            0,
+            None,
+            StructType {
+                fields: vec![
+                    field(1, "id", PrimitiveType::Long),
+                    field(2, "category", PrimitiveType::String),
+                    field(3, "ts", PrimitiveType::Timestamptz),
+                    field(
+                        4,
+                        "price",
+                        PrimitiveType::Decimal {
+                            precision: 10,
+                            scale: 2,
+                        },
+                    ),
+                ],
+            },
+        )
+    }
+
+    fn spec() -> PartitionSpec {
+        let field =
+            |source_id: i32, field_id: i32, name: &str, transform: Transform| PartitionField {
+                source_id,
+                source_ids: vec![],
+                field_id,
+                name: name.to_string(),
+                transform,
+            };
+        PartitionSpec {
+            spec_id: 1,
+            fields: vec![
+                field(2, 1000, "category", Transform::Identity),
+                field(3, 1001, "ts_day", Transform::Day),
+                field(4, 1002, "price", Transform::Identity),
+            ],
+        }
+    }
+
+    fn data_file(path: &str, partition: Vec<Option<Literal>>) -> DataFile {
+        DataFile {
+            content: DataContentType::Data,
+            file_path: path.to_string(),
+            file_format: FileFormat::Parquet,
+            partition,
+            record_count: 10,
+            file_size_in_bytes: 1024,
+            column_sizes: Some(HashMap::from([(1, 80), (2, 120)])),
+            value_counts: Some(HashMap::from([(1, 10), (2, 10)])),
+            null_value_counts: Some(HashMap::from([(1, 0), (2, 1)])),
+            nan_value_counts: None,
+            lower_bounds: Some(HashMap::from([(1, Bound::from(&Literal::Long(1)))])),
+            upper_bounds: Some(HashMap::from([(1, Bound::from(&Literal::Long(10)))])),
+            key_metadata: None,
+            split_offsets: Some(vec![4]),
+            equality_ids: None,
+            sort_order_id: Some(0),
+            referenced_data_file: None,
+            content_offset: None,
+            content_size_in_bytes: None,
+        }
+    }
+
+    #[test]
+    fn test_file_format() {
+        for name in ["PARQUET", "parquet", "Parquet"] {
+            assert_eq!(FileFormat::Parquet, name.parse::<FileFormat>().unwrap());
+        }
+        assert_eq!(FileFormat::Orc, "orc".parse::<FileFormat>().unwrap());
+        assert!("csv".parse::<FileFormat>().is_err());
+        for format in [
+            FileFormat::Parquet,
+            FileFormat::Avro,
+            FileFormat::Orc,
+            FileFormat::Puffin,
+        ] {
+            assert_eq!(format, format.to_string().parse::<FileFormat>().unwrap());
+            let location = format!("s3://bucket/data/file.{}", format.extension());
+            assert_eq!(Some(format), FileFormat::from_location(&location));
+        }
+        assert_eq!(None, FileFormat::from_location("file:/data/file"));
+    }
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let schema = schema();
+        let spec = spec();
+        let mut writer = ManifestWriter::new("file:/m0.avro".to_string(), 42, 3, &schema, &spec);
+        let added = data_file(
+            "file:/data/a.parquet",
+            vec![
+                Some(Literal::String("books".to_string())),
+                Some(Literal::Date(19000)),
+                Some(Literal::Decimal(1999)),
+            ],
+        );
+        let mut existing = data_file("file:/data/b.parquet", vec![None, None, None]);
+        existing.referenced_data_file = Some("file:/data/c.parquet".to_string());
+        existing.content_offset = Some(4);
+        existing.content_size_in_bytes = Some(42);
+        writer.add_entry(ManifestEntry::added(added.clone()));
+        writer.add_entry(ManifestEntry {
+            status: ManifestStatus::Existing,
+            snapshot_id: Some(7),
+            sequence_number: Some(1),
+            file_sequence_number: Some(1),
+            data_file: existing.clone(),
+        });
+        let (bytes, manifest) = writer.finish().unwrap();
+
+        assert_eq!(bytes.len() as i64, manifest.manifest_length);
+        assert_eq!(1, manifest.added_files_count);
+        assert_eq!(1, manifest.existing_files_count);
+        assert_eq!(1, manifest.min_sequence_number);
+        let partitions = manifest.partitions.clone().unwrap();
+        assert!(partitions[0].contains_null);
+        assert_eq!(
+            Some(Bound::from(b"books".to_vec())),
+            partitions[0].lower_bound
+        );
+
+        let partition_type = spec.partition_type(schema.schema(), &schema).unwrap();
+        let entries = read_manifest(&manifest, &bytes, &partition_type).unwrap();
+        assert_eq!(
+            vec![
+                ManifestEntry {
+                    status: ManifestStatus::Added,
+                    snapshot_id: Some(42),
+                    sequence_number: Some(3),
+                    file_sequence_number: Some(3),
+                    data_file: added,
+                },
+                ManifestEntry {
+                    status: ManifestStatus::Existing,
+                    snapshot_id: Some(7),
+                    sequence_number: Some(1),
+                    file_sequence_number: Some(1),
+                    data_file: existing,
+                },
+            ],
+            entries
+        );
+
+        // Partition fields are resolved by field id, e.g. after a partition field was renamed
+        let mut renamed = partition_type.clone();
+        renamed.fields[0].name = "genre".to_string();
+        let entries = read_manifest(&manifest, &bytes, &renamed).unwrap();
+        assert_eq!(
+            Some(Literal::String("books".to_string())),
+            entries[0].data_file.partition[0]
+        );
+    }
+
+    #[test]
+    fn test_manifest_header_has_field_ids_and_metadata() {
+        let schema = schema();
+        let spec = spec();
+        let writer = ManifestWriter::new("file:/m0.avro".to_string(), 42, 3, &schema, &spec);
+        let (bytes, _) = writer.finish().unwrap();
+
+        let reader = Reader::new(bytes.as_slice()).unwrap();
+        assert_eq!(
+            Some(&b"1".to_vec()),
+            reader.user_metadata().get("partition-spec-id")
+        );
+        assert_eq!(
+            Some(&b"data".to_vec()),
+            reader.user_metadata().get("content")
+        );
+        let header = serde_json::to_string(reader.writer_schema()).unwrap();
+        assert!(header.contains(r#""field-id":1001"#));
+    }
+
+    #[test]
+    fn test_read_manifest_file() {
+        let schema = schema();
+        let spec = spec();
+        let mut writer = ManifestWriter::new("file:/m0.avro".to_string(), 42, 3, &schema, &spec);
+        let added = data_file(
+            "file:/data/a.parquet",
+            vec![Some(Literal::String("books".to_string())), None, None],
+        );
+        writer.add_entry(ManifestEntry::added(added.clone()));
+        let (bytes, _) = writer.finish().unwrap();
+
+        // Without the manifest list entry nothing is inherited
+        let entries = read_manifest_file(&bytes).unwrap();
+        assert_eq!(
+            vec![ManifestEntry {
+                status: ManifestStatus::Added,
+                snapshot_id: Some(42),
+                sequence_number: None,
+                file_sequence_number: None,
+                data_file: added,
+            }],
+            entries
+        );
+        assert!(read_manifest_file(b"not avro").is_err());
+    }
+
+    #[test]
+    fn test_manifest_rejects_mixed_content() {
+        let schema = schema();
+        let spec = spec();
+        let mut writer = ManifestWriter::new("file:/m0.avro".to_string(), 42, 3, &schema, &spec);
+        let mut deletes = data_file("file:/data/d.parquet", vec![None, None, None]);
+        deletes.content = DataContentType::PositionDeletes;
+        writer.add_entry(ManifestEntry::added(data_file(
+            "file:/data/a.parquet",
+            vec![None, None, None],
+        )));
+        writer.add_entry(ManifestEntry::added(deletes));
+
+        assert!(writer.finish().is_err());
+    }
+}
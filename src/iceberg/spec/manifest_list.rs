@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::fmt;
+
 use once_cell::sync::Lazy;
 #[cfg(test)]
 use proptest;
@@ -6,13 +9,16 @@ use proptest_derive::Arbitrary;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
-use crate::iceberg::spec::manifest_list_avro_schema::{
-    MANIFEST_LIST_V1_SCHEMA, MANIFEST_LIST_V2_SCHEMA,
-};
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::spec::avro::{field_names, schema_to_avro, FieldIdResolver};
+use crate::iceberg::spec::partition_spec::{PartitionSpec, Transform};
+use crate::iceberg::spec::schema::{IcebergType, ListType, PrimitiveType, StructField, StructType};
+use crate::iceberg::spec::values::{Bound, Literal};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(test, derive(Arbitrary))]
-// TODO: Deserialization should really be done based on field-ids and not names (like any other iceberg file)
+// read_manifest_list resolves fields by field id before decoding into these structs. The
+// aliases below are only needed when decoding records by name with apache_avro::from_value
 // Manifest V2 reader should be able to read V1 as well. See https://iceberg.apache.org/spec/#specification
 // This is achieved by using default values for fields that are either not present in V1 or
 // are optional in V1 but required in V2. Note that this is different from making those fields
@@ -73,7 +79,6 @@ pub struct ManifestListV2 {
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(test, derive(Arbitrary))]
-// TODO: Deserialization should really be done based on field-ids and not names (like any other iceberg file)
 // TODO: Do we really need this V1 struct as according to the iceberg spec V2 readers should read V1 as well? We might if we write to V1
 pub struct ManifestListV1 {
     pub manifest_path: String,
@@ -130,13 +135,11 @@ pub struct FieldSummaryV2 {
     pub contains_null: bool,
     pub contains_nan: Option<bool>,
 
-    #[serde(with = "serde_bytes")]
-    #[cfg_attr(test, proptest(strategy(option_vec_strategy)))]
-    pub lower_bound: Option<Vec<u8>>,
+    #[cfg_attr(test, proptest(strategy(option_bound_strategy)))]
+    pub lower_bound: Option<Bound>,
 
-    #[cfg_attr(test, proptest(strategy(option_vec_strategy)))]
-    #[serde(with = "serde_bytes")]
-    pub upper_bound: Option<Vec<u8>>,
+    #[cfg_attr(test, proptest(strategy(option_bound_strategy)))]
+    pub upper_bound: Option<Bound>,
 }
 
 #[cfg(test)]
@@ -147,27 +150,213 @@ where
     proptest::option::of(proptest::collection::vec(T::arbitrary(), 1..10))
 }
 
+#[cfg(test)]
+fn option_bound_strategy() -> impl proptest::strategy::Strategy<Value = Option<Bound>> {
+    use proptest::strategy::Strategy;
+    option_vec_strategy::<u8>().prop_map(|bytes| bytes.map(Bound::new))
+}
+
+// Summary of a partition field of a manifest with its bounds decoded to values of the type of
+// the partition field, see ManifestListV2::decoded_partitions
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedFieldSummary {
+    pub name: String,
+    pub transform: Transform,
+    pub field_type: PrimitiveType,
+    pub contains_null: bool,
+    pub contains_nan: Option<bool>,
+    pub lower_bound: Option<Literal>,
+    pub upper_bound: Option<Literal>,
+}
+
 impl ManifestListV2 {
+    // Partition field summaries of the manifest with decoded bounds. The spec must be the spec
+    // the manifest was written with, and the schema must hold its source columns
+    pub fn decoded_partitions(
+        &self,
+        spec: &PartitionSpec,
+        schema: &StructType,
+    ) -> Result<Vec<DecodedFieldSummary>> {
+        if spec.spec_id != self.partition_spec_id {
+            return Err(IcebergError::Invalid(format!(
+                "Manifest {} was written with partition spec {}, not {}",
+                self.manifest_path, self.partition_spec_id, spec.spec_id
+            )));
+        }
+        let partition_type = spec.partition_type(schema, schema)?;
+        let summaries = self.partitions.as_deref().unwrap_or_default();
+        if summaries.len() != spec.fields.len() {
+            return Err(IcebergError::Invalid(format!(
+                "Manifest {} has {} partition field summaries, its partition spec has {} fields",
+                self.manifest_path,
+                summaries.len(),
+                spec.fields.len()
+            )));
+        }
+        spec.fields
+            .iter()
+            .zip(&partition_type.fields)
+            .zip(summaries)
+            .map(|((field, partition_field), summary)| {
+                let IcebergType::Primitive(field_type) = &partition_field.field_type else {
+                    unreachable!("partition fields are primitive")
+                };
+                let decode = |bound: &Option<Bound>| {
+                    bound
+                        .as_ref()
+                        .map(|bound| bound.decode(field_type).map(|value| value.into_owned()))
+                        .transpose()
+                };
+                Ok(DecodedFieldSummary {
+                    name: field.name.clone(),
+                    transform: field.transform.clone(),
+                    field_type: field_type.clone(),
+                    contains_null: summary.contains_null,
+                    contains_nan: summary.contains_nan,
+                    lower_bound: decode(&summary.lower_bound)?,
+                    upper_bound: decode(&summary.upper_bound)?,
+                })
+            })
+            .collect()
+    }
+
     pub fn avro_schema<'a>() -> &'a apache_avro::Schema {
         static SCHEMA: Lazy<apache_avro::Schema> =
-            Lazy::new(|| apache_avro::Schema::parse_str(MANIFEST_LIST_V2_SCHEMA).unwrap());
+            Lazy::new(|| schema_to_avro(&manifest_list_type(2), "manifest_list").unwrap());
         &SCHEMA
     }
 
     pub fn raw_avro_schema() -> &'static str {
-        MANIFEST_LIST_V2_SCHEMA
+        static SCHEMA: Lazy<String> =
+            Lazy::new(|| serde_json::to_string(ManifestListV2::avro_schema()).unwrap());
+        &SCHEMA
     }
 }
 
 impl ManifestListV1 {
     pub fn avro_schema<'a>() -> &'a apache_avro::Schema {
         static SCHEMA: Lazy<apache_avro::Schema> =
-            Lazy::new(|| apache_avro::Schema::parse_str(MANIFEST_LIST_V1_SCHEMA).unwrap());
+            Lazy::new(|| schema_to_avro(&manifest_list_type(1), "manifest_list").unwrap());
         &SCHEMA
     }
 
     pub fn raw_avro_schema() -> &'static str {
-        MANIFEST_LIST_V1_SCHEMA
+        static SCHEMA: Lazy<String> =
+            Lazy::new(|| serde_json::to_string(ManifestListV1::avro_schema()).unwrap());
+        &SCHEMA
+    }
+}
+
+// Iceberg type of manifest list entries of the given format version, as defined by the spec. V1
+// lacks the content and sequence numbers and has optional file and row counts
+fn manifest_list_type(format_version: i32) -> StructType {
+    let v2 = format_version >= 2;
+    let primitive = |primitive| IcebergType::Primitive(primitive);
+    let field_summary = StructType {
+        fields: vec![
+            StructField::new(
+                509,
+                "contains_null",
+                true,
+                primitive(PrimitiveType::Boolean),
+            ),
+            StructField::new(
+                518,
+                "contains_nan",
+                false,
+                primitive(PrimitiveType::Boolean),
+            ),
+            StructField::new(510, "lower_bound", false, primitive(PrimitiveType::Binary)),
+            StructField::new(511, "upper_bound", false, primitive(PrimitiveType::Binary)),
+        ],
+    };
+    let partitions = ListType {
+        element_id: 508,
+        element_required: true,
+        element: Box::new(IcebergType::Struct(field_summary)),
+    };
+    let mut fields = vec![
+        StructField::new(500, "manifest_path", true, primitive(PrimitiveType::String)),
+        StructField::new(501, "manifest_length", true, primitive(PrimitiveType::Long)),
+        StructField::new(
+            502,
+            "partition_spec_id",
+            true,
+            primitive(PrimitiveType::Int),
+        ),
+    ];
+    if v2 {
+        fields.extend([
+            StructField::new(517, "content", true, primitive(PrimitiveType::Int)),
+            StructField::new(515, "sequence_number", true, primitive(PrimitiveType::Long)),
+            StructField::new(
+                516,
+                "min_sequence_number",
+                true,
+                primitive(PrimitiveType::Long),
+            ),
+        ]);
+    }
+    fields.extend([
+        StructField::new(
+            503,
+            "added_snapshot_id",
+            true,
+            primitive(PrimitiveType::Long),
+        ),
+        StructField::new(504, "added_files_count", v2, primitive(PrimitiveType::Int)),
+        StructField::new(
+            505,
+            "existing_files_count",
+            v2,
+            primitive(PrimitiveType::Int),
+        ),
+        StructField::new(
+            506,
+            "deleted_files_count",
+            v2,
+            primitive(PrimitiveType::Int),
+        ),
+        StructField::new(512, "added_rows_count", v2, primitive(PrimitiveType::Long)),
+        StructField::new(
+            513,
+            "existing_rows_count",
+            v2,
+            primitive(PrimitiveType::Long),
+        ),
+        StructField::new(
+            514,
+            "deleted_rows_count",
+            v2,
+            primitive(PrimitiveType::Long),
+        ),
+        StructField::new(507, "partitions", false, IcebergType::List(partitions)),
+        StructField::new(519, "key_metadata", false, primitive(PrimitiveType::Binary)),
+    ]);
+    StructType { fields }
+}
+
+// e.g. "ts_day: 2022-10-08 to 2022-10-09, contains nulls"
+impl fmt::Display for DecodedFieldSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bound = |bound: &Option<Literal>| match bound {
+            Some(bound) => self.transform.to_human_string(Some(bound)),
+            None => "unknown".to_string(),
+        };
+        write!(
+            f,
+            "{}: {} to {}",
+            self.name,
+            bound(&self.lower_bound),
+            bound(&self.upper_bound)
+        )?;
+        if self.contains_null {
+            write!(f, ", contains nulls")?;
+        }
+        if self.contains_nan == Some(true) {
+            write!(f, ", contains NaNs")?;
+        }
+        Ok(())
     }
 }
 
@@ -177,12 +366,47 @@ impl FileType {
     }
 }
 
+// Reads the manifest files of a snapshot from a manifest list of either format version. Fields
+// are resolved by field id, whatever name the writer gave them
+pub fn read_manifest_list(data: &[u8]) -> Result<Vec<ManifestListV2>> {
+    static FIELD_NAMES: Lazy<HashMap<i32, String>> =
+        Lazy::new(|| field_names(ManifestListV2::avro_schema()));
+    let reader = apache_avro::Reader::new(data)?;
+    let writer_schema = reader.writer_schema().clone();
+    let resolver = FieldIdResolver::new(&writer_schema, &FIELD_NAMES);
+    reader
+        .map(|record| Ok(apache_avro::from_value(&resolver.resolve(record?))?))
+        .collect()
+}
+
+// Writes a V2 manifest list for the snapshot with the given id
+pub fn write_manifest_list(
+    manifests: &[ManifestListV2],
+    snapshot_id: i64,
+    parent_snapshot_id: Option<i64>,
+    sequence_number: i64,
+) -> Result<Vec<u8>> {
+    let mut writer = apache_avro::Writer::new(ManifestListV2::avro_schema(), Vec::new());
+    writer.add_user_metadata("snapshot-id".to_string(), snapshot_id.to_string())?;
+    writer.add_user_metadata(
+        "parent-snapshot-id".to_string(),
+        parent_snapshot_id.map_or("null".to_string(), |id| id.to_string()),
+    )?;
+    writer.add_user_metadata("sequence-number".to_string(), sequence_number.to_string())?;
+    writer.add_user_metadata("format-version".to_string(), "2")?;
+    for manifest in manifests {
+        writer.append_ser(manifest)?;
+    }
+    Ok(writer.into_inner()?)
+}
+
 #[cfg(test)]
 mod tests {
     use proptest::proptest;
     use std::path::PathBuf;
 
     use super::*;
+    use crate::iceberg::spec::partition_spec::PartitionField;
 
     struct Setup {
         resources: PathBuf,
@@ -231,9 +455,9 @@ mod tests {
                     deleted_rows_count: 0,
                     partitions: Some(
                         vec![
-                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![10, 0, 0, 0]), upper_bound: Some(vec![12, 0, 0, 0]) },
-                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![81, 75, 0, 0]), upper_bound: Some(vec![81, 75, 0, 0]) },
-                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 32, 115, 116, 114, 105, 110, 103]), upper_bound: Some(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 110, 111, 116, 104, 101, 114, 32, 115, 116, 114, 105, 110, 103]) },
+                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![10, 0, 0, 0])), upper_bound: Some(Bound::from(vec![12, 0, 0, 0])) },
+                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![81, 75, 0, 0])), upper_bound: Some(Bound::from(vec![81, 75, 0, 0])) },
+                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 32, 115, 116, 114, 105, 110, 103])), upper_bound: Some(Bound::from(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 110, 111, 116, 104, 101, 114, 32, 115, 116, 114, 105, 110, 103])) },
                         ]),
                     key_metadata: None,
                 },
@@ -265,9 +489,9 @@ mod tests {
                     deleted_rows_count: 0,
                     partitions: Some(
                         vec![
-                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![10, 0, 0, 0]), upper_bound: Some(vec![12, 0, 0, 0]) },
-                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![81, 75, 0, 0]), upper_bound: Some(vec![81, 75, 0, 0]) },
-                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 32, 115, 116, 114, 105, 110, 103]), upper_bound: Some(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 110, 111, 116, 104, 101, 114, 32, 115, 116, 114, 105, 110, 103]) }]
+                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![10, 0, 0, 0])), upper_bound: Some(Bound::from(vec![12, 0, 0, 0])) },
+                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![81, 75, 0, 0])), upper_bound: Some(Bound::from(vec![81, 75, 0, 0])) },
+                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 32, 115, 116, 114, 105, 110, 103])), upper_bound: Some(Bound::from(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 110, 111, 116, 104, 101, 114, 32, 115, 116, 114, 105, 110, 103])) }]
                     ),
                     key_metadata: None,
                 },
@@ -296,9 +520,9 @@ mod tests {
                     deleted_rows_count: Some(0),
                     partitions: Some(
                         vec![
-                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![10, 0, 0, 0]), upper_bound: Some(vec![12, 0, 0, 0]) },
-                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![81, 75, 0, 0]), upper_bound: Some(vec![81, 75, 0, 0]) },
-                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 32, 115, 116, 114, 105, 110, 103]), upper_bound: Some(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 110, 111, 116, 104, 101, 114, 32, 115, 116, 114, 105, 110, 103]) },
+                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![10, 0, 0, 0])), upper_bound: Some(Bound::from(vec![12, 0, 0, 0])) },
+                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![81, 75, 0, 0])), upper_bound: Some(Bound::from(vec![81, 75, 0, 0])) },
+                            FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 32, 115, 116, 114, 105, 110, 103])), upper_bound: Some(Bound::from(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 110, 111, 116, 104, 101, 114, 32, 115, 116, 114, 105, 110, 103])) },
                         ]),
                     key_metadata: None,
                 },
@@ -326,9 +550,9 @@ mod tests {
                 deleted_rows_count: 0,
                 partitions: Some(
                     vec![
-                        FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![10, 0, 0, 0]), upper_bound: Some(vec![12, 0, 0, 0]) },
-                        FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![81, 75, 0, 0]), upper_bound: Some(vec![81, 75, 0, 0]) },
-                        FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 32, 115, 116, 114, 105, 110, 103]), upper_bound: Some(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 110, 111, 116, 104, 101, 114, 32, 115, 116, 114, 105, 110, 103]) }]
+                        FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![10, 0, 0, 0])), upper_bound: Some(Bound::from(vec![12, 0, 0, 0])) },
+                        FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![81, 75, 0, 0])), upper_bound: Some(Bound::from(vec![81, 75, 0, 0])) },
+                        FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 32, 115, 116, 114, 105, 110, 103])), upper_bound: Some(Bound::from(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 110, 111, 116, 104, 101, 114, 32, 115, 116, 114, 105, 110, 103])) }]
                 ),
                 key_metadata: None,
             };
@@ -359,9 +583,9 @@ mod tests {
                 deleted_rows_count: Some(0),
                 partitions: Some(
                     vec![
-                        FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![10, 0, 0, 0]), upper_bound: Some(vec![12, 0, 0, 0]) },
-                        FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![81, 75, 0, 0]), upper_bound: Some(vec![81, 75, 0, 0]) },
-                        FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 32, 115, 116, 114, 105, 110, 103]), upper_bound: Some(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 110, 111, 116, 104, 101, 114, 32, 115, 116, 114, 105, 110, 103]) },
+                        FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![10, 0, 0, 0])), upper_bound: Some(Bound::from(vec![12, 0, 0, 0])) },
+                        FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![81, 75, 0, 0])), upper_bound: Some(Bound::from(vec![81, 75, 0, 0])) },
+                        FieldSummaryV2 { contains_null: false, contains_nan: Some(false), lower_bound: Some(Bound::from(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 32, 115, 116, 114, 105, 110, 103])), upper_bound: Some(Bound::from(vec![116, 104, 105, 115, 32, 105, 115, 32, 97, 110, 111, 116, 104, 101, 114, 32, 115, 116, 114, 105, 110, 103])) },
                     ]),
                 key_metadata: None,
             };
@@ -376,6 +600,129 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_write_and_read_manifest_list() {
+        let v2_contents = Setup::new().manifest_v2();
+        let manifests = read_manifest_list(&v2_contents).unwrap();
+
+        let encoded = write_manifest_list(&manifests, 1, None, 1).unwrap();
+        assert_eq!(manifests, read_manifest_list(&encoded).unwrap());
+
+        let reader = apache_avro::Reader::new(encoded.as_slice()).unwrap();
+        assert_eq!(
+            Some(&b"1".to_vec()),
+            reader.user_metadata().get("snapshot-id")
+        );
+    }
+
+    #[test]
+    fn test_decoded_partitions() {
+        let mut manifest = read_manifest_list(&Setup::new().manifest_v2()).unwrap()[0].clone();
+        manifest.partition_spec_id = 1;
+        manifest.partitions = Some(vec![FieldSummaryV2 {
+            contains_null: true,
+            contains_nan: None,
+            lower_bound: Some(Bound::from(&Literal::Date(19273))),
+            upper_bound: Some(Bound::from(&Literal::Date(19274))),
+        }]);
+        let schema = StructType {
+            fields: vec![StructField::new(
+                1,
+                "ts",
+                true,
+                IcebergType::Primitive(PrimitiveType::Timestamp),
+            )],
+        };
+        let spec = PartitionSpec {
+            spec_id: 1,
+            fields: vec![PartitionField {
+                source_id: 1,
+                source_ids: vec![],
+                field_id: 1000,
+                name: "ts_day".to_string(),
+                transform: Transform::Day,
+            }],
+        };
+        let summaries = manifest.decoded_partitions(&spec, &schema).unwrap();
+        assert_eq!(Some(Literal::Date(19273)), summaries[0].lower_bound);
+        assert_eq!(PrimitiveType::Date, summaries[0].field_type);
+        assert_eq!(
+            "ts_day: 2022-10-08 to 2022-10-09, contains nulls",
+            summaries[0].to_string()
+        );
+
+        let other_spec = PartitionSpec {
+            spec_id: 0,
+            fields: vec![],
+        };
+        assert!(manifest.decoded_partitions(&other_spec, &schema).is_err());
+    }
+
+    #[test]
+    fn test_read_manifest_list_by_field_id() {
+        // Spark's V1 manifest lists name the file counts added_data_files_count and so on
+        let v1 = read_manifest_list(&Setup::new().manifest_v1()).unwrap();
+        assert_eq!(2, v1[0].added_files_count);
+        assert_eq!(2, v1[0].added_rows_count);
+
+        // A writer using its own names
+        let schema = ManifestListV2::raw_avro_schema()
+            .replace("\"manifest_path\"", "\"path\"")
+            .replace("\"added_files_count\"", "\"added_data_files_count\"")
+            .replace("\"contains_null\"", "\"has_nulls\"");
+        let schema = apache_avro::Schema::parse_str(&schema).unwrap();
+        let manifests = read_manifest_list(&Setup::new().manifest_v2()).unwrap();
+        let rename = |name: String| match name.as_str() {
+            "manifest_path" => "path".to_string(),
+            "added_files_count" => "added_data_files_count".to_string(),
+            "contains_null" => "has_nulls".to_string(),
+            _ => name,
+        };
+        let mut writer = apache_avro::Writer::new(&schema, Vec::new());
+        for manifest in &manifests {
+            let apache_avro::types::Value::Record(fields) =
+                apache_avro::to_value(manifest).unwrap()
+            else {
+                unreachable!()
+            };
+            let fields = fields
+                .into_iter()
+                .map(|(name, value)| (rename(name), rename_nested(value, &rename)))
+                .collect();
+            writer
+                .append(apache_avro::types::Value::Record(fields))
+                .unwrap();
+        }
+        let encoded = writer.into_inner().unwrap();
+        assert_eq!(manifests, read_manifest_list(&encoded).unwrap());
+    }
+
+    // Renames the fields of records nested in arrays and unions
+    fn rename_nested(
+        value: apache_avro::types::Value,
+        rename: &impl Fn(String) -> String,
+    ) -> apache_avro::types::Value {
+        use apache_avro::types::Value;
+        match value {
+            Value::Record(fields) => Value::Record(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (rename(name), rename_nested(value, rename)))
+                    .collect(),
+            ),
+            Value::Array(values) => Value::Array(
+                values
+                    .into_iter()
+                    .map(|value| rename_nested(value, rename))
+                    .collect(),
+            ),
+            Value::Union(index, value) => {
+                Value::Union(index, Box::new(rename_nested(*value, rename)))
+            }
+            value => value,
+        }
+    }
+
     proptest! {
         #[test]
         fn test_manifest_list_v1_roundtrip_arbitrary(v1_manifest_list: ManifestListV1) {
@@ -1,7 +1,20 @@
+#[cfg(feature = "avro")]
+pub mod avro;
+pub mod borrowed_schema;
+#[cfg(feature = "avro")]
+pub mod manifest;
+#[cfg(feature = "avro")]
 pub mod manifest_list;
-pub(crate) mod manifest_list_avro_schema;
+pub mod name_mapping;
 pub mod partition_spec;
+#[cfg(feature = "arrow")]
+pub mod partition_statistics;
 pub mod schema;
+pub mod schema_compatibility;
 pub mod snapshot;
+#[cfg(feature = "avro")]
+pub mod snapshot_summary;
 pub mod sort_orders;
 pub mod table_metadata;
+pub mod values;
+pub mod view_metadata;
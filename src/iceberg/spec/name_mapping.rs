@@ -0,0 +1,152 @@
+// Name mappings assign field ids to the columns of data files written without them, e.g. files of
+// tables imported from Hive. A mapping is stored as JSON in the schema.name-mapping.default table
+// property and maps the names a column had in data files, including names from before renames, to
+// its field id. Nested fields, list elements ("element") and map keys and values ("key", "value")
+// are mapped by the fields of their parent
+use serde::{Deserialize, Serialize};
+
+use super::schema::{IcebergType, StructType};
+use crate::iceberg::error::Result;
+
+pub const DEFAULT_NAME_MAPPING_PROPERTY: &str = "schema.name-mapping.default";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(transparent)]
+pub struct NameMapping {
+    pub fields: Vec<MappedField>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct MappedField {
+    // Columns without an id are known by the mapping but not read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field_id: Option<i32>,
+    pub names: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<MappedField>,
+}
+
+impl NameMapping {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    // Mapping of the current names of the fields of a schema, e.g. for the schema of an imported
+    // table
+    pub fn from_schema(schema: &StructType) -> Self {
+        NameMapping {
+            fields: map_struct(schema),
+        }
+    }
+
+    // Top-level field with the given name
+    pub fn field(&self, name: &str) -> Option<&MappedField> {
+        find(&self.fields, name)
+    }
+
+    // Field id of a column given the names along its path, e.g. ["location", "latitude"]
+    pub fn field_id(&self, path: &[&str]) -> Option<i32> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.field(first)?, |field, name| field.field(name))?
+            .field_id
+    }
+}
+
+impl MappedField {
+    // Nested field with the given name
+    pub fn field(&self, name: &str) -> Option<&MappedField> {
+        find(&self.fields, name)
+    }
+}
+
+pub(crate) fn find<'a>(fields: &'a [MappedField], name: &str) -> Option<&'a MappedField> {
+    fields
+        .iter()
+        .find(|field| field.names.iter().any(|n| n == name))
+}
+
+fn map_struct(schema: &StructType) -> Vec<MappedField> {
+    schema
+        .fields
+        .iter()
+        .map(|field| mapped(field.id, &field.name, &field.field_type))
+        .collect()
+}
+
+fn mapped(id: i32, name: &str, field_type: &IcebergType) -> MappedField {
+    let fields = match field_type {
+        IcebergType::Primitive(_) => vec![],
+        IcebergType::Struct(nested) => map_struct(nested),
+        IcebergType::List(list) => vec![mapped(list.element_id, "element", &list.element)],
+        IcebergType::Map(map) => vec![
+            mapped(map.key_id, "key", &map.key),
+            mapped(map.value_id, "value", &map.value),
+        ],
+    };
+    MappedField {
+        field_id: Some(id),
+        names: vec![name.to_string()],
+        fields,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::spec::schema::{ListType, PrimitiveType, StructField};
+
+    #[test]
+    fn test_name_mapping() {
+        let json = r#"[
+            {"field-id": 1, "names": ["id", "record_id"]},
+            {"field-id": 2, "names": ["data"]},
+            {"names": ["ignored"]},
+            {"field-id": 3, "names": ["location"], "fields": [
+                {"field-id": 4, "names": ["latitude", "lat"]},
+                {"field-id": 5, "names": ["longitude", "long"]}
+            ]}
+        ]"#;
+        let mapping = NameMapping::from_json(json).unwrap();
+        assert_eq!(4, mapping.fields.len());
+        assert_eq!(Some(1), mapping.field_id(&["record_id"]));
+        assert_eq!(None, mapping.field_id(&["ignored"]));
+        assert_eq!(None, mapping.field_id(&["missing"]));
+        assert_eq!(Some(4), mapping.field_id(&["location", "lat"]));
+        assert_eq!(None, mapping.field_id(&["location", "altitude"]));
+        assert_eq!(None, mapping.field_id(&[]));
+        assert_eq!(
+            mapping,
+            NameMapping::from_json(&mapping.to_json().unwrap()).unwrap()
+        );
+        assert!(NameMapping::from_json(r#"{"field-id": 1}"#).is_err());
+
+        let schema = StructType {
+            fields: vec![
+                StructField::new(1, "id", true, IcebergType::Primitive(PrimitiveType::Long)),
+                StructField::new(
+                    2,
+                    "tags",
+                    false,
+                    IcebergType::List(ListType {
+                        element_id: 3,
+                        element_required: false,
+                        element: Box::new(IcebergType::Primitive(PrimitiveType::String)),
+                    }),
+                ),
+            ],
+        };
+        let mapping = NameMapping::from_schema(&schema);
+        assert_eq!(Some(1), mapping.field_id(&["id"]));
+        assert_eq!(Some(3), mapping.field_id(&["tags", "element"]));
+        assert_eq!(
+            r#"[{"field-id":1,"names":["id"]},{"field-id":2,"names":["tags"],"fields":[{"field-id":3,"names":["element"]}]}]"#,
+            mapping.to_json().unwrap()
+        );
+    }
+}
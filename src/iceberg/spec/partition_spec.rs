@@ -3,23 +3,74 @@ use regex::Regex;
 use serde::de::{self, IntoDeserializer};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+use super::schema::{FieldNames, IcebergType, PrimitiveType, StructField, StructType};
+use super::values::{civil_from_days, Literal};
+use crate::iceberg::error::{self, IcebergError};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct PartitionSpec {
     pub spec_id: i32,
     pub fields: Vec<PartitionField>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
-#[serde(rename_all = "kebab-case")]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case", try_from = "PartitionFieldJson")]
 pub struct PartitionField {
+    // First source column of the transform
     pub source_id: i32,
+    // All source columns of multi-argument transforms (V3), empty for transforms of one column
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub source_ids: Vec<i32>,
     pub field_id: i32,
     pub name: String,
     pub transform: Transform,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+// Partition fields as written by engines, which may only write source-ids for multi-argument
+// transforms
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PartitionFieldJson {
+    source_id: Option<i32>,
+    #[serde(default)]
+    source_ids: Vec<i32>,
+    field_id: i32,
+    name: String,
+    transform: Transform,
+}
+
+impl TryFrom<PartitionFieldJson> for PartitionField {
+    type Error = String;
+
+    fn try_from(field: PartitionFieldJson) -> Result<Self, Self::Error> {
+        Ok(PartitionField {
+            source_id: first_source_id(field.source_id, &field.source_ids, &field.name)?,
+            source_ids: field.source_ids,
+            field_id: field.field_id,
+            name: field.name,
+            transform: field.transform,
+        })
+    }
+}
+
+// Source id of a partition or sort field written with source-id, source-ids or both
+pub(crate) fn first_source_id(
+    source_id: Option<i32>,
+    source_ids: &[i32],
+    field: &str,
+) -> Result<i32, String> {
+    match (source_id, source_ids.first()) {
+        (Some(id), Some(first)) if id != *first => Err(format!(
+            "Field {} has source-id {} but source-ids {:?}",
+            field, id, source_ids
+        )),
+        (Some(id), _) | (None, Some(&id)) => Ok(id),
+        (None, None) => Err(format!("Field {} has no source-id or source-ids", field)),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 // Set remote to Self to make it easy to override Serialize and Deserialize implementations
 // for specific enum variants such as Bucket and Truncate. This avoid boilerplate for using
 // default implementations for others
@@ -32,6 +83,212 @@ pub enum Transform {
     Month,
     Day,
     Hour,
+    // Transform unknown to this version, e.g. written by a newer engine. Tables using it can be
+    // loaded and scanned, but values can't be transformed, so they can't be written to and don't
+    // prune files by partition
+    #[serde(skip)]
+    Unknown(String),
+}
+
+impl PartitionSpec {
+    // Struct type of the partition tuple of files written with this spec. Partition fields are
+    // always optional. Field names describe source columns missing from the schema
+    pub fn partition_type(
+        &self,
+        schema: &StructType,
+        field_names: &dyn FieldNames,
+    ) -> error::Result<StructType> {
+        let fields = self
+            .fields
+            .iter()
+            .map(|field| {
+                let source = schema
+                    .primitive_type_by_id(field.source_id)
+                    .ok_or_else(|| {
+                        IcebergError::Invalid(format!(
+                            "Partition field {} has no primitive source column in the schema, \
+                             its source is {}",
+                            field.name,
+                            field_names.describe_field(field.source_id)
+                        ))
+                    })?;
+                Ok(StructField {
+                    id: field.field_id,
+                    name: field.name.clone(),
+                    required: false,
+                    field_type: IcebergType::Primitive(field.transform.result_type(source)),
+                    doc: None,
+                    initial_default: None,
+                    write_default: None,
+                })
+            })
+            .collect::<error::Result<Vec<_>>>()?;
+        Ok(StructType { fields })
+    }
+
+    // Relative path of the files of a partition, e.g. "category=books/ts_day=2022-10-08"
+    pub fn partition_path(&self, values: &[Option<Literal>]) -> String {
+        self.fields
+            .iter()
+            .zip(values)
+            .map(|(field, value)| {
+                format!(
+                    "{}={}",
+                    escape_path(&field.name),
+                    escape_path(&field.transform.to_human_string(value.as_ref()))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+impl Transform {
+    // Applies the transform to a source value as defined by the spec
+    pub fn apply(&self, value: &Literal) -> error::Result<Literal> {
+        let unsupported =
+            || IcebergError::Unsupported(format!("Transform {:?} of value {:?}", self, value));
+        Ok(match (self, value) {
+            (Transform::Identity, _) => value.clone(),
+            (Transform::Bucket(n), _) => {
+                let hash = match value {
+                    Literal::Int(v) | Literal::Date(v) => murmur3_32(&(*v as i64).to_le_bytes()),
+                    Literal::Boolean(_) | Literal::Float(_) | Literal::Double(_) => {
+                        return Err(unsupported())
+                    }
+                    _ => murmur3_32(&value.to_bytes()),
+                };
+                Literal::Int((hash as i32 & i32::MAX) % *n as i32)
+            }
+            (Transform::Truncate(width), Literal::Int(v)) => {
+                Literal::Int(v - v.rem_euclid(*width as i32))
+            }
+            (Transform::Truncate(width), Literal::Long(v)) => {
+                Literal::Long(v - v.rem_euclid(*width as i64))
+            }
+            (Transform::Truncate(width), Literal::Decimal(v)) => {
+                Literal::Decimal(v - v.rem_euclid(*width as i128))
+            }
+            (Transform::Truncate(width), Literal::String(v)) => {
+                Literal::String(v.chars().take(*width as usize).collect())
+            }
+            (Transform::Truncate(width), Literal::Binary(v)) => {
+                Literal::Binary(v.iter().take(*width as usize).copied().collect())
+            }
+            (Transform::Year | Transform::Month | Transform::Day, Literal::Date(days)) => {
+                self.apply_to_days(*days as i64)
+            }
+            (
+                Transform::Year | Transform::Month | Transform::Day,
+                Literal::Timestamp(micros) | Literal::Timestamptz(micros),
+            ) => self.apply_to_days(micros.div_euclid(MICROS_PER_DAY)),
+            (Transform::Hour, Literal::Timestamp(micros) | Literal::Timestamptz(micros)) => {
+                Literal::Int(micros.div_euclid(MICROS_PER_HOUR) as i32)
+            }
+            _ => return Err(unsupported()),
+        })
+    }
+
+    fn apply_to_days(&self, days: i64) -> Literal {
+        let (year, month, _) = civil_from_days(days);
+        match self {
+            Transform::Year => Literal::Int((year - 1970) as i32),
+            Transform::Month => Literal::Int(((year - 1970) * 12 + month as i64 - 1) as i32),
+            _ => Literal::Date(days as i32),
+        }
+    }
+
+    // Representation of a transformed value used in partition paths
+    pub fn to_human_string(&self, value: Option<&Literal>) -> String {
+        let value = match value {
+            Some(value) => value,
+            None => return "null".to_string(),
+        };
+        match (self, value) {
+            (Transform::Year, Literal::Int(years)) => format!("{:04}", 1970 + *years as i64),
+            (Transform::Month, Literal::Int(months)) => {
+                let months = *months as i64;
+                format!(
+                    "{:04}-{:02}",
+                    1970 + months.div_euclid(12),
+                    months.rem_euclid(12) + 1
+                )
+            }
+            (Transform::Hour, Literal::Int(hours)) => {
+                let hours = *hours as i64;
+                let (year, month, day) = civil_from_days(hours.div_euclid(24));
+                format!(
+                    "{:04}-{:02}-{:02}-{:02}",
+                    year,
+                    month,
+                    day,
+                    hours.rem_euclid(24)
+                )
+            }
+            (_, Literal::Date(days)) => {
+                let (year, month, day) = civil_from_days(*days as i64);
+                format!("{:04}-{:02}-{:02}", year, month, day)
+            }
+            (_, value) => value.to_string(),
+        }
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, Transform::Unknown(_))
+    }
+
+    // Whether the transform is defined for values of the source type. Variants can't be
+    // transformed and transforms of nanosecond timestamps aren't supported yet
+    pub fn can_transform(&self, source: &PrimitiveType) -> bool {
+        if matches!(
+            source,
+            PrimitiveType::TimestampNs | PrimitiveType::TimestamptzNs | PrimitiveType::Variant
+        ) {
+            return false;
+        }
+        match self {
+            Transform::Unknown(_) => false,
+            Transform::Identity => true,
+            Transform::Bucket(_) => !matches!(
+                source,
+                PrimitiveType::Boolean | PrimitiveType::Float | PrimitiveType::Double
+            ),
+            Transform::Truncate(_) => matches!(
+                source,
+                PrimitiveType::Int
+                    | PrimitiveType::Long
+                    | PrimitiveType::Decimal { .. }
+                    | PrimitiveType::String
+                    | PrimitiveType::Binary
+            ),
+            Transform::Year | Transform::Month | Transform::Day => matches!(
+                source,
+                PrimitiveType::Date | PrimitiveType::Timestamp | PrimitiveType::Timestamptz
+            ),
+            Transform::Hour => matches!(
+                source,
+                PrimitiveType::Timestamp | PrimitiveType::Timestamptz
+            ),
+        }
+    }
+
+    // Whether ordering transformed values orders the source values, which sort orders rely on.
+    // Buckets are hashes
+    pub fn preserves_order(&self) -> bool {
+        !matches!(self, Transform::Bucket(_) | Transform::Unknown(_))
+    }
+
+    pub fn result_type(&self, source: &PrimitiveType) -> PrimitiveType {
+        match self {
+            Transform::Identity | Transform::Truncate(_) => source.clone(),
+            Transform::Bucket(_) | Transform::Year | Transform::Month | Transform::Hour => {
+                PrimitiveType::Int
+            }
+            Transform::Day => PrimitiveType::Date,
+            // As in the reference implementation
+            Transform::Unknown(_) => PrimitiveType::String,
+        }
+    }
 }
 
 impl<'de> Deserialize<'de> for Transform {
@@ -45,7 +302,8 @@ impl<'de> Deserialize<'de> for Transform {
         } else if value.starts_with("truncate") {
             try_deserialize_truncate(value.into_deserializer())
         } else {
-            Self::deserialize(value.into_deserializer())
+            Self::deserialize(value.as_str().into_deserializer())
+                .or_else(|_: de::value::Error| Ok(Transform::Unknown(value)))
         }
     }
 }
@@ -60,11 +318,61 @@ impl Serialize for Transform {
             Transform::Truncate(bucket) => {
                 serializer.serialize_str(&format!("truncate[{}]", bucket))
             }
+            Transform::Unknown(transform) => serializer.serialize_str(transform),
             _ => Self::serialize(self, serializer),
         }
     }
 }
 
+const MICROS_PER_HOUR: i64 = 3_600_000_000;
+const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+
+// 32 bit x86 variant of murmur3 with seed 0, the hash used by the bucket transform
+fn murmur3_32(data: &[u8]) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+    let mix = |k: u32| k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+    let mut hash: u32 = 0;
+    let chunks = data.chunks_exact(4);
+    let tail = chunks.remainder();
+    for chunk in chunks {
+        hash ^= mix(u32::from_le_bytes(chunk.try_into().unwrap()));
+        hash = hash
+            .rotate_left(13)
+            .wrapping_mul(5)
+            .wrapping_add(0xe654_6b64);
+    }
+    if !tail.is_empty() {
+        let k = tail
+            .iter()
+            .enumerate()
+            .fold(0u32, |k, (i, byte)| k ^ ((*byte as u32) << (8 * i)));
+        hash ^= mix(k);
+    }
+
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
+
+// Escapes a partition name or value for use in a path, like Java's URLEncoder
+fn escape_path(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' | b'*' | b'_' => {
+                (byte as char).to_string()
+            }
+            b' ' => "+".to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
 fn try_deserialize_bucket<'de, D>(deserializer: D) -> Result<Transform, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -177,7 +485,7 @@ mod tests {
         ];
         let transforms = variants.map(|variant| {
             serde_json::from_str::<Transform>(variant)
-                .expect(&format!("Failed for variant: {}", variant))
+                .unwrap_or_else(|_| panic!("Failed for variant: {}", variant))
         });
         assert_eq!(
             [
@@ -205,11 +513,13 @@ mod tests {
 
         for transform in transforms {
             let ser = serde_json::to_string(&transform)
-                .expect(&format!("Serialization failed for {:?}", &transform));
-            let rt_transform = serde_json::from_str::<Transform>(&ser).expect(&format!(
-                "Deserializion of serialized transform {:?}, {} failed",
-                &transform, ser
-            ));
+                .unwrap_or_else(|_| panic!("Serialization failed for {:?}", &transform));
+            let rt_transform = serde_json::from_str::<Transform>(&ser).unwrap_or_else(|_| {
+                panic!(
+                    "Deserializion of serialized transform {:?}, {} failed",
+                    &transform, ser
+                )
+            });
             assert_eq!(transform, rt_transform);
         }
     }
@@ -243,12 +553,14 @@ mod tests {
             fields: vec![
                 PartitionField {
                     source_id: 4,
+                    source_ids: vec![],
                     field_id: 1000,
                     name: "ts_day".to_string(),
                     transform: Transform::Day,
                 },
                 PartitionField {
                     source_id: 1,
+                    source_ids: vec![],
                     field_id: 1001,
                     name: "id_bucket".to_string(),
                     transform: Transform::Bucket(16),
@@ -259,6 +571,51 @@ mod tests {
         assert_eq!(deserialized_partition_spec, expected_partition_spec);
     }
 
+    #[test]
+    fn test_unknown_and_multi_argument_transforms() {
+        let json = r#"{
+            "spec-id": 1,
+            "fields": [
+                {"source-id": 1, "field-id": 1000, "name": "id_zorder", "transform": "zorder"},
+                {"source-ids": [2, 3], "field-id": 1001, "name": "xy", "transform": "geohash[4]"},
+                {"source-id": 2, "source-ids": [2], "field-id": 1002, "name": "x", "transform": "identity"}
+            ]
+        }"#;
+        let spec: PartitionSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            Transform::Unknown("zorder".to_string()),
+            spec.fields[0].transform
+        );
+        assert_eq!(
+            (2, vec![2, 3]),
+            (spec.fields[1].source_id, spec.fields[1].source_ids.clone())
+        );
+        assert_eq!(
+            (2, Transform::Identity),
+            (spec.fields[2].source_id, spec.fields[2].transform.clone())
+        );
+        let serialized = serde_json::to_value(&spec).unwrap();
+        assert_eq!("geohash[4]", serialized["fields"][1]["transform"]);
+        assert_eq!(spec, serde_json::from_value(serialized).unwrap());
+
+        let transform = &spec.fields[0].transform;
+        assert!(transform.is_unknown());
+        assert!(!transform.can_transform(&PrimitiveType::Long));
+        assert!(!transform.preserves_order());
+        assert!(transform.apply(&Literal::Long(1)).is_err());
+        assert_eq!(
+            PrimitiveType::String,
+            transform.result_type(&PrimitiveType::Long)
+        );
+
+        for invalid in [
+            r#"{"field-id": 1000, "name": "a", "transform": "zorder"}"#,
+            r#"{"source-id": 1, "source-ids": [2], "field-id": 1000, "name": "a", "transform": "zorder"}"#,
+        ] {
+            assert!(serde_json::from_str::<PartitionField>(invalid).is_err());
+        }
+    }
+
     #[test]
     fn test_partition_spec_serde_roundtrip() {
         let spec = PartitionSpec {
@@ -266,12 +623,14 @@ mod tests {
             fields: vec![
                 PartitionField {
                     source_id: 4,
+                    source_ids: vec![],
                     field_id: 1000,
                     name: "ts_day".to_string(),
                     transform: Transform::Day,
                 },
                 PartitionField {
                     source_id: 1,
+                    source_ids: vec![],
                     field_id: 1001,
                     name: "id_truncate".to_string(),
                     transform: Transform::Truncate(16),
@@ -283,4 +642,117 @@ mod tests {
         let deserialized: PartitionSpec = serde_json::from_str(&serialized).unwrap();
         assert_eq!(spec, deserialized);
     }
+
+    // Hash values from Appendix B of the spec
+    #[test]
+    fn test_bucket_hash_matches_spec() {
+        let hash = |literal: Literal| match Transform::Bucket(i32::MAX as u32).apply(&literal) {
+            Ok(Literal::Int(bucket)) => bucket,
+            other => panic!("Unexpected bucket {:?}", other),
+        };
+        assert_eq!(2017239379, hash(Literal::Int(34)));
+        assert_eq!(2017239379, hash(Literal::Long(34)));
+        assert_eq!(1210000089, hash(Literal::String("iceberg".to_string())));
+        // Negative hashes are masked to positive values
+        assert_eq!(-653330422 & i32::MAX, hash(Literal::Date(17486)));
+        assert_eq!(-500754589 & i32::MAX, hash(Literal::Decimal(1420)));
+        assert_eq!(
+            -188683207 & i32::MAX,
+            hash(Literal::Binary(vec![0, 1, 2, 3]))
+        );
+        assert_eq!(
+            1488055340,
+            hash(Literal::Uuid(
+                uuid::Uuid::parse_str("f79c3e09-677c-4bbd-a479-3f349cb785e7").unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_truncate_transform() {
+        assert_eq!(
+            Literal::Int(0),
+            Transform::Truncate(10).apply(&Literal::Int(1)).unwrap()
+        );
+        assert_eq!(
+            Literal::Int(-10),
+            Transform::Truncate(10).apply(&Literal::Int(-1)).unwrap()
+        );
+        assert_eq!(
+            Literal::Decimal(1050),
+            Transform::Truncate(50)
+                .apply(&Literal::Decimal(1065))
+                .unwrap()
+        );
+        assert_eq!(
+            Literal::String("ice".to_string()),
+            Transform::Truncate(3)
+                .apply(&Literal::String("iceberg".to_string()))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_time_transforms() {
+        // 2017-11-16T22:31:08 and the day before the epoch
+        let timestamp = Literal::Timestamp(1510871468000000);
+        assert_eq!(Literal::Int(47), Transform::Year.apply(&timestamp).unwrap());
+        assert_eq!(
+            Literal::Int(574),
+            Transform::Month.apply(&timestamp).unwrap()
+        );
+        assert_eq!(
+            Literal::Date(17486),
+            Transform::Day.apply(&timestamp).unwrap()
+        );
+        assert_eq!(
+            Literal::Int(419686),
+            Transform::Hour.apply(&timestamp).unwrap()
+        );
+        assert_eq!(
+            Literal::Date(-1),
+            Transform::Day.apply(&Literal::Timestamp(-1)).unwrap()
+        );
+        assert_eq!(
+            Literal::Int(-1),
+            Transform::Month.apply(&Literal::Date(-1)).unwrap()
+        );
+        assert!(Transform::Hour.apply(&Literal::Date(1)).is_err());
+    }
+
+    #[test]
+    fn test_partition_path() {
+        let spec = PartitionSpec {
+            spec_id: 0,
+            fields: vec![
+                PartitionField {
+                    source_id: 1,
+                    source_ids: vec![],
+                    field_id: 1000,
+                    name: "category".to_string(),
+                    transform: Transform::Identity,
+                },
+                PartitionField {
+                    source_id: 2,
+                    source_ids: vec![],
+                    field_id: 1001,
+                    name: "ts_hour".to_string(),
+                    transform: Transform::Hour,
+                },
+                PartitionField {
+                    source_id: 2,
+                    source_ids: vec![],
+                    field_id: 1002,
+                    name: "ts_month".to_string(),
+                    transform: Transform::Month,
+                },
+            ],
+        };
+        let path = spec.partition_path(&[
+            Some(Literal::String("a b/c".to_string())),
+            Some(Literal::Int(419686)),
+            None,
+        ]);
+        assert_eq!("category=a+b%2Fc/ts_hour=2017-11-16-22/ts_month=null", path);
+    }
 }
@@ -0,0 +1,270 @@
+// Partition statistics files hold a row per partition of a snapshot with the number of records and
+// files of the partition, so that engines can estimate the size of partitions without reading
+// manifests. Partitions are typed with the unified partition type of the table, see
+// TableMetadataV2::unified_partition_type
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, AsArray, Int32Array, Int64Array, RecordBatch};
+use arrow::datatypes::{Int32Type, Int64Type};
+use bytes::Bytes;
+use parquet::arrow::ArrowWriter;
+
+use super::manifest::{DataContentType, ManifestEntry};
+use super::schema::{IcebergType, PrimitiveType, StructField, StructType};
+use super::values::Literal;
+use crate::iceberg::arrow::{literal_from_array, partitions_to_array, schema_to_arrow};
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::reader::ParquetReader;
+use crate::iceberg::writer::default_writer_properties;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionStatistics {
+    // Values of the fields of the unified partition type, None for fields the partition spec of
+    // the files doesn't have
+    pub partition: Vec<Option<Literal>>,
+    // Latest partition spec the files of the partition were written with
+    pub spec_id: i32,
+    pub data_record_count: i64,
+    pub data_file_count: i32,
+    pub total_data_file_size_in_bytes: i64,
+    pub position_delete_record_count: i64,
+    pub position_delete_file_count: i32,
+    pub equality_delete_record_count: i64,
+    pub equality_delete_file_count: i32,
+    // Number of records once deletes are applied, when known
+    pub total_record_count: Option<i64>,
+    // Commit time in milliseconds and id of the last snapshot that added files to the partition
+    pub last_updated_at: Option<i64>,
+    pub last_updated_snapshot_id: Option<i64>,
+}
+
+impl PartitionStatistics {
+    pub fn new(partition: Vec<Option<Literal>>, spec_id: i32) -> Self {
+        PartitionStatistics {
+            partition,
+            spec_id,
+            data_record_count: 0,
+            data_file_count: 0,
+            total_data_file_size_in_bytes: 0,
+            position_delete_record_count: 0,
+            position_delete_file_count: 0,
+            equality_delete_record_count: 0,
+            equality_delete_file_count: 0,
+            total_record_count: None,
+            last_updated_at: None,
+            last_updated_snapshot_id: None,
+        }
+    }
+
+    // Counts a live entry of the partition, added by the snapshot committed at the given time
+    pub fn add(&mut self, entry: &ManifestEntry, spec_id: i32, committed_at: Option<i64>) {
+        let file = &entry.data_file;
+        match file.content {
+            DataContentType::Data => {
+                self.data_record_count += file.record_count;
+                self.data_file_count += 1;
+                self.total_data_file_size_in_bytes += file.file_size_in_bytes;
+            }
+            DataContentType::PositionDeletes => {
+                self.position_delete_record_count += file.record_count;
+                self.position_delete_file_count += 1;
+            }
+            DataContentType::EqualityDeletes => {
+                self.equality_delete_record_count += file.record_count;
+                self.equality_delete_file_count += 1;
+            }
+        }
+        self.spec_id = self.spec_id.max(spec_id);
+        if committed_at.is_some() && committed_at > self.last_updated_at {
+            self.last_updated_at = committed_at;
+            self.last_updated_snapshot_id = entry.snapshot_id;
+        }
+    }
+}
+
+// Schema of partition statistics files, with the field ids of the spec
+pub fn partition_statistics_schema(partition_type: &StructType) -> StructType {
+    let field = |id, name: &str, required, field_type| StructField {
+        id,
+        name: name.to_string(),
+        required,
+        field_type,
+        doc: None,
+        initial_default: None,
+        write_default: None,
+    };
+    let int = || IcebergType::Primitive(PrimitiveType::Int);
+    let long = || IcebergType::Primitive(PrimitiveType::Long);
+    StructType {
+        fields: vec![
+            field(
+                1,
+                "partition",
+                true,
+                IcebergType::Struct(partition_type.clone()),
+            ),
+            field(2, "spec_id", true, int()),
+            field(3, "data_record_count", true, long()),
+            field(4, "data_file_count", true, int()),
+            field(5, "total_data_file_size_in_bytes", true, long()),
+            field(6, "position_delete_record_count", false, long()),
+            field(7, "position_delete_file_count", false, int()),
+            field(8, "equality_delete_record_count", false, long()),
+            field(9, "equality_delete_file_count", false, int()),
+            field(10, "total_record_count", false, long()),
+            field(11, "last_updated_at", false, long()),
+            field(12, "last_updated_snapshot_id", false, long()),
+        ],
+    }
+}
+
+// Writes the statistics to a Parquet file. Tables without partition fields have no partition
+// statistics, as Parquet can't store the empty partition struct
+pub fn write_partition_statistics(
+    statistics: &[PartitionStatistics],
+    partition_type: &StructType,
+) -> Result<Vec<u8>> {
+    if partition_type.fields.is_empty() {
+        return Err(IcebergError::Invalid(
+            "Partition statistics of an unpartitioned table".to_string(),
+        ));
+    }
+    let schema = Arc::new(schema_to_arrow(&partition_statistics_schema(
+        partition_type,
+    ))?);
+    let partitions: Vec<&[Option<Literal>]> = statistics
+        .iter()
+        .map(|statistics| statistics.partition.as_slice())
+        .collect();
+    let ints = |value: fn(&PartitionStatistics) -> i32| -> ArrayRef {
+        Arc::new(statistics.iter().map(value).collect::<Int32Array>())
+    };
+    let longs = |value: fn(&PartitionStatistics) -> i64| -> ArrayRef {
+        Arc::new(statistics.iter().map(value).collect::<Int64Array>())
+    };
+    let optional_longs = |value: fn(&PartitionStatistics) -> Option<i64>| -> ArrayRef {
+        Arc::new(statistics.iter().map(value).collect::<Int64Array>())
+    };
+    let columns = vec![
+        Arc::new(partitions_to_array(&partitions, partition_type)?) as ArrayRef,
+        ints(|s| s.spec_id),
+        longs(|s| s.data_record_count),
+        ints(|s| s.data_file_count),
+        longs(|s| s.total_data_file_size_in_bytes),
+        longs(|s| s.position_delete_record_count),
+        ints(|s| s.position_delete_file_count),
+        longs(|s| s.equality_delete_record_count),
+        ints(|s| s.equality_delete_file_count),
+        optional_longs(|s| s.total_record_count),
+        optional_longs(|s| s.last_updated_at),
+        optional_longs(|s| s.last_updated_snapshot_id),
+    ];
+    let batch = RecordBatch::try_new(schema.clone(), columns)?;
+    let mut writer = ArrowWriter::try_new(Vec::new(), schema, Some(default_writer_properties()))?;
+    writer.write(&batch)?;
+    Ok(writer.into_inner()?)
+}
+
+pub fn read_partition_statistics(
+    data: Bytes,
+    partition_type: &StructType,
+) -> Result<Vec<PartitionStatistics>> {
+    let schema = partition_statistics_schema(partition_type);
+    let mut statistics = vec![];
+    for batch in ParquetReader::try_new(&schema)?.read(data)? {
+        let batch = batch?;
+        let partitions = batch.column(0).as_struct();
+        let int = |column: usize, row: usize| {
+            let column = batch.column(column).as_primitive::<Int32Type>();
+            column.is_valid(row).then(|| column.value(row))
+        };
+        let long = |column: usize, row: usize| {
+            let column = batch.column(column).as_primitive::<Int64Type>();
+            column.is_valid(row).then(|| column.value(row))
+        };
+        for row in 0..batch.num_rows() {
+            let partition = partition_type
+                .fields
+                .iter()
+                .zip(partitions.columns())
+                .map(|(field, column)| match &field.field_type {
+                    IcebergType::Primitive(primitive) => {
+                        literal_from_array(column.as_ref(), row, primitive)
+                    }
+                    _ => Ok(None),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            statistics.push(PartitionStatistics {
+                partition,
+                spec_id: required(int(1, row), "spec_id")?,
+                data_record_count: required(long(2, row), "data_record_count")?,
+                data_file_count: required(int(3, row), "data_file_count")?,
+                total_data_file_size_in_bytes: required(
+                    long(4, row),
+                    "total_data_file_size_in_bytes",
+                )?,
+                position_delete_record_count: long(5, row).unwrap_or_default(),
+                position_delete_file_count: int(6, row).unwrap_or_default(),
+                equality_delete_record_count: long(7, row).unwrap_or_default(),
+                equality_delete_file_count: int(8, row).unwrap_or_default(),
+                total_record_count: long(9, row),
+                last_updated_at: long(10, row),
+                last_updated_snapshot_id: long(11, row),
+            });
+        }
+    }
+    Ok(statistics)
+}
+
+fn required<T>(value: Option<T>, name: &str) -> Result<T> {
+    value.ok_or_else(|| IcebergError::Invalid(format!("Partition statistics without {}", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition_statistics_roundtrip() {
+        let field = |id, name: &str, primitive| StructField {
+            id,
+            name: name.to_string(),
+            required: false,
+            field_type: IcebergType::Primitive(primitive),
+            doc: None,
+            initial_default: None,
+            write_default: None,
+        };
+        let partition_type = StructType {
+            fields: vec![
+                field(1000, "category", PrimitiveType::String),
+                field(1001, "day", PrimitiveType::Date),
+            ],
+        };
+        let mut first = PartitionStatistics::new(
+            vec![
+                Some(Literal::String("a".to_string())),
+                Some(Literal::Date(3)),
+            ],
+            1,
+        );
+        first.data_record_count = 10;
+        first.data_file_count = 2;
+        first.total_data_file_size_in_bytes = 1024;
+        first.position_delete_record_count = 1;
+        first.position_delete_file_count = 1;
+        first.last_updated_at = Some(1_000);
+        first.last_updated_snapshot_id = Some(7);
+        // Written with a spec without the day field
+        let mut second = PartitionStatistics::new(vec![None, None], 0);
+        second.data_record_count = 5;
+        second.data_file_count = 1;
+
+        let data =
+            write_partition_statistics(&[first.clone(), second.clone()], &partition_type).unwrap();
+        let read = read_partition_statistics(Bytes::from(data), &partition_type).unwrap();
+        assert_eq!(vec![first, second], read);
+
+        assert!(write_partition_statistics(&[], &StructType { fields: vec![] }).is_err());
+    }
+}
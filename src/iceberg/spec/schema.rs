@@ -1,33 +1,47 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::io;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::de::{self, IntoDeserializer};
 use serde::{Deserialize, Serialize};
+use serde_json::ser::{CharEscape, Formatter};
+
+use crate::iceberg::error::{self, IcebergError};
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct IcebergSchemaV2 {
     pub schema_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub identifier_field_ids: Option<Vec<i32>>,
     #[serde(flatten)]
-    pub schema: StructType,
+    schema: StructType,
+    #[serde(skip)]
+    index: FieldIndex,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct IcebergSchemaV1 {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub schema_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub identifier_field_ids: Option<Vec<i32>>,
     #[serde(flatten)]
     pub schema: StructType,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case", tag = "type", rename = "struct")]
 pub struct StructType {
     pub fields: Vec<StructField>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct StructField {
     pub id: i32,
@@ -35,13 +49,16 @@ pub struct StructField {
     pub required: bool,
     #[serde(rename = "type")]
     pub field_type: IcebergType,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub doc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub initial_default: Option<String>, // Optional JSON encoded value
-    pub write_default: Option<String>,   // Optional JSON encoded value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub write_default: Option<String>, // Optional JSON encoded value
 }
 
 // An enum encompassing all the types representable by Iceberg Schema
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 // Set remote to Self to make it easy to override Serialize and Deserialize implementations
 // for specific enum variants such as Fixed and Decimal. This avoid boilerplate for using
 // default implementations for others
@@ -68,7 +85,7 @@ pub enum IcebergType {
 // An enum to represent untagged types in Iceberg Schema. Untagged types are represented
 // directly by a JSON string, whereas tagged types are represented as JSON objects which
 // have the key 'type' and hence are tagged
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 // Set remote to Self to make it easy to override Serialize and Deserialize implementations
 // for specific enum variants such as Fixed and Decimal. This avoid boilerplate for using
 // default implementations for others
@@ -79,7 +96,10 @@ pub enum PrimitiveType {
     Long,
     Float,
     Double,
-    Decimal { precision: u8, scale: u32 }, // precision must be 38 or less
+    Decimal {
+        precision: u8,
+        scale: u32,
+    }, // precision must be 38 or less
     Date,
     Time,
     Timestamp,
@@ -88,9 +108,16 @@ pub enum PrimitiveType {
     Uuid,
     Fixed(u32),
     Binary,
+    // Types added by format version 3. Tables using them can be loaded, but nanosecond values
+    // have no literals and variants no Arrow representation yet
+    #[serde(rename = "timestamp_ns")]
+    TimestampNs,
+    #[serde(rename = "timestamptz_ns")]
+    TimestamptzNs,
+    Variant,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case", tag = "type", rename = "list")]
 pub struct ListType {
     pub element_id: i32,
@@ -98,7 +125,7 @@ pub struct ListType {
     pub element: Box<IcebergType>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case", tag = "type", rename = "map")]
 pub struct MapType {
     pub key_id: i32,
@@ -108,6 +135,435 @@ pub struct MapType {
     pub value: Box<IcebergType>,
 }
 
+impl StructField {
+    // A field without doc or defaults
+    pub fn new(id: i32, name: &str, required: bool, field_type: IcebergType) -> Self {
+        StructField {
+            id,
+            name: name.to_string(),
+            required,
+            field_type,
+            doc: None,
+            initial_default: None,
+            write_default: None,
+        }
+    }
+}
+
+impl StructType {
+    // Top-level field with the given name
+    pub fn field_by_name(&self, name: &str) -> Option<&StructField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    // Field with the given id anywhere in the struct, including structs nested in list elements
+    // and map keys and values
+    pub fn field_by_id(&self, id: i32) -> Option<&StructField> {
+        self.fields.iter().find_map(|field| {
+            if field.id == id {
+                Some(field)
+            } else {
+                field.field_type.field_by_id(id)
+            }
+        })
+    }
+
+    // Field with the given dotted name, e.g. "location.lat" or "points.element.x" for a field of
+    // the structs of a list
+    pub fn field_by_path(&self, path: &str) -> Option<&StructField> {
+        let mut names = path.split('.');
+        let mut field = self.field_by_name(names.next()?)?;
+        let mut field_type = &field.field_type;
+        for name in names {
+            field_type = match (field_type, name) {
+                (IcebergType::Struct(nested), _) => {
+                    field = nested.field_by_name(name)?;
+                    &field.field_type
+                }
+                (IcebergType::List(list), "element") => &list.element,
+                (IcebergType::Map(map), "key") => &map.key,
+                (IcebergType::Map(map), "value") => &map.value,
+                _ => return None,
+            };
+        }
+        // Paths ending with a list element or map key or value name no struct field
+        std::ptr::eq(field_type, &field.field_type).then_some(field)
+    }
+
+    // Looks up the type of a field id anywhere in the struct, including list elements and map
+    // keys and values
+    pub fn type_by_id(&self, id: i32) -> Option<&IcebergType> {
+        self.fields.iter().find_map(|field| {
+            if field.id == id {
+                Some(&field.field_type)
+            } else {
+                field.field_type.type_by_id(id)
+            }
+        })
+    }
+
+    pub fn primitive_type_by_id(&self, id: i32) -> Option<&PrimitiveType> {
+        match self.type_by_id(id) {
+            Some(IcebergType::Primitive(primitive)) => Some(primitive),
+            _ => None,
+        }
+    }
+
+    // Highest field id anywhere in the struct, None for empty structs
+    pub fn max_field_id(&self) -> Option<i32> {
+        self.fields
+            .iter()
+            .flat_map(|field| [Some(field.id), field.field_type.max_field_id()])
+            .flatten()
+            .max()
+    }
+
+    // Dotted name of a field id anywhere in the struct, e.g. "location.lat" or "tags.element"
+    pub fn name_by_id(&self, id: i32) -> Option<String> {
+        self.fields.iter().find_map(|field| {
+            if field.id == id {
+                Some(field.name.clone())
+            } else {
+                let nested = field.field_type.name_by_id(id)?;
+                Some(format!("{}.{}", field.name, nested))
+            }
+        })
+    }
+}
+
+// Translates field ids to column names for error messages, so that users don't have to look
+// ids up in the schemas of the table. Implemented by schemas and by table metadata, which also
+// names the columns dropped from the current schema
+pub trait FieldNames: Debug + Send + Sync {
+    // Dotted name of the field and the id of the schema it was found in, if known
+    fn field_name(&self, id: i32) -> Option<(String, Option<i32>)>;
+
+    // e.g. "column data (field id 2, schema 1)"
+    fn describe_field(&self, id: i32) -> String {
+        match self.field_name(id) {
+            Some((name, Some(schema_id))) => {
+                format!("column {} (field id {}, schema {})", name, id, schema_id)
+            }
+            Some((name, None)) => format!("column {} (field id {})", name, id),
+            None => format!("unknown field id {}", id),
+        }
+    }
+}
+
+impl FieldNames for StructType {
+    fn field_name(&self, id: i32) -> Option<(String, Option<i32>)> {
+        self.name_by_id(id).map(|name| (name, None))
+    }
+}
+
+impl FieldNames for IcebergSchemaV2 {
+    fn field_name(&self, id: i32) -> Option<(String, Option<i32>)> {
+        let name = self.name_by_id(id)?;
+        Some((name.to_string(), Some(self.schema_id)))
+    }
+}
+
+impl IcebergSchemaV2 {
+    pub fn new(schema_id: i32, identifier_field_ids: Option<Vec<i32>>, schema: StructType) -> Self {
+        IcebergSchemaV2 {
+            schema_id,
+            identifier_field_ids,
+            schema,
+            index: FieldIndex::default(),
+        }
+    }
+
+    // Schema of the fields with the given ids along with the structs, lists and maps they are
+    // nested in, e.g. to read only some fields of a struct column. Selecting a struct, list or
+    // map selects everything nested in it. Maps keep their keys whole, entries can't be read
+    // without them. Ids that are not in the schema are ignored
+    pub fn project(&self, field_ids: &[i32]) -> StructType {
+        let selected: HashSet<i32> = field_ids.iter().copied().collect();
+        self.schema.prune(&selected)
+    }
+
+    pub fn schema(&self) -> &StructType {
+        &self.schema
+    }
+
+    // Fields to change in place. The index of the lookups below is dropped, to be built again
+    // from the changed fields
+    pub fn schema_mut(&mut self) -> &mut StructType {
+        self.index = FieldIndex::default();
+        &mut self.schema
+    }
+
+    // The lookups below go through an index of the fields of the schema, built on the first
+    // lookup, instead of walking the nested fields every time
+
+    // Field with the given id anywhere in the schema, see StructType::field_by_id
+    pub fn field_by_id(&self, id: i32) -> Option<&StructField> {
+        let steps = &self.index().by_id.get(&id)?.steps;
+        match steps.last()? {
+            Step::Field(_) => self.resolve(steps)?.0,
+            _ => None,
+        }
+    }
+
+    // Field with the given dotted name, see StructType::field_by_path
+    pub fn field_by_path(&self, path: &str) -> Option<&StructField> {
+        self.field_by_id(self.field_id_by_path(path)?)
+    }
+
+    // Id of a field, list element or map key or value given its dotted name, e.g. "tags.element"
+    pub fn field_id_by_path(&self, path: &str) -> Option<i32> {
+        self.index().by_path.get(path).copied()
+    }
+
+    // Dotted name of a field id, see StructType::name_by_id
+    pub fn name_by_id(&self, id: i32) -> Option<&str> {
+        self.index().by_id.get(&id).map(|entry| entry.path.as_str())
+    }
+
+    // Type of a field id, including list elements and map keys and values
+    pub fn type_by_id(&self, id: i32) -> Option<&IcebergType> {
+        Some(self.resolve(&self.index().by_id.get(&id)?.steps)?.1)
+    }
+
+    fn index(&self) -> &IndexedFields {
+        self.index.0.get_or_init(|| {
+            let mut fields = IndexedFields::default();
+            fields.add_struct(&self.schema, &mut vec![], "");
+            fields
+        })
+    }
+
+    // The field, None for list elements and map keys and values, and the type at the end of
+    // the steps
+    fn resolve(&self, steps: &[Step]) -> Option<(Option<&StructField>, &IcebergType)> {
+        let (first, rest) = steps.split_first()?;
+        let Step::Field(position) = first else {
+            return None;
+        };
+        let mut field = self.schema.fields.get(*position)?;
+        let mut field_type = &field.field_type;
+        let mut is_field = true;
+        for step in rest {
+            is_field = matches!(step, Step::Field(_));
+            field_type = match (field_type, step) {
+                (IcebergType::Struct(nested), Step::Field(position)) => {
+                    field = nested.fields.get(*position)?;
+                    &field.field_type
+                }
+                (IcebergType::List(list), Step::Element) => &list.element,
+                (IcebergType::Map(map), Step::Key) => &map.key,
+                (IcebergType::Map(map), Step::Value) => &map.value,
+                _ => return None,
+            };
+        }
+        Some((is_field.then_some(field), field_type))
+    }
+}
+
+// Lazily built index of the fields of a schema, dropped whenever the fields are borrowed
+// mutably. It is derived from the fields, so it isn't part of the value of the schema
+#[derive(Default, Clone)]
+struct FieldIndex(OnceLock<IndexedFields>);
+
+impl PartialEq for FieldIndex {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for FieldIndex {}
+
+impl Debug for FieldIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FieldIndex")
+    }
+}
+
+#[derive(Default, Clone)]
+struct IndexedFields {
+    by_id: HashMap<i32, IndexedField>,
+    by_path: HashMap<String, i32>,
+}
+
+// Positions of the fields along the path from the root of the schema to a field
+#[derive(Clone)]
+struct IndexedField {
+    steps: Vec<Step>,
+    path: String,
+}
+
+#[derive(Clone, Copy)]
+enum Step {
+    Field(usize),
+    Element,
+    Key,
+    Value,
+}
+
+impl IndexedFields {
+    fn add_struct(&mut self, struct_type: &StructType, steps: &mut Vec<Step>, prefix: &str) {
+        for (position, field) in struct_type.fields.iter().enumerate() {
+            steps.push(Step::Field(position));
+            self.add(
+                field.id,
+                &field.field_type,
+                steps,
+                format!("{}{}", prefix, field.name),
+            );
+            steps.pop();
+        }
+    }
+
+    fn add(&mut self, id: i32, field_type: &IcebergType, steps: &mut Vec<Step>, path: String) {
+        match field_type {
+            IcebergType::Primitive(_) => {}
+            IcebergType::Struct(nested) => self.add_struct(nested, steps, &format!("{}.", path)),
+            IcebergType::List(list) => {
+                self.add_nested(list.element_id, &list.element, steps, Step::Element, &path)
+            }
+            IcebergType::Map(map) => {
+                self.add_nested(map.key_id, &map.key, steps, Step::Key, &path);
+                self.add_nested(map.value_id, &map.value, steps, Step::Value, &path);
+            }
+        }
+        self.by_path.insert(path.clone(), id);
+        self.by_id.insert(
+            id,
+            IndexedField {
+                steps: steps.clone(),
+                path,
+            },
+        );
+    }
+
+    fn add_nested(
+        &mut self,
+        id: i32,
+        field_type: &IcebergType,
+        steps: &mut Vec<Step>,
+        step: Step,
+        parent: &str,
+    ) {
+        let name = match step {
+            Step::Element => "element",
+            Step::Key => "key",
+            _ => "value",
+        };
+        steps.push(step);
+        self.add(id, field_type, steps, format!("{}.{}", parent, name));
+        steps.pop();
+    }
+}
+
+impl StructType {
+    // Fields of the struct that are selected or have selected nested fields, see
+    // IcebergSchemaV2::project
+    fn prune(&self, selected: &HashSet<i32>) -> StructType {
+        StructType {
+            fields: self
+                .fields
+                .iter()
+                .filter_map(|field| {
+                    if selected.contains(&field.id) {
+                        return Some(field.clone());
+                    }
+                    Some(StructField {
+                        field_type: field.field_type.prune(selected)?,
+                        ..field.clone()
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl IcebergType {
+    // The type with only its selected nested fields, None if none are selected
+    fn prune(&self, selected: &HashSet<i32>) -> Option<IcebergType> {
+        match self {
+            IcebergType::Primitive(_) => None,
+            IcebergType::Struct(struct_type) => {
+                let pruned = struct_type.prune(selected);
+                (!pruned.fields.is_empty()).then_some(IcebergType::Struct(pruned))
+            }
+            IcebergType::List(list) if selected.contains(&list.element_id) => Some(self.clone()),
+            IcebergType::List(list) => Some(IcebergType::List(ListType {
+                element: Box::new(list.element.prune(selected)?),
+                ..list.clone()
+            })),
+            IcebergType::Map(map)
+                if selected.contains(&map.key_id)
+                    || selected.contains(&map.value_id)
+                    || map.key.prune(selected).is_some() =>
+            {
+                Some(self.clone())
+            }
+            IcebergType::Map(map) => Some(IcebergType::Map(MapType {
+                value: Box::new(map.value.prune(selected)?),
+                ..map.clone()
+            })),
+        }
+    }
+
+    fn field_by_id(&self, id: i32) -> Option<&StructField> {
+        match self {
+            IcebergType::Primitive(_) => None,
+            IcebergType::Struct(struct_type) => struct_type.field_by_id(id),
+            IcebergType::List(list) => list.element.field_by_id(id),
+            IcebergType::Map(map) => map
+                .key
+                .field_by_id(id)
+                .or_else(|| map.value.field_by_id(id)),
+        }
+    }
+
+    // Type of a field id nested in this type
+    fn type_by_id(&self, id: i32) -> Option<&IcebergType> {
+        match self {
+            IcebergType::Primitive(_) => None,
+            IcebergType::Struct(struct_type) => struct_type.type_by_id(id),
+            IcebergType::List(list) if list.element_id == id => Some(&list.element),
+            IcebergType::List(list) => list.element.type_by_id(id),
+            IcebergType::Map(map) if map.key_id == id => Some(&map.key),
+            IcebergType::Map(map) if map.value_id == id => Some(&map.value),
+            IcebergType::Map(map) => map.key.type_by_id(id).or_else(|| map.value.type_by_id(id)),
+        }
+    }
+
+    fn max_field_id(&self) -> Option<i32> {
+        match self {
+            IcebergType::Primitive(_) => None,
+            IcebergType::Struct(struct_type) => struct_type.max_field_id(),
+            IcebergType::List(list) => list.element.max_field_id().max(Some(list.element_id)),
+            IcebergType::Map(map) => map
+                .key
+                .max_field_id()
+                .max(map.value.max_field_id())
+                .max(Some(map.key_id.max(map.value_id))),
+        }
+    }
+
+    // Name of a field id nested in this type, relative to this type
+    fn name_by_id(&self, id: i32) -> Option<String> {
+        let nested = |name: &str, nested: &IcebergType| {
+            nested
+                .name_by_id(id)
+                .map(|nested| format!("{}.{}", name, nested))
+        };
+        match self {
+            IcebergType::Primitive(_) => None,
+            IcebergType::Struct(struct_type) => struct_type.name_by_id(id),
+            IcebergType::List(list) if list.element_id == id => Some("element".to_string()),
+            IcebergType::List(list) => nested("element", &list.element),
+            IcebergType::Map(map) if map.key_id == id => Some("key".to_string()),
+            IcebergType::Map(map) if map.value_id == id => Some("value".to_string()),
+            IcebergType::Map(map) => {
+                nested("key", &map.key).or_else(|| nested("value", &map.value))
+            }
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for PrimitiveType {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -207,6 +663,164 @@ where
     }
 }
 
+// How decimal types are written. Both forms are accepted when reading
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum DecimalFormat {
+    // decimal(p, s), as written by the Java implementation
+    #[default]
+    Spaced,
+    // decimal(p,s), as written by Spark and most engines
+    Compact,
+}
+
+impl FromStr for DecimalFormat {
+    type Err = IcebergError;
+
+    fn from_str(s: &str) -> error::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "spaced" => Ok(DecimalFormat::Spaced),
+            "compact" => Ok(DecimalFormat::Compact),
+            _ => Err(IcebergError::Invalid(format!(
+                "Unknown decimal format {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl DecimalFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DecimalFormat::Spaced => "spaced",
+            DecimalFormat::Compact => "compact",
+        }
+    }
+}
+
+// Serializes metadata as JSON with the decimal types of its schemas in the given format, so that
+// metadata can be written byte for byte like the files of another engine. Types are serialized
+// spaced and rewritten as they are written, other strings are left as they are
+pub fn to_json_with_decimal_format<T: Serialize>(
+    value: &T,
+    format: DecimalFormat,
+) -> error::Result<Vec<u8>> {
+    match format {
+        DecimalFormat::Spaced => Ok(serde_json::to_vec(value)?),
+        DecimalFormat::Compact => {
+            let mut data = vec![];
+            let mut serializer =
+                serde_json::Serializer::with_formatter(&mut data, CompactDecimals::default());
+            value.serialize(&mut serializer)?;
+            Ok(data)
+        }
+    }
+}
+
+// Compact JSON formatter writing the string types of schema fields, lists and maps compact
+#[derive(Default)]
+struct CompactDecimals {
+    // Keys of the enclosing object members, None for arrays, the innermost last
+    path: Vec<Option<String>>,
+    // Member key being written
+    key: Option<String>,
+    // Type being written, held back until it is complete
+    type_name: Option<String>,
+}
+
+impl CompactDecimals {
+    // Whether the string being started is the type of a field, list element or map key or value
+    fn at_type(&self) -> bool {
+        let member = |keys: &[&str], key: &Option<String>| {
+            key.as_deref().is_some_and(|key| keys.contains(&key))
+        };
+        self.key.is_none()
+            && self
+                .path
+                .last()
+                .is_some_and(|key| member(&["type", "element", "key", "value"], key))
+            && self.path.iter().any(|key| member(&["fields"], key))
+    }
+}
+
+impl Formatter for CompactDecimals {
+    fn begin_string<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if self.at_type() {
+            self.type_name = Some(String::new());
+        }
+        writer.write_all(b"\"")
+    }
+
+    fn end_string<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if let Some(type_name) = self.type_name.take() {
+            let type_name = match type_name.starts_with("decimal(") {
+                true => type_name.replace(", ", ","),
+                false => type_name,
+            };
+            writer.write_all(type_name.as_bytes())?;
+        }
+        writer.write_all(b"\"")
+    }
+
+    fn write_string_fragment<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        fragment: &str,
+    ) -> io::Result<()> {
+        if let Some(key) = &mut self.key {
+            key.push_str(fragment);
+        }
+        match &mut self.type_name {
+            Some(type_name) => {
+                type_name.push_str(fragment);
+                Ok(())
+            }
+            None => writer.write_all(fragment.as_bytes()),
+        }
+    }
+
+    fn write_char_escape<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        char_escape: CharEscape,
+    ) -> io::Result<()> {
+        // Types have no escaped characters, the string is something else
+        if let Some(held) = self.type_name.take() {
+            writer.write_all(held.as_bytes())?;
+        }
+        // Escaped characters of keys are left out, the keys of interest have none
+        serde_json::ser::CompactFormatter.write_char_escape(writer, char_escape)
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.key = Some(String::new());
+        serde_json::ser::CompactFormatter.begin_object_key(writer, first)
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.path.push(self.key.take());
+        writer.write_all(b":")
+    }
+
+    fn end_object_value<W: ?Sized + io::Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.path.pop();
+        Ok(())
+    }
+
+    fn begin_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.path.push(None);
+        writer.write_all(b"[")
+    }
+
+    fn end_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.path.pop();
+        writer.write_all(b"]")
+    }
+}
+
 impl Serialize for PrimitiveType {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -249,7 +863,7 @@ mod tests {
         let data = [r#""fixed[1]""#, r#""fixed[400]""#];
         let iceberg_types = data.map(|datum| {
             serde_json::from_str::<PrimitiveType>(datum)
-                .expect(&format!("Failed for variant {}", datum))
+                .unwrap_or_else(|_| panic!("Failed for variant {}", datum))
         });
         assert_eq!(
             [PrimitiveType::Fixed(1), PrimitiveType::Fixed(400)],
@@ -262,7 +876,7 @@ mod tests {
         let data = [r#""decimal(1, 20)""#, r#""decimal(38, 2)""#];
         let iceberg_types = data.map(|datum| {
             serde_json::from_str::<PrimitiveType>(datum)
-                .expect(&format!("Failed for variant {}", datum))
+                .unwrap_or_else(|_| panic!("Failed for variant {}", datum))
         });
         assert_eq!(
             [
@@ -296,9 +910,9 @@ mod tests {
 
         for iceberg_type in iceberg_types {
             let ser = serde_json::to_string(&iceberg_type)
-                .expect(&format!("Failed to serialize {:?}", iceberg_type));
+                .unwrap_or_else(|_| panic!("Failed to serialize {:?}", iceberg_type));
             let deser: PrimitiveType =
-                serde_json::from_str(&ser).expect(&format!("Failed to deser {:?}", ser));
+                serde_json::from_str(&ser).unwrap_or_else(|_| panic!("Failed to deser {:?}", ser));
             assert_eq!(iceberg_type, deser);
         }
     }
@@ -318,11 +932,14 @@ mod tests {
             r#""string""#,
             r#""uuid""#,
             r#""binary""#,
+            r#""timestamp_ns""#,
+            r#""timestamptz_ns""#,
+            r#""variant""#,
         ];
 
         let iceberg_types = data.map(|datum| {
             serde_json::from_str::<PrimitiveType>(datum)
-                .expect(&format!("Failed for variant {}", datum))
+                .unwrap_or_else(|_| panic!("Failed for variant {}", datum))
         });
 
         assert_eq!(
@@ -338,7 +955,10 @@ mod tests {
                 PrimitiveType::Timestamptz,
                 PrimitiveType::String,
                 PrimitiveType::Uuid,
-                PrimitiveType::Binary
+                PrimitiveType::Binary,
+                PrimitiveType::TimestampNs,
+                PrimitiveType::TimestamptzNs,
+                PrimitiveType::Variant
             ],
             iceberg_types
         );
@@ -359,13 +979,16 @@ mod tests {
             PrimitiveType::String,
             PrimitiveType::Uuid,
             PrimitiveType::Binary,
+            PrimitiveType::TimestampNs,
+            PrimitiveType::TimestamptzNs,
+            PrimitiveType::Variant,
         ];
 
         for iceberg_type in iceberg_types {
             let ser = serde_json::to_string(&iceberg_type)
-                .expect(&format!("Failed to serialize {:?}", iceberg_type));
+                .unwrap_or_else(|_| panic!("Failed to serialize {:?}", iceberg_type));
             let deser: PrimitiveType =
-                serde_json::from_str(&ser).expect(&format!("Failed to deser {:?}", ser));
+                serde_json::from_str(&ser).unwrap_or_else(|_| panic!("Failed to deser {:?}", ser));
             assert_eq!(iceberg_type, deser);
         }
     }
@@ -751,15 +1374,186 @@ mod tests {
 
         let deser: IcebergSchemaV2 = serde_json::from_str(data).unwrap();
         assert_eq!(
-            IcebergSchemaV2 {
-                schema_id: 123,
-                identifier_field_ids: Some(vec![1, 2, 3]),
-                schema: ib_struct
-            },
+            IcebergSchemaV2::new(123, Some(vec![1, 2, 3]), ib_struct),
             deser
         );
     }
 
+    // id, points: list<struct<x>> and tags: map<string, string>
+    fn nested_schema() -> IcebergSchemaV2 {
+        let primitive = |primitive| Box::new(IcebergType::Primitive(primitive));
+        IcebergSchemaV2::new(
+            3,
+            None,
+            StructType {
+                fields: vec![
+                    StructField::new(1, "id", true, IcebergType::Primitive(PrimitiveType::Long)),
+                    StructField::new(
+                        2,
+                        "points",
+                        false,
+                        IcebergType::List(ListType {
+                            element_id: 3,
+                            element_required: true,
+                            element: Box::new(IcebergType::Struct(StructType {
+                                fields: vec![StructField::new(
+                                    4,
+                                    "x",
+                                    true,
+                                    IcebergType::Primitive(PrimitiveType::Double),
+                                )],
+                            })),
+                        }),
+                    ),
+                    StructField::new(
+                        5,
+                        "tags",
+                        false,
+                        IcebergType::Map(MapType {
+                            key_id: 6,
+                            key: primitive(PrimitiveType::String),
+                            value_id: 7,
+                            value_required: false,
+                            value: primitive(PrimitiveType::String),
+                        }),
+                    ),
+                ],
+            },
+        )
+    }
+
+    #[test]
+    fn test_field_names() {
+        let schema = nested_schema();
+        assert_eq!(Some("id".to_string()), schema.schema.name_by_id(1));
+        assert_eq!(
+            Some("points.element".to_string()),
+            schema.schema.name_by_id(3)
+        );
+        assert_eq!(
+            Some("points.element.x".to_string()),
+            schema.schema.name_by_id(4)
+        );
+        assert_eq!(Some("tags.key".to_string()), schema.schema.name_by_id(6));
+        assert_eq!(Some("tags.value".to_string()), schema.schema.name_by_id(7));
+        assert_eq!(None, schema.schema.name_by_id(8));
+
+        assert_eq!(
+            "column points.element.x (field id 4, schema 3)",
+            schema.describe_field(4)
+        );
+        assert_eq!("column id (field id 1)", schema.schema.describe_field(1));
+        assert_eq!("unknown field id 8", schema.describe_field(8));
+    }
+
+    #[test]
+    fn test_project() {
+        let schema = nested_schema();
+        let ids = |projected: &StructType| {
+            (0..10)
+                .filter(|id| projected.type_by_id(*id).is_some())
+                .collect::<Vec<i32>>()
+        };
+        assert_eq!(schema.schema, schema.project(&[1, 2, 5]));
+        assert!(schema.project(&[]).fields.is_empty());
+        assert!(schema.project(&[42]).fields.is_empty());
+        assert_eq!(vec![1], ids(&schema.project(&[1])));
+        // Selecting a nested field keeps the list and struct it is in
+        let projected = schema.project(&[4]);
+        assert_eq!(vec![2, 3, 4], ids(&projected));
+        assert_eq!("points", projected.fields[0].name);
+        // Selecting the list element selects the whole list, selecting a map value keeps the
+        // key of the map
+        assert_eq!(vec![2, 3, 4], ids(&schema.project(&[3])));
+        assert_eq!(vec![1, 5, 6, 7], ids(&schema.project(&[7, 1])));
+        // Fields keep the order of the schema
+        let projected = schema.project(&[5, 1]);
+        assert_eq!(
+            vec!["id", "tags"],
+            projected
+                .fields
+                .iter()
+                .map(|field| field.name.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_field_lookups() {
+        let schema = nested_schema();
+        for _ in 0..2 {
+            assert_eq!("x", schema.field_by_id(4).unwrap().name);
+            assert_eq!(
+                Some(4),
+                schema.field_by_path("points.element.x").map(|f| f.id)
+            );
+            assert_eq!(Some(3), schema.field_id_by_path("points.element"));
+            assert_eq!(Some(7), schema.field_id_by_path("tags.value"));
+            assert_eq!(Some("points.element.x"), schema.name_by_id(4));
+            assert_eq!(
+                Some(&IcebergType::Primitive(PrimitiveType::String)),
+                schema.type_by_id(6)
+            );
+            // List elements and map keys and values have a type but are not struct fields
+            assert!(schema.type_by_id(3).is_some());
+            assert_eq!(None, schema.field_by_id(3));
+            assert_eq!(None, schema.field_by_path("tags.key"));
+            assert_eq!(None, schema.field_by_path("points.x"));
+            assert_eq!(None, schema.field_by_id(8));
+        }
+
+        let fields = &schema.schema;
+        assert_eq!("x", fields.field_by_id(4).unwrap().name);
+        assert_eq!(None, fields.field_by_id(3));
+        assert_eq!(Some(2), fields.field_by_name("points").map(|f| f.id));
+        assert_eq!(None, fields.field_by_name("x"));
+        assert_eq!(
+            Some(4),
+            fields.field_by_path("points.element.x").map(|f| f.id)
+        );
+        assert_eq!(Some(1), fields.field_by_path("id").map(|f| f.id));
+        assert_eq!(None, fields.field_by_path("points.element"));
+        assert_eq!(None, fields.field_by_path("id.x"));
+        assert_eq!(None, fields.field_by_path(""));
+
+        // Changing a clone in place doesn't affect the original
+        let mut renamed = schema.clone();
+        renamed.schema_mut().fields[0].name = "key".to_string();
+        assert_eq!(Some(1), renamed.field_id_by_path("key"));
+        assert_eq!(Some(1), schema.field_id_by_path("id"));
+        assert_eq!(None, schema.field_id_by_path("key"));
+        assert_ne!(schema, renamed);
+    }
+
+    #[test]
+    fn test_field_lookups_after_changes() {
+        let mut schema = nested_schema();
+        assert_eq!(Some(5), schema.field_id_by_path("tags"));
+        assert_eq!(None, schema.field_by_id(8));
+
+        schema.schema_mut().fields.push(StructField::new(
+            8,
+            "data",
+            false,
+            IcebergType::Primitive(PrimitiveType::String),
+        ));
+        assert_eq!("data", schema.field_by_id(8).unwrap().name);
+        assert_eq!(Some(8), schema.field_id_by_path("data"));
+
+        schema.schema_mut().fields.remove(2);
+        assert_eq!(None, schema.field_id_by_path("tags"));
+        assert_eq!(None, schema.type_by_id(6));
+        // The fields after the removed one moved, lookups find them at their new positions
+        assert_eq!("data", schema.field_by_id(8).unwrap().name);
+        assert_eq!(Some("data"), schema.name_by_id(8));
+
+        // Clones of a schema with a built index are changed independently
+        let mut renamed = schema.clone();
+        renamed.schema_mut().fields[2].name = "data2".to_string();
+        assert_eq!(Some("data2"), renamed.name_by_id(8));
+        assert_eq!(Some("data"), schema.name_by_id(8));
+    }
+
     #[test]
     fn test_iceberg_schema_v2_serde_roundtrip() {
         let ib_struct = StructType {
@@ -843,11 +1637,7 @@ mod tests {
             ],
         };
 
-        let schema = IcebergSchemaV2 {
-            schema_id: 123,
-            identifier_field_ids: Some(vec![1, 2, 3]),
-            schema: ib_struct,
-        };
+        let schema = IcebergSchemaV2::new(123, Some(vec![1, 2, 3]), ib_struct);
 
         let ser = serde_json::to_string(&schema).unwrap();
         let deser: IcebergSchemaV2 = serde_json::from_str(&ser).unwrap();
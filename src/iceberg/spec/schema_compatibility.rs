@@ -0,0 +1,550 @@
+// Classification of the changes between two schemas of a table, so that schema changes can be
+// checked before they are committed. Fields are matched by id, list elements and map keys and
+// values included, following the schema evolution rules of the spec: fields can be added as
+// optional, dropped, renamed, reordered, made optional and have their type promoted. Anything
+// else would make data files written with the old schema unreadable with the new one
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::schema::{IcebergSchemaV2, IcebergType, PrimitiveType, StructType};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SchemaChange {
+    AddedOptionalField {
+        field_id: i32,
+        name: String,
+    },
+    // Compatible only if the field has an initial default, which old data files are read with
+    AddedRequiredField {
+        field_id: i32,
+        name: String,
+        has_initial_default: bool,
+    },
+    DroppedField {
+        field_id: i32,
+        name: String,
+    },
+    RenamedField {
+        field_id: i32,
+        from: String,
+        to: String,
+    },
+    // Fields can only be reordered within their struct
+    MovedField {
+        field_id: i32,
+        from: String,
+        to: String,
+    },
+    // int to long, float to double and decimals to a higher precision with the same scale
+    PromotedType {
+        field_id: i32,
+        name: String,
+        from: PrimitiveType,
+        to: PrimitiveType,
+    },
+    IllegalTypeChange {
+        field_id: i32,
+        name: String,
+        from: String,
+        to: String,
+    },
+    RequiredToOptional {
+        field_id: i32,
+        name: String,
+    },
+    OptionalToRequired {
+        field_id: i32,
+        name: String,
+    },
+}
+
+impl SchemaChange {
+    // Whether data written with the old schema can still be read with the new one
+    pub fn is_compatible(&self) -> bool {
+        match self {
+            SchemaChange::AddedRequiredField {
+                has_initial_default,
+                ..
+            } => *has_initial_default,
+            SchemaChange::MovedField { .. }
+            | SchemaChange::IllegalTypeChange { .. }
+            | SchemaChange::OptionalToRequired { .. } => false,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaChange::AddedOptionalField { field_id, name } => {
+                write!(f, "Added optional field {} ({})", name, field_id)
+            }
+            SchemaChange::AddedRequiredField {
+                field_id,
+                name,
+                has_initial_default: true,
+            } => write!(
+                f,
+                "Added required field {} ({}) with an initial default",
+                name, field_id
+            ),
+            SchemaChange::AddedRequiredField { field_id, name, .. } => write!(
+                f,
+                "Added required field {} ({}) without an initial default",
+                name, field_id
+            ),
+            SchemaChange::DroppedField { field_id, name } => {
+                write!(f, "Dropped field {} ({})", name, field_id)
+            }
+            SchemaChange::RenamedField { field_id, from, to } => {
+                write!(f, "Renamed field {} ({}) to {}", from, field_id, to)
+            }
+            SchemaChange::MovedField { field_id, from, to } => {
+                write!(f, "Moved field {} ({}) to {}", from, field_id, to)
+            }
+            SchemaChange::PromotedType {
+                field_id,
+                name,
+                from,
+                to,
+            } => write!(
+                f,
+                "Promoted field {} ({}) from {} to {}",
+                name,
+                field_id,
+                describe_primitive(from),
+                describe_primitive(to)
+            ),
+            SchemaChange::IllegalTypeChange {
+                field_id,
+                name,
+                from,
+                to,
+            } => write!(
+                f,
+                "Changed type of field {} ({}) from {} to {}",
+                name, field_id, from, to
+            ),
+            SchemaChange::RequiredToOptional { field_id, name } => {
+                write!(f, "Made field {} ({}) optional", name, field_id)
+            }
+            SchemaChange::OptionalToRequired { field_id, name } => {
+                write!(f, "Made field {} ({}) required", name, field_id)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SchemaCompatibility {
+    // Ordered by field id
+    pub changes: Vec<SchemaChange>,
+}
+
+impl SchemaCompatibility {
+    pub fn is_compatible(&self) -> bool {
+        self.changes.iter().all(SchemaChange::is_compatible)
+    }
+
+    pub fn incompatible_changes(&self) -> impl Iterator<Item = &SchemaChange> {
+        self.changes.iter().filter(|change| !change.is_compatible())
+    }
+}
+
+// Changes from the old schema to the new one. The type of a field that changed kind, e.g. from
+// struct to list, is reported as a single illegal change rather than as changes of its fields
+pub fn check_compatibility(old: &IcebergSchemaV2, new: &IcebergSchemaV2) -> SchemaCompatibility {
+    let old_fields = index(old.schema());
+    let new_fields = index(new.schema());
+    let mut changes = vec![];
+    for (field_id, old_field) in &old_fields {
+        let Some(new_field) = new_fields.get(field_id) else {
+            if !replaced_with_parent(old_field, &old_fields, &new_fields) {
+                changes.push(SchemaChange::DroppedField {
+                    field_id: *field_id,
+                    name: old_field.path.clone(),
+                });
+            }
+            continue;
+        };
+        let field_id = *field_id;
+        if old_field.parent != new_field.parent {
+            changes.push(SchemaChange::MovedField {
+                field_id,
+                from: old_field.path.clone(),
+                to: new_field.path.clone(),
+            });
+        } else if old_field.name != new_field.name {
+            changes.push(SchemaChange::RenamedField {
+                field_id,
+                from: old_field.path.clone(),
+                to: new_field.path.clone(),
+            });
+        }
+        let name = new_field.path.clone();
+        match (old_field.field_type, new_field.field_type) {
+            (IcebergType::Primitive(from), IcebergType::Primitive(to)) if from != to => {
+                if can_promote(from, to) {
+                    changes.push(SchemaChange::PromotedType {
+                        field_id,
+                        name: name.clone(),
+                        from: from.clone(),
+                        to: to.clone(),
+                    });
+                } else {
+                    changes.push(SchemaChange::IllegalTypeChange {
+                        field_id,
+                        name: name.clone(),
+                        from: describe(old_field.field_type),
+                        to: describe(new_field.field_type),
+                    });
+                }
+            }
+            (from, to) if kind(from) != kind(to) => {
+                changes.push(SchemaChange::IllegalTypeChange {
+                    field_id,
+                    name: name.clone(),
+                    from: describe(from),
+                    to: describe(to),
+                });
+            }
+            _ => {}
+        }
+        match (old_field.required, new_field.required) {
+            (true, false) => changes.push(SchemaChange::RequiredToOptional { field_id, name }),
+            (false, true) => changes.push(SchemaChange::OptionalToRequired { field_id, name }),
+            _ => {}
+        }
+    }
+    for (field_id, new_field) in &new_fields {
+        if old_fields.contains_key(field_id) {
+            continue;
+        }
+        if replaced_with_parent(new_field, &new_fields, &old_fields) {
+            continue;
+        }
+        let name = new_field.path.clone();
+        changes.push(if new_field.required {
+            SchemaChange::AddedRequiredField {
+                field_id: *field_id,
+                name,
+                has_initial_default: new_field.has_initial_default,
+            }
+        } else {
+            SchemaChange::AddedOptionalField {
+                field_id: *field_id,
+                name,
+            }
+        });
+    }
+    changes.sort_by_key(field_id);
+    SchemaCompatibility { changes }
+}
+
+// Fields nested in a field that was added or dropped, or that changed kind, are part of the
+// change of that field
+fn replaced_with_parent(
+    field: &IndexedField,
+    fields: &BTreeMap<i32, IndexedField>,
+    other_fields: &BTreeMap<i32, IndexedField>,
+) -> bool {
+    field.parent.is_some_and(|parent| {
+        other_fields.get(&parent).is_none_or(|other| {
+            kind(other.field_type) != kind(fields[&parent].field_type)
+                || replaced_with_parent(other, other_fields, fields)
+        })
+    })
+}
+
+fn field_id(change: &SchemaChange) -> i32 {
+    match change {
+        SchemaChange::AddedOptionalField { field_id, .. }
+        | SchemaChange::AddedRequiredField { field_id, .. }
+        | SchemaChange::DroppedField { field_id, .. }
+        | SchemaChange::RenamedField { field_id, .. }
+        | SchemaChange::MovedField { field_id, .. }
+        | SchemaChange::PromotedType { field_id, .. }
+        | SchemaChange::IllegalTypeChange { field_id, .. }
+        | SchemaChange::RequiredToOptional { field_id, .. }
+        | SchemaChange::OptionalToRequired { field_id, .. } => *field_id,
+    }
+}
+
+fn can_promote(from: &PrimitiveType, to: &PrimitiveType) -> bool {
+    match (from, to) {
+        (PrimitiveType::Int, PrimitiveType::Long) => true,
+        (PrimitiveType::Float, PrimitiveType::Double) => true,
+        (
+            PrimitiveType::Decimal { precision, scale },
+            PrimitiveType::Decimal {
+                precision: to_precision,
+                scale: to_scale,
+            },
+        ) => scale == to_scale && precision <= to_precision,
+        _ => false,
+    }
+}
+
+// A field, list element or map key or value, with the id of the field it is nested in
+struct IndexedField<'a> {
+    parent: Option<i32>,
+    name: &'a str,
+    // Dotted name from the root of the schema
+    path: String,
+    required: bool,
+    has_initial_default: bool,
+    field_type: &'a IcebergType,
+}
+
+fn index(schema: &StructType) -> BTreeMap<i32, IndexedField<'_>> {
+    let mut fields = BTreeMap::new();
+    index_struct(schema, None, "", &mut fields);
+    fields
+}
+
+fn index_struct<'a>(
+    struct_type: &'a StructType,
+    parent: Option<i32>,
+    prefix: &str,
+    fields: &mut BTreeMap<i32, IndexedField<'a>>,
+) {
+    for field in &struct_type.fields {
+        let path = format!("{}{}", prefix, field.name);
+        index_type(&field.field_type, field.id, &path, fields);
+        fields.insert(
+            field.id,
+            IndexedField {
+                parent,
+                name: &field.name,
+                path,
+                required: field.required,
+                has_initial_default: field.initial_default.is_some(),
+                field_type: &field.field_type,
+            },
+        );
+    }
+}
+
+fn index_type<'a>(
+    field_type: &'a IcebergType,
+    id: i32,
+    path: &str,
+    fields: &mut BTreeMap<i32, IndexedField<'a>>,
+) {
+    let mut nested = |nested_id: i32, name: &'a str, required: bool, nested: &'a IcebergType| {
+        let path = format!("{}.{}", path, name);
+        index_type(nested, nested_id, &path, fields);
+        fields.insert(
+            nested_id,
+            IndexedField {
+                parent: Some(id),
+                name,
+                path,
+                required,
+                has_initial_default: false,
+                field_type: nested,
+            },
+        );
+    };
+    match field_type {
+        IcebergType::Primitive(_) => {}
+        IcebergType::Struct(struct_type) => {
+            index_struct(struct_type, Some(id), &format!("{}.", path), fields)
+        }
+        IcebergType::List(list) => nested(
+            list.element_id,
+            "element",
+            list.element_required,
+            &list.element,
+        ),
+        IcebergType::Map(map) => {
+            nested(map.key_id, "key", true, &map.key);
+            nested(map.value_id, "value", map.value_required, &map.value);
+        }
+    }
+}
+
+fn kind(field_type: &IcebergType) -> &'static str {
+    match field_type {
+        IcebergType::Primitive(_) => "primitive",
+        IcebergType::Struct(_) => "struct",
+        IcebergType::List(_) => "list",
+        IcebergType::Map(_) => "map",
+    }
+}
+
+fn describe(field_type: &IcebergType) -> String {
+    match field_type {
+        IcebergType::Primitive(primitive) => describe_primitive(primitive),
+        nested => kind(nested).to_string(),
+    }
+}
+
+fn describe_primitive(primitive: &PrimitiveType) -> String {
+    match serde_json::to_value(primitive) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => format!("{:?}", primitive),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::spec::schema::{ListType, StructField};
+
+    fn schema(fields: Vec<StructField>) -> IcebergSchemaV2 {
+        IcebergSchemaV2::new(0, None, StructType { fields })
+    }
+
+    fn primitive(primitive: PrimitiveType) -> IcebergType {
+        IcebergType::Primitive(primitive)
+    }
+
+    fn location(fields: Vec<StructField>) -> StructField {
+        StructField::new(
+            3,
+            "location",
+            false,
+            IcebergType::Struct(StructType { fields }),
+        )
+    }
+
+    #[test]
+    fn test_check_compatibility() {
+        let old = schema(vec![
+            StructField::new(1, "id", true, primitive(PrimitiveType::Int)),
+            StructField::new(2, "data", false, primitive(PrimitiveType::String)),
+            location(vec![
+                StructField::new(4, "lat", false, primitive(PrimitiveType::Float)),
+                StructField::new(5, "long", false, primitive(PrimitiveType::Float)),
+            ]),
+            StructField::new(
+                6,
+                "tags",
+                false,
+                IcebergType::List(ListType {
+                    element_id: 7,
+                    element_required: true,
+                    element: Box::new(primitive(PrimitiveType::String)),
+                }),
+            ),
+        ]);
+        assert!(check_compatibility(&old, &old).changes.is_empty());
+
+        let mut required_with_default =
+            StructField::new(10, "version", true, primitive(PrimitiveType::Int));
+        required_with_default.initial_default = Some("1".to_string());
+        let new = schema(vec![
+            StructField::new(1, "id", false, primitive(PrimitiveType::Long)),
+            StructField::new(2, "payload", false, primitive(PrimitiveType::String)),
+            location(vec![
+                StructField::new(4, "latitude", false, primitive(PrimitiveType::Double)),
+                StructField::new(8, "altitude", false, primitive(PrimitiveType::Float)),
+            ]),
+            StructField::new(9, "created", false, primitive(PrimitiveType::Timestamp)),
+            required_with_default,
+        ]);
+        let compatibility = check_compatibility(&old, &new);
+        assert!(compatibility.is_compatible());
+        assert_eq!(
+            vec![
+                SchemaChange::PromotedType {
+                    field_id: 1,
+                    name: "id".to_string(),
+                    from: PrimitiveType::Int,
+                    to: PrimitiveType::Long
+                },
+                SchemaChange::RequiredToOptional {
+                    field_id: 1,
+                    name: "id".to_string()
+                },
+                SchemaChange::RenamedField {
+                    field_id: 2,
+                    from: "data".to_string(),
+                    to: "payload".to_string()
+                },
+                SchemaChange::RenamedField {
+                    field_id: 4,
+                    from: "location.lat".to_string(),
+                    to: "location.latitude".to_string()
+                },
+                SchemaChange::PromotedType {
+                    field_id: 4,
+                    name: "location.latitude".to_string(),
+                    from: PrimitiveType::Float,
+                    to: PrimitiveType::Double
+                },
+                SchemaChange::DroppedField {
+                    field_id: 5,
+                    name: "location.long".to_string()
+                },
+                // The element of the dropped list is dropped with it
+                SchemaChange::DroppedField {
+                    field_id: 6,
+                    name: "tags".to_string()
+                },
+                SchemaChange::AddedOptionalField {
+                    field_id: 8,
+                    name: "location.altitude".to_string()
+                },
+                SchemaChange::AddedOptionalField {
+                    field_id: 9,
+                    name: "created".to_string()
+                },
+                SchemaChange::AddedRequiredField {
+                    field_id: 10,
+                    name: "version".to_string(),
+                    has_initial_default: true
+                },
+            ],
+            compatibility.changes
+        );
+        assert_eq!(
+            "Promoted field id (1) from int to long",
+            compatibility.changes[0].to_string()
+        );
+
+        let new = schema(vec![
+            StructField::new(1, "id", true, primitive(PrimitiveType::String)),
+            StructField::new(2, "data", true, primitive(PrimitiveType::String)),
+            location(vec![StructField::new(
+                5,
+                "long",
+                false,
+                primitive(PrimitiveType::Float),
+            )]),
+            StructField::new(4, "lat", false, primitive(PrimitiveType::Float)),
+            StructField::new(6, "tags", false, primitive(PrimitiveType::String)),
+            StructField::new(10, "version", true, primitive(PrimitiveType::Int)),
+        ]);
+        let compatibility = check_compatibility(&old, &new);
+        assert!(!compatibility.is_compatible());
+        let incompatible: Vec<String> = compatibility
+            .incompatible_changes()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            vec![
+                "Changed type of field id (1) from int to string",
+                "Made field data (2) required",
+                "Moved field location.lat (4) to lat",
+                "Changed type of field tags (6) from list to string",
+                "Added required field version (10) without an initial default",
+            ],
+            incompatible
+        );
+        // The list element replaced by a string isn't reported on its own
+        assert_eq!(5, compatibility.changes.len());
+    }
+
+    #[test]
+    fn test_decimal_promotion() {
+        let decimal = |precision, scale| PrimitiveType::Decimal { precision, scale };
+        assert!(can_promote(&decimal(9, 2), &decimal(18, 2)));
+        assert!(!can_promote(&decimal(18, 2), &decimal(9, 2)));
+        assert!(!can_promote(&decimal(9, 2), &decimal(18, 3)));
+        assert!(!can_promote(&PrimitiveType::Long, &PrimitiveType::Int));
+        assert!(!can_promote(&PrimitiveType::Int, &PrimitiveType::Double));
+    }
+}
@@ -2,27 +2,40 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct SnapshotV2 {
     pub snapshot_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_snapshot_id: Option<i64>,
     pub sequence_number: i64,
     pub timestamp_ms: i64,
     pub summary: Summary,
     pub manifest_list: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub schema_id: Option<i32>,
+    // Row lineage (V3): id of the first row added by the snapshot and the number of rows it
+    // assigned ids to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_row_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added_rows: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case", remote = "Self")]
 pub struct SnapshotV1 {
     pub snapshot_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_snapshot_id: Option<i64>,
     pub timestamp_ms: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub manifest_list: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub manifests: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub summary: Option<Summary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub schema_id: Option<i64>,
 }
 
@@ -51,7 +64,7 @@ impl Serialize for SnapshotV1 {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub struct Summary {
     pub operation: Operation,
@@ -59,7 +72,7 @@ pub struct Summary {
     pub rest: HashMap<String, String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Operation {
     Append,
@@ -68,21 +81,42 @@ pub enum Operation {
     Delete,
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+impl Operation {
+    // Name written to snapshot summaries
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Append => "append",
+            Operation::Replace => "replace",
+            Operation::Overwrite => "overwrite",
+            Operation::Delete => "delete",
+        }
+    }
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct SnapshotRefV2 {
     pub snapshot_id: i64,
     #[serde(flatten)]
     pub ref_type: RefType,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_ref_age_ms: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "lowercase", tag = "type")]
 pub enum RefType {
     #[serde(rename_all = "kebab-case")]
     Branch {
+        #[serde(skip_serializing_if = "Option::is_none")]
         min_snapshots_to_keep: Option<i32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
         max_snapshot_age_ms: Option<i64>,
     },
     Tag,
@@ -121,11 +155,37 @@ mod tests {
                 },
                 manifest_list: "s3://b/wh/.../s1.avro".to_string(),
                 schema_id: Some(0),
+                first_row_id: None,
+                added_rows: None,
             },
             deser
         );
     }
 
+    #[test]
+    fn test_snapshot_row_lineage() {
+        let data = r#"
+        {
+          "snapshot-id": 2,
+          "sequence-number": 2,
+          "timestamp-ms": 1515100955770,
+          "summary": {"operation": "append"},
+          "manifest-list": "s3://b/wh/.../s2.avro",
+          "first-row-id": 100,
+          "added-rows": 25
+        }
+        "#;
+
+        let snapshot: SnapshotV2 = serde_json::from_str(data).unwrap();
+        assert_eq!(
+            (Some(100), Some(25)),
+            (snapshot.first_row_id, snapshot.added_rows)
+        );
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(100, json["first-row-id"]);
+        assert_eq!(snapshot, serde_json::from_value(json).unwrap());
+    }
+
     #[test]
     fn test_snapshot_v1() {
         let data = r#"
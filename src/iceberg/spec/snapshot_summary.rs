@@ -0,0 +1,274 @@
+// Metrics of the summary of a snapshot, under the property names engines read: the counts of
+// the files, records and bytes added and removed by the snapshot, the totals of the table once
+// it is committed and the number of partitions it changed. As in other implementations, counts
+// that are zero are left out and totals are only carried over from parent summaries that have
+// them
+use std::collections::{BTreeSet, HashMap};
+
+use super::manifest::{DataContentType, DataFile};
+use super::partition_spec::PartitionSpec;
+use super::snapshot::{Operation, Summary};
+
+pub const ADDED_DATA_FILES: &str = "added-data-files";
+pub const DELETED_DATA_FILES: &str = "deleted-data-files";
+pub const TOTAL_DATA_FILES: &str = "total-data-files";
+pub const ADDED_DELETE_FILES: &str = "added-delete-files";
+pub const ADDED_POSITION_DELETE_FILES: &str = "added-position-delete-files";
+pub const ADDED_EQUALITY_DELETE_FILES: &str = "added-equality-delete-files";
+pub const REMOVED_DELETE_FILES: &str = "removed-delete-files";
+pub const REMOVED_POSITION_DELETE_FILES: &str = "removed-position-delete-files";
+pub const REMOVED_EQUALITY_DELETE_FILES: &str = "removed-equality-delete-files";
+pub const TOTAL_DELETE_FILES: &str = "total-delete-files";
+pub const ADDED_RECORDS: &str = "added-records";
+pub const DELETED_RECORDS: &str = "deleted-records";
+pub const TOTAL_RECORDS: &str = "total-records";
+pub const ADDED_FILES_SIZE: &str = "added-files-size";
+pub const REMOVED_FILES_SIZE: &str = "removed-files-size";
+pub const TOTAL_FILES_SIZE: &str = "total-files-size";
+pub const ADDED_POSITION_DELETES: &str = "added-position-deletes";
+pub const REMOVED_POSITION_DELETES: &str = "removed-position-deletes";
+pub const TOTAL_POSITION_DELETES: &str = "total-position-deletes";
+pub const ADDED_EQUALITY_DELETES: &str = "added-equality-deletes";
+pub const REMOVED_EQUALITY_DELETES: &str = "removed-equality-deletes";
+pub const TOTAL_EQUALITY_DELETES: &str = "total-equality-deletes";
+pub const CHANGED_PARTITION_COUNT: &str = "changed-partition-count";
+// Write-audit-publish: id of the write of a staged snapshot, and of the staged write a
+// cherry-picked snapshot published
+pub const WAP_ID: &str = "wap.id";
+pub const PUBLISHED_WAP_ID: &str = "published-wap-id";
+pub const SOURCE_SNAPSHOT_ID: &str = "source-snapshot-id";
+
+#[derive(Debug, Clone, Default)]
+pub struct SummaryBuilder {
+    properties: HashMap<String, String>,
+    added: FileMetrics,
+    removed: FileMetrics,
+    // Spec ids and paths of the partitions of the added and removed files
+    changed_partitions: BTreeSet<(i32, String)>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct FileMetrics {
+    data_files: i64,
+    position_delete_files: i64,
+    equality_delete_files: i64,
+    records: i64,
+    files_size: i64,
+    position_deletes: i64,
+    equality_deletes: i64,
+}
+
+impl FileMetrics {
+    fn add(&mut self, file: &DataFile) {
+        self.files_size += file.file_size_in_bytes;
+        match file.content {
+            DataContentType::Data => {
+                self.data_files += 1;
+                self.records += file.record_count;
+            }
+            DataContentType::PositionDeletes => {
+                self.position_delete_files += 1;
+                self.position_deletes += file.record_count;
+            }
+            DataContentType::EqualityDeletes => {
+                self.equality_delete_files += 1;
+                self.equality_deletes += file.record_count;
+            }
+        }
+    }
+
+    fn delete_files(&self) -> i64 {
+        self.position_delete_files + self.equality_delete_files
+    }
+}
+
+impl SummaryBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Property of the summary other than its metrics, e.g. the id of the job that wrote the
+    // snapshot. Metrics take precedence over properties with the same name
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.properties.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn set_all(&mut self, properties: HashMap<String, String>) {
+        self.properties.extend(properties);
+    }
+
+    // Data or delete file added by the snapshot, written with the given spec
+    pub fn added_file(&mut self, spec: &PartitionSpec, file: &DataFile) {
+        self.added.add(file);
+        self.changed_partition(spec, file);
+    }
+
+    pub fn removed_file(&mut self, spec: &PartitionSpec, file: &DataFile) {
+        self.removed.add(file);
+        self.changed_partition(spec, file);
+    }
+
+    fn changed_partition(&mut self, spec: &PartitionSpec, file: &DataFile) {
+        self.changed_partitions
+            .insert((spec.spec_id, spec.partition_path(&file.partition)));
+    }
+
+    // Summary of a snapshot on top of a snapshot with the given summary, None for the first
+    // snapshot of a table
+    pub fn build(&self, operation: Operation, parent: Option<&Summary>) -> Summary {
+        let mut rest = self.properties.clone();
+        let (added, removed) = (&self.added, &self.removed);
+        for (key, value) in [
+            (ADDED_DATA_FILES, added.data_files),
+            (DELETED_DATA_FILES, removed.data_files),
+            (ADDED_DELETE_FILES, added.delete_files()),
+            (ADDED_POSITION_DELETE_FILES, added.position_delete_files),
+            (ADDED_EQUALITY_DELETE_FILES, added.equality_delete_files),
+            (REMOVED_DELETE_FILES, removed.delete_files()),
+            (REMOVED_POSITION_DELETE_FILES, removed.position_delete_files),
+            (REMOVED_EQUALITY_DELETE_FILES, removed.equality_delete_files),
+            (ADDED_RECORDS, added.records),
+            (DELETED_RECORDS, removed.records),
+            (ADDED_FILES_SIZE, added.files_size),
+            (REMOVED_FILES_SIZE, removed.files_size),
+            (ADDED_POSITION_DELETES, added.position_deletes),
+            (REMOVED_POSITION_DELETES, removed.position_deletes),
+            (ADDED_EQUALITY_DELETES, added.equality_deletes),
+            (REMOVED_EQUALITY_DELETES, removed.equality_deletes),
+        ] {
+            if value > 0 {
+                rest.insert(key.to_string(), value.to_string());
+            }
+        }
+        if !self.changed_partitions.is_empty() {
+            rest.insert(
+                CHANGED_PARTITION_COUNT.to_string(),
+                self.changed_partitions.len().to_string(),
+            );
+        }
+        for (key, change) in [
+            (TOTAL_DATA_FILES, added.data_files - removed.data_files),
+            (
+                TOTAL_DELETE_FILES,
+                added.delete_files() - removed.delete_files(),
+            ),
+            (TOTAL_RECORDS, added.records - removed.records),
+            (TOTAL_FILES_SIZE, added.files_size - removed.files_size),
+            (
+                TOTAL_POSITION_DELETES,
+                added.position_deletes - removed.position_deletes,
+            ),
+            (
+                TOTAL_EQUALITY_DELETES,
+                added.equality_deletes - removed.equality_deletes,
+            ),
+        ] {
+            let previous = match parent {
+                None => Some(0),
+                Some(parent) => parent
+                    .rest
+                    .get(key)
+                    .and_then(|value| value.parse::<i64>().ok()),
+            };
+            if let Some(previous) = previous {
+                rest.insert(key.to_string(), (previous + change).to_string());
+            }
+        }
+        Summary { operation, rest }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::spec::manifest::FileFormat;
+    use crate::iceberg::spec::partition_spec::{PartitionField, Transform};
+    use crate::iceberg::spec::values::Literal;
+
+    fn file(content: DataContentType, partition: i32, records: i64, size: i64) -> DataFile {
+        DataFile {
+            content,
+            file_path: format!("{}-{}.parquet", partition, records),
+            file_format: FileFormat::Parquet,
+            partition: vec![Some(Literal::Int(partition))],
+            record_count: records,
+            file_size_in_bytes: size,
+            column_sizes: None,
+            value_counts: None,
+            null_value_counts: None,
+            nan_value_counts: None,
+            lower_bounds: None,
+            upper_bounds: None,
+            key_metadata: None,
+            split_offsets: None,
+            equality_ids: None,
+            sort_order_id: None,
+            referenced_data_file: None,
+            content_offset: None,
+            content_size_in_bytes: None,
+        }
+    }
+
+    #[test]
+    fn test_summary_builder() {
+        let spec = PartitionSpec {
+            spec_id: 0,
+            fields: vec![PartitionField {
+                source_id: 1,
+                source_ids: vec![],
+                field_id: 1000,
+                name: "id".to_string(),
+                transform: Transform::Identity,
+            }],
+        };
+        let mut builder = SummaryBuilder::new();
+        builder.set("app", "test");
+        builder.set(ADDED_RECORDS, "overridden");
+        builder.added_file(&spec, &file(DataContentType::Data, 1, 10, 100));
+        builder.added_file(&spec, &file(DataContentType::Data, 1, 5, 50));
+        builder.added_file(&spec, &file(DataContentType::PositionDeletes, 2, 3, 30));
+        builder.removed_file(&spec, &file(DataContentType::Data, 3, 4, 40));
+        let first = builder.build(Operation::Overwrite, None);
+        let expected: HashMap<String, String> = [
+            ("app", "test"),
+            (ADDED_DATA_FILES, "2"),
+            (DELETED_DATA_FILES, "1"),
+            (ADDED_DELETE_FILES, "1"),
+            (ADDED_POSITION_DELETE_FILES, "1"),
+            (ADDED_RECORDS, "15"),
+            (DELETED_RECORDS, "4"),
+            (ADDED_FILES_SIZE, "180"),
+            (REMOVED_FILES_SIZE, "40"),
+            (ADDED_POSITION_DELETES, "3"),
+            (CHANGED_PARTITION_COUNT, "3"),
+            (TOTAL_DATA_FILES, "1"),
+            (TOTAL_DELETE_FILES, "1"),
+            (TOTAL_RECORDS, "11"),
+            (TOTAL_FILES_SIZE, "140"),
+            (TOTAL_POSITION_DELETES, "3"),
+            (TOTAL_EQUALITY_DELETES, "0"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+        assert_eq!(expected, first.rest);
+
+        let mut builder = SummaryBuilder::new();
+        builder.added_file(&spec, &file(DataContentType::Data, 1, 2, 20));
+        let second = builder.build(Operation::Append, Some(&first));
+        assert_eq!("1", second.rest[ADDED_DATA_FILES]);
+        assert_eq!("13", second.rest[TOTAL_RECORDS]);
+        assert_eq!("2", second.rest[TOTAL_DATA_FILES]);
+        assert_eq!("1", second.rest[CHANGED_PARTITION_COUNT]);
+        assert!(!second.rest.contains_key(DELETED_DATA_FILES));
+
+        // Totals missing from the parent can't be carried over
+        let parent = Summary {
+            operation: Operation::Append,
+            rest: HashMap::from([(TOTAL_RECORDS.to_string(), "100".to_string())]),
+        };
+        let third = builder.build(Operation::Append, Some(&parent));
+        assert_eq!("102", third.rest[TOTAL_RECORDS]);
+        assert!(!third.rest.contains_key(TOTAL_DATA_FILES));
+    }
+}
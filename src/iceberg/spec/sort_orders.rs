@@ -1,46 +1,133 @@
 use serde::{Deserialize, Serialize};
 
-use super::partition_spec::Transform;
+use super::partition_spec::{first_source_id, Transform};
+use super::schema::StructType;
+use crate::iceberg::error::{IcebergError, Result};
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+// Id of the unsorted order, which has no fields
+pub const UNSORTED_ORDER_ID: i32 = 0;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct SortOrders {
     pub order_id: i32,
     pub fields: Vec<SortField>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
-#[serde(rename_all = "kebab-case")]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case", try_from = "SortFieldJson")]
 pub struct SortField {
     pub transform: Transform,
+    // First source column of the transform
     pub source_id: i32,
+    // All source columns of multi-argument transforms (V3), empty for transforms of one column
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub source_ids: Vec<i32>,
     pub direction: Direction,
     pub null_order: NullOrder,
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct SortFieldJson {
+    transform: Transform,
+    source_id: Option<i32>,
+    #[serde(default)]
+    source_ids: Vec<i32>,
+    direction: Direction,
+    null_order: NullOrder,
+}
+
+impl TryFrom<SortFieldJson> for SortField {
+    type Error = String;
+
+    fn try_from(field: SortFieldJson) -> std::result::Result<Self, Self::Error> {
+        let name = format!("sorting by {:?}", field.transform);
+        Ok(SortField {
+            source_id: first_source_id(field.source_id, &field.source_ids, &name)?,
+            transform: field.transform,
+            source_ids: field.source_ids,
+            direction: field.direction,
+            null_order: field.null_order,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Direction {
     Asc,
     Desc,
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub enum NullOrder {
     NullsFirst,
     NullsLast,
 }
 
+impl SortOrders {
+    pub fn unsorted() -> Self {
+        SortOrders {
+            order_id: UNSORTED_ORDER_ID,
+            fields: vec![],
+        }
+    }
+
+    pub fn is_unsorted(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    // Checks that the order sorts primitive columns of the schema with transforms that apply to
+    // them and preserve their order, and that only the unsorted order has id 0
+    pub fn validate(&self, schema: &StructType) -> Result<()> {
+        if self.is_unsorted() != (self.order_id == UNSORTED_ORDER_ID) {
+            return Err(IcebergError::Invalid(format!(
+                "Sort order {} must have fields unless its id is {}",
+                self.order_id, UNSORTED_ORDER_ID
+            )));
+        }
+        for field in &self.fields {
+            let source = schema
+                .primitive_type_by_id(field.source_id)
+                .ok_or_else(|| {
+                    IcebergError::Invalid(format!(
+                    "Sort order {} sorts field {}, which is not a primitive column of the schema",
+                    self.order_id, field.source_id
+                ))
+                })?;
+            let column = schema
+                .name_by_id(field.source_id)
+                .unwrap_or_else(|| field.source_id.to_string());
+            if !field.transform.can_transform(source) {
+                return Err(IcebergError::Invalid(format!(
+                    "Sort order {} applies {:?} to column {} of type {:?}",
+                    self.order_id, field.transform, column, source
+                )));
+            }
+            if !field.transform.preserves_order() {
+                return Err(IcebergError::Invalid(format!(
+                    "Sort order {} sorts column {} by {:?}, which doesn't preserve its order",
+                    self.order_id, column, field.transform
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::iceberg::spec::schema::{IcebergType, PrimitiveType, StructField};
 
     #[test]
     fn test_direction() {
         let ser = [r#""asc""#, r#""desc""#];
         let directions = ser.map(|ser| {
-            serde_json::from_str::<Direction>(ser).expect(&format!("Failed for input {}", ser))
+            serde_json::from_str::<Direction>(ser)
+                .unwrap_or_else(|_| panic!("Failed for input {}", ser))
         });
         assert_eq!([Direction::Asc, Direction::Desc], directions);
     }
@@ -56,7 +143,8 @@ mod tests {
     fn test_null_order() {
         let ser = [r#""nulls-last""#, r#""nulls-first""#];
         let null_orders = ser.map(|ser| {
-            serde_json::from_str::<NullOrder>(ser).expect(&format!("Failed for input {}", ser))
+            serde_json::from_str::<NullOrder>(ser)
+                .unwrap_or_else(|_| panic!("Failed for input {}", ser))
         });
         assert_eq!([NullOrder::NullsLast, NullOrder::NullsFirst], null_orders);
     }
@@ -67,4 +155,56 @@ mod tests {
         let null_order = serde_json::from_str::<NullOrder>(ser);
         assert!(null_order.is_err());
     }
+
+    #[test]
+    fn test_validate() {
+        let schema = StructType {
+            fields: vec![
+                StructField::new(1, "id", true, IcebergType::Primitive(PrimitiveType::Long)),
+                StructField::new(
+                    2,
+                    "ts",
+                    false,
+                    IcebergType::Primitive(PrimitiveType::Timestamp),
+                ),
+                StructField::new(
+                    3,
+                    "ok",
+                    false,
+                    IcebergType::Primitive(PrimitiveType::Boolean),
+                ),
+            ],
+        };
+        let order = |order_id, fields: &[(i32, Transform)]| SortOrders {
+            order_id,
+            fields: fields
+                .iter()
+                .map(|(source_id, transform)| SortField {
+                    transform: transform.clone(),
+                    source_id: *source_id,
+                    source_ids: vec![],
+                    direction: Direction::Asc,
+                    null_order: NullOrder::NullsFirst,
+                })
+                .collect(),
+        };
+        assert!(SortOrders::unsorted().validate(&schema).is_ok());
+        assert!(
+            order(1, &[(1, Transform::Truncate(10)), (2, Transform::Day)])
+                .validate(&schema)
+                .is_ok()
+        );
+
+        let invalid = [
+            order(1, &[]),
+            order(0, &[(1, Transform::Identity)]),
+            order(1, &[(4, Transform::Identity)]),
+            order(1, &[(1, Transform::Day)]),
+            order(1, &[(3, Transform::Truncate(2))]),
+            order(1, &[(1, Transform::Bucket(16))]),
+        ];
+        for order in invalid {
+            assert!(order.validate(&schema).is_err(), "{:?}", order);
+        }
+    }
 }
@@ -1,15 +1,23 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::value::RawValue;
 use serde_json::Value;
 use uuid::Uuid;
 
+use super::name_mapping::{NameMapping, DEFAULT_NAME_MAPPING_PROPERTY};
 use super::partition_spec::{PartitionField, PartitionSpec};
-use super::schema::{IcebergSchemaV1, IcebergSchemaV2};
-use super::snapshot::{SnapshotRefV2, SnapshotV1, SnapshotV2};
+use super::schema::{FieldNames, IcebergSchemaV1, IcebergSchemaV2, StructType};
+use super::snapshot::{Operation, SnapshotRefV2, SnapshotV1, SnapshotV2, Summary};
 use super::sort_orders::SortOrders;
+use crate::iceberg::error::{self, IcebergError};
 
-#[derive(Debug, Eq, PartialEq)]
+// Name of the branch that tracks the current snapshot of a table
+pub const MAIN_BRANCH: &str = "main";
+
+#[derive(Debug, Clone, Eq, PartialEq)]
 // Write custom serializer and deserializer for TableMetadata to
 // delegate to TableMetadataV2 (and other versions in future). Ideally
 // We'd not have to do this and instead can utilize tag and rename attributes
@@ -19,10 +27,12 @@ use super::sort_orders::SortOrders;
 pub enum TableMetadata {
     V1(TableMetadataV1),
     V2(TableMetadataV2),
+    #[cfg(feature = "format-v3")]
+    V3(TableMetadataV3),
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
-#[serde(rename_all = "kebab-case", tag = "format-version")]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
 pub struct TableMetadataV2 {
     pub format_version: i32,
     pub table_uuid: Uuid,
@@ -35,60 +45,568 @@ pub struct TableMetadataV2 {
     pub partition_specs: Vec<PartitionSpec>,
     pub default_spec_id: i32,
     pub last_partition_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub properties: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub current_snapshot_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub snapshots: Option<Vec<SnapshotV2>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub snapshot_log: Option<Vec<SnapshotLog>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata_log: Option<Vec<MetadataLog>>,
     pub sort_orders: Vec<SortOrders>,
     pub default_sort_order_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub refs: Option<HashMap<String, SnapshotRefV2>>,
-    pub statistics: Option<Statistics>, // Unused: See documentation in Statistics structure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub statistics: Option<Vec<StatisticsFile>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partition_statistics: Option<Vec<PartitionStatisticsFile>>,
+}
+
+// Format version 3 metadata: the fields of version 2 along with the ones added by version 3.
+// Tables with version 3 metadata can be read but not committed to, see TableCapability
+#[cfg(feature = "format-v3")]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct TableMetadataV3 {
+    #[serde(flatten)]
+    pub metadata: TableMetadataV2,
+    // First row id assigned to the rows of the next snapshot, for row lineage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_row_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encryption_keys: Option<Vec<EncryptionKey>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
-#[serde(rename_all = "kebab-case", tag = "format-version")]
+// A key encrypting manifest lists or other keys of the table
+#[cfg(feature = "format-v3")]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct EncryptionKey {
+    pub key_id: String,
+    // Base64 encoded
+    pub encrypted_key_metadata: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted_by_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub properties: Option<HashMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
 pub struct TableMetadataV1 {
     pub format_version: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub table_uuid: Option<Uuid>,
     pub location: String,
     pub last_updated_ms: i64,
     pub last_column_id: i32,
     pub schema: IcebergSchemaV1,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub schemas: Option<Vec<IcebergSchemaV1>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub current_schema_id: Option<i32>,
     pub partition_spec: Vec<PartitionField>,
     pub partition_specs: Vec<PartitionSpec>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub default_spec_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub last_partition_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub properties: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub current_snapshot_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub snapshots: Option<Vec<SnapshotV1>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub snapshot_log: Option<Vec<SnapshotLog>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata_log: Option<Vec<MetadataLog>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub sort_orders: Option<Vec<SortOrders>>,
     pub default_sort_order_id: i32,
-    pub statistics: Option<Statistics>, // Unused: See documentation in Statistics structure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub statistics: Option<Vec<StatisticsFile>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partition_statistics: Option<Vec<PartitionStatisticsFile>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct SnapshotLog {
     pub snapshot_id: i64,
     pub timestamp_ms: i64,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct MetadataLog {
     pub metadata_file: String,
     pub timestamp_ms: i64,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
-pub struct Statistics {
-    // We are not going to implement this yet. Statistics must be read from
-    // puffin files, but they are optional for readers to read
+// Statistics of a snapshot stored in a Puffin file, e.g. NDV sketches used by cost-based
+// optimizers. Tables have at most one statistics file per snapshot
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct StatisticsFile {
+    pub snapshot_id: i64,
+    pub statistics_path: String,
+    pub file_size_in_bytes: i64,
+    pub file_footer_size_in_bytes: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_metadata: Option<String>,
+    pub blob_metadata: Vec<BlobMetadata>,
+}
+
+// A blob of a statistics file, without its position in the file
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct BlobMetadata {
+    #[serde(rename = "type")]
+    pub blob_type: String,
+    pub snapshot_id: i64,
+    pub sequence_number: i64,
+    pub fields: Vec<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub properties: Option<HashMap<String, String>>,
+}
+
+// Record counts and file counts of every partition of a snapshot, stored in a Parquet file. Tables
+// have at most one partition statistics file per snapshot
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct PartitionStatisticsFile {
+    pub snapshot_id: i64,
+    pub statistics_path: String,
+    pub file_size_in_bytes: i64,
+}
+
+impl TableMetadata {
+    pub fn format_version(&self) -> i32 {
+        match self {
+            TableMetadata::V1(_) => 1,
+            TableMetadata::V2(_) => 2,
+            #[cfg(feature = "format-v3")]
+            TableMetadata::V3(_) => 3,
+        }
+    }
+
+    // Normalized view of the metadata. V1 metadata is upgraded following the rules of the spec,
+    // the fields added by V3 are dropped. The format version of the result is left untouched so
+    // that callers can tell them apart
+    pub fn into_v2(self) -> error::Result<TableMetadataV2> {
+        match self {
+            TableMetadata::V1(metadata) => TableMetadataV2::try_from(metadata),
+            TableMetadata::V2(metadata) => Ok(metadata),
+            #[cfg(feature = "format-v3")]
+            TableMetadata::V3(metadata) => Ok(metadata.metadata),
+        }
+    }
+
+    // Violations of the invariants of the metadata, see TableMetadataV2::validate. Fails if V1
+    // metadata can't be upgraded
+    pub fn validate(&self) -> error::Result<Vec<MetadataViolation>> {
+        match self {
+            TableMetadata::V2(metadata) => Ok(metadata.validate()),
+            #[cfg(feature = "format-v3")]
+            TableMetadata::V3(metadata) => Ok(metadata.metadata.validate()),
+            TableMetadata::V1(_) => Ok(self.clone().into_v2()?.validate()),
+        }
+    }
+}
+
+// An inconsistency between fields of table metadata, see TableMetadataV2::validate
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum MetadataViolation {
+    MissingCurrentSchema {
+        schema_id: i32,
+    },
+    MissingDefaultPartitionSpec {
+        spec_id: i32,
+    },
+    MissingDefaultSortOrder {
+        order_id: i32,
+    },
+    MissingCurrentSnapshot {
+        snapshot_id: i64,
+    },
+    FieldIdAboveLastColumnId {
+        schema_id: i32,
+        field_id: i32,
+        last_column_id: i32,
+    },
+    SequenceNumberAboveLast {
+        snapshot_id: i64,
+        sequence_number: i64,
+        last_sequence_number: i64,
+    },
+    // Snapshots have higher sequence numbers than their parents
+    SequenceNumberNotIncreasing {
+        snapshot_id: i64,
+        sequence_number: i64,
+        parent_snapshot_id: i64,
+        parent_sequence_number: i64,
+    },
+    // Expiring snapshots keeps the ids of expired parents, so metadata of tables with expired
+    // snapshots has these as well
+    MissingParentSnapshot {
+        snapshot_id: i64,
+        parent_snapshot_id: i64,
+    },
+}
+
+impl fmt::Display for MetadataViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataViolation::MissingCurrentSchema { schema_id } => {
+                write!(f, "Current schema {} is missing", schema_id)
+            }
+            MetadataViolation::MissingDefaultPartitionSpec { spec_id } => {
+                write!(f, "Default partition spec {} is missing", spec_id)
+            }
+            MetadataViolation::MissingDefaultSortOrder { order_id } => {
+                write!(f, "Default sort order {} is missing", order_id)
+            }
+            MetadataViolation::MissingCurrentSnapshot { snapshot_id } => {
+                write!(f, "Current snapshot {} is missing", snapshot_id)
+            }
+            MetadataViolation::FieldIdAboveLastColumnId {
+                schema_id,
+                field_id,
+                last_column_id,
+            } => write!(
+                f,
+                "Field id {} of schema {} is above last-column-id {}",
+                field_id, schema_id, last_column_id
+            ),
+            MetadataViolation::SequenceNumberAboveLast {
+                snapshot_id,
+                sequence_number,
+                last_sequence_number,
+            } => write!(
+                f,
+                "Sequence number {} of snapshot {} is above last-sequence-number {}",
+                sequence_number, snapshot_id, last_sequence_number
+            ),
+            MetadataViolation::SequenceNumberNotIncreasing {
+                snapshot_id,
+                sequence_number,
+                parent_snapshot_id,
+                parent_sequence_number,
+            } => write!(
+                f,
+                "Sequence number {} of snapshot {} is not above sequence number {} of its parent {}",
+                sequence_number, snapshot_id, parent_sequence_number, parent_snapshot_id
+            ),
+            MetadataViolation::MissingParentSnapshot {
+                snapshot_id,
+                parent_snapshot_id,
+            } => write!(
+                f,
+                "Parent {} of snapshot {} is missing",
+                parent_snapshot_id, snapshot_id
+            ),
+        }
+    }
+}
+
+impl TableMetadataV2 {
+    // Checks the invariants between fields of the metadata that deserialization doesn't, returning
+    // every violation found. Sequence numbers are only checked for format version 2 and later, V1
+    // metadata has none
+    pub fn validate(&self) -> Vec<MetadataViolation> {
+        let mut violations = vec![];
+        if self.schema_by_id(self.current_schema_id).is_none() {
+            violations.push(MetadataViolation::MissingCurrentSchema {
+                schema_id: self.current_schema_id,
+            });
+        }
+        if self.partition_spec_by_id(self.default_spec_id).is_none() {
+            violations.push(MetadataViolation::MissingDefaultPartitionSpec {
+                spec_id: self.default_spec_id,
+            });
+        }
+        if self.default_sort_order().is_err() {
+            violations.push(MetadataViolation::MissingDefaultSortOrder {
+                order_id: self.default_sort_order_id,
+            });
+        }
+        if let Some(snapshot_id) = self.current_snapshot_id.filter(|id| *id != -1) {
+            if self.snapshot_by_id(snapshot_id).is_none() {
+                violations.push(MetadataViolation::MissingCurrentSnapshot { snapshot_id });
+            }
+        }
+        for schema in &self.schemas {
+            match schema.schema().max_field_id() {
+                Some(field_id) if field_id > self.last_column_id => {
+                    violations.push(MetadataViolation::FieldIdAboveLastColumnId {
+                        schema_id: schema.schema_id,
+                        field_id,
+                        last_column_id: self.last_column_id,
+                    })
+                }
+                _ => {}
+            }
+        }
+        for snapshot in self.snapshots.iter().flatten() {
+            let parent = snapshot
+                .parent_snapshot_id
+                .map(|parent_id| (parent_id, self.snapshot_by_id(parent_id)));
+            if let Some((parent_snapshot_id, None)) = parent {
+                violations.push(MetadataViolation::MissingParentSnapshot {
+                    snapshot_id: snapshot.snapshot_id,
+                    parent_snapshot_id,
+                });
+            }
+            if self.format_version < 2 {
+                continue;
+            }
+            if snapshot.sequence_number > self.last_sequence_number {
+                violations.push(MetadataViolation::SequenceNumberAboveLast {
+                    snapshot_id: snapshot.snapshot_id,
+                    sequence_number: snapshot.sequence_number,
+                    last_sequence_number: self.last_sequence_number,
+                });
+            }
+            if let Some((parent_snapshot_id, Some(parent))) = parent {
+                if snapshot.sequence_number <= parent.sequence_number {
+                    violations.push(MetadataViolation::SequenceNumberNotIncreasing {
+                        snapshot_id: snapshot.snapshot_id,
+                        sequence_number: snapshot.sequence_number,
+                        parent_snapshot_id,
+                        parent_sequence_number: parent.sequence_number,
+                    });
+                }
+            }
+        }
+        violations
+    }
+
+    pub fn schema_by_id(&self, schema_id: i32) -> Option<&IcebergSchemaV2> {
+        self.schemas
+            .iter()
+            .find(|schema| schema.schema_id == schema_id)
+    }
+
+    pub fn current_schema(&self) -> error::Result<&IcebergSchemaV2> {
+        self.schema_by_id(self.current_schema_id).ok_or_else(|| {
+            IcebergError::Invalid(format!(
+                "Current schema {} is missing from table metadata",
+                self.current_schema_id
+            ))
+        })
+    }
+
+    pub fn partition_spec_by_id(&self, spec_id: i32) -> Option<&PartitionSpec> {
+        self.partition_specs
+            .iter()
+            .find(|spec| spec.spec_id == spec_id)
+    }
+
+    pub fn default_partition_spec(&self) -> error::Result<&PartitionSpec> {
+        self.partition_spec_by_id(self.default_spec_id)
+            .ok_or_else(|| {
+                IcebergError::Invalid(format!(
+                    "Default partition spec {} is missing from table metadata",
+                    self.default_spec_id
+                ))
+            })
+    }
+
+    // Name mapping of the schema.name-mapping.default property, for data files without field ids
+    pub fn name_mapping(&self) -> error::Result<Option<NameMapping>> {
+        self.properties
+            .as_ref()
+            .and_then(|properties| properties.get(DEFAULT_NAME_MAPPING_PROPERTY))
+            .map(|json| NameMapping::from_json(json))
+            .transpose()
+    }
+
+    pub fn default_sort_order(&self) -> error::Result<&SortOrders> {
+        self.sort_orders
+            .iter()
+            .find(|order| order.order_id == self.default_sort_order_id)
+            .ok_or_else(|| {
+                IcebergError::Invalid(format!(
+                    "Default sort order {} is missing from table metadata",
+                    self.default_sort_order_id
+                ))
+            })
+    }
+
+    pub fn snapshot_by_id(&self, snapshot_id: i64) -> Option<&SnapshotV2> {
+        self.snapshots
+            .iter()
+            .flatten()
+            .find(|snapshot| snapshot.snapshot_id == snapshot_id)
+    }
+
+    // Java writes -1 as current snapshot id of tables without snapshots
+    pub fn current_snapshot(&self) -> Option<&SnapshotV2> {
+        self.current_snapshot_id
+            .filter(|id| *id != -1)
+            .and_then(|id| self.snapshot_by_id(id))
+    }
+
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties
+            .as_ref()
+            .and_then(|properties| properties.get(key))
+            .map(String::as_str)
+    }
+
+    pub fn statistics_file(&self, snapshot_id: i64) -> Option<&StatisticsFile> {
+        self.statistics
+            .iter()
+            .flatten()
+            .find(|statistics| statistics.snapshot_id == snapshot_id)
+    }
+
+    pub fn partition_statistics_file(&self, snapshot_id: i64) -> Option<&PartitionStatisticsFile> {
+        self.partition_statistics
+            .iter()
+            .flatten()
+            .find(|statistics| statistics.snapshot_id == snapshot_id)
+    }
+
+    // Partition type unifying the partition specs of the table: the fields of all specs by field
+    // id, as used by metadata spanning partition specs such as partition statistics
+    pub fn unified_partition_type(&self) -> error::Result<StructType> {
+        let schema = self.current_schema()?.schema();
+        let mut fields = BTreeMap::new();
+        for spec in &self.partition_specs {
+            for field in spec.partition_type(schema, self)?.fields {
+                fields.entry(field.id).or_insert(field);
+            }
+        }
+        Ok(StructType {
+            fields: fields.into_values().collect(),
+        })
+    }
+}
+
+// Looks ids up in the current schema, then in older schemas from the newest to the oldest
+impl FieldNames for TableMetadataV2 {
+    fn field_name(&self, id: i32) -> Option<(String, Option<i32>)> {
+        let mut schemas: Vec<&IcebergSchemaV2> = self.schemas.iter().collect();
+        schemas.sort_by_key(|schema| {
+            (
+                schema.schema_id != self.current_schema_id,
+                -schema.schema_id,
+            )
+        });
+        schemas.into_iter().find_map(|schema| schema.field_name(id))
+    }
+}
+
+impl TryFrom<TableMetadataV1> for TableMetadataV2 {
+    type Error = IcebergError;
+
+    fn try_from(metadata: TableMetadataV1) -> error::Result<Self> {
+        let schemas = match metadata.schemas {
+            Some(schemas) if !schemas.is_empty() => schemas,
+            _ => vec![metadata.schema.clone()],
+        };
+        let current_schema_id = metadata
+            .current_schema_id
+            .or(metadata.schema.schema_id)
+            .unwrap_or(0);
+        let schemas = schemas
+            .into_iter()
+            .map(|schema| {
+                IcebergSchemaV2::new(
+                    schema.schema_id.unwrap_or(0),
+                    schema.identifier_field_ids,
+                    schema.schema,
+                )
+            })
+            .collect();
+
+        // partition-specs is optional in V1, the single partition-spec is the default spec
+        let partition_specs = if metadata.partition_specs.is_empty() {
+            vec![PartitionSpec {
+                spec_id: 0,
+                fields: metadata.partition_spec,
+            }]
+        } else {
+            metadata.partition_specs
+        };
+        let default_spec_id = metadata.default_spec_id.unwrap_or(0);
+        let last_partition_id = metadata.last_partition_id.unwrap_or_else(|| {
+            partition_specs
+                .iter()
+                .flat_map(|spec| spec.fields.iter().map(|field| field.field_id))
+                .max()
+                .unwrap_or(999)
+        });
+
+        let snapshots = metadata
+            .snapshots
+            .map(|snapshots| {
+                snapshots
+                    .into_iter()
+                    .map(|snapshot| {
+                        let manifest_list = snapshot.manifest_list.ok_or_else(|| {
+                            IcebergError::Unsupported(format!(
+                                "Snapshot {} lists its manifests without a manifest list",
+                                snapshot.snapshot_id
+                            ))
+                        })?;
+                        Ok(SnapshotV2 {
+                            snapshot_id: snapshot.snapshot_id,
+                            parent_snapshot_id: snapshot.parent_snapshot_id,
+                            // V1 snapshots read as if they were written at sequence number 0
+                            sequence_number: 0,
+                            timestamp_ms: snapshot.timestamp_ms,
+                            summary: snapshot.summary.unwrap_or(Summary {
+                                operation: Operation::Append,
+                                rest: HashMap::new(),
+                            }),
+                            manifest_list,
+                            schema_id: snapshot.schema_id.map(|id| id as i32),
+                            first_row_id: None,
+                            added_rows: None,
+                        })
+                    })
+                    .collect::<error::Result<Vec<_>>>()
+            })
+            .transpose()?;
+
+        let sort_orders = match metadata.sort_orders {
+            Some(sort_orders) if !sort_orders.is_empty() => sort_orders,
+            _ => vec![SortOrders {
+                order_id: 0,
+                fields: vec![],
+            }],
+        };
+
+        Ok(TableMetadataV2 {
+            format_version: metadata.format_version,
+            table_uuid: metadata.table_uuid.unwrap_or_else(Uuid::new_v4),
+            location: metadata.location,
+            last_sequence_number: 0,
+            last_updated_ms: metadata.last_updated_ms,
+            last_column_id: metadata.last_column_id,
+            schemas,
+            current_schema_id,
+            partition_specs,
+            default_spec_id,
+            last_partition_id,
+            properties: metadata.properties,
+            current_snapshot_id: metadata.current_snapshot_id,
+            snapshots,
+            snapshot_log: metadata.snapshot_log,
+            metadata_log: metadata.metadata_log,
+            sort_orders,
+            default_sort_order_id: metadata.default_sort_order_id,
+            refs: None,
+            statistics: metadata.statistics,
+            partition_statistics: metadata.partition_statistics,
+        })
+    }
 }
 
 impl<'de> Deserialize<'de> for TableMetadata {
@@ -124,6 +642,19 @@ impl<'de> Deserialize<'de> for TableMetadata {
                         e
                     ))
                 }),
+            #[cfg(feature = "format-v3")]
+            3 => TableMetadataV3::deserialize(value)
+                .map(TableMetadata::V3)
+                .map_err(|e| {
+                    serde::de::Error::custom(format!(
+                        "Unable to deserialize version 3 metadata: error: {}",
+                        e
+                    ))
+                }),
+            #[cfg(not(feature = "format-v3"))]
+            3 => Err(serde::de::Error::custom(
+                "Unsupported metadata format-version 3, it needs the format-v3 feature",
+            )),
             _ => Err(serde::de::Error::custom(format!(
                 "Unsupported metadata format-version {}",
                 format_version
@@ -137,38 +668,122 @@ impl Serialize for TableMetadata {
     where
         S: Serializer,
     {
-        // Shadow the TableMetadata. This is mainly so that in the match arm below we can take
-        // a reference and use references all the way and avoid cloning the metadata
-        #[derive(Serialize)]
-        #[serde(untagged)]
-        enum TableMetadataShadow<'a> {
-            V1(&'a TableMetadataV1),
-            V2(&'a TableMetadataV2),
+        // All versions carry their format-version as a regular field
+        match self {
+            TableMetadata::V2(metadata) => metadata.serialize(serializer),
+            TableMetadata::V1(metadata) => metadata.serialize(serializer),
+            #[cfg(feature = "format-v3")]
+            TableMetadata::V3(metadata) => metadata.serialize(serializer),
         }
+    }
+}
 
-        #[derive(Serialize)]
-        #[serde(rename_all = "kebab-case")]
-        struct VersionedTableMetadata<'a> {
-            format_version: i32,
-            #[serde(flatten)]
-            metadata: TableMetadataShadow<'a>,
-        }
+// Format version 2 metadata with the schemas, partition specs and snapshots left undecoded
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct LazyTableMetadataV2<'a> {
+    format_version: i32,
+    table_uuid: Uuid,
+    location: String,
+    last_sequence_number: i64,
+    last_updated_ms: i64,
+    last_column_id: i32,
+    #[serde(borrow)]
+    schemas: Vec<&'a RawValue>,
+    current_schema_id: i32,
+    #[serde(borrow)]
+    partition_specs: Vec<&'a RawValue>,
+    default_spec_id: i32,
+    last_partition_id: i32,
+    properties: Option<HashMap<String, String>>,
+    current_snapshot_id: Option<i64>,
+    #[serde(borrow)]
+    snapshots: Option<Vec<&'a RawValue>>,
+    snapshot_log: Option<Vec<SnapshotLog>>,
+    metadata_log: Option<Vec<MetadataLog>>,
+    sort_orders: Vec<SortOrders>,
+    default_sort_order_id: i32,
+    refs: Option<HashMap<String, SnapshotRefV2>>,
+    statistics: Option<Vec<StatisticsFile>>,
+    partition_statistics: Option<Vec<PartitionStatisticsFile>>,
+}
 
-        let meta = match self {
-            TableMetadata::V2(metadata) => VersionedTableMetadata {
-                format_version: 2,
-                metadata: TableMetadataShadow::V2(metadata),
-            },
-            TableMetadata::V1(metadata) => VersionedTableMetadata {
-                format_version: 1,
-                metadata: TableMetadataShadow::V1(metadata),
-            },
+impl TableMetadata {
+    // Decodes a metadata file that follows `previous`, e.g. when refreshing a table. Schemas,
+    // partition specs and snapshots never change once added to a table, so the ones already in
+    // `previous` are reused and only the new ones are decoded. Other metadata is decoded in full
+    pub fn from_json_reusing(data: &[u8], previous: &TableMetadataV2) -> error::Result<Self> {
+        let lazy = match serde_json::from_slice::<LazyTableMetadataV2>(data) {
+            Ok(lazy) if lazy.format_version == 2 => lazy,
+            _ => return Ok(serde_json::from_slice(data)?),
         };
-
-        meta.serialize(serializer)
+        Ok(TableMetadata::V2(TableMetadataV2 {
+            format_version: lazy.format_version,
+            table_uuid: lazy.table_uuid,
+            location: lazy.location,
+            last_sequence_number: lazy.last_sequence_number,
+            last_updated_ms: lazy.last_updated_ms,
+            last_column_id: lazy.last_column_id,
+            schemas: decode_reusing(lazy.schemas, &previous.schemas, "schema-id", |schema| {
+                schema.schema_id as i64
+            })?,
+            current_schema_id: lazy.current_schema_id,
+            partition_specs: decode_reusing(
+                lazy.partition_specs,
+                &previous.partition_specs,
+                "spec-id",
+                |spec| spec.spec_id as i64,
+            )?,
+            default_spec_id: lazy.default_spec_id,
+            last_partition_id: lazy.last_partition_id,
+            properties: lazy.properties,
+            current_snapshot_id: lazy.current_snapshot_id,
+            snapshots: lazy
+                .snapshots
+                .map(|snapshots| {
+                    decode_reusing(
+                        snapshots,
+                        previous.snapshots.as_deref().unwrap_or_default(),
+                        "snapshot-id",
+                        |snapshot| snapshot.snapshot_id,
+                    )
+                })
+                .transpose()?,
+            snapshot_log: lazy.snapshot_log,
+            metadata_log: lazy.metadata_log,
+            sort_orders: lazy.sort_orders,
+            default_sort_order_id: lazy.default_sort_order_id,
+            refs: lazy.refs,
+            statistics: lazy.statistics,
+            partition_statistics: lazy.partition_statistics,
+        }))
     }
 }
 
+// Decodes the raw elements of a list, taking the elements of `previous` with the same id
+// instead of decoding them again
+fn decode_reusing<T: DeserializeOwned + Clone>(
+    raw: Vec<&RawValue>,
+    previous: &[T],
+    id_key: &str,
+    id: impl Fn(&T) -> i64,
+) -> error::Result<Vec<T>> {
+    let previous: HashMap<i64, &T> = previous.iter().map(|item| (id(item), item)).collect();
+    raw.into_iter()
+        .map(|raw| {
+            let fields: HashMap<&str, &RawValue> = serde_json::from_str(raw.get())?;
+            let raw_id = fields
+                .get(id_key)
+                .map(|value| serde_json::from_str::<i64>(value.get()))
+                .transpose()?;
+            match raw_id.and_then(|raw_id| previous.get(&raw_id)) {
+                Some(item) => Ok((*item).clone()),
+                None => Ok(serde_json::from_str(raw.get())?),
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -599,4 +1214,288 @@ mod tests {
 
         assert_eq!(v2_metadata, v2_metadata_deser);
     }
+
+    #[test]
+    fn test_serialized_metadata_has_single_format_version() {
+        let metadata: TableMetadata = serde_json::from_str(MINIMAL_V1_METADATA).unwrap();
+        let serialized = serde_json::to_string(&metadata).unwrap();
+
+        assert_eq!(1, serialized.matches("format-version").count());
+        assert!(!serialized.contains("null"));
+    }
+
+    #[cfg(not(feature = "format-v3"))]
+    #[test]
+    fn test_v3_metadata_needs_feature() {
+        let v3 = MINIMAL_V1_METADATA.replace(r#""format-version" : 1"#, r#""format-version" : 3"#);
+        let error = serde_json::from_str::<TableMetadata>(&v3).unwrap_err();
+        assert!(error.to_string().contains("format-v3 feature"));
+    }
+
+    #[cfg(all(feature = "format-v3", feature = "arrow"))]
+    #[test]
+    fn test_v3_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let v2 = crate::iceberg::test_utils::create_table(dir.path())
+            .metadata()
+            .clone();
+        let mut json = serde_json::to_value(&v2).unwrap();
+        json["format-version"] = 3.into();
+        json["next-row-id"] = 42.into();
+        let fields = json["schemas"][0]["fields"].as_array_mut().unwrap();
+        for (id, name, field_type) in [
+            (3, "event_ns", "timestamp_ns"),
+            (4, "event_tz_ns", "timestamptz_ns"),
+            (5, "payload", "variant"),
+        ] {
+            fields.push(serde_json::json!({
+                "id": id, "name": name, "required": false, "type": field_type
+            }));
+        }
+        json["encryption-keys"] = serde_json::json!([
+            {"key-id": "k1", "encrypted-key-metadata": "AAAA", "encrypted-by-id": "kms"}
+        ]);
+        let metadata: TableMetadata = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(3, metadata.format_version());
+        let TableMetadata::V3(v3) = &metadata else {
+            panic!("Expected version 3 metadata");
+        };
+        assert_eq!(Some(42), v3.next_row_id);
+        let keys = v3.encryption_keys.as_ref().unwrap();
+        assert_eq!("k1", keys[0].key_id);
+        assert_eq!(Some("kms"), keys[0].encrypted_by_id.as_deref());
+        let schema = v3.metadata.current_schema().unwrap().schema();
+        assert_eq!(
+            Some(&crate::iceberg::spec::schema::PrimitiveType::Variant),
+            schema.primitive_type_by_id(5)
+        );
+        assert_eq!(v2.schemas[0].schema().fields, schema.fields[..2]);
+        assert_eq!(json, serde_json::to_value(&metadata).unwrap());
+        assert_eq!(3, metadata.into_v2().unwrap().format_version);
+    }
+
+    #[test]
+    #[cfg(feature = "arrow")]
+    fn test_field_names_of_dropped_columns() {
+        use crate::iceberg::spec::partition_spec::Transform;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut metadata = crate::iceberg::test_utils::create_table(dir.path())
+            .metadata()
+            .clone();
+        let mut dropped = metadata.current_schema().unwrap().clone();
+        dropped.schema_id = 1;
+        dropped
+            .schema_mut()
+            .fields
+            .retain(|field| field.name != "data");
+        metadata.schemas.push(dropped);
+        metadata.current_schema_id = 1;
+        metadata.partition_specs[0].fields = vec![PartitionField {
+            source_id: 2,
+            source_ids: vec![],
+            field_id: 1000,
+            name: "data".to_string(),
+            transform: Transform::Identity,
+        }];
+
+        assert_eq!(
+            "column id (field id 1, schema 1)",
+            metadata.describe_field(1)
+        );
+        assert_eq!(
+            "column data (field id 2, schema 0)",
+            metadata.describe_field(2)
+        );
+        let error = metadata.unified_partition_type().unwrap_err().to_string();
+        assert!(
+            error.contains("its source is column data (field id 2, schema 0)"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_statistics_file() {
+        let data = r#"
+            {
+                "snapshot-id": 3055729675574597004,
+                "statistics-path": "s3://a/b/stats.puffin",
+                "file-size-in-bytes": 413,
+                "file-footer-size-in-bytes": 42,
+                "blob-metadata": [
+                    {
+                        "type": "apache-datasketches-theta-v1",
+                        "snapshot-id": 3055729675574597004,
+                        "sequence-number": 1,
+                        "fields": [1],
+                        "properties": {"ndv": "3"}
+                    }
+                ]
+            }
+        "#;
+        let statistics: StatisticsFile = serde_json::from_str(data).unwrap();
+        assert_eq!(42, statistics.file_footer_size_in_bytes);
+        assert_eq!(
+            "apache-datasketches-theta-v1",
+            statistics.blob_metadata[0].blob_type
+        );
+        assert_eq!(vec![1], statistics.blob_metadata[0].fields);
+        let json = serde_json::to_value(&statistics).unwrap();
+        assert_eq!(
+            "apache-datasketches-theta-v1",
+            json["blob-metadata"][0]["type"]
+        );
+        assert!(json.get("key-metadata").is_none());
+    }
+
+    #[test]
+    fn test_from_json_reusing() {
+        let v1: TableMetadata = serde_json::from_str(MINIMAL_V1_METADATA).unwrap();
+        let previous = v1.clone().into_v2().unwrap();
+        assert_eq!(
+            v1,
+            TableMetadata::from_json_reusing(MINIMAL_V1_METADATA.as_bytes(), &previous).unwrap()
+        );
+
+        // Decoding from scratch and reusing the same metadata give the same result
+        let previous = TableMetadataV2 {
+            format_version: 2,
+            ..previous
+        };
+        let v2 = TableMetadata::V2(previous.clone());
+        let json = serde_json::to_vec(&v2).unwrap();
+        let empty = TableMetadataV2 {
+            schemas: vec![],
+            partition_specs: vec![],
+            snapshots: None,
+            ..previous.clone()
+        };
+        assert_eq!(v2, TableMetadata::from_json_reusing(&json, &empty).unwrap());
+        assert_eq!(
+            v2,
+            TableMetadata::from_json_reusing(&json, &previous).unwrap()
+        );
+        assert!(TableMetadata::from_json_reusing(b"{}", &previous).is_err());
+    }
+
+    #[test]
+    fn test_v1_metadata_upgrade() {
+        let metadata: TableMetadata = serde_json::from_str(MINIMAL_V1_METADATA).unwrap();
+        assert_eq!(1, metadata.format_version());
+
+        let metadata = metadata.into_v2().unwrap();
+        assert_eq!(0, metadata.current_schema().unwrap().schema_id);
+        assert_eq!(1, metadata.default_partition_spec().unwrap().fields.len());
+        assert_eq!(1000, metadata.last_partition_id);
+        assert_eq!(1, metadata.sort_orders.len());
+
+        let snapshot = metadata.current_snapshot().unwrap();
+        assert_eq!(0, snapshot.sequence_number);
+        assert_eq!(Operation::Append, snapshot.summary.operation);
+    }
+
+    #[test]
+    fn test_validate() {
+        let v1: TableMetadata = serde_json::from_str(MINIMAL_V1_METADATA).unwrap();
+        assert!(v1.validate().unwrap().is_empty());
+
+        let valid = TableMetadataV2 {
+            format_version: 2,
+            ..v1.into_v2().unwrap()
+        };
+        assert!(valid.validate().is_empty());
+        let snapshot = valid.current_snapshot().unwrap().clone();
+        let mut metadata = TableMetadataV2 {
+            current_schema_id: 5,
+            default_spec_id: 7,
+            default_sort_order_id: 3,
+            current_snapshot_id: Some(42),
+            last_column_id: 0,
+            ..valid
+        };
+        metadata.snapshots.as_mut().unwrap().extend([
+            SnapshotV2 {
+                snapshot_id: 2,
+                parent_snapshot_id: Some(1),
+                ..snapshot.clone()
+            },
+            SnapshotV2 {
+                snapshot_id: 3,
+                parent_snapshot_id: Some(99),
+                sequence_number: 5,
+                ..snapshot
+            },
+        ]);
+        let violations = metadata.validate();
+        assert_eq!(
+            vec![
+                MetadataViolation::MissingCurrentSchema { schema_id: 5 },
+                MetadataViolation::MissingDefaultPartitionSpec { spec_id: 7 },
+                MetadataViolation::MissingDefaultSortOrder { order_id: 3 },
+                MetadataViolation::MissingCurrentSnapshot { snapshot_id: 42 },
+                MetadataViolation::FieldIdAboveLastColumnId {
+                    schema_id: 0,
+                    field_id: 1,
+                    last_column_id: 0
+                },
+                MetadataViolation::SequenceNumberNotIncreasing {
+                    snapshot_id: 2,
+                    sequence_number: 0,
+                    parent_snapshot_id: 1,
+                    parent_sequence_number: 0
+                },
+                MetadataViolation::MissingParentSnapshot {
+                    snapshot_id: 3,
+                    parent_snapshot_id: 99
+                },
+                MetadataViolation::SequenceNumberAboveLast {
+                    snapshot_id: 3,
+                    sequence_number: 5,
+                    last_sequence_number: 0
+                },
+            ],
+            violations
+        );
+        assert_eq!(
+            "Parent 99 of snapshot 3 is missing",
+            violations[6].to_string()
+        );
+
+        // V1 metadata has no sequence numbers to check
+        metadata.format_version = 1;
+        assert_eq!(6, metadata.validate().len());
+    }
+
+    const MINIMAL_V1_METADATA: &str = r#"
+        {
+          "format-version" : 1,
+          "location" : "file:/warehouse/db.db/table",
+          "last-updated-ms" : 1665194853343,
+          "last-column-id" : 1,
+          "schema" : {
+            "type" : "struct",
+            "fields" : [ {
+              "id" : 1,
+              "name" : "id",
+              "required" : true,
+              "type" : "long"
+            } ]
+          },
+          "partition-spec" : [ {
+            "name" : "id_bucket",
+            "transform" : "bucket[4]",
+            "source-id" : 1,
+            "field-id" : 1000
+          } ],
+          "partition-specs" : [ ],
+          "default-sort-order-id" : 0,
+          "current-snapshot-id" : 1,
+          "snapshots" : [ {
+            "snapshot-id" : 1,
+            "timestamp-ms" : 1665194853904,
+            "manifest-list" : "file:/warehouse/db.db/table/metadata/snap-1.avro"
+          } ]
+        }
+        "#;
 }
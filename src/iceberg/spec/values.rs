@@ -0,0 +1,645 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::OnceLock;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+use super::schema::PrimitiveType;
+use crate::iceberg::error::{IcebergError, Result};
+
+// A single value of an Iceberg primitive type. Used for partition values, column bounds and
+// expression literals. Temporal values are stored in their Iceberg physical representation
+// (days since epoch for dates, microseconds for times and timestamps) and decimals as the
+// unscaled value (the scale is part of the type)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Boolean(bool),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Decimal(i128),
+    Date(i32),
+    Time(i64),
+    Timestamp(i64),
+    Timestamptz(i64),
+    String(String),
+    Uuid(Uuid),
+    Fixed(Vec<u8>),
+    Binary(Vec<u8>),
+}
+
+impl Literal {
+    // Binary single-value serialization as defined in Appendix D of the Iceberg spec. This is
+    // the representation used for lower and upper bounds in manifests
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Literal::Boolean(v) => vec![u8::from(*v)],
+            Literal::Int(v) | Literal::Date(v) => v.to_le_bytes().to_vec(),
+            Literal::Long(v)
+            | Literal::Time(v)
+            | Literal::Timestamp(v)
+            | Literal::Timestamptz(v) => v.to_le_bytes().to_vec(),
+            Literal::Float(v) => v.to_le_bytes().to_vec(),
+            Literal::Double(v) => v.to_le_bytes().to_vec(),
+            Literal::Decimal(v) => decimal_to_bytes(*v),
+            Literal::String(v) => v.as_bytes().to_vec(),
+            Literal::Uuid(v) => v.as_bytes().to_vec(),
+            Literal::Fixed(v) | Literal::Binary(v) => v.clone(),
+        }
+    }
+
+    pub fn try_from_bytes(bytes: &[u8], primitive: &PrimitiveType) -> Result<Literal> {
+        let invalid = || {
+            IcebergError::Invalid(format!(
+                "Can't decode {} bytes as a {:?} value",
+                bytes.len(),
+                primitive
+            ))
+        };
+        Ok(match primitive {
+            PrimitiveType::Boolean => match bytes {
+                [0] => Literal::Boolean(false),
+                [_] => Literal::Boolean(true),
+                _ => return Err(invalid()),
+            },
+            PrimitiveType::Int => {
+                Literal::Int(i32::from_le_bytes(bytes.try_into().map_err(|_| invalid())?))
+            }
+            PrimitiveType::Date => {
+                Literal::Date(i32::from_le_bytes(bytes.try_into().map_err(|_| invalid())?))
+            }
+            // Spec allows promoting int to long, so bounds of a long column can be 4 bytes long
+            PrimitiveType::Long => Literal::Long(read_long(bytes).ok_or_else(invalid)?),
+            PrimitiveType::Time => Literal::Time(read_long(bytes).ok_or_else(invalid)?),
+            PrimitiveType::Timestamp => Literal::Timestamp(read_long(bytes).ok_or_else(invalid)?),
+            PrimitiveType::Timestamptz => {
+                Literal::Timestamptz(read_long(bytes).ok_or_else(invalid)?)
+            }
+            PrimitiveType::Float => {
+                Literal::Float(f32::from_le_bytes(bytes.try_into().map_err(|_| invalid())?))
+            }
+            // Same as for long, float can be promoted to double
+            PrimitiveType::Double => match bytes.len() {
+                4 => Literal::Double(f32::from_le_bytes(bytes.try_into().unwrap()) as f64),
+                8 => Literal::Double(f64::from_le_bytes(bytes.try_into().unwrap())),
+                _ => return Err(invalid()),
+            },
+            PrimitiveType::Decimal { .. } => {
+                Literal::Decimal(decimal_from_bytes(bytes).ok_or_else(invalid)?)
+            }
+            PrimitiveType::String => {
+                Literal::String(String::from_utf8(bytes.to_vec()).map_err(|_| invalid())?)
+            }
+            PrimitiveType::Uuid => Literal::Uuid(Uuid::from_slice(bytes).map_err(|_| invalid())?),
+            PrimitiveType::Fixed(_) => Literal::Fixed(bytes.to_vec()),
+            PrimitiveType::Binary => Literal::Binary(bytes.to_vec()),
+            PrimitiveType::TimestampNs | PrimitiveType::TimestamptzNs | PrimitiveType::Variant => {
+                return Err(IcebergError::Unsupported(format!(
+                    "Values of type {:?}",
+                    primitive
+                )))
+            }
+        })
+    }
+}
+
+impl Literal {
+    // Converts the literal to the given type, e.g. to bind a filter literal to the type of the
+    // column it is compared with. Strings are parsed for temporal, uuid and decimal types
+    pub fn to_type(&self, primitive: &PrimitiveType) -> Result<Literal> {
+        let invalid =
+            || IcebergError::Invalid(format!("Can't convert {:?} to {:?}", self, primitive));
+        let scale_of = |scale: &u32| 10i128.checked_pow(*scale).ok_or_else(invalid);
+        Ok(match (self, primitive) {
+            (Literal::Boolean(_), PrimitiveType::Boolean)
+            | (Literal::Int(_), PrimitiveType::Int)
+            | (Literal::Long(_), PrimitiveType::Long)
+            | (Literal::Float(_), PrimitiveType::Float)
+            | (Literal::Double(_), PrimitiveType::Double)
+            | (Literal::Decimal(_), PrimitiveType::Decimal { .. })
+            | (Literal::Date(_), PrimitiveType::Date)
+            | (Literal::Time(_), PrimitiveType::Time)
+            | (Literal::Timestamp(_), PrimitiveType::Timestamp)
+            | (Literal::Timestamptz(_), PrimitiveType::Timestamptz)
+            | (Literal::String(_), PrimitiveType::String)
+            | (Literal::Uuid(_), PrimitiveType::Uuid)
+            | (Literal::Binary(_), PrimitiveType::Binary) => self.clone(),
+            (Literal::Int(v), _) => Literal::Long(*v as i64).to_type(primitive)?,
+            (Literal::Long(v), PrimitiveType::Int) => {
+                Literal::Int(i32::try_from(*v).map_err(|_| invalid())?)
+            }
+            (Literal::Long(v), PrimitiveType::Float) => Literal::Float(*v as f32),
+            (Literal::Long(v), PrimitiveType::Double) => Literal::Double(*v as f64),
+            (Literal::Long(v), PrimitiveType::Date) => {
+                Literal::Date(i32::try_from(*v).map_err(|_| invalid())?)
+            }
+            (Literal::Long(v), PrimitiveType::Time) => Literal::Time(*v),
+            (Literal::Long(v), PrimitiveType::Timestamp) => Literal::Timestamp(*v),
+            (Literal::Long(v), PrimitiveType::Timestamptz) => Literal::Timestamptz(*v),
+            (Literal::Long(v), PrimitiveType::Decimal { scale, .. }) => Literal::Decimal(
+                (*v as i128)
+                    .checked_mul(scale_of(scale)?)
+                    .ok_or_else(invalid)?,
+            ),
+            (Literal::Float(v), PrimitiveType::Double) => Literal::Double(*v as f64),
+            (Literal::Double(v), PrimitiveType::Float) => Literal::Float(*v as f32),
+            (Literal::Timestamp(v), PrimitiveType::Timestamptz) => Literal::Timestamptz(*v),
+            (Literal::Timestamptz(v), PrimitiveType::Timestamp) => Literal::Timestamp(*v),
+            (Literal::String(v), PrimitiveType::Date) => {
+                Literal::Date(parse_date(v).ok_or_else(invalid)?)
+            }
+            (Literal::String(v), PrimitiveType::Time) => {
+                Literal::Time(parse_time(v).ok_or_else(invalid)?)
+            }
+            (Literal::String(v), PrimitiveType::Timestamp) => {
+                Literal::Timestamp(parse_timestamp(v).ok_or_else(invalid)?)
+            }
+            (Literal::String(v), PrimitiveType::Timestamptz) => {
+                Literal::Timestamptz(parse_timestamp(v).ok_or_else(invalid)?)
+            }
+            (Literal::String(v), PrimitiveType::Uuid) => {
+                Literal::Uuid(Uuid::parse_str(v).map_err(|_| invalid())?)
+            }
+            (Literal::String(v), PrimitiveType::Decimal { scale, .. }) => {
+                Literal::Decimal(parse_decimal(v, *scale).ok_or_else(invalid)?)
+            }
+            (Literal::Binary(v) | Literal::Fixed(v), PrimitiveType::Fixed(length))
+                if v.len() == *length as usize =>
+            {
+                Literal::Fixed(v.clone())
+            }
+            (Literal::Fixed(v), PrimitiveType::Binary) => Literal::Binary(v.clone()),
+            _ => return Err(invalid()),
+        })
+    }
+}
+
+// Literals of the same type are ordered like their Iceberg type. Literals of different types
+// are not comparable
+impl PartialOrd for Literal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Literal::Boolean(a), Literal::Boolean(b)) => a.partial_cmp(b),
+            (Literal::Int(a), Literal::Int(b)) => a.partial_cmp(b),
+            (Literal::Long(a), Literal::Long(b)) => a.partial_cmp(b),
+            (Literal::Float(a), Literal::Float(b)) => a.partial_cmp(b),
+            (Literal::Double(a), Literal::Double(b)) => a.partial_cmp(b),
+            (Literal::Decimal(a), Literal::Decimal(b)) => a.partial_cmp(b),
+            (Literal::Date(a), Literal::Date(b)) => a.partial_cmp(b),
+            (Literal::Time(a), Literal::Time(b)) => a.partial_cmp(b),
+            (Literal::Timestamp(a), Literal::Timestamp(b)) => a.partial_cmp(b),
+            (Literal::Timestamptz(a), Literal::Timestamptz(b)) => a.partial_cmp(b),
+            (Literal::String(a), Literal::String(b)) => a.partial_cmp(b),
+            (Literal::Uuid(a), Literal::Uuid(b)) => a.partial_cmp(b),
+            (Literal::Fixed(a), Literal::Fixed(b)) => a.partial_cmp(b),
+            (Literal::Binary(a), Literal::Binary(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Boolean(v) => write!(f, "{}", v),
+            Literal::Int(v) | Literal::Date(v) => write!(f, "{}", v),
+            Literal::Long(v)
+            | Literal::Time(v)
+            | Literal::Timestamp(v)
+            | Literal::Timestamptz(v) => {
+                write!(f, "{}", v)
+            }
+            Literal::Float(v) => write!(f, "{}", v),
+            Literal::Double(v) => write!(f, "{}", v),
+            Literal::Decimal(v) => write!(f, "{}", v),
+            Literal::String(v) => write!(f, "{}", v),
+            Literal::Uuid(v) => write!(f, "{}", v),
+            Literal::Fixed(v) | Literal::Binary(v) => {
+                for byte in v {
+                    write!(f, "{:02x}", byte)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// Year, month and day of a number of days since 1970-01-01 in the proleptic Gregorian calendar
+// (see http://howardhinnant.github.io/date_algorithms.html#civil_from_days)
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// Inverse of civil_from_days
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let day_of_year = (153 * mp + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// ISO representation of a timestamp in milliseconds since epoch, in UTC
+// (e.g. 2022-10-08T02:07:33.343Z)
+pub fn format_timestamp_ms(timestamp_ms: i64) -> String {
+    let (days, ms) = (
+        timestamp_ms.div_euclid(86_400_000),
+        timestamp_ms.rem_euclid(86_400_000),
+    );
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+// Parses an ISO date (e.g. 2022-10-08) to days since epoch
+fn parse_date(value: &str) -> Option<i32> {
+    static REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(-?\d{4,})-(\d{2})-(\d{2})$").unwrap());
+    let captures = REGEX.captures(value)?;
+    let month = captures[2].parse().ok()?;
+    let day = captures[3].parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    i32::try_from(days_from_civil(captures[1].parse().ok()?, month, day)).ok()
+}
+
+// Parses an ISO time of day (e.g. 10:15:30.123456) to microseconds from midnight
+fn parse_time(value: &str) -> Option<i64> {
+    static REGEX: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^(\d{2}):(\d{2})(?::(\d{2})(?:\.(\d{1,6}))?)?$").unwrap());
+    let captures = REGEX.captures(value)?;
+    let hours: i64 = captures[1].parse().ok()?;
+    let minutes: i64 = captures[2].parse().ok()?;
+    let seconds: i64 = captures
+        .get(3)
+        .map_or(Some(0), |s| s.as_str().parse().ok())?;
+    if hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+    let micros = captures.get(4).map_or(Some(0), |fraction| {
+        format!("{:0<6}", fraction.as_str()).parse::<i64>().ok()
+    })?;
+    Some(((hours * 60 + minutes) * 60 + seconds) * 1_000_000 + micros)
+}
+
+// Parses an ISO timestamp (e.g. 2022-10-08T10:15:30.123+02:00) to microseconds since epoch.
+// Timestamps with an offset are adjusted to UTC
+fn parse_timestamp(value: &str) -> Option<i64> {
+    static REGEX: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^([^T ]+)(?:[T ]([^Z+-]+))?(Z|[+-](\d{2}):(\d{2}))?$").unwrap());
+    let captures = REGEX.captures(value)?;
+    let days = parse_date(&captures[1])? as i64;
+    let time = captures
+        .get(2)
+        .map_or(Some(0), |time| parse_time(time.as_str()))?;
+    let offset = match (captures.get(3), captures.get(4), captures.get(5)) {
+        (Some(sign), Some(hours), Some(minutes)) => {
+            let hours: i64 = hours.as_str().parse().ok()?;
+            let minutes: i64 = minutes.as_str().parse().ok()?;
+            let offset = (hours * 60 + minutes) * 60_000_000;
+            if sign.as_str().starts_with('-') {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => 0,
+    };
+    Some(days * 86_400_000_000 + time - offset)
+}
+
+// Parses a decimal string (e.g. -12.5) to its unscaled value for the given scale
+fn parse_decimal(value: &str, scale: u32) -> Option<i128> {
+    static REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^([+-]?)(\d*)(?:\.(\d*))?$").unwrap());
+    let captures = REGEX.captures(value)?;
+    let integer = captures.get(2).map_or("", |m| m.as_str());
+    let fraction = captures.get(3).map_or("", |m| m.as_str());
+    if (integer.is_empty() && fraction.is_empty()) || fraction.len() > scale as usize {
+        return None;
+    }
+    let unscaled: i128 = format!("{}{:0<width$}", integer, fraction, width = scale as usize)
+        .parse()
+        .ok()?;
+    Some(if &captures[1] == "-" {
+        -unscaled
+    } else {
+        unscaled
+    })
+}
+
+fn read_long(bytes: &[u8]) -> Option<i64> {
+    match bytes.len() {
+        4 => Some(i32::from_le_bytes(bytes.try_into().ok()?) as i64),
+        8 => Some(i64::from_le_bytes(bytes.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+// A lower or upper bound of a column or partition field, in its binary single-value
+// serialization. The typed value is decoded on first use and cached, so that evaluating several
+// predicates against the bounds of a file decodes them once
+#[derive(Clone)]
+pub struct Bound {
+    bytes: Vec<u8>,
+    decoded: OnceLock<(PrimitiveType, Literal)>,
+}
+
+impl Bound {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Bound {
+            bytes,
+            decoded: OnceLock::new(),
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    // Value of the bound for a field of the given type. Only the value decoded for the first
+    // type is cached, decoding with another type (e.g. the promoted type of the field) doesn't
+    // replace it
+    pub fn decode(&self, primitive: &PrimitiveType) -> Result<Cow<'_, Literal>> {
+        if self.decoded.get().is_none() {
+            let literal = Literal::try_from_bytes(&self.bytes, primitive)?;
+            let _ = self.decoded.set((primitive.clone(), literal));
+        }
+        match self.decoded.get() {
+            Some((decoded_type, literal)) if decoded_type == primitive => {
+                Ok(Cow::Borrowed(literal))
+            }
+            _ => Literal::try_from_bytes(&self.bytes, primitive).map(Cow::Owned),
+        }
+    }
+}
+
+impl From<Vec<u8>> for Bound {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bound::new(bytes)
+    }
+}
+
+impl From<&Literal> for Bound {
+    fn from(literal: &Literal) -> Self {
+        Bound::new(literal.to_bytes())
+    }
+}
+
+impl PartialEq for Bound {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl Eq for Bound {}
+
+impl fmt::Debug for Bound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Bound").field(&self.bytes).finish()
+    }
+}
+
+impl Serialize for Bound {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.bytes)
+    }
+}
+
+impl<'de> Deserialize<'de> for Bound {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        serde_bytes::ByteBuf::deserialize(deserializer).map(|bytes| Bound::new(bytes.into_vec()))
+    }
+}
+
+// Two's complement big endian representation of the unscaled value using the minimum number of
+// bytes
+fn decimal_to_bytes(value: i128) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let sign_byte = if value < 0 { 0xff } else { 0x00 };
+    let mut start = 0;
+    // Drop leading sign bytes as long as the next byte still carries the sign in its top bit
+    while start < bytes.len() - 1
+        && bytes[start] == sign_byte
+        && (bytes[start + 1] & 0x80) == (sign_byte & 0x80)
+    {
+        start += 1;
+    }
+    bytes[start..].to_vec()
+}
+
+fn decimal_from_bytes(bytes: &[u8]) -> Option<i128> {
+    if bytes.is_empty() || bytes.len() > 16 {
+        return None;
+    }
+    let sign_byte = if bytes[0] & 0x80 != 0 { 0xff } else { 0x00 };
+    let mut buffer = [sign_byte; 16];
+    buffer[16 - bytes.len()..].copy_from_slice(bytes);
+    Some(i128::from_be_bytes(buffer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literal_bytes_roundtrip() {
+        let literals = [
+            (Literal::Boolean(true), PrimitiveType::Boolean),
+            (Literal::Int(-42), PrimitiveType::Int),
+            (Literal::Long(1 << 40), PrimitiveType::Long),
+            (Literal::Float(1.5), PrimitiveType::Float),
+            (Literal::Double(-2.25), PrimitiveType::Double),
+            (
+                Literal::Decimal(-12345),
+                PrimitiveType::Decimal {
+                    precision: 10,
+                    scale: 2,
+                },
+            ),
+            (Literal::Date(19000), PrimitiveType::Date),
+            (Literal::Time(3_600_000_000), PrimitiveType::Time),
+            (
+                Literal::Timestamp(1665194853343000),
+                PrimitiveType::Timestamp,
+            ),
+            (
+                Literal::Timestamptz(1665194853343000),
+                PrimitiveType::Timestamptz,
+            ),
+            (
+                Literal::String("iceberg".to_string()),
+                PrimitiveType::String,
+            ),
+            (Literal::Uuid(Uuid::from_u128(42)), PrimitiveType::Uuid),
+            (Literal::Fixed(vec![1, 2, 3]), PrimitiveType::Fixed(3)),
+            (Literal::Binary(vec![0, 255]), PrimitiveType::Binary),
+        ];
+
+        for (literal, primitive) in literals {
+            let bytes = literal.to_bytes();
+            assert_eq!(
+                literal,
+                Literal::try_from_bytes(&bytes, &primitive).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_int_bytes_are_little_endian() {
+        // Matches the bounds Spark wrote in the manifest list test fixtures
+        assert_eq!(vec![10, 0, 0, 0], Literal::Int(10).to_bytes());
+    }
+
+    #[test]
+    fn test_decimal_bytes_use_minimum_length() {
+        assert_eq!(vec![0x00], decimal_to_bytes(0));
+        assert_eq!(vec![0x7f], decimal_to_bytes(127));
+        assert_eq!(vec![0x00, 0x80], decimal_to_bytes(128));
+        assert_eq!(vec![0xff], decimal_to_bytes(-1));
+        assert_eq!(vec![0xff, 0x7f], decimal_to_bytes(-129));
+    }
+
+    #[test]
+    fn test_long_accepts_promoted_int_bounds() {
+        assert_eq!(
+            Literal::Long(10),
+            Literal::try_from_bytes(&[10, 0, 0, 0], &PrimitiveType::Long).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bound_caches_decoded_value() {
+        let bound = Bound::from(&Literal::Int(10));
+        let decoded = bound.decode(&PrimitiveType::Int).unwrap();
+        assert!(matches!(decoded, Cow::Borrowed(Literal::Int(10))));
+        assert!(matches!(
+            bound.decode(&PrimitiveType::Int).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        // Promoted types are decoded without replacing the cached value
+        assert_eq!(
+            Literal::Long(10),
+            *bound.decode(&PrimitiveType::Long).unwrap()
+        );
+        assert!(matches!(
+            bound.decode(&PrimitiveType::Int).unwrap(),
+            Cow::Borrowed(Literal::Int(10))
+        ));
+        assert!(Bound::new(vec![1, 2, 3])
+            .decode(&PrimitiveType::Int)
+            .is_err());
+    }
+
+    #[test]
+    fn test_invalid_bytes_fail() {
+        assert!(Literal::try_from_bytes(&[1, 2, 3], &PrimitiveType::Int).is_err());
+        assert!(Literal::try_from_bytes(&[0xff], &PrimitiveType::String).is_err());
+    }
+
+    #[test]
+    fn test_literal_ordering() {
+        assert!(Literal::Int(1) < Literal::Int(2));
+        assert!(Literal::String("a".to_string()) < Literal::String("b".to_string()));
+        assert_eq!(None, Literal::Int(1).partial_cmp(&Literal::Long(1)));
+    }
+
+    #[test]
+    fn test_literal_to_type() {
+        assert_eq!(
+            Literal::Long(1),
+            Literal::Int(1).to_type(&PrimitiveType::Long).unwrap()
+        );
+        assert_eq!(
+            Literal::Decimal(1250),
+            Literal::Long(125)
+                .to_type(&PrimitiveType::Decimal {
+                    precision: 9,
+                    scale: 1
+                })
+                .unwrap()
+        );
+        assert_eq!(
+            Literal::Decimal(-1250),
+            Literal::String("-12.50".to_string())
+                .to_type(&PrimitiveType::Decimal {
+                    precision: 9,
+                    scale: 2
+                })
+                .unwrap()
+        );
+        assert_eq!(
+            Literal::Date(17486),
+            Literal::String("2017-11-16".to_string())
+                .to_type(&PrimitiveType::Date)
+                .unwrap()
+        );
+        assert_eq!(
+            Literal::Timestamptz(1510871468000000),
+            Literal::String("2017-11-16T22:31:08".to_string())
+                .to_type(&PrimitiveType::Timestamptz)
+                .unwrap()
+        );
+        assert_eq!(
+            Literal::Timestamp(1510871468123000),
+            Literal::String("2017-11-17T00:31:08.123+02:00".to_string())
+                .to_type(&PrimitiveType::Timestamp)
+                .unwrap()
+        );
+        assert_eq!(
+            Literal::Time(81068000000),
+            Literal::String("22:31:08".to_string())
+                .to_type(&PrimitiveType::Time)
+                .unwrap()
+        );
+        assert!(Literal::Long(1 << 40).to_type(&PrimitiveType::Int).is_err());
+        assert!(Literal::String("2017-13-01".to_string())
+            .to_type(&PrimitiveType::Date)
+            .is_err());
+        assert!(Literal::Boolean(true)
+            .to_type(&PrimitiveType::Long)
+            .is_err());
+    }
+
+    #[test]
+    fn test_civil_days_roundtrip() {
+        for days in [-719_468, -1, 0, 17486, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days, days_from_civil(year, month, day));
+        }
+        assert_eq!((2017, 11, 16), civil_from_days(17486));
+        assert_eq!(
+            "2022-10-08T02:07:33.343Z",
+            format_timestamp_ms(1_665_194_853_343)
+        );
+        assert_eq!("1969-12-31T23:59:59.999Z", format_timestamp_ms(-1));
+    }
+}
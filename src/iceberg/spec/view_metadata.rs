@@ -0,0 +1,221 @@
+// Metadata of Iceberg views, following the view spec: versions of the view, each with the SQL
+// text of the query in one or more dialects and the id of the schema of its result. Like tables,
+// views are tracked by the location of their current metadata file
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::schema::IcebergSchemaV2;
+use crate::iceberg::error::{IcebergError, Result};
+
+pub const VIEW_FORMAT_VERSION: i32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ViewMetadata {
+    pub view_uuid: Uuid,
+    pub format_version: i32,
+    pub location: String,
+    pub current_version_id: i32,
+    pub versions: Vec<ViewVersion>,
+    pub version_log: Vec<ViewHistoryEntry>,
+    pub schemas: Vec<IcebergSchemaV2>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub properties: Option<HashMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ViewVersion {
+    pub version_id: i32,
+    pub timestamp_ms: i64,
+    pub schema_id: i32,
+    // Engine that created the version and its version, among others
+    pub summary: HashMap<String, String>,
+    pub representations: Vec<ViewRepresentation>,
+    // Catalog and namespace resolving unqualified identifiers of the query
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_catalog: Option<String>,
+    pub default_namespace: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase", tag = "type")]
+pub enum ViewRepresentation {
+    Sql(SqlViewRepresentation),
+    // Representations of types added by later versions of the spec, which readers skip
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SqlViewRepresentation {
+    pub sql: String,
+    pub dialect: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ViewHistoryEntry {
+    pub timestamp_ms: i64,
+    pub version_id: i32,
+}
+
+impl ViewMetadata {
+    pub fn from_json(data: &[u8]) -> Result<Self> {
+        let metadata: ViewMetadata = serde_json::from_slice(data)?;
+        if metadata.format_version != VIEW_FORMAT_VERSION {
+            return Err(IcebergError::Unsupported(format!(
+                "View metadata format-version {}",
+                metadata.format_version
+            )));
+        }
+        Ok(metadata)
+    }
+
+    pub fn version_by_id(&self, version_id: i32) -> Option<&ViewVersion> {
+        self.versions
+            .iter()
+            .find(|version| version.version_id == version_id)
+    }
+
+    pub fn current_version(&self) -> Result<&ViewVersion> {
+        self.version_by_id(self.current_version_id).ok_or_else(|| {
+            IcebergError::Invalid(format!(
+                "Current version {} is missing from view metadata",
+                self.current_version_id
+            ))
+        })
+    }
+
+    pub fn schema_by_id(&self, schema_id: i32) -> Option<&IcebergSchemaV2> {
+        self.schemas
+            .iter()
+            .find(|schema| schema.schema_id == schema_id)
+    }
+
+    // Schema of the result of the current version
+    pub fn current_schema(&self) -> Result<&IcebergSchemaV2> {
+        let schema_id = self.current_version()?.schema_id;
+        self.schema_by_id(schema_id).ok_or_else(|| {
+            IcebergError::Invalid(format!(
+                "Schema {} is missing from view metadata",
+                schema_id
+            ))
+        })
+    }
+
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties
+            .as_ref()
+            .and_then(|properties| properties.get(key))
+            .map(String::as_str)
+    }
+}
+
+impl ViewVersion {
+    pub fn sql_representations(&self) -> impl Iterator<Item = &SqlViewRepresentation> {
+        self.representations
+            .iter()
+            .filter_map(|representation| match representation {
+                ViewRepresentation::Sql(sql) => Some(sql),
+                ViewRepresentation::Unknown => None,
+            })
+    }
+
+    // SQL text of the query in the given dialect, matched case-insensitively
+    pub fn sql(&self, dialect: &str) -> Option<&str> {
+        self.sql_representations()
+            .find(|sql| sql.dialect.eq_ignore_ascii_case(dialect))
+            .map(|sql| sql.sql.as_str())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Example of the view spec
+    pub(crate) const VIEW_METADATA: &str = r#"
+    {
+      "view-uuid": "fa6506c3-7681-40c8-86dc-e36561f83385",
+      "format-version": 1,
+      "location": "s3://bucket/warehouse/default.db/event_agg",
+      "current-version-id": 2,
+      "properties": {"comment": "Daily event counts"},
+      "versions": [
+        {
+          "version-id": 1,
+          "timestamp-ms": 1573518431292,
+          "schema-id": 1,
+          "default-catalog": "prod",
+          "default-namespace": ["default"],
+          "summary": {"engine-name": "Spark", "engine-version": "3.3.2"},
+          "representations": [
+            {"type": "sql", "sql": "SELECT COUNT(1), CAST(event_ts AS DATE) FROM events GROUP BY 2", "dialect": "spark"}
+          ]
+        },
+        {
+          "version-id": 2,
+          "timestamp-ms": 1573518981593,
+          "schema-id": 1,
+          "default-namespace": ["default"],
+          "summary": {"engine-name": "Trino"},
+          "representations": [
+            {"type": "sql", "sql": "SELECT count(*), CAST(event_ts AS date) FROM events GROUP BY 2", "dialect": "trino"},
+            {"type": "substrait", "plan": "AAAA"}
+          ]
+        }
+      ],
+      "schemas": [
+        {
+          "schema-id": 1,
+          "type": "struct",
+          "fields": [
+            {"id": 1, "name": "event_count", "required": false, "type": "long", "doc": "Count of events"},
+            {"id": 2, "name": "event_date", "required": false, "type": "date"}
+          ]
+        }
+      ],
+      "version-log": [
+        {"timestamp-ms": 1573518431292, "version-id": 1},
+        {"timestamp-ms": 1573518981593, "version-id": 2}
+      ]
+    }
+    "#;
+
+    #[test]
+    fn test_view_metadata() {
+        let metadata = ViewMetadata::from_json(VIEW_METADATA.as_bytes()).unwrap();
+        assert_eq!(Some("Daily event counts"), metadata.property("comment"));
+        let version = metadata.current_version().unwrap();
+        assert_eq!(2, version.version_id);
+        assert_eq!(ViewRepresentation::Unknown, version.representations[1]);
+        assert_eq!(1, version.sql_representations().count());
+        assert!(version.sql("TRINO").unwrap().starts_with("SELECT count(*)"));
+        assert_eq!(None, version.sql("spark"));
+        assert_eq!(
+            Some("prod"),
+            metadata
+                .version_by_id(1)
+                .unwrap()
+                .default_catalog
+                .as_deref()
+        );
+        let schema = metadata.current_schema().unwrap();
+        assert_eq!("event_count", schema.schema().fields[0].name);
+        assert_eq!(2, metadata.version_log.len());
+
+        let mut json: serde_json::Value = serde_json::from_str(VIEW_METADATA).unwrap();
+        json["format-version"] = 2.into();
+        assert!(matches!(
+            ViewMetadata::from_json(&serde_json::to_vec(&json).unwrap()),
+            Err(IcebergError::Unsupported(_))
+        ));
+        json["format-version"] = 1.into();
+        json["current-version-id"] = 3.into();
+        let metadata = ViewMetadata::from_json(&serde_json::to_vec(&json).unwrap()).unwrap();
+        assert!(metadata.current_version().is_err());
+    }
+}
@@ -0,0 +1,140 @@
+// Table loaded straight from one of its metadata files, without a catalog, e.g. to inspect a table
+// at a known location or a past version of it. Static tables are immutable: without a catalog
+// there is no current metadata to refresh or commit to, and reads go through a ReadOnlyFileIO so
+// that nothing can be written through them either
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::iceberg::error::Result;
+use crate::iceberg::ident::{Namespace, TableIdent};
+use crate::iceberg::io::FileIO;
+use crate::iceberg::read_only::ReadOnlyFileIO;
+use crate::iceberg::table::Table;
+
+// Namespace of the identifiers of static tables, unless given
+pub const STATIC_NAMESPACE: &str = "static";
+
+#[derive(Debug, Clone)]
+pub struct StaticTable {
+    table: Table,
+}
+
+impl StaticTable {
+    // Loads the metadata file at the location. The table is identified as
+    // "static.<directory of the table>" in errors and reports, see with_ident
+    pub fn from_metadata_location(
+        metadata_location: &str,
+        file_io: Arc<dyn FileIO>,
+    ) -> Result<Self> {
+        let file_io = Arc::new(ReadOnlyFileIO::new(file_io));
+        let table = Table::load(
+            default_ident(metadata_location)?,
+            metadata_location.to_string(),
+            file_io,
+        )?;
+        Ok(StaticTable { table })
+    }
+
+    pub fn with_ident(self, ident: TableIdent) -> Self {
+        StaticTable {
+            table: self.table.with_ident(ident),
+        }
+    }
+
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    pub fn into_table(self) -> Table {
+        self.table
+    }
+}
+
+impl Deref for StaticTable {
+    type Target = Table;
+
+    fn deref(&self) -> &Table {
+        &self.table
+    }
+}
+
+// Names the table after its directory, the parent of the metadata directory, whose dots aren't
+// allowed in names
+fn default_ident(metadata_location: &str) -> Result<TableIdent> {
+    let name = metadata_location
+        .rsplit(['/', ':'])
+        .nth(2)
+        .filter(|name| !name.is_empty())
+        .unwrap_or("table")
+        .replace('.', "_");
+    TableIdent::try_new(
+        Namespace::try_new(vec![STATIC_NAMESPACE.to_string()])?,
+        &name,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::error::IcebergError;
+    use crate::iceberg::io::LocalFileIO;
+    use crate::iceberg::test_utils::{append_ids, ids_batch, TestCatalog};
+    use crate::iceberg::writer::partitioned::PartitionedWriter;
+
+    #[test]
+    fn test_static_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let table_dir = dir.path().join("events.v2");
+        let catalog = TestCatalog::new();
+        let created = catalog.create_table("db", "events", &table_dir);
+        let table = append_ids(&catalog, &created, &[1, 2]);
+
+        let file_io = Arc::new(LocalFileIO::new());
+        let loaded =
+            StaticTable::from_metadata_location(table.metadata_location(), file_io.clone())
+                .unwrap();
+        assert_eq!("static.events_v2", loaded.ident().to_string());
+        assert_eq!(table.metadata_location(), loaded.metadata_location());
+        assert_eq!(
+            table.metadata().current_snapshot_id,
+            loaded.metadata().current_snapshot_id
+        );
+        let files = loaded.scan().plan_files().unwrap();
+        assert_eq!(1, files.tasks().len());
+
+        // Previous versions can be loaded as well
+        let previous =
+            StaticTable::from_metadata_location(created.metadata_location(), file_io).unwrap();
+        assert!(previous.metadata().current_snapshot().is_none());
+
+        // Nothing can be written through static tables
+        let named = loaded.with_ident("db.events".parse().unwrap());
+        assert_eq!("db.events", named.ident().to_string());
+        let error = append(&catalog, named.table());
+        assert!(
+            matches!(error, Err(IcebergError::ReadOnly(_))),
+            "{:?}",
+            error
+        );
+    }
+
+    fn append(catalog: &TestCatalog, table: &Table) -> Result<Table> {
+        let mut writer = PartitionedWriter::for_table(table)?;
+        writer.write(&ids_batch(&[3]))?;
+        table
+            .new_append()
+            .add_files(writer.close()?)
+            .commit(catalog)
+    }
+
+    #[test]
+    fn test_default_ident() {
+        for (location, name) in [
+            ("file:/warehouse/db.db/t/metadata/v1.metadata.json", "t"),
+            ("s3://bucket/t/metadata/00001-a.metadata.json", "t"),
+            ("v1.metadata.json", "table"),
+        ] {
+            assert_eq!(name, default_ident(location).unwrap().name());
+        }
+    }
+}
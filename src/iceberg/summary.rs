@@ -0,0 +1,97 @@
+// Size summaries of tables: the record, file and byte counts of a snapshot, in total and per
+// partition, so that dashboards can report the size of tables without reading data files. They
+// are aggregated from the partition statistics file of the snapshot when there is one and from its
+// manifests otherwise
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::operations::statistics::read_or_compute_partition_statistics;
+use crate::iceberg::spec::partition_statistics::PartitionStatistics;
+use crate::iceberg::table::Table;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableSummary {
+    // None for tables without snapshots, whose counts are all zero
+    pub snapshot_id: Option<i64>,
+    pub data_record_count: i64,
+    pub data_file_count: i64,
+    pub total_data_file_size_in_bytes: i64,
+    pub position_delete_record_count: i64,
+    pub position_delete_file_count: i64,
+    pub equality_delete_record_count: i64,
+    pub equality_delete_file_count: i64,
+    // Counts of the partitions with live files, typed with the unified partition type of the
+    // table. Unpartitioned tables have a single partition without values
+    pub partitions: Vec<PartitionStatistics>,
+}
+
+impl Table {
+    // Summary of the current snapshot
+    pub fn summary(&self) -> Result<TableSummary> {
+        match self.metadata().current_snapshot_id {
+            Some(snapshot_id) => self.snapshot_summary(snapshot_id),
+            None => Ok(TableSummary::default()),
+        }
+    }
+
+    pub fn snapshot_summary(&self, snapshot_id: i64) -> Result<TableSummary> {
+        let snapshot = self
+            .metadata()
+            .snapshot_by_id(snapshot_id)
+            .ok_or_else(|| IcebergError::NotFound(format!("Snapshot {}", snapshot_id)))?;
+        let partitions = read_or_compute_partition_statistics(self, snapshot)?;
+        let mut summary = TableSummary {
+            snapshot_id: Some(snapshot_id),
+            ..TableSummary::default()
+        };
+        for partition in &partitions {
+            summary.data_record_count += partition.data_record_count;
+            summary.data_file_count += i64::from(partition.data_file_count);
+            summary.total_data_file_size_in_bytes += partition.total_data_file_size_in_bytes;
+            summary.position_delete_record_count += partition.position_delete_record_count;
+            summary.position_delete_file_count += i64::from(partition.position_delete_file_count);
+            summary.equality_delete_record_count += partition.equality_delete_record_count;
+            summary.equality_delete_file_count += i64::from(partition.equality_delete_file_count);
+        }
+        summary.partitions = partitions;
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+
+    #[test]
+    fn test_summary() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        assert_eq!(TableSummary::default(), table.summary().unwrap());
+
+        let table = append_ids(&catalog, &table, &[1, 2, 3]);
+        let first = table.metadata().current_snapshot_id.unwrap();
+        let table = append_ids(&catalog, &table, &[4]);
+        let summary = table.summary().unwrap();
+        assert_eq!(table.metadata().current_snapshot_id, summary.snapshot_id);
+        assert_eq!(4, summary.data_record_count);
+        assert_eq!(2, summary.data_file_count);
+        let snapshot = table.metadata().current_snapshot().unwrap();
+        let manifests = table.manifests(snapshot).unwrap();
+        let size: i64 = manifests
+            .iter()
+            .flat_map(|manifest| table.manifest_entries(manifest).unwrap())
+            .map(|entry| entry.data_file.file_size_in_bytes)
+            .sum();
+        assert_eq!(size, summary.total_data_file_size_in_bytes);
+        assert_eq!(0, summary.position_delete_file_count);
+        assert_eq!(1, summary.partitions.len());
+        assert_eq!(4, summary.partitions[0].data_record_count);
+
+        let summary = table.snapshot_summary(first).unwrap();
+        assert_eq!((3, 1), (summary.data_record_count, summary.data_file_count));
+        assert!(matches!(
+            table.snapshot_summary(-1),
+            Err(IcebergError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,632 @@
+use std::sync::Arc;
+
+use crate::iceberg::cache::MetadataCache;
+use crate::iceberg::changelog::ChangelogScan;
+use crate::iceberg::encryption::{
+    decrypt_manifest, EncryptionManager, KeyManagementClient, StandardEncryptionManager,
+};
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::expr::Predicate;
+use crate::iceberg::ident::{Namespace, TableIdent};
+use crate::iceberg::io::FileIO;
+use crate::iceberg::metadata_tables::{MetadataTable, MetadataTableType};
+use crate::iceberg::metrics::{LoggingMetricsReporter, MetricsReporter};
+use crate::iceberg::operations::append::FastAppend;
+use crate::iceberg::operations::cherry_pick::CherryPick;
+use crate::iceberg::operations::expire::ExpireSnapshots;
+use crate::iceberg::operations::orphan::DeleteOrphanFiles;
+use crate::iceberg::operations::overwrite::{OverwriteFiles, ReplacePartitions};
+use crate::iceberg::operations::refs::ManageRefs;
+use crate::iceberg::operations::rewrite_files::RewriteDataFiles;
+use crate::iceberg::operations::rewrite_manifests::RewriteManifests;
+use crate::iceberg::operations::row_delta::RowDelta;
+use crate::iceberg::operations::sort_order::UpdateSortOrder;
+use crate::iceberg::operations::statistics::{
+    read_or_compute_partition_statistics, ComputePartitionStatistics, ComputeTableStatistics,
+    UpdatePartitionStatistics, UpdateStatistics,
+};
+use crate::iceberg::operations::transaction::Transaction;
+use crate::iceberg::properties::TableProperties;
+use crate::iceberg::rows::{read_rows, RowIter};
+use crate::iceberg::scan::{ScanLimits, TableScan};
+use crate::iceberg::spec::manifest::{read_manifest, ManifestEntry};
+use crate::iceberg::spec::manifest_list::{read_manifest_list, ManifestListV2};
+use crate::iceberg::spec::partition_statistics::PartitionStatistics;
+use crate::iceberg::spec::snapshot::SnapshotV2;
+use crate::iceberg::spec::snapshot_summary::WAP_ID;
+use crate::iceberg::spec::table_metadata::{TableMetadata, TableMetadataV2, MAIN_BRANCH};
+
+// Selects a snapshot of a table, current or historical
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotSelector {
+    Current,
+    Id(i64),
+    // The snapshot that was current at the given time, according to the snapshot log
+    AsOfTimestamp(i64),
+    // The snapshot a branch or tag points to
+    Ref(String),
+}
+
+// What can be done with a table, depending on the format version of its metadata
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableCapability {
+    Scan,
+    Commit,
+}
+
+// A table as of the metadata file it was loaded from. V1 metadata is upgraded on load so that
+// readers only deal with V2 structures, the original format version is kept for writers
+#[derive(Debug, Clone)]
+pub struct Table {
+    ident: TableIdent,
+    format_version: i32,
+    metadata: TableMetadataV2,
+    metadata_location: String,
+    file_io: Arc<dyn FileIO>,
+    scan_limits: ScanLimits,
+    kms: Option<Arc<dyn KeyManagementClient>>,
+    encryption: Option<Arc<dyn EncryptionManager>>,
+    cache: Option<Arc<MetadataCache>>,
+    metrics: Arc<dyn MetricsReporter>,
+}
+
+impl Table {
+    pub fn try_new(
+        ident: TableIdent,
+        metadata: TableMetadata,
+        metadata_location: String,
+        file_io: Arc<dyn FileIO>,
+    ) -> Result<Self> {
+        Ok(Table {
+            ident,
+            format_version: metadata.format_version(),
+            metadata: metadata.into_v2()?,
+            metadata_location,
+            file_io,
+            scan_limits: ScanLimits::default(),
+            kms: None,
+            encryption: None,
+            cache: None,
+            metrics: Arc::new(LoggingMetricsReporter),
+        })
+    }
+
+    // Reads the metadata file at the given location
+    #[tracing::instrument(level = "debug", skip(file_io), fields(table = %ident))]
+    pub fn load(
+        ident: TableIdent,
+        metadata_location: String,
+        file_io: Arc<dyn FileIO>,
+    ) -> Result<Self> {
+        let metadata: TableMetadata = serde_json::from_slice(&file_io.read(&metadata_location)?)?;
+        Self::try_new(ident, metadata, metadata_location, file_io)
+    }
+
+    // Loads the metadata file at the given location, a later version of the metadata of this
+    // table. Schemas, partition specs and snapshots already decoded are reused, see
+    // TableMetadata::from_json_reusing. The settings of the handle are kept
+    #[tracing::instrument(level = "debug", skip(self), fields(table = %self.ident))]
+    pub fn refresh(&self, metadata_location: &str) -> Result<Self> {
+        if metadata_location == self.metadata_location {
+            return Ok(self.clone());
+        }
+        let data = self.file_io.read(metadata_location)?;
+        let metadata = TableMetadata::from_json_reusing(&data, &self.metadata)?;
+        Ok(Table {
+            format_version: metadata.format_version(),
+            metadata: metadata.into_v2()?,
+            metadata_location: metadata_location.to_string(),
+            ..self.clone()
+        })
+    }
+
+    pub fn ident(&self) -> &TableIdent {
+        &self.ident
+    }
+
+    pub fn namespace(&self) -> &Namespace {
+        self.ident.namespace()
+    }
+
+    pub fn name(&self) -> &str {
+        self.ident.name()
+    }
+
+    pub fn format_version(&self) -> i32 {
+        self.format_version
+    }
+
+    // V1 and V3 tables are read only, commits would lose fields or miss required updates
+    pub fn supports(&self, capability: TableCapability) -> bool {
+        match capability {
+            TableCapability::Scan => true,
+            TableCapability::Commit => self.format_version == 2,
+        }
+    }
+
+    pub fn metadata(&self) -> &TableMetadataV2 {
+        &self.metadata
+    }
+
+    pub fn properties(&self) -> TableProperties {
+        TableProperties::from_metadata(&self.metadata)
+    }
+
+    pub fn metadata_location(&self) -> &str {
+        &self.metadata_location
+    }
+
+    pub fn file_io(&self) -> &Arc<dyn FileIO> {
+        &self.file_io
+    }
+
+    pub(crate) fn with_ident(mut self, ident: TableIdent) -> Self {
+        self.ident = ident;
+        self
+    }
+
+    // The same table read and written through another FileIO
+    pub fn with_file_io(mut self, file_io: Arc<dyn FileIO>) -> Self {
+        self.file_io = file_io;
+        self
+    }
+
+    // Guardrails enforced when planning scans of this handle
+    pub fn with_scan_limits(mut self, scan_limits: ScanLimits) -> Self {
+        self.scan_limits = scan_limits;
+        self
+    }
+
+    pub fn scan_limits(&self) -> &ScanLimits {
+        &self.scan_limits
+    }
+
+    pub fn snapshot_at(&self, selector: &SnapshotSelector) -> Result<&SnapshotV2> {
+        let metadata = &self.metadata;
+        let snapshot_id = match selector {
+            SnapshotSelector::Current => metadata.current_snapshot_id.filter(|id| *id != -1),
+            SnapshotSelector::Id(snapshot_id) => Some(*snapshot_id),
+            SnapshotSelector::AsOfTimestamp(timestamp_ms) => metadata
+                .snapshot_log
+                .iter()
+                .flatten()
+                .filter(|entry| entry.timestamp_ms <= *timestamp_ms)
+                .max_by_key(|entry| entry.timestamp_ms)
+                .map(|entry| entry.snapshot_id),
+            SnapshotSelector::Ref(name) => match metadata.refs.as_ref().and_then(|r| r.get(name)) {
+                Some(snapshot_ref) => Some(snapshot_ref.snapshot_id),
+                None if name == MAIN_BRANCH => metadata.current_snapshot_id,
+                None => {
+                    return Err(IcebergError::NotFound(format!(
+                        "Ref {} of table {}",
+                        name, self.ident
+                    )))
+                }
+            },
+        };
+        snapshot_id
+            .and_then(|snapshot_id| metadata.snapshot_by_id(snapshot_id))
+            .ok_or_else(|| {
+                IcebergError::NotFound(format!("Snapshot {:?} of table {}", selector, self.ident))
+            })
+    }
+
+    // Latest snapshot staged with the write id, e.g. to audit it before publishing it
+    pub fn staged_snapshot(&self, wap_id: &str) -> Result<&SnapshotV2> {
+        self.metadata
+            .snapshots
+            .iter()
+            .flatten()
+            .filter(|snapshot| {
+                snapshot.summary.rest.get(WAP_ID).map(String::as_str) == Some(wap_id)
+            })
+            .max_by_key(|snapshot| snapshot.sequence_number)
+            .ok_or_else(|| {
+                IcebergError::NotFound(format!(
+                    "Snapshot staged with wap.id {} in table {}",
+                    wap_id, self.ident
+                ))
+            })
+    }
+
+    // Client unwrapping the keys of data files encrypted with Parquet modular encryption
+    pub fn with_key_management_client(mut self, kms: Arc<dyn KeyManagementClient>) -> Self {
+        self.kms = Some(kms);
+        self
+    }
+
+    pub fn key_management_client(&self) -> Option<&Arc<dyn KeyManagementClient>> {
+        self.kms.as_ref()
+    }
+
+    // Manager decrypting the manifests of the table, instead of the standard one of tables with
+    // a key management client
+    pub fn with_encryption_manager(mut self, encryption: Arc<dyn EncryptionManager>) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    pub fn encryption_manager(&self) -> Option<Arc<dyn EncryptionManager>> {
+        self.encryption.clone().or_else(|| {
+            let kms = self.kms.clone()?;
+            let manager = StandardEncryptionManager::for_table(kms, &self.metadata)?;
+            Some(Arc::new(manager) as Arc<dyn EncryptionManager>)
+        })
+    }
+
+    // Receives the metrics of the scans planned and the snapshots committed through this handle
+    pub fn with_metrics_reporter(mut self, metrics: Arc<dyn MetricsReporter>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn metrics_reporter(&self) -> &Arc<dyn MetricsReporter> {
+        &self.metrics
+    }
+
+    // Cache the manifest lists and manifests of the table are read through
+    pub fn with_metadata_cache(mut self, cache: Arc<MetadataCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    // Reads the manifest list of a snapshot of the table
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(table = %self.ident, manifest_list = %snapshot.manifest_list)
+    )]
+    pub fn manifests(&self, snapshot: &SnapshotV2) -> Result<Vec<ManifestListV2>> {
+        match &self.cache {
+            Some(cache) => Ok(cache
+                .manifest_list(&snapshot.manifest_list, self.file_io.as_ref())?
+                .as_ref()
+                .clone()),
+            None => read_manifest_list(&self.file_io.read(&snapshot.manifest_list)?),
+        }
+    }
+
+    // Reads the entries of a manifest, with the partition values typed by the partition spec
+    // the manifest was written with
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(table = %self.ident, manifest = %manifest.manifest_path)
+    )]
+    pub fn manifest_entries(&self, manifest: &ManifestListV2) -> Result<Vec<ManifestEntry>> {
+        let spec = self
+            .metadata
+            .partition_spec_by_id(manifest.partition_spec_id)
+            .ok_or_else(|| {
+                IcebergError::Invalid(format!(
+                    "Partition spec {} of manifest {} is missing from table metadata",
+                    manifest.partition_spec_id, manifest.manifest_path
+                ))
+            })?;
+        let partition_type =
+            spec.partition_type(self.metadata.current_schema()?.schema(), &self.metadata)?;
+        let encryption = self.encryption_manager();
+        match &self.cache {
+            Some(cache) => Ok(cache
+                .manifest(
+                    manifest,
+                    &partition_type,
+                    self.file_io.as_ref(),
+                    encryption.as_deref(),
+                )?
+                .as_ref()
+                .clone()),
+            None => {
+                let data = self.file_io.read(&manifest.manifest_path)?;
+                let data = decrypt_manifest(encryption.as_deref(), manifest, &data)?;
+                read_manifest(manifest, &data, &partition_type)
+            }
+        }
+    }
+
+    pub fn scan(&self) -> TableScan<'_> {
+        TableScan::new(self)
+    }
+
+    // Rows of the current snapshot matching the filter, with the given top-level columns or all
+    // of them. Unlike scans, the filter applies to each row and not only to data files
+    pub fn rows(&self, filter: Option<Predicate>, columns: Option<&[&str]>) -> Result<RowIter> {
+        read_rows(self, filter, columns)
+    }
+
+    // Scan of the data files appended after `from_snapshot` up to `to_snapshot` included, see
+    // TableScan::appends_between
+    pub fn incremental_scan(&self, from_snapshot: i64, to_snapshot: i64) -> TableScan<'_> {
+        self.scan().appends_between(from_snapshot, to_snapshot)
+    }
+
+    // Rows inserted and deleted by the snapshots after `from_snapshot` up to `to_snapshot`
+    // included
+    pub fn changelog(&self, from_snapshot: i64, to_snapshot: i64) -> ChangelogScan<'_> {
+        ChangelogScan::new(self, from_snapshot, to_snapshot)
+    }
+
+    // Snapshots, history, manifests, files or partitions of the table as Arrow record batches
+    pub fn metadata_table(&self, table_type: MetadataTableType) -> MetadataTable<'_> {
+        MetadataTable::new(self, table_type)
+    }
+
+    pub fn new_append(&self) -> FastAppend<'_> {
+        FastAppend::new(self)
+    }
+
+    // Replaces data files of the current snapshot with new ones
+    pub fn new_overwrite(&self) -> OverwriteFiles<'_> {
+        OverwriteFiles::new(self)
+    }
+
+    // Replaces the partitions of the added files, see ReplacePartitions
+    pub fn new_replace_partitions(&self) -> ReplacePartitions<'_> {
+        ReplacePartitions::new(self)
+    }
+
+    // Compacts the small data files of the current snapshot
+    pub fn rewrite_data_files(&self) -> RewriteDataFiles<'_> {
+        RewriteDataFiles::new(self)
+    }
+
+    // Compacts the manifests of the current snapshot
+    pub fn rewrite_manifests(&self) -> RewriteManifests<'_> {
+        RewriteManifests::new(self)
+    }
+
+    // Sets or removes the statistics files of snapshots
+    pub fn update_statistics(&self) -> UpdateStatistics<'_> {
+        UpdateStatistics::new(self)
+    }
+
+    // Computes NDV statistics of the current snapshot into a Puffin file
+    pub fn compute_statistics(&self) -> ComputeTableStatistics<'_> {
+        ComputeTableStatistics::new(self)
+    }
+
+    // Sets or removes the partition statistics files of snapshots
+    pub fn update_partition_statistics(&self) -> UpdatePartitionStatistics<'_> {
+        UpdatePartitionStatistics::new(self)
+    }
+
+    // Computes the partition statistics of the current snapshot into a partition statistics
+    // file. Maintenance actions keep them up to date once computed
+    pub fn compute_partition_statistics(&self) -> ComputePartitionStatistics<'_> {
+        ComputePartitionStatistics::new(self)
+    }
+
+    // Record and file counts of the partitions of the current snapshot, from its partition
+    // statistics file when there is one
+    pub fn partition_statistics(&self) -> Result<Vec<PartitionStatistics>> {
+        match self.metadata.current_snapshot() {
+            Some(snapshot) => read_or_compute_partition_statistics(self, snapshot),
+            None => Ok(vec![]),
+        }
+    }
+
+    // Commits delete files and new data files together, see RowDelta
+    pub fn new_row_delta(&self) -> RowDelta<'_> {
+        RowDelta::new(self)
+    }
+
+    // Creates, moves and drops branches and tags
+    pub fn manage_refs(&self) -> ManageRefs<'_> {
+        ManageRefs::new(self)
+    }
+
+    // Rolls the table back to an ancestor of its current snapshot, see ManageRefs::rollback_to
+    pub fn rollback_to(&self, snapshot_id: i64) -> ManageRefs<'_> {
+        self.manage_refs().rollback_to(snapshot_id)
+    }
+
+    pub fn set_current_snapshot(&self, snapshot_id: i64) -> ManageRefs<'_> {
+        self.manage_refs().set_current_snapshot(snapshot_id)
+    }
+
+    // Publishes a snapshot that isn't an ancestor of the current one, see CherryPick
+    pub fn cherry_pick(&self, snapshot_id: i64) -> CherryPick<'_> {
+        CherryPick::snapshot(self, snapshot_id)
+    }
+
+    // Publishes the latest snapshot staged with the write id
+    pub fn publish_wap_id(&self, wap_id: &str) -> CherryPick<'_> {
+        CherryPick::wap_id(self, wap_id)
+    }
+
+    // Expires snapshots older than the timestamp, keeping at least the last `retain_last`
+    // snapshots of every branch. See ExpireSnapshots for the retention rules
+    pub fn expire_snapshots(&self, older_than_ms: i64, retain_last: usize) -> ExpireSnapshots<'_> {
+        ExpireSnapshots::new(self, older_than_ms, retain_last)
+    }
+
+    // Deletes the files below the table location not referenced by its metadata and last
+    // modified before the timestamp. See DeleteOrphanFiles
+    pub fn delete_orphan_files(&self, older_than_ms: i64) -> DeleteOrphanFiles<'_> {
+        DeleteOrphanFiles::new(self, older_than_ms)
+    }
+
+    // Adds a sort order or switches the default sort order
+    pub fn update_sort_order(&self) -> UpdateSortOrder<'_> {
+        UpdateSortOrder::new(self)
+    }
+
+    // Commits metadata changes, such as column docs, together as a single new metadata version
+    pub fn new_transaction(&self) -> Transaction<'_> {
+        Transaction::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::iceberg::catalog::IcebergCatalog;
+    use crate::iceberg::encryption::ENCRYPTION_KEY_ID_PROPERTY;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog, XorKms};
+
+    #[test]
+    fn test_snapshot_at() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        assert!(table.snapshot_at(&SnapshotSelector::Current).is_err());
+
+        catalog.set_time_ms(2_000_000_000_000);
+        let table = append_ids(&catalog, &table, &[1]);
+        let first = table.metadata().current_snapshot_id.unwrap();
+        catalog.set_time_ms(2_000_000_001_000);
+        let table = append_ids(&catalog, &table, &[2]);
+        let second = table.metadata().current_snapshot_id.unwrap();
+        let table = table
+            .manage_refs()
+            .create_tag("v1", first)
+            .commit(&catalog)
+            .unwrap();
+
+        let at = |selector: SnapshotSelector| table.snapshot_at(&selector).map(|s| s.snapshot_id);
+        assert_eq!(second, at(SnapshotSelector::Current).unwrap());
+        assert_eq!(first, at(SnapshotSelector::Id(first)).unwrap());
+        assert_eq!(
+            first,
+            at(SnapshotSelector::AsOfTimestamp(2_000_000_000_999)).unwrap()
+        );
+        assert_eq!(
+            second,
+            at(SnapshotSelector::AsOfTimestamp(2_000_000_001_000)).unwrap()
+        );
+        assert_eq!(first, at(SnapshotSelector::Ref("v1".to_string())).unwrap());
+        assert_eq!(
+            second,
+            at(SnapshotSelector::Ref(MAIN_BRANCH.to_string())).unwrap()
+        );
+
+        assert!(at(SnapshotSelector::Id(42)).is_err());
+        assert!(at(SnapshotSelector::AsOfTimestamp(1_000)).is_err());
+        assert!(at(SnapshotSelector::Ref("missing".to_string())).is_err());
+    }
+
+    #[cfg(feature = "format-v3")]
+    #[test]
+    fn test_v3_table_is_read_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = append_ids(&catalog, &catalog.create_table("db", "t", dir.path()), &[1]);
+        assert!(table.supports(TableCapability::Commit));
+
+        let mut json = serde_json::to_value(table.metadata()).unwrap();
+        json["format-version"] = 3.into();
+        json["next-row-id"] = 1.into();
+        let location = format!("{}/metadata/v3.metadata.json", table.metadata().location);
+        table
+            .file_io()
+            .write(&location, serde_json::to_vec(&json).unwrap().into())
+            .unwrap();
+        let v3 = Table::load("db.t".parse().unwrap(), location, table.file_io().clone()).unwrap();
+        assert_eq!(3, v3.format_version());
+        assert!(v3.supports(TableCapability::Scan));
+        assert!(!v3.supports(TableCapability::Commit));
+        assert_eq!(
+            1,
+            v3.scan().plan_files().unwrap().to_arrow().unwrap().count()
+        );
+
+        let error = crate::iceberg::catalog::write_metadata_file(
+            v3.file_io().as_ref(),
+            Some(&v3),
+            v3.metadata().clone(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, IcebergError::Unsupported(_)), "{}", error);
+    }
+
+    #[test]
+    fn test_refresh_reuses_decoded_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let stale = append_ids(&catalog, &table, &[1]);
+        let current = append_ids(&catalog, &stale, &[2]);
+
+        let refreshed = catalog
+            .refresh_table(
+                &stale
+                    .clone()
+                    .with_scan_limits(ScanLimits::new().with_max_files(5)),
+            )
+            .unwrap();
+        assert_eq!(current.metadata_location(), refreshed.metadata_location());
+        assert_eq!(current.metadata(), refreshed.metadata());
+        assert_eq!(
+            &ScanLimits::new().with_max_files(5),
+            refreshed.scan_limits()
+        );
+
+        // Snapshots known to the handle are not decoded again
+        let mut marked = stale.clone();
+        let mut metadata = marked.metadata.clone();
+        let snapshot = &mut metadata.snapshots.as_mut().unwrap()[0];
+        snapshot
+            .summary
+            .rest
+            .insert("decoded".to_string(), "before".to_string());
+        marked.metadata = metadata;
+        let refreshed = catalog.refresh_table(&marked).unwrap();
+        let snapshots = refreshed.metadata().snapshots.as_ref().unwrap();
+        assert_eq!(2, snapshots.len());
+        assert!(snapshots[0].summary.rest.contains_key("decoded"));
+        assert!(!snapshots[1].summary.rest.contains_key("decoded"));
+        assert_eq!(
+            current.metadata_location(),
+            catalog
+                .refresh_table(&refreshed)
+                .unwrap()
+                .metadata_location()
+        );
+    }
+
+    #[test]
+    fn test_read_encrypted_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = TestCatalog::new();
+        let table = catalog.create_table("db", "t", dir.path());
+        let table = append_ids(&catalog, &table, &[1, 2]);
+        let mut metadata = table.metadata().clone();
+        metadata.properties = Some(HashMap::from([(
+            ENCRYPTION_KEY_ID_PROPERTY.to_string(),
+            "master".to_string(),
+        )]));
+        let table = catalog.commit_table(&table, metadata).unwrap();
+        let snapshot = table.snapshot_at(&SnapshotSelector::Current).unwrap();
+        let manifest = table.manifests(snapshot).unwrap().remove(0);
+        let expected = table.manifest_entries(&manifest).unwrap();
+
+        let key_metadata = XorKms::wrap_key(b"0123456789012345", "master");
+        let manager = StandardEncryptionManager::new(Arc::new(XorKms), "master");
+        let data = table.file_io().read(&manifest.manifest_path).unwrap();
+        let encrypted = ManifestListV2 {
+            manifest_path: format!("{}.encrypted", manifest.manifest_path),
+            key_metadata: Some(key_metadata.clone()),
+            ..manifest
+        };
+        table
+            .file_io()
+            .write(
+                &encrypted.manifest_path,
+                Bytes::from(manager.encrypt(&data, &key_metadata).unwrap()),
+            )
+            .unwrap();
+
+        assert!(matches!(
+            table.manifest_entries(&encrypted),
+            Err(IcebergError::Unsupported(_))
+        ));
+        let table = table.with_key_management_client(Arc::new(XorKms));
+        assert_eq!(expected, table.manifest_entries(&encrypted).unwrap());
+        let cached = table.with_metadata_cache(Arc::new(MetadataCache::new(1 << 20)));
+        assert_eq!(expected, cached.manifest_entries(&encrypted).unwrap());
+    }
+}
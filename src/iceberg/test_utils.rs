@@ -0,0 +1,272 @@
+// Helpers building tables on the local filesystem for tests
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use arrow::array::{Int64Array, RecordBatch, StringArray};
+use uuid::Uuid;
+
+use crate::iceberg::arrow::schema_to_arrow;
+use crate::iceberg::catalog::{write_metadata_file, IcebergCatalog};
+use crate::iceberg::encryption::KeyManagementClient;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::ident::{Namespace, TableIdent};
+use crate::iceberg::io::{FileIO, LocalFileIO};
+use crate::iceberg::spec::partition_spec::PartitionSpec;
+use crate::iceberg::spec::schema::{
+    IcebergSchemaV2, IcebergType, PrimitiveType, StructField, StructType,
+};
+use crate::iceberg::spec::sort_orders::SortOrders;
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+use crate::iceberg::table::Table;
+use crate::iceberg::writer::partitioned::PartitionedWriter;
+
+// Schema of test tables: a required id and an optional data column
+pub fn test_schema() -> StructType {
+    StructType {
+        fields: vec![
+            StructField {
+                id: 1,
+                name: "id".to_string(),
+                required: true,
+                field_type: IcebergType::Primitive(PrimitiveType::Long),
+                doc: None,
+                initial_default: None,
+                write_default: None,
+            },
+            StructField {
+                id: 2,
+                name: "data".to_string(),
+                required: false,
+                field_type: IcebergType::Primitive(PrimitiveType::String),
+                doc: None,
+                initial_default: None,
+                write_default: None,
+            },
+        ],
+    }
+}
+
+pub fn ids_batch(ids: &[i64]) -> RecordBatch {
+    let data: Vec<String> = ids.iter().map(|id| format!("row-{}", id)).collect();
+    RecordBatch::try_new(
+        Arc::new(schema_to_arrow(&test_schema()).unwrap()),
+        vec![
+            Arc::new(Int64Array::from(ids.to_vec())),
+            Arc::new(StringArray::from(data)),
+        ],
+    )
+    .unwrap()
+}
+
+// Creates an unpartitioned table without snapshots in the given directory
+pub fn create_table(dir: &Path) -> Table {
+    let metadata = new_metadata(dir, test_schema());
+    write_metadata(Arc::new(LocalFileIO::new()), None, metadata)
+}
+
+fn new_metadata(dir: &Path, schema: StructType) -> TableMetadataV2 {
+    let location = format!("file:{}", dir.display());
+    TableMetadataV2 {
+        format_version: 2,
+        table_uuid: Uuid::new_v4(),
+        location: location.clone(),
+        last_sequence_number: 0,
+        last_updated_ms: 1665194853343,
+        last_column_id: schema
+            .fields
+            .iter()
+            .map(|field| field.id)
+            .max()
+            .unwrap_or(0),
+        schemas: vec![IcebergSchemaV2::new(0, None, schema)],
+        current_schema_id: 0,
+        partition_specs: vec![PartitionSpec {
+            spec_id: 0,
+            fields: vec![],
+        }],
+        default_spec_id: 0,
+        last_partition_id: 999,
+        properties: None,
+        current_snapshot_id: None,
+        snapshots: None,
+        snapshot_log: None,
+        metadata_log: None,
+        sort_orders: vec![SortOrders {
+            order_id: 0,
+            fields: vec![],
+        }],
+        default_sort_order_id: 0,
+        refs: None,
+        statistics: None,
+        partition_statistics: None,
+    }
+}
+
+// Commits a snapshot appending the batch, with one data file per partition
+pub fn append(table: &Table, batch: &RecordBatch) -> Table {
+    let catalog = TestCatalog::new();
+    catalog.register(table);
+    let mut writer = PartitionedWriter::for_table(table).unwrap();
+    writer.write(batch).unwrap();
+    table
+        .new_append()
+        .add_files(writer.close().unwrap())
+        .commit(&catalog)
+        .unwrap()
+}
+
+// Commits a snapshot appending the ids through the catalog
+pub fn append_ids(catalog: &TestCatalog, table: &Table, ids: &[i64]) -> Table {
+    let mut writer = PartitionedWriter::for_table(table).unwrap();
+    writer.write(&ids_batch(ids)).unwrap();
+    table
+        .new_append()
+        .add_files(writer.close().unwrap())
+        .commit(catalog)
+        .unwrap()
+}
+
+fn write_metadata(
+    file_io: Arc<dyn FileIO>,
+    base: Option<&Table>,
+    metadata: TableMetadataV2,
+) -> Table {
+    let metadata_location = write_metadata_file(file_io.as_ref(), base, metadata).unwrap();
+    let ident = base.map_or_else(|| "db.table".parse().unwrap(), |base| base.ident().clone());
+    Table::load(ident, metadata_location, file_io).unwrap()
+}
+
+// Catalog keeping the metadata locations of tables in memory
+#[derive(Debug, Default)]
+pub struct TestCatalog {
+    tables: Mutex<BTreeMap<TableIdent, String>>,
+    // Overrides the clock of commits
+    time_ms: Mutex<Option<i64>>,
+}
+
+impl TestCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Creates an unpartitioned table with the test schema in the given directory
+    pub fn create_table(&self, namespace: &str, name: &str, dir: &Path) -> Table {
+        self.create_table_with_schema(namespace, name, dir, test_schema())
+    }
+
+    pub fn create_table_with_schema(
+        &self,
+        namespace: &str,
+        name: &str,
+        dir: &Path,
+        schema: StructType,
+    ) -> Table {
+        let ident = TableIdent::try_new(namespace.parse().unwrap(), name).unwrap();
+        let file_io = Arc::new(LocalFileIO::new());
+        let location =
+            write_metadata_file(file_io.as_ref(), None, new_metadata(dir, schema)).unwrap();
+        self.tables
+            .lock()
+            .unwrap()
+            .insert(ident.clone(), location.clone());
+        Table::load(ident, location, file_io).unwrap()
+    }
+
+    pub fn set_time_ms(&self, time_ms: i64) {
+        *self.time_ms.lock().unwrap() = Some(time_ms);
+    }
+
+    fn location(&self, ident: &TableIdent) -> Result<String> {
+        self.tables
+            .lock()
+            .unwrap()
+            .get(ident)
+            .cloned()
+            .ok_or_else(|| IcebergError::NotFound(format!("Table {}", ident)))
+    }
+
+    // Tracks an existing table at its current metadata location
+    pub fn register(&self, table: &Table) {
+        self.tables
+            .lock()
+            .unwrap()
+            .insert(table.ident().clone(), table.metadata_location().to_string());
+    }
+}
+
+impl IcebergCatalog for TestCatalog {
+    fn load_table(&self, ident: &TableIdent) -> Result<Table> {
+        Table::load(
+            ident.clone(),
+            self.location(ident)?,
+            Arc::new(LocalFileIO::new()),
+        )
+    }
+
+    fn refresh_table(&self, table: &Table) -> Result<Table> {
+        table.refresh(&self.location(table.ident())?)
+    }
+
+    fn commit_table(&self, base: &Table, metadata: TableMetadataV2) -> Result<Table> {
+        let mut tables = self.tables.lock().unwrap();
+        let ident = base.ident();
+        if tables.get(ident).map(String::as_str) != Some(base.metadata_location()) {
+            return Err(IcebergError::CommitConflict(format!(
+                "Table {} changed since it was loaded",
+                ident
+            )));
+        }
+        let location = write_metadata_file(base.file_io().as_ref(), Some(base), metadata)?;
+        tables.insert(ident.clone(), location.clone());
+        Table::load(ident.clone(), location, base.file_io().clone())
+    }
+
+    fn current_time_ms(&self) -> Result<i64> {
+        Ok(self
+            .time_ms
+            .lock()
+            .unwrap()
+            .unwrap_or_else(crate::iceberg::operations::current_time_ms))
+    }
+
+    fn list_tables(&self, namespace: &Namespace) -> Result<Vec<String>> {
+        // Keys are ordered by namespace and name
+        Ok(self
+            .tables
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|ident| ident.namespace() == namespace)
+            .map(|ident| ident.name().to_string())
+            .collect())
+    }
+
+    fn drop_table(&self, ident: &TableIdent, _purge: bool) -> Result<()> {
+        self.tables
+            .lock()
+            .unwrap()
+            .remove(ident)
+            .map(|_| ())
+            .ok_or_else(|| IcebergError::NotFound(format!("Table {}", ident)))
+    }
+}
+
+// Key management "service" wrapping keys by XOR with the bytes of the key id
+#[derive(Debug)]
+pub struct XorKms;
+
+impl XorKms {
+    pub fn wrap_key(key: &[u8], key_id: &str) -> Vec<u8> {
+        key.iter()
+            .zip(key_id.bytes().cycle())
+            .map(|(k, w)| k ^ w)
+            .collect()
+    }
+}
+
+impl KeyManagementClient for XorKms {
+    fn unwrap_key(&self, wrapped_key: &[u8], wrapping_key_id: &str) -> Result<Vec<u8>> {
+        Ok(Self::wrap_key(wrapped_key, wrapping_key_id))
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use crate::iceberg::error::Result;
+use crate::iceberg::ident::{Namespace, TableIdent};
+use crate::iceberg::io::FileIO;
+use crate::iceberg::spec::view_metadata::{ViewMetadata, ViewVersion};
+
+// A view loaded from its current metadata file, see IcebergCatalog::load_view. Views are read
+// only for now: their SQL and schema can be introspected but not changed
+#[derive(Debug, Clone)]
+pub struct View {
+    ident: TableIdent,
+    metadata: ViewMetadata,
+    metadata_location: String,
+}
+
+impl View {
+    pub fn new(ident: TableIdent, metadata: ViewMetadata, metadata_location: String) -> Self {
+        View {
+            ident,
+            metadata,
+            metadata_location,
+        }
+    }
+
+    // Reads the metadata file at the given location
+    pub fn load(
+        ident: TableIdent,
+        metadata_location: String,
+        file_io: Arc<dyn FileIO>,
+    ) -> Result<Self> {
+        let metadata = ViewMetadata::from_json(&file_io.read(&metadata_location)?)?;
+        Ok(Self::new(ident, metadata, metadata_location))
+    }
+
+    pub fn ident(&self) -> &TableIdent {
+        &self.ident
+    }
+
+    pub fn namespace(&self) -> &Namespace {
+        self.ident.namespace()
+    }
+
+    pub fn name(&self) -> &str {
+        self.ident.name()
+    }
+
+    pub fn metadata(&self) -> &ViewMetadata {
+        &self.metadata
+    }
+
+    pub fn metadata_location(&self) -> &str {
+        &self.metadata_location
+    }
+
+    pub fn current_version(&self) -> Result<&ViewVersion> {
+        self.metadata.current_version()
+    }
+}
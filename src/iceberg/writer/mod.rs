@@ -0,0 +1,763 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, Float32Array, Float64Array, RecordBatch};
+use arrow::compute::{cast, lexsort_to_indices, take_record_batch, SortColumn, SortOptions};
+use arrow::datatypes::{DataType, SchemaRef};
+use bytes::Bytes;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::metadata::{ParquetMetaData, ParquetMetaDataReader};
+use parquet::file::properties::{WriterProperties, DEFAULT_MAX_ROW_GROUP_SIZE, DEFAULT_PAGE_SIZE};
+use parquet::file::statistics::Statistics;
+use parquet::schema::types::ColumnPath;
+
+use crate::iceberg::arrow::{field_id, literal_from_array, literals_to_array, schema_to_arrow};
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::io::FileIO;
+use crate::iceberg::properties::TableProperties;
+use crate::iceberg::reader::row_group_offset;
+use crate::iceberg::spec::manifest::{DataContentType, DataFile, FileFormat};
+use crate::iceberg::spec::partition_spec::Transform;
+use crate::iceberg::spec::schema::{IcebergType, PrimitiveType, StructType};
+use crate::iceberg::spec::sort_orders::{Direction, NullOrder, SortOrders};
+use crate::iceberg::spec::table_metadata::TableMetadataV2;
+use crate::iceberg::spec::values::{Bound, Literal};
+
+pub mod partitioned;
+pub mod position_delete;
+
+// Writes Arrow record batches to a single Parquet data file with Iceberg field ids and returns
+// the DataFile describing it, including the column metrics used for scan planning. The file is
+// buffered in memory and handed to FileIO on close
+pub struct ParquetWriter {
+    file_io: Arc<dyn FileIO>,
+    location: String,
+    schema: SchemaRef,
+    primitive_types: HashMap<i32, PrimitiveType>,
+    partition: Vec<Option<Literal>>,
+    sort_order_id: Option<i32>,
+    writer: ArrowWriter<Vec<u8>>,
+    nan_value_counts: HashMap<i32, i64>,
+    // Length of the string and binary bounds, None for full bounds
+    bounds_truncate_length: Option<usize>,
+}
+
+// Length of the string and binary bounds of data files, in characters for strings and bytes for
+// binaries, the default of the spec's truncate metrics mode
+pub const BOUNDS_TRUNCATE_LENGTH: usize = 16;
+
+impl ParquetWriter {
+    pub fn try_new(
+        file_io: Arc<dyn FileIO>,
+        location: String,
+        schema: &StructType,
+    ) -> Result<Self> {
+        Self::try_new_with_properties(file_io, location, schema, default_writer_properties())
+    }
+
+    pub fn try_new_with_properties(
+        file_io: Arc<dyn FileIO>,
+        location: String,
+        schema: &StructType,
+        properties: WriterProperties,
+    ) -> Result<Self> {
+        let arrow_schema = Arc::new(schema_to_arrow(schema)?);
+        let writer = ArrowWriter::try_new(Vec::new(), arrow_schema.clone(), Some(properties))?;
+        Ok(ParquetWriter {
+            file_io,
+            location,
+            schema: arrow_schema,
+            primitive_types: primitive_types_by_id(schema),
+            partition: vec![],
+            sort_order_id: None,
+            writer,
+            nan_value_counts: HashMap::new(),
+            bounds_truncate_length: Some(BOUNDS_TRUNCATE_LENGTH),
+        })
+    }
+
+    // Partition values of the file, in the order of the fields of the table's partition spec
+    pub fn with_partition(mut self, partition: Vec<Option<Literal>>) -> Self {
+        self.partition = partition;
+        self
+    }
+
+    // Id of the sort order the rows of the file are written in. Callers are responsible for
+    // writing the rows sorted, see sort_batch
+    pub fn with_sort_order_id(mut self, sort_order_id: i32) -> Self {
+        self.sort_order_id = Some(sort_order_id);
+        self
+    }
+
+    // Keeps the string and binary bounds whole instead of truncating them, e.g. for the file
+    // paths of position delete files, which readers compare with the paths of data files
+    pub fn with_full_bounds(mut self) -> Self {
+        self.bounds_truncate_length = None;
+        self
+    }
+
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    // Write a batch. Columns are matched to the table schema by position and cast to the
+    // expected types where needed, e.g. when the batch lacks field id metadata
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        let batch = align_batch(batch, &self.schema)?;
+        for (field, column) in self.schema.fields().iter().zip(batch.columns()) {
+            if let (Some(id), DataType::Float32 | DataType::Float64) =
+                (field_id(field), field.data_type())
+            {
+                *self.nan_value_counts.entry(id).or_insert(0) += count_nans(column);
+            }
+        }
+        Ok(self.writer.write(&batch)?)
+    }
+
+    // Number of bytes written so far (approximate until the file is closed)
+    pub fn bytes_written(&self) -> usize {
+        self.writer.bytes_written() + self.writer.in_progress_size()
+    }
+
+    pub fn close(self) -> Result<DataFile> {
+        let buffer = self.writer.into_inner()?;
+        let data = Bytes::from(buffer);
+        let metadata = ParquetMetaDataReader::new().parse_and_finish(&data)?;
+        let file_size_in_bytes = data.len() as i64;
+        self.file_io.write(&self.location, data)?;
+
+        let mut data_file = DataFile {
+            content: DataContentType::Data,
+            file_path: self.location,
+            file_format: FileFormat::Parquet,
+            partition: self.partition,
+            record_count: metadata.file_metadata().num_rows(),
+            file_size_in_bytes,
+            column_sizes: None,
+            value_counts: None,
+            null_value_counts: None,
+            nan_value_counts: Some(self.nan_value_counts),
+            lower_bounds: None,
+            upper_bounds: None,
+            key_metadata: None,
+            split_offsets: None,
+            equality_ids: None,
+            sort_order_id: self.sort_order_id,
+            referenced_data_file: None,
+            content_offset: None,
+            content_size_in_bytes: None,
+        };
+        collect_metrics(
+            &metadata,
+            &self.primitive_types,
+            self.bounds_truncate_length,
+            &mut data_file,
+        );
+        Ok(data_file)
+    }
+}
+
+// Prefix of the table properties enabling bloom filters on a column when set to true, e.g.
+// "write.parquet.bloom-filter-enabled.column.id". Nested columns are named by their dotted path
+pub const BLOOM_FILTER_ENABLED_PROPERTY_PREFIX: &str = "write.parquet.bloom-filter-enabled.column.";
+
+// Settings of the Parquet files written for tables, applied with PartitionedWriter::with_config
+// and PositionDeleteWriter::with_config
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct WriterConfig {
+    pub compression: Compression,
+    // Maximum number of rows of a row group
+    pub max_row_group_size: usize,
+    // Size in bytes above which data pages are closed, best effort
+    pub data_page_size_limit: usize,
+    // Columns getting a bloom filter in every row group, used by scans to skip row groups when
+    // looking up values of the columns
+    pub bloom_filter_columns: Vec<String>,
+}
+
+impl Default for WriterConfig {
+    fn default() -> Self {
+        WriterConfig {
+            compression: Compression::ZSTD(ZstdLevel::default()),
+            max_row_group_size: DEFAULT_MAX_ROW_GROUP_SIZE,
+            data_page_size_limit: DEFAULT_PAGE_SIZE,
+            bloom_filter_columns: vec![],
+        }
+    }
+}
+
+impl WriterConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn with_max_row_group_size(mut self, rows: usize) -> Self {
+        self.max_row_group_size = rows;
+        self
+    }
+
+    pub fn with_data_page_size_limit(mut self, bytes: usize) -> Self {
+        self.data_page_size_limit = bytes;
+        self
+    }
+
+    pub fn with_bloom_filter_column(mut self, column: &str) -> Self {
+        self.bloom_filter_columns.push(column.to_string());
+        self
+    }
+
+    // Default settings with the compression codec and bloom filters of the table properties.
+    // Fails for tables whose data files aren't written as Parquet
+    pub fn for_table(metadata: &TableMetadataV2) -> Result<Self> {
+        let properties = TableProperties::from_metadata(metadata);
+        let format = properties.default_file_format()?;
+        if format != FileFormat::Parquet {
+            return Err(IcebergError::Unsupported(format!(
+                "Writing {} data files to table {}",
+                format.as_str(),
+                metadata.location
+            )));
+        }
+        let mut columns: Vec<String> = properties
+            .as_map()
+            .iter()
+            .filter(|(_, value)| value.eq_ignore_ascii_case("true"))
+            .filter_map(|(key, _)| key.strip_prefix(BLOOM_FILTER_ENABLED_PROPERTY_PREFIX))
+            .map(str::to_string)
+            .collect();
+        columns.sort();
+        Ok(WriterConfig {
+            compression: properties.parquet_compression()?,
+            bloom_filter_columns: columns,
+            ..Self::default()
+        })
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.max_row_group_size == 0 || self.data_page_size_limit == 0 {
+            return Err(IcebergError::Invalid(format!(
+                "Writers need a positive row group size and page size limit, got {} and {}",
+                self.max_row_group_size, self.data_page_size_limit
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn writer_properties(&self) -> WriterProperties {
+        let mut builder = WriterProperties::builder()
+            .set_compression(self.compression)
+            .set_max_row_group_size(self.max_row_group_size)
+            .set_data_page_size_limit(self.data_page_size_limit);
+        for column in &self.bloom_filter_columns {
+            let path = ColumnPath::new(column.split('.').map(str::to_string).collect());
+            builder = builder.set_column_bloom_filter_enabled(path, true);
+        }
+        builder.build()
+    }
+}
+
+pub fn default_writer_properties() -> WriterProperties {
+    WriterConfig::default().writer_properties()
+}
+
+pub(crate) fn align_batch(batch: &RecordBatch, schema: &SchemaRef) -> Result<RecordBatch> {
+    if batch.num_columns() != schema.fields().len() {
+        return Err(IcebergError::Invalid(format!(
+            "Record batch has {} columns but the table schema has {}",
+            batch.num_columns(),
+            schema.fields().len()
+        )));
+    }
+    let columns = batch
+        .columns()
+        .iter()
+        .zip(schema.fields())
+        .map(|(column, field)| {
+            if column.data_type() == field.data_type() {
+                Ok(column.clone())
+            } else {
+                Ok(cast(column, field.data_type())?)
+            }
+        })
+        .collect::<Result<Vec<ArrayRef>>>()?;
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+// Sorts the rows of a batch aligned with the schema by a sort order. Fields sort by the
+// transformed values of their source columns, which must be top-level primitive columns, with
+// the direction and null order of the field
+pub fn sort_batch(
+    batch: &RecordBatch,
+    schema: &StructType,
+    order: &SortOrders,
+) -> Result<RecordBatch> {
+    if order.is_unsorted() || batch.num_rows() < 2 {
+        return Ok(batch.clone());
+    }
+    let columns = order
+        .fields
+        .iter()
+        .map(|field| {
+            let (index, primitive) = schema
+                .fields
+                .iter()
+                .enumerate()
+                .find(|(_, column)| column.id == field.source_id)
+                .and_then(|(index, column)| match &column.field_type {
+                    IcebergType::Primitive(primitive) => Some((index, primitive)),
+                    _ => None,
+                })
+                .ok_or_else(|| {
+                    IcebergError::Unsupported(format!(
+                        "Sort order {} must sort top-level primitive columns, not field {}",
+                        order.order_id, field.source_id
+                    ))
+                })?;
+            let column = batch.column(index);
+            let values = match field.transform {
+                Transform::Identity => column.clone(),
+                _ => {
+                    let values = (0..batch.num_rows())
+                        .map(|row| {
+                            literal_from_array(column.as_ref(), row, primitive)?
+                                .map(|value| field.transform.apply(&value))
+                                .transpose()
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    literals_to_array(&values, &field.transform.result_type(primitive))?
+                }
+            };
+            Ok(SortColumn {
+                values,
+                options: Some(SortOptions {
+                    descending: field.direction == Direction::Desc,
+                    nulls_first: field.null_order == NullOrder::NullsFirst,
+                }),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let indices = lexsort_to_indices(&columns, None)?;
+    Ok(take_record_batch(batch, &indices)?)
+}
+
+fn count_nans(column: &ArrayRef) -> i64 {
+    if let Some(array) = column.as_any().downcast_ref::<Float32Array>() {
+        array.iter().flatten().filter(|v| v.is_nan()).count() as i64
+    } else if let Some(array) = column.as_any().downcast_ref::<Float64Array>() {
+        array.iter().flatten().filter(|v| v.is_nan()).count() as i64
+    } else {
+        0
+    }
+}
+
+// Primitive types of all fields of the schema that can have bounds, i.e. primitive fields that
+// are not nested in a list or map
+pub(crate) fn primitive_types_by_id(schema: &StructType) -> HashMap<i32, PrimitiveType> {
+    fn visit(schema: &StructType, types: &mut HashMap<i32, PrimitiveType>) {
+        for field in &schema.fields {
+            match &field.field_type {
+                IcebergType::Primitive(primitive) => {
+                    types.insert(field.id, primitive.clone());
+                }
+                IcebergType::Struct(nested) => visit(nested, types),
+                IcebergType::List(_) | IcebergType::Map(_) => {}
+            }
+        }
+    }
+    let mut types = HashMap::new();
+    visit(schema, &mut types);
+    types
+}
+
+fn collect_metrics(
+    metadata: &ParquetMetaData,
+    primitive_types: &HashMap<i32, PrimitiveType>,
+    truncate_length: Option<usize>,
+    data_file: &mut DataFile,
+) {
+    let mut column_sizes = HashMap::new();
+    let mut value_counts = HashMap::new();
+    let mut null_value_counts = HashMap::new();
+    let mut lower_bounds: HashMap<i32, Literal> = HashMap::new();
+    let mut upper_bounds: HashMap<i32, Literal> = HashMap::new();
+    let mut split_offsets = vec![];
+
+    for row_group in metadata.row_groups() {
+        if let Some(offset) = row_group_offset(row_group) {
+            split_offsets.push(offset);
+        }
+
+        for column in row_group.columns() {
+            let basic_info = column.column_descr().self_type().get_basic_info();
+            if !basic_info.has_id() {
+                continue;
+            }
+            let id = basic_info.id();
+            *column_sizes.entry(id).or_insert(0) += column.compressed_size();
+            *value_counts.entry(id).or_insert(0) += column.num_values();
+
+            let (Some(statistics), Some(primitive)) =
+                (column.statistics(), primitive_types.get(&id))
+            else {
+                continue;
+            };
+            if let Some(null_count) = statistics.null_count_opt() {
+                *null_value_counts.entry(id).or_insert(0) += null_count as i64;
+            }
+            if let Some(min) = statistic_to_literal(statistics, primitive, true) {
+                match lower_bounds.get(&id) {
+                    Some(current) if current <= &min => {}
+                    _ => {
+                        lower_bounds.insert(id, min);
+                    }
+                }
+            }
+            if let Some(max) = statistic_to_literal(statistics, primitive, false) {
+                match upper_bounds.get(&id) {
+                    Some(current) if current >= &max => {}
+                    _ => {
+                        upper_bounds.insert(id, max);
+                    }
+                }
+            }
+        }
+    }
+
+    data_file.column_sizes = Some(column_sizes);
+    data_file.value_counts = Some(value_counts);
+    data_file.null_value_counts = Some(null_value_counts);
+    data_file.lower_bounds = Some(
+        lower_bounds
+            .into_iter()
+            .map(|(id, literal)| match truncate_length {
+                Some(length) => (id, Bound::from(&truncate_lower_bound(literal, length))),
+                None => (id, Bound::from(&literal)),
+            })
+            .collect(),
+    );
+    // Upper bounds that can't be truncated are left out
+    data_file.upper_bounds = Some(
+        upper_bounds
+            .into_iter()
+            .filter_map(|(id, literal)| match truncate_length {
+                Some(length) => Some((id, Bound::from(&truncate_upper_bound(literal, length)?))),
+                None => Some((id, Bound::from(&literal))),
+            })
+            .collect(),
+    );
+    data_file.split_offsets = Some(split_offsets);
+}
+
+// Lower bound of at most `length` characters or bytes: the prefix of the bound
+fn truncate_lower_bound(bound: Literal, length: usize) -> Literal {
+    match bound {
+        Literal::String(value) => Literal::String(value.chars().take(length).collect()),
+        Literal::Binary(mut value) => {
+            value.truncate(length);
+            Literal::Binary(value)
+        }
+        bound => bound,
+    }
+}
+
+// Upper bound of at most `length` characters or bytes: the prefix of the bound with its last
+// character or byte incremented, so that it stays above the values of the column. None when all
+// the characters or bytes of the prefix are at their maximum
+fn truncate_upper_bound(bound: Literal, length: usize) -> Option<Literal> {
+    match bound {
+        Literal::String(value) if value.chars().count() > length => {
+            let mut chars: Vec<char> = value.chars().take(length).collect();
+            while let Some(last) = chars.pop() {
+                // The next code point, skipping surrogates which aren't characters
+                let next = (last as u32 + 1..=char::MAX as u32).find_map(char::from_u32);
+                if let Some(next) = next {
+                    chars.push(next);
+                    return Some(Literal::String(chars.into_iter().collect()));
+                }
+            }
+            None
+        }
+        Literal::Binary(mut value) if value.len() > length => {
+            value.truncate(length);
+            while let Some(last) = value.pop() {
+                if last < u8::MAX {
+                    value.push(last + 1);
+                    return Some(Literal::Binary(value));
+                }
+            }
+            None
+        }
+        bound => Some(bound),
+    }
+}
+
+// Convert the min (or max) of a column chunk's statistics to a literal of the Iceberg type
+fn statistic_to_literal(
+    statistics: &Statistics,
+    primitive: &PrimitiveType,
+    min: bool,
+) -> Option<Literal> {
+    macro_rules! pick {
+        ($stats:expr) => {
+            if min {
+                $stats.min_opt()
+            } else {
+                $stats.max_opt()
+            }
+        };
+    }
+
+    match statistics {
+        Statistics::Boolean(s) => pick!(s).map(|v| Literal::Boolean(*v)),
+        Statistics::Int32(s) => pick!(s).and_then(|v| match primitive {
+            PrimitiveType::Int => Some(Literal::Int(*v)),
+            PrimitiveType::Date => Some(Literal::Date(*v)),
+            PrimitiveType::Decimal { .. } => Some(Literal::Decimal(*v as i128)),
+            _ => None,
+        }),
+        Statistics::Int64(s) => pick!(s).and_then(|v| match primitive {
+            PrimitiveType::Long => Some(Literal::Long(*v)),
+            PrimitiveType::Time => Some(Literal::Time(*v)),
+            PrimitiveType::Timestamp => Some(Literal::Timestamp(*v)),
+            PrimitiveType::Timestamptz => Some(Literal::Timestamptz(*v)),
+            PrimitiveType::Decimal { .. } => Some(Literal::Decimal(*v as i128)),
+            _ => None,
+        }),
+        Statistics::Float(s) => pick!(s).map(|v| Literal::Float(*v)),
+        Statistics::Double(s) => pick!(s).map(|v| Literal::Double(*v)),
+        Statistics::ByteArray(s) => {
+            pick!(s).and_then(|v| Literal::try_from_bytes(v.data(), primitive).ok())
+        }
+        Statistics::FixedLenByteArray(s) => {
+            pick!(s).and_then(|v| Literal::try_from_bytes(v.data(), primitive).ok())
+        }
+        Statistics::Int96(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::io::LocalFileIO;
+    use crate::iceberg::reader::ParquetReader;
+    use crate::iceberg::spec::schema::StructField;
+    use arrow::array::{Int32Array, StringArray};
+    use arrow::datatypes::{Field, Schema};
+
+    fn table_schema() -> StructType {
+        StructType {
+            fields: vec![
+                StructField {
+                    id: 1,
+                    name: "id".to_string(),
+                    required: true,
+                    field_type: IcebergType::Primitive(PrimitiveType::Long),
+                    doc: None,
+                    initial_default: None,
+                    write_default: None,
+                },
+                StructField {
+                    id: 2,
+                    name: "name".to_string(),
+                    required: false,
+                    field_type: IcebergType::Primitive(PrimitiveType::String),
+                    doc: None,
+                    initial_default: None,
+                    write_default: None,
+                },
+                StructField {
+                    id: 3,
+                    name: "score".to_string(),
+                    required: false,
+                    field_type: IcebergType::Primitive(PrimitiveType::Double),
+                    doc: None,
+                    initial_default: None,
+                    write_default: None,
+                },
+            ],
+        }
+    }
+
+    // A batch as a user would build it: no field ids and narrower types than the table schema
+    fn user_batch() -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("score", DataType::Float64, true),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![3, 1, 2])),
+                Arc::new(StringArray::from(vec![Some("b"), None, Some("a")])),
+                Arc::new(Float64Array::from(vec![Some(1.0), Some(f64::NAN), None])),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_write_collects_metrics() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = format!("file:{}/data/1.parquet", dir.path().display());
+        let file_io: Arc<dyn FileIO> = Arc::new(LocalFileIO::new());
+
+        let mut writer =
+            ParquetWriter::try_new(file_io.clone(), location.clone(), &table_schema()).unwrap();
+        writer.write(&user_batch()).unwrap();
+        let data_file = writer.close().unwrap();
+
+        assert_eq!(location, data_file.file_path);
+        assert_eq!(FileFormat::Parquet, data_file.file_format);
+        assert_eq!(3, data_file.record_count);
+        assert_eq!(
+            file_io.read(&location).unwrap().len() as i64,
+            data_file.file_size_in_bytes
+        );
+
+        let lower_bounds = data_file.lower_bounds.unwrap();
+        let upper_bounds = data_file.upper_bounds.unwrap();
+        assert_eq!(
+            Literal::Long(1),
+            *lower_bounds[&1].decode(&PrimitiveType::Long).unwrap()
+        );
+        assert_eq!(
+            Literal::Long(3),
+            *upper_bounds[&1].decode(&PrimitiveType::Long).unwrap()
+        );
+        assert_eq!(b"a", lower_bounds[&2].bytes());
+        assert_eq!(b"b", upper_bounds[&2].bytes());
+
+        let null_value_counts = data_file.null_value_counts.unwrap();
+        assert_eq!(0, null_value_counts[&1]);
+        assert_eq!(1, null_value_counts[&2]);
+        assert_eq!(1, data_file.nan_value_counts.unwrap()[&3]);
+        assert_eq!(3, data_file.value_counts.unwrap()[&2]);
+        assert!(data_file.column_sizes.unwrap()[&1] > 0);
+        assert_eq!(1, data_file.split_offsets.unwrap().len());
+    }
+
+    #[test]
+    fn test_write_truncates_bounds() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_io: Arc<dyn FileIO> = Arc::new(LocalFileIO::new());
+        let names = [
+            "abcdefghijklmnopqrstuvwxyz".to_string(),
+            format!("abcdefghijklmno{}z", char::MAX),
+        ];
+        let batch = |names: &[String]| {
+            RecordBatch::try_new(
+                user_batch().schema(),
+                vec![
+                    Arc::new(Int32Array::from(vec![1; names.len()])),
+                    Arc::new(StringArray::from(names.to_vec())),
+                    Arc::new(Float64Array::from(vec![1.0; names.len()])),
+                ],
+            )
+            .unwrap()
+        };
+        let write = |names: &[String], full_bounds: bool| {
+            let location = format!(
+                "{}/data/{}-{}.parquet",
+                dir.path().display(),
+                names.len(),
+                full_bounds
+            );
+            let mut writer =
+                ParquetWriter::try_new(file_io.clone(), location, &table_schema()).unwrap();
+            if full_bounds {
+                writer = writer.with_full_bounds();
+            }
+            writer.write(&batch(names)).unwrap();
+            writer.close().unwrap()
+        };
+
+        let data_file = write(&names, false);
+        let lower_bounds = data_file.lower_bounds.unwrap();
+        let upper_bounds = data_file.upper_bounds.unwrap();
+        assert_eq!(b"abcdefghijklmnop", lower_bounds[&2].bytes());
+        // The 16th character of the max can't be incremented, the 15th is
+        assert_eq!(b"abcdefghijklmnp", upper_bounds[&2].bytes());
+
+        let data_file = write(&names[..1], false);
+        assert_eq!(
+            b"abcdefghijklmnoq",
+            data_file.upper_bounds.unwrap()[&2].bytes()
+        );
+        let data_file = write(&names, true);
+        assert_eq!(
+            names[1].as_bytes(),
+            data_file.upper_bounds.unwrap()[&2].bytes()
+        );
+    }
+
+    #[test]
+    fn test_truncate_bounds() {
+        let string = |value: &str| Literal::String(value.to_string());
+        assert_eq!(string("ab"), truncate_lower_bound(string("abc"), 2));
+        assert_eq!(Some(string("ac")), truncate_upper_bound(string("abc"), 2));
+        assert_eq!(Some(string("abc")), truncate_upper_bound(string("abc"), 3));
+        let max = char::MAX.to_string();
+        assert_eq!(None, truncate_upper_bound(string(&max.repeat(3)), 2));
+        // Surrogates aren't characters
+        let value = format!("a{}b", '\u{d7ff}');
+        assert_eq!(
+            Some(format!("a{}", '\u{e000}')),
+            match truncate_upper_bound(string(&value), 2) {
+                Some(Literal::String(value)) => Some(value),
+                _ => None,
+            }
+        );
+
+        let binary = |value: &[u8]| Literal::Binary(value.to_vec());
+        assert_eq!(binary(&[1]), truncate_lower_bound(binary(&[1, 2]), 1));
+        assert_eq!(
+            Some(binary(&[2])),
+            truncate_upper_bound(binary(&[1, 0xff, 3]), 2)
+        );
+        assert_eq!(None, truncate_upper_bound(binary(&[0xff, 0xff, 1]), 2));
+        assert_eq!(Literal::Long(1), truncate_lower_bound(Literal::Long(1), 2));
+    }
+
+    #[test]
+    fn test_written_file_is_readable_by_field_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = format!("{}/data/1.parquet", dir.path().display());
+        let file_io: Arc<dyn FileIO> = Arc::new(LocalFileIO::new());
+
+        let mut writer =
+            ParquetWriter::try_new(file_io.clone(), location.clone(), &table_schema()).unwrap();
+        writer.write(&user_batch()).unwrap();
+        writer.close().unwrap();
+
+        let reader = ParquetReader::try_new(&table_schema()).unwrap();
+        let batches = reader
+            .read(file_io.read(&location).unwrap())
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(3, batches[0].num_rows());
+        assert_eq!(&DataType::Int64, batches[0].column(0).data_type());
+    }
+
+    #[test]
+    fn test_write_rejects_mismatched_batch() {
+        let file_io: Arc<dyn FileIO> = Arc::new(LocalFileIO::new());
+        let mut writer =
+            ParquetWriter::try_new(file_io, "/tmp/unused.parquet".to_string(), &table_schema())
+                .unwrap();
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)])),
+            vec![Arc::new(Int32Array::from(vec![1]))],
+        )
+        .unwrap();
+        assert!(writer.write(&batch).is_err());
+    }
+}
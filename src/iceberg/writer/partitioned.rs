@@ -0,0 +1,599 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use arrow::array::{RecordBatch, UInt32Array};
+use arrow::compute::{concat_batches, take_record_batch};
+use arrow::datatypes::SchemaRef;
+use parquet::file::properties::WriterProperties;
+use uuid::Uuid;
+
+use super::{align_batch, default_writer_properties, sort_batch, ParquetWriter, WriterConfig};
+use crate::iceberg::arrow::{literal_from_array, schema_to_arrow};
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::io::FileIO;
+use crate::iceberg::spec::manifest::{DataFile, FileFormat};
+use crate::iceberg::spec::partition_spec::PartitionSpec;
+use crate::iceberg::spec::schema::{IcebergType, PrimitiveType, StructType};
+use crate::iceberg::spec::sort_orders::SortOrders;
+use crate::iceberg::spec::values::Literal;
+use crate::iceberg::table::Table;
+
+// Writes record batches to data files split by the partition spec of the table. Partition
+// values are derived from the rows by applying the transforms of the spec, and every partition
+// gets its own file under "<data location>/<partition path>/". Unpartitioned specs produce a
+// single file. Writers with a sort order hold the rows of every file until close and write them
+// sorted, recording the order id in the data files
+pub struct PartitionedWriter {
+    file_io: Arc<dyn FileIO>,
+    data_location: String,
+    schema: StructType,
+    arrow_schema: SchemaRef,
+    spec: PartitionSpec,
+    // Index of the source column and type of every partition field
+    sources: Vec<(usize, PrimitiveType)>,
+    properties: WriterProperties,
+    sort_order: Option<SortOrders>,
+    // Open writers keyed by partition values
+    writers: BTreeMap<PartitionKey, ParquetWriter>,
+    // Rows waiting to be sorted, keyed by partition values
+    pending: BTreeMap<PartitionKey, Vec<RecordBatch>>,
+}
+
+// Partition values as map keys. Paths can't be keys as they render null and the string "null"
+// the same. Literals are only partially ordered: floats are ordered by their total order, and
+// values of different types, which partition fields don't produce, by their debug output
+#[derive(Debug, Clone)]
+struct PartitionKey(Vec<Option<Literal>>);
+
+impl Ord for PartitionKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .map(|(a, b)| match (a, b) {
+                (Some(Literal::Float(a)), Some(Literal::Float(b))) => a.total_cmp(b),
+                (Some(Literal::Double(a)), Some(Literal::Double(b))) => a.total_cmp(b),
+                _ => a
+                    .partial_cmp(b)
+                    .unwrap_or_else(|| format!("{:?}", a).cmp(&format!("{:?}", b))),
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| self.0.len().cmp(&other.0.len()))
+    }
+}
+
+impl PartialOrd for PartitionKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for PartitionKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for PartitionKey {}
+
+impl PartitionedWriter {
+    pub fn try_new(
+        file_io: Arc<dyn FileIO>,
+        data_location: String,
+        schema: &StructType,
+        spec: &PartitionSpec,
+    ) -> Result<Self> {
+        let sources = spec
+            .fields
+            .iter()
+            .map(|field| {
+                schema
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .find(|(_, column)| column.id == field.source_id)
+                    .and_then(|(index, column)| match &column.field_type {
+                        IcebergType::Primitive(primitive) => Some((index, primitive.clone())),
+                        _ => None,
+                    })
+                    .ok_or_else(|| {
+                        IcebergError::Unsupported(format!(
+                            "Partition field {} must have a top-level primitive source column",
+                            field.name
+                        ))
+                    })
+                    .and_then(|(index, primitive)| {
+                        if field.transform.can_transform(&primitive) {
+                            Ok((index, primitive))
+                        } else {
+                            Err(IcebergError::Unsupported(format!(
+                                "Writing partition field {} with transform {:?} of {:?}",
+                                field.name, field.transform, primitive
+                            )))
+                        }
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(PartitionedWriter {
+            file_io,
+            data_location,
+            schema: schema.clone(),
+            arrow_schema: Arc::new(schema_to_arrow(schema)?),
+            spec: spec.clone(),
+            sources,
+            properties: default_writer_properties(),
+            sort_order: None,
+            writers: BTreeMap::new(),
+            pending: BTreeMap::new(),
+        })
+    }
+
+    // Writer for the current schema and default partition spec of the table, placing files in
+    // the data directory of the table, with the settings of the table properties
+    pub fn for_table(table: &Table) -> Result<Self> {
+        let metadata = table.metadata();
+        Self::try_new(
+            table.file_io().clone(),
+            format!("{}/data", metadata.location.trim_end_matches('/')),
+            metadata.current_schema()?.schema(),
+            metadata.default_partition_spec()?,
+        )?
+        .with_config(&WriterConfig::for_table(metadata)?)
+    }
+
+    pub fn with_writer_properties(mut self, properties: WriterProperties) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    pub fn with_config(self, config: &WriterConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(self.with_writer_properties(config.writer_properties()))
+    }
+
+    // Sorts the rows of every file by the order, see sort_batch. The unsorted order writes rows
+    // as they come
+    pub fn with_sort_order(mut self, order: &SortOrders) -> Result<Self> {
+        order.validate(&self.schema)?;
+        self.sort_order = Some(order.clone()).filter(|order| !order.is_unsorted());
+        Ok(self)
+    }
+
+    // Sorts the rows of every file by the default sort order of the table
+    pub fn with_table_sort_order(self, table: &Table) -> Result<Self> {
+        self.with_sort_order(table.metadata().default_sort_order()?)
+    }
+
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        let batch = align_batch(batch, &self.arrow_schema)?;
+        if self.spec.fields.is_empty() {
+            return self.write_partition(PartitionKey(vec![]), batch);
+        }
+
+        // Group the rows by partition, keeping them in their original order
+        let mut partitions: BTreeMap<PartitionKey, Vec<u32>> = BTreeMap::new();
+        for row in 0..batch.num_rows() {
+            let values = self
+                .spec
+                .fields
+                .iter()
+                .zip(&self.sources)
+                .map(|(field, (index, primitive))| {
+                    literal_from_array(batch.column(*index).as_ref(), row, primitive)?
+                        .map(|value| field.transform.apply(&value))
+                        .transpose()
+                })
+                .collect::<Result<Vec<_>>>()?;
+            partitions
+                .entry(PartitionKey(values))
+                .or_default()
+                .push(row as u32);
+        }
+
+        for (key, rows) in partitions {
+            let rows = take_record_batch(&batch, &UInt32Array::from(rows))?;
+            self.write_partition(key, rows)?;
+        }
+        Ok(())
+    }
+
+    // Closes all the files and returns them ordered by partition path
+    pub fn close(mut self) -> Result<Vec<DataFile>> {
+        if let Some(order) = self.sort_order.clone() {
+            for (key, batches) in std::mem::take(&mut self.pending) {
+                let batch = concat_batches(&self.arrow_schema, &batches)?;
+                let sorted = sort_batch(&batch, &self.schema, &order)?;
+                self.writer(key)?.write(&sorted)?;
+            }
+        }
+        let mut writers: Vec<_> = std::mem::take(&mut self.writers).into_iter().collect();
+        writers.sort_by_cached_key(|(key, _)| self.spec.partition_path(&key.0));
+        writers
+            .into_iter()
+            .map(|(_, writer)| writer.close())
+            .collect()
+    }
+
+    fn write_partition(&mut self, key: PartitionKey, batch: RecordBatch) -> Result<()> {
+        if self.sort_order.is_some() {
+            self.pending.entry(key).or_default().push(batch);
+            return Ok(());
+        }
+        self.writer(key)?.write(&batch)
+    }
+
+    fn writer(&mut self, key: PartitionKey) -> Result<&mut ParquetWriter> {
+        if !self.writers.contains_key(&key) {
+            let path = self.spec.partition_path(&key.0);
+            let directory = if path.is_empty() {
+                self.data_location.clone()
+            } else {
+                format!("{}/{}", self.data_location, path)
+            };
+            let writer = ParquetWriter::try_new_with_properties(
+                self.file_io.clone(),
+                format!(
+                    "{}/{}.{}",
+                    directory,
+                    Uuid::new_v4(),
+                    FileFormat::Parquet.extension()
+                ),
+                &self.schema,
+                self.properties.clone(),
+            )?
+            .with_partition(key.0.clone());
+            let writer = match &self.sort_order {
+                Some(order) => writer.with_sort_order_id(order.order_id),
+                None => writer,
+            };
+            self.writers.insert(key.clone(), writer);
+        }
+        Ok(self.writers.get_mut(&key).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{Int64Array, StringArray, TimestampMicrosecondArray};
+    use parquet::basic::Compression;
+    use parquet::file::metadata::ParquetMetaDataReader;
+
+    use super::*;
+    use crate::iceberg::arrow::UTC_TIMEZONE;
+    use crate::iceberg::io::LocalFileIO;
+    use crate::iceberg::reader::ParquetReader;
+    use crate::iceberg::spec::partition_spec::{PartitionField, Transform};
+    use crate::iceberg::spec::schema::StructField;
+    use crate::iceberg::spec::sort_orders::{Direction, NullOrder, SortField};
+
+    fn schema() -> StructType {
+        let field = |id: i32, name: &str, primitive: PrimitiveType| StructField {
+            id,
+            name: name.to_string(),
+            required: false,
+            field_type: IcebergType::Primitive(primitive),
+            doc: None,
+            initial_default: None,
+            write_default: None,
+        };
+        StructType {
+            fields: vec![
+                field(1, "id", PrimitiveType::Long),
+                field(2, "category", PrimitiveType::String),
+                field(3, "ts", PrimitiveType::Timestamptz),
+            ],
+        }
+    }
+
+    fn spec() -> PartitionSpec {
+        PartitionSpec {
+            spec_id: 0,
+            fields: vec![
+                PartitionField {
+                    source_id: 2,
+                    source_ids: vec![],
+                    field_id: 1000,
+                    name: "category".to_string(),
+                    transform: Transform::Identity,
+                },
+                PartitionField {
+                    source_id: 3,
+                    source_ids: vec![],
+                    field_id: 1001,
+                    name: "ts_day".to_string(),
+                    transform: Transform::Day,
+                },
+            ],
+        }
+    }
+
+    fn batch() -> RecordBatch {
+        const DAY: i64 = 86_400_000_000;
+        RecordBatch::try_new(
+            Arc::new(schema_to_arrow(&schema()).unwrap()),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3, 4])),
+                Arc::new(StringArray::from(vec![
+                    Some("books"),
+                    Some("games"),
+                    Some("books"),
+                    None,
+                ])),
+                Arc::new(
+                    TimestampMicrosecondArray::from(vec![DAY, DAY + 1, DAY + 2, 2 * DAY])
+                        .with_timezone(UTC_TIMEZONE),
+                ),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rows_are_routed_to_partitions() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_io: Arc<dyn FileIO> = Arc::new(LocalFileIO::new());
+        let mut writer = PartitionedWriter::try_new(
+            file_io.clone(),
+            format!("file:{}/data", dir.path().display()),
+            &schema(),
+            &spec(),
+        )
+        .unwrap();
+        writer.write(&batch()).unwrap();
+        let data_files = writer.close().unwrap();
+
+        let partitions: Vec<_> = data_files
+            .iter()
+            .map(|file| (file.partition.clone(), file.record_count))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    vec![
+                        Some(Literal::String("books".to_string())),
+                        Some(Literal::Date(1))
+                    ],
+                    2
+                ),
+                (
+                    vec![
+                        Some(Literal::String("games".to_string())),
+                        Some(Literal::Date(1))
+                    ],
+                    1
+                ),
+                (vec![None, Some(Literal::Date(2))], 1),
+            ],
+            partitions
+        );
+        assert!(data_files[0]
+            .file_path
+            .contains("/data/category=books/ts_day=1970-01-02/"));
+
+        let reader = ParquetReader::try_new(&schema()).unwrap();
+        let batches = reader
+            .read(file_io.read(&data_files[0].file_path).unwrap())
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let ids = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(vec![1, 3], ids.values().to_vec());
+    }
+
+    #[test]
+    fn test_null_and_null_string_partitions() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = PartitionedWriter::try_new(
+            Arc::new(LocalFileIO::new()),
+            format!("file:{}/data", dir.path().display()),
+            &schema(),
+            &PartitionSpec {
+                spec_id: 0,
+                fields: spec().fields[..1].to_vec(),
+            },
+        )
+        .unwrap();
+        let batch = batch();
+        let categories = StringArray::from(vec![Some("null"), None, Some("null"), None]);
+        let batch = RecordBatch::try_new(
+            batch.schema(),
+            vec![
+                batch.column(0).clone(),
+                Arc::new(categories),
+                batch.column(2).clone(),
+            ],
+        )
+        .unwrap();
+        writer.write(&batch).unwrap();
+        let data_files = writer.close().unwrap();
+
+        // Both partitions have the path category=null, but their own files
+        assert_eq!(2, data_files.len());
+        assert_ne!(data_files[0].file_path, data_files[1].file_path);
+        let mut partitions: Vec<_> = data_files
+            .iter()
+            .map(|file| (file.partition.clone(), file.record_count))
+            .collect();
+        partitions.sort_by_key(|(partition, _)| partition[0].is_some());
+        assert_eq!(
+            vec![
+                (vec![None], 2),
+                (vec![Some(Literal::String("null".to_string()))], 2)
+            ],
+            partitions
+        );
+    }
+
+    #[test]
+    fn test_sorted_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_io: Arc<dyn FileIO> = Arc::new(LocalFileIO::new());
+        let order = SortOrders {
+            order_id: 1,
+            fields: vec![
+                SortField {
+                    transform: Transform::Day,
+                    source_id: 3,
+                    source_ids: vec![],
+                    direction: Direction::Desc,
+                    null_order: NullOrder::NullsLast,
+                },
+                SortField {
+                    transform: Transform::Identity,
+                    source_id: 2,
+                    source_ids: vec![],
+                    direction: Direction::Asc,
+                    null_order: NullOrder::NullsFirst,
+                },
+            ],
+        };
+        let mut writer = PartitionedWriter::try_new(
+            file_io.clone(),
+            format!("file:{}/data", dir.path().display()),
+            &schema(),
+            &PartitionSpec {
+                spec_id: 0,
+                fields: vec![],
+            },
+        )
+        .unwrap()
+        .with_sort_order(&order)
+        .unwrap();
+        writer.write(&batch()).unwrap();
+        writer.write(&batch().slice(0, 2)).unwrap();
+        let data_files = writer.close().unwrap();
+        assert_eq!(1, data_files.len());
+        assert_eq!(Some(1), data_files[0].sort_order_id);
+
+        let reader = ParquetReader::try_new(&schema()).unwrap();
+        let batches = reader
+            .read(file_io.read(&data_files[0].file_path).unwrap())
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let rows = concat_batches(&batches[0].schema(), &batches).unwrap();
+        let ids = rows
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        // Day 2 first, then day 1 by category with the "books" rows before the "games" rows
+        let mut books = ids.values()[1..4].to_vec();
+        books.sort();
+        assert_eq!((4, vec![1, 1, 3]), (ids.value(0), books));
+        assert_eq!(vec![2, 2], ids.values()[4..].to_vec());
+
+        // Unsorted orders write rows as they come, invalid orders are rejected
+        let writer = |order: &SortOrders| {
+            PartitionedWriter::try_new(
+                file_io.clone(),
+                format!("file:{}/data", dir.path().display()),
+                &schema(),
+                &spec(),
+            )
+            .unwrap()
+            .with_sort_order(order)
+        };
+        let mut unsorted = writer(&SortOrders::unsorted()).unwrap();
+        unsorted.write(&batch()).unwrap();
+        assert!(unsorted
+            .close()
+            .unwrap()
+            .iter()
+            .all(|file| file.sort_order_id.is_none()));
+        let mut bucketed = order.clone();
+        bucketed.fields[0].transform = Transform::Bucket(4);
+        assert!(writer(&bucketed).is_err());
+    }
+
+    #[test]
+    fn test_unknown_transform_is_not_writable() {
+        let mut spec = spec();
+        spec.fields[1].transform = Transform::Unknown("zorder".to_string());
+        let writer = PartitionedWriter::try_new(
+            Arc::new(LocalFileIO::new()),
+            "file:/tmp/data".to_string(),
+            &schema(),
+            &spec,
+        );
+        assert!(matches!(writer, Err(IcebergError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_unpartitioned_spec_writes_single_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = PartitionedWriter::try_new(
+            Arc::new(LocalFileIO::new()),
+            format!("file:{}/data", dir.path().display()),
+            &schema(),
+            &PartitionSpec {
+                spec_id: 0,
+                fields: vec![],
+            },
+        )
+        .unwrap();
+        writer.write(&batch()).unwrap();
+        writer.write(&batch()).unwrap();
+        let data_files = writer.close().unwrap();
+
+        assert_eq!(1, data_files.len());
+        assert_eq!(8, data_files[0].record_count);
+        assert!(data_files[0].partition.is_empty());
+    }
+
+    #[test]
+    fn test_writer_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_io = Arc::new(LocalFileIO::new());
+        let unpartitioned = PartitionSpec {
+            spec_id: 0,
+            fields: vec![],
+        };
+        let writer = |config: &WriterConfig| {
+            PartitionedWriter::try_new(
+                file_io.clone(),
+                format!("file:{}/data", dir.path().display()),
+                &schema(),
+                &unpartitioned,
+            )
+            .unwrap()
+            .with_config(config)
+        };
+
+        let config = WriterConfig::new()
+            .with_compression(Compression::SNAPPY)
+            .with_max_row_group_size(3);
+        let mut partitioned = writer(&config).unwrap();
+        partitioned.write(&batch()).unwrap();
+        partitioned.write(&batch()).unwrap();
+        let data_files = partitioned.close().unwrap();
+        let data = file_io.read(&data_files[0].file_path).unwrap();
+        let metadata = ParquetMetaDataReader::new()
+            .parse_and_finish(&data)
+            .unwrap();
+        assert_eq!(3, metadata.num_row_groups());
+        let column = metadata.row_group(0).column(0);
+        assert_eq!(Compression::SNAPPY, column.compression());
+
+        let config = WriterConfig::new().with_max_row_group_size(0);
+        assert!(matches!(writer(&config), Err(IcebergError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_nested_source_columns_are_rejected() {
+        let mut spec = spec();
+        spec.fields[0].source_id = 42;
+        assert!(PartitionedWriter::try_new(
+            Arc::new(LocalFileIO::new()),
+            "file:/tmp/data".to_string(),
+            &schema(),
+            &spec,
+        )
+        .is_err());
+    }
+}
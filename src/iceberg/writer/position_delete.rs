@@ -0,0 +1,157 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use arrow::array::{Int64Array, RecordBatch, StringArray};
+use parquet::file::properties::WriterProperties;
+use uuid::Uuid;
+
+use super::{default_writer_properties, ParquetWriter, WriterConfig};
+use crate::iceberg::arrow::schema_to_arrow;
+use crate::iceberg::deletes::position_delete_schema;
+use crate::iceberg::error::Result;
+use crate::iceberg::io::FileIO;
+use crate::iceberg::spec::manifest::{DataContentType, DataFile, FileFormat};
+use crate::iceberg::spec::values::Literal;
+use crate::iceberg::table::Table;
+
+// Writes a position delete file, marking rows of data files as deleted by their position in the
+// file. Positions are buffered and written sorted by file path and position, as the spec
+// requires. A delete file only applies to data files of its own partition
+pub struct PositionDeleteWriter {
+    file_io: Arc<dyn FileIO>,
+    location: String,
+    partition: Vec<Option<Literal>>,
+    properties: WriterProperties,
+    positions: BTreeSet<(String, i64)>,
+}
+
+impl PositionDeleteWriter {
+    pub fn new(file_io: Arc<dyn FileIO>, location: String) -> Self {
+        PositionDeleteWriter {
+            file_io,
+            location,
+            partition: vec![],
+            properties: default_writer_properties(),
+            positions: BTreeSet::new(),
+        }
+    }
+
+    // Writer of a delete file for the given partition of the default partition spec of the
+    // table, placed next to the data files of the partition
+    pub fn for_table(table: &Table, partition: Vec<Option<Literal>>) -> Result<Self> {
+        let metadata = table.metadata();
+        let path = metadata
+            .default_partition_spec()?
+            .partition_path(&partition);
+        let mut directory = format!("{}/data", metadata.location.trim_end_matches('/'));
+        if !path.is_empty() {
+            directory = format!("{}/{}", directory, path);
+        }
+        let location = format!(
+            "{}/{}-deletes.{}",
+            directory,
+            Uuid::new_v4(),
+            FileFormat::Parquet.extension()
+        );
+        Ok(Self::new(table.file_io().clone(), location).with_partition(partition))
+    }
+
+    // Partition values of the delete file, in the order of the fields of the table's partition
+    // spec
+    pub fn with_partition(mut self, partition: Vec<Option<Literal>>) -> Self {
+        self.partition = partition;
+        self
+    }
+
+    pub fn with_writer_properties(mut self, properties: WriterProperties) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    pub fn with_config(self, config: &WriterConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(self.with_writer_properties(config.writer_properties()))
+    }
+
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    // Marks the row at the given position of the data file as deleted
+    pub fn delete(&mut self, file_path: &str, position: i64) {
+        self.positions.insert((file_path.to_string(), position));
+    }
+
+    pub fn close(self) -> Result<DataFile> {
+        let schema = position_delete_schema();
+        let (file_paths, positions): (Vec<String>, Vec<i64>) = self.positions.into_iter().unzip();
+        let batch = RecordBatch::try_new(
+            Arc::new(schema_to_arrow(&schema)?),
+            vec![
+                Arc::new(StringArray::from(file_paths)),
+                Arc::new(Int64Array::from(positions)),
+            ],
+        )?;
+        let mut writer = ParquetWriter::try_new_with_properties(
+            self.file_io,
+            self.location,
+            &schema,
+            self.properties,
+        )?
+        .with_partition(self.partition)
+        .with_full_bounds();
+        writer.write(&batch)?;
+        let mut data_file = writer.close()?;
+        data_file.content = DataContentType::PositionDeletes;
+        Ok(data_file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iceberg::deletes::{
+        POSITION_DELETE_FILE_PATH_FIELD_ID, POSITION_DELETE_POS_FIELD_ID,
+    };
+    use crate::iceberg::io::LocalFileIO;
+    use crate::iceberg::reader::ParquetReader;
+    use crate::iceberg::spec::values::Bound;
+
+    #[test]
+    fn test_position_deletes_are_sorted() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = format!("{}/deletes.parquet", dir.path().display());
+        let file_io: Arc<dyn FileIO> = Arc::new(LocalFileIO::new());
+        let mut writer = PositionDeleteWriter::new(file_io.clone(), location.clone());
+        writer.delete("file:/b.parquet", 0);
+        writer.delete("file:/a.parquet", 7);
+        writer.delete("file:/a.parquet", 3);
+        writer.delete("file:/a.parquet", 3);
+        let delete_file = writer.close().unwrap();
+
+        assert_eq!(DataContentType::PositionDeletes, delete_file.content);
+        assert_eq!(3, delete_file.record_count);
+        assert_eq!(
+            b"file:/a.parquet",
+            delete_file.lower_bounds.unwrap()[&POSITION_DELETE_FILE_PATH_FIELD_ID].bytes()
+        );
+        assert_eq!(
+            Bound::from(&Literal::Long(7)),
+            delete_file.upper_bounds.unwrap()[&POSITION_DELETE_POS_FIELD_ID]
+        );
+
+        let reader = ParquetReader::try_new(&position_delete_schema()).unwrap();
+        let batch = reader
+            .read(file_io.read(&location).unwrap())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let positions = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(vec![3, 7, 0], positions.values().to_vec());
+    }
+}
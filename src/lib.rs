@@ -1,2 +1,9 @@
-pub(crate) mod hms;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "arrow")]
+pub mod config;
+#[cfg(feature = "hms")]
+pub mod hms;
 pub mod iceberg;
+#[cfg(feature = "planner")]
+pub mod planner;
@@ -1,68 +1,945 @@
-mod hms;
-mod iceberg;
-
-use iceberg::spec::table_metadata::TableMetadata;
+use rustberg::config::RustbergConfig;
+use rustberg::iceberg::expr::Predicate;
+use rustberg::iceberg::freshness::{check_freshness, parse_duration_ms};
+use rustberg::iceberg::metadata_tables::MetadataTableType;
+use rustberg::iceberg::rows::Value as RowValue;
+use rustberg::iceberg::spec::avro::avro_file_to_json;
+use rustberg::iceberg::spec::manifest::read_manifest_file;
+use rustberg::iceberg::spec::manifest_list::read_manifest_list;
+use rustberg::iceberg::spec::schema::{IcebergType, PrimitiveType};
+use rustberg::iceberg::spec::snapshot::RefType;
+use rustberg::iceberg::spec::table_metadata::TableMetadata;
+use rustberg::iceberg::spec::values::{format_timestamp_ms, Literal};
+use rustberg::iceberg::static_table::StaticTable;
+use rustberg::iceberg::table::Table;
 
 use std::error::Error;
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use thrift::protocol::{TBinaryInputProtocol, TBinaryOutputProtocol};
-use thrift::transport::{TBufferedReadTransport, TBufferedWriteTransport};
-use thrift::transport::{TIoChannel, TTcpChannel};
+use arrow::array::RecordBatch;
+use arrow::datatypes::DataType;
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rustberg::iceberg::catalog::IcebergCatalog;
+use serde_json::{json, Value};
 
-use crate::hms::hms_api::ThriftHiveMetastoreSyncClient;
+const CONFIG_HELP: &str = "The metastore and warehouse are configured in the file named by \
+                           RUSTBERG_CONFIG and in RUSTBERG_* environment variables";
 
-use crate::hms::hms_api::TThriftHiveMetastoreSyncClient;
+#[derive(Parser)]
+#[command(name = "rustberg", about = "Inspect Iceberg tables", after_help = CONFIG_HELP)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
 
-fn main() -> Result<(), Box<dyn Error>> {
-    println!("connect to Hive Metastore on localhost:9083");
-    let mut c = TTcpChannel::new();
-    c.open("localhost:9083")?;
+#[derive(Subcommand)]
+enum Command {
+    #[command(subcommand, about = "Commands on the tables of a namespace")]
+    Tables(TablesCommand),
+    #[command(subcommand, about = "Commands on a table")]
+    Table(TableCommand),
+    #[command(
+        subcommand,
+        about = "Decode a metadata file, manifest list or manifest",
+        after_help = "Files are printed as decoded by rustberg, or as written with --output json \
+                      or yaml"
+    )]
+    Inspect(InspectCommand),
+}
 
-    let (i_chan, o_chan) = c.split()?;
+#[derive(Subcommand)]
+enum TablesCommand {
+    #[command(about = "List the Iceberg tables of a namespace")]
+    List {
+        namespace: String,
+        #[command(flatten)]
+        output: Output,
+    },
+}
 
-    let i_prot = TBinaryInputProtocol::new(TBufferedReadTransport::new(i_chan), true);
-    let o_prot = TBinaryOutputProtocol::new(TBufferedWriteTransport::new(o_chan), true);
+#[derive(Subcommand)]
+enum TableCommand {
+    #[command(about = "Print the location, schema, partitioning and properties of a table")]
+    Describe {
+        #[arg(value_name = "NAMESPACE.NAME")]
+        table: String,
+        #[command(flatten)]
+        output: Output,
+    },
+    #[command(about = "List the snapshots of a table")]
+    Snapshots {
+        #[arg(value_name = "NAMESPACE.NAME")]
+        table: String,
+        #[command(flatten)]
+        output: Output,
+    },
+    #[command(about = "List the data files of a snapshot, the current one by default")]
+    Files {
+        #[arg(value_name = "NAMESPACE.NAME")]
+        table: String,
+        #[arg(long, value_name = "ID")]
+        snapshot: Option<i64>,
+        #[command(flatten)]
+        output: Output,
+    },
+    #[command(
+        about = "Print the first rows of the current snapshot of a table",
+        after_help = "Filters are written like SQL, e.g. \"x > 5 and (name = 'a' or name is \
+                      null)\". Files are read one at a time until enough rows match"
+    )]
+    Head {
+        #[arg(value_name = "NAMESPACE.NAME")]
+        table: String,
+        #[arg(short = 'n', long, value_name = "ROWS", default_value_t = 20)]
+        limit: usize,
+        #[arg(
+            long,
+            value_name = "COLUMN,...",
+            value_delimiter = ',',
+            help = "Top-level columns to print, all of them by default"
+        )]
+        columns: Option<Vec<String>>,
+        #[arg(long = "where", value_name = "FILTER", value_parser = |s: &str| s.parse::<Predicate>())]
+        filter: Option<Predicate>,
+        #[command(flatten)]
+        output: Output,
+    },
+    #[command(
+        about = "Print a metadata table: snapshots, history, manifests, files or partitions"
+    )]
+    Metadata {
+        #[arg(value_name = "NAMESPACE.NAME")]
+        table: String,
+        #[arg(value_name = "TYPE", value_parser = |s: &str| s.parse::<MetadataTableType>())]
+        table_type: MetadataTableType,
+        #[arg(
+            long,
+            value_name = "ID",
+            help = "Snapshot of the manifests, files and partitions, the current one by default"
+        )]
+        snapshot: Option<i64>,
+        #[command(flatten)]
+        output: Output,
+    },
+    #[command(
+        about = "Check that a table was written to recently",
+        after_help = "Exits with 0 when the table is fresh, 1 when it is stale and 2 when it \
+                      can't be checked"
+    )]
+    Freshness {
+        #[arg(long, value_name = "LOCATION")]
+        metadata_location: String,
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration_ms)]
+        max_age: i64,
+        #[arg(long, value_name = "NAME")]
+        branch: Option<String>,
+        #[arg(long, value_name = "NAMESPACE.NAME", default_value = "db.table")]
+        table: String,
+        #[command(flatten)]
+        output: Output,
+    },
+    #[command(about = "Roll the table back to an ancestor of its current snapshot")]
+    Rollback {
+        #[arg(value_name = "NAMESPACE.NAME")]
+        table: String,
+        #[arg(long, value_name = "ID")]
+        snapshot_id: i64,
+        #[command(flatten)]
+        output: Output,
+    },
+    #[command(
+        about = "Expire the snapshots older than a maximum age",
+        after_help = "Branches keep their last snapshots and tagged snapshots are kept, unless \
+                      their retention settings say otherwise"
+    )]
+    ExpireSnapshots {
+        #[arg(value_name = "NAMESPACE.NAME")]
+        table: String,
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration_ms)]
+        older_than: i64,
+        #[arg(
+            long,
+            value_name = "COUNT",
+            default_value_t = 1,
+            help = "Snapshots of every branch kept whatever their age"
+        )]
+        retain_last: usize,
+        #[command(flatten)]
+        output: Output,
+    },
+    #[command(about = "Create a branch at a snapshot, the current one by default")]
+    CreateBranch {
+        #[arg(value_name = "NAMESPACE.NAME")]
+        table: String,
+        name: String,
+        #[arg(long, value_name = "ID")]
+        snapshot_id: Option<i64>,
+        #[command(flatten)]
+        output: Output,
+    },
+    #[command(about = "Create a tag at a snapshot, the current one by default")]
+    CreateTag {
+        #[arg(value_name = "NAMESPACE.NAME")]
+        table: String,
+        name: String,
+        #[arg(long, value_name = "ID")]
+        snapshot_id: Option<i64>,
+        #[command(flatten)]
+        output: Output,
+    },
+    #[command(about = "Drop a branch or tag other than main")]
+    DropRef {
+        #[arg(value_name = "NAMESPACE.NAME")]
+        table: String,
+        name: String,
+        #[command(flatten)]
+        output: Output,
+    },
+}
 
-    let mut client = ThriftHiveMetastoreSyncClient::new(i_prot, o_prot);
+#[derive(Subcommand)]
+enum InspectCommand {
+    #[command(about = "Decode a table metadata file")]
+    Metadata {
+        #[arg(value_name = "PATH|URI")]
+        location: String,
+        #[arg(long, help = "Print the JSON of the file, same as --output json")]
+        json: bool,
+        #[command(flatten)]
+        output: Output,
+    },
+    #[command(about = "Decode the manifests of a manifest list")]
+    ManifestList {
+        #[arg(value_name = "PATH|URI")]
+        location: String,
+        #[arg(
+            long,
+            help = "Print the schema, metadata and records of the Avro file as JSON, same as \
+                    --output json"
+        )]
+        json: bool,
+        #[command(flatten)]
+        output: Output,
+        #[arg(
+            long,
+            value_name = "PATH|URI",
+            help = "Table metadata file whose partition specs and schema decode the partition \
+                    bounds of the manifests"
+        )]
+        metadata: Option<String>,
+    },
+    #[command(about = "Decode the entries of a manifest")]
+    Manifest {
+        #[arg(value_name = "PATH|URI")]
+        location: String,
+        #[arg(
+            long,
+            help = "Print the schema, metadata and records of the Avro file as JSON, same as \
+                    --output json"
+        )]
+        json: bool,
+        #[command(flatten)]
+        output: Output,
+    },
+}
 
-    let dbs = client.get_all_databases()?;
+// Output of every command. JSON and YAML name fields like the table columns, scripts can rely
+// on them
+#[derive(Args)]
+struct Output {
+    #[arg(
+        long = "output",
+        short = 'o',
+        visible_alias = "format",
+        value_enum,
+        default_value_t = Format::Table
+    )]
+    format: Format,
+}
 
-    println!("{:?}", dbs);
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+    Yaml,
+}
 
-    let table = client.get_table("db1".to_string(), "db1v2table1".to_string())?;
-    // println!("{:#?}", table);
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Tables(TablesCommand::List { namespace, output }) => {
+            list_tables(&namespace, output.format)
+        }
+        Command::Table(TableCommand::Describe { table, output }) => {
+            describe_table(&table, output.format)
+        }
+        Command::Table(TableCommand::Snapshots { table, output }) => {
+            list_snapshots(&table, output.format)
+        }
+        Command::Table(TableCommand::Files {
+            table,
+            snapshot,
+            output,
+        }) => list_files(&table, snapshot, output.format),
+        Command::Table(TableCommand::Head {
+            table,
+            limit,
+            columns,
+            filter,
+            output,
+        }) => head(&table, limit, columns, filter, output.format),
+        Command::Table(TableCommand::Metadata {
+            table,
+            table_type,
+            snapshot,
+            output,
+        }) => print_metadata_table(&table, table_type, snapshot, output.format),
+        Command::Table(TableCommand::Freshness {
+            metadata_location,
+            max_age,
+            branch,
+            table,
+            output,
+        }) => {
+            return table_freshness(
+                &metadata_location,
+                max_age,
+                branch.as_deref(),
+                &table,
+                output.format,
+            )
+        }
+        Command::Table(TableCommand::Rollback {
+            table,
+            snapshot_id,
+            output,
+        }) => rollback(&table, snapshot_id, output.format),
+        Command::Table(TableCommand::ExpireSnapshots {
+            table,
+            older_than,
+            retain_last,
+            output,
+        }) => expire_snapshots(&table, older_than, retain_last, output.format),
+        Command::Table(TableCommand::CreateBranch {
+            table,
+            name,
+            snapshot_id,
+            output,
+        }) => create_ref(&table, &name, RefKind::Branch, snapshot_id, output.format),
+        Command::Table(TableCommand::CreateTag {
+            table,
+            name,
+            snapshot_id,
+            output,
+        }) => create_ref(&table, &name, RefKind::Tag, snapshot_id, output.format),
+        Command::Table(TableCommand::DropRef {
+            table,
+            name,
+            output,
+        }) => drop_ref(&table, &name, output.format),
+        Command::Inspect(command) => inspect(command),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(2)
+        }
+    }
+}
 
-    let params = table
-        .parameters
-        .ok_or("Couldn't find parameters attribute in HMS table")?;
-    let metadata_location = params
-        .get("metadata_location")
-        .ok_or("Couldn't find metadata location for table")?;
+// Exits with 0 when the table is fresh, 1 when it is stale and 2 when it can't be checked
+fn table_freshness(
+    metadata_location: &str,
+    max_age_ms: i64,
+    branch: Option<&str>,
+    table: &str,
+    format: Format,
+) -> ExitCode {
+    let check = || -> Result<bool, Box<dyn Error>> {
+        let file_io = RustbergConfig::load()?.file_io()?;
+        let table = StaticTable::from_metadata_location(metadata_location, file_io)?
+            .with_ident(table.parse()?);
+        let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+        let report = check_freshness(&table, branch, max_age_ms, now_ms)?;
+        if format == Format::Table {
+            println!("{}", report);
+        } else {
+            let report = json!({
+                "table": report.table.to_string(),
+                "branch": report.branch,
+                "snapshot_id": report.snapshot_id,
+                "snapshot_timestamp_ms": report.snapshot_timestamp_ms,
+                "age_ms": report.age_ms,
+                "max_age_ms": report.max_age_ms,
+                "stale": report.stale,
+            });
+            print_value(&report, format)?;
+        }
+        Ok(report.stale)
+    };
+    match check() {
+        Ok(false) => ExitCode::SUCCESS,
+        Ok(true) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(2)
+        }
+    }
+}
 
-    println!("{}", metadata_location);
+// Loads a table registered in the configured metastore
+fn load_table(table: &str) -> Result<Table, Box<dyn Error>> {
+    let catalog = RustbergConfig::load()?.hms_catalog()?;
+    Ok(catalog.load_table(&table.parse()?)?)
+}
 
-    // A hack for now
-    let metadata_location = metadata_location.strip_prefix("file:").unwrap();
+fn list_tables(namespace: &str, format: Format) -> Result<(), Box<dyn Error>> {
+    let catalog = RustbergConfig::load()?.hms_catalog()?;
+    let rows = catalog
+        .list_tables(&namespace.parse()?)?
+        .into_iter()
+        .map(|name| vec![json!(namespace), json!(name)])
+        .collect();
+    print_rows(&["namespace", "name"], rows, format)
+}
 
-    let metadata = std::fs::read_to_string(metadata_location).unwrap();
+fn describe_table(table: &str, format: Format) -> Result<(), Box<dyn Error>> {
+    let table = load_table(table)?;
+    let metadata = table.metadata();
+    if format != Format::Table {
+        let description = json!({
+            "metadata-location": table.metadata_location(),
+            "metadata": metadata,
+        });
+        return print_value(&description, format);
+    }
+    let mut rows = vec![
+        vec![json!("name"), json!(format!("{}", table.ident()))],
+        vec![json!("location"), json!(metadata.location)],
+        vec![json!("metadata-location"), json!(table.metadata_location())],
+        vec![json!("format-version"), json!(metadata.format_version)],
+        vec![json!("table-uuid"), json!(metadata.table_uuid.to_string())],
+        vec![
+            json!("last-updated"),
+            json!(format_timestamp_ms(metadata.last_updated_ms)),
+        ],
+        vec![
+            json!("current-snapshot-id"),
+            json!(metadata.current_snapshot_id),
+        ],
+        vec![
+            json!("partition-spec"),
+            serde_json::to_value(metadata.default_partition_spec()?)?,
+        ],
+    ];
+    let mut properties: Vec<_> = metadata.properties.iter().flatten().collect();
+    properties.sort();
+    for (key, value) in properties {
+        rows.push(vec![json!(key), json!(value)]);
+    }
+    print_rows(&["property", "value"], rows, format)?;
+    println!();
+    let rows = metadata
+        .current_schema()?
+        .schema()
+        .fields
+        .iter()
+        .map(|field| {
+            Ok(vec![
+                json!(field.id),
+                json!(field.name),
+                serde_json::to_value(&field.field_type)?,
+                json!(field.required),
+            ])
+        })
+        .collect::<Result<_, serde_json::Error>>()?;
+    print_rows(&["id", "column", "type", "required"], rows, format)
+}
 
-    let metadata: TableMetadata = serde_json::from_str(&metadata).unwrap();
+fn list_snapshots(table: &str, format: Format) -> Result<(), Box<dyn Error>> {
+    let table = load_table(table)?;
+    let metadata = table.metadata();
+    let summary = |snapshot: &rustberg::iceberg::spec::snapshot::SnapshotV2, key: &str| {
+        json!(snapshot.summary.rest.get(key))
+    };
+    let rows = metadata
+        .snapshots
+        .iter()
+        .flatten()
+        .map(|snapshot| {
+            // Branches and tags pointing at the snapshot
+            let mut refs: Vec<_> = metadata
+                .refs
+                .iter()
+                .flatten()
+                .filter(|(_, r)| r.snapshot_id == snapshot.snapshot_id)
+                .map(|(name, _)| name.as_str())
+                .collect();
+            refs.sort();
+            vec![
+                json!(snapshot.snapshot_id),
+                json!(snapshot.parent_snapshot_id),
+                json!(format_timestamp_ms(snapshot.timestamp_ms)),
+                serde_json::to_value(&snapshot.summary.operation).unwrap_or_default(),
+                summary(snapshot, "added-data-files"),
+                summary(snapshot, "total-records"),
+                json!(refs.join(",")),
+                json!(snapshot.manifest_list),
+            ]
+        })
+        .collect();
+    let columns = [
+        "snapshot_id",
+        "parent_id",
+        "committed_at",
+        "operation",
+        "added_files",
+        "total_records",
+        "refs",
+        "manifest_list",
+    ];
+    print_rows(&columns, rows, format)
+}
 
-    println!("{:#?}", metadata);
+fn list_files(table: &str, snapshot_id: Option<i64>, format: Format) -> Result<(), Box<dyn Error>> {
+    let table = load_table(table)?;
+    let scan = match snapshot_id {
+        Some(snapshot_id) => table.scan().with_snapshot_id(snapshot_id),
+        None => table.scan(),
+    };
+    let plan = scan.plan_files()?;
+    let rows = plan
+        .tasks()
+        .iter()
+        .map(|task| {
+            let file = &task.data_file;
+            let partition: Vec<Value> = file
+                .partition
+                .iter()
+                .map(|value| {
+                    value
+                        .as_ref()
+                        .map(|v| json!(v.to_string()))
+                        .unwrap_or_default()
+                })
+                .collect();
+            vec![
+                json!(file.file_path),
+                json!(file.file_format.to_string()),
+                json!(partition),
+                json!(file.record_count),
+                json!(file.file_size_in_bytes),
+                json!(task.deletes.len()),
+            ]
+        })
+        .collect();
+    let columns = [
+        "file_path",
+        "file_format",
+        "partition",
+        "record_count",
+        "file_size_in_bytes",
+        "delete_files",
+    ];
+    print_rows(&columns, rows, format)
+}
 
-    // Temporary: try to decode a manifest list avro file directly
-    // let manifest_list_location = "/Users/jsiva/sw/code/rust/rustberg/test_warehouse/db1.db/db1v2table1/metadata/snap-1644494390386601185-1-3e48831e-8e8e-418e-92ed-1e01e655dae2.avro";
-    let manifest_list_location = "/Users/jsiva/sw/code/rust/rustberg/test_warehouse/db1.db/db1v1table1/metadata/snap-9164160847201777787-1-a3f00225-0cde-48c0-baab-b11dd79d821b.avro";
-    let reader = apache_avro::Reader::new(std::fs::File::open(manifest_list_location).unwrap());
+fn head(
+    table: &str,
+    limit: usize,
+    columns: Option<Vec<String>>,
+    filter: Option<Predicate>,
+    format: Format,
+) -> Result<(), Box<dyn Error>> {
+    let table = load_table(table)?;
+    let columns: Option<Vec<&str>> = columns
+        .as_ref()
+        .map(|columns| columns.iter().map(String::as_str).collect());
+    let schema = table.metadata().current_schema()?.schema();
+    let mut names: Vec<String> = vec![];
+    let mut rows = vec![];
+    // Rows are read lazily, the files after the first `limit` matching rows aren't read
+    for row in table.rows(filter, columns.as_deref())?.take(limit) {
+        let row = row?;
+        if names.is_empty() {
+            names = row.columns().to_vec();
+        }
+        let values = row
+            .columns()
+            .iter()
+            .zip(row.values())
+            .map(|(column, value)| match schema.field_by_name(column) {
+                Some(field) => row_value(value.as_ref(), &field.field_type),
+                None => Value::Null,
+            })
+            .collect();
+        rows.push(values);
+    }
+    if names.is_empty() {
+        names = match &columns {
+            Some(columns) => columns.iter().map(|c| c.to_string()).collect(),
+            None => schema.fields.iter().map(|f| f.name.clone()).collect(),
+        };
+    }
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    print_rows(&names, rows, format)
+}
 
-    for value in reader.unwrap() {
-        println!(
-            "{:#?}",
-            apache_avro::from_value::<crate::iceberg::spec::manifest_list::ManifestListV2>(
-                &value.unwrap()
-            )
-        )
+// JSON value of a row value. Numbers and booleans are kept as JSON numbers and booleans, dates
+// and times are printed in ISO format and decimals with their scale
+fn row_value(value: Option<&RowValue>, field_type: &IcebergType) -> Value {
+    let value = match value {
+        Some(value) => value,
+        None => return Value::Null,
+    };
+    match (value, field_type) {
+        (RowValue::Primitive(literal), IcebergType::Primitive(primitive)) => {
+            match (literal, primitive) {
+                (Literal::Boolean(v), _) => json!(v),
+                (Literal::Int(v), _) => json!(v),
+                (Literal::Long(v), _) => json!(v),
+                (Literal::Float(v), _) => json!(v),
+                (Literal::Double(v), _) => json!(v),
+                (Literal::Date(days), _) => {
+                    json!(format_timestamp_ms(*days as i64 * 86_400_000)[..10])
+                }
+                (Literal::Time(micros), _) => {
+                    let timestamp = format_timestamp_ms(micros.div_euclid(1000));
+                    json!(format!(
+                        "{}{:03}",
+                        &timestamp[11..23],
+                        micros.rem_euclid(1000)
+                    ))
+                }
+                (Literal::Timestamp(micros), _) => {
+                    json!(format_timestamp_ms(micros.div_euclid(1000)).trim_end_matches('Z'))
+                }
+                (Literal::Timestamptz(micros), _) => {
+                    json!(format_timestamp_ms(micros.div_euclid(1000)))
+                }
+                (Literal::Decimal(unscaled), PrimitiveType::Decimal { scale, .. }) => {
+                    let digits = format!(
+                        "{:0>width$}",
+                        unscaled.unsigned_abs(),
+                        width = *scale as usize + 1
+                    );
+                    let (integer, fraction) = digits.split_at(digits.len() - *scale as usize);
+                    let sign = if *unscaled < 0 { "-" } else { "" };
+                    match fraction.is_empty() {
+                        true => json!(format!("{}{}", sign, integer)),
+                        false => json!(format!("{}{}.{}", sign, integer, fraction)),
+                    }
+                }
+                (literal, _) => json!(literal.to_string()),
+            }
+        }
+        (RowValue::Struct(values), IcebergType::Struct(fields)) => Value::Object(
+            fields
+                .fields
+                .iter()
+                .zip(values)
+                .map(|(field, value)| {
+                    (
+                        field.name.clone(),
+                        row_value(value.as_ref(), &field.field_type),
+                    )
+                })
+                .collect(),
+        ),
+        (RowValue::List(values), IcebergType::List(list)) => Value::Array(
+            values
+                .iter()
+                .map(|value| row_value(value.as_ref(), &list.element))
+                .collect(),
+        ),
+        // Maps are printed as objects keyed by the keys as printed in table cells
+        (RowValue::Map(entries), IcebergType::Map(map)) => Value::Object(
+            entries
+                .iter()
+                .map(|(key, value)| {
+                    let key = match row_value(Some(key), &map.key) {
+                        Value::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    (key, row_value(value.as_ref(), &map.value))
+                })
+                .collect(),
+        ),
+        _ => Value::Null,
     }
+}
+
+#[derive(Clone, Copy)]
+enum RefKind {
+    Branch,
+    Tag,
+}
 
+fn rollback(table: &str, snapshot_id: i64, format: Format) -> Result<(), Box<dyn Error>> {
+    let catalog = RustbergConfig::load()?.hms_catalog()?;
+    let table = catalog.load_table(&table.parse()?)?;
+    let table = table.rollback_to(snapshot_id).commit(&catalog)?;
+    print_refs(&table, format)
+}
+
+fn expire_snapshots(
+    table: &str,
+    older_than_ms: i64,
+    retain_last: usize,
+    format: Format,
+) -> Result<(), Box<dyn Error>> {
+    let catalog = RustbergConfig::load()?.hms_catalog()?;
+    let table = catalog.load_table(&table.parse()?)?;
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+    let result = table
+        .expire_snapshots(now_ms - older_than_ms, retain_last)
+        .commit(&catalog)?;
+    let rows = vec![vec![
+        json!(result.expired_snapshot_ids),
+        json!(result.removed_refs),
+        json!(result.unreachable_files.len()),
+    ]];
+    let columns = ["expired_snapshot_ids", "removed_refs", "unreachable_files"];
+    print_rows(&columns, rows, format)
+}
+
+fn create_ref(
+    table: &str,
+    name: &str,
+    kind: RefKind,
+    snapshot_id: Option<i64>,
+    format: Format,
+) -> Result<(), Box<dyn Error>> {
+    let catalog = RustbergConfig::load()?.hms_catalog()?;
+    let table = catalog.load_table(&table.parse()?)?;
+    let snapshot_id = match snapshot_id {
+        Some(snapshot_id) => snapshot_id,
+        None => {
+            table
+                .metadata()
+                .current_snapshot()
+                .ok_or("The table has no current snapshot, pass --snapshot-id")?
+                .snapshot_id
+        }
+    };
+    let refs = table.manage_refs();
+    let refs = match kind {
+        RefKind::Branch => refs.create_branch(name, snapshot_id),
+        RefKind::Tag => refs.create_tag(name, snapshot_id),
+    };
+    print_refs(&refs.commit(&catalog)?, format)
+}
+
+fn drop_ref(table: &str, name: &str, format: Format) -> Result<(), Box<dyn Error>> {
+    let catalog = RustbergConfig::load()?.hms_catalog()?;
+    let table = catalog.load_table(&table.parse()?)?;
+    let table = table.manage_refs().drop_ref(name).commit(&catalog)?;
+    print_refs(&table, format)
+}
+
+// Prints the branches and tags of a table once changed
+fn print_refs(table: &Table, format: Format) -> Result<(), Box<dyn Error>> {
+    let mut refs: Vec<_> = table.metadata().refs.iter().flatten().collect();
+    refs.sort_by_key(|(name, _)| name.as_str());
+    let rows = refs
+        .into_iter()
+        .map(|(name, snapshot_ref)| {
+            let ref_type = match snapshot_ref.ref_type {
+                RefType::Branch { .. } => "branch",
+                RefType::Tag => "tag",
+            };
+            vec![
+                json!(name),
+                json!(ref_type),
+                json!(snapshot_ref.snapshot_id),
+            ]
+        })
+        .collect();
+    print_rows(&["name", "type", "snapshot_id"], rows, format)
+}
+
+fn print_metadata_table(
+    table: &str,
+    table_type: MetadataTableType,
+    snapshot_id: Option<i64>,
+    format: Format,
+) -> Result<(), Box<dyn Error>> {
+    let table = load_table(table)?;
+    let metadata_table = match snapshot_id {
+        Some(snapshot_id) => table
+            .metadata_table(table_type)
+            .with_snapshot_id(snapshot_id),
+        None => table.metadata_table(table_type),
+    };
+    let batch = metadata_table.to_record_batch()?;
+    let schema = batch.schema();
+    let columns: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+    print_rows(&columns, batch_rows(&batch)?, format)
+}
+
+// Values of the rows of a batch as displayed by arrow. Integers and booleans are kept as JSON
+// numbers and booleans
+fn batch_rows(batch: &RecordBatch) -> Result<Vec<Vec<Value>>, Box<dyn Error>> {
+    let options = FormatOptions::default();
+    let formatters = batch
+        .columns()
+        .iter()
+        .map(|column| ArrayFormatter::try_new(column.as_ref(), &options))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut rows = vec![];
+    for row in 0..batch.num_rows() {
+        let values = batch
+            .columns()
+            .iter()
+            .zip(&formatters)
+            .map(|(column, formatter)| {
+                if column.is_null(row) {
+                    return Ok(Value::Null);
+                }
+                let value = formatter.value(row).to_string();
+                Ok(match column.data_type() {
+                    DataType::Int32 | DataType::Int64 | DataType::Boolean => {
+                        serde_json::from_str(&value)?
+                    }
+                    _ => Value::String(value),
+                })
+            })
+            .collect::<Result<_, serde_json::Error>>()?;
+        rows.push(values);
+    }
+    Ok(rows)
+}
+
+// Prints a file as rustberg decodes it, or as written in JSON or YAML. Avro files are printed
+// record by record so that files rustberg fails to decode can still be looked at
+fn inspect(command: InspectCommand) -> Result<(), Box<dyn Error>> {
+    let (location, json, format) = match &command {
+        InspectCommand::Metadata {
+            location,
+            json,
+            output,
+        }
+        | InspectCommand::ManifestList {
+            location,
+            json,
+            output,
+            ..
+        }
+        | InspectCommand::Manifest {
+            location,
+            json,
+            output,
+        } => (location, *json, output.format),
+    };
+    let format = match json {
+        true => Format::Json,
+        false => format,
+    };
+    let file_io = RustbergConfig::load()?.file_io()?;
+    let data = file_io.read(location)?;
+    match command {
+        InspectCommand::Metadata { .. } if format != Format::Table => {
+            let metadata: Value = serde_json::from_slice(&data)?;
+            print_value(&metadata, format)?;
+        }
+        InspectCommand::Metadata { .. } => {
+            let metadata: TableMetadata = serde_json::from_slice(&data)?;
+            println!("{:#?}", metadata);
+        }
+        _ if format != Format::Table => print_value(&avro_file_to_json(&data)?, format)?,
+        InspectCommand::ManifestList { metadata, .. } => {
+            let metadata = match metadata {
+                Some(location) => {
+                    let metadata: TableMetadata =
+                        serde_json::from_slice(&file_io.read(&location)?)?;
+                    Some(metadata.into_v2()?)
+                }
+                None => None,
+            };
+            for manifest in read_manifest_list(&data)? {
+                println!("{:#?}", manifest);
+                let Some(metadata) = &metadata else {
+                    continue;
+                };
+                let spec = metadata
+                    .partition_spec_by_id(manifest.partition_spec_id)
+                    .ok_or_else(|| {
+                        format!(
+                            "Partition spec {} of manifest {} is missing from table metadata",
+                            manifest.partition_spec_id, manifest.manifest_path
+                        )
+                    })?;
+                let schema = metadata.current_schema()?.schema();
+                for summary in manifest.decoded_partitions(spec, schema)? {
+                    println!("  {}", summary);
+                }
+            }
+        }
+        InspectCommand::Manifest { .. } => {
+            for entry in read_manifest_file(&data)? {
+                println!("{:#?}", entry);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Prints a value as pretty JSON or YAML
+fn print_value(value: &Value, format: Format) -> Result<(), Box<dyn Error>> {
+    match format {
+        Format::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        Format::Json | Format::Table => println!("{}", serde_json::to_string_pretty(value)?),
+    }
+    Ok(())
+}
+
+// Prints rows as a list of objects keyed by column in JSON or YAML, or as columns aligned on
+// their widest value. JSON lists are printed on a single line
+fn print_rows(
+    columns: &[&str],
+    rows: Vec<Vec<Value>>,
+    format: Format,
+) -> Result<(), Box<dyn Error>> {
+    if format != Format::Table {
+        let objects: Vec<Value> = rows
+            .into_iter()
+            .map(|row| {
+                let fields = columns.iter().map(|c| c.to_string()).zip(row);
+                Value::Object(fields.collect())
+            })
+            .collect();
+        match format {
+            Format::Json => println!("{}", Value::Array(objects)),
+            _ => print_value(&Value::Array(objects), format)?,
+        }
+        return Ok(());
+    }
+    let cell = |value: &Value| match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let rows: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| row.iter().map(cell).collect())
+        .collect();
+    let mut widths: Vec<usize> = columns.iter().map(|c| c.len()).collect();
+    for row in &rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    let header: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+    for row in std::iter::once(&header).chain(&rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{:width$}", value, width = width))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
     Ok(())
 }
@@ -0,0 +1,576 @@
+// gRPC service planning scans on behalf of executors written in other languages. Executors name
+// a table of the catalog of the service along with the scan to plan, and get back the data files
+// to read. All the Iceberg metadata handling (manifests, partition specs, metrics pruning) stays on
+// the Rust side
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use crate::iceberg::catalog::IcebergCatalog;
+use crate::iceberg::error::{IcebergError, Result};
+use crate::iceberg::expr::Predicate;
+use crate::iceberg::ident::TableIdent;
+use crate::iceberg::spec::manifest::DataContentType;
+use crate::iceberg::spec::values::Literal;
+use crate::iceberg::table::Table;
+
+pub mod proto {
+    tonic::include_proto!("rustberg.planner.v1");
+}
+
+use proto::expression::Expr;
+use proto::planner_server::Planner;
+use proto::predicate_expression::Operator;
+use proto::value::Value;
+
+pub use proto::planner_server::PlannerServer;
+
+type TaskStream = Pin<Box<dyn Stream<Item = std::result::Result<proto::ScanTask, Status>> + Send>>;
+
+// Plans not streamed within the TTL are dropped, as are the oldest plans beyond the maximum
+// number, so that plans abandoned by executors don't accumulate
+pub const DEFAULT_PLAN_TTL: Duration = Duration::from_secs(10 * 60);
+pub const DEFAULT_MAX_PLANS: usize = 1024;
+
+pub struct PlannerService {
+    // Tables are only resolved through the catalog, executors can't have other files read
+    catalog: Arc<dyn IcebergCatalog>,
+    // Planned scans waiting to be streamed, keyed by plan id
+    plans: Mutex<HashMap<String, PendingPlan>>,
+    plan_ttl: Duration,
+    max_plans: usize,
+}
+
+struct PendingPlan {
+    tasks: Vec<proto::ScanTask>,
+    planned_at: Instant,
+}
+
+impl PlannerService {
+    pub fn new(catalog: Arc<dyn IcebergCatalog>) -> Self {
+        PlannerService {
+            catalog,
+            plans: Mutex::new(HashMap::new()),
+            plan_ttl: DEFAULT_PLAN_TTL,
+            max_plans: DEFAULT_MAX_PLANS,
+        }
+    }
+
+    // Time executors have to stream the tasks of a plan
+    pub fn with_plan_ttl(mut self, ttl: Duration) -> Self {
+        self.plan_ttl = ttl;
+        self
+    }
+
+    // Number of plans waiting to be streamed, the oldest are dropped to make room for new ones
+    pub fn with_max_plans(mut self, max_plans: usize) -> Self {
+        self.max_plans = max_plans.max(1);
+        self
+    }
+
+    fn add_plan(&self, plan_id: String, tasks: Vec<proto::ScanTask>) {
+        let mut plans = self.plans.lock().unwrap();
+        let now = Instant::now();
+        plans.retain(|_, plan| now.duration_since(plan.planned_at) < self.plan_ttl);
+        while plans.len() >= self.max_plans {
+            let oldest = plans
+                .iter()
+                .min_by_key(|(_, plan)| plan.planned_at)
+                .map(|(plan_id, _)| plan_id.clone())
+                .unwrap();
+            plans.remove(&oldest);
+        }
+        plans.insert(
+            plan_id,
+            PendingPlan {
+                tasks,
+                planned_at: now,
+            },
+        );
+    }
+
+    fn take_plan(&self, plan_id: &str) -> Result<Vec<proto::ScanTask>> {
+        self.plans
+            .lock()
+            .unwrap()
+            .remove(plan_id)
+            .filter(|plan| plan.planned_at.elapsed() < self.plan_ttl)
+            .map(|plan| plan.tasks)
+            .ok_or_else(|| IcebergError::NotFound(format!("Plan {}", plan_id)))
+    }
+
+    // The table of the catalog, at the given metadata location if any. The location must be the
+    // current or a previous metadata file of the table, as recorded in its metadata log
+    fn open_table(&self, table: Option<proto::TableIdentifier>) -> Result<Table> {
+        let table = table.ok_or_else(|| invalid("Missing table"))?;
+        let ident = TableIdent::try_new(table.namespace.parse()?, &table.name)?;
+        let current = self.catalog.load_table(&ident)?;
+        let location = table.metadata_location;
+        if location.is_empty() || location == current.metadata_location() {
+            return Ok(current);
+        }
+        let logged = current
+            .metadata()
+            .metadata_log
+            .iter()
+            .flatten()
+            .any(|log| log.metadata_file == location);
+        if !logged {
+            return Err(IcebergError::Invalid(format!(
+                "{} is not a metadata file of table {}",
+                location, ident
+            )));
+        }
+        Table::load(ident, location, current.file_io().clone())
+    }
+
+    fn plan(&self, request: proto::PlanScanRequest) -> Result<proto::PlanScanResponse> {
+        let table = self.open_table(request.table)?;
+        let mut scan = table.scan();
+        if let Some(snapshot_id) = request.snapshot_id {
+            scan = scan.with_snapshot_id(snapshot_id);
+        }
+        if !request.columns.is_empty() {
+            let columns: Vec<&str> = request.columns.iter().map(String::as_str).collect();
+            scan = scan.select(&columns);
+        }
+        if let Some(filter) = &request.filter {
+            scan = scan.filter(predicate_from_proto(filter)?);
+        }
+        if request.case_insensitive {
+            scan = scan.case_insensitive();
+        }
+        if !request.spec_ids.is_empty() {
+            scan = scan.with_spec_ids(&request.spec_ids);
+        }
+        if let Some(split_size) = request.split_size {
+            scan = scan.with_split_size(split_size);
+        }
+        let plan = scan.plan_files()?;
+
+        let metadata = table.metadata();
+        let tasks = plan
+            .tasks()
+            .iter()
+            .map(|task| proto::ScanTask {
+                file_path: task.data_file.file_path.clone(),
+                file_format: task.data_file.file_format.to_string(),
+                record_count: task.data_file.record_count,
+                file_size_in_bytes: task.data_file.file_size_in_bytes,
+                spec_id: task.spec_id,
+                partition: metadata
+                    .partition_spec_by_id(task.spec_id)
+                    .map(|spec| spec.partition_path(&task.data_file.partition))
+                    .unwrap_or_default(),
+                sequence_number: task.sequence_number,
+                deletes: task
+                    .deletes
+                    .iter()
+                    .map(|delete| proto::DeleteFile {
+                        file_path: delete.file_path.clone(),
+                        file_format: delete.file_format.to_string(),
+                        content: match delete.content {
+                            DataContentType::EqualityDeletes => {
+                                proto::delete_file::Content::EqualityDeletes
+                            }
+                            _ => proto::delete_file::Content::PositionDeletes,
+                        } as i32,
+                        record_count: delete.record_count,
+                        file_size_in_bytes: delete.file_size_in_bytes,
+                        equality_ids: delete.equality_ids.clone().unwrap_or_default(),
+                        content_offset: delete.content_offset,
+                        content_size_in_bytes: delete.content_size_in_bytes,
+                    })
+                    .collect(),
+                start: task.start,
+                length: task.length,
+            })
+            .collect::<Vec<_>>();
+        let fingerprint = plan.fingerprint();
+        // Files split in several tasks are counted once
+        let estimate = plan.estimate();
+        let response = proto::PlanScanResponse {
+            plan_id: Uuid::new_v4().to_string(),
+            fingerprint: Some(proto::Fingerprint {
+                table_uuid: fingerprint.table_uuid.to_string(),
+                metadata_location: fingerprint.metadata_location.clone(),
+                snapshot_id: fingerprint.snapshot_id,
+            }),
+            task_count: tasks.len() as i64,
+            total_bytes: estimate.total_bytes,
+            total_records: estimate.record_count,
+        };
+        self.add_plan(response.plan_id.clone(), tasks);
+        Ok(response)
+    }
+}
+
+#[tonic::async_trait]
+impl Planner for Arc<PlannerService> {
+    async fn load_table(
+        &self,
+        request: Request<proto::LoadTableRequest>,
+    ) -> std::result::Result<Response<proto::LoadTableResponse>, Status> {
+        let service = self.clone();
+        let response = blocking(move || {
+            let table = service.open_table(request.into_inner().table)?;
+            let metadata = table.metadata();
+            Ok(proto::LoadTableResponse {
+                table_uuid: metadata.table_uuid.to_string(),
+                format_version: table.format_version(),
+                current_snapshot_id: metadata
+                    .current_snapshot()
+                    .map(|snapshot| snapshot.snapshot_id),
+                schema_json: serde_json::to_string(metadata.current_schema()?)?,
+                location: metadata.location.clone(),
+            })
+        })
+        .await?;
+        Ok(Response::new(response))
+    }
+
+    async fn plan_scan(
+        &self,
+        request: Request<proto::PlanScanRequest>,
+    ) -> std::result::Result<Response<proto::PlanScanResponse>, Status> {
+        let service = self.clone();
+        let response = blocking(move || service.plan(request.into_inner())).await?;
+        Ok(Response::new(response))
+    }
+
+    type StreamTasksStream = TaskStream;
+
+    async fn stream_tasks(
+        &self,
+        request: Request<proto::StreamTasksRequest>,
+    ) -> std::result::Result<Response<Self::StreamTasksStream>, Status> {
+        let plan_id = request.into_inner().plan_id;
+        let tasks = self.take_plan(&plan_id).map_err(status)?;
+        let stream: TaskStream = Box::pin(tokio_stream::iter(tasks.into_iter().map(Ok)));
+        Ok(Response::new(stream))
+    }
+}
+
+// Metadata and manifests are read with blocking IO, off the async worker threads
+async fn blocking<T, F>(f: F) -> std::result::Result<T, Status>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .map_err(status)
+}
+
+fn invalid(message: &str) -> IcebergError {
+    IcebergError::Invalid(message.to_string())
+}
+
+fn status(error: IcebergError) -> Status {
+    match error {
+        IcebergError::NotFound(_) => Status::not_found(error.to_string()),
+        IcebergError::Invalid(_) => Status::invalid_argument(error.to_string()),
+        IcebergError::Unsupported(_) => Status::unimplemented(error.to_string()),
+        IcebergError::Forbidden(_) => Status::permission_denied(error.to_string()),
+        IcebergError::ReadOnly(_) => Status::failed_precondition(error.to_string()),
+        IcebergError::CommitConflict(_) => Status::aborted(error.to_string()),
+        _ => Status::internal(error.to_string()),
+    }
+}
+
+fn predicate_from_proto(expression: &proto::Expression) -> Result<Predicate> {
+    let child = |expression: &Option<Box<proto::Expression>>| {
+        expression
+            .as_deref()
+            .ok_or_else(|| invalid("Missing operand of expression"))
+            .and_then(predicate_from_proto)
+    };
+    match expression
+        .expr
+        .as_ref()
+        .ok_or_else(|| invalid("Empty expression"))?
+    {
+        Expr::Constant(true) => Ok(Predicate::AlwaysTrue),
+        Expr::Constant(false) => Ok(Predicate::AlwaysFalse),
+        Expr::And(and) => Ok(child(&and.left)?.and(child(&and.right)?)),
+        Expr::Or(or) => Ok(child(&or.left)?.or(child(&or.right)?)),
+        Expr::Not(not) => Ok(predicate_from_proto(not)?.negate()),
+        Expr::Predicate(predicate) => {
+            let column = predicate.column.as_str();
+            let values = predicate
+                .values
+                .iter()
+                .map(literal_from_proto)
+                .collect::<Result<Vec<_>>>()?;
+            let value = || -> Result<Literal> {
+                match values.as_slice() {
+                    [value] => Ok(value.clone()),
+                    _ => Err(IcebergError::Invalid(format!(
+                        "Comparison on {} needs exactly one value",
+                        column
+                    ))),
+                }
+            };
+            let op = Operator::try_from(predicate.op)
+                .map_err(|_| IcebergError::Invalid(format!("Operator {}", predicate.op)))?;
+            Ok(match op {
+                Operator::Unspecified => return Err(invalid("Missing predicate operator")),
+                Operator::IsNull => Predicate::is_null(column),
+                Operator::NotNull => Predicate::not_null(column),
+                Operator::IsNan => Predicate::is_nan(column),
+                Operator::NotNan => Predicate::not_nan(column),
+                Operator::LessThan => Predicate::less_than(column, value()?),
+                Operator::LessThanOrEq => Predicate::less_than_or_eq(column, value()?),
+                Operator::GreaterThan => Predicate::greater_than(column, value()?),
+                Operator::GreaterThanOrEq => Predicate::greater_than_or_eq(column, value()?),
+                Operator::Eq => Predicate::equal(column, value()?),
+                Operator::NotEq => Predicate::not_equal(column, value()?),
+                Operator::StartsWith => match value()? {
+                    Literal::String(prefix) => Predicate::starts_with(column, &prefix),
+                    _ => return Err(invalid("starts_with needs a string")),
+                },
+                Operator::In => Predicate::is_in(column, values),
+                Operator::NotIn => Predicate::not_in(column, values),
+            })
+        }
+    }
+}
+
+fn literal_from_proto(value: &proto::Value) -> Result<Literal> {
+    match value.value.as_ref().ok_or_else(|| invalid("Empty value"))? {
+        Value::Boolean(v) => Ok(Literal::Boolean(*v)),
+        Value::Long(v) => Ok(Literal::Long(*v)),
+        Value::Double(v) => Ok(Literal::Double(*v)),
+        Value::String(v) => Ok(Literal::String(v.clone())),
+        Value::Binary(v) => Ok(Literal::Binary(v.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_stream::StreamExt;
+
+    use super::*;
+    use crate::iceberg::test_utils::{append_ids, TestCatalog};
+
+    fn identifier(table: &Table) -> Option<proto::TableIdentifier> {
+        Some(proto::TableIdentifier {
+            namespace: "db".to_string(),
+            name: "table".to_string(),
+            metadata_location: table.metadata_location().to_string(),
+        })
+    }
+
+    fn greater_than(column: &str, value: i64) -> proto::Expression {
+        proto::Expression {
+            expr: Some(Expr::Predicate(proto::PredicateExpression {
+                op: Operator::GreaterThan as i32,
+                column: column.to_string(),
+                values: vec![proto::Value {
+                    value: Some(Value::Long(value)),
+                }],
+            })),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_and_stream_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = Arc::new(TestCatalog::new());
+        let table = catalog.create_table("db", "table", dir.path());
+        let table = append_ids(&catalog, &table, &[1, 2, 3]);
+        let table = append_ids(&catalog, &table, &[10, 11]);
+        let service = Arc::new(PlannerService::new(catalog));
+
+        let loaded = service
+            .load_table(Request::new(proto::LoadTableRequest {
+                table: identifier(&table),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(2, loaded.format_version);
+        assert_eq!(
+            table.metadata().current_snapshot_id,
+            loaded.current_snapshot_id
+        );
+
+        let plan = service
+            .plan_scan(Request::new(proto::PlanScanRequest {
+                table: identifier(&table),
+                filter: Some(greater_than("id", 5)),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(1, plan.task_count);
+        assert_eq!(2, plan.total_records);
+
+        let other_spec = service
+            .plan_scan(Request::new(proto::PlanScanRequest {
+                table: identifier(&table),
+                spec_ids: vec![1],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(0, other_spec.task_count);
+
+        let tasks = service
+            .stream_tasks(Request::new(proto::StreamTasksRequest {
+                plan_id: plan.plan_id.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(1, tasks.len());
+        assert_eq!(2, tasks[0].as_ref().unwrap().record_count);
+
+        // Plans can only be streamed once
+        let error = service
+            .stream_tasks(Request::new(proto::StreamTasksRequest {
+                plan_id: plan.plan_id,
+            }))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(tonic::Code::NotFound, error.code());
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_plans_are_evicted() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = Arc::new(TestCatalog::new());
+        let table = catalog.create_table("db", "table", dir.path());
+        let plan = |service: Arc<PlannerService>| {
+            let table = identifier(&table);
+            async move {
+                service
+                    .plan_scan(Request::new(proto::PlanScanRequest {
+                        table,
+                        ..Default::default()
+                    }))
+                    .await
+                    .unwrap()
+                    .into_inner()
+                    .plan_id
+            }
+        };
+        let stream = |service: Arc<PlannerService>, plan_id: String| async move {
+            service
+                .stream_tasks(Request::new(proto::StreamTasksRequest { plan_id }))
+                .await
+                .map(drop)
+                .map_err(|e| e.code())
+        };
+
+        // Beyond the maximum number of plans, the oldest are dropped
+        let service = Arc::new(PlannerService::new(catalog.clone()).with_max_plans(2));
+        let abandoned = plan(service.clone()).await;
+        let first = plan(service.clone()).await;
+        let second = plan(service.clone()).await;
+        assert_eq!(2, service.plans.lock().unwrap().len());
+        assert_eq!(
+            Err(tonic::Code::NotFound),
+            stream(service.clone(), abandoned).await
+        );
+        assert_eq!(Ok(()), stream(service.clone(), first).await);
+        assert_eq!(Ok(()), stream(service.clone(), second).await);
+
+        // Expired plans can't be streamed and are dropped when planning
+        let service = Arc::new(PlannerService::new(catalog.clone()).with_plan_ttl(Duration::ZERO));
+        let expired = plan(service.clone()).await;
+        plan(service.clone()).await;
+        assert_eq!(1, service.plans.lock().unwrap().len());
+        assert_eq!(
+            Err(tonic::Code::NotFound),
+            stream(service.clone(), expired).await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_filters_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = Arc::new(TestCatalog::new());
+        let table = catalog.create_table("db", "table", dir.path());
+        let service = Arc::new(PlannerService::new(catalog));
+
+        let error = service
+            .plan_scan(Request::new(proto::PlanScanRequest {
+                table: identifier(&table),
+                filter: Some(greater_than("missing", 5)),
+                ..Default::default()
+            }))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(tonic::Code::NotFound, error.code());
+
+        let error = predicate_from_proto(&proto::Expression { expr: None })
+            .err()
+            .unwrap();
+        assert_eq!(tonic::Code::InvalidArgument, status(error).code());
+    }
+
+    #[tokio::test]
+    async fn test_tables_are_resolved_through_the_catalog() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = Arc::new(TestCatalog::new());
+        let created = catalog.create_table("db", "table", dir.path());
+        let table = append_ids(&catalog, &created, &[1, 2]);
+        let other_dir = tempfile::tempdir().unwrap();
+        let other = catalog.create_table("db", "other", other_dir.path());
+        let service = Arc::new(PlannerService::new(catalog));
+        let load = |namespace: &str, name: &str, metadata_location: &str| {
+            let request = Request::new(proto::LoadTableRequest {
+                table: Some(proto::TableIdentifier {
+                    namespace: namespace.to_string(),
+                    name: name.to_string(),
+                    metadata_location: metadata_location.to_string(),
+                }),
+            });
+            let service = service.clone();
+            async move {
+                service
+                    .load_table(request)
+                    .await
+                    .map(|response| response.into_inner().current_snapshot_id)
+                    .map_err(|e| e.code())
+            }
+        };
+
+        // Without a location the current metadata of the table is used, previous metadata files
+        // of the table can be given
+        let current = table.metadata().current_snapshot_id;
+        assert_eq!(Ok(current), load("db", "table", "").await);
+        assert_eq!(
+            Ok(None),
+            load("db", "table", created.metadata_location()).await
+        );
+
+        // Other files are rejected, even the metadata files of other tables
+        for location in [
+            other.metadata_location().to_string(),
+            format!("file:{}/secret.json", dir.path().display()),
+        ] {
+            assert_eq!(
+                Err(tonic::Code::InvalidArgument),
+                load("db", "table", &location).await
+            );
+        }
+        assert_eq!(
+            Err(tonic::Code::NotFound),
+            load("db", "missing", table.metadata_location()).await
+        );
+    }
+}
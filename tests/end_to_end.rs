@@ -0,0 +1,300 @@
+// End to end tests going through the public API only: a catalog implemented outside of the
+// crate commits tables written with rustberg's own writers, and scans read them back from the
+// FileIO
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use arrow::array::{Array, Int64Array, RecordBatch, StringArray};
+use rustberg::iceberg::arrow::schema_to_arrow;
+use rustberg::iceberg::catalog::{write_metadata_file, IcebergCatalog};
+use rustberg::iceberg::error::{IcebergError, Result};
+use rustberg::iceberg::expr::Predicate;
+use rustberg::iceberg::ident::TableIdent;
+use rustberg::iceberg::io::{FileIO, LocalFileIO};
+use rustberg::iceberg::spec::partition_spec::{PartitionField, PartitionSpec, Transform};
+use rustberg::iceberg::spec::schema::{
+    IcebergSchemaV2, IcebergType, PrimitiveType, StructField, StructType,
+};
+use rustberg::iceberg::spec::snapshot::Operation;
+use rustberg::iceberg::spec::sort_orders::SortOrders;
+use rustberg::iceberg::spec::table_metadata::TableMetadataV2;
+use rustberg::iceberg::spec::values::Literal;
+use rustberg::iceberg::table::Table;
+use rustberg::iceberg::writer::partitioned::PartitionedWriter;
+use rustberg::iceberg::writer::position_delete::PositionDeleteWriter;
+use uuid::Uuid;
+
+#[cfg(feature = "hms")]
+#[path = "end_to_end/containers.rs"]
+mod containers;
+
+// Catalog keeping metadata locations in a JSON file of the warehouse, like a minimal metastore
+#[derive(Debug)]
+struct FileCatalog {
+    file_io: Arc<dyn FileIO>,
+    warehouse: String,
+    lock: Mutex<()>,
+}
+
+impl FileCatalog {
+    fn new(dir: &Path) -> Self {
+        FileCatalog {
+            file_io: Arc::new(LocalFileIO::new()),
+            warehouse: format!("file:{}", dir.display()),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn registry_location(&self) -> String {
+        format!("{}/catalog.json", self.warehouse)
+    }
+
+    fn registry(&self) -> Result<HashMap<String, String>> {
+        let location = self.registry_location();
+        if !self.file_io.exists(&location)? {
+            return Ok(HashMap::new());
+        }
+        Ok(serde_json::from_slice(&self.file_io.read(&location)?)?)
+    }
+
+    fn save(&self, registry: &HashMap<String, String>) -> Result<()> {
+        self.file_io.write(
+            &self.registry_location(),
+            serde_json::to_vec(registry)?.into(),
+        )
+    }
+
+    fn create_table(&self, ident: &TableIdent, spec: PartitionSpec) -> Result<Table> {
+        let _lock = self.lock.lock().unwrap();
+        let metadata = TableMetadataV2 {
+            format_version: 2,
+            table_uuid: Uuid::new_v4(),
+            location: format!(
+                "{}/{}.db/{}",
+                self.warehouse,
+                ident.namespace(),
+                ident.name()
+            ),
+            last_sequence_number: 0,
+            last_updated_ms: 0,
+            last_column_id: 2,
+            schemas: vec![IcebergSchemaV2::new(0, None, schema())],
+            current_schema_id: 0,
+            partition_specs: vec![spec],
+            default_spec_id: 0,
+            last_partition_id: 1000,
+            properties: None,
+            current_snapshot_id: None,
+            snapshots: None,
+            snapshot_log: None,
+            metadata_log: None,
+            sort_orders: vec![SortOrders {
+                order_id: 0,
+                fields: vec![],
+            }],
+            default_sort_order_id: 0,
+            refs: None,
+            statistics: None,
+            partition_statistics: None,
+        };
+        let location = write_metadata_file(self.file_io.as_ref(), None, metadata)?;
+        let mut registry = self.registry()?;
+        registry.insert(ident.to_string(), location.clone());
+        self.save(&registry)?;
+        Table::load(ident.clone(), location, self.file_io.clone())
+    }
+}
+
+impl IcebergCatalog for FileCatalog {
+    fn load_table(&self, ident: &TableIdent) -> Result<Table> {
+        let location = self
+            .registry()?
+            .remove(&ident.to_string())
+            .ok_or_else(|| IcebergError::NotFound(format!("Table {}", ident)))?;
+        Table::load(ident.clone(), location, self.file_io.clone())
+    }
+
+    fn commit_table(&self, base: &Table, metadata: TableMetadataV2) -> Result<Table> {
+        let _lock = self.lock.lock().unwrap();
+        let key = base.ident().to_string();
+        let mut registry = self.registry()?;
+        if registry.get(&key).map(String::as_str) != Some(base.metadata_location()) {
+            return Err(IcebergError::Invalid(format!(
+                "Table {} changed since it was loaded",
+                key
+            )));
+        }
+        let location = write_metadata_file(self.file_io.as_ref(), Some(base), metadata)?;
+        registry.insert(key, location.clone());
+        self.save(&registry)?;
+        Table::load(base.ident().clone(), location, self.file_io.clone())
+    }
+
+    fn drop_table(&self, ident: &TableIdent, _purge: bool) -> Result<()> {
+        let _lock = self.lock.lock().unwrap();
+        let mut registry = self.registry()?;
+        registry
+            .remove(&ident.to_string())
+            .ok_or_else(|| IcebergError::NotFound(format!("Table {}", ident)))?;
+        self.save(&registry)
+    }
+}
+
+fn schema() -> StructType {
+    let field = |id, name: &str, required, primitive| StructField {
+        id,
+        name: name.to_string(),
+        required,
+        field_type: IcebergType::Primitive(primitive),
+        doc: None,
+        initial_default: None,
+        write_default: None,
+    };
+    StructType {
+        fields: vec![
+            field(1, "id", true, PrimitiveType::Long),
+            field(2, "category", false, PrimitiveType::String),
+        ],
+    }
+}
+
+fn by_category() -> PartitionSpec {
+    PartitionSpec {
+        spec_id: 0,
+        fields: vec![PartitionField {
+            source_id: 2,
+            source_ids: vec![],
+            field_id: 1000,
+            name: "category".to_string(),
+            transform: Transform::Identity,
+        }],
+    }
+}
+
+fn batch(rows: &[(i64, &str)]) -> RecordBatch {
+    let ids: Vec<i64> = rows.iter().map(|(id, _)| *id).collect();
+    let categories: Vec<&str> = rows.iter().map(|(_, category)| *category).collect();
+    RecordBatch::try_new(
+        Arc::new(schema_to_arrow(&schema()).unwrap()),
+        vec![
+            Arc::new(Int64Array::from(ids)),
+            Arc::new(StringArray::from(categories)),
+        ],
+    )
+    .unwrap()
+}
+
+fn append(catalog: &dyn IcebergCatalog, table: &Table, rows: &[(i64, &str)]) -> Table {
+    let mut writer = PartitionedWriter::for_table(table).unwrap();
+    writer.write(&batch(rows)).unwrap();
+    table
+        .new_append()
+        .add_files(writer.close().unwrap())
+        .commit(catalog)
+        .unwrap()
+}
+
+fn scan_ids(table: &Table, filter: Option<Predicate>) -> Vec<i64> {
+    let mut scan = table.scan();
+    if let Some(filter) = filter {
+        scan = scan.filter(filter);
+    }
+    let mut ids = vec![];
+    for batch in scan.plan_files().unwrap().to_arrow().unwrap() {
+        let batch = batch.unwrap();
+        let column = batch.column(0);
+        let column = column.as_any().downcast_ref::<Int64Array>().unwrap();
+        ids.extend((0..column.len()).map(|row| column.value(row)));
+    }
+    ids.sort();
+    ids
+}
+
+#[test]
+fn test_write_commit_and_scan() {
+    let dir = tempfile::tempdir().unwrap();
+    let catalog = FileCatalog::new(dir.path());
+    let table = catalog
+        .create_table(&"db.events".parse().unwrap(), by_category())
+        .unwrap();
+
+    let table = append(&catalog, &table, &[(1, "a"), (2, "b"), (3, "a")]);
+    let table = append(&catalog, &table, &[(4, "b"), (5, "c")]);
+
+    // A handle loaded from the catalog sees both commits
+    let loaded = catalog.load_table(&"db.events".parse().unwrap()).unwrap();
+    assert_eq!(table.metadata_location(), loaded.metadata_location());
+    assert_eq!(vec![1, 2, 3, 4, 5], scan_ids(&loaded, None));
+    let filter = Predicate::equal("category", Literal::String("b".to_string()));
+    let plan = loaded.scan().filter(filter.clone()).plan_files().unwrap();
+    assert_eq!(2, plan.tasks().len());
+    assert_eq!(vec![2, 4], scan_ids(&loaded, Some(filter)));
+
+    // Once another handle committed, the first one is stale
+    append(&catalog, &loaded, &[(6, "c")]);
+    assert!(table.new_append().commit(&catalog).is_err());
+}
+
+#[test]
+fn test_row_level_deletes_and_maintenance() {
+    let dir = tempfile::tempdir().unwrap();
+    let catalog = FileCatalog::new(dir.path());
+    let mut table = catalog
+        .create_table(&"db.events".parse().unwrap(), by_category())
+        .unwrap();
+    for rows in [
+        [(1, "a"), (2, "a")],
+        [(3, "a"), (4, "b")],
+        [(5, "a"), (6, "b")],
+    ] {
+        table = append(&catalog, &table, &rows);
+    }
+
+    // Delete id 3, the first row of its file in partition a
+    let task = table
+        .scan()
+        .filter(Predicate::equal("id", Literal::Long(3)))
+        .plan_files()
+        .unwrap()
+        .tasks()
+        .iter()
+        .find(|task| task.data_file.partition == vec![Some(Literal::String("a".to_string()))])
+        .unwrap()
+        .clone();
+    let mut writer = PositionDeleteWriter::for_table(&table, task.data_file.partition).unwrap();
+    writer.delete(&task.data_file.file_path, 0);
+    let table = table
+        .new_row_delta()
+        .add_deletes(writer.close().unwrap())
+        .commit(&catalog)
+        .unwrap();
+    assert_eq!(vec![1, 2, 4, 5, 6], scan_ids(&table, None));
+
+    // Compaction applies the deletes and keeps the rows
+    let result = table.rewrite_data_files().commit(&catalog).unwrap();
+    assert_eq!(5, result.rewritten_files.len());
+    assert_eq!(2, result.added_files.len());
+    let table = result.table;
+    assert_eq!(
+        Operation::Replace,
+        table
+            .metadata()
+            .current_snapshot()
+            .unwrap()
+            .summary
+            .operation
+    );
+    assert_eq!(vec![1, 2, 4, 5, 6], scan_ids(&table, None));
+
+    let table = table.rewrite_manifests().commit(&catalog).unwrap().table;
+    let snapshot = table.metadata().current_snapshot().unwrap();
+    assert_eq!(2, table.manifests(snapshot).unwrap().len());
+    let loaded = catalog.load_table(&"db.events".parse().unwrap()).unwrap();
+    assert_eq!(vec![1, 2, 4, 5, 6], scan_ids(&loaded, None));
+
+    catalog
+        .drop_table(&"db.events".parse().unwrap(), false)
+        .unwrap();
+    assert!(catalog.load_table(&"db.events".parse().unwrap()).is_err());
+}
@@ -0,0 +1,331 @@
+// End to end tests against a Hive Metastore and a MinIO object store started in containers:
+// tables are registered with HmsCatalog, their files are written to and read from MinIO. They
+// need Docker and are ignored by default, run them with
+//   cargo test --test end_to_end -- --ignored
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use rustberg::hms::catalog::HmsCatalog;
+use rustberg::hms::{connect, HmsAuth};
+use rustberg::iceberg::catalog::{new_table_metadata, write_metadata_file, IcebergCatalog};
+use rustberg::iceberg::error::{IcebergError, Result};
+use rustberg::iceberg::expr::Predicate;
+use rustberg::iceberg::ident::{Namespace, TableIdent};
+use rustberg::iceberg::io::{FileIO, FileInfo};
+use rustberg::iceberg::spec::schema::PrimitiveType;
+use rustberg::iceberg::spec::values::Literal;
+use rusty_s3::actions::{ListObjectsV2, S3Action};
+use rusty_s3::{Bucket, Credentials, UrlStyle};
+use testcontainers::core::{CopyDataSource, IntoContainerPort, WaitFor};
+use testcontainers::runners::SyncRunner;
+use testcontainers::{Container, GenericImage, ImageExt};
+use url::Url;
+use uuid::Uuid;
+
+use super::{append, by_category, scan_ids, schema};
+
+const MINIO_USER: &str = "rustberg";
+const MINIO_PASSWORD: &str = "rustberg-secret";
+const BUCKET: &str = "warehouse";
+const REGION: &str = "us-east-1";
+const SIGNATURE_TTL: Duration = Duration::from_secs(60);
+
+// FileIO of an S3 compatible object store, for locations like s3://bucket/key. Hadoop's S3A
+// scheme is accepted too, as the metastore needs it to create the directories of tables
+#[derive(Debug)]
+struct S3FileIO {
+    endpoint: Url,
+    credentials: Credentials,
+}
+
+impl S3FileIO {
+    fn new(endpoint: Url) -> Self {
+        S3FileIO {
+            endpoint,
+            credentials: Credentials::new(MINIO_USER, MINIO_PASSWORD),
+        }
+    }
+
+    fn bucket(&self, name: &str) -> Result<Bucket> {
+        Bucket::new(
+            self.endpoint.clone(),
+            UrlStyle::Path,
+            name.to_string(),
+            REGION,
+        )
+        .map_err(|e| IcebergError::Invalid(format!("Bucket {}: {}", name, e)))
+    }
+
+    // Bucket and key of the location
+    fn object(&self, location: &str) -> Result<(Bucket, String)> {
+        let path = location
+            .strip_prefix("s3://")
+            .or_else(|| location.strip_prefix("s3a://"))
+            .ok_or_else(|| IcebergError::Invalid(format!("{} is not an S3 location", location)))?;
+        let (bucket, key) = path.split_once('/').unwrap_or((path, ""));
+        Ok((self.bucket(bucket)?, key.to_string()))
+    }
+
+    fn create_bucket(&self, name: &str) -> Result<()> {
+        let bucket = self.bucket(name)?;
+        let url = bucket.create_bucket(&self.credentials).sign(SIGNATURE_TTL);
+        ureq::put(url.as_str())
+            .call()
+            .map_err(|e| s3_error(e, name))?;
+        Ok(())
+    }
+}
+
+fn s3_error(error: ureq::Error, location: &str) -> IcebergError {
+    match error {
+        ureq::Error::Status(404, _) => IcebergError::NotFound(location.to_string()),
+        e => IcebergError::Io(std::io::Error::other(format!("{}: {}", location, e))),
+    }
+}
+
+impl FileIO for S3FileIO {
+    fn read(&self, location: &str) -> Result<Bytes> {
+        let (bucket, key) = self.object(location)?;
+        let url = bucket
+            .get_object(Some(&self.credentials), &key)
+            .sign(SIGNATURE_TTL);
+        let response = ureq::get(url.as_str())
+            .call()
+            .map_err(|e| s3_error(e, location))?;
+        let mut data = vec![];
+        response.into_reader().read_to_end(&mut data)?;
+        Ok(data.into())
+    }
+
+    fn write(&self, location: &str, data: Bytes) -> Result<()> {
+        let (bucket, key) = self.object(location)?;
+        let url = bucket
+            .put_object(Some(&self.credentials), &key)
+            .sign(SIGNATURE_TTL);
+        ureq::put(url.as_str())
+            .send_bytes(&data)
+            .map_err(|e| s3_error(e, location))?;
+        Ok(())
+    }
+
+    fn exists(&self, location: &str) -> Result<bool> {
+        let (bucket, key) = self.object(location)?;
+        let url = bucket
+            .head_object(Some(&self.credentials), &key)
+            .sign(SIGNATURE_TTL);
+        match ureq::head(url.as_str()).call() {
+            Ok(_) => Ok(true),
+            Err(ureq::Error::Status(404, _)) => Ok(false),
+            Err(e) => Err(s3_error(e, location)),
+        }
+    }
+
+    fn delete(&self, location: &str) -> Result<()> {
+        let (bucket, key) = self.object(location)?;
+        let url = bucket
+            .delete_object(Some(&self.credentials), &key)
+            .sign(SIGNATURE_TTL);
+        ureq::delete(url.as_str())
+            .call()
+            .map_err(|e| s3_error(e, location))?;
+        Ok(())
+    }
+
+    fn list(&self, location: &str) -> Result<Vec<FileInfo>> {
+        let (bucket, key) = self.object(location)?;
+        let prefix = format!("{}/", key.trim_end_matches('/'));
+        let scheme = &location[..location.find("://").unwrap()];
+        let mut files = vec![];
+        let mut continuation = None;
+        loop {
+            let mut action = ListObjectsV2::new(&bucket, Some(&self.credentials));
+            action.with_prefix(prefix.as_str());
+            if let Some(token) = &continuation {
+                action.with_continuation_token(String::clone(token));
+            }
+            let url = action.sign(SIGNATURE_TTL);
+            let response = ureq::get(url.as_str())
+                .call()
+                .map_err(|e| s3_error(e, location))?
+                .into_string()?;
+            let listing = ListObjectsV2::parse_response(response)
+                .map_err(|e| IcebergError::Invalid(format!("Listing {}: {}", location, e)))?;
+            for object in listing.contents {
+                // Directory markers created by the metastore aren't files
+                if object.key.ends_with('/') {
+                    continue;
+                }
+                let last_modified =
+                    Literal::String(object.last_modified).to_type(&PrimitiveType::Timestamptz)?;
+                let Literal::Timestamptz(micros) = last_modified else {
+                    unreachable!("strings convert to timestamps of the requested type")
+                };
+                files.push(FileInfo {
+                    location: format!("{}://{}/{}", scheme, bucket.name(), object.key),
+                    size_in_bytes: object.size,
+                    last_modified_ms: micros / 1000,
+                });
+            }
+            continuation = listing.next_continuation_token;
+            if continuation.is_none() {
+                return Ok(files);
+            }
+        }
+    }
+}
+
+// Hadoop settings of the metastore for creating table directories in MinIO
+fn core_site(minio_host: &str) -> String {
+    let properties = [
+        ("fs.s3a.endpoint", format!("http://{}:9000", minio_host)),
+        ("fs.s3a.access.key", MINIO_USER.to_string()),
+        ("fs.s3a.secret.key", MINIO_PASSWORD.to_string()),
+        ("fs.s3a.path.style.access", "true".to_string()),
+        ("fs.s3a.connection.ssl.enabled", "false".to_string()),
+    ];
+    let properties: String = properties
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "  <property><name>{}</name><value>{}</value></property>\n",
+                name, value
+            )
+        })
+        .collect();
+    format!("<configuration>\n{}</configuration>\n", properties)
+}
+
+struct Services {
+    // Stopped when dropped
+    _minio: Container<GenericImage>,
+    _metastore: Container<GenericImage>,
+    file_io: Arc<S3FileIO>,
+    metastore_address: String,
+}
+
+// Starts MinIO and a metastore able to reach it on a network of their own
+fn start_services() -> Services {
+    let network = format!("rustberg-{}", Uuid::new_v4());
+    let minio_host = format!("minio-{}", Uuid::new_v4());
+    let minio = GenericImage::new("minio/minio", "RELEASE.2024-10-13T13-34-11Z")
+        .with_exposed_port(9000.tcp())
+        .with_wait_for(WaitFor::message_on_either_std("API:"))
+        .with_cmd(["server", "/data"])
+        .with_env_var("MINIO_ROOT_USER", MINIO_USER)
+        .with_env_var("MINIO_ROOT_PASSWORD", MINIO_PASSWORD)
+        .with_network(&network)
+        .with_container_name(&minio_host)
+        .start()
+        .unwrap();
+    let endpoint = format!(
+        "http://{}:{}",
+        minio.get_host().unwrap(),
+        minio.get_host_port_ipv4(9000).unwrap()
+    );
+    let file_io = Arc::new(S3FileIO::new(endpoint.parse().unwrap()));
+    file_io.create_bucket(BUCKET).unwrap();
+
+    // The S3A filesystem is among the optional tools of the Hadoop of the image
+    let metastore = GenericImage::new("apache/hive", "4.0.1")
+        .with_exposed_port(9083.tcp())
+        .with_wait_for(WaitFor::message_on_either_std(
+            "Starting Hive Metastore Server",
+        ))
+        .with_env_var("SERVICE_NAME", "metastore")
+        .with_env_var("HADOOP_OPTIONAL_TOOLS", "hadoop-aws")
+        .with_env_var("HIVE_CUSTOM_CONF_DIR", "/hive-custom-conf")
+        .with_copy_to(
+            "/hive-custom-conf/core-site.xml",
+            CopyDataSource::Data(core_site(&minio_host).into_bytes()),
+        )
+        .with_network(&network)
+        .with_startup_timeout(Duration::from_secs(180))
+        .start()
+        .unwrap();
+    let metastore_address = format!(
+        "{}:{}",
+        metastore.get_host().unwrap(),
+        metastore.get_host_port_ipv4(9083).unwrap()
+    );
+    Services {
+        _minio: minio,
+        _metastore: metastore,
+        file_io,
+        metastore_address,
+    }
+}
+
+impl Services {
+    // Catalog of a new connection, waiting for the metastore to accept them: it logs that it
+    // starts before listening
+    fn catalog(&self) -> HmsCatalog {
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let client = loop {
+            match connect(&self.metastore_address, HmsAuth::None) {
+                Ok(client) => break client,
+                Err(e) if Instant::now() > deadline => panic!("Metastore unreachable: {}", e),
+                Err(_) => sleep(Duration::from_secs(1)),
+            }
+        };
+        HmsCatalog::new(client, self.file_io.clone()).with_warehouse(&format!("s3a://{}", BUCKET))
+    }
+}
+
+#[test]
+#[ignore = "needs Docker"]
+fn test_hms_and_object_store() {
+    let services = start_services();
+    let catalog = services.catalog();
+    let namespace = Namespace::try_new(vec!["db".to_string()]).unwrap();
+    catalog
+        .create_namespace(&namespace, HashMap::new())
+        .unwrap();
+
+    // HmsCatalog registers tables whose first metadata file was written by the caller
+    let ident: TableIdent = "db.events".parse().unwrap();
+    let metadata = new_table_metadata(
+        format!("s3a://{}/db.db/events", BUCKET),
+        schema(),
+        by_category(),
+    );
+    let location = write_metadata_file(services.file_io.as_ref(), None, metadata).unwrap();
+    let table = catalog.register_table(&ident, &location).unwrap();
+    assert_eq!(
+        vec!["events".to_string()],
+        catalog.list_tables(&namespace).unwrap()
+    );
+
+    let table = append(&catalog, &table, &[(1, "a"), (2, "b"), (3, "a")]);
+    let table = append(&catalog, &table, &[(4, "b"), (5, "c")]);
+    assert!(table
+        .metadata()
+        .current_snapshot()
+        .is_some_and(|snapshot| snapshot.manifest_list.starts_with("s3a://")));
+
+    // A catalog of another connection sees the commits in the metastore
+    let loaded = services.catalog().load_table(&ident).unwrap();
+    assert_eq!(table.metadata_location(), loaded.metadata_location());
+    assert_eq!(vec![1, 2, 3, 4, 5], scan_ids(&loaded, None));
+    let filter = Predicate::equal("category", Literal::String("b".to_string()));
+    assert_eq!(vec![2, 4], scan_ids(&loaded, Some(filter)));
+    let data_files = services
+        .file_io
+        .list(&format!("{}/data", loaded.metadata().location))
+        .unwrap();
+    assert_eq!(4, data_files.len());
+
+    // Stale handles fail to commit
+    append(&catalog, &loaded, &[(6, "c")]);
+    assert!(table.new_append().commit(&catalog).is_err());
+
+    catalog.drop_table(&ident, true).unwrap();
+    assert!(matches!(
+        catalog.load_table(&ident),
+        Err(IcebergError::NotFound(_))
+    ));
+    assert!(!services.file_io.exists(&location).unwrap());
+}
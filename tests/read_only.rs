@@ -0,0 +1,103 @@
+// Read-only mode is enabled for the whole process, hence this separate test binary
+use std::sync::Arc;
+
+use arrow::array::{Int64Array, RecordBatch, StringArray};
+use rustberg::iceberg::arrow::schema_to_arrow;
+use rustberg::iceberg::catalog::write_metadata_file;
+use rustberg::iceberg::error::IcebergError;
+use rustberg::iceberg::io::{FileIO, LocalFileIO};
+use rustberg::iceberg::read_only::{is_read_only, set_read_only};
+use rustberg::iceberg::spec::partition_spec::PartitionSpec;
+use rustberg::iceberg::spec::schema::{
+    IcebergSchemaV2, IcebergType, PrimitiveType, StructField, StructType,
+};
+use rustberg::iceberg::spec::sort_orders::SortOrders;
+use rustberg::iceberg::spec::table_metadata::TableMetadataV2;
+use rustberg::iceberg::table::Table;
+use rustberg::iceberg::writer::partitioned::PartitionedWriter;
+use uuid::Uuid;
+
+fn metadata(location: String) -> TableMetadataV2 {
+    TableMetadataV2 {
+        format_version: 2,
+        table_uuid: Uuid::new_v4(),
+        location,
+        last_sequence_number: 0,
+        last_updated_ms: 0,
+        last_column_id: 2,
+        schemas: vec![IcebergSchemaV2::new(
+            0,
+            None,
+            StructType {
+                fields: vec![
+                    StructField::new(1, "id", true, IcebergType::Primitive(PrimitiveType::Long)),
+                    StructField::new(
+                        2,
+                        "data",
+                        false,
+                        IcebergType::Primitive(PrimitiveType::String),
+                    ),
+                ],
+            },
+        )],
+        current_schema_id: 0,
+        partition_specs: vec![PartitionSpec {
+            spec_id: 0,
+            fields: vec![],
+        }],
+        default_spec_id: 0,
+        last_partition_id: 999,
+        properties: None,
+        current_snapshot_id: None,
+        snapshots: None,
+        snapshot_log: None,
+        metadata_log: None,
+        sort_orders: vec![SortOrders {
+            order_id: 0,
+            fields: vec![],
+        }],
+        default_sort_order_id: 0,
+        refs: None,
+        statistics: None,
+        partition_statistics: None,
+    }
+}
+
+#[test]
+fn test_read_only_mode() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_io: Arc<dyn FileIO> = Arc::new(LocalFileIO::new());
+    let table_location = format!("file:{}/db.db/t", dir.path().display());
+    let metadata_location =
+        write_metadata_file(file_io.as_ref(), None, metadata(table_location)).unwrap();
+
+    assert!(!is_read_only());
+    set_read_only();
+    assert!(is_read_only());
+
+    // Reads still work
+    let table = Table::load("db.t".parse().unwrap(), metadata_location, file_io.clone()).unwrap();
+    assert!(table.scan().plan_files().unwrap().tasks().is_empty());
+
+    assert!(matches!(
+        write_metadata_file(file_io.as_ref(), Some(&table), table.metadata().clone()),
+        Err(IcebergError::ReadOnly(_))
+    ));
+    let schema =
+        Arc::new(schema_to_arrow(table.metadata().current_schema().unwrap().schema()).unwrap());
+    let batch = RecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(Int64Array::from(vec![1])),
+            Arc::new(StringArray::from(vec!["a"])),
+        ],
+    )
+    .unwrap();
+    let mut writer = PartitionedWriter::for_table(&table).unwrap();
+    let written = writer.write(&batch).and_then(|_| writer.close());
+    assert!(matches!(written, Err(IcebergError::ReadOnly(_))));
+    assert!(matches!(
+        file_io.delete(table.metadata_location()),
+        Err(IcebergError::ReadOnly(_))
+    ));
+}